@@ -0,0 +1,45 @@
+use std::io::Write;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use base64::Engine;
+
+/// Kept open for the whole run: on X11 and Wayland the copied text is only
+/// available while its owner is alive.
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// Put `text` on the system clipboard. Without one (e.g. over SSH), ask the
+/// terminal to set it with an OSC 52 escape sequence instead.
+pub fn copy(text: &str) -> Result<()> {
+    match copy_native(text) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::debug!("system clipboard unavailable, using OSC 52: {e:#}");
+            copy_osc52(text)
+        }
+    }
+}
+
+fn copy_native(text: &str) -> Result<()> {
+    let mut guard = CLIPBOARD
+        .lock()
+        .map_err(|_| anyhow::anyhow!("clipboard lock poisoned"))?;
+
+    let clipboard = match guard.take() {
+        Some(clipboard) => clipboard,
+        None => arboard::Clipboard::new().context("Failed to open the clipboard")?,
+    };
+
+    guard
+        .insert(clipboard)
+        .set_text(text)
+        .context("Failed to set the clipboard")
+}
+
+fn copy_osc52(text: &str) -> Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07").context("Failed to write to the terminal")?;
+    stdout.flush().context("Failed to write to the terminal")
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use super::CommandResult;
+
+/// `/add-dir <path>` lets the session read, edit and search another
+/// directory until it ends.
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    if args.is_empty() {
+        return CommandResult::Info(
+            "Usage: /add-dir <path>. Use --add-dir or permissions.additionalDirectories in \
+             settings to add directories at launch."
+                .to_string(),
+        );
+    }
+
+    match resolve(args, cwd) {
+        Ok(dir) if dir.starts_with(cwd) => CommandResult::Info(format!(
+            "{} is inside the working directory already.",
+            dir.display()
+        )),
+        Ok(dir) => CommandResult::AddDir(dir),
+        Err(e) => CommandResult::Info(format!("{e:#}")),
+    }
+}
+
+/// Resolve `path` against `cwd` (expanding a leading `~`) to an existing
+/// directory's canonical path.
+pub fn resolve(path: &str, cwd: &Path) -> Result<PathBuf> {
+    let home = || super::config::home_dir().context("Could not determine the home directory");
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => home()?.join(rest),
+        None if path == "~" => home()?,
+        None => cwd.join(path),
+    };
+
+    let dir = path
+        .canonicalize()
+        .with_context(|| format!("Cannot access {}", path.display()))?;
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_canonicalizes_relative_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        let shared = tmp.path().join("shared");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&shared).unwrap();
+
+        let dir = resolve("../shared", &project).unwrap();
+        assert_eq!(dir, shared.canonicalize().unwrap());
+
+        assert!(resolve("missing", &project).is_err());
+        std::fs::write(shared.join("file.txt"), "").unwrap();
+        assert!(resolve("../shared/file.txt", &project).is_err());
+    }
+
+    #[test]
+    fn run_rejects_directories_inside_cwd() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cwd = tmp.path().canonicalize().unwrap();
+        std::fs::create_dir(cwd.join("src")).unwrap();
+
+        let CommandResult::Info(text) = run("src", &cwd) else {
+            panic!("expected a note");
+        };
+        assert!(text.contains("inside the working directory"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use claude_code_core::agents::{self, AgentDef};
+
+use super::CommandResult;
+
+const USAGE: &str = "Usage: /agents | /agents create <name> [description]";
+
+/// `/agents` lists subagent definitions; `/agents create` writes a new one
+/// to `.claude/agents/` and opens it in `$EDITOR`.
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    let (sub, rest) = args.split_once(' ').unwrap_or((args, ""));
+
+    match sub {
+        "" => CommandResult::Info(list(cwd)),
+        "create" => create(rest.trim(), cwd),
+        _ => CommandResult::Info(USAGE.to_string()),
+    }
+}
+
+fn list(cwd: &Path) -> String {
+    let (agents, errors) = agents::load(cwd);
+
+    let mut text = if agents.is_empty() {
+        String::from("No agents defined.\n")
+    } else {
+        let mut text = String::from("Agents (Claude delegates to them with the Task tool):\n");
+        for agent in &agents {
+            let tools = agent
+                .tools
+                .as_ref()
+                .map_or("all tools".to_string(), |t| t.join(", "));
+            let model = agent.model.as_deref().unwrap_or("session model");
+            text.push_str(&format!(
+                "  {} — {}\n    {tools}; {model}; {}\n",
+                agent.name,
+                agent.description,
+                agent.path.display()
+            ));
+        }
+        text
+    };
+
+    for (path, e) in &errors {
+        text.push_str(&format!("  ✗ {}: {e:#}\n", path.display()));
+    }
+
+    text.push('\n');
+    text.push_str(USAGE);
+    text
+}
+
+fn create(args: &str, cwd: &Path) -> CommandResult {
+    let (name, description) = args.split_once(' ').unwrap_or((args, ""));
+    if name.is_empty() {
+        return CommandResult::Info(USAGE.to_string());
+    }
+
+    let description = match description.trim() {
+        "" => "TODO: when Claude should use this agent".to_string(),
+        d => d.to_string(),
+    };
+
+    let agent = AgentDef {
+        name: name.to_string(),
+        description,
+        tools: Some(vec!["Read".into(), "Grep".into(), "Glob".into()]),
+        model: None,
+        prompt: format!(
+            "You are the {name} agent. TODO: describe the role, what to look for and \
+             what the final report should contain."
+        ),
+        path: Default::default(),
+    };
+
+    match agents::create(&agents::project_agents_dir(cwd), &agent) {
+        Ok(path) => CommandResult::EditFile(path),
+        Err(e) => CommandResult::Info(format!("Could not create agent: {e:#}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_writes_a_loadable_template() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let CommandResult::EditFile(path) = run("create reviewer Reviews diffs", tmp.path()) else {
+            panic!("expected the new file to be opened");
+        };
+        assert_eq!(path, tmp.path().join(".claude/agents/reviewer.md"));
+
+        let text = list(tmp.path());
+        assert!(text.contains("reviewer — Reviews diffs"), "{text}");
+        assert!(text.contains("Read, Grep, Glob"));
+    }
+
+    #[test]
+    fn create_rejects_bad_names_and_duplicates() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        assert!(matches!(
+            run("create Bad_Name", tmp.path()),
+            CommandResult::Info(_)
+        ));
+
+        run("create helper", tmp.path());
+        let CommandResult::Info(text) = run("create helper", tmp.path()) else {
+            panic!("expected an error");
+        };
+        assert!(text.contains("already exists"));
+    }
+}
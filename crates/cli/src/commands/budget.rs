@@ -0,0 +1,60 @@
+use claude_code_core::session::Budget;
+
+use super::CommandResult;
+use super::cost::{dollars, group};
+
+/// `/budget` shows the session's spending against its limits; `/budget
+/// continue` allows another configured budget's worth and resumes a paused
+/// turn.
+pub fn run(args: &str) -> CommandResult {
+    match args {
+        "" => CommandResult::Budget { extend: false },
+        "continue" => CommandResult::Budget { extend: true },
+        _ => CommandResult::Info("Usage: /budget | /budget continue".to_string()),
+    }
+}
+
+pub fn report((usd, tokens): (f64, u64), limits: Budget) -> String {
+    if limits == Budget::default() {
+        return format!(
+            "This session has spent about {} ({} tokens). Set maxBudgetUsd or \
+             maxTokensPerSession in settings to pause it at a limit.",
+            dollars(Some(usd)),
+            group(tokens)
+        );
+    }
+
+    let mut text = String::from("Session budget:\n");
+    if let Some(max) = limits.max_usd {
+        text.push_str(&format!(
+            "  Cost:    {} of {}\n",
+            dollars(Some(usd)),
+            dollars(Some(max))
+        ));
+    }
+    if let Some(max) = limits.max_tokens {
+        text.push_str(&format!("  Tokens:  {} of {}\n", group(tokens), group(max)));
+    }
+    text.push_str("\n/budget continue allows as much again on top of what has been spent.");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_only_set_limits() {
+        let limits = Budget {
+            max_usd: Some(5.0),
+            max_tokens: None,
+        };
+        let text = report((2.5, 1_200_000), limits);
+        assert!(text.contains("Cost:    $2.50 of $5.00"));
+        assert!(!text.contains("Tokens:"));
+
+        let text = report((0.5, 1_200), Budget::default());
+        assert!(text.contains("1,200 tokens"));
+        assert!(text.contains("maxBudgetUsd"));
+    }
+}
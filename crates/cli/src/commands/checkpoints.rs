@@ -0,0 +1,77 @@
+use ccrs_git::Checkpoint;
+
+use super::CommandResult;
+use super::resume::ago;
+
+/// `/checkpoints` lists this session's checkpoints; `/checkpoints restore
+/// <n|id>` puts the files back as they were at one of them.
+pub fn run(args: &str) -> CommandResult {
+    let mut parts = args.split_whitespace();
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (None, _, _) => CommandResult::Checkpoints(None),
+        (Some("restore"), Some(target), None) => {
+            CommandResult::Checkpoints(Some(target.to_string()))
+        }
+        _ => CommandResult::Info("Usage: /checkpoints | /checkpoints restore <n|id>".to_string()),
+    }
+}
+
+/// The checkpoint a restore target names: a number from the listing, or a
+/// commit ID (possibly abbreviated, possibly from another session).
+pub fn resolve(target: &str, list: &[Checkpoint]) -> String {
+    match target.parse::<usize>() {
+        Ok(n) if (1..=list.len()).contains(&n) => list[n - 1].id.clone(),
+        _ => target.to_string(),
+    }
+}
+
+pub fn format_list(list: &[Checkpoint], now: u64) -> String {
+    if list.is_empty() {
+        return "No checkpoints yet. They are taken around each turn when the \
+                working directory is in a git repository."
+            .to_string();
+    }
+
+    let mut text = String::from("Checkpoints, newest first:\n");
+    for (i, checkpoint) in list.iter().enumerate() {
+        text.push_str(&format!(
+            "  {:>2}. {}  {:<10} {}\n",
+            i + 1,
+            &checkpoint.id[..checkpoint.id.len().min(7)],
+            ago(now, checkpoint.time.max(0) as u64),
+            checkpoint.message
+        ));
+    }
+    text.push_str("\nRestore one with /checkpoints restore <n>.");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(id: &str, message: &str, time: i64) -> Checkpoint {
+        Checkpoint {
+            id: id.to_string(),
+            message: message.to_string(),
+            time,
+        }
+    }
+
+    #[test]
+    fn numbers_resolve_against_the_listing() {
+        let list = [
+            checkpoint("bbbbbbbbbb", "After: fix", 200),
+            checkpoint("aaaaaaaaaa", "Before: fix", 100),
+        ];
+        assert_eq!(resolve("2", &list), "aaaaaaaaaa");
+        // Out of range numbers and IDs are passed through
+        assert_eq!(resolve("3", &list), "3");
+        assert_eq!(resolve("abc1234", &list), "abc1234");
+
+        let text = format_list(&list, 250);
+        assert!(text.contains("   1. bbbbbbb  just now   After: fix"));
+        assert!(text.contains("   2. aaaaaaa  2m ago     Before: fix"));
+    }
+}
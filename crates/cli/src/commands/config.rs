@@ -0,0 +1,469 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use claude_code_core::config::{self, Settings, ThemeSetting};
+use claude_code_core::models::ModelAliases;
+
+use super::CommandResult;
+
+/// Keys `/config set` accepts.
+const SETTABLE: &[&str] = &[
+    "model",
+    "maxTokens",
+    "thinking",
+    "autoUpdates",
+    "theme",
+    "vimMode",
+];
+
+/// `/config` shows the merged settings and where each value comes from;
+/// `/config set <key> <value>` and `/config unset <key>` edit
+/// `.claude/settings.local.json`.
+pub fn run(args: &str, cwd: &Path, aliases: &ModelAliases) -> CommandResult {
+    let mut parts = args.split_whitespace();
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (None, _, _) => CommandResult::Info(show(cwd)),
+        (Some("set"), Some(key), Some(value)) => {
+            CommandResult::Info(set(cwd, key, Some(value), aliases))
+        }
+        (Some("unset"), Some(key), None) => CommandResult::Info(set(cwd, key, None, aliases)),
+        _ => CommandResult::Info(
+            "Usage: /config | /config set <key> <value> | /config unset <key>".to_string(),
+        ),
+    }
+}
+
+fn set(cwd: &Path, key: &str, value: Option<&str>, aliases: &ModelAliases) -> String {
+    if !SETTABLE.contains(&key) {
+        return format!(
+            "Unknown or unsupported key: {key}. Settable keys: {}.",
+            SETTABLE.join(", ")
+        );
+    }
+
+    let json = match (key, value) {
+        (_, None) => None,
+        // Store model IDs, not aliases
+        ("model", Some(v)) => Some(
+            aliases
+                .resolve(v)
+                .map_or_else(|| v.into(), |(id, _)| id.into()),
+        ),
+        ("theme", Some(v)) if crate::tui::THEMES.contains(&v) => Some(v.to_string().into()),
+        ("theme", Some(_)) => {
+            return format!("theme must be one of: {}.", crate::tui::THEMES.join(", "));
+        }
+        ("maxTokens", Some(v)) => match v.parse::<u32>() {
+            Ok(n) if n > 0 => Some(n.into()),
+            _ => return "maxTokens must be a positive number.".to_string(),
+        },
+        ("thinking", Some(v)) => match v.parse::<u32>() {
+            Ok(n) => Some(n.into()),
+            Err(_) => return "thinking must be a token budget, or 0 for off.".to_string(),
+        },
+        (_, Some(v)) => match v.parse::<bool>() {
+            Ok(b) => Some(b.into()),
+            Err(_) => return format!("{key} must be true or false."),
+        },
+    };
+    let value = json.as_ref().map(|v| match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    });
+
+    let path = config::local_settings_path(cwd);
+
+    match config::write_setting(&path, key, json) {
+        Ok(()) => match value {
+            Some(value) => {
+                let note = match key {
+                    "model" => " New sessions use it; /model switches this one.",
+                    "theme" | "vimMode" | "maxTokens" | "thinking" => " New sessions use it.",
+                    _ => "",
+                };
+                format!("Set {key} = {value} in {}.{note}", short_path(&path, cwd))
+            }
+            None => format!("Removed {key} from {}.", short_path(&path, cwd)),
+        },
+        Err(e) => format!("Failed to update settings: {e:#}"),
+    }
+}
+
+fn show(cwd: &Path) -> String {
+    let mut paths = config::settings_paths(cwd);
+    paths.push(config::managed_settings_path());
+
+    let sources: Vec<(PathBuf, Option<Result<Settings>>)> = paths
+        .into_iter()
+        .map(|path| {
+            let settings = path.exists().then(|| config::read_settings_file(&path));
+            (path, settings)
+        })
+        .collect();
+
+    let mut text = report(cwd, &sources);
+    text.push_str(&env_overrides(&|key| std::env::var(key).ok()));
+    text
+}
+
+/// The settings environment variables that are set, which override every
+/// file but the managed one.
+fn env_overrides(env: &dyn Fn(&str) -> Option<String>) -> String {
+    let set: Vec<_> = config::SETTINGS_ENV_VARS
+        .iter()
+        .filter_map(|&(var, key)| {
+            let value = env(var).filter(|v| !v.trim().is_empty())?;
+            Some(format!("  {var}={value} (overrides {key})\n"))
+        })
+        .collect();
+
+    if set.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n\nEnvironment overrides, applied after every file but managed settings:\n{}",
+        set.concat()
+    )
+}
+
+/// Render the settings files in merge order, then each effective value with
+/// the file it came from.
+fn report(cwd: &Path, sources: &[(PathBuf, Option<Result<Settings>>)]) -> String {
+    let mut text = String::from("Settings files (later files override earlier ones):\n");
+
+    for (path, settings) in sources {
+        let status = match settings {
+            None => "not found".to_string(),
+            Some(Ok(_)) => "loaded".to_string(),
+            Some(Err(e)) => format!("ignored: {e:#}"),
+        };
+        text.push_str(&format!("  {:<36} {status}\n", short_path(path, cwd)));
+    }
+
+    // (key, value, source) in display order
+    let mut values: Vec<(&str, String, String)> = Vec::new();
+    let mut model = None;
+    let mut auto_updates = None;
+    let mut theme = None;
+    let mut vim_mode = None;
+    let mut max_tokens = None;
+    let mut thinking = None;
+
+    for (path, settings) in sources {
+        let Some(Ok(settings)) = settings else {
+            continue;
+        };
+        let source = short_path(path, cwd);
+
+        if let Some(m) = &settings.model {
+            model = Some((m.clone(), source.clone()));
+        }
+        if let Some(enabled) = settings.auto_updates {
+            auto_updates = Some((enabled.to_string(), source.clone()));
+        }
+        if let Some(enabled) = settings.vim_mode {
+            vim_mode = Some((enabled.to_string(), source.clone()));
+        }
+        if let Some(n) = settings.max_tokens {
+            max_tokens = Some((n.to_string(), source.clone()));
+        }
+        if let Some(budget) = settings.thinking {
+            let value = match budget {
+                0 => "off".to_string(),
+                n => n.to_string(),
+            };
+            thinking = Some((value, source.clone()));
+        }
+        match &settings.theme {
+            Some(ThemeSetting::Preset(name)) => theme = Some((name.clone(), source.clone())),
+            Some(ThemeSetting::Custom { preset, colors }) => {
+                let name = preset.as_deref().unwrap_or("dark");
+                let value = format!("{name} + {} colors", colors.len());
+                theme = Some((value, source.clone()));
+            }
+            None => {}
+        }
+        // Names only: values are often secrets
+        for name in settings.env.keys() {
+            values.push(("env", name.clone(), source.clone()));
+        }
+        for (event, count) in settings.hooks.counts() {
+            let value = format!(
+                "{event}: {count} command{}",
+                if count == 1 { "" } else { "s" }
+            );
+            values.push(("hooks", value, source.clone()));
+        }
+        for (alias, target) in &settings.model_aliases {
+            values.push((
+                "modelAliases",
+                format!("{alias} → {target}"),
+                source.clone(),
+            ));
+        }
+        let bedrock = &settings.bedrock;
+        if let Some(enabled) = bedrock.enabled {
+            values.push(("bedrock.enabled", enabled.to_string(), source.clone()));
+        }
+        for (key, value) in [
+            ("bedrock.region", &bedrock.region),
+            ("bedrock.awsProfile", &bedrock.aws_profile),
+            ("bedrock.inferenceProfile", &bedrock.inference_profile),
+        ] {
+            if let Some(value) = value {
+                values.push((key, value.clone(), source.clone()));
+            }
+        }
+        for (model, id) in &bedrock.models {
+            values.push(("bedrock.models", format!("{model} → {id}"), source.clone()));
+        }
+        let vertex = &settings.vertex;
+        if let Some(enabled) = vertex.enabled {
+            values.push(("vertex.enabled", enabled.to_string(), source.clone()));
+        }
+        for (key, value) in [
+            ("vertex.projectId", &vertex.project_id),
+            ("vertex.region", &vertex.region),
+        ] {
+            if let Some(value) = value {
+                values.push((key, value.clone(), source.clone()));
+            }
+        }
+        if let Some(path) = &vertex.credentials_file {
+            values.push((
+                "vertex.credentialsFile",
+                path.display().to_string(),
+                source.clone(),
+            ));
+        }
+        for (model, id) in &vertex.models {
+            values.push(("vertex.models", format!("{model} → {id}"), source.clone()));
+        }
+        let tls = &settings.tls;
+        for (key, path) in tls
+            .ca_certs
+            .iter()
+            .map(|path| ("tls.caCerts", path))
+            .chain(tls.client_cert.iter().map(|path| ("tls.clientCert", path)))
+            .chain(tls.client_key.iter().map(|path| ("tls.clientKey", path)))
+        {
+            values.push((key, path.display().to_string(), source.clone()));
+        }
+        for rule in &settings.permissions.allow {
+            values.push(("permissions.allow", rule.clone(), source.clone()));
+        }
+        for rule in &settings.permissions.deny {
+            values.push(("permissions.deny", rule.clone(), source.clone()));
+        }
+        for dir in &settings.permissions.additional_directories {
+            let dir = dir.display().to_string();
+            values.push(("permissions.additionalDirectories", dir, source.clone()));
+        }
+    }
+
+    if let Some((m, source)) = model {
+        values.insert(0, ("model", m, source));
+    }
+    if let Some((enabled, source)) = auto_updates {
+        values.push(("autoUpdates", enabled, source));
+    }
+    if let Some((name, source)) = theme {
+        values.push(("theme", name, source));
+    }
+    if let Some((enabled, source)) = vim_mode {
+        values.push(("vimMode", enabled, source));
+    }
+    if let Some((n, source)) = max_tokens {
+        values.push(("maxTokens", n, source));
+    }
+    if let Some((budget, source)) = thinking {
+        values.push(("thinking", budget, source));
+    }
+    values.sort_by_key(|(key, _, _)| *key);
+
+    text.push('\n');
+    if values.is_empty() {
+        text.push_str("No settings configured.\n");
+    } else {
+        for (key, value, source) in &values {
+            text.push_str(&format!("  {key:<34} {value:<24} ({source})\n"));
+        }
+    }
+
+    text.push_str("\nChange a value with /config set <key> <value> (keys: ");
+    text.push_str(&SETTABLE.join(", "));
+    text.push_str(").");
+    text
+}
+
+/// Show paths relative to the project or home directory when possible.
+pub(super) fn short_path(path: &Path, cwd: &Path) -> String {
+    if let Ok(rel) = path.strip_prefix(cwd) {
+        return rel.display().to_string();
+    }
+    if let Some(home) = home_dir()
+        && let Ok(rel) = path.strip_prefix(&home)
+    {
+        return format!("~/{}", rel.display());
+    }
+    path.display().to_string()
+}
+
+pub(super) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(json: &str) -> Option<Result<Settings>> {
+        Some(Ok(serde_json::from_str(json).unwrap()))
+    }
+
+    #[test]
+    fn report_attributes_values_to_their_file() {
+        let cwd = Path::new("/proj");
+        let sources = [
+            (
+                PathBuf::from("/proj/.claude/settings.json"),
+                settings(
+                    r#"{"model":"claude-opus-4-6","permissions":{"allow":["Bash(cargo:*)"]}}"#,
+                ),
+            ),
+            (
+                PathBuf::from("/proj/.claude/settings.local.json"),
+                settings(r#"{"model":"claude-haiku-4-5"}"#),
+            ),
+        ];
+
+        let text = report(cwd, &sources);
+
+        assert!(text.contains("claude-haiku-4-5"));
+        assert!(!text.contains("claude-opus-4-6"));
+        let model_line = text.lines().find(|l| l.contains("model ")).unwrap();
+        assert!(model_line.ends_with("(.claude/settings.local.json)"));
+        let allow_line = text.lines().find(|l| l.contains("Bash(cargo:*)")).unwrap();
+        assert!(allow_line.ends_with("(.claude/settings.json)"));
+    }
+
+    #[test]
+    fn report_lists_missing_and_broken_files() {
+        let cwd = Path::new("/proj");
+        let sources = [
+            (PathBuf::from("/proj/.claude/settings.json"), None),
+            (
+                PathBuf::from("/proj/.claude/settings.local.json"),
+                Some(Err(anyhow::anyhow!("bad JSON"))),
+            ),
+        ];
+
+        let text = report(cwd, &sources);
+
+        assert!(text.contains("not found"));
+        assert!(text.contains("ignored: bad JSON"));
+        assert!(text.contains("No settings configured."));
+    }
+
+    #[test]
+    fn set_writes_resolved_model_to_local_settings() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let text = set(tmp.path(), "model", Some("haiku"), &ModelAliases::default());
+        assert!(text.starts_with("Set model = claude-haiku"), "{text}");
+
+        let path = config::local_settings_path(tmp.path());
+        let saved = config::read_settings_file(&path).unwrap();
+        assert!(saved.model.unwrap().starts_with("claude-haiku"));
+
+        set(tmp.path(), "model", None, &ModelAliases::default());
+        assert!(config::read_settings_file(&path).unwrap().model.is_none());
+    }
+
+    #[test]
+    fn set_auto_updates_requires_a_bool() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            set(
+                tmp.path(),
+                "autoUpdates",
+                Some("nope"),
+                &ModelAliases::default()
+            ),
+            "autoUpdates must be true or false."
+        );
+
+        set(
+            tmp.path(),
+            "autoUpdates",
+            Some("false"),
+            &ModelAliases::default(),
+        );
+        let path = config::local_settings_path(tmp.path());
+        let saved = config::read_settings_file(&path).unwrap();
+        assert_eq!(saved.auto_updates, Some(false));
+    }
+
+    #[test]
+    fn set_theme_requires_a_preset() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        assert!(
+            set(tmp.path(), "theme", Some("neon"), &ModelAliases::default())
+                .starts_with("theme must be one of")
+        );
+
+        set(
+            tmp.path(),
+            "theme",
+            Some("solarized"),
+            &ModelAliases::default(),
+        );
+        let path = config::local_settings_path(tmp.path());
+        let saved = config::read_settings_file(&path).unwrap();
+        assert_eq!(saved.theme, Some(ThemeSetting::Preset("solarized".into())));
+    }
+
+    #[test]
+    fn set_numeric_model_defaults() {
+        let tmp = tempfile::tempdir().unwrap();
+        let aliases = ModelAliases::default();
+
+        assert_eq!(
+            set(tmp.path(), "maxTokens", Some("0"), &aliases),
+            "maxTokens must be a positive number."
+        );
+        set(tmp.path(), "maxTokens", Some("8192"), &aliases);
+        set(tmp.path(), "thinking", Some("0"), &aliases);
+
+        let path = config::local_settings_path(tmp.path());
+        let saved = config::read_settings_file(&path).unwrap();
+        assert_eq!(saved.max_tokens, Some(8192));
+        assert_eq!(saved.thinking, Some(0));
+    }
+
+    #[test]
+    fn set_rejects_unknown_keys() {
+        let tmp = tempfile::tempdir().unwrap();
+        let text = set(tmp.path(), "colour", Some("blue"), &ModelAliases::default());
+        assert!(text.starts_with("Unknown or unsupported key"));
+        assert!(!config::local_settings_path(tmp.path()).exists());
+    }
+
+    #[test]
+    fn env_overrides_list_the_variables_that_are_set() {
+        let env = |key: &str| match key {
+            "CLAUDE_CODE_MODEL" => Some("claude-opus-4-6".to_string()),
+            "CLAUDE_CODE_VIM_MODE" => Some(String::new()),
+            _ => None,
+        };
+
+        let text = env_overrides(&env);
+
+        assert!(text.contains("CLAUDE_CODE_MODEL=claude-opus-4-6 (overrides model)"));
+        assert!(!text.contains("VIM_MODE"));
+        assert_eq!(env_overrides(&|_| None), "");
+    }
+}
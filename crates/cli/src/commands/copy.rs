@@ -0,0 +1,57 @@
+use super::CommandResult;
+
+/// What `/copy` (or its key bindings) puts on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyTarget {
+    /// The last assistant message, as markdown.
+    Response,
+    /// The last fenced code block in an assistant message.
+    Code,
+    /// A tool call's output, counting back from the latest (1 = latest).
+    ToolOutput(usize),
+}
+
+/// `/copy` copies the last response; `/copy code` the last code block;
+/// `/copy tool [n]` the output of the n-th most recent tool call.
+pub fn run(args: &str) -> CommandResult {
+    let mut parts = args.split_whitespace();
+
+    let target = match (parts.next(), parts.next(), parts.next()) {
+        (None, _, _) => Some(CopyTarget::Response),
+        (Some("code"), None, _) => Some(CopyTarget::Code),
+        (Some("tool"), None, _) => Some(CopyTarget::ToolOutput(1)),
+        (Some("tool"), Some(n), None) => n
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .map(CopyTarget::ToolOutput),
+        _ => None,
+    };
+
+    match target {
+        Some(target) => CommandResult::Copy(target),
+        None => CommandResult::Info("Usage: /copy | /copy code | /copy tool [n]".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(args: &str) -> Option<CopyTarget> {
+        match run(args) {
+            CommandResult::Copy(target) => Some(target),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!(target(""), Some(CopyTarget::Response));
+        assert_eq!(target("code"), Some(CopyTarget::Code));
+        assert_eq!(target("tool"), Some(CopyTarget::ToolOutput(1)));
+        assert_eq!(target("tool 3"), Some(CopyTarget::ToolOutput(3)));
+        assert_eq!(target("tool 0"), None);
+        assert_eq!(target("everything"), None);
+    }
+}
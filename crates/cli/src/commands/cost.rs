@@ -0,0 +1,162 @@
+use claude_code_core::api::{TurnUsage, Usage};
+use claude_code_core::pricing;
+
+use super::CommandResult;
+
+/// Most recent turns listed in the per-turn breakdown.
+const MAX_TURNS: usize = 20;
+
+pub fn run(turns: &[TurnUsage]) -> CommandResult {
+    CommandResult::Info(report(turns))
+}
+
+fn report(turns: &[TurnUsage]) -> String {
+    if turns.is_empty() {
+        return "No tokens used yet in this session.".to_string();
+    }
+
+    let mut total = Usage::default();
+    for turn in turns {
+        total += turn.usage;
+    }
+
+    let mut text = String::from("Session usage:\n");
+    text.push_str(&format!(
+        "  Input:        {:>12}\n",
+        group(total.input_tokens)
+    ));
+    text.push_str(&format!(
+        "  Output:       {:>12}\n",
+        group(total.output_tokens)
+    ));
+    text.push_str(&format!(
+        "  Cache write:  {:>12}\n",
+        group(total.cache_creation_input_tokens)
+    ));
+    text.push_str(&format!(
+        "  Cache read:   {:>12}\n",
+        group(total.cache_read_input_tokens)
+    ));
+
+    // Per model, in order of first use
+    let mut by_model: Vec<(&str, usize, Option<f64>)> = Vec::new();
+    for turn in turns {
+        let cost = pricing::cost(&turn.model, &turn.usage);
+        match by_model.iter_mut().find(|(m, _, _)| *m == turn.model) {
+            Some((_, count, sum)) => {
+                *count += 1;
+                *sum = sum.zip(cost).map(|(a, b)| a + b);
+            }
+            None => by_model.push((&turn.model, 1, cost)),
+        }
+    }
+
+    let total_cost: Option<f64> = by_model.iter().map(|(_, _, c)| *c).sum();
+    text.push_str(&format!("  Est. cost:    {:>12}\n", dollars(total_cost)));
+
+    text.push_str("\nBy model:\n");
+    for (model, count, cost) in &by_model {
+        let plural = if *count == 1 { "" } else { "s" };
+        text.push_str(&format!(
+            "  {model:<20} {count:>3} turn{plural:<1}  {}\n",
+            dollars(*cost)
+        ));
+    }
+
+    let skipped = turns.len().saturating_sub(MAX_TURNS);
+    text.push_str("\nPer turn:\n");
+    if skipped > 0 {
+        text.push_str(&format!("  ({skipped} earlier turns not shown)\n"));
+    }
+    for (i, turn) in turns.iter().enumerate().skip(skipped) {
+        let u = &turn.usage;
+        text.push_str(&format!(
+            "  {n:>3}. {model:<20} {input:>8} in {output:>8} out {cache:>8} cache  {cost}\n",
+            n = i + 1,
+            model = turn.model,
+            input = group(u.input_tokens),
+            output = group(u.output_tokens),
+            cache = group(u.cache_creation_input_tokens + u.cache_read_input_tokens),
+            cost = dollars(pricing::cost(&turn.model, u)),
+        ));
+    }
+
+    text.push_str("\nCosts are estimates from list prices.");
+    text
+}
+
+/// `12345` → `12,345`.
+pub(super) fn group(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+pub(super) fn dollars(cost: Option<f64>) -> String {
+    match cost {
+        Some(c) if c >= 1.0 => format!("${c:.2}"),
+        Some(c) => format!("${c:.4}"),
+        None => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(model: &str, input: u64, output: u64) -> TurnUsage {
+        TurnUsage {
+            model: model.to_string(),
+            usage: Usage {
+                input_tokens: input,
+                output_tokens: output,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Collapse column padding so assertions don't depend on widths.
+    fn flatten(text: &str) -> String {
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn group_thousands() {
+        assert_eq!(group(0), "0");
+        assert_eq!(group(999), "999");
+        assert_eq!(group(1000), "1,000");
+        assert_eq!(group(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn report_totals_and_breaks_down_by_model() {
+        let turns = [
+            turn("claude-sonnet-4-5", 1_000_000, 0),
+            turn("claude-opus-4-6", 0, 1_000_000),
+            turn("claude-sonnet-4-5", 1_000_000, 0),
+        ];
+
+        let text = flatten(&report(&turns));
+
+        assert!(text.contains("Input: 2,000,000"));
+        // 2 × $3 (Sonnet input) + $25 (Opus output)
+        assert!(text.contains("Est. cost: $31.00"));
+        assert!(text.contains("claude-sonnet-4-5 2 turns $6.00"));
+        assert!(text.contains("claude-opus-4-6 1 turn $25.00"));
+        assert!(text.contains("2. claude-opus-4-6 0 in 1,000,000 out 0 cache $25.00"));
+    }
+
+    #[test]
+    fn unknown_model_makes_total_unknown() {
+        let text = flatten(&report(&[turn("mystery", 10, 10)]));
+        assert!(text.contains("Est. cost: unknown"));
+    }
+}
@@ -0,0 +1,418 @@
+use std::path::Path;
+
+use claude_code_core::api::ModelAccess;
+use claude_code_core::config::{self, SettingsProblem, TokenType};
+use claude_code_core::permission::PermissionHandler;
+use claude_code_core::session::Session;
+use claude_code_core::vault::{KeySource, PASSPHRASE_ENV};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    /// What to do about a warning or failure.
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every check against the live session and format the report.
+/// `profile` is the active credential profile.
+pub async fn run<P: PermissionHandler>(session: &Session<P>, profile: Option<&str>) -> String {
+    let mut checks = vec![match session.cloud_provider() {
+        Some(provider) => Check::ok("Credentials", format!("cloud credentials, for {provider}")),
+        None => credentials(profile),
+    }];
+    checks.extend(api(session).await);
+    checks.extend(settings(session.cwd()));
+
+    #[cfg(feature = "search")]
+    checks.push(embedding_model());
+
+    #[cfg(feature = "git")]
+    checks.push(git_repo(session.cwd()));
+    checks.push(git_cli());
+
+    checks.extend(terminal(&|key| std::env::var(key).ok()));
+
+    report(&checks)
+}
+
+fn report(checks: &[Check]) -> String {
+    let mut text = String::from("Diagnostics:\n");
+
+    for check in checks {
+        let icon = match check.status {
+            Status::Ok => "✓",
+            Status::Warn => "!",
+            Status::Fail => "✗",
+        };
+        text.push_str(&format!("  {icon} {:<16} {}\n", check.name, check.detail));
+        if let Some(fix) = &check.fix {
+            text.push_str(&format!("      → {fix}\n"));
+        }
+    }
+
+    let problems = checks.iter().filter(|c| c.status != Status::Ok).count();
+    if problems == 0 {
+        text.push_str("\nEverything looks good.");
+    } else {
+        text.push_str(&format!("\n{problems} issue(s) found."));
+    }
+    text
+}
+
+// ---------------------------------------------------------------------------
+// Checks
+// ---------------------------------------------------------------------------
+
+fn credentials(profile: Option<&str>) -> Check {
+    const NAME: &str = "Credentials";
+
+    if let Some((key, creds)) = config::credentials_from_env() {
+        let kind = if creds.is_oauth {
+            "OAuth token"
+        } else {
+            "API key"
+        };
+        return Check::ok(NAME, format!("{kind} from {key}"));
+    }
+
+    let login = match profile {
+        Some(name) => format!("ccrs --profile {name} login"),
+        None => "ccrs login".to_string(),
+    };
+    let saved = match profile {
+        Some(name) => format!("saved in profile {name}"),
+        None => "saved".to_string(),
+    };
+
+    match config::load_credentials(profile) {
+        Ok(Some(creds)) => {
+            let kind = match creds.token_type() {
+                TokenType::OAuthAccess => "OAuth access token",
+                TokenType::OAuthRefresh => "OAuth refresh token",
+                TokenType::ApiKey => "API key",
+            };
+            match config::credentials_encryption(profile) {
+                Ok(Some(KeySource::Machine)) => Check::ok(
+                    NAME,
+                    format!("{kind} {saved}, encrypted with the machine key"),
+                ),
+                Ok(Some(KeySource::Passphrase)) => {
+                    Check::ok(NAME, format!("{kind} {saved}, encrypted with a passphrase"))
+                }
+                _ => Check::warn(
+                    NAME,
+                    format!("{kind} {saved}, unencrypted"),
+                    format!("Set {PASSPHRASE_ENV} to encrypt them on the next launch."),
+                ),
+            }
+        }
+        Ok(None) => Check::fail(
+            NAME,
+            "no saved credentials",
+            format!("Run `{login}` to authenticate."),
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{e:#}"),
+            format!("Delete the credentials file in the config directory and run `{login}`."),
+        ),
+    }
+}
+
+async fn api<P: PermissionHandler>(session: &Session<P>) -> Vec<Check> {
+    let model = session.model();
+    let relogin = match session.cloud_provider() {
+        Some(provider) => {
+            format!("Check your cloud credentials and their permissions for {provider}.")
+        }
+        None => "Your token may have expired or been revoked. Run `ccrs login`.".to_string(),
+    };
+
+    match session.check_model_access().await {
+        Ok(ModelAccess::Granted) => vec![
+            Check::ok("API", "reachable"),
+            Check::ok("Model access", model),
+        ],
+        Ok(ModelAccess::Unauthorized(message)) => vec![
+            Check::ok("API", "reachable"),
+            Check::fail(
+                "Model access",
+                format!("credentials rejected: {message}"),
+                relogin,
+            ),
+        ],
+        Ok(ModelAccess::NotFound) => vec![
+            Check::ok("API", "reachable"),
+            Check::fail(
+                "Model access",
+                format!("{model} is not available to this account"),
+                "Pick another model with /model.",
+            ),
+        ],
+        Ok(ModelAccess::Other(code, message)) => vec![
+            Check::ok("API", "reachable"),
+            Check::warn(
+                "Model access",
+                format!("unexpected HTTP {code}: {message}"),
+                "Retry later; the API may be degraded.",
+            ),
+        ],
+        Err(e) => vec![Check::fail(
+            "API",
+            format!("unreachable: {e:#}"),
+            "Check your network connection and proxy settings (HTTPS_PROXY).",
+        )],
+    }
+}
+
+fn settings(cwd: &Path) -> Vec<Check> {
+    let mut paths = config::settings_paths(cwd);
+    paths.push(config::managed_settings_path());
+
+    let mut checks: Vec<Check> = config::check_settings_files(&paths)
+        .into_iter()
+        .map(|problem| match problem {
+            SettingsProblem::Invalid { .. } => Check::fail(
+                "Settings",
+                problem.to_string(),
+                "Fix the JSON; until then this file is ignored.",
+            ),
+            SettingsProblem::UnknownKey { .. } => Check::warn(
+                "Settings",
+                problem.to_string(),
+                "Check the spelling; the key has no effect.",
+            ),
+        })
+        .collect();
+
+    if checks.is_empty() {
+        checks.push(Check::ok("Settings", "all settings files parse"));
+    }
+    checks
+}
+
+#[cfg(feature = "search")]
+fn embedding_model() -> Check {
+    const NAME: &str = "Embedding model";
+
+    if ccrs_search::is_model_cached() {
+        let dir = ccrs_search::model_cache_dir().unwrap_or_default();
+        Check::ok(NAME, format!("cached in {}", dir.display()))
+    } else {
+        Check::warn(
+            NAME,
+            "not downloaded yet; the first semantic search will fetch it",
+            "Run `ccrs index` to download it ahead of time.",
+        )
+    }
+}
+
+#[cfg(feature = "git")]
+fn git_repo(cwd: &Path) -> Check {
+    match ccrs_git::repo_root(cwd) {
+        Ok(root) => Check::ok("Git repository", root.display().to_string()),
+        Err(_) => Check::warn(
+            "Git repository",
+            "not inside a git repository",
+            "Git tools and incremental search updates need one; run `git init` if this is a project.",
+        ),
+    }
+}
+
+fn git_cli() -> Check {
+    let found = std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths)
+            .any(|dir| dir.join("git").is_file() || dir.join("git.exe").is_file())
+    });
+
+    if found {
+        Check::ok("Git CLI", "found on PATH")
+    } else {
+        Check::warn(
+            "Git CLI",
+            "not found on PATH",
+            "Install git so shell commands that use it work.",
+        )
+    }
+}
+
+fn terminal(env: &dyn Fn(&str) -> Option<String>) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let term = env("TERM").unwrap_or_default();
+    let colorterm = env("COLORTERM").unwrap_or_default();
+
+    checks.push(if term.is_empty() || term == "dumb" {
+        Check::warn(
+            "Terminal",
+            format!("TERM={term:?} has no color or cursor support"),
+            "Run in a full terminal emulator, or use `ccrs -p` for scripts.",
+        )
+    } else if colorterm == "truecolor" || colorterm == "24bit" {
+        Check::ok("Terminal", format!("{term}, truecolor"))
+    } else if term.contains("256color") {
+        Check::ok("Terminal", format!("{term}, 256 colors"))
+    } else {
+        Check::warn(
+            "Terminal",
+            format!("{term}, basic colors"),
+            "Set TERM=xterm-256color for better rendering.",
+        )
+    });
+
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|key| env(key).filter(|v| !v.is_empty()))
+        .unwrap_or_default();
+    let utf8 = locale.to_uppercase().replace('-', "").contains("UTF8");
+
+    checks.push(if utf8 {
+        Check::ok("Unicode", locale)
+    } else {
+        Check::warn(
+            "Unicode",
+            format!("locale {locale:?} is not UTF-8"),
+            "Set LANG to a UTF-8 locale (e.g. en_US.UTF-8) so symbols render.",
+        )
+    });
+
+    if let Ok((cols, rows)) = crossterm::terminal::size() {
+        checks.push(if cols < 80 || rows < 24 {
+            Check::warn(
+                "Window size",
+                format!("{cols}x{rows}"),
+                "Enlarge the window to at least 80x24.",
+            )
+        } else {
+            Check::ok("Window size", format!("{cols}x{rows}"))
+        });
+    }
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_with(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn terminal_detects_truecolor_and_utf8() {
+        let env = env_with(&[
+            ("TERM", "xterm-256color"),
+            ("COLORTERM", "truecolor"),
+            ("LANG", "en_US.UTF-8"),
+        ]);
+
+        let checks = terminal(&env);
+
+        assert_eq!(checks[0].detail, "xterm-256color, truecolor");
+        assert_eq!(checks[0].status, Status::Ok);
+        assert_eq!(checks[1].status, Status::Ok);
+    }
+
+    #[test]
+    fn terminal_warns_on_dumb_term_and_c_locale() {
+        let env = env_with(&[("TERM", "dumb"), ("LC_ALL", "C")]);
+
+        let checks = terminal(&env);
+
+        assert_eq!(checks[0].status, Status::Warn);
+        assert_eq!(checks[1].status, Status::Warn);
+        assert!(checks[1].fix.as_deref().unwrap().contains("UTF-8"));
+    }
+
+    #[test]
+    fn settings_reports_malformed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let claude_dir = tmp.path().join(".claude");
+        std::fs::create_dir_all(&claude_dir).unwrap();
+        std::fs::write(claude_dir.join("settings.local.json"), "{oops").unwrap();
+
+        let checks = settings(tmp.path());
+
+        assert!(
+            checks
+                .iter()
+                .any(|c| c.status == Status::Fail && c.detail.contains("settings.local.json"))
+        );
+    }
+
+    #[test]
+    fn settings_warns_about_unknown_keys() {
+        let tmp = tempfile::tempdir().unwrap();
+        let claude_dir = tmp.path().join(".claude");
+        std::fs::create_dir_all(&claude_dir).unwrap();
+        std::fs::write(claude_dir.join("settings.json"), r#"{"modle": "opus"}"#).unwrap();
+
+        // The user's own settings file may be listed too
+        let checks = settings(tmp.path());
+        let project = tmp.path().display().to_string();
+        let check = checks
+            .iter()
+            .find(|c| c.detail.starts_with(&project))
+            .unwrap();
+
+        assert_eq!(check.status, Status::Warn);
+        assert!(check.detail.ends_with("unknown key `modle`"));
+    }
+
+    #[test]
+    fn report_counts_issues_and_shows_fixes() {
+        let checks = [
+            Check::ok("API", "reachable"),
+            Check::fail("Credentials", "missing", "Run `ccrs login`."),
+        ];
+
+        let text = report(&checks);
+
+        assert!(text.contains("✓ API"));
+        assert!(text.contains("✗ Credentials"));
+        assert!(text.contains("→ Run `ccrs login`."));
+        assert!(text.ends_with("1 issue(s) found."));
+    }
+}
@@ -0,0 +1,578 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+use claude_code_core::api::{Content, ContentBlock, Message};
+
+use super::CommandResult;
+
+/// Tool output lines kept per tool call in markdown and HTML exports.
+const MAX_OUTPUT_LINES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ExportFormat {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// `/export [markdown|html|json] [path]`. With only a path, the format is
+/// taken from its extension.
+pub fn run(args: &str) -> CommandResult {
+    let mut parts = args.split_whitespace();
+    let first = parts.next();
+
+    let (format, path) = match first.and_then(ExportFormat::parse) {
+        Some(format) => (Some(format), parts.next()),
+        None => (None, first),
+    };
+
+    if parts.next().is_some() {
+        return CommandResult::Info("Usage: /export [markdown|html|json] [path]".to_string());
+    }
+
+    let path = path.map(PathBuf::from);
+    let format = format
+        .or_else(|| {
+            path.as_ref()
+                .and_then(|p| p.extension())
+                .and_then(|ext| ExportFormat::parse(&ext.to_string_lossy()))
+        })
+        .unwrap_or(ExportFormat::Markdown);
+
+    CommandResult::Export { format, path }
+}
+
+/// Session details written alongside the transcript.
+pub struct ExportMeta<'a> {
+    pub id: &'a str,
+    pub model: &'a str,
+    pub cwd: &'a Path,
+}
+
+/// Write the conversation to `path` (default: `claude-<id>.<ext>` in the
+/// working directory) and return where it went.
+pub fn write(
+    format: ExportFormat,
+    path: Option<PathBuf>,
+    meta: &ExportMeta<'_>,
+    history: &[Message],
+) -> Result<PathBuf> {
+    if history.is_empty() {
+        anyhow::bail!("Nothing to export yet.");
+    }
+
+    let path = path.unwrap_or_else(|| format!("claude-{}.{}", meta.id, format.extension()).into());
+    let path = if path.is_absolute() {
+        path
+    } else {
+        meta.cwd.join(path)
+    };
+
+    let contents = match format {
+        ExportFormat::Markdown => to_markdown(meta, &entries(history)),
+        ExportFormat::Html => to_html(meta, &entries(history)),
+        ExportFormat::Json => to_json(meta, history)?,
+    };
+
+    fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+// ---------------------------------------------------------------------------
+// Transcript model
+// ---------------------------------------------------------------------------
+
+enum Entry<'a> {
+    User(&'a str),
+    Assistant(&'a str),
+    Tool {
+        name: &'a str,
+        /// Short description, e.g. the file path or command.
+        summary: Option<&'a str>,
+        body: ToolBody<'a>,
+        result: Option<(&'a str, bool)>,
+    },
+}
+
+enum ToolBody<'a> {
+    Diff { old: &'a str, new: &'a str },
+    Code(&'a str),
+    Input(String),
+}
+
+/// Flatten messages into display entries, pairing each tool call with its
+/// result.
+fn entries(history: &[Message]) -> Vec<Entry<'_>> {
+    let results: HashMap<&str, (&str, bool)> = history
+        .iter()
+        .filter_map(|m| match &m.content {
+            Content::Blocks(blocks) => Some(blocks),
+            Content::Text(_) => None,
+        })
+        .flatten()
+        .filter_map(|b| match b {
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => Some((
+                tool_use_id.as_str(),
+                (content.as_str(), is_error.unwrap_or(false)),
+            )),
+            _ => None,
+        })
+        .collect();
+
+    let mut out = Vec::new();
+
+    for msg in history {
+        let is_user = msg.role == "user";
+        let text_entry = |text| {
+            if is_user {
+                Entry::User(text)
+            } else {
+                Entry::Assistant(text)
+            }
+        };
+
+        match &msg.content {
+            Content::Text(text) => out.push(text_entry(text)),
+            Content::Blocks(blocks) => {
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text } => out.push(text_entry(text)),
+                        ContentBlock::ToolUse { id, name, input } => out.push(Entry::Tool {
+                            name,
+                            summary: tool_summary(name, input),
+                            body: tool_body(name, input),
+                            result: results.get(id.as_str()).copied(),
+                        }),
+                        // Results are paired with their calls; images and
+                        // thinking stay in the JSON export only
+                        ContentBlock::ToolResult { .. }
+                        | ContentBlock::Image { .. }
+                        | ContentBlock::Thinking { .. }
+                        | ContentBlock::RedactedThinking { .. } => {}
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn tool_summary<'a>(name: &str, input: &'a Value) -> Option<&'a str> {
+    let key = match name {
+        "Bash" => "command",
+        "Read" | "Write" | "Edit" => "file_path",
+        "Glob" | "Grep" => "pattern",
+        "Fetch" => "url",
+        "Search" => "query",
+        "List" => "path",
+        _ => return None,
+    };
+    input.get(key).and_then(|v| v.as_str())
+}
+
+fn tool_body<'a>(name: &str, input: &'a Value) -> ToolBody<'a> {
+    let field = |key| input.get(key).and_then(|v: &Value| v.as_str());
+
+    match name {
+        "Edit" => match (field("old_string"), field("new_string")) {
+            (Some(old), Some(new)) => ToolBody::Diff { old, new },
+            _ => ToolBody::Input(input.to_string()),
+        },
+        "Write" => match field("content") {
+            Some(content) => ToolBody::Code(content),
+            None => ToolBody::Input(input.to_string()),
+        },
+        // The summary already shows the interesting part
+        "Bash" | "Read" | "Glob" | "List" | "Search" => ToolBody::Input(String::new()),
+        _ => ToolBody::Input(serde_json::to_string_pretty(input).unwrap_or_default()),
+    }
+}
+
+fn diff_lines(old: &str, new: &str) -> String {
+    let mut out = String::new();
+    for line in old.lines() {
+        out.push_str(&format!("-{line}\n"));
+    }
+    for line in new.lines() {
+        out.push_str(&format!("+{line}\n"));
+    }
+    out
+}
+
+fn truncate_output(output: &str) -> String {
+    let total = output.lines().count();
+    if total <= MAX_OUTPUT_LINES {
+        return output.trim_end().to_string();
+    }
+
+    let mut kept: Vec<&str> = output.lines().take(MAX_OUTPUT_LINES).collect();
+    let note = format!("… ({} more lines)", total - MAX_OUTPUT_LINES);
+    kept.push(&note);
+    kept.join("\n")
+}
+
+// ---------------------------------------------------------------------------
+// Markdown
+// ---------------------------------------------------------------------------
+
+/// A code fence longer than any backtick run in `text`.
+fn fence(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn code_block(lang: &str, text: &str) -> String {
+    let fence = fence(text);
+    format!("{fence}{lang}\n{}\n{fence}\n\n", text.trim_end())
+}
+
+fn to_markdown(meta: &ExportMeta<'_>, entries: &[Entry<'_>]) -> String {
+    let mut out = format!(
+        "# Conversation\n\n- Session: `{}`\n- Model: `{}`\n- Directory: `{}`\n\n",
+        meta.id,
+        meta.model,
+        meta.cwd.display()
+    );
+
+    // Only repeat the speaker heading when the speaker changes
+    let mut last_user: Option<bool> = None;
+    let mut heading = |out: &mut String, is_user: bool| {
+        if last_user != Some(is_user) {
+            out.push_str(if is_user {
+                "## User\n\n"
+            } else {
+                "## Assistant\n\n"
+            });
+            last_user = Some(is_user);
+        }
+    };
+
+    for entry in entries {
+        match entry {
+            Entry::User(text) => {
+                heading(&mut out, true);
+                out.push_str(text.trim_end());
+                out.push_str("\n\n");
+            }
+            Entry::Assistant(text) => {
+                heading(&mut out, false);
+                out.push_str(text.trim_end());
+                out.push_str("\n\n");
+            }
+            Entry::Tool {
+                name,
+                summary,
+                body,
+                result,
+            } => {
+                heading(&mut out, false);
+                match summary {
+                    Some(s) if !s.contains('\n') => {
+                        out.push_str(&format!("**{name}** `{s}`\n\n"));
+                    }
+                    Some(s) => {
+                        out.push_str(&format!("**{name}**\n\n"));
+                        out.push_str(&code_block("", s));
+                    }
+                    None => out.push_str(&format!("**{name}**\n\n")),
+                }
+
+                match body {
+                    ToolBody::Diff { old, new } => {
+                        out.push_str(&code_block("diff", &diff_lines(old, new)));
+                    }
+                    ToolBody::Code(code) => out.push_str(&code_block("", code)),
+                    ToolBody::Input(input) if input.is_empty() => {}
+                    ToolBody::Input(input) => out.push_str(&code_block("json", input)),
+                }
+
+                if let Some((output, is_error)) = result {
+                    let label = if *is_error { "Error" } else { "Output" };
+                    out.push_str(&format!("<details><summary>{label}</summary>\n\n"));
+                    out.push_str(&code_block("", &truncate_output(output)));
+                    out.push_str("</details>\n\n");
+                }
+            }
+        }
+    }
+
+    out
+}
+
+// ---------------------------------------------------------------------------
+// HTML
+// ---------------------------------------------------------------------------
+
+const HTML_STYLE: &str = "\
+body { font-family: -apple-system, system-ui, sans-serif; max-width: 52rem; margin: 2rem auto; padding: 0 1rem; color: #222; }
+.meta { color: #666; font-size: 0.9rem; }
+.msg { margin: 1.5rem 0; }
+.role { font-weight: 600; margin-bottom: 0.3rem; }
+.user .role { color: #1a5fb4; }
+.assistant .role { color: #a8590f; }
+.text { white-space: pre-wrap; }
+.tool { border-left: 3px solid #ccc; padding-left: 0.8rem; margin: 0.8rem 0; }
+pre { background: #f6f6f6; padding: 0.6rem; overflow-x: auto; }
+.add { color: #1d7a1d; }
+.del { color: #b3261e; }
+.error summary { color: #b3261e; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn to_html(meta: &ExportMeta<'_>, entries: &[Entry<'_>]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Conversation {id}</title>\n<style>\n{HTML_STYLE}</style>\n</head>\n<body>\n<h1>Conversation</h1>\n<p class=\"meta\">Session <code>{id}</code> · Model <code>{model}</code> · <code>{cwd}</code></p>\n",
+        id = escape(meta.id),
+        model = escape(meta.model),
+        cwd = escape(&meta.cwd.display().to_string()),
+    );
+
+    let mut last_user: Option<bool> = None;
+
+    for entry in entries {
+        let is_user = matches!(entry, Entry::User(_));
+        if last_user != Some(is_user) {
+            if last_user.is_some() {
+                out.push_str("</div>\n");
+            }
+            let (class, role) = if is_user {
+                ("user", "User")
+            } else {
+                ("assistant", "Assistant")
+            };
+            out.push_str(&format!(
+                "<div class=\"msg {class}\">\n<div class=\"role\">{role}</div>\n"
+            ));
+            last_user = Some(is_user);
+        }
+
+        match entry {
+            Entry::User(text) | Entry::Assistant(text) => {
+                out.push_str(&format!(
+                    "<div class=\"text\">{}</div>\n",
+                    escape(text.trim_end())
+                ));
+            }
+            Entry::Tool {
+                name,
+                summary,
+                body,
+                result,
+            } => {
+                out.push_str("<div class=\"tool\">\n");
+                out.push_str(&format!("<strong>{}</strong>", escape(name)));
+                if let Some(s) = summary {
+                    out.push_str(&format!(" <code>{}</code>", escape(s)));
+                }
+                out.push('\n');
+
+                match body {
+                    ToolBody::Diff { old, new } => {
+                        out.push_str("<pre>");
+                        for line in old.lines() {
+                            out.push_str(&format!(
+                                "<span class=\"del\">-{}</span>\n",
+                                escape(line)
+                            ));
+                        }
+                        for line in new.lines() {
+                            out.push_str(&format!(
+                                "<span class=\"add\">+{}</span>\n",
+                                escape(line)
+                            ));
+                        }
+                        out.push_str("</pre>\n");
+                    }
+                    ToolBody::Code(code) => {
+                        out.push_str(&format!("<pre>{}</pre>\n", escape(code.trim_end())));
+                    }
+                    ToolBody::Input(input) if input.is_empty() => {}
+                    ToolBody::Input(input) => {
+                        out.push_str(&format!("<pre>{}</pre>\n", escape(input)));
+                    }
+                }
+
+                if let Some((output, is_error)) = result {
+                    let (class, label) = if *is_error {
+                        (" class=\"error\"", "Error")
+                    } else {
+                        ("", "Output")
+                    };
+                    out.push_str(&format!(
+                        "<details{class}><summary>{label}</summary><pre>{}</pre></details>\n",
+                        escape(&truncate_output(output))
+                    ));
+                }
+
+                out.push_str("</div>\n");
+            }
+        }
+    }
+
+    if last_user.is_some() {
+        out.push_str("</div>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// ---------------------------------------------------------------------------
+// JSON
+// ---------------------------------------------------------------------------
+
+fn to_json(meta: &ExportMeta<'_>, history: &[Message]) -> Result<String> {
+    let value = json!({
+        "session_id": meta.id,
+        "model": meta.model,
+        "cwd": meta.cwd,
+        "messages": history,
+    });
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> Vec<Message> {
+        vec![
+            Message {
+                role: "user".to_string(),
+                content: Content::text("rename foo"),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: Content::blocks(vec![
+                    ContentBlock::Text {
+                        text: "Renaming <foo>.".to_string(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "t1".to_string(),
+                        name: "Edit".to_string(),
+                        input: json!({
+                            "file_path": "src/lib.rs",
+                            "old_string": "fn foo()",
+                            "new_string": "fn bar()",
+                        }),
+                    },
+                ]),
+            },
+            Message {
+                role: "user".to_string(),
+                content: Content::blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "t1".to_string(),
+                    content: "Edited src/lib.rs".to_string(),
+                    is_error: None,
+                }]),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: Content::text("Done."),
+            },
+        ]
+    }
+
+    fn meta() -> ExportMeta<'static> {
+        ExportMeta {
+            id: "abc",
+            model: "claude-sonnet-4-5",
+            cwd: Path::new("/project"),
+        }
+    }
+
+    #[test]
+    fn parse_args() {
+        let parsed = |args| match run(args) {
+            CommandResult::Export { format, path } => (format, path),
+            _ => panic!("expected export"),
+        };
+
+        assert_eq!(parsed(""), (ExportFormat::Markdown, None));
+        assert_eq!(parsed("html"), (ExportFormat::Html, None));
+        assert_eq!(
+            parsed("json out.txt"),
+            (ExportFormat::Json, Some(PathBuf::from("out.txt")))
+        );
+        assert_eq!(
+            parsed("chat.html"),
+            (ExportFormat::Html, Some(PathBuf::from("chat.html")))
+        );
+        assert!(matches!(run("md a b"), CommandResult::Info(_)));
+    }
+
+    #[test]
+    fn markdown_pairs_tool_results_and_renders_diffs() {
+        let md = to_markdown(&meta(), &entries(&history()));
+
+        assert!(md.contains("## User\n\nrename foo"));
+        assert!(md.contains("**Edit** `src/lib.rs`"));
+        assert!(md.contains("```diff\n-fn foo()\n+fn bar()\n```"));
+        assert!(md.contains("<summary>Output</summary>\n\n```\nEdited src/lib.rs\n```"));
+        // Consecutive assistant blocks share one heading
+        assert_eq!(md.matches("## Assistant").count(), 1);
+    }
+
+    #[test]
+    fn html_escapes_text() {
+        let html = to_html(&meta(), &entries(&history()));
+
+        assert!(html.contains("Renaming &lt;foo&gt;."));
+        assert!(html.contains("<span class=\"del\">-fn foo()</span>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn fence_outgrows_backticks_in_content() {
+        assert_eq!(fence("plain"), "```");
+        assert_eq!(fence("has ``` inside"), "````");
+    }
+
+    #[test]
+    fn write_defaults_to_cwd() {
+        let tmp = tempfile::tempdir().unwrap();
+        let meta = ExportMeta {
+            cwd: tmp.path(),
+            ..meta()
+        };
+
+        let path = write(ExportFormat::Json, None, &meta, &history()).unwrap();
+
+        assert_eq!(path, tmp.path().join("claude-abc.json"));
+        let value: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(value["messages"].as_array().unwrap().len(), 4);
+    }
+}
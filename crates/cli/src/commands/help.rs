@@ -7,12 +7,34 @@ pub fn run() -> CommandResult {
 Available commands:
   /help /h   — Show this help message
   /quit /q   — Exit the application
+  /add-dir   — Let Claude read, edit and search another directory
+  /agents    — List subagents; /agents create <name> adds one
   /clear     — Clear conversation history
-  /model     — List or switch models",
+  /compact   — Summarize the conversation to free up context
+  /config    — Show settings and their sources; /config set <key> <value>
+  /copy      — Copy the last response; /copy code or /copy tool [n]
+  /cost      — Show token usage and estimated cost
+  /doctor    — Check credentials, API access and environment
+  /export    — Save the conversation as markdown, html or json
+  /init      — Draft a CLAUDE.md for this repository and edit it
+  /budget    — Show spending against the budget; /budget continue carries on
+  /login     — Sign in again and switch this session's credentials
+  /logout    — Revoke and delete the active profile's stored credentials
+  /memory    — Show memory files; /memory edit opens one in $EDITOR
+  /model     — List or switch models
+  /mouse     — Toggle mouse capture to select text with the terminal
+  /permissions — List, add or remove permission rules
+  /pin       — Keep a file at the top of every request; /unpin removes it
+  /profile   — List credential profiles or switch to one
+  /resume    — List or resume saved sessions
+  /t <name>  — Send a prompt template with arg=value; /t alone picks one
+  # <note>   — Add a note to CLAUDE.md",
     );
 
+    #[cfg(feature = "git")]
+    text.push_str("\n  /checkpoints — List file checkpoints; /checkpoints restore <n> rolls back");
     #[cfg(feature = "voice")]
-    text.push_str("\n  /rec       — Record and transcribe voice input");
+    text.push_str("\n  /rec       — Dictate into the prompt (or hold Alt+V)");
 
     CommandResult::Info(text)
 }
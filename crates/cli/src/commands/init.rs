@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::Path;
+
+use claude_code_core::project;
+
+use super::CommandResult;
+
+pub const FILE_NAME: &str = "CLAUDE.md";
+
+/// `/init` drafts a `CLAUDE.md` from the repository and opens it in
+/// `$EDITOR`; what is saved there gets written (`--force` to replace an
+/// existing file).
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    let force = match args {
+        "" => false,
+        "--force" => true,
+        _ => return CommandResult::Info("Usage: /init [--force]".to_string()),
+    };
+
+    if cwd.join(FILE_NAME).exists() && !force {
+        return CommandResult::Info(format!(
+            "{FILE_NAME} already exists. Run /init --force to draft a replacement."
+        ));
+    }
+    CommandResult::Init
+}
+
+/// The draft, from walking the repository: slow on big ones, so run it off
+/// the UI thread.
+pub fn draft(cwd: &Path) -> String {
+    let name = cwd
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "this".to_string());
+
+    project::analyze(cwd).to_claude_md(&name)
+}
+
+/// Write the reviewed draft. An emptied draft writes nothing.
+pub fn save(cwd: &Path, text: &str) -> String {
+    let path = cwd.join(FILE_NAME);
+    if text.trim().is_empty() {
+        return format!("The draft was empty, so {FILE_NAME} wasn't written.");
+    }
+
+    match fs::write(&path, format!("{}\n", text.trim_end())) {
+        Ok(()) => format!(
+            "Wrote {}. Fill in the TODOs to give Claude more context.",
+            path.display()
+        ),
+        Err(e) => format!("Failed to write {}: {e}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn existing_files_need_force() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(matches!(run("", tmp.path()), CommandResult::Init));
+
+        let path = tmp.path().join(FILE_NAME);
+        fs::write(&path, "mine").unwrap();
+        assert!(matches!(run("", tmp.path()), CommandResult::Info(_)));
+        assert!(matches!(run("--force", tmp.path()), CommandResult::Init));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "mine");
+    }
+
+    #[test]
+    fn saves_the_reviewed_draft() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(FILE_NAME);
+
+        assert!(draft(tmp.path()).starts_with("# CLAUDE.md"));
+
+        save(tmp.path(), "  \n");
+        assert!(!path.exists());
+
+        save(tmp.path(), "# CLAUDE.md\n\nEdited.");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# CLAUDE.md\n\nEdited.\n"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+
+use claude_code_core::auth::{self, TokenRefresh};
+use claude_code_core::config::{self, Credentials, TokenType};
+use claude_code_core::store;
+
+use crate::ui;
+
+/// Interactive OAuth or API-key login on a cooked terminal.
+pub async fn prompt() -> Result<Credentials> {
+    let method = ui::prompt_login_method()?;
+
+    match method {
+        ui::LoginMethod::OAuth => {
+            let store_refresh = ui::prompt_store_refresh()?;
+            let mut session = auth::start_oauth()?;
+
+            println!("Opening browser for authentication...");
+
+            if webbrowser::open(&session.auth_url).is_err() {
+                println!("Could not open browser. Please visit this URL manually:");
+                println!("{}", session.auth_url);
+            }
+
+            let code = if session.listening() {
+                wait_or_paste(&mut session).await?
+            } else {
+                let input = ui::prompt_oauth_code()?;
+                auth::parse_callback(&session, &input)?
+            };
+            auth::exchange_oauth_code(&session, &code, store_refresh).await
+        }
+        ui::LoginMethod::ApiKey => {
+            let key = ui::prompt_api_key()?;
+            Ok(Credentials::new(key, false))
+        }
+    }
+}
+
+/// Take the code from the browser's redirect, or from a paste when the
+/// browser runs elsewhere (e.g. over SSH) and can't reach the local port.
+async fn wait_or_paste(session: &mut auth::OAuthSession) -> Result<auth::AuthCode> {
+    println!("Waiting for the browser to finish signing in.");
+    println!("If it can't reach this machine, paste the URL it ends up on.");
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let mut paste = tokio::task::spawn_blocking({
+        let cancel = cancel.clone();
+        move || ui::read_line_until("Callback URL", &cancel)
+    });
+
+    tokio::select! {
+        code = session.wait_for_callback() => match code {
+            Ok(Some(code)) => {
+                cancel.store(true, Ordering::Relaxed);
+                let _ = paste.await;
+                println!("Signed in from the browser.");
+                return Ok(code);
+            }
+            Ok(None) => {
+                println!("The browser hasn't come back; paste the URL it ended up on.");
+            }
+            Err(e) => {
+                cancel.store(true, Ordering::Relaxed);
+                let _ = paste.await;
+                return Err(e);
+            }
+        },
+        input = &mut paste => return pasted(session, input??),
+    }
+    pasted(session, paste.await??)
+}
+
+fn pasted(session: &auth::OAuthSession, input: Option<String>) -> Result<auth::AuthCode> {
+    match input {
+        Some(input) => auth::parse_callback(session, input.trim()),
+        None => anyhow::bail!("Login cancelled"),
+    }
+}
+
+/// A usable access token from stored credentials.
+pub struct Access {
+    pub token: String,
+    pub is_oauth: bool,
+    /// Credentials to save in place of the stored ones.
+    pub updated: Option<Credentials>,
+    /// Renews `token` during the session; only refresh-token logins have it.
+    /// Nothing is saved until [`TokenRefresh::save_to`] says where.
+    pub refresh: Option<TokenRefresh>,
+}
+
+/// Turn stored credentials into a usable access token, refreshing if needed.
+pub async fn access_token(creds: &Credentials) -> Result<Access> {
+    match creds.token_type() {
+        TokenType::OAuthAccess => {
+            anyhow::ensure!(
+                !creds.is_expired(store::now()),
+                "The saved access token has expired; log in again"
+            );
+            Ok(Access {
+                token: creds.token.clone(),
+                is_oauth: true,
+                updated: None,
+                refresh: None,
+            })
+        }
+        TokenType::OAuthRefresh => {
+            let (access, updated) = auth::refresh_access_token(creds).await?;
+            Ok(Access {
+                refresh: TokenRefresh::new(updated.clone(), &access),
+                token: access.token,
+                is_oauth: true,
+                updated: Some(updated),
+            })
+        }
+        TokenType::ApiKey => Ok(Access {
+            token: creds.token.clone(),
+            is_oauth: false,
+            updated: None,
+            refresh: None,
+        }),
+    }
+}
+
+/// Run the login flow outside of TUI raw mode, save the result to
+/// `profile` and return the new access token.
+pub async fn run(profile: Option<&str>) -> Result<Access> {
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste,
+        crossterm::terminal::LeaveAlternateScreen,
+    )?;
+
+    let result = async {
+        let creds = prompt().await?;
+        let mut access = access_token(&creds).await?;
+        config::save_credentials(profile, access.updated.as_ref().unwrap_or(&creds))?;
+        access.refresh = access
+            .refresh
+            .map(|refresh| refresh.save_to(profile.map(str::to_string)));
+        Ok(access)
+    }
+    .await;
+
+    // Return to TUI mode - the caller redraws
+    crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen)?;
+    crossterm::terminal::enable_raw_mode()?;
+
+    result
+}
@@ -0,0 +1,20 @@
+use claude_code_core::auth::{self, Revocation};
+
+/// Revoke and delete `profile`'s stored credentials, for `/logout` and
+/// `ccrs logout`. Returns what happened, for display.
+pub async fn run(profile: Option<&str>) -> String {
+    match auth::revoke(profile).await {
+        Ok(Revocation::Revoked) => {
+            "Signed out: the token was revoked and stored credentials deleted.".to_string()
+        }
+        Ok(Revocation::ApiKeyDeleted) => "Stored API key deleted. It stays valid until you \
+             revoke it in the Anthropic Console."
+            .to_string(),
+        Ok(Revocation::DeletedOnly(e)) => format!(
+            "Stored credentials deleted, but the token could not be revoked ({e:#}); \
+             it stays valid until it expires."
+        ),
+        Ok(Revocation::NothingStored) => "No stored credentials to delete.".to_string(),
+        Err(e) => format!("Logout failed: {e:#}"),
+    }
+}
@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::Path;
+
+use claude_code_core::memory::{self, MemoryFile};
+
+use super::CommandResult;
+
+/// Lines shown per file before truncating.
+const MAX_LINES: usize = 40;
+
+/// `/memory` shows the memory files; `/memory edit [file]` opens one in
+/// `$EDITOR`.
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    match args.split_once(' ').unwrap_or((args, "")) {
+        ("", _) => CommandResult::Info(show(cwd)),
+        ("edit", file) => {
+            let file = match file.trim() {
+                "" => MemoryFile::Project,
+                name => match name.parse::<MemoryFile>() {
+                    Ok(file) => file,
+                    Err(e) => return CommandResult::Info(e.to_string()),
+                },
+            };
+            match file.path(cwd) {
+                Some(path) => CommandResult::EditFile(path),
+                None => CommandResult::Info("Could not determine the home directory.".to_string()),
+            }
+        }
+        _ => CommandResult::Info(
+            "Usage: /memory | /memory edit [user|project|instructions]".to_string(),
+        ),
+    }
+}
+
+/// `# <note>` appends the note to the project `CLAUDE.md`.
+pub fn remember(note: &str, cwd: &Path) -> CommandResult {
+    let note = note.trim();
+    if note.is_empty() {
+        return CommandResult::Info("Usage: # <note to remember>".to_string());
+    }
+
+    let Some(path) = MemoryFile::Project.path(cwd) else {
+        return CommandResult::Info("Could not locate CLAUDE.md.".to_string());
+    };
+
+    match memory::append(&path, note) {
+        Ok(()) => CommandResult::MemoryUpdated(format!("Noted in {}.", path.display())),
+        Err(e) => CommandResult::Info(format!("Failed to save memory: {e:#}")),
+    }
+}
+
+fn show(cwd: &Path) -> String {
+    let mut text = String::from("Memory files (loaded into every session):\n");
+    let mut contents = String::new();
+
+    for file in MemoryFile::ALL {
+        let Some(path) = file.path(cwd) else {
+            continue;
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(body) => {
+                let lines = body.lines().count();
+                text.push_str(&format!(
+                    "  {:<13} {} ({lines} lines)\n",
+                    file.name(),
+                    path.display()
+                ));
+
+                contents.push_str(&format!("\n── {} ──\n", path.display()));
+                for line in body.lines().take(MAX_LINES) {
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+                if lines > MAX_LINES {
+                    contents.push_str(&format!("… {} more lines\n", lines - MAX_LINES));
+                }
+            }
+            Err(_) => text.push_str(&format!(
+                "  {:<13} {} (not created)\n",
+                file.name(),
+                path.display()
+            )),
+        }
+    }
+
+    text.push_str(&contents);
+    text.push_str(
+        "\nEdit with /memory edit [user|project|instructions]. \
+         Start a message with # to add a note to CLAUDE.md.",
+    );
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remember_appends_to_project_claude_md() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let result = remember(" always run clippy ", tmp.path());
+
+        assert!(matches!(result, CommandResult::MemoryUpdated(_)));
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("CLAUDE.md")).unwrap(),
+            "- always run clippy\n"
+        );
+    }
+
+    #[test]
+    fn edit_resolves_memory_files() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let CommandResult::EditFile(path) = run("edit", tmp.path()) else {
+            panic!("expected an edit request");
+        };
+        assert_eq!(path, tmp.path().join("CLAUDE.md"));
+
+        let CommandResult::EditFile(path) = run("edit instructions", tmp.path()) else {
+            panic!("expected an edit request");
+        };
+        assert_eq!(path, tmp.path().join(".claude/instructions.md"));
+
+        assert!(matches!(
+            run("edit nope", tmp.path()),
+            CommandResult::Info(_)
+        ));
+    }
+
+    #[test]
+    fn show_truncates_long_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let body: String = (1..=50).map(|i| format!("line {i}\n")).collect();
+        fs::write(tmp.path().join("CLAUDE.md"), body).unwrap();
+
+        let text = show(tmp.path());
+
+        assert!(text.contains("(50 lines)"));
+        assert!(text.contains("line 40\n"));
+        assert!(!text.contains("line 41\n"));
+        assert!(text.contains("… 10 more lines"));
+    }
+}
@@ -1,38 +1,204 @@
+mod add_dir;
+mod agents;
+pub mod budget;
+#[cfg(feature = "git")]
+pub mod checkpoints;
 mod clear;
+mod config;
+pub mod copy;
+mod cost;
+pub mod doctor;
+pub mod export;
 mod help;
+pub mod init;
+pub mod login;
+pub mod logout;
+mod memory;
 mod model;
+mod mouse;
+pub mod permissions;
+pub mod pin;
+mod profile;
 mod quit;
 #[cfg(feature = "voice")]
 pub mod rec;
+mod resume;
+pub mod template;
+#[cfg(feature = "voice")]
+pub mod transcribe;
+#[cfg(feature = "voice")]
+mod whisper;
+
+use std::path::{Path, PathBuf};
+
+use claude_code_core::api::TurnUsage;
+use claude_code_core::config::Credentials;
+use claude_code_core::models::ModelAliases;
+use claude_code_core::store::SavedSession;
+use claude_code_core::templates::Template;
+
+/// Session state visible to slash commands.
+pub struct CommandContext<'a> {
+    pub model: &'a str,
+    pub cwd: &'a Path,
+    pub turns: &'a [TurnUsage],
+    /// Active credential profile; `None` for the default one.
+    pub profile: Option<&'a str>,
+    /// Resolves the names given to `/model` and `/config set model`.
+    pub model_aliases: &'a ModelAliases,
+}
 
 #[allow(dead_code)]
 pub enum CommandResult {
     Continue,
     Exit,
     Clear,
+    /// Summarize the conversation to free up context, optionally focusing
+    /// on the given topic.
+    Compact(Option<String>),
     SetModel {
         id: String,
         label: String,
     },
+    Resume(Box<SavedSession>),
+    Export {
+        format: export::ExportFormat,
+        path: Option<PathBuf>,
+    },
+    Doctor,
+    Login,
+    /// Revoke and delete the stored credentials.
+    Logout,
+    SwitchProfile {
+        profile: Option<String>,
+        creds: Credentials,
+        model: Option<String>,
+    },
+    /// Put part of the conversation on the clipboard.
+    Copy(copy::CopyTarget),
+    /// Grant the session another directory (canonical path).
+    AddDir(PathBuf),
+    /// List the pinned files, or pin one.
+    Pin(Option<PathBuf>),
+    Unpin(PathBuf),
+    /// Open a file in `$EDITOR`, then reload memory.
+    EditFile(PathBuf),
+    /// Draft a `CLAUDE.md` and open it in `$EDITOR` for review.
+    Init,
+    MemoryUpdated(String),
+    Permissions {
+        message: String,
+        change: permissions::RuleChange,
+    },
+    /// Turn mouse capture on or off, or toggle it with `None`.
+    Mouse(Option<bool>),
+    /// Show spending against the budget, or allow another budget's worth.
+    Budget {
+        extend: bool,
+    },
+    /// List the session's checkpoints, or restore the given one.
+    #[cfg(feature = "git")]
+    Checkpoints(Option<String>),
+    /// Choose a prompt template in the picker.
+    PickTemplate(Vec<Template>),
+    /// Send a message as if it had been typed.
+    Prompt(String),
     Info(String),
     #[cfg(feature = "voice")]
-    SendMessage(String),
-    #[cfg(feature = "voice")]
     RecordVoice,
 }
 
-/// Try to handle input as a slash command.
-/// Returns `None` if the input is not a command.
-pub fn handle_command(input: &str, current_model: &str) -> Option<CommandResult> {
+/// Try to handle input as a slash command or a `#` memory note.
+/// Returns `None` if the input is a regular message.
+pub fn handle_command(input: &str, ctx: &CommandContext<'_>) -> Option<CommandResult> {
+    if let Some(note) = input.strip_prefix('#') {
+        return Some(memory::remember(note, ctx.cwd));
+    }
+
     let cmd = input.split_whitespace().next()?;
 
     match cmd {
         "/help" | "/h" => Some(help::run()),
         "/quit" | "/exit" | "/q" => Some(quit::run()),
+        "/add-dir" => {
+            let args = input.strip_prefix("/add-dir").unwrap_or("").trim();
+            Some(add_dir::run(args, ctx.cwd))
+        }
+        "/agents" => {
+            let args = input.strip_prefix("/agents").unwrap_or("").trim();
+            Some(agents::run(args, ctx.cwd))
+        }
+        "/budget" => {
+            let args = input.strip_prefix("/budget").unwrap_or("").trim();
+            Some(budget::run(args))
+        }
+        #[cfg(feature = "git")]
+        "/checkpoints" => {
+            let args = input.strip_prefix("/checkpoints").unwrap_or("").trim();
+            Some(checkpoints::run(args))
+        }
         "/clear" => Some(clear::run()),
+        "/compact" => {
+            let args = input.strip_prefix("/compact").unwrap_or("").trim();
+            Some(CommandResult::Compact(
+                (!args.is_empty()).then(|| args.to_string()),
+            ))
+        }
+        "/config" => {
+            let args = input.strip_prefix("/config").unwrap_or("").trim();
+            Some(config::run(args, ctx.cwd, ctx.model_aliases))
+        }
+        "/copy" => {
+            let args = input.strip_prefix("/copy").unwrap_or("").trim();
+            Some(copy::run(args))
+        }
+        "/cost" => Some(cost::run(ctx.turns)),
+        "/doctor" => Some(CommandResult::Doctor),
+        "/login" => Some(CommandResult::Login),
+        "/logout" => Some(CommandResult::Logout),
+        "/init" => {
+            let args = input.strip_prefix("/init").unwrap_or("").trim();
+            Some(init::run(args, ctx.cwd))
+        }
+        "/export" => {
+            let args = input.strip_prefix("/export").unwrap_or("").trim();
+            Some(export::run(args))
+        }
+        "/memory" => {
+            let args = input.strip_prefix("/memory").unwrap_or("").trim();
+            Some(memory::run(args, ctx.cwd))
+        }
         "/model" => {
             let args = input.strip_prefix("/model").unwrap_or("").trim();
-            Some(model::run(args, current_model))
+            Some(model::run(args, ctx.model, ctx.model_aliases))
+        }
+        "/mouse" => {
+            let args = input.strip_prefix("/mouse").unwrap_or("").trim();
+            Some(mouse::run(args))
+        }
+        "/permissions" => {
+            let args = input.strip_prefix("/permissions").unwrap_or("").trim();
+            Some(permissions::run(args, ctx.cwd))
+        }
+        "/pin" => {
+            let args = input.strip_prefix("/pin").unwrap_or("").trim();
+            Some(pin::run(args, ctx.cwd))
+        }
+        "/unpin" => {
+            let args = input.strip_prefix("/unpin").unwrap_or("").trim();
+            Some(pin::unpin(args, ctx.cwd))
+        }
+        "/profile" => {
+            let args = input.strip_prefix("/profile").unwrap_or("").trim();
+            Some(profile::run(args, ctx.profile, ctx.model_aliases))
+        }
+        "/resume" => {
+            let args = input.strip_prefix("/resume").unwrap_or("").trim();
+            Some(resume::run(args, ctx.cwd))
+        }
+        "/t" | "/template" => {
+            let args = input.split_once(' ').map_or("", |(_, rest)| rest).trim();
+            Some(template::run(args, ctx.cwd))
         }
         #[cfg(feature = "voice")]
         "/rec" => Some(CommandResult::RecordVoice),
@@ -1,37 +1,25 @@
 use claude_code_core::api::{AVAILABLE_MODELS, DEFAULT_MODEL};
+use claude_code_core::models::ModelAliases;
 
 use super::CommandResult;
 
-pub fn run(args: &str, current_model: &str) -> CommandResult {
+pub fn run(args: &str, current_model: &str, aliases: &ModelAliases) -> CommandResult {
     let requested = args.trim();
 
     if requested.is_empty() {
-        return CommandResult::Info(list_models(current_model));
+        return CommandResult::Info(list_models(current_model, aliases));
     }
 
-    // Try exact match first, then substring match
-    let matched = AVAILABLE_MODELS
-        .iter()
-        .find(|(id, _)| *id == requested)
-        .or_else(|| {
-            AVAILABLE_MODELS.iter().find(|(id, label)| {
-                id.contains(requested) || label.to_lowercase().contains(&requested.to_lowercase())
-            })
-        });
-
-    match matched {
-        Some((id, label)) => CommandResult::SetModel {
-            id: id.to_string(),
-            label: label.to_string(),
-        },
+    match aliases.resolve(requested) {
+        Some((id, label)) => CommandResult::SetModel { id, label },
         None => CommandResult::Info(format!(
             "Unknown model: {requested}\n{}",
-            list_models(current_model)
+            list_models(current_model, aliases)
         )),
     }
 }
 
-fn list_models(current_model: &str) -> String {
+fn list_models(current_model: &str, aliases: &ModelAliases) -> String {
     let mut text = String::from("Available models:\n");
 
     for (id, label) in AVAILABLE_MODELS {
@@ -50,6 +38,11 @@ fn list_models(current_model: &str) -> String {
         text.push_str(&format!("  {id} — {label}{default}{marker}\n"));
     }
 
-    text.push_str("\nUsage: /model <name>");
+    text.push_str("\nAliases:\n");
+    for (alias, id) in aliases.list() {
+        text.push_str(&format!("  {alias} → {id}\n"));
+    }
+
+    text.push_str("\nUsage: /model <name or alias>");
     text
 }
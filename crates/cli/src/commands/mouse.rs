@@ -0,0 +1,11 @@
+use super::CommandResult;
+
+/// `/mouse` toggles mouse capture; `/mouse on` or `/mouse off` sets it.
+pub fn run(args: &str) -> CommandResult {
+    match args {
+        "" => CommandResult::Mouse(None),
+        "on" => CommandResult::Mouse(Some(true)),
+        "off" => CommandResult::Mouse(Some(false)),
+        _ => CommandResult::Info("Usage: /mouse [on|off]".to_string()),
+    }
+}
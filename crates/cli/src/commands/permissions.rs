@@ -0,0 +1,327 @@
+use std::path::{Path, PathBuf};
+
+use claude_code_core::config::{self, SettingsLayer};
+use claude_code_core::permission::{self, RuleKind};
+
+use super::CommandResult;
+use super::config::short_path;
+
+const USAGE: &str = "\
+Usage:
+  /permissions                                      List rules
+  /permissions add <allow|deny> <rule> [layer]      Add a rule (layer: user, project, local; default local)
+  /permissions remove <n>                           Remove rule n from its file";
+
+/// A rule added or removed, to apply to the running session.
+pub struct RuleChange {
+    pub kind: RuleKind,
+    pub rule: String,
+    pub added: bool,
+}
+
+/// A rule as found in one settings file.
+struct Entry {
+    layer: SettingsLayer,
+    path: PathBuf,
+    kind: RuleKind,
+    rule: String,
+}
+
+/// Settings files rules can live in, in merge order.
+type Layers = [(SettingsLayer, PathBuf)];
+
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    let layers: Vec<_> = SettingsLayer::ALL
+        .into_iter()
+        .chain([SettingsLayer::Managed])
+        .filter_map(|layer| Some((layer, layer.path(cwd)?)))
+        .collect();
+
+    run_in(args, cwd, &layers)
+}
+
+fn run_in(args: &str, cwd: &Path, layers: &Layers) -> CommandResult {
+    let (sub, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+
+    match sub {
+        "" => CommandResult::Info(list(cwd, layers)),
+        "add" => add(rest, cwd, layers),
+        "remove" | "rm" => remove(rest, cwd, layers),
+        _ => CommandResult::Info(USAGE.to_string()),
+    }
+}
+
+fn list(cwd: &Path, layers: &Layers) -> String {
+    let entries = entries(layers);
+
+    if entries.is_empty() {
+        return format!("No permission rules configured.\n\n{USAGE}");
+    }
+
+    let mut text = String::from("Permission rules (deny wins over allow):\n");
+    for (i, entry) in entries.iter().enumerate() {
+        text.push_str(&format!(
+            "  {n:>2}. {kind:<5} {rule:<32} {layer:<7} ({path})\n",
+            n = i + 1,
+            kind = entry.kind.key(),
+            rule = entry.rule,
+            layer = entry.layer.name(),
+            path = short_path(&entry.path, cwd),
+        ));
+    }
+    text.push('\n');
+    text.push_str(USAGE);
+    text
+}
+
+fn add(args: &str, cwd: &Path, layers: &Layers) -> CommandResult {
+    let Some((kind, rest)) = args.split_once(' ') else {
+        return CommandResult::Info(USAGE.to_string());
+    };
+    let kind = match kind {
+        "allow" => RuleKind::Allow,
+        "deny" => RuleKind::Deny,
+        _ => return CommandResult::Info(USAGE.to_string()),
+    };
+
+    // Rules may contain spaces (`Bash(rm -rf:*)`); the layer is an optional
+    // trailing word.
+    let rest = rest.trim();
+    let (rule, layer) = match rest.rsplit_once(' ') {
+        Some((_, "managed")) => {
+            return CommandResult::Info(
+                "Managed settings can only be changed by an administrator.".to_string(),
+            );
+        }
+        Some((rule, layer)) => match layer.parse::<SettingsLayer>() {
+            Ok(layer) => (rule.trim(), layer),
+            Err(_) => (rest, SettingsLayer::Local),
+        },
+        None => (rest, SettingsLayer::Local),
+    };
+
+    if !permission::is_valid_rule(rule) {
+        return CommandResult::Info(format!(
+            "Invalid rule: {rule}. Rules look like Bash(cargo test:*), Read(*), Edit(src/main.rs) or Git(push:*)."
+        ));
+    }
+
+    let Some((_, path)) = layers.iter().find(|(l, _)| *l == layer) else {
+        return CommandResult::Info("Could not determine the home directory.".to_string());
+    };
+
+    match config::add_permission_rule(path, kind, rule) {
+        Ok(true) => CommandResult::Permissions {
+            message: format!(
+                "Added {} rule {rule} to {}.",
+                kind.key(),
+                short_path(path, cwd)
+            ),
+            change: RuleChange {
+                kind,
+                rule: rule.to_string(),
+                added: true,
+            },
+        },
+        Ok(false) => CommandResult::Info(format!(
+            "{rule} is already in {} {}.",
+            short_path(path, cwd),
+            kind.key()
+        )),
+        Err(e) => CommandResult::Info(format!("Failed to update settings: {e:#}")),
+    }
+}
+
+fn remove(args: &str, cwd: &Path, layers: &Layers) -> CommandResult {
+    let listed = entries(layers);
+
+    let Some(entry) = args
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|i| listed.get(i))
+    else {
+        return CommandResult::Info(format!(
+            "Pick a rule number from /permissions (1-{}).",
+            listed.len()
+        ));
+    };
+
+    if entry.layer == SettingsLayer::Managed {
+        return CommandResult::Info(format!(
+            "{} is set in managed settings ({}), which only an administrator can change.",
+            entry.rule,
+            entry.path.display()
+        ));
+    }
+
+    if let Err(e) = config::remove_permission_rule(&entry.path, entry.kind, &entry.rule) {
+        return CommandResult::Info(format!("Failed to update settings: {e:#}"));
+    }
+
+    let message = format!(
+        "Removed {} rule {} from {}.",
+        entry.kind.key(),
+        entry.rule,
+        short_path(&entry.path, cwd)
+    );
+
+    // The same rule may still come from another layer
+    let still_active = entries(layers)
+        .iter()
+        .any(|e| e.kind == entry.kind && e.rule == entry.rule);
+    if still_active {
+        return CommandResult::Info(format!(
+            "{message} It is still set in another settings file."
+        ));
+    }
+
+    CommandResult::Permissions {
+        message,
+        change: RuleChange {
+            kind: entry.kind,
+            rule: entry.rule.clone(),
+            added: false,
+        },
+    }
+}
+
+/// Every rule from every readable settings file, in merge order.
+fn entries(layers: &Layers) -> Vec<Entry> {
+    let mut entries = Vec::new();
+
+    for (layer, path) in layers {
+        let Ok(settings) = config::read_settings_file(path) else {
+            continue;
+        };
+
+        for (kind, rules) in [
+            (RuleKind::Allow, settings.permissions.allow),
+            (RuleKind::Deny, settings.permissions.deny),
+        ] {
+            entries.extend(rules.into_iter().map(|rule| Entry {
+                layer: *layer,
+                path: path.clone(),
+                kind,
+                rule,
+            }));
+        }
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Layers rooted in a temp dir, so the real home directory is untouched.
+    fn layers(root: &Path) -> Vec<(SettingsLayer, PathBuf)> {
+        let claude = root.join(".claude");
+        vec![
+            (SettingsLayer::User, root.join("home/.claude/settings.json")),
+            (SettingsLayer::Project, claude.join("settings.json")),
+            (SettingsLayer::Local, claude.join("settings.local.json")),
+            (
+                SettingsLayer::Managed,
+                root.join("etc/managed-settings.json"),
+            ),
+        ]
+    }
+
+    fn run(args: &str, root: &Path) -> CommandResult {
+        run_in(args, root, &layers(root))
+    }
+
+    fn list(root: &Path) -> String {
+        super::list(root, &layers(root))
+    }
+
+    fn message(result: CommandResult) -> String {
+        match result {
+            CommandResult::Info(text) => text,
+            CommandResult::Permissions { message, .. } => message,
+            _ => panic!("unexpected command result"),
+        }
+    }
+
+    #[test]
+    fn add_writes_rule_with_spaces_to_chosen_layer() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let result = run("add deny Bash(rm -rf:*) project", tmp.path());
+        let CommandResult::Permissions { change, .. } = result else {
+            panic!("expected a permissions change");
+        };
+        assert_eq!(change.kind, RuleKind::Deny);
+        assert_eq!(change.rule, "Bash(rm -rf:*)");
+
+        let path = SettingsLayer::Project.path(tmp.path()).unwrap();
+        let saved = config::read_settings_file(&path).unwrap();
+        assert_eq!(saved.permissions.deny, vec!["Bash(rm -rf:*)"]);
+    }
+
+    #[test]
+    fn add_defaults_to_local_and_rejects_bad_rules() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let text = message(run("add allow cargo test", tmp.path()));
+        assert!(text.starts_with("Invalid rule"), "{text}");
+
+        run("add allow Bash(cargo test:*)", tmp.path());
+        let path = config::local_settings_path(tmp.path());
+        let saved = config::read_settings_file(&path).unwrap();
+        assert_eq!(saved.permissions.allow, vec!["Bash(cargo test:*)"]);
+    }
+
+    #[test]
+    fn list_numbers_rules_and_remove_uses_the_number() {
+        let tmp = tempfile::tempdir().unwrap();
+        run("add allow Bash(ls:*) project", tmp.path());
+        run("add allow Bash(cat:*)", tmp.path());
+
+        let text = list(tmp.path());
+        assert!(text.contains("1. allow Bash(ls:*)"), "{text}");
+        assert!(text.contains("2. allow Bash(cat:*)"), "{text}");
+
+        let text = message(run("remove 1", tmp.path()));
+        assert_eq!(
+            text,
+            "Removed allow rule Bash(ls:*) from .claude/settings.json."
+        );
+
+        let text = list(tmp.path());
+        assert!(!text.contains("Bash(ls:*)"));
+        assert!(text.contains("1. allow Bash(cat:*)"));
+    }
+
+    #[test]
+    fn remove_keeps_rule_active_when_another_layer_has_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        run("add allow Bash(ls:*) project", tmp.path());
+        run("add allow Bash(ls:*) local", tmp.path());
+
+        let result = run("remove 1", tmp.path());
+        assert!(matches!(result, CommandResult::Info(ref t) if t.contains("still set")));
+    }
+
+    #[test]
+    fn managed_rules_are_listed_but_not_removable() {
+        let tmp = tempfile::tempdir().unwrap();
+        let managed = tmp.path().join("etc/managed-settings.json");
+        std::fs::create_dir_all(managed.parent().unwrap()).unwrap();
+        std::fs::write(&managed, r#"{"permissions": {"deny": ["Bash(curl:*)"]}}"#).unwrap();
+
+        assert!(list(tmp.path()).contains("1. deny  Bash(curl:*)"));
+        let text = message(run("add deny Bash(wget:*) managed", tmp.path()));
+        assert!(
+            text.contains("only be changed by an administrator"),
+            "{text}"
+        );
+
+        let text = message(run("remove 1", tmp.path()));
+        assert!(text.contains("only an administrator"), "{text}");
+        assert!(std::fs::read_to_string(&managed).unwrap().contains("curl"));
+    }
+}
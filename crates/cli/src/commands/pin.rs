@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use super::CommandResult;
+
+/// `/pin` lists the pinned files; `/pin <file>` keeps a file at the top of
+/// every request, re-read whenever it changes.
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    if args.is_empty() {
+        return CommandResult::Pin(None);
+    }
+
+    match resolve(args, cwd) {
+        Ok(path) => CommandResult::Pin(Some(path)),
+        Err(e) => CommandResult::Info(format!("{e:#}")),
+    }
+}
+
+/// `/unpin <file>` stops showing a pinned file, even one deleted since.
+pub fn unpin(args: &str, cwd: &Path) -> CommandResult {
+    if args.is_empty() {
+        return CommandResult::Info("Usage: /unpin <file>".to_string());
+    }
+    let path = resolve(args, cwd).unwrap_or_else(|_| cwd.join(args));
+    CommandResult::Unpin(path)
+}
+
+/// Resolve `path` against `cwd` (expanding a leading `~`) to an existing
+/// file.
+fn resolve(path: &str, cwd: &Path) -> Result<PathBuf> {
+    let home = || super::config::home_dir().context("Could not determine the home directory");
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => home()?.join(rest),
+        None => cwd.join(path),
+    };
+    if !path.is_file() {
+        bail!("{} is not a file", path.display());
+    }
+    Ok(path)
+}
+
+/// The `/pin` listing, paths relative to `cwd`.
+pub fn format_list(paths: &[PathBuf], cwd: &Path) -> String {
+    if paths.is_empty() {
+        return "No pinned files. Pin one with /pin <file>, or list them under pinnedFiles \
+                in settings."
+            .to_string();
+    }
+
+    let mut text = String::from("Pinned files, re-read when they change:\n");
+    for path in paths {
+        let shown = path.strip_prefix(cwd).unwrap_or(path);
+        text.push_str(&format!("  {}\n", shown.display()));
+    }
+    text.push_str("\nRemove one with /unpin <file>.");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_existing_files_are_pinned() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("schema.json"), "{}").unwrap();
+
+        let CommandResult::Pin(Some(path)) = run("schema.json", tmp.path()) else {
+            panic!("expected a pin");
+        };
+        assert_eq!(path, tmp.path().join("schema.json"));
+        assert!(matches!(run("", tmp.path()), CommandResult::Pin(None)));
+        assert!(matches!(run(".", tmp.path()), CommandResult::Info(_)));
+        // Deleted files can still be unpinned
+        assert!(matches!(
+            unpin("gone.md", tmp.path()),
+            CommandResult::Unpin(_)
+        ));
+
+        let text = format_list(&[path], tmp.path());
+        assert!(text.contains("\n  schema.json\n"));
+    }
+}
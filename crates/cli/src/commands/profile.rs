@@ -0,0 +1,90 @@
+use claude_code_core::config::{self, Credentials, DEFAULT_PROFILE};
+use claude_code_core::models::ModelAliases;
+
+use super::CommandResult;
+
+/// `/profile` lists credential profiles; `/profile <name>` switches this
+/// session to that profile's token and default model.
+pub fn run(args: &str, current: Option<&str>, aliases: &ModelAliases) -> CommandResult {
+    match args {
+        "" => CommandResult::Info(list(current)),
+        name => switch(name, aliases),
+    }
+}
+
+fn list(current: Option<&str>) -> String {
+    let profiles = match config::list_profiles() {
+        Ok(profiles) => profiles,
+        Err(e) => return format!("Could not list profiles: {e:#}"),
+    };
+
+    let mut text = String::from("Profiles:\n");
+    let names = std::iter::once(DEFAULT_PROFILE).chain(profiles.iter().map(String::as_str));
+    for name in names {
+        let active = current.unwrap_or(DEFAULT_PROFILE) == name;
+        let marker = if active { "*" } else { " " };
+        let profile = (name != DEFAULT_PROFILE).then_some(name);
+
+        let model = profile
+            .and_then(|p| config::load_profile_settings(p).ok())
+            .and_then(|s| s.model)
+            .map_or(String::new(), |m| format!(" ({m})"));
+        let status = match config::load_credentials(profile) {
+            Ok(Some(_)) => "",
+            Ok(None) => " — not logged in",
+            Err(_) => " — unreadable credentials",
+        };
+        text.push_str(&format!("  {marker} {name}{model}{status}\n"));
+    }
+
+    text.push_str("\nSwitch with /profile <name>. Create one with `ccrs --profile <name> login`.");
+    text
+}
+
+fn switch(name: &str, aliases: &ModelAliases) -> CommandResult {
+    let profile = (name != DEFAULT_PROFILE).then(|| name.to_string());
+
+    let settings = match profile.as_deref().map(config::load_profile_settings) {
+        Some(Err(e)) => return CommandResult::Info(format!("{e:#}")),
+        Some(Ok(settings)) => settings,
+        None => Default::default(),
+    };
+
+    let creds: Credentials = match config::load_credentials(profile.as_deref()) {
+        Ok(Some(creds)) => creds,
+        Ok(None) => {
+            let login = match &profile {
+                Some(name) => format!("ccrs --profile {name} login"),
+                None => "ccrs login".to_string(),
+            };
+            return CommandResult::Info(format!(
+                "Profile {name} has no credentials. Run `{login}` first."
+            ));
+        }
+        Err(e) => return CommandResult::Info(format!("{e:#}")),
+    };
+
+    // Unknown names are passed through as raw model IDs, as on the command line
+    let model = settings
+        .model
+        .map(|m| aliases.resolve(&m).map_or(m, |(id, _)| id));
+
+    CommandResult::SwitchProfile {
+        profile,
+        creds,
+        model,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switch_rejects_invalid_names() {
+        let CommandResult::Info(text) = run("../elsewhere", None, &ModelAliases::default()) else {
+            panic!("expected an error");
+        };
+        assert!(text.contains("Invalid profile name"), "{text}");
+    }
+}
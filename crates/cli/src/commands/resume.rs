@@ -0,0 +1,136 @@
+use std::path::Path;
+
+use claude_code_core::store::{self, SavedSession};
+
+use super::CommandResult;
+
+/// Sessions shown by a bare `/resume`.
+const MAX_LISTED: usize = 10;
+
+/// Longest first-prompt preview in the listing, in characters.
+const PREVIEW_CHARS: usize = 50;
+
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    let requested = args.trim();
+
+    let sessions: Vec<SavedSession> = match store::list() {
+        Ok(all) => all.into_iter().filter(|s| s.cwd == cwd).collect(),
+        Err(e) => return CommandResult::Info(format!("Failed to list sessions: {e}")),
+    };
+
+    if requested.is_empty() {
+        return CommandResult::Info(list_sessions(&sessions, store::now()));
+    }
+
+    // A listing number, then an ID from this directory, then any saved ID
+    let chosen = match requested.parse::<usize>() {
+        Ok(n) if (1..=sessions.len().min(MAX_LISTED)).contains(&n) => {
+            sessions.into_iter().nth(n - 1)
+        }
+        _ => sessions
+            .into_iter()
+            .find(|s| s.id == requested)
+            .or_else(|| store::load(requested).ok()),
+    };
+
+    match chosen {
+        Some(saved) => CommandResult::Resume(Box::new(saved)),
+        None => CommandResult::Info(format!(
+            "No saved session matches {requested}. Type /resume to list sessions."
+        )),
+    }
+}
+
+fn list_sessions(sessions: &[SavedSession], now: u64) -> String {
+    if sessions.is_empty() {
+        return "No saved sessions in this directory.".to_string();
+    }
+
+    let mut text = String::from("Saved sessions:\n");
+
+    for (i, session) in sessions.iter().take(MAX_LISTED).enumerate() {
+        let prompt = session.first_prompt().unwrap_or_default();
+        let first_line = prompt.lines().next().unwrap_or("");
+        let preview: String = first_line.chars().take(PREVIEW_CHARS).collect();
+        let ellipsis = if first_line.chars().count() > PREVIEW_CHARS || prompt.contains('\n') {
+            "…"
+        } else {
+            ""
+        };
+
+        text.push_str(&format!(
+            "  {n:>2}. {ago:>8}  \"{preview}{ellipsis}\"  ({input} in / {output} out)  {id}\n",
+            n = i + 1,
+            ago = ago(now, session.updated_at),
+            input = format_tokens(session.usage.input_tokens),
+            output = format_tokens(session.usage.output_tokens),
+            id = session.id,
+        ));
+    }
+
+    text.push_str("\nUsage: /resume <number|id>");
+    text
+}
+
+/// Coarse relative time, e.g. `5m ago`.
+pub(super) fn ago(now: u64, then: u64) -> String {
+    let secs = now.saturating_sub(then);
+
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86_400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+fn format_tokens(n: u64) -> String {
+    if n >= 1000 {
+        format!("{:.1}k", n as f64 / 1000.0)
+    } else {
+        n.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use claude_code_core::api::{Content, Message, Usage};
+
+    use super::*;
+
+    #[test]
+    fn ago_buckets() {
+        assert_eq!(ago(100, 100), "just now");
+        assert_eq!(ago(1000, 100), "15m ago");
+        assert_eq!(ago(7300, 100), "2h ago");
+        assert_eq!(ago(200_000, 100), "2d ago");
+        // Clock skew never underflows
+        assert_eq!(ago(100, 200), "just now");
+    }
+
+    #[test]
+    fn listing_shows_preview_tokens_and_id() {
+        let session = SavedSession {
+            id: "abc".to_string(),
+            cwd: PathBuf::from("/p"),
+            model: "m".to_string(),
+            created_at: 0,
+            updated_at: 0,
+            usage: Usage {
+                input_tokens: 12_345,
+                output_tokens: 678,
+                ..Default::default()
+            },
+            turns: Vec::new(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: Content::text("fix the build\nit fails on CI"),
+            }],
+        };
+
+        let text = list_sessions(&[session], 120);
+        assert!(text.contains("1.   2m ago  \"fix the build…\"  (12.3k in / 678 out)  abc"));
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use claude_code_core::templates::{self, Template};
+
+use super::CommandResult;
+
+const USAGE: &str = "Usage: /t | /t <name> [arg=value ...]";
+
+/// `/t` opens the template picker; `/t <name> arg=value` fills in a prompt
+/// template from `.claude/templates/` and sends it.
+pub fn run(args: &str, cwd: &Path) -> CommandResult {
+    let (templates, errors) = templates::load(cwd);
+    let (name, rest) = args.split_once(' ').unwrap_or((args, ""));
+
+    if name.is_empty() {
+        return if templates.is_empty() {
+            CommandResult::Info(list(&templates, &errors))
+        } else {
+            CommandResult::PickTemplate(templates)
+        };
+    }
+
+    let Some(template) = templates.iter().find(|t| t.name == name) else {
+        return CommandResult::Info(format!(
+            "No template named {name}.\n\n{}",
+            list(&templates, &errors)
+        ));
+    };
+
+    match templates::parse_args(rest).and_then(|args| template.render(&args, cwd)) {
+        Ok(prompt) => CommandResult::Prompt(prompt),
+        Err(e) => CommandResult::Info(format!("{e:#}")),
+    }
+}
+
+fn list(templates: &[Template], errors: &[(std::path::PathBuf, anyhow::Error)]) -> String {
+    let mut text = if templates.is_empty() {
+        String::from(
+            "No templates yet. Write prompts with {{placeholders}} to \
+             .claude/templates/<name>.md (or ~/.claude/templates/).\n",
+        )
+    } else {
+        let mut text = String::from("Templates:\n");
+        for template in templates {
+            text.push_str(&format!("  {}", template.usage()));
+            if let Some(description) = &template.description {
+                text.push_str(&format!(" — {description}"));
+            }
+            text.push('\n');
+        }
+        text
+    };
+
+    for (path, e) in errors {
+        text.push_str(&format!("  ✗ {}: {e:#}\n", path.display()));
+    }
+
+    text.push('\n');
+    text.push_str(USAGE);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_a_project_template() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = templates::project_templates_dir(tmp.path());
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.md"), "Release notes for v{{version}}").unwrap();
+
+        let CommandResult::Prompt(prompt) = run("notes version=2.1", tmp.path()) else {
+            panic!("expected a prompt");
+        };
+        assert_eq!(prompt, "Release notes for v2.1");
+
+        let CommandResult::Info(text) = run("notes", tmp.path()) else {
+            panic!("expected an error");
+        };
+        assert!(text.contains("missing `version`"), "{text}");
+
+        let CommandResult::Info(text) = run("other", tmp.path()) else {
+            panic!("expected an error");
+        };
+        assert!(text.contains("/t notes version=<string>"), "{text}");
+    }
+}
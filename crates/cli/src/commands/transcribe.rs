@@ -0,0 +1,265 @@
+use std::io::Cursor;
+use std::pin::Pin;
+
+use anyhow::{Context, Result, anyhow, bail};
+use claude_code_core::config::{VoiceBackend, VoiceSettings};
+use claude_code_core::tls;
+
+use super::whisper::Whisper;
+
+/// Microphone audio, mono.
+pub struct Recording {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl Recording {
+    /// 16-bit PCM WAV, which every backend accepts.
+    pub fn wav(&self) -> Result<Vec<u8>> {
+        encode_wav(&self.samples, self.sample_rate)
+    }
+}
+
+/// Speech to text for `/rec`.
+pub trait Transcriber: Send + Sync {
+    /// Shown while transcribing, e.g. "Deepgram (nova-3)".
+    fn name(&self) -> String;
+
+    fn transcribe<'a>(
+        &'a self,
+        recording: &'a Recording,
+    ) -> Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>>;
+
+    /// Shown instead of the name while transcription waits on something
+    /// slow, such as downloading a model.
+    fn progress(&self) -> Option<String> {
+        None
+    }
+}
+
+/// The backend chosen in settings. Fails before anything is recorded if
+/// its API key is missing.
+pub fn from_settings(voice: &VoiceSettings) -> Result<Box<dyn Transcriber>> {
+    let backend = voice.backend.unwrap_or_default();
+    if backend == VoiceBackend::Whisper {
+        return Ok(Box::new(Whisper::new(voice)));
+    }
+
+    let (key_env, default_model) = match backend {
+        VoiceBackend::Mistral => ("MISTRAL_API_KEY", "voxtral-mini-2602"),
+        VoiceBackend::OpenAi => ("OPENAI_API_KEY", "whisper-1"),
+        VoiceBackend::Deepgram => ("DEEPGRAM_API_KEY", "nova-3"),
+        VoiceBackend::Whisper => unreachable!(),
+    };
+    let key_env = voice.api_key_env.as_deref().unwrap_or(key_env);
+    let api_key = std::env::var(key_env)
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .with_context(|| {
+            format!(
+                "{key_env} not set. Set it, pick another voice.backend, or use \
+                 \"whisper\" to transcribe offline."
+            )
+        })?;
+
+    Ok(Box::new(Api {
+        backend,
+        api_key,
+        model: voice
+            .model
+            .clone()
+            .unwrap_or_else(|| default_model.to_string()),
+        language: voice.language.clone(),
+    }))
+}
+
+// ---------------------------------------------------------------------------
+// Transcription APIs
+// ---------------------------------------------------------------------------
+
+struct Api {
+    backend: VoiceBackend,
+    api_key: String,
+    model: String,
+    language: Option<String>,
+}
+
+impl Transcriber for Api {
+    fn name(&self) -> String {
+        let provider = match self.backend {
+            VoiceBackend::Mistral => "Mistral",
+            VoiceBackend::OpenAi => "OpenAI",
+            VoiceBackend::Deepgram => "Deepgram",
+            VoiceBackend::Whisper => "whisper.cpp",
+        };
+        format!("{provider} ({})", self.model)
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        recording: &'a Recording,
+    ) -> Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>> {
+        Box::pin(async move {
+            let wav = recording.wav()?;
+            let request = match self.backend {
+                VoiceBackend::Deepgram => tls::client()
+                    .post(self.deepgram_url())
+                    .header("authorization", format!("Token {}", self.api_key))
+                    .header("content-type", "audio/wav")
+                    .body(wav),
+                VoiceBackend::Mistral => tls::client()
+                    .post("https://api.mistral.ai/v1/audio/transcriptions")
+                    .header("x-api-key", &self.api_key)
+                    .multipart(self.form(wav)?),
+                VoiceBackend::OpenAi => tls::client()
+                    .post("https://api.openai.com/v1/audio/transcriptions")
+                    .bearer_auth(&self.api_key)
+                    .multipart(self.form(wav)?),
+                VoiceBackend::Whisper => unreachable!(),
+            };
+
+            let resp = request
+                .send()
+                .await
+                .context("Transcription request failed")?;
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let body = resp.text().await.unwrap_or_default();
+                bail!("Transcription failed ({status}): {body}");
+            }
+            let json: serde_json::Value = resp.json().await?;
+            self.text(&json)
+        })
+    }
+}
+
+impl Api {
+    /// The OpenAI-style upload Mistral shares.
+    fn form(&self, wav: Vec<u8>) -> Result<reqwest::multipart::Form> {
+        let part = reqwest::multipart::Part::bytes(wav)
+            .file_name("recording.wav")
+            .mime_str("audio/wav")?;
+        let mut form = reqwest::multipart::Form::new()
+            .text("model", self.model.clone())
+            .part("file", part);
+        if let Some(language) = &self.language {
+            form = form.text("language", language.clone());
+        }
+        Ok(form)
+    }
+
+    fn deepgram_url(&self) -> String {
+        let language = match &self.language {
+            Some(language) => format!("language={language}"),
+            None => "detect_language=true".to_string(),
+        };
+        format!(
+            "https://api.deepgram.com/v1/listen?model={}&smart_format=true&{language}",
+            self.model
+        )
+    }
+
+    fn text(&self, json: &serde_json::Value) -> Result<String> {
+        let text = match self.backend {
+            VoiceBackend::Deepgram => {
+                &json["results"]["channels"][0]["alternatives"][0]["transcript"]
+            }
+            _ => &json["text"],
+        };
+        text.as_str()
+            .map(|s| s.trim().to_string())
+            .ok_or_else(|| anyhow!("No transcript in the {} response", self.name()))
+    }
+}
+
+pub(super) fn encode_wav(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(Vec::new());
+
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut writer = hound::WavWriter::new(&mut cursor, spec)?;
+
+    for &sample in samples {
+        let clamped = sample.clamp(-1.0, 1.0);
+        let value = (clamped * i16::MAX as f32) as i16;
+        writer.write_sample(value)?;
+    }
+
+    writer.finalize()?;
+
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(backend: VoiceBackend, model: &str, language: Option<&str>) -> Api {
+        Api {
+            backend,
+            api_key: "key".to_string(),
+            model: model.to_string(),
+            language: language.map(String::from),
+        }
+    }
+
+    #[test]
+    fn deepgram_detects_the_language_unless_told() {
+        assert_eq!(
+            api(VoiceBackend::Deepgram, "nova-3", None).deepgram_url(),
+            "https://api.deepgram.com/v1/listen?model=nova-3&smart_format=true&detect_language=true"
+        );
+        assert!(
+            api(VoiceBackend::Deepgram, "nova-3", Some("fr"))
+                .deepgram_url()
+                .ends_with("&language=fr")
+        );
+    }
+
+    #[test]
+    fn reads_each_response_shape() {
+        let openai = api(VoiceBackend::OpenAi, "whisper-1", None);
+        assert_eq!(
+            openai
+                .text(&serde_json::json!({"text": " Hello. "}))
+                .unwrap(),
+            "Hello."
+        );
+
+        let deepgram = api(VoiceBackend::Deepgram, "nova-3", None);
+        let response = serde_json::json!({"results": {"channels": [
+            {"alternatives": [{"transcript": "Bonjour.", "confidence": 0.98}]}
+        ]}});
+        assert_eq!(deepgram.text(&response).unwrap(), "Bonjour.");
+        assert!(deepgram.text(&serde_json::json!({"text": "x"})).is_err());
+        assert_eq!(deepgram.name(), "Deepgram (nova-3)");
+    }
+
+    #[test]
+    fn missing_api_keys_fail_before_recording() {
+        let voice = VoiceSettings {
+            backend: Some(VoiceBackend::OpenAi),
+            api_key_env: Some("CCRS_TEST_UNSET_TRANSCRIPTION_KEY".to_string()),
+            ..Default::default()
+        };
+        let error = from_settings(&voice).err().unwrap();
+        assert!(
+            error
+                .to_string()
+                .starts_with("CCRS_TEST_UNSET_TRANSCRIPTION_KEY not set")
+        );
+    }
+
+    #[test]
+    fn wav_header_carries_the_sample_rate() {
+        let wav = encode_wav(&[0.0, 0.5, -0.5], 16_000).unwrap();
+        let reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 16_000);
+        assert_eq!(reader.len(), 3);
+    }
+}
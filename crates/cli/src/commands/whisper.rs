@@ -0,0 +1,374 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result, anyhow, bail};
+use claude_code_core::config::VoiceSettings;
+use claude_code_core::tls;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use super::transcribe::{Recording, Transcriber, encode_wav};
+
+/// whisper.cpp's converted models, as `ggml-<name>.bin`.
+const MODELS_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+const DEFAULT_MODEL: &str = "base.en";
+
+/// SHA-256 of the models in `MODELS_URL`, checked after downloading. Other
+/// models need `voice.modelSha256`.
+const MODEL_SHA256: &[(&str, &str)] = &[
+    (
+        "tiny",
+        "be07e048e1e599ad46341c8d2a135645097a538221678b7acdd1b1919c6e1b21",
+    ),
+    (
+        "tiny.en",
+        "921e4cf8686fdd993dcd081a5da5b6c365bfde1162e72b08d75ac75289920b1f",
+    ),
+    (
+        "base",
+        "60ed5bc3dd14eea856493d334349b405782ddcaf0028d4b5df4088345fba2efe",
+    ),
+    (
+        "base.en",
+        "a03779c86df3323075f5e796cb2ce5029f00ec8869eee3fdfb897afe36c6d002",
+    ),
+    (
+        "small",
+        "1be3a9b2063867b937e64e2ec7483364a79917e157fa98c5d94b5c1fffea987b",
+    ),
+    (
+        "small.en",
+        "c6138d6d58ecc8322097e0f987c32f1be8bb0a18532a3f88f734d1bbf9c41e5d",
+    ),
+    (
+        "medium",
+        "6c14d5adee5f86394037b4e4e8b59f1673b6cee10e3cf0b11bbdbee79c156208",
+    ),
+    (
+        "medium.en",
+        "cc37e93478338ec7700281a7ac30a10128929eb8f427dda2e865faa8f6da4356",
+    ),
+    (
+        "large-v3",
+        "64d182b440b98d5203c4f9bd541544d84c605196c4f7b845dfa11fb23594d1e2",
+    ),
+    (
+        "large-v3-turbo",
+        "1fc70f774d38eb169993ac391eea357ef47c88757ef72ee5943879b7e8e2bc69",
+    ),
+];
+const DEFAULT_COMMAND: &str = "whisper-cli";
+
+/// whisper.cpp only reads 16 kHz audio.
+const SAMPLE_RATE: u32 = 16_000;
+
+/// Live transcription runs several parts at once: one download, and a
+/// file per part.
+static DOWNLOAD: Mutex<()> = Mutex::const_new(());
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Transcribes on this machine with whisper.cpp, downloading the model
+/// into the cache directory the first time.
+pub struct Whisper {
+    model: String,
+    /// Expected SHA-256 of the model file, if known.
+    sha256: Option<String>,
+    command: PathBuf,
+    language: String,
+    /// The model download in progress, for the status line.
+    progress: std::sync::Mutex<Option<String>>,
+}
+
+impl Whisper {
+    pub fn new(voice: &VoiceSettings) -> Self {
+        let model = voice.model.as_deref().unwrap_or(DEFAULT_MODEL).to_string();
+        // English-only models reject other languages
+        let language = match &voice.language {
+            Some(language) => language.clone(),
+            None if model.ends_with(".en") => "en".to_string(),
+            None => "auto".to_string(),
+        };
+        let sha256 = voice
+            .model_sha256
+            .as_deref()
+            .or_else(|| {
+                MODEL_SHA256
+                    .iter()
+                    .find(|(name, _)| *name == model)
+                    .map(|(_, hash)| *hash)
+            })
+            .map(str::to_ascii_lowercase);
+        Self {
+            model,
+            sha256,
+            command: voice
+                .whisper_command
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_COMMAND)),
+            language,
+            progress: std::sync::Mutex::new(None),
+        }
+    }
+
+    fn set_progress(&self, progress: Option<String>) {
+        *self.progress.lock().unwrap_or_else(|e| e.into_inner()) = progress;
+    }
+}
+
+impl Transcriber for Whisper {
+    fn name(&self) -> String {
+        format!("whisper.cpp ({})", self.model)
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        recording: &'a Recording,
+    ) -> Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>> {
+        Box::pin(run(self, recording))
+    }
+
+    fn progress(&self) -> Option<String> {
+        self.progress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+async fn run(whisper: &Whisper, recording: &Recording) -> Result<String> {
+    let model_path = ensure_model(&whisper.model, whisper.sha256.as_deref(), &|progress| {
+        whisper.set_progress(progress)
+    })
+    .await;
+    whisper.set_progress(None);
+    let model_path = model_path?;
+
+    let wav = std::env::temp_dir().join(format!(
+        "ccrs-rec-{}-{}.wav",
+        std::process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    let resampled = resample(&recording.samples, recording.sample_rate, SAMPLE_RATE);
+    std::fs::write(&wav, encode_wav(&resampled, SAMPLE_RATE)?)
+        .context("Failed to write the recording")?;
+
+    let command = &whisper.command;
+    let output = tokio::process::Command::new(command)
+        .arg("--model")
+        .arg(&model_path)
+        .arg("--file")
+        .arg(&wav)
+        .args([
+            "--language",
+            &whisper.language,
+            "--no-timestamps",
+            "--no-prints",
+        ])
+        .output()
+        .await;
+    let _ = std::fs::remove_file(&wav);
+
+    let output = match output {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => bail!(
+            "{} not found. Install whisper.cpp (e.g. `brew install whisper-cpp`) or set \
+             voice.whisperCommand.",
+            command.display()
+        ),
+        Err(e) => return Err(anyhow!(e).context(format!("Failed to run {}", command.display()))),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("whisper.cpp failed: {}", stderr.trim());
+    }
+
+    Ok(join_lines(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The model file, downloaded and checked against `sha256` if it isn't
+/// cached yet. `progress` gets a line to show while downloading.
+async fn ensure_model(
+    name: &str,
+    sha256: Option<&str>,
+    progress: &(dyn Fn(Option<String>) + Sync),
+) -> Result<PathBuf> {
+    let _download = DOWNLOAD.lock().await;
+    let path = model_path(name)?;
+    if path.exists() {
+        return Ok(path);
+    }
+    let Some(expected) = sha256 else {
+        bail!(
+            "No checksum is known for whisper model {name}. Set voice.modelSha256 to the \
+             SHA-256 of ggml-{name}.bin (shown on its Hugging Face page) to download it."
+        );
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("Failed to create the model cache directory")?;
+    }
+
+    let url = format!("{MODELS_URL}/ggml-{name}.bin");
+    let mut resp = tls::client()
+        .get(&url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("Failed to download whisper model {name}"))?;
+
+    let total = resp.content_length();
+    let partial = path.with_extension("part");
+    let mut file = std::fs::File::create(&partial).context("Failed to create the model file")?;
+    let mut hasher = Sha256::new();
+    let mut done = 0u64;
+    let mut shown = None;
+
+    while let Some(chunk) = resp.chunk().await.context("Model download interrupted")? {
+        file.write_all(&chunk)?;
+        hasher.update(&chunk);
+        done += chunk.len() as u64;
+
+        let mb = done / (1024 * 1024);
+        if shown != Some(mb) {
+            shown = Some(mb);
+            progress(Some(match total {
+                Some(total) => format!(
+                    "Downloading whisper model {name}: {mb} / {} MB…",
+                    total / (1024 * 1024)
+                ),
+                None => format!("Downloading whisper model {name}: {mb} MB…"),
+            }));
+        }
+    }
+
+    let actual = hex::encode(hasher.finalize());
+    if actual != expected {
+        drop(file);
+        let _ = std::fs::remove_file(&partial);
+        bail!(
+            "The downloaded whisper model {name} doesn't match its checksum (expected \
+             {expected}, got {actual}), so it was discarded"
+        );
+    }
+
+    file.sync_all()?;
+    std::fs::rename(&partial, &path).context("Failed to save the model file")?;
+    Ok(path)
+}
+
+/// `<cache>/ccrs/models/whisper/ggml-<name>.bin`, next to the search
+/// embedding model.
+fn model_path(name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    if !valid {
+        bail!("Invalid whisper model name `{name}`");
+    }
+    let cache = dirs::cache_dir().context("Could not determine the cache directory")?;
+    Ok(cache
+        .join("ccrs")
+        .join("models")
+        .join("whisper")
+        .join(format!("ggml-{name}.bin")))
+}
+
+/// Linear interpolation: plenty for speech.
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = f64::from(from) / f64::from(to);
+    let len = (samples.len() as f64 / ratio) as usize;
+
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let index = pos as usize;
+            let frac = (pos - index as f64) as f32;
+            let a = samples[index];
+            let b = samples.get(index + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// whisper.cpp prints one line per segment.
+fn join_lines(output: &str) -> String {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resamples_to_sixteen_kilohertz() {
+        let samples: Vec<f32> = (0..48_000).map(|i| i as f32).collect();
+
+        let resampled = resample(&samples, 48_000, SAMPLE_RATE);
+
+        assert_eq!(resampled.len(), 16_000);
+        assert_eq!(resampled[1], 3.0);
+        assert_eq!(resample(&samples, SAMPLE_RATE, SAMPLE_RATE).len(), 48_000);
+    }
+
+    #[test]
+    fn model_names_stay_in_the_cache_directory() {
+        assert!(
+            model_path("base.en")
+                .unwrap()
+                .ends_with("whisper/ggml-base.en.bin")
+        );
+        assert!(model_path("../../etc/passwd").is_err());
+        assert!(model_path("").is_err());
+    }
+
+    #[test]
+    fn known_models_have_checksums() {
+        let mut voice = VoiceSettings::default();
+        let whisper = Whisper::new(&voice);
+        assert_eq!(whisper.sha256.as_deref().map(str::len), Some(64));
+
+        voice.model = Some("base.en-q5_1".to_string());
+        assert_eq!(Whisper::new(&voice).sha256, None);
+        voice.model_sha256 = Some("ABC123".to_string());
+        assert_eq!(Whisper::new(&voice).sha256.as_deref(), Some("abc123"));
+    }
+
+    #[tokio::test]
+    async fn models_without_a_checksum_arent_downloaded() {
+        let error = ensure_model("ccrs-test-unknown", None, &|_| {})
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("voice.modelSha256"));
+    }
+
+    #[test]
+    fn english_models_default_to_english() {
+        let mut voice = VoiceSettings::default();
+        assert_eq!(Whisper::new(&voice).language, "en");
+
+        voice.model = Some("small".to_string());
+        assert_eq!(Whisper::new(&voice).language, "auto");
+
+        voice.language = Some("de".to_string());
+        assert_eq!(Whisper::new(&voice).language, "de");
+    }
+
+    #[test]
+    fn segments_join_into_one_message() {
+        assert_eq!(
+            join_lines(" Hello there.\n\n How are you?\n"),
+            "Hello there. How are you?"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+/// Open `path` in `$VISUAL` or `$EDITOR` (falling back to `vi`) and wait for
+/// the editor to exit. The terminal must already be out of raw mode.
+pub fn open(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // Allow editors with arguments, e.g. `code --wait`
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("$EDITOR is empty")?;
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor `{editor}`"))?;
+
+    if !status.success() {
+        bail!("Editor `{editor}` exited with {status}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,343 @@
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+use claude_code_core::api::{ApiError, Usage};
+use claude_code_core::event::EventHandler;
+use claude_code_core::session::{BudgetExceeded, MaxTurnsReached, Session};
+
+use crate::permissions::RulePermissions;
+
+/// How headless output is written to stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Plain response text, streamed as it arrives
+    #[default]
+    Text,
+    /// A single JSON result object once the response is complete
+    Json,
+    /// Newline-delimited JSON events as they happen
+    StreamJson,
+}
+
+/// Writes session events to stdout in the chosen format. In text mode, tool
+/// activity and errors go to stderr so stdout stays pipeable.
+struct OutputHandler {
+    format: OutputFormat,
+    /// Full response text, for the final result.
+    text: String,
+    /// ID of the tool use in progress (tools run one at a time).
+    tool_id: String,
+}
+
+impl OutputHandler {
+    fn emit(&self, event: Value) {
+        if self.format == OutputFormat::StreamJson {
+            println!("{event}");
+        }
+    }
+}
+
+impl EventHandler for OutputHandler {
+    fn on_text(&mut self, text: &str) {
+        self.text.push_str(text);
+
+        match self.format {
+            OutputFormat::Text => {
+                let mut out = io::stdout().lock();
+                let _ = out.write_all(text.as_bytes());
+                let _ = out.flush();
+            }
+            _ => self.emit(json!({ "type": "text", "text": text })),
+        }
+    }
+
+    fn on_thinking(&mut self, text: &str) {
+        self.emit(json!({ "type": "thinking", "text": text }));
+    }
+
+    fn on_error(&mut self, message: &str) {
+        match self.format {
+            OutputFormat::Text => eprintln!("Error: {message}"),
+            _ => self.emit(json!({ "type": "error", "message": message })),
+        }
+    }
+
+    fn on_tool_use_start(&mut self, name: &str, id: &str, input: &Value) {
+        self.tool_id = id.to_string();
+
+        match self.format {
+            OutputFormat::Text => eprintln!("[{name}]"),
+            _ => self.emit(json!({
+                "type": "tool_use",
+                "id": id,
+                "name": name,
+                "input": input,
+            })),
+        }
+    }
+
+    fn on_tool_result(&mut self, name: &str, output: &str, is_error: bool) {
+        match self.format {
+            OutputFormat::Text => {
+                if is_error {
+                    eprintln!("[{name} failed] {}", output.lines().next().unwrap_or(""));
+                }
+            }
+            _ => self.emit(json!({
+                "type": "tool_result",
+                "tool_use_id": self.tool_id,
+                "name": name,
+                "content": output,
+                "is_error": is_error,
+            })),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Failures and exit codes
+// ---------------------------------------------------------------------------
+
+/// Ways a headless run can fail, each with a stable exit code so wrappers
+/// can branch on them. Clap's usage errors exit with 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Anything not covered below (exit 1).
+    Other,
+    /// Missing or rejected credentials (exit 3).
+    Auth,
+    /// The permission rules denied at least one tool call (exit 4). The
+    /// response is still printed.
+    PermissionDenied,
+    /// `--max-turns` was reached (exit 5).
+    MaxTurns,
+    /// The API or the network failed (exit 6).
+    Api,
+    /// `maxBudgetUsd` or `maxTokensPerSession` was reached (exit 7).
+    Budget,
+    /// Interrupted with Ctrl+C (exit 130).
+    Cancelled,
+}
+
+impl Failure {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Other => 1,
+            Self::Auth => 3,
+            Self::PermissionDenied => 4,
+            Self::MaxTurns => 5,
+            Self::Api => 6,
+            Self::Budget => 7,
+            Self::Cancelled => 130,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Other => "error",
+            Self::Auth => "auth_failed",
+            Self::PermissionDenied => "permission_denied",
+            Self::MaxTurns => "max_turns",
+            Self::Api => "api_error",
+            Self::Budget => "budget_exceeded",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub(crate) fn classify(e: &anyhow::Error) -> Self {
+        if let Some(e) = e.downcast_ref::<ApiError>() {
+            return match e {
+                ApiError::Cancelled => Self::Cancelled,
+                ApiError::Unauthorized(_) => Self::Auth,
+                ApiError::Request { .. } => Self::Api,
+            };
+        }
+        if e.downcast_ref::<MaxTurnsReached>().is_some() {
+            return Self::MaxTurns;
+        }
+        if e.downcast_ref::<BudgetExceeded>().is_some() {
+            return Self::Budget;
+        }
+        Self::Other
+    }
+}
+
+/// Report a failure as the last line on stderr, a JSON object, and return
+/// its exit code.
+pub fn fail(failure: Failure, message: &str) -> ExitCode {
+    eprintln!("{}", error_json(failure, message));
+    ExitCode::from(failure.exit_code())
+}
+
+fn error_json(failure: Failure, message: &str) -> Value {
+    json!({
+        "type": "error",
+        "error": failure.name(),
+        "exit_code": failure.exit_code(),
+        "message": message,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Run
+// ---------------------------------------------------------------------------
+
+fn usage_json(usage: &Usage) -> Value {
+    json!(usage)
+}
+
+/// The final event: the whole response, or the error that ended the run.
+pub(crate) fn result_json(session_id: &str, text: &str, outcome: &Result<Usage>) -> Value {
+    let mut result = json!({
+        "type": "result",
+        "session_id": session_id,
+        "is_error": outcome.is_err(),
+        "result": text,
+    });
+
+    match outcome {
+        Ok(usage) => result["usage"] = usage_json(usage),
+        Err(e) => result["error"] = json!(e.to_string()),
+    }
+
+    result
+}
+
+/// Send a single prompt, print the response and exit. Ctrl+C cancels.
+pub async fn run(
+    mut session: Session<RulePermissions>,
+    prompt: &str,
+    format: OutputFormat,
+) -> Result<ExitCode> {
+    let mut handler = OutputHandler {
+        format,
+        text: String::new(),
+        tool_id: String::new(),
+    };
+    let cancel = CancellationToken::new();
+
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            token.cancel();
+        }
+    });
+
+    handler.emit(json!({
+        "type": "init",
+        "session_id": session.id(),
+        "model": session.model(),
+        "cwd": session.cwd(),
+    }));
+
+    let result = session.send_message(prompt, &mut handler, &cancel).await;
+
+    if let Err(e) = session.save() {
+        eprintln!("Warning: failed to save session: {e}");
+    }
+
+    match format {
+        OutputFormat::Text => println!(),
+        OutputFormat::Json => println!("{}", result_json(session.id(), &handler.text, &result)),
+        OutputFormat::StreamJson => {
+            if let Ok(usage) = &result {
+                handler.emit(json!({ "type": "usage", "usage": usage_json(usage) }));
+            }
+            handler.emit(result_json(session.id(), &handler.text, &result));
+        }
+    }
+
+    let denied = session.permissions_mut().denied();
+    let code = match result {
+        Err(e) => {
+            if format == OutputFormat::Text {
+                eprintln!("Error: {e:#}");
+            }
+            fail(Failure::classify(&e), &format!("{e:#}"))
+        }
+        Ok(_) if denied > 0 => fail(
+            Failure::PermissionDenied,
+            &format!("The permission rules denied {denied} tool call(s)"),
+        ),
+        Ok(_) => ExitCode::SUCCESS,
+    };
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_json_success() {
+        let usage = Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            ..Default::default()
+        };
+        let value = result_json("abc", "hello", &Ok(usage));
+
+        assert_eq!(value["type"], "result");
+        assert_eq!(value["session_id"], "abc");
+        assert_eq!(value["is_error"], false);
+        assert_eq!(value["result"], "hello");
+        assert_eq!(value["usage"]["output_tokens"], 5);
+        assert!(value.get("error").is_none());
+    }
+
+    #[test]
+    fn result_json_error_keeps_partial_text() {
+        let value = result_json("abc", "partial", &Err(anyhow::anyhow!("Cancelled")));
+
+        assert_eq!(value["is_error"], true);
+        assert_eq!(value["result"], "partial");
+        assert_eq!(value["error"], "Cancelled");
+        assert!(value.get("usage").is_none());
+    }
+
+    #[test]
+    fn failures_are_classified_from_typed_errors() {
+        let classify = |e: anyhow::Error| Failure::classify(&e);
+
+        assert_eq!(classify(ApiError::Cancelled.into()), Failure::Cancelled);
+        assert_eq!(classify(ApiError::Unauthorized(401).into()), Failure::Auth);
+        assert_eq!(
+            classify(
+                ApiError::Request {
+                    status: Some(529),
+                    message: "Overloaded".into()
+                }
+                .into()
+            ),
+            Failure::Api
+        );
+        assert_eq!(classify(MaxTurnsReached(3).into()), Failure::MaxTurns);
+        assert_eq!(
+            classify(
+                BudgetExceeded {
+                    reason: "Paused".into(),
+                    mid_turn: true
+                }
+                .into()
+            ),
+            Failure::Budget
+        );
+        assert_eq!(classify(anyhow::anyhow!("disk full")), Failure::Other);
+    }
+
+    #[test]
+    fn error_json_names_the_failure() {
+        let value = error_json(Failure::MaxTurns, "Stopped after 3 turns");
+
+        assert_eq!(value["type"], "error");
+        assert_eq!(value["error"], "max_turns");
+        assert_eq!(value["exit_code"], 5);
+        assert_eq!(value["message"], "Stopped after 3 turns");
+    }
+}
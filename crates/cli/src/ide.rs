@@ -0,0 +1,545 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use claude_code_core::event::{EventHandler, TaskStatus};
+use claude_code_core::permission::{
+    self, Decision, PermissionConfig, PermissionHandler, PermissionMode, Tool,
+};
+use claude_code_core::session::Session;
+use claude_code_core::tools::ToolProgress;
+
+use crate::headless::result_json;
+use crate::permissions::{self, Reply};
+
+/// Where protocol events go: stdout for `--ide`, the browsers for `serve`.
+pub type Sink = Arc<dyn Fn(Value) + Send + Sync>;
+
+/// Writes each event as a line on stdout.
+pub fn stdout_sink() -> Sink {
+    Arc::new(|event| {
+        let mut out = io::stdout().lock();
+        let _ = writeln!(out, "{event}");
+        let _ = out.flush();
+    })
+}
+
+pub fn error(message: impl Into<String>) -> Value {
+    json!({ "type": "error", "message": message.into() })
+}
+
+// ---------------------------------------------------------------------------
+// Requests
+// ---------------------------------------------------------------------------
+
+/// A line from the editor.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Start a turn. Only one runs at a time.
+    Prompt { text: String },
+    /// Cancel the running turn.
+    Stop,
+    /// Answer a `permission_request`.
+    Permission {
+        id: String,
+        decision: Answer,
+        #[serde(default)]
+        feedback: Option<String>,
+    },
+    /// Start a new conversation.
+    Clear,
+    /// Allow another budget's worth of spending after a turn paused on it.
+    ExtendBudget,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Answer {
+    Allow,
+    AllowAlways,
+    Deny,
+}
+
+impl Answer {
+    fn reply(self, feedback: Option<String>) -> Reply {
+        match (self, feedback) {
+            (Self::Allow, _) => Reply::AllowOnce,
+            (Self::AllowAlways, _) => Reply::AllowAlways,
+            (Self::Deny, Some(feedback)) if !feedback.trim().is_empty() => {
+                Reply::DenyWithFeedback(feedback)
+            }
+            (Self::Deny, _) => Reply::Deny,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Permissions
+// ---------------------------------------------------------------------------
+
+/// What the permission handler shares with the request loop.
+struct Shared {
+    sink: Sink,
+    /// Permission requests waiting for the editor, by ID. `None` once
+    /// the requests have ended and nobody is left to answer.
+    pending: Mutex<Option<HashMap<String, std_mpsc::SyncSender<Reply>>>>,
+    next_id: AtomicU64,
+    /// The tool call in progress, which permission requests belong to.
+    tool_use_id: Mutex<String>,
+}
+
+impl Shared {
+    fn emit(&self, event: Value) {
+        (self.sink)(event);
+    }
+}
+
+/// Asks the editor about tool calls the rules don't settle, blocking the
+/// session's worker thread until it answers.
+pub struct IdePermissions {
+    config: PermissionConfig,
+    mode: PermissionMode,
+    project_dir: PathBuf,
+    shared: Arc<Shared>,
+}
+
+impl IdePermissions {
+    pub fn new(
+        config: PermissionConfig,
+        mode: PermissionMode,
+        project_dir: PathBuf,
+        sink: Sink,
+    ) -> Self {
+        Self {
+            config,
+            mode,
+            project_dir,
+            shared: Arc::new(Shared {
+                sink,
+                pending: Mutex::new(Some(HashMap::new())),
+                next_id: AtomicU64::new(0),
+                tool_use_id: Mutex::new(String::new()),
+            }),
+        }
+    }
+}
+
+impl PermissionHandler for IdePermissions {
+    fn allow(&mut self, tool: &Tool<'_>) -> bool {
+        self.decide(tool) == Decision::Allow
+    }
+
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        let rule = self.config.check(tool, &self.project_dir);
+        if let Some(allowed) = self.mode.resolve(tool, rule) {
+            return if allowed {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            };
+        }
+
+        let id = (self.shared.next_id.fetch_add(1, Ordering::Relaxed) + 1).to_string();
+        let rule = permission::suggested_rule(tool);
+        let (tx, rx) = std_mpsc::sync_channel(1);
+        match self.shared.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(id.clone(), tx),
+            None => return Decision::Deny,
+        };
+
+        self.shared.emit(json!({
+            "type": "permission_request",
+            "id": id,
+            "tool_use_id": *self.shared.tool_use_id.lock().unwrap(),
+            "description": permissions::describe(tool),
+            "rule": rule,
+        }));
+
+        match rx.recv().unwrap_or(Reply::Deny) {
+            Reply::AllowOnce => Decision::Allow,
+            Reply::AllowAlways => {
+                if let Some(rule) = rule
+                    && let Err(e) =
+                        permissions::allow_always(&mut self.config, &self.project_dir, &rule)
+                {
+                    self.shared
+                        .emit(error(format!("Failed to save rule {rule}: {e:#}")));
+                }
+                Decision::Allow
+            }
+            Reply::Deny => Decision::Deny,
+            Reply::DenyWithFeedback(feedback) => Decision::DenyWithFeedback(feedback),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Events
+// ---------------------------------------------------------------------------
+
+/// Writes session events as lines, in the `stream-json` shapes.
+struct Events {
+    shared: Arc<Shared>,
+    cwd: PathBuf,
+    /// The turn's response text, for its result.
+    text: String,
+}
+
+impl Events {
+    fn tool_use_id(&self) -> String {
+        self.shared.tool_use_id.lock().unwrap().clone()
+    }
+}
+
+impl EventHandler for Events {
+    fn on_text(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.shared.emit(json!({ "type": "text", "text": text }));
+    }
+
+    fn on_thinking(&mut self, text: &str) {
+        self.shared
+            .emit(json!({ "type": "thinking", "text": text }));
+    }
+
+    fn on_error(&mut self, message: &str) {
+        self.shared.emit(error(message));
+    }
+
+    fn on_tool_use_start(&mut self, name: &str, id: &str, input: &Value) {
+        *self.shared.tool_use_id.lock().unwrap() = id.to_string();
+
+        let mut event = json!({
+            "type": "tool_use",
+            "id": id,
+            "name": name,
+            "input": input,
+        });
+        if let Some(diff) = preview(name, input, &self.cwd) {
+            event["diff"] = diff;
+        }
+        self.shared.emit(event);
+    }
+
+    fn on_tool_result(&mut self, name: &str, output: &str, is_error: bool) {
+        self.shared.emit(json!({
+            "type": "tool_result",
+            "tool_use_id": self.tool_use_id(),
+            "name": name,
+            "content": output,
+            "is_error": is_error,
+        }));
+    }
+
+    fn on_tool_progress(&mut self, _name: &str, progress: &ToolProgress) {
+        self.shared.emit(json!({
+            "type": "tool_progress",
+            "tool_use_id": self.tool_use_id(),
+            "label": progress.label,
+            "done": progress.done,
+            "total": progress.total,
+            "bytes": progress.bytes,
+        }));
+    }
+
+    fn on_usage(&mut self, usage: &claude_code_core::api::Usage) {
+        self.shared.emit(json!({ "type": "usage", "usage": usage }));
+    }
+
+    fn on_task_status(&mut self, id: &str, description: &str, status: &TaskStatus) {
+        let mut event = json!({
+            "type": "task_status",
+            "tool_use_id": id,
+            "description": description,
+        });
+        event["status"] = match status {
+            TaskStatus::Queued => "queued",
+            TaskStatus::Running(tool) => {
+                if let Some(tool) = tool {
+                    event["tool"] = tool.as_str().into();
+                }
+                "running"
+            }
+            TaskStatus::Retrying(secs) => {
+                event["retry_in"] = (*secs).into();
+                "retrying"
+            }
+            TaskStatus::Done => "done",
+            TaskStatus::Failed => "failed",
+        }
+        .into();
+        self.shared.emit(event);
+    }
+}
+
+/// The whole file before and after an Edit or Write, for the editor's diff
+/// view. `None` for other tools and for edits that won't apply.
+fn preview(name: &str, input: &Value, cwd: &Path) -> Option<Value> {
+    let field = |key: &str| input.get(key).and_then(Value::as_str);
+    let path = cwd.join(field("file_path")?);
+    let before = std::fs::read_to_string(&path).unwrap_or_default();
+
+    let after = match name {
+        "Write" => field("content")?.to_string(),
+        "Edit" => {
+            let (old, new) = (field("old_string")?, field("new_string")?);
+            if old.is_empty() || !before.contains(old) {
+                return None;
+            }
+            if input.get("replace_all").and_then(Value::as_bool) == Some(true) {
+                before.replace(old, new)
+            } else {
+                before.replacen(old, new, 1)
+            }
+        }
+        _ => return None,
+    };
+
+    Some(json!({ "path": path, "before": before, "after": after }))
+}
+
+// ---------------------------------------------------------------------------
+// Run
+// ---------------------------------------------------------------------------
+
+enum Command {
+    Prompt(String, CancellationToken),
+    Clear,
+    ExtendBudget,
+}
+
+/// Serve requests from stdin until it closes.
+pub async fn run(mut session: Session<IdePermissions>) -> Result<ExitCode> {
+    let sink = Arc::clone(&session.permissions_mut().shared.sink);
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let reader = tokio::spawn(async move {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Request>(&line) {
+                Ok(request) => {
+                    let _ = tx.send(request);
+                }
+                Err(e) => sink(error(format!("Invalid request: {e}"))),
+            }
+        }
+        anyhow::Ok(())
+    });
+
+    drive(session, rx).await?;
+    reader.await??;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Carry out requests until their sender goes away. The session runs in
+/// its own task, so stops and permission answers get through while a turn
+/// is running.
+pub async fn drive(
+    mut session: Session<IdePermissions>,
+    mut requests: mpsc::UnboundedReceiver<Request>,
+) -> Result<()> {
+    let shared = Arc::clone(&session.permissions_mut().shared);
+
+    shared.emit(json!({
+        "type": "init",
+        "session_id": session.id(),
+        "model": session.model(),
+        "cwd": session.cwd(),
+    }));
+
+    let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+    let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+    let worker = tokio::spawn(work(session, cmd_rx, done_tx));
+    let mut turn: Option<CancellationToken> = None;
+
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                let Some(request) = request else {
+                    break;
+                };
+
+                match request {
+                    Request::Prompt { .. } | Request::Clear if turn.is_some() => {
+                        shared.emit(error("A prompt is still running; send stop first."));
+                    }
+                    Request::Prompt { text } => {
+                        let cancel = CancellationToken::new();
+                        turn = Some(cancel.clone());
+                        let _ = cmd_tx.send(Command::Prompt(text, cancel));
+                    }
+                    Request::Clear => {
+                        let _ = cmd_tx.send(Command::Clear);
+                    }
+                    Request::ExtendBudget => {
+                        let _ = cmd_tx.send(Command::ExtendBudget);
+                    }
+                    Request::Stop => {
+                        if let Some(cancel) = &turn {
+                            cancel.cancel();
+                        }
+                    }
+                    Request::Permission { id, decision, feedback } => {
+                        let respond = shared
+                            .pending
+                            .lock()
+                            .unwrap()
+                            .as_mut()
+                            .and_then(|pending| pending.remove(&id));
+                        match respond {
+                            Some(respond) => {
+                                let _ = respond.send(decision.reply(feedback));
+                                shared.emit(json!({ "type": "permission_answered", "id": id }));
+                            }
+                            None => shared.emit(error(format!("No permission request {id} is waiting."))),
+                        }
+                    }
+                }
+            }
+            Some(()) = done_rx.recv() => turn = None,
+        }
+    }
+
+    // Nobody is left: stop the turn and deny whatever it would have asked
+    if let Some(cancel) = turn {
+        cancel.cancel();
+    }
+    shared.pending.lock().unwrap().take();
+    drop(cmd_tx);
+    worker.await?;
+    Ok(())
+}
+
+async fn work(
+    mut session: Session<IdePermissions>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    done: mpsc::UnboundedSender<()>,
+) {
+    let shared = Arc::clone(&session.permissions_mut().shared);
+    let mut events = Events {
+        shared: Arc::clone(&shared),
+        cwd: session.cwd().to_path_buf(),
+        text: String::new(),
+    };
+
+    while let Some(cmd) = commands.recv().await {
+        match cmd {
+            Command::Prompt(text, cancel) => {
+                events.text.clear();
+                let result = session.send_message(&text, &mut events, &cancel).await;
+                if let Err(e) = session.save() {
+                    shared.emit(error(format!("Failed to save session: {e:#}")));
+                }
+                shared.emit(result_json(session.id(), &events.text, &result));
+                let _ = done.send(());
+            }
+            Command::Clear => {
+                session.clear();
+                shared.emit(json!({ "type": "cleared", "session_id": session.id() }));
+            }
+            Command::ExtendBudget => {
+                let limits = session.extend_budget();
+                shared.emit(json!({
+                    "type": "budget_extended",
+                    "max_usd": limits.max_usd,
+                    "max_tokens": limits.max_tokens,
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_requests() {
+        let parse = |line: &str| serde_json::from_str::<Request>(line).unwrap();
+
+        assert_eq!(
+            parse(r#"{"type":"prompt","text":"Fix the build"}"#),
+            Request::Prompt {
+                text: "Fix the build".to_string()
+            }
+        );
+        assert_eq!(parse(r#"{"type":"stop"}"#), Request::Stop);
+        assert_eq!(parse(r#"{"type":"extend_budget"}"#), Request::ExtendBudget);
+
+        let Request::Permission {
+            id,
+            decision,
+            feedback,
+        } = parse(r#"{"type":"permission","id":"3","decision":"deny","feedback":"Use cargo"}"#)
+        else {
+            panic!("expected a permission answer");
+        };
+        assert_eq!(id, "3");
+        assert_eq!(
+            decision.reply(feedback),
+            Reply::DenyWithFeedback("Use cargo".to_string())
+        );
+        assert_eq!(Answer::AllowAlways.reply(None), Reply::AllowAlways);
+        assert_eq!(Answer::Deny.reply(Some(" ".to_string())), Reply::Deny);
+
+        assert!(serde_json::from_str::<Request>(r#"{"type":"launch"}"#).is_err());
+    }
+
+    #[test]
+    fn previews_whole_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.txt"), "one\ntwo\ntwo\n").unwrap();
+
+        let edit = json!({"file_path": "a.txt", "old_string": "two", "new_string": "2"});
+        let diff = preview("Edit", &edit, tmp.path()).unwrap();
+        assert_eq!(diff["before"], "one\ntwo\ntwo\n");
+        assert_eq!(diff["after"], "one\n2\ntwo\n");
+
+        let all = json!({"file_path": "a.txt", "old_string": "two", "new_string": "2", "replace_all": true});
+        assert_eq!(
+            preview("Edit", &all, tmp.path()).unwrap()["after"],
+            "one\n2\n2\n"
+        );
+
+        let missing = json!({"file_path": "a.txt", "old_string": "three", "new_string": "3"});
+        assert!(preview("Edit", &missing, tmp.path()).is_none());
+
+        let new_file = json!({"file_path": "b.txt", "content": "hi\n"});
+        let diff = preview("Write", &new_file, tmp.path()).unwrap();
+        assert_eq!(
+            (diff["before"].as_str(), diff["after"].as_str()),
+            (Some(""), Some("hi\n"))
+        );
+
+        assert!(preview("Read", &json!({"file_path": "a.txt"}), tmp.path()).is_none());
+    }
+
+    #[test]
+    fn nothing_is_allowed_once_the_editor_is_gone() {
+        let mut perms = IdePermissions::new(
+            PermissionConfig::default(),
+            PermissionMode::Default,
+            PathBuf::from("/project"),
+            Arc::new(|_| {}),
+        );
+        perms.shared.pending.lock().unwrap().take();
+
+        let decision = perms.decide(&Tool::Bash { command: "make" });
+        assert_eq!(decision, Decision::Deny);
+    }
+}
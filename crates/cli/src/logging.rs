@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use claude_code_core::config;
+
+/// Overrides the default filter, in `RUST_LOG` syntax (e.g. `ccrs_search=trace`).
+const FILTER_ENV: &str = "CCRS_LOG";
+
+/// Daily log files kept before the oldest is deleted.
+const MAX_LOG_FILES: usize = 7;
+
+/// `logs/` in the config directory.
+pub fn log_dir() -> Result<PathBuf> {
+    Ok(config::config_dir()?.join("logs"))
+}
+
+/// Send logs to a daily-rotated file under the config directory and, with
+/// `console`, to stderr as well. `verbose` lowers the default level from
+/// info to debug.
+///
+/// The console layer is for headless runs and subcommands only: stderr
+/// output would corrupt the TUI. Keep the returned guard alive until exit so
+/// buffered lines get flushed.
+pub fn init(verbose: bool, console: bool) -> Option<WorkerGuard> {
+    let default = if verbose {
+        "debug,hyper=info,reqwest=info,h2=info,rustls=info,tantivy=info"
+    } else {
+        "info,tantivy=warn"
+    };
+    let filter = || EnvFilter::try_from_env(FILTER_ENV).unwrap_or_else(|_| EnvFilter::new(default));
+
+    let (file_layer, guard) = match file_appender() {
+        Some(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(filter());
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    let console_layer = console.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_target(false)
+            .without_time()
+            .with_filter(filter())
+    });
+
+    // Fails only if a subscriber is already set, which is harmless
+    let _ = tracing_subscriber::registry()
+        .with(file_layer)
+        .with(console_layer)
+        .try_init();
+
+    guard
+}
+
+/// `None` when the log directory can't be created; logging is best-effort.
+fn file_appender() -> Option<RollingFileAppender> {
+    let dir = log_dir().ok()?;
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("ccrs")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+        .ok()
+}
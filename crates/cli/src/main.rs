@@ -1,16 +1,39 @@
+mod clipboard;
 mod commands;
+mod editor;
+mod headless;
+mod ide;
+mod logging;
 mod permissions;
+mod run;
+#[cfg(feature = "search")]
+mod search;
+#[cfg(feature = "serve")]
+mod serve;
+mod stdin;
 mod tui;
 mod ui;
+mod update;
 
-use anyhow::Result;
-use clap::Parser;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use claude_code_core::config::{Credentials, TokenType};
-use claude_code_core::session::SessionBuilder;
-use claude_code_core::{auth, config};
+use anyhow::{Context, Result, bail};
+use clap::{Parser, Subcommand};
 
-use permissions::ChannelPermissions;
+use claude_code_core::bedrock::Bedrock;
+use claude_code_core::config::{Mergeable, Settings, SettingsProblem, TokenType};
+use claude_code_core::models::ModelAliases;
+use claude_code_core::openai::OpenAi;
+use claude_code_core::permission::PermissionMode;
+use claude_code_core::session::{Budget, SessionBuilder};
+use claude_code_core::tls;
+use claude_code_core::tools::ToolEnv;
+use claude_code_core::vertex::Vertex;
+use claude_code_core::{config, store};
+
+use permissions::{ChannelPermissions, RulePermissions};
 
 #[derive(Parser)]
 #[command(name = "ccrs", version, about = "Claude Code — Rust edition")]
@@ -18,81 +41,658 @@ struct Cli {
     /// Force re-login, ignoring saved credentials
     #[arg(long)]
     login: bool,
+
+    /// Credential profile: its own login, default model and settings overlay
+    /// (stored under `profiles/<NAME>` in the config directory)
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Debug-level logging; headless runs and subcommands also log to stderr
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Model to use, by ID or name (e.g. "opus")
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Let Claude think before answering, with up to this many tokens of
+    /// extended thinking per request
+    #[arg(long, value_name = "TOKENS", value_parser = clap::value_parser!(u32).range(1024..))]
+    thinking: Option<u32>,
+
+    /// Working directory (default: current directory)
+    #[arg(long, value_name = "DIR")]
+    cwd: Option<PathBuf>,
+
+    /// Permission mode: default, acceptEdits, plan or bypassPermissions
+    /// (default: `permissions.defaultMode` from settings, else default)
+    #[arg(long, value_name = "MODE")]
+    permission_mode: Option<PermissionMode>,
+
+    /// Resume a saved session by ID
+    #[arg(long, value_name = "ID", conflicts_with = "continue_session")]
+    resume: Option<String>,
+
+    /// Resume the most recent session in the working directory
+    #[arg(short = 'c', long = "continue")]
+    continue_session: bool,
+
+    /// Extra directory Claude may read, edit and search (repeatable)
+    #[arg(long = "add-dir", value_name = "DIR")]
+    add_dir: Vec<PathBuf>,
+
+    /// Extra settings file, merged after the user and project settings
+    #[arg(long, value_name = "FILE")]
+    settings: Option<PathBuf>,
+
+    /// Send a single prompt, print the response and exit. Piped stdin is
+    /// attached as context.
+    #[arg(short, long, value_name = "PROMPT")]
+    print: Option<String>,
+
+    /// Stop a --print run after this many model round-trips (exit code 5)
+    #[arg(long, value_name = "N", requires = "print")]
+    max_turns: Option<usize>,
+
+    /// Replace the generated instructions (tool guide, directories and
+    /// CLAUDE.md files) for this run
+    #[arg(long, value_name = "PROMPT")]
+    system_prompt: Option<String>,
+
+    /// Add instructions after the generated ones for this run
+    #[arg(long, value_name = "PROMPT")]
+    append_system_prompt: Option<String>,
+
+    /// Output format for --print
+    #[arg(long, value_enum, default_value_t, requires = "print")]
+    output_format: headless::OutputFormat,
+
+    /// Let an editor drive the session: JSON requests on stdin, events on
+    /// stdout, one per line
+    #[arg(long, conflicts_with = "print")]
+    ide: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-async fn login() -> Result<Credentials> {
-    let method = ui::prompt_login_method()?;
+#[derive(Subcommand)]
+enum Command {
+    /// Log in and save credentials, without starting a session
+    Login,
 
-    match method {
-        ui::LoginMethod::OAuth => {
-            let store_refresh = ui::prompt_store_refresh()?;
-            let session = auth::start_oauth()?;
+    /// Revoke the stored login and delete it
+    Logout,
 
-            println!("Opening browser for authentication...");
+    /// Download the latest release and replace this binary
+    SelfUpdate,
 
-            if webbrowser::open(&session.auth_url).is_err() {
-                println!("Could not open browser. Please visit this URL manually:");
-                println!("{}", session.auth_url);
-            }
+    /// Manage saved sessions
+    Sessions {
+        #[command(subcommand)]
+        command: SessionsCommand,
+    },
+
+    /// Search the codebase and print ranked hits, without starting a session
+    #[cfg(feature = "search")]
+    Search {
+        /// The search query (words are joined with spaces)
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// Maximum number of results
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+
+        /// Context lines around matches in snippets
+        #[arg(short = 'C', long = "context", default_value_t = 2)]
+        context_lines: usize,
+
+        /// Directory to index (default: current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Show each hit's BM25/semantic ranks, fusion score and boost
+        #[arg(long)]
+        explain: bool,
+
+        /// Print hits as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Build the search index and download the embedding model ahead of time
+    #[cfg(feature = "search")]
+    Index {
+        /// Directory to index (default: current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+
+    /// Record from the microphone, edit the transcript and print it, e.g.
+    /// for `ccrs -p "$(ccrs rec)"`
+    #[cfg(feature = "voice")]
+    Rec,
+
+    /// Work through a task file of prompts and shell commands in order,
+    /// without a terminal, and print a JSON report
+    Run {
+        /// The task file, YAML or JSON
+        file: PathBuf,
+
+        /// Write the report here instead of stdout
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+    },
+
+    /// Serve a web UI for a session, on this machine only by default
+    #[cfg(feature = "serve")]
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// The address to listen on; anything but loopback exposes the
+        /// session to the network
+        #[arg(long, default_value = "127.0.0.1")]
+        host: std::net::IpAddr,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionsCommand {
+    /// Delete sessions beyond `cleanupPeriodDays` and `sessionsMaxSizeMb`
+    Prune {
+        /// List what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
 
-            let input = ui::prompt_oauth_code()?;
-            let code = auth::parse_callback(&session, &input)?;
-            auth::exchange_oauth_code(&session, &code, store_refresh).await
+/// Run a non-interactive subcommand.
+async fn run_command(command: Command, profile: Option<&str>, settings: &Settings) -> Result<()> {
+    match command {
+        Command::Login => {
+            let creds = commands::login::prompt().await?;
+            config::save_credentials(profile, &creds)?;
+            println!("Credentials saved.");
+            Ok(())
         }
-        ui::LoginMethod::ApiKey => {
-            let key = ui::prompt_api_key()?;
-            Ok(Credentials {
-                token: key,
-                is_oauth: false,
-            })
+
+        Command::Logout => {
+            println!("{}", commands::logout::run(profile).await);
+            Ok(())
+        }
+
+        Command::SelfUpdate => update::self_update().await,
+
+        Command::Sessions {
+            command: SessionsCommand::Prune { dry_run },
+        } => {
+            let pruned = store::prune(settings.session_retention(), None, dry_run)?;
+            let verb = if dry_run { "Would delete" } else { "Deleted" };
+            println!(
+                "{verb} {} session{} ({:.1} MB); {} kept.",
+                pruned.removed,
+                if pruned.removed == 1 { "" } else { "s" },
+                pruned.bytes as f64 / (1024.0 * 1024.0),
+                pruned.kept
+            );
+            Ok(())
+        }
+
+        #[cfg(feature = "search")]
+        Command::Search {
+            query,
+            limit,
+            context_lines,
+            path,
+            explain,
+            json,
+        } => {
+            let dir = path.map_or_else(std::env::current_dir, Ok)?;
+            search::search(&dir, &query.join(" "), limit, context_lines, explain, json)
         }
-    }
-}
 
-async fn get_access_token(creds: &Credentials) -> Result<(String, bool, Option<Credentials>)> {
-    match creds.token_type() {
-        TokenType::OAuthAccess => Ok((creds.token.clone(), true, None)),
-        TokenType::OAuthRefresh => {
-            println!("Refreshing access token...");
-            let (access_token, updated_creds) = auth::refresh_access_token(creds).await?;
-            Ok((access_token, true, Some(updated_creds)))
+        #[cfg(feature = "search")]
+        Command::Index { path } => {
+            let dir = path.map_or_else(std::env::current_dir, Ok)?;
+            search::index(&dir)
         }
-        TokenType::ApiKey => Ok((creds.token.clone(), false, None)),
+
+        #[cfg(feature = "voice")]
+        Command::Rec => {
+            let text = commands::rec::dictate(&settings.voice).await?;
+            if text.trim().is_empty() {
+                bail!("Nothing was transcribed");
+            }
+            println!("{text}");
+            Ok(())
+        }
+
+        Command::Run { .. } => unreachable!("ccrs run starts sessions"),
+
+        #[cfg(feature = "serve")]
+        Command::Serve { .. } => unreachable!("ccrs serve starts a session"),
     }
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+async fn main() -> Result<ExitCode> {
+    let mut cli = Cli::parse();
+
+    // Headless runs keep stdout for the response or the IDE protocol
+    let scripted =
+        cli.print.is_some() || cli.ide || matches!(cli.command, Some(Command::Run { .. }));
+    // The TUI owns the terminal, so it only logs to the file
+    let interactive = cli.command.is_none() && !scripted;
+    let _log_guard = logging::init(cli.verbose, cli.verbose && !interactive);
+
+    if let Some(dir) = &cli.cwd {
+        std::env::set_current_dir(dir)
+            .with_context(|| format!("Failed to change directory to {}", dir.display()))?;
+    }
 
-    println!("claude-code-rs v0.1.0\n");
+    // `default` names the unnamed profile
+    let profile = cli
+        .profile
+        .take()
+        .filter(|name| name != config::DEFAULT_PROFILE);
+    if let Some(name) = &profile {
+        config::profile_dir(name)?;
+    }
+
+    let cwd = std::env::current_dir()?;
+
+    let mut settings = config::load_settings(&cwd);
+    let mut settings_files = config::settings_paths(&cwd);
+    if let Some(name) = &profile {
+        settings = settings.merge(config::load_profile_settings(name)?);
+        settings_files.push(config::profile_settings_path(name)?);
+    }
+    if let Some(path) = &cli.settings {
+        settings = settings.merge(config::read_settings_file(path)?);
+        settings_files.push(path.clone());
+    }
+    settings_files.push(config::managed_settings_path());
+    let settings_problems = config::check_settings_files(&settings_files);
+    settings = settings.merge(config::settings_from_env()?);
+    // Managed settings go last so nothing else can override them
+    settings = settings.merge(config::load_managed_settings());
+    // Before any HTTP client is built, subcommands included
+    tls::configure(&settings.tls)?;
+    #[cfg(feature = "otel")]
+    let _telemetry = claude_code_core::telemetry::init()?;
+
+    // Unlike the other subcommands, running tasks and serving need a session
+    let task_file = match cli.command.take_if(|c| matches!(c, Command::Run { .. })) {
+        Some(Command::Run { file, report }) => Some((file, report)),
+        _ => None,
+    };
+    #[cfg(feature = "serve")]
+    let serve = match cli.command.take_if(|c| matches!(c, Command::Serve { .. })) {
+        Some(Command::Serve { port, host }) => Some(std::net::SocketAddr::new(host, port)),
+        _ => None,
+    };
 
-    let creds = match config::load_credentials()? {
-        Some(c) if !cli.login => {
-            println!("Loaded saved credentials.");
-            c
+    if let Some(command) = cli.command {
+        run_command(command, profile.as_deref(), &settings).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let permission_mode = cli
+        .permission_mode
+        .or(settings.permissions.default_mode)
+        .unwrap_or_default();
+    if permission_mode == PermissionMode::BypassPermissions
+        && settings.permissions.bypass_disabled()
+    {
+        bail!("bypassPermissions mode is disabled by your settings (disableBypassPermissionsMode)");
+    }
+
+    // `--add-dir` extends the directories granted in settings
+    let dirs = &mut settings.permissions.additional_directories;
+    for dir in &cli.add_dir {
+        let dir = dir
+            .canonicalize()
+            .with_context(|| format!("Cannot access --add-dir {}", dir.display()))?;
+        if !dir.is_dir() {
+            bail!("--add-dir {} is not a directory", dir.display());
         }
-        _ => {
-            let c = login().await?;
-            config::save_credentials(&c)?;
-            println!("Credentials saved.");
-            c
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    let saved = if let Some(id) = &cli.resume {
+        Some(store::load(id)?)
+    } else if cli.continue_session {
+        Some(store::latest_for(&cwd)?.context("No saved session for this directory")?)
+    } else if !scripted && std::io::stdin().is_terminal() {
+        recover_interrupted(&cwd)?
+    } else {
+        None
+    };
+
+    // Startup cleanup; the session being resumed is kept whatever its age
+    let keep = saved.as_ref().map(|s| s.id.as_str());
+    match store::prune(settings.session_retention(), keep, false) {
+        Ok(pruned) if pruned.removed > 0 => {
+            tracing::info!(removed = pruned.removed, "pruned saved sessions");
         }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("could not prune saved sessions: {e:#}"),
+    }
+
+    // Unknown names are passed through as raw model IDs
+    let model_aliases = ModelAliases::new(std::mem::take(&mut settings.model_aliases));
+    let model = cli
+        .model
+        .or(settings.model.take())
+        .map(|m| model_aliases.resolve(&m).map_or(m, |(id, _)| id));
+
+    // Read piped input up front, before anything else touches stdin
+    let piped = if cli.print.is_some() {
+        stdin::read_piped()?
+    } else {
+        None
     };
 
-    let (access_token, is_oauth, updated_creds) = get_access_token(&creds).await?;
+    if !scripted {
+        println!("claude-code-rs v0.1.0\n");
+    }
+
+    let mut builder = if settings.bedrock.is_enabled() {
+        // AWS credentials sign the requests; no Anthropic login needed
+        let bedrock = match Bedrock::new(&settings.bedrock) {
+            Ok(bedrock) => bedrock,
+            Err(e) if scripted => {
+                return Ok(headless::fail(headless::Failure::Auth, &format!("{e:#}")));
+            }
+            Err(e) => return Err(e),
+        };
+        if !scripted {
+            println!("Using Amazon Bedrock in {}.", bedrock.region());
+        }
+        SessionBuilder::new(String::new(), false).bedrock(bedrock)
+    } else if settings.vertex.is_enabled() {
+        // Google credentials authorize the requests
+        let vertex = match Vertex::new(&settings.vertex) {
+            Ok(vertex) => vertex,
+            Err(e) if scripted => {
+                return Ok(headless::fail(headless::Failure::Auth, &format!("{e:#}")));
+            }
+            Err(e) => return Err(e),
+        };
+        if !scripted {
+            println!(
+                "Using Google Vertex AI in {} ({}).",
+                vertex.region(),
+                vertex.project()
+            );
+        }
+        SessionBuilder::new(String::new(), false).vertex(vertex)
+    } else if settings.openai.is_enabled() {
+        // Local servers need no credentials; a key, if any, is in settings
+        let openai = match OpenAi::new(&settings.openai) {
+            Ok(openai) => openai,
+            Err(e) if scripted => {
+                return Ok(headless::fail(headless::Failure::Auth, &format!("{e:#}")));
+            }
+            Err(e) => return Err(e),
+        };
+        if !scripted {
+            println!(
+                "Using the OpenAI-compatible endpoint at {}.",
+                openai.base_url()
+            );
+        }
+        SessionBuilder::new(String::new(), false).openai(openai)
+    } else {
+        let env_creds = config::credentials_from_env();
+        let from_env = env_creds.is_some();
+
+        let login_hint = match &profile {
+            Some(name) => format!("ccrs --profile {name} login"),
+            None => "ccrs login".to_string(),
+        };
 
-    if let Some(new_creds) = updated_creds {
-        config::save_credentials(&new_creds)?;
+        let mut stored = config::load_credentials(profile.as_deref())?;
+        // An access token saved without its refresh token can't be renewed
+        if !from_env && !cli.login && stored.as_ref().is_some_and(|c| c.is_expired(store::now())) {
+            let message = format!("The saved login has expired. Run `{login_hint}` again.");
+            if scripted {
+                return Ok(headless::fail(headless::Failure::Auth, &message));
+            }
+            println!("The saved login has expired.");
+            stored = None;
+        }
+
+        let creds = match (env_creds, stored) {
+            (Some((key, c)), _) => {
+                if !scripted {
+                    println!("Using credentials from {key}.");
+                }
+                c
+            }
+            (None, Some(c)) if !cli.login => {
+                if !scripted {
+                    match &profile {
+                        Some(name) => println!("Loaded saved credentials for profile {name}."),
+                        None => println!("Loaded saved credentials."),
+                    }
+                }
+                c
+            }
+            (None, _) if scripted => {
+                let message =
+                    format!("Not logged in. Set ANTHROPIC_API_KEY or run `{login_hint}` first.");
+                return Ok(headless::fail(headless::Failure::Auth, &message));
+            }
+            (None, _) => {
+                let c = commands::login::prompt().await?;
+                config::save_credentials(profile.as_deref(), &c)?;
+                println!("Credentials saved.");
+                c
+            }
+        };
+
+        if creds.token_type() == TokenType::OAuthRefresh {
+            eprintln!("Refreshing access token...");
+        }
+        let access = match commands::login::access_token(&creds).await {
+            Ok(access) => access,
+            Err(e) if scripted => {
+                return Ok(headless::fail(headless::Failure::Auth, &format!("{e:#}")));
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Environment credentials are never written to disk
+        if let Some(new_creds) = &access.updated
+            && !from_env
+        {
+            config::save_credentials(profile.as_deref(), new_creds)?;
+        }
+
+        let mut builder = SessionBuilder::new(access.token, access.is_oauth);
+        if let Some(mut refresh) = access.refresh {
+            if !from_env {
+                refresh = refresh.save_to(profile.clone());
+            }
+            builder = builder.token_refresh(refresh);
+        }
+        builder
+    };
+    builder = builder
+        .additional_dirs(settings.permissions.additional_directories.clone())
+        .pinned_files(std::mem::take(&mut settings.pinned_files))
+        .model_aliases(model_aliases.clone())
+        .summarize_tool_output(settings.summarize_tool_output != Some(false))
+        .budget(Budget {
+            max_usd: settings.max_budget_usd,
+            max_tokens: settings.max_tokens_per_session,
+        })
+        .repo(std::mem::take(&mut settings.repo))
+        .tool_env(ToolEnv::new(
+            std::mem::take(&mut settings.env),
+            &settings.redact_env,
+        ));
+    #[cfg(feature = "git")]
+    {
+        builder = builder.checkpoints(settings.checkpoints != Some(false));
+    }
+    if let Some(saved) = saved {
+        builder = builder.resume(saved);
+    }
+    if let Some(model) = model {
+        builder = builder.model(model);
+    }
+    if let Some(model) = settings.small_model.take() {
+        builder = builder.small_model(model);
+    }
+    if let Some(prompt) = cli.system_prompt {
+        builder = builder.system_prompt(prompt);
+    }
+    if let Some(prompt) = cli.append_system_prompt {
+        builder = builder.append_system_prompt(prompt);
+    }
+    if let Some(max_turns) = cli.max_turns {
+        builder = builder.max_turns(max_turns);
+    }
+    if let Some(max_tokens) = settings.max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    if let Some(budget) = cli.thinking.or(settings.thinking_budget()) {
+        builder = builder.thinking(budget);
     }
 
-    let cwd = std::env::current_dir()?;
-    let settings = config::load_settings(&cwd);
+    if let Some(prompt) = cli.print {
+        let prompt = match piped {
+            Some(input) => {
+                if input.truncated {
+                    eprintln!(
+                        "Note: stdin truncated to its first {} bytes; the rest wasn't read.",
+                        input.text.len()
+                    );
+                }
+                stdin::attach(&prompt, &input)
+            }
+            None => prompt,
+        };
+
+        for problem in &settings_problems {
+            eprintln!("Warning: {problem}");
+        }
+
+        let perms = RulePermissions::new(settings.permissions, permission_mode, cwd);
+        let session = builder.permissions(perms)?;
+        return headless::run(session, &prompt, cli.output_format).await;
+    }
+
+    if let Some((file, report)) = task_file {
+        for problem in &settings_problems {
+            eprintln!("Warning: {problem}");
+        }
+
+        let options = run::RunOptions {
+            permissions: settings.permissions.clone(),
+            permission_mode,
+            model_aliases,
+            report,
+        };
+        let perms = RulePermissions::new(settings.permissions, permission_mode, cwd);
+        let session = builder.permissions(perms)?;
+        return run::run(session, &file, options).await;
+    }
+
+    if cli.ide {
+        for problem in &settings_problems {
+            eprintln!("Warning: {problem}");
+        }
+
+        let perms = ide::IdePermissions::new(
+            settings.permissions,
+            permission_mode,
+            cwd,
+            ide::stdout_sink(),
+        );
+        let session = builder.permissions(perms)?;
+        return ide::run(session).await;
+    }
+
+    #[cfg(feature = "serve")]
+    if let Some(addr) = serve {
+        for problem in &settings_problems {
+            eprintln!("Warning: {problem}");
+        }
+
+        let hub = serve::Hub::new();
+        let perms =
+            ide::IdePermissions::new(settings.permissions, permission_mode, cwd, hub.sink());
+        let session = builder.permissions(perms)?;
+        return serve::run(session, hub, addr).await;
+    }
 
     let (ui_tx, ui_rx) = tokio::sync::mpsc::unbounded_channel();
-    let perms = ChannelPermissions::new(settings.permissions, cwd.clone(), ui_tx.clone());
 
-    let session = SessionBuilder::new(access_token, is_oauth).permissions(perms)?;
+    for problem in settings_problems {
+        let event = match problem {
+            SettingsProblem::Invalid { .. } => tui::UiEvent::Error(problem.to_string()),
+            SettingsProblem::UnknownKey { .. } => {
+                tui::UiEvent::Info(format!("Settings: {problem}"))
+            }
+        };
+        let _ = ui_tx.send(event);
+    }
+
+    // The notice shows up in the TUI whenever the check finishes
+    if settings.auto_updates.unwrap_or(true) {
+        let tx = ui_tx.clone();
+        tokio::spawn(async move {
+            if let Some(notice) = update::check().await {
+                let _ = tx.send(tui::UiEvent::Info(notice));
+            }
+        });
+    }
+    let perms = ChannelPermissions::new(
+        settings.permissions,
+        permission_mode,
+        cwd.clone(),
+        ui_tx.clone(),
+    );
 
-    tui::run(cwd, session, ui_tx, ui_rx)
+    let session = builder.permissions(perms)?;
+    tokio::spawn(session.refresh_model_list());
+
+    let options = tui::Options {
+        theme: settings.theme.as_ref(),
+        vim_mode: settings.vim_mode.unwrap_or(false),
+        status_line: settings.status_line.as_ref(),
+        permission_mode,
+        model_aliases,
+        #[cfg(feature = "voice")]
+        voice: &settings.voice,
+    };
+    tui::run(cwd, session, profile, options, ui_tx, ui_rx)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Offer the session a crash cut off in `cwd`, once: a restored session is
+/// saved as it stopped, a declined one is forgotten.
+fn recover_interrupted(cwd: &Path) -> Result<Option<store::SavedSession>> {
+    let saved = match store::interrupted_for(cwd) {
+        Ok(Some(saved)) => saved,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            tracing::warn!("could not look for interrupted sessions: {e:#}");
+            return Ok(None);
+        }
+    };
+
+    let restore = ui::prompt_restore(&saved)?;
+    if restore {
+        store::save(&saved)?;
+    }
+    store::discard_interrupted(&saved.id)?;
+    Ok(restore.then_some(saved))
 }
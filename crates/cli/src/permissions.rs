@@ -1,18 +1,34 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc as std_mpsc;
 
+use anyhow::Result;
 use tokio::sync::mpsc;
 
-use claude_code_core::permission::{PermissionConfig, PermissionHandler, Tool};
+use claude_code_core::config;
+use claude_code_core::permission::{
+    self, Decision, PermissionConfig, PermissionHandler, PermissionMode, RuleKind, Tool,
+};
 
 use crate::tui::UiEvent;
 
+/// The user's answer to a permission prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    AllowOnce,
+    /// Allow, and save the suggested rule so it isn't asked again.
+    AllowAlways,
+    Deny,
+    /// Deny, telling the model why or what to do instead.
+    DenyWithFeedback(String),
+}
+
 /// Channel-based permission handler for the TUI.
 ///
 /// On rule miss, sends a `UiEvent::PermissionRequest` with a oneshot channel
-/// and blocks the current thread waiting for the UI's y/n response.
+/// and blocks the current thread waiting for the UI's answer.
 pub struct ChannelPermissions {
     config: PermissionConfig,
+    mode: PermissionMode,
     project_dir: PathBuf,
     ui_tx: mpsc::UnboundedSender<UiEvent>,
 }
@@ -20,48 +36,137 @@ pub struct ChannelPermissions {
 impl ChannelPermissions {
     pub fn new(
         config: PermissionConfig,
+        mode: PermissionMode,
         project_dir: PathBuf,
         ui_tx: mpsc::UnboundedSender<UiEvent>,
     ) -> Self {
         Self {
             config,
+            mode,
             project_dir,
             ui_tx,
         }
     }
+
+    /// Rules in effect, for live edits from `/permissions`.
+    pub fn config_mut(&mut self) -> &mut PermissionConfig {
+        &mut self.config
+    }
+
+    fn allow_always(&mut self, rule: &str) {
+        let event = match allow_always(&mut self.config, &self.project_dir, rule) {
+            Ok(()) => UiEvent::Info(format!("Allowed {rule} in .claude/settings.local.json.")),
+            Err(e) => UiEvent::Error(format!("Failed to save rule {rule}: {e:#}")),
+        };
+        let _ = self.ui_tx.send(event);
+    }
+}
+
+/// Apply an "always allow" rule now and save it to the local settings.
+pub fn allow_always(config: &mut PermissionConfig, project_dir: &Path, rule: &str) -> Result<()> {
+    config.add_rule(RuleKind::Allow, rule);
+    let path = config::local_settings_path(project_dir);
+    config::add_permission_rule(&path, RuleKind::Allow, rule)?;
+    Ok(())
+}
+
+/// What a tool call would do, for permission prompts.
+pub fn describe(tool: &Tool<'_>) -> String {
+    match tool {
+        Tool::Bash { command } => format!("Run command: {command}"),
+        Tool::Read { path } => format!("Read file: {}", path.display()),
+        Tool::Write { path } => format!("Write file: {}", path.display()),
+        Tool::Edit { path } => format!("Edit file: {}", path.display()),
+        Tool::Fetch { url, method } => format!("HTTP {method} {url}"),
+        Tool::Git { subcommand } => format!("Git {subcommand}"),
+        Tool::Repo { action } => format!("Repo {action}"),
+        Tool::Glob => "Search files by pattern".to_string(),
+        Tool::Grep => "Search file contents".to_string(),
+        Tool::List => "List directory contents".to_string(),
+        Tool::Search => "Full-text search across codebase".to_string(),
+        Tool::TodoWrite => "Update the task list".to_string(),
+        Tool::Test => "Run the tests".to_string(),
+        _ => "Unknown tool action".to_string(),
+    }
 }
 
 impl PermissionHandler for ChannelPermissions {
     fn allow(&mut self, tool: &Tool<'_>) -> bool {
-        // Check rule-based config first
-        if let Some(allowed) = self.config.check(tool, &self.project_dir) {
-            return allowed;
+        self.decide(tool) == Decision::Allow
+    }
+
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        // Check rule-based config and permission mode first
+        let rule = self.config.check(tool, &self.project_dir);
+        if let Some(allowed) = self.mode.resolve(tool, rule) {
+            return if allowed {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            };
         }
 
         // No matching rule — ask the UI
-        let description = match tool {
-            Tool::Bash { command } => format!("Run command: {command}"),
-            Tool::Read { path } => format!("Read file: {}", path.display()),
-            Tool::Write { path } => format!("Write file: {}", path.display()),
-            Tool::Edit { path } => format!("Edit file: {}", path.display()),
-            Tool::Fetch { url, method } => format!("HTTP {method} {url}"),
-            Tool::Git { subcommand } => format!("Git {subcommand}"),
-            Tool::Glob => "Search files by pattern".to_string(),
-            Tool::Grep => "Search file contents".to_string(),
-            Tool::List => "List directory contents".to_string(),
-            Tool::Search => "Full-text search across codebase".to_string(),
-            _ => "Unknown tool action".to_string(),
-        };
-
+        let description = describe(tool);
+        let rule = permission::suggested_rule(tool);
         let (tx, rx) = std_mpsc::sync_channel(1);
 
         let _ = self.ui_tx.send(UiEvent::PermissionRequest {
             description,
+            rule: rule.clone(),
             respond: tx,
         });
 
         // Block until the UI responds — safe because this runs in a spawned
         // tokio task, blocking only one worker thread.
-        rx.recv().unwrap_or(false)
+        match rx.recv().unwrap_or(Reply::Deny) {
+            Reply::AllowOnce => Decision::Allow,
+            Reply::AllowAlways => {
+                if let Some(rule) = rule {
+                    self.allow_always(&rule);
+                }
+                Decision::Allow
+            }
+            Reply::Deny => Decision::Deny,
+            Reply::DenyWithFeedback(feedback) => Decision::DenyWithFeedback(feedback),
+        }
+    }
+}
+
+/// Rule-only permission handler for headless (`-p`) runs.
+///
+/// There is no one to ask, so anything not explicitly allowed by the
+/// permission rules or mode is denied.
+pub struct RulePermissions {
+    config: PermissionConfig,
+    mode: PermissionMode,
+    project_dir: PathBuf,
+    denied: usize,
+}
+
+impl RulePermissions {
+    pub fn new(config: PermissionConfig, mode: PermissionMode, project_dir: PathBuf) -> Self {
+        Self {
+            config,
+            mode,
+            project_dir,
+            denied: 0,
+        }
+    }
+
+    /// Tool calls denied so far.
+    pub fn denied(&self) -> usize {
+        self.denied
+    }
+}
+
+impl PermissionHandler for RulePermissions {
+    fn allow(&mut self, tool: &Tool<'_>) -> bool {
+        let rule = self.config.check(tool, &self.project_dir);
+        let allowed = self.mode.resolve(tool, rule).unwrap_or(false);
+        if !allowed {
+            self.denied += 1;
+        }
+        allowed
     }
 }
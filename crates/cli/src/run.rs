@@ -0,0 +1,483 @@
+//! `ccrs run`: work through a task file of prompts and shell commands,
+//! one after the other, without a terminal, and report how each went.
+//!
+//! The file is YAML or JSON.
+//!
+//! ```yaml
+//! model: sonnet
+//! permissionMode: acceptEdits
+//! timeout: 10m
+//! tasks:
+//!   - name: fix lints
+//!     prompt: Run cargo clippy and fix what it reports.
+//!   - name: test
+//!     command: cargo test
+//!     continueOnError: true
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::process::{ExitCode, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+use claude_code_core::event::EventHandler;
+use claude_code_core::models::ModelAliases;
+use claude_code_core::permission::{PermissionConfig, PermissionMode};
+use claude_code_core::session::Session;
+
+use crate::headless::Failure;
+use crate::permissions::RulePermissions;
+
+/// Command output kept in the report, from its end; the start of a long
+/// build log rarely says why it failed.
+const MAX_OUTPUT_CHARS: usize = 64 * 1024;
+
+// ---------------------------------------------------------------------------
+// Task file
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TaskFile {
+    /// Defaults for every task.
+    model: Option<String>,
+    permission_mode: Option<PermissionMode>,
+    timeout: Option<Timeout>,
+    #[serde(default)]
+    continue_on_error: bool,
+    tasks: Vec<Task>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Task {
+    name: Option<String>,
+    /// Sent to a fresh session.
+    prompt: Option<String>,
+    /// Run with `sh -c` in the working directory.
+    command: Option<String>,
+    model: Option<String>,
+    permission_mode: Option<PermissionMode>,
+    timeout: Option<Timeout>,
+    continue_on_error: Option<bool>,
+}
+
+/// Seconds, or a number with an `s`, `m` or `h` suffix.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Timeout {
+    Secs(u64),
+    Text(String),
+}
+
+impl Timeout {
+    fn duration(&self) -> Result<Duration> {
+        let text = match self {
+            Self::Secs(secs) => return Ok(Duration::from_secs(*secs)),
+            Self::Text(text) => text.trim(),
+        };
+        let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => text.split_at(i),
+            None => (text, "s"),
+        };
+        let number: u64 = number
+            .parse()
+            .with_context(|| format!("Invalid timeout \"{text}\""))?;
+        let secs = match unit.trim() {
+            "s" => number,
+            "m" => number * 60,
+            "h" => number * 3600,
+            _ => bail!("Invalid timeout \"{text}\": use seconds or a s, m or h suffix"),
+        };
+        Ok(Duration::from_secs(secs))
+    }
+}
+
+impl Task {
+    fn kind(&self) -> &'static str {
+        if self.command.is_some() {
+            "command"
+        } else {
+            "prompt"
+        }
+    }
+
+    fn label(&self, index: usize) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("task {}", index + 1),
+        }
+    }
+}
+
+/// Read a task file, YAML or JSON (which is also YAML). A bare list is taken
+/// as the tasks, without defaults.
+fn parse_task_file(text: &str) -> Result<TaskFile> {
+    let document: serde_norway::Value = serde_norway::from_str(text)?;
+    // Parsed again as the type, so errors keep their line and column
+    let file = if document.is_sequence() {
+        TaskFile {
+            tasks: serde_norway::from_str(text)?,
+            ..Default::default()
+        }
+    } else {
+        serde_norway::from_str(text)?
+    };
+
+    for (i, task) in file.tasks.iter().enumerate() {
+        match (&task.prompt, &task.command) {
+            (Some(_), Some(_)) => bail!("{} has both a prompt and a command", task.label(i)),
+            (None, None) => bail!("{} needs a prompt or a command", task.label(i)),
+            _ => {}
+        }
+        if let Some(timeout) = &task.timeout {
+            timeout.duration()?;
+        }
+    }
+    if let Some(timeout) = &file.timeout {
+        timeout.duration()?;
+    }
+    Ok(file)
+}
+
+// ---------------------------------------------------------------------------
+// Run
+// ---------------------------------------------------------------------------
+
+/// What tasks fall back on when neither they nor the file say otherwise.
+pub struct RunOptions {
+    pub permissions: PermissionConfig,
+    pub permission_mode: PermissionMode,
+    pub model_aliases: ModelAliases,
+    /// `--report`; the report goes to stdout without it.
+    pub report: Option<PathBuf>,
+}
+
+/// Collects the response; tool activity goes to stderr as in `--print`.
+#[derive(Default)]
+struct Collector {
+    text: String,
+}
+
+impl EventHandler for Collector {
+    fn on_text(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    fn on_error(&mut self, message: &str) {
+        eprintln!("  Error: {message}");
+    }
+
+    fn on_tool_use_start(&mut self, name: &str, _id: &str, _input: &Value) {
+        eprintln!("  [{name}]");
+    }
+
+    fn on_tool_result(&mut self, name: &str, output: &str, is_error: bool) {
+        if is_error {
+            eprintln!("  [{name} failed] {}", output.lines().next().unwrap_or(""));
+        }
+    }
+}
+
+/// Run every task in `path`, then write the JSON report. Exits with 1 if
+/// any task failed; Ctrl+C stops the run and skips what's left.
+pub async fn run(
+    mut session: Session<RulePermissions>,
+    path: &Path,
+    options: RunOptions,
+) -> Result<ExitCode> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file = parse_task_file(&text).with_context(|| format!("Invalid {}", path.display()))?;
+
+    let interrupt = CancellationToken::new();
+    let token = interrupt.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            token.cancel();
+        }
+    });
+
+    let default_model = session.model().to_string();
+    let started = Instant::now();
+    let mut reports = Vec::new();
+    let mut stopped = false;
+
+    for (i, task) in file.tasks.iter().enumerate() {
+        let label = task.label(i);
+        let mut report = json!({ "name": label, "kind": task.kind() });
+
+        if stopped || interrupt.is_cancelled() {
+            report["status"] = json!("skipped");
+            reports.push(report);
+            continue;
+        }
+        eprintln!("[{}/{}] {label}", i + 1, file.tasks.len());
+
+        let timeout = match task.timeout.as_ref().or(file.timeout.as_ref()) {
+            Some(timeout) => Some(timeout.duration()?),
+            None => None,
+        };
+        let task_started = Instant::now();
+        let cancel = interrupt.child_token();
+        let timer = timeout.map(|timeout| {
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                cancel.cancel();
+            })
+        });
+
+        let outcome = match (&task.prompt, &task.command) {
+            (_, Some(command)) => run_command(command, session.cwd(), &cancel, &mut report).await,
+            (Some(prompt), None) => {
+                let mode = task
+                    .permission_mode
+                    .or(file.permission_mode)
+                    .unwrap_or(options.permission_mode);
+                let model = match task.model.as_ref().or(file.model.as_ref()) {
+                    Some(m) => options
+                        .model_aliases
+                        .resolve(m)
+                        .map_or_else(|| m.clone(), |(id, _)| id),
+                    None => default_model.clone(),
+                };
+                if mode == PermissionMode::BypassPermissions
+                    && options.permissions.bypass_disabled()
+                {
+                    Err(anyhow::anyhow!(
+                        "bypassPermissions mode is disabled by your settings"
+                    ))
+                } else {
+                    session.clear();
+                    session.set_model(model);
+                    *session.permissions_mut() = RulePermissions::new(
+                        options.permissions.clone(),
+                        mode,
+                        session.cwd().to_path_buf(),
+                    );
+                    run_prompt(&mut session, prompt, &cancel, &mut report).await
+                }
+            }
+            (None, None) => unreachable!("checked when parsing"),
+        };
+        if let Some(timer) = timer {
+            timer.abort();
+        }
+
+        let timed_out = cancel.is_cancelled() && !interrupt.is_cancelled();
+        report["duration_ms"] = json!(task_started.elapsed().as_millis() as u64);
+        report["status"] = json!(match &outcome {
+            Ok(()) => "success",
+            Err(_) if timed_out => "timeout",
+            Err(_) if interrupt.is_cancelled() => "cancelled",
+            Err(_) => "failed",
+        });
+        match outcome {
+            Ok(()) => eprintln!("  done in {:.1}s", task_started.elapsed().as_secs_f64()),
+            Err(e) => {
+                let message = match timeout {
+                    Some(timeout) if timed_out => {
+                        format!("Timed out after {}s", timeout.as_secs())
+                    }
+                    _ => format!("{e:#}"),
+                };
+                eprintln!("  {message}");
+                report["error"] = json!(message);
+                if !task.continue_on_error.unwrap_or(file.continue_on_error) {
+                    stopped = true;
+                }
+            }
+        }
+        reports.push(report);
+    }
+
+    let success = reports.iter().all(|r| r["status"] == "success");
+    let report = json!({
+        "file": path,
+        "success": success,
+        "duration_ms": started.elapsed().as_millis() as u64,
+        "tasks": reports,
+    });
+    let text = serde_json::to_string_pretty(&report)?;
+    match &options.report {
+        Some(path) => std::fs::write(path, text + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        None => println!("{text}"),
+    }
+
+    Ok(if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(Failure::Other.exit_code())
+    })
+}
+
+/// Send `prompt` to the freshly cleared session. Denied tool calls fail the
+/// task, as they fail a `--print` run.
+async fn run_prompt(
+    session: &mut Session<RulePermissions>,
+    prompt: &str,
+    cancel: &CancellationToken,
+    report: &mut Value,
+) -> Result<()> {
+    let mut collector = Collector::default();
+    let result = session.send_message(prompt, &mut collector, cancel).await;
+
+    if let Err(e) = session.save() {
+        eprintln!("  Warning: failed to save session: {e}");
+    }
+    report["session_id"] = json!(session.id());
+    report["model"] = json!(session.model());
+    report["result"] = json!(collector.text);
+    report["cost_usd"] = json!(session.spent().0);
+
+    let usage = result.inspect_err(|e| {
+        report["exit_code"] = json!(Failure::classify(e).exit_code());
+    })?;
+    report["usage"] = json!(usage);
+
+    let denied = session.permissions_mut().denied();
+    if denied > 0 {
+        report["exit_code"] = json!(Failure::PermissionDenied.exit_code());
+        bail!("The permission rules denied {denied} tool call(s)");
+    }
+    report["exit_code"] = json!(0);
+    Ok(())
+}
+
+/// Run `command` with `sh -c`, killing it if `cancel` fires.
+async fn run_command(
+    command: &str,
+    cwd: &Path,
+    cancel: &CancellationToken,
+    report: &mut Value,
+) -> Result<()> {
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start sh")?;
+
+    let output = tokio::select! {
+        output = child.wait_with_output() => output?,
+        _ = cancel.cancelled() => bail!("Cancelled"),
+    };
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    report["output"] = json!(tail(&text, MAX_OUTPUT_CHARS));
+    report["exit_code"] = json!(output.status.code());
+
+    if !output.status.success() {
+        match output.status.code() {
+            Some(code) => bail!("Exited with code {code}"),
+            None => bail!("Killed by a signal"),
+        }
+    }
+    Ok(())
+}
+
+/// The last `max` bytes of `text`, on a character boundary.
+fn tail(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut start = text.len() - max;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_task_files_parse() {
+        let file = parse_task_file(
+            "# nightly\n\
+             model: sonnet\n\
+             timeout: 5m\n\
+             tasks:\n\
+             - name: fix lints  # the first one\n\
+             \x20 prompt: |\n\
+             \x20   Run clippy.\n\
+             \n\
+             \x20   Fix \"what\" it reports.\n\
+             \x20 permissionMode: acceptEdits\n\
+             - command: 'cargo test -- --nocapture'\n\
+             \x20 continueOnError: true\n\
+             \x20 timeout: 90\n",
+        )
+        .unwrap();
+
+        assert_eq!(file.model.as_deref(), Some("sonnet"));
+        assert_eq!(
+            file.timeout.unwrap().duration().unwrap(),
+            Duration::from_secs(300)
+        );
+        assert_eq!(file.tasks.len(), 2);
+
+        let first = &file.tasks[0];
+        assert_eq!(first.name.as_deref(), Some("fix lints"));
+        assert_eq!(
+            first.prompt.as_deref(),
+            Some("Run clippy.\n\nFix \"what\" it reports.\n")
+        );
+        assert_eq!(first.permission_mode, Some(PermissionMode::AcceptEdits));
+
+        let second = &file.tasks[1];
+        assert_eq!(second.command.as_deref(), Some("cargo test -- --nocapture"));
+        assert_eq!(second.continue_on_error, Some(true));
+        assert_eq!(second.label(1), "task 2");
+        assert_eq!(
+            second.timeout.as_ref().unwrap().duration().unwrap(),
+            Duration::from_secs(90)
+        );
+    }
+
+    #[test]
+    fn bad_task_files_are_rejected() {
+        assert!(parse_task_file("tasks: []\n    model: x\n").is_err());
+        assert!(parse_task_file("model: a\nmodel: b\ntasks: []\n").is_err());
+        // Text after a quoted string isn't dropped
+        let err = parse_task_file("- command: \"cargo build\" && cargo test\n").unwrap_err();
+        assert!(err.to_string().contains("line 1"), "{err}");
+        // A bare JSON list is the tasks
+        assert_eq!(
+            parse_task_file(r#"[{"prompt": "hi"}]"#)
+                .unwrap()
+                .tasks
+                .len(),
+            1
+        );
+        let err = parse_task_file("- name: both\n  prompt: a\n  command: b\n").unwrap_err();
+        assert!(err.to_string().contains("both a prompt and a command"));
+        assert!(parse_task_file("tasks:\n  - prompt: a\n    retries: 2\n").is_err());
+        assert!(parse_task_file("timeout: 5 days\ntasks: []\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn commands_report_their_output_and_exit_code() {
+        let cancel = CancellationToken::new();
+        let mut report = json!({});
+        run_command("echo out; exit 3", Path::new("."), &cancel, &mut report)
+            .await
+            .unwrap_err();
+        assert_eq!(report["exit_code"], 3);
+        assert_eq!(report["output"], "out\n");
+
+        assert_eq!(tail("héllo", 4), "llo");
+    }
+}
@@ -0,0 +1,136 @@
+//! Non-interactive `ccrs search` / `ccrs index` entry points.
+//!
+//! Progress goes to stderr so stdout only carries results.
+
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::Result;
+use ccrs_search::{Progress, ScoreExplanation, SearchHit, SearchIndex, SourceRank, Stage};
+
+use claude_code_core::tools::search::format_hits;
+
+/// Build the index under `dir` and print ranked hits for `query`.
+pub fn search(
+    dir: &Path,
+    query: &str,
+    limit: usize,
+    context_lines: usize,
+    explain: bool,
+    json: bool,
+) -> Result<()> {
+    let index = open(dir)?;
+    index.build_embeddings_with_progress(&print_progress)?;
+    let hits = index.search(query, limit, context_lines)?;
+
+    if json {
+        println!("{}", hits_to_json(&hits, explain));
+    } else if hits.is_empty() {
+        println!("No results found.");
+    } else {
+        println!("{}", format_hits(&hits, explain));
+    }
+
+    Ok(())
+}
+
+/// Build the BM25 index and embeddings under `dir`, downloading the embedding
+/// model if it is not cached yet.
+pub fn index(dir: &Path) -> Result<()> {
+    let index = open(dir)?;
+
+    let start = Instant::now();
+    index.build_embeddings_with_progress(&print_progress)?;
+    eprintln!("Embeddings ready in {:.1}s", start.elapsed().as_secs_f64());
+
+    Ok(())
+}
+
+fn open(dir: &Path) -> Result<SearchIndex> {
+    let start = Instant::now();
+    let (index, stats) = SearchIndex::open_with_progress(dir, &print_progress)?;
+
+    eprintln!(
+        "Indexed {} files ({:.1} KB, {} boilerplate terms pruned) in {:.1}s",
+        stats.files,
+        stats.bytes as f64 / 1024.0,
+        stats.pruned_terms,
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(index)
+}
+
+/// Overwrite one stderr line per stage, ending it once the stage is done.
+/// Padding covers a longer line left by a stage whose total was a guess.
+fn print_progress(progress: Progress) {
+    let line = match progress.stage {
+        Stage::Download => format!(
+            "Downloading the embedding model: {:.1}/{:.1} MB",
+            progress.done as f64 / 1e6,
+            progress.total as f64 / 1e6
+        ),
+        Stage::Index => format!("Reading files: {}/{}", progress.done, progress.total),
+        Stage::Embed => format!("Embedding files: {}/{}", progress.done, progress.total),
+    };
+
+    let end = if progress.done >= progress.total {
+        "\n"
+    } else {
+        ""
+    };
+    let mut stderr = std::io::stderr();
+    let _ = write!(stderr, "\r{line:<60}{end}");
+    let _ = stderr.flush();
+}
+
+fn hits_to_json(hits: &[SearchHit], explain: bool) -> serde_json::Value {
+    let hits: Vec<serde_json::Value> = hits
+        .iter()
+        .map(|hit| {
+            let snippets: Vec<serde_json::Value> = hit
+                .snippets
+                .iter()
+                .map(|s| {
+                    serde_json::json!({
+                        "line": s.line_number,
+                        "lines": s.lines,
+                    })
+                })
+                .collect();
+
+            let mut value = serde_json::json!({
+                "path": hit.path,
+                "score": hit.score,
+                "snippets": snippets,
+            });
+
+            if explain {
+                value["explain"] = explanation_to_json(&hit.explanation);
+            }
+
+            value
+        })
+        .collect();
+
+    serde_json::Value::Array(hits)
+}
+
+fn explanation_to_json(explanation: &ScoreExplanation) -> serde_json::Value {
+    let source = |rank: Option<SourceRank>| match rank {
+        Some(r) => serde_json::json!({
+            "rank": r.rank,
+            "score": r.score,
+            "contribution": r.contribution,
+        }),
+        None => serde_json::Value::Null,
+    };
+
+    serde_json::json!({
+        "bm25": source(explanation.bm25),
+        "semantic": source(explanation.semantic),
+        "fused": explanation.fused,
+        "boost": explanation.boost,
+    })
+}
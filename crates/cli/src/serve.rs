@@ -0,0 +1,227 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::Html;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{get, post};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::{broadcast, mpsc};
+
+use claude_code_core::session::Session;
+
+use crate::ide::{self, IdePermissions, Request, Sink};
+
+/// The whole web app: one page that speaks the `--ide` protocol.
+const PAGE: &str = include_str!("serve.html");
+
+/// Every event of the conversation, replayed to each browser that
+/// connects, then the live ones.
+pub struct Hub {
+    log: Mutex<Vec<Arc<str>>>,
+    live: broadcast::Sender<Arc<str>>,
+}
+
+impl Hub {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            log: Mutex::new(Vec::new()),
+            live: broadcast::channel(1024).0,
+        })
+    }
+
+    pub fn sink(self: &Arc<Self>) -> Sink {
+        let hub = Arc::clone(self);
+        Arc::new(move |event| hub.publish(event))
+    }
+
+    fn publish(&self, event: Value) {
+        let line: Arc<str> = event.to_string().into();
+        // Sent under the lock, so a new subscriber misses nothing
+        let mut log = self.log.lock().unwrap();
+        if event["type"] == "cleared" {
+            // Keeping `init`, which always comes first
+            log.truncate(1);
+        }
+        log.push(Arc::clone(&line));
+        let _ = self.live.send(line);
+    }
+
+    fn subscribe(&self) -> (Vec<Arc<str>>, broadcast::Receiver<Arc<str>>) {
+        let log = self.log.lock().unwrap();
+        (log.clone(), self.live.subscribe())
+    }
+}
+
+#[derive(Clone)]
+struct App {
+    hub: Arc<Hub>,
+    /// Taken on shutdown, which ends the session.
+    requests: Arc<Mutex<Option<mpsc::UnboundedSender<Request>>>>,
+    /// Required on every request: the page can run commands.
+    token: Arc<str>,
+}
+
+#[derive(Deserialize)]
+struct Auth {
+    token: Option<String>,
+}
+
+impl App {
+    fn check(&self, auth: &Auth) -> Result<(), StatusCode> {
+        match &auth.token {
+            Some(token) if **token == *self.token => Ok(()),
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+}
+
+async fn page() -> Html<&'static str> {
+    Html(PAGE)
+}
+
+async fn events(
+    State(app): State<App>,
+    Query(auth): Query<Auth>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    app.check(&auth)?;
+
+    let (replay, live) = app.hub.subscribe();
+    let replay = stream::iter(replay);
+    let live = stream::unfold(live, |mut live| async move {
+        loop {
+            match live.recv().await {
+                Ok(line) => return Some((line, live)),
+                // A slow browser misses some text rather than stalling others
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = replay
+        .chain(live)
+        .map(|line| Ok(Event::default().data(&*line)));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+async fn request(
+    State(app): State<App>,
+    Query(auth): Query<Auth>,
+    body: String,
+) -> Result<StatusCode, (StatusCode, String)> {
+    app.check(&auth)
+        .map_err(|status| (status, "Missing or wrong token".to_string()))?;
+
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {e}")))?;
+    // Not part of the editor protocol, but browsers that connect later
+    // should see what was asked
+    if let Request::Prompt { text } = &request {
+        app.hub.publish(json!({ "type": "prompt", "text": text }));
+    }
+    let sent = match app.requests.lock().unwrap().as_ref() {
+        Some(requests) => requests.send(request).is_ok(),
+        None => false,
+    };
+    if !sent {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The session has ended".to_string(),
+        ));
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Serve the web UI on `addr` until Ctrl+C.
+pub async fn run(
+    session: Session<IdePermissions>,
+    hub: Arc<Hub>,
+    addr: SocketAddr,
+) -> Result<ExitCode> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {addr}"))?;
+    let token: Arc<str> = format!("{:032x}", rand::random::<u128>()).into();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let requests = Arc::new(Mutex::new(Some(tx)));
+    let app = App {
+        hub,
+        requests: Arc::clone(&requests),
+        token: Arc::clone(&token),
+    };
+    let router = Router::new()
+        .route("/", get(page))
+        .route("/events", get(events))
+        .route("/request", post(request))
+        .with_state(app);
+
+    println!(
+        "Open http://{}/?token={token} (Ctrl+C to stop)",
+        listener.local_addr()?
+    );
+    if !addr.ip().is_loopback() {
+        println!(
+            "Anyone who can reach this address with the URL can run commands as you. \
+             An SSH tunnel to 127.0.0.1 is safer."
+        );
+    }
+
+    let driver = tokio::spawn(ide::drive(session, rx));
+    tokio::select! {
+        served = axum::serve(listener, router) => served?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    // Browsers may keep their event streams open; ending the requests ends
+    // the session, stopping any turn and saving it
+    requests.lock().unwrap().take();
+    driver.await??;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_browsers_get_the_conversation_so_far() {
+        let hub = Hub::new();
+        let sink = hub.sink();
+        sink(json!({"type": "init"}));
+        sink(json!({"type": "text", "text": "Hi"}));
+
+        let (replay, mut live) = hub.subscribe();
+        assert_eq!(replay.len(), 2);
+
+        sink(json!({"type": "cleared"}));
+        assert_eq!(&*live.try_recv().unwrap(), r#"{"type":"cleared"}"#);
+        let (replay, _) = hub.subscribe();
+        assert_eq!(replay.len(), 2);
+        assert!(replay[0].contains("init"));
+    }
+
+    #[test]
+    fn requests_need_the_token() {
+        let app = App {
+            hub: Hub::new(),
+            requests: Arc::new(Mutex::new(None)),
+            token: "abc".into(),
+        };
+        let auth = |token: Option<&str>| Auth {
+            token: token.map(String::from),
+        };
+
+        assert!(app.check(&auth(Some("abc"))).is_ok());
+        assert_eq!(app.check(&auth(Some("abd"))), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(app.check(&auth(None)), Err(StatusCode::UNAUTHORIZED));
+    }
+}
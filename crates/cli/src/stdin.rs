@@ -0,0 +1,134 @@
+use std::io::{self, IsTerminal, Read};
+
+use anyhow::{Context, Result};
+
+/// Maximum bytes of piped stdin attached to the first message.
+const MAX_STDIN_BYTES: usize = 100_000;
+
+/// Piped stdin content, possibly truncated to `MAX_STDIN_BYTES`.
+pub struct PipedInput {
+    pub text: String,
+    /// Whether stdin had more than `MAX_STDIN_BYTES`. The rest is never
+    /// read, so an endless pipe doesn't hang and a huge one isn't drained.
+    pub truncated: bool,
+}
+
+/// Read stdin when it is piped (not a TTY). Returns `None` for a terminal or
+/// empty input.
+pub fn read_piped() -> Result<Option<PipedInput>> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+
+    read_limited(stdin.lock(), MAX_STDIN_BYTES)
+}
+
+fn read_limited(reader: impl Read, limit: usize) -> Result<Option<PipedInput>> {
+    // One byte past the limit tells whether there was more
+    let mut buf = Vec::new();
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut buf)
+        .context("Failed to read stdin")?;
+
+    let truncated = buf.len() > limit;
+    if truncated {
+        buf.truncate(limit);
+        // Don't split a multi-byte character at the cut. Invalid bytes
+        // before it are left to the lossy conversion.
+        let mut start = 0;
+        while let Err(e) = std::str::from_utf8(&buf[start..]) {
+            match e.error_len() {
+                Some(len) => start += e.valid_up_to() + len,
+                None => {
+                    buf.truncate(start + e.valid_up_to());
+                    break;
+                }
+            }
+        }
+    }
+
+    let text = String::from_utf8_lossy(&buf).into_owned();
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(PipedInput { text, truncated }))
+}
+
+/// Prepend piped input to the user's prompt as context.
+pub fn attach(prompt: &str, input: &PipedInput) -> String {
+    let mut message = String::from("<stdin>\n");
+    message.push_str(input.text.trim_end());
+    message.push('\n');
+    if input.truncated {
+        message.push_str(&format!(
+            "[truncated: showing first {} of more than {MAX_STDIN_BYTES} bytes]\n",
+            input.text.len()
+        ));
+    }
+    message.push_str("</stdin>\n\n");
+    message.push_str(prompt);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_small_input_whole() {
+        let input = read_limited("hello\n".as_bytes(), 100).unwrap().unwrap();
+        assert_eq!(input.text, "hello\n");
+        assert!(!input.truncated);
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        // "é" is two bytes; a limit of 4 would cut the second one in half
+        let input = read_limited("abéé".as_bytes(), 4).unwrap().unwrap();
+        assert_eq!(input.text, "ab\u{e9}");
+        assert!(input.truncated);
+
+        let exact = read_limited("abcd".as_bytes(), 4).unwrap().unwrap();
+        assert!(!exact.truncated);
+    }
+
+    #[test]
+    fn invalid_bytes_before_the_cut_are_kept() {
+        // A stray Latin-1 "é" early in a long log
+        let mut log = b"caf\xe9 ".to_vec();
+        log.extend("ok é".repeat(10).bytes());
+        // The cut falls inside the third "é"
+        let input = read_limited(log.as_slice(), 19).unwrap().unwrap();
+        assert_eq!(input.text, "caf\u{fffd} ok éok éok ");
+        assert!(input.truncated);
+    }
+
+    #[test]
+    fn stops_reading_past_the_limit() {
+        // An endless pipe, like `tail -f`
+        let input = read_limited(io::repeat(b'x'), 10).unwrap().unwrap();
+        assert_eq!(input.text, "x".repeat(10));
+        assert!(input.truncated);
+    }
+
+    #[test]
+    fn empty_input_is_none() {
+        assert!(read_limited("  \n".as_bytes(), 100).unwrap().is_none());
+    }
+
+    #[test]
+    fn attach_adds_truncation_notice() {
+        let input = PipedInput {
+            text: "error: boom".to_string(),
+            truncated: true,
+        };
+        let message = attach("explain this", &input);
+        assert!(message.starts_with(
+            "<stdin>\nerror: boom\n[truncated: showing first 11 of more than 100000 bytes]\n</stdin>"
+        ));
+        assert!(message.ends_with("\n\nexplain this"));
+    }
+}
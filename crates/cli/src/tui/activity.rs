@@ -0,0 +1,269 @@
+use std::time::{Duration, Instant};
+
+use claude_code_core::event::TaskStatus;
+use claude_code_core::tools::ToolProgress;
+
+use super::status::format_tokens;
+
+/// Cells in a progress bar.
+const BAR_WIDTH: usize = 12;
+
+/// Longest tool detail shown, in characters.
+const MAX_DETAIL: usize = 40;
+
+/// What Claude is doing while busy, shown next to the spinner so long
+/// silences don't look like hangs.
+pub struct Activity {
+    started: Instant,
+    /// Running tools, e.g. "Bash: cargo test"; a subagent's calls nest
+    /// inside its Task.
+    tools: Vec<String>,
+    /// Latest progress of the running tool's slow step.
+    progress: Option<ToolProgress>,
+    /// Subagents running side by side as (ID, description, status), the
+    /// latest update last.
+    tasks: Vec<(String, String, TaskStatus)>,
+    /// Output tokens of the finished requests.
+    tokens: u64,
+    /// Characters streamed since the last usage report.
+    streamed: usize,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            tools: Vec::new(),
+            progress: None,
+            tasks: Vec::new(),
+            tokens: 0,
+            streamed: 0,
+        }
+    }
+}
+
+impl Activity {
+    pub fn tool_started(&mut self, name: &str, input: &serde_json::Value) {
+        self.tools.push(describe(name, input));
+    }
+
+    pub fn tool_finished(&mut self) {
+        self.tools.pop();
+        self.progress = None;
+    }
+
+    pub fn tool_progress(&mut self, progress: ToolProgress) {
+        self.progress = Some(progress);
+    }
+
+    pub fn task_status(&mut self, id: String, description: String, status: TaskStatus) {
+        self.tasks.retain(|(task, _, _)| *task != id);
+        self.tasks.push((id, description, status));
+    }
+
+    pub fn streamed(&mut self, text: &str) {
+        self.streamed += text.chars().count();
+    }
+
+    /// A request finished; its reported count replaces the estimate.
+    pub fn output_tokens(&mut self, tokens: u64) {
+        self.tokens += tokens;
+        self.streamed = 0;
+    }
+
+    /// "Bash: cargo test · 42s · 1.3k tokens"
+    pub fn summary(&self) -> String {
+        self.summary_at(self.started.elapsed())
+    }
+
+    fn summary_at(&self, elapsed: Duration) -> String {
+        // About four characters per token until the usage arrives
+        let tokens = self.tokens + self.streamed.div_ceil(4) as u64;
+
+        let mut parts = Vec::new();
+        if let Some(tool) = self.tools.last() {
+            parts.push(tool.clone());
+        } else if !self.tasks.is_empty() {
+            parts.push(format_tasks(&self.tasks));
+        }
+        if let Some(progress) = &self.progress {
+            parts.push(format_progress(progress));
+        }
+        parts.push(format_elapsed(elapsed));
+        if tokens > 0 {
+            parts.push(format!("{} tokens", format_tokens(tokens)));
+        }
+
+        parts.join(" · ")
+    }
+}
+
+/// Tool name and the input field that says what it's doing.
+fn describe(name: &str, input: &serde_json::Value) -> String {
+    let detail = ["command", "file_path", "pattern", "query", "description"]
+        .iter()
+        .find_map(|key| input.get(key).and_then(|v| v.as_str()))
+        .and_then(|s| s.lines().next())
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+
+    match detail {
+        Some(detail) if detail.chars().count() > MAX_DETAIL => {
+            let cut: String = detail.chars().take(MAX_DETAIL - 1).collect();
+            format!("{name}: {cut}…")
+        }
+        Some(detail) => format!("{name}: {detail}"),
+        None => name.to_string(),
+    }
+}
+
+/// "3 agents, 1 done · Find callers: Grep", naming the subagent heard from
+/// last that is still at work.
+fn format_tasks(tasks: &[(String, String, TaskStatus)]) -> String {
+    let finished = |status: &TaskStatus| matches!(status, TaskStatus::Done | TaskStatus::Failed);
+    let done = tasks.iter().filter(|(_, _, s)| finished(s)).count();
+    let mut text = format!("{} agents, {done} done", tasks.len());
+
+    if let Some((_, description, status)) = tasks.iter().rev().find(|(_, _, s)| !finished(s)) {
+        text.push_str(&format!(" · {description}"));
+        match status {
+            TaskStatus::Queued => text.push_str(": queued"),
+            TaskStatus::Running(Some(tool)) => text.push_str(&format!(": {tool}")),
+            TaskStatus::Retrying(secs) => {
+                text.push_str(&format!(": rate limited, retrying in {secs}s"));
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+/// "Embedding files ██████░░░░░░ 512/1024", or just the label while the
+/// total is unknown.
+fn format_progress(progress: &ToolProgress) -> String {
+    if progress.total == 0 {
+        return format!("{}…", progress.label);
+    }
+
+    let done = progress.done.min(progress.total);
+    let filled = (done as usize * BAR_WIDTH) / progress.total as usize;
+    let bar = "█".repeat(filled) + &"░".repeat(BAR_WIDTH - filled);
+    let count = if progress.bytes {
+        format!(
+            "{:.1}/{:.1} MB",
+            done as f64 / 1e6,
+            progress.total as f64 / 1e6
+        )
+    } else {
+        format!("{done}/{}", progress.total)
+    };
+
+    format!("{} {bar} {count}", progress.label)
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_names_the_tool_time_and_tokens() {
+        let mut activity = Activity::default();
+        activity.tool_started("Task", &serde_json::json!({"description": "Run the tests"}));
+        activity.tool_started("Bash", &serde_json::json!({"command": "cargo test\necho"}));
+        activity.output_tokens(1200);
+        activity.streamed(&"x".repeat(400));
+
+        assert_eq!(
+            activity.summary_at(Duration::from_secs(42)),
+            "Bash: cargo test · 42s · 1.3k tokens"
+        );
+
+        activity.tool_finished();
+        activity.output_tokens(150);
+        assert_eq!(
+            activity.summary_at(Duration::from_secs(125)),
+            "Task: Run the tests · 2m 05s · 1.4k tokens"
+        );
+    }
+
+    #[test]
+    fn progress_shows_a_bar_until_the_tool_ends() {
+        let mut activity = Activity::default();
+        activity.tool_started("Search", &serde_json::json!({"query": "auth"}));
+        activity.tool_progress(ToolProgress {
+            label: "Embedding files".to_string(),
+            done: 256,
+            total: 1024,
+            bytes: false,
+        });
+        assert_eq!(
+            activity.summary_at(Duration::from_secs(3)),
+            "Search: auth · Embedding files ███░░░░░░░░░ 256/1024 · 3s"
+        );
+
+        activity.tool_progress(ToolProgress {
+            label: "Downloading the embedding model".to_string(),
+            done: 45_500_000,
+            total: 91_000_000,
+            bytes: true,
+        });
+        assert!(
+            activity
+                .summary_at(Duration::ZERO)
+                .contains("██████░░░░░░ 45.5/91.0 MB")
+        );
+
+        activity.tool_finished();
+        assert_eq!(activity.summary_at(Duration::from_secs(4)), "4s");
+    }
+
+    #[test]
+    fn parallel_tasks_show_the_latest_busy_one() {
+        let mut activity = Activity::default();
+        let mut update = |id: &str, description: &str, status| {
+            activity.task_status(id.to_string(), description.to_string(), status);
+        };
+        update("a", "Find callers", TaskStatus::Queued);
+        update("b", "Review diff", TaskStatus::Queued);
+        update(
+            "a",
+            "Find callers",
+            TaskStatus::Running(Some("Grep".into())),
+        );
+        update("b", "Review diff", TaskStatus::Done);
+
+        assert_eq!(
+            activity.summary_at(Duration::from_secs(5)),
+            "2 agents, 1 done · Find callers: Grep · 5s"
+        );
+
+        activity.task_status("a".into(), "Find callers".into(), TaskStatus::Retrying(4));
+        assert!(
+            activity
+                .summary_at(Duration::ZERO)
+                .contains("Find callers: rate limited, retrying in 4s")
+        );
+
+        activity.task_status("a".into(), "Find callers".into(), TaskStatus::Failed);
+        assert_eq!(activity.summary_at(Duration::ZERO), "2 agents, 2 done · 0s");
+    }
+
+    #[test]
+    fn long_details_are_shortened() {
+        let input = serde_json::json!({"file_path": "a/".repeat(30)});
+        let label = describe("Read", &input);
+
+        assert_eq!(label.chars().count(), "Read: ".len() + MAX_DETAIL);
+        assert!(label.ends_with('…'));
+        assert_eq!(describe("Task", &serde_json::json!({})), "Task");
+    }
+}
@@ -0,0 +1,307 @@
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use similar::{ChangeTag, TextDiff};
+
+use super::highlight;
+use super::theme::Theme;
+
+/// Unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Files longer than this are diffed without syntax colors.
+const MAX_HIGHLIGHT_LINES: usize = 5000;
+
+/// The text an Edit or Write call replaces, and where it starts in the file.
+pub struct FileChange {
+    path: String,
+    old: String,
+    new: String,
+    /// 1-based line of the first line of `old` / `new`.
+    start_line: usize,
+}
+
+impl FileChange {
+    /// Capture an Edit or Write call before it runs, reading the file for
+    /// the edit's position and the content a Write replaces.
+    pub fn before_run(name: &str, input: &serde_json::Value, cwd: &Path) -> Option<Self> {
+        let mut change = Self::from_input(name, input)?;
+        let current = std::fs::read_to_string(cwd.join(&change.path)).ok();
+
+        match name {
+            "Write" => change.old = current.unwrap_or_default(),
+            _ => change.start_line = line_of(current.as_deref(), &change.old),
+        }
+
+        Some(change)
+    }
+
+    /// Rebuild a change from a past call. The file already holds the result,
+    /// so an edit is located by its new text and a Write shows as new lines.
+    pub fn after_run(name: &str, input: &serde_json::Value, cwd: &Path) -> Option<Self> {
+        let mut change = Self::from_input(name, input)?;
+
+        if name == "Edit" {
+            let current = std::fs::read_to_string(cwd.join(&change.path)).ok();
+            change.start_line = line_of(current.as_deref(), &change.new);
+        }
+
+        Some(change)
+    }
+
+    fn from_input(name: &str, input: &serde_json::Value) -> Option<Self> {
+        let field = |key: &str| input.get(key).and_then(|v| v.as_str());
+
+        let (old, new) = match name {
+            "Edit" => (field("old_string")?, field("new_string")?),
+            "Write" => ("", field("content")?),
+            _ => return None,
+        };
+
+        Some(Self {
+            path: field("file_path")?.to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+            start_line: 1,
+        })
+    }
+
+    /// Render as unified-diff hunks with old/new line numbers, syntax colors
+    /// and the changed parts of each line emphasized.
+    pub fn render(&self, theme: &Theme) -> Vec<Line<'static>> {
+        let diff = TextDiff::configure()
+            .timeout(Duration::from_millis(200))
+            .diff_lines(&self.old, &self.new);
+
+        let lang = Path::new(&self.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        let old_spans = highlight_side(&self.old, lang, theme);
+        let new_spans = highlight_side(&self.new, lang, theme);
+
+        let gutter = Style::new().fg(theme.dim);
+        let mut lines = Vec::new();
+
+        for (i, group) in diff.grouped_ops(CONTEXT_LINES).iter().enumerate() {
+            if i > 0 {
+                lines.push(Line::styled("     ⋯", gutter));
+            }
+
+            for op in group {
+                for change in diff.iter_inline_changes(op) {
+                    let number = |index: Option<usize>| match index {
+                        Some(i) => format!("{:>4}", i + self.start_line),
+                        None => "    ".to_string(),
+                    };
+
+                    let (sign, bg, emphasis_bg, side) = match change.tag() {
+                        ChangeTag::Delete => (
+                            '-',
+                            Some(theme.diff_delete_bg),
+                            theme.diff_delete_emphasis,
+                            &old_spans,
+                        ),
+                        ChangeTag::Insert => (
+                            '+',
+                            Some(theme.diff_insert_bg),
+                            theme.diff_insert_emphasis,
+                            &new_spans,
+                        ),
+                        ChangeTag::Equal => (' ', None, Color::Reset, &new_spans),
+                    };
+                    let index = match change.tag() {
+                        ChangeTag::Delete => change.old_index(),
+                        _ => change.new_index(),
+                    };
+
+                    // The line's text, and byte ranges of its changed parts
+                    let mut text = String::new();
+                    let mut emphasized = Vec::new();
+                    for (emph, part) in change.iter_strings_lossy() {
+                        let part = part.trim_end_matches(['\n', '\r']);
+                        if emph && bg.is_some() {
+                            emphasized.push(text.len()..text.len() + part.len());
+                        }
+                        text.push_str(part);
+                    }
+
+                    let base = side
+                        .as_ref()
+                        .and_then(|s| index.and_then(|i| s.get(i)))
+                        .filter(|spans| line_text(spans) == text)
+                        .cloned()
+                        .unwrap_or_else(|| {
+                            vec![Span::styled(text, plain_style(change.tag(), theme))]
+                        });
+
+                    let mut spans = vec![Span::styled(
+                        format!(
+                            "{} {} {sign} ",
+                            number(change.old_index()),
+                            number(change.new_index())
+                        ),
+                        gutter,
+                    )];
+                    spans.extend(emphasize(base, bg, &emphasized, emphasis_bg));
+                    lines.push(Line::from(spans));
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+/// 1-based line where `needle` starts in `content`, or 1 if not found.
+fn line_of(content: Option<&str>, needle: &str) -> usize {
+    match content {
+        Some(content) if !needle.is_empty() => content
+            .find(needle)
+            .map(|at| content[..at].matches('\n').count() + 1)
+            .unwrap_or(1),
+        _ => 1,
+    }
+}
+
+fn highlight_side(text: &str, lang: &str, theme: &Theme) -> Option<Vec<Vec<Span<'static>>>> {
+    if lang.is_empty() || text.lines().count() > MAX_HIGHLIGHT_LINES {
+        return None;
+    }
+    highlight::highlight_uncached(text, lang, theme)
+}
+
+/// Colors for a line without syntax highlighting.
+fn plain_style(tag: ChangeTag, theme: &Theme) -> Style {
+    match tag {
+        ChangeTag::Delete => Style::new().fg(theme.diff_delete),
+        ChangeTag::Insert => Style::new().fg(theme.diff_insert),
+        ChangeTag::Equal => Style::new().fg(theme.text),
+    }
+}
+
+fn line_text(spans: &[Span]) -> String {
+    spans.iter().map(|s| s.content.as_ref()).collect()
+}
+
+/// Give `spans` the line background `bg`, with `emphasis_bg` and bold over
+/// the byte ranges in `emphasized` (splitting spans at their edges).
+fn emphasize(
+    spans: Vec<Span<'static>>,
+    bg: Option<Color>,
+    emphasized: &[Range<usize>],
+    emphasis_bg: Color,
+) -> Vec<Span<'static>> {
+    let Some(bg) = bg else {
+        return spans;
+    };
+
+    let is_emphasized = |at: usize| emphasized.iter().any(|r| r.contains(&at));
+    let part = |text: &str, style: Style, at: usize| {
+        let style = if is_emphasized(at) {
+            style.bg(emphasis_bg).add_modifier(Modifier::BOLD)
+        } else {
+            style.bg(bg)
+        };
+        Span::styled(text.to_string(), style)
+    };
+
+    let mut out = Vec::new();
+    let mut offset = 0;
+
+    for span in spans {
+        let content = span.content.as_ref();
+        let mut start = 0;
+
+        // Split wherever emphasis starts or stops inside this span
+        for (i, _) in content.char_indices().skip(1) {
+            if is_emphasized(offset + i) != is_emphasized(offset + start) {
+                out.push(part(&content[start..i], span.style, offset + start));
+                start = i;
+            }
+        }
+        if start < content.len() {
+            out.push(part(&content[start..], span.style, offset + start));
+        }
+
+        offset += content.len();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn edit_diff_keeps_unchanged_lines_and_numbers_them() {
+        let change = FileChange {
+            path: "notes.txt".to_string(),
+            old: "one\ntwo\nthree\n".to_string(),
+            new: "one\n2\nthree\n".to_string(),
+            start_line: 10,
+        };
+
+        let lines: Vec<String> = change.render(&Theme::default()).iter().map(text).collect();
+
+        assert_eq!(
+            lines,
+            [
+                "  10   10   one",
+                "  11      - two",
+                "       11 + 2",
+                "  12   12   three",
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_words_are_emphasized() {
+        let change = FileChange {
+            path: "a.txt".to_string(),
+            old: "let total = 1;\n".to_string(),
+            new: "let total = 2;\n".to_string(),
+            start_line: 1,
+        };
+
+        let lines = change.render(&Theme::default());
+        let insert = &lines[1];
+        let emphasized: String = insert
+            .spans
+            .iter()
+            .filter(|s| s.style.bg == Some(Theme::default().diff_insert_emphasis))
+            .map(|s| s.content.as_ref())
+            .collect();
+        assert_eq!(emphasized, "2;");
+    }
+
+    #[test]
+    fn line_of_locates_the_edit() {
+        let content = "a\nb\nc\n";
+        assert_eq!(line_of(Some(content), "c"), 3);
+        assert_eq!(line_of(Some(content), "zzz"), 1);
+        assert_eq!(line_of(None, "c"), 1);
+    }
+
+    #[test]
+    fn write_before_run_diffs_against_the_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("f.txt"), "keep\nold\n").unwrap();
+        let input = serde_json::json!({"file_path": "f.txt", "content": "keep\nnew\n"});
+
+        let change = FileChange::before_run("Write", &input, tmp.path()).unwrap();
+
+        let lines: Vec<String> = change.render(&Theme::default()).iter().map(text).collect();
+        assert_eq!(lines[0], "   1    1   keep");
+        assert!(lines[1].ends_with("- old"));
+        assert!(lines[2].ends_with("+ new"));
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use claude_code_core::config;
+use serde::{Deserialize, Serialize};
+
+const DIR_NAME: &str = "drafts";
+
+/// Unsent input, kept on disk so a quit or crash doesn't lose it.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    /// Working directory the draft was typed in; a file whose name happens
+    /// to collide with another directory's is ignored.
+    cwd: PathBuf,
+    /// The input box, pastes expanded.
+    pub input: String,
+    /// Messages typed but not sent yet, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub queued: Vec<String>,
+}
+
+impl Draft {
+    pub fn is_empty(&self) -> bool {
+        self.input.trim().is_empty() && self.queued.iter().all(|q| q.trim().is_empty())
+    }
+
+    /// Everything unsent as one input: queued messages first, separated by
+    /// blank lines.
+    pub fn into_input(self) -> String {
+        self.queued
+            .into_iter()
+            .chain(Some(self.input))
+            .filter(|text| !text.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// One draft per working directory, rewritten whenever it changes and
+/// removed once everything has been sent.
+#[derive(Default)]
+pub struct DraftStore {
+    cwd: PathBuf,
+    path: Option<PathBuf>,
+    /// Last state written, to skip writing on every redraw.
+    saved: Draft,
+}
+
+impl DraftStore {
+    /// The draft for `cwd` in the config directory. Drafts are best-effort:
+    /// without a config dir nothing is saved.
+    pub fn for_cwd(cwd: &Path) -> Self {
+        let dir = config::config_dir().ok().map(|d| d.join(DIR_NAME));
+        Self::in_dir(dir, cwd)
+    }
+
+    fn in_dir(dir: Option<PathBuf>, cwd: &Path) -> Self {
+        let name: String = cwd
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let mut store = Self {
+            cwd: cwd.to_path_buf(),
+            path: dir.map(|d| d.join(format!("{name}.json"))),
+            saved: Draft::default(),
+        };
+        store.saved.cwd = store.cwd.clone();
+        store
+    }
+
+    /// The draft left by the last run, if any.
+    pub fn load(&mut self) -> Option<Draft> {
+        let text = fs::read_to_string(self.path.as_ref()?).ok()?;
+        let draft: Draft = serde_json::from_str(&text).ok()?;
+        if draft.cwd != self.cwd || draft.is_empty() {
+            return None;
+        }
+        self.saved = draft.clone();
+        Some(draft)
+    }
+
+    /// Record the unsent input, writing only when it changed.
+    pub fn save(&mut self, input: &str, queued: &[String]) {
+        if self.saved.input == input && self.saved.queued == queued {
+            return;
+        }
+        self.saved.input = input.to_string();
+        self.saved.queued = queued.to_vec();
+
+        let Some(path) = &self.path else {
+            return;
+        };
+        // Losing a draft is not worth interrupting the session for
+        if self.saved.is_empty() {
+            let _ = fs::remove_file(path);
+            return;
+        }
+        let Ok(json) = serde_json::to_string(&self.saved) else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        // Write then rename, so a crash mid-write keeps the previous draft
+        let tmp = path.with_extension("json.tmp");
+        if fs::write(&tmp, json).is_ok() {
+            let _ = fs::rename(&tmp, path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drafts_survive_a_restart_until_sent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = Some(tmp.path().join(DIR_NAME));
+        let cwd = Path::new("/work/project");
+
+        let mut store = DraftStore::in_dir(dir.clone(), cwd);
+        assert!(store.load().is_none());
+        store.save("a long\nprompt", &["held back".to_string()]);
+
+        let mut reopened = DraftStore::in_dir(dir.clone(), cwd);
+        let draft = reopened.load().unwrap();
+        assert_eq!(draft.input, "a long\nprompt");
+        assert_eq!(draft.into_input(), "held back\n\na long\nprompt");
+        // Another directory has its own draft
+        assert!(
+            DraftStore::in_dir(dir.clone(), Path::new("/work"))
+                .load()
+                .is_none()
+        );
+
+        reopened.save("", &[]);
+        assert!(DraftStore::in_dir(dir, cwd).load().is_none());
+        assert_eq!(fs::read_dir(tmp.path().join(DIR_NAME)).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn colliding_names_are_ignored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = Some(tmp.path().to_path_buf());
+
+        DraftStore::in_dir(dir.clone(), Path::new("/a-b")).save("mine", &[]);
+        assert!(DraftStore::in_dir(dir, Path::new("/a/b")).load().is_none());
+    }
+}
@@ -1,31 +1,79 @@
+use std::path::PathBuf;
 use std::sync::mpsc as std_mpsc;
 
 use tokio::sync::mpsc;
 
-use claude_code_core::api::Usage;
-use claude_code_core::event::EventHandler;
+use claude_code_core::api::{TurnUsage, Usage};
+use claude_code_core::auth::TokenRefresh;
+use claude_code_core::config::Credentials;
+use claude_code_core::event::{EventHandler, TaskStatus};
+use claude_code_core::store::SavedSession;
+use claude_code_core::tools::ToolProgress;
+
+use crate::commands::export::ExportFormat;
+use crate::commands::permissions::RuleChange;
+use crate::permissions::Reply;
+
+use super::diff::FileChange;
 
 /// Events sent from the session task to the UI.
 pub enum UiEvent {
     Text(String),
+    Thinking(String),
     Error(String),
+    Info(String),
     ToolStart {
         name: String,
         input: serde_json::Value,
+        /// What an Edit or Write is about to change, read before it runs.
+        change: Option<FileChange>,
     },
     ToolExecuting {
         input: serde_json::Value,
     },
     ToolResult {
+        name: String,
         output: String,
         is_error: bool,
     },
     ToolEnd,
-    Done(Usage),
+    /// How far the running tool's slow step has got.
+    ToolProgress(ToolProgress),
+    /// A parallel subagent's state.
+    TaskStatus {
+        id: String,
+        description: String,
+        status: TaskStatus,
+    },
+    Done,
+    /// Per-turn usage after a message completes or fails.
+    Turns(Vec<TurnUsage>),
+    /// Tokens the conversation now occupies in the context window.
+    ContextTokens(u64),
+    /// Tokens a model request produced.
+    OutputTokens(u64),
+    /// New output of the status line command.
+    StatusLine(String),
     Failed(String),
+    /// The session reached its budget; `resume` is sent once the user
+    /// allows more.
+    BudgetPaused {
+        message: String,
+        resume: String,
+    },
+    /// `/init`'s draft, ready for review in the editor.
+    ClaudeMdDraft(String),
+    /// The session now uses this profile's credentials (and model, if it
+    /// sets one).
+    ProfileSwitched {
+        profile: Option<String>,
+        model: Option<String>,
+    },
     PermissionRequest {
         description: String,
-        respond: std_mpsc::SyncSender<bool>,
+        /// Rule saved by "always allow", when one can cover the call.
+        rule: Option<String>,
+        respond: std_mpsc::SyncSender<Reply>,
     },
 }
 
@@ -34,12 +82,45 @@ pub enum SessionCmd {
     SendMessage(String),
     SetModel(String),
     Clear,
+    Compact(Option<String>),
+    Resume(Box<SavedSession>),
+    Export {
+        format: ExportFormat,
+        path: Option<PathBuf>,
+    },
+    Doctor,
+    Logout,
+    ReloadMemory,
+    /// Draft a `CLAUDE.md` for the working directory.
+    DraftClaudeMd,
+    Budget {
+        extend: bool,
+    },
+    #[cfg(feature = "git")]
+    Checkpoints(Option<String>),
+    AddDir(PathBuf),
+    /// List the pinned files, or pin one.
+    Pin(Option<PathBuf>),
+    Unpin(PathBuf),
+    UpdatePermissions(RuleChange),
+    SetCredentials {
+        access_token: String,
+        is_oauth: bool,
+        refresh: Option<TokenRefresh>,
+    },
+    SwitchProfile {
+        profile: Option<String>,
+        creds: Credentials,
+        model: Option<String>,
+    },
     Stop,
 }
 
 /// Bridges `EventHandler` trait calls into `UiEvent` channel sends.
 pub struct ChannelEventHandler {
     pub tx: mpsc::UnboundedSender<UiEvent>,
+    /// Resolves relative paths in Edit and Write calls.
+    pub cwd: PathBuf,
 }
 
 impl EventHandler for ChannelEventHandler {
@@ -47,6 +128,10 @@ impl EventHandler for ChannelEventHandler {
         let _ = self.tx.send(UiEvent::Text(text.to_string()));
     }
 
+    fn on_thinking(&mut self, text: &str) {
+        let _ = self.tx.send(UiEvent::Thinking(text.to_string()));
+    }
+
     fn on_error(&mut self, message: &str) {
         let _ = self.tx.send(UiEvent::Error(message.to_string()));
     }
@@ -55,6 +140,7 @@ impl EventHandler for ChannelEventHandler {
         let _ = self.tx.send(UiEvent::ToolStart {
             name: name.to_string(),
             input: input.clone(),
+            change: FileChange::before_run(name, input, &self.cwd),
         });
     }
 
@@ -64,8 +150,9 @@ impl EventHandler for ChannelEventHandler {
         });
     }
 
-    fn on_tool_result(&mut self, _name: &str, output: &str, is_error: bool) {
+    fn on_tool_result(&mut self, name: &str, output: &str, is_error: bool) {
         let _ = self.tx.send(UiEvent::ToolResult {
+            name: name.to_string(),
             output: output.to_string(),
             is_error,
         });
@@ -74,4 +161,21 @@ impl EventHandler for ChannelEventHandler {
     fn on_tool_use_end(&mut self, _name: &str) {
         let _ = self.tx.send(UiEvent::ToolEnd);
     }
+
+    fn on_tool_progress(&mut self, _name: &str, progress: &ToolProgress) {
+        let _ = self.tx.send(UiEvent::ToolProgress(progress.clone()));
+    }
+
+    fn on_task_status(&mut self, id: &str, description: &str, status: &TaskStatus) {
+        let _ = self.tx.send(UiEvent::TaskStatus {
+            id: id.to_string(),
+            description: description.to_string(),
+            status: status.clone(),
+        });
+    }
+
+    fn on_usage(&mut self, usage: &Usage) {
+        let _ = self.tx.send(UiEvent::ContextTokens(usage.context_tokens()));
+        let _ = self.tx.send(UiEvent::OutputTokens(usage.output_tokens));
+    }
 }
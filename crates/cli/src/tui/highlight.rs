@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use super::theme::Theme;
+
+/// Highlighted blocks kept between frames; cleared when it grows past this.
+const CACHE_ENTRIES: usize = 256;
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// Spans for each line of a block.
+type Highlighted = Vec<Vec<Span<'static>>>;
+
+/// The conversation is redrawn every frame, so highlighting is cached by
+/// (language, code).
+static CACHE: LazyLock<Mutex<HashMap<(String, String), Highlighted>>> =
+    LazyLock::new(Default::default);
+
+/// The language named by a fence info string, e.g. `rust` in
+/// "```rust,ignore" or `py` in "```py title=x".
+pub fn fence_language(info: &str) -> &str {
+    info.split([',', ' ', '{']).next().unwrap_or("").trim()
+}
+
+/// Highlight `code` as `lang` (a name or file extension), one span list per
+/// line. `None` if the language is unknown.
+pub fn highlight(code: &str, lang: &str, theme: &Theme) -> Option<Highlighted> {
+    if lang.is_empty() {
+        return None;
+    }
+
+    let key = (lang.to_string(), code.to_string());
+    if let Some(lines) = CACHE.lock().ok()?.get(&key) {
+        return Some(lines.clone());
+    }
+
+    let lines = highlight_uncached(code, lang, theme)?;
+
+    if let Ok(mut cache) = CACHE.lock() {
+        if cache.len() >= CACHE_ENTRIES {
+            cache.clear();
+        }
+        cache.insert(key, lines.clone());
+    }
+
+    Some(lines)
+}
+
+/// [`highlight`] for text that is rendered once, such as a diff.
+pub fn highlight_uncached(code: &str, lang: &str, theme: &Theme) -> Option<Highlighted> {
+    let syntax = SYNTAXES.find_syntax_by_token(lang)?;
+    let mut highlighter = HighlightLines::new(syntax, THEMES.themes.get(theme.syntax)?);
+
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter.highlight_line(line, &SYNTAXES).ok()?;
+        let spans = ranges
+            .into_iter()
+            .map(|(style, text)| {
+                let text = text.trim_end_matches(['\n', '\r']).to_string();
+                Span::styled(text, to_style(style, theme))
+            })
+            .collect();
+        lines.push(spans);
+    }
+
+    Some(lines)
+}
+
+fn to_style(style: syntect::highlighting::Style, theme: &Theme) -> Style {
+    let fg = style.foreground;
+    let mut out = Style::default().fg(theme.fit(Color::Rgb(fg.r, fg.g, fg.b)));
+
+    if style.font_style.contains(FontStyle::BOLD) {
+        out = out.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        out = out.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        out = out.add_modifier(Modifier::UNDERLINED);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fence_language_takes_the_first_word() {
+        assert_eq!(fence_language("rust"), "rust");
+        assert_eq!(fence_language("rust,ignore"), "rust");
+        assert_eq!(fence_language("py title=\"x\""), "py");
+        assert_eq!(fence_language(""), "");
+    }
+
+    #[test]
+    fn highlight_colors_known_languages() {
+        let lines = highlight("fn main() {}\nlet x = 1;\n", "rust", &Theme::default()).unwrap();
+
+        assert_eq!(lines.len(), 2);
+        let text: String = lines[0].iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text, "fn main() {}");
+        // More than one color on the line
+        assert!(lines[0].len() > 1);
+
+        assert!(highlight("whatever", "no-such-language", &Theme::default()).is_none());
+        assert!(highlight("plain", "", &Theme::default()).is_none());
+    }
+}
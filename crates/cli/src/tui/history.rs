@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use claude_code_core::config;
+
+/// Entries kept on disk; the oldest are dropped first.
+const MAX_ENTRIES: usize = 1000;
+
+const FILE_NAME: &str = "history.jsonl";
+
+/// Submitted prompts, recalled with Up/Down and persisted across runs as
+/// one JSON string per line (so multi-line prompts survive). Each prompt is
+/// appended, so instances running at once keep each other's.
+pub struct InputHistory {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+    /// Index into `entries` while browsing, `None` when editing fresh input.
+    position: Option<usize>,
+    /// Input that was being typed before browsing started.
+    draft: String,
+}
+
+impl InputHistory {
+    /// Load from the config directory. History is best-effort: without a
+    /// config dir it still works in memory.
+    pub fn load() -> Self {
+        let path = config::config_dir().ok().map(|d| d.join(FILE_NAME));
+        Self::load_from(path)
+    }
+
+    fn load_from(path: Option<PathBuf>) -> Self {
+        let entries = path
+            .as_deref()
+            .and_then(|p| load_entries(p).ok())
+            .unwrap_or_default();
+
+        Self {
+            entries,
+            path,
+            position: None,
+            draft: String::new(),
+        }
+    }
+
+    /// Record a submitted prompt, moving duplicates to the end.
+    pub fn push(&mut self, entry: &str) {
+        self.position = None;
+        self.draft.clear();
+
+        if entry.trim().is_empty() {
+            return;
+        }
+
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+
+        self.append(entry);
+    }
+
+    /// Step back to an older entry. `current` is kept as the draft when
+    /// browsing starts.
+    pub fn prev(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(p) => p - 1,
+        };
+
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Step forward to a newer entry, ending at the saved draft.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    fn append(&self, entry: &str) {
+        let (Some(path), Ok(line)) = (&self.path, serde_json::to_string(entry)) else {
+            return;
+        };
+        // Losing history is not worth interrupting the session for
+        let _ = fs::File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| {
+                file.lock()?;
+                file.write_all(format!("{line}\n").as_bytes())
+            });
+    }
+}
+
+/// The entries in `path`, oldest first without duplicates. Past the cap the
+/// file is rewritten with only the newest, under a lock so appends from
+/// other instances aren't lost.
+fn load_entries(path: &Path) -> io::Result<Vec<String>> {
+    let mut file = match fs::File::options().read(true).write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    file.lock()?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+
+    // Keep the latest copy of each entry
+    let mut seen = HashSet::new();
+    let mut entries: Vec<String> = text
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<String>(line).ok())
+        .filter(|entry| seen.insert(entry.clone()))
+        .take(MAX_ENTRIES)
+        .collect();
+    entries.reverse();
+
+    if text.lines().count() > MAX_ENTRIES {
+        let mut compacted = String::new();
+        for entry in &entries {
+            compacted.push_str(&serde_json::to_string(entry)?);
+            compacted.push('\n');
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(compacted.as_bytes())?;
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browse_back_and_forth_restores_draft() {
+        let mut history = InputHistory::load_from(None);
+        history.push("first");
+        history.push("second");
+
+        assert_eq!(history.prev("typing"), Some("second"));
+        assert_eq!(history.prev("second"), Some("first"));
+        assert_eq!(history.prev("first"), None);
+        assert_eq!(history.next(), Some("second"));
+        assert_eq!(history.next(), Some("typing"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn push_dedups_and_persists_across_loads() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(FILE_NAME);
+
+        let mut history = InputHistory::load_from(Some(path.clone()));
+        history.push("one");
+        history.push("multi\nline");
+        history.push("one");
+        history.push("   ");
+
+        let mut reloaded = InputHistory::load_from(Some(path));
+        assert_eq!(reloaded.entries, vec!["multi\nline", "one"]);
+        assert_eq!(reloaded.prev(""), Some("one"));
+    }
+
+    #[test]
+    fn instances_running_at_once_keep_each_others_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(FILE_NAME);
+
+        let mut first = InputHistory::load_from(Some(path.clone()));
+        let mut second = InputHistory::load_from(Some(path.clone()));
+        first.push("from the first");
+        second.push("from the second");
+        first.push("first again");
+
+        let reloaded = InputHistory::load_from(Some(path));
+        assert_eq!(
+            reloaded.entries,
+            vec!["from the first", "from the second", "first again"]
+        );
+    }
+
+    #[test]
+    fn loading_compacts_a_long_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(FILE_NAME);
+        let lines: String = (0..MAX_ENTRIES + 5)
+            .map(|i| format!("\"{}\"\n", i % (MAX_ENTRIES + 2)))
+            .collect();
+        fs::write(&path, lines).unwrap();
+
+        let history = InputHistory::load_from(Some(path.clone()));
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries.last().unwrap(), "2");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap().lines().count(),
+            MAX_ENTRIES
+        );
+    }
+
+    #[test]
+    fn push_caps_size() {
+        let mut history = InputHistory::load_from(None);
+        for i in 0..MAX_ENTRIES + 5 {
+            history.push(&i.to_string());
+        }
+
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries[0], "5");
+    }
+}
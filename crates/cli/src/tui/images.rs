@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use image::DynamicImage;
+use ratatui::layout::Rect;
+use ratatui_image::Resize;
+use ratatui_image::picker::{Picker, ProtocolType};
+use ratatui_image::protocol::Protocol;
+
+use claude_code_core::api::ImageSource;
+
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+/// Largest size an image is drawn at, in cells.
+const MAX_COLS: u16 = 60;
+const MAX_ROWS: u16 = 16;
+
+/// An image in the transcript. Without a graphics protocol only the label
+/// is shown.
+pub struct InlineImage {
+    pub label: String,
+    pub protocol: Option<Protocol>,
+}
+
+impl InlineImage {
+    /// Rows the picture takes below its label.
+    pub fn rows(&self) -> u16 {
+        self.protocol.as_ref().map_or(0, |p| p.area().height)
+    }
+}
+
+/// Encodes images for the terminal's graphics protocol.
+#[derive(Default)]
+pub struct Images {
+    /// `None` when the terminal has no Kitty, iTerm2 or Sixel support.
+    picker: Option<Picker>,
+}
+
+impl Images {
+    /// Ask the terminal which graphics protocol it speaks and its cell size.
+    /// Must run in raw mode, before the event loop reads input. Half-block
+    /// "pixels" don't count: images then show as placeholders.
+    pub fn detect() -> Self {
+        let picker = match Picker::from_query_stdio() {
+            Ok(picker) => Some(picker),
+            Err(e) => {
+                tracing::debug!("terminal graphics query failed: {e}");
+                None
+            }
+        };
+
+        Self {
+            picker: picker.filter(|p| p.protocol_type() != ProtocolType::Halfblocks),
+        }
+    }
+
+    /// A local image file; `path` is shown as given.
+    pub fn file(&self, path: &Path, shown: &str) -> InlineImage {
+        let label = match image::image_dimensions(path) {
+            Ok((width, height)) => format!("{shown} ({width}×{height})"),
+            Err(_) => shown.to_string(),
+        };
+
+        self.encode(label, || {
+            image::ImageReader::open(path)
+                .context("Failed to open the image")?
+                .with_guessed_format()
+                .context("Failed to read the image")?
+                .decode()
+                .context("Failed to decode the image")
+        })
+    }
+
+    /// An image block from the conversation.
+    pub fn block(&self, source: &ImageSource) -> InlineImage {
+        match source {
+            ImageSource::Base64 { media_type, data } => self.encode(media_type.clone(), || {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(data)
+                    .context("Invalid base64 image data")?;
+                image::load_from_memory(&bytes).context("Failed to decode the image")
+            }),
+            // Not downloaded just to draw it
+            ImageSource::Url { url } => InlineImage {
+                label: url.clone(),
+                protocol: None,
+            },
+        }
+    }
+
+    fn encode(&self, label: String, load: impl FnOnce() -> Result<DynamicImage>) -> InlineImage {
+        let Some(picker) = &self.picker else {
+            return InlineImage {
+                label,
+                protocol: None,
+            };
+        };
+
+        let size = Rect::new(0, 0, MAX_COLS, MAX_ROWS);
+        let protocol = load().and_then(|image| {
+            picker
+                .new_protocol(image, size, Resize::Fit(None))
+                .context("Failed to encode the image")
+        });
+
+        match protocol {
+            Ok(protocol) => InlineImage {
+                label,
+                protocol: Some(protocol),
+            },
+            Err(e) => {
+                tracing::debug!("cannot show image {label}: {e:#}");
+                InlineImage {
+                    label,
+                    protocol: None,
+                }
+            }
+        }
+    }
+}
+
+/// Local image files mentioned in `text`, as written and resolved against
+/// `cwd`, in order of first mention.
+pub fn referenced_paths(text: &str, cwd: &Path) -> Vec<(String, PathBuf)> {
+    let mut out: Vec<(String, PathBuf)> = Vec::new();
+
+    // Paths end up in backticks, quotes, brackets and markdown links
+    let words = text.split(|c: char| c.is_whitespace() || "`'\"()[]<>".contains(c));
+
+    for word in words {
+        let word = word.trim_end_matches([',', '.', ':', ';', '!', '?']);
+        let is_image = Path::new(word)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()));
+        if !is_image {
+            continue;
+        }
+
+        let path = match word.strip_prefix("~/") {
+            Some(rest) => match std::env::var_os("HOME") {
+                Some(home) => PathBuf::from(home).join(rest),
+                None => continue,
+            },
+            None => cwd.join(word),
+        };
+
+        if path.is_file() && !out.iter().any(|(_, p)| *p == path) {
+            out.push((word.to_string(), path));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_existing_image_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/chart.png"), b"").unwrap();
+        std::fs::write(dir.path().join("Logo.JPG"), b"").unwrap();
+
+        let text = "Saved the plot to `docs/chart.png`. See [the logo](Logo.JPG), \
+                    not missing.png or notes.txt. Again: docs/chart.png.";
+        let found: Vec<String> = referenced_paths(text, dir.path())
+            .into_iter()
+            .map(|(shown, _)| shown)
+            .collect();
+
+        assert_eq!(found, ["docs/chart.png", "Logo.JPG"]);
+    }
+
+    #[test]
+    fn without_a_protocol_images_are_placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dot.png");
+        image::RgbImage::new(3, 2).save(&path).unwrap();
+
+        let image = Images::default().file(&path, "dot.png");
+
+        assert_eq!(image.label, "dot.png (3×2)");
+        assert_eq!(image.rows(), 0);
+    }
+}
@@ -1,14 +1,18 @@
-use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
-use ratatui::style::{Color, Modifier, Style};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
 
+use super::highlight;
+use super::theme::Theme;
+
 /// Convert markdown text to ratatui Lines with styling.
-pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
+pub fn render_markdown(text: &str, theme: &Theme) -> Vec<Line<'static>> {
     let mut lines = Vec::new();
     let mut current_spans: Vec<Span<'static>> = Vec::new();
     let mut style_stack: Vec<Style> = vec![Style::default()];
     let mut in_code_block = false;
     let mut code_block_lines: Vec<String> = Vec::new();
+    let mut code_block_lang = String::new();
     let mut list_depth: usize = 0;
 
     let options = Options::all();
@@ -22,7 +26,7 @@ pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
                     flush_line(&mut lines, &mut current_spans);
                     style_stack.push(
                         Style::default()
-                            .fg(Color::Cyan)
+                            .fg(theme.heading)
                             .add_modifier(Modifier::BOLD),
                     );
                 }
@@ -37,15 +41,19 @@ pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
                     style_stack.push(base.add_modifier(Modifier::BOLD));
                 }
 
-                Tag::CodeBlock(_) => {
+                Tag::CodeBlock(kind) => {
                     flush_line(&mut lines, &mut current_spans);
                     in_code_block = true;
                     code_block_lines.clear();
+                    code_block_lang = match kind {
+                        CodeBlockKind::Fenced(info) => highlight::fence_language(&info).to_string(),
+                        CodeBlockKind::Indented => String::new(),
+                    };
                 }
 
                 Tag::Link { .. } => {
                     let base = current_style(&style_stack);
-                    style_stack.push(base.fg(Color::Blue).add_modifier(Modifier::UNDERLINED));
+                    style_stack.push(base.fg(theme.link).add_modifier(Modifier::UNDERLINED));
                 }
 
                 Tag::List(_) => {
@@ -56,7 +64,7 @@ pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
                     flush_line(&mut lines, &mut current_spans);
                     let indent = "  ".repeat(list_depth);
                     current_spans.push(Span::raw(indent));
-                    current_spans.push(Span::styled("• ", Style::default().fg(Color::Yellow)));
+                    current_spans.push(Span::styled("• ", Style::default().fg(theme.bullet)));
                 }
 
                 _ => {}
@@ -77,11 +85,28 @@ pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
                 TagEnd::CodeBlock => {
                     in_code_block = false;
 
-                    for code_line in &code_block_lines {
-                        lines.push(Line::from(vec![
-                            Span::raw("  "),
-                            Span::styled(code_line.clone(), Style::default().fg(Color::Green)),
-                        ]));
+                    let code = code_block_lines.join("\n");
+                    match highlight::highlight(&code, &code_block_lang, theme) {
+                        Some(highlighted) => {
+                            for spans in highlighted {
+                                let mut line = vec![Span::raw("  ")];
+                                line.extend(spans);
+                                lines.push(Line::from(line));
+                            }
+                        }
+
+                        // Unknown or missing language
+                        None => {
+                            for code_line in &code_block_lines {
+                                lines.push(Line::from(vec![
+                                    Span::raw("  "),
+                                    Span::styled(
+                                        code_line.clone(),
+                                        Style::default().fg(theme.code),
+                                    ),
+                                ]));
+                            }
+                        }
                     }
 
                     code_block_lines.clear();
@@ -124,15 +149,13 @@ pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
             Event::Code(code) => {
                 current_spans.push(Span::styled(
                     code.to_string(),
-                    Style::default().fg(Color::Green),
+                    Style::default().fg(theme.code),
                 ));
             }
 
-            Event::SoftBreak => {
-                // Soft break = space in normal flow
-                if !in_code_block {
-                    flush_line(&mut lines, &mut current_spans);
-                }
+            // Soft break = space in normal flow
+            Event::SoftBreak if !in_code_block => {
+                flush_line(&mut lines, &mut current_spans);
             }
 
             Event::HardBreak => {
@@ -141,10 +164,7 @@ pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
 
             Event::Rule => {
                 flush_line(&mut lines, &mut current_spans);
-                lines.push(Line::styled(
-                    "─".repeat(60),
-                    Style::default().fg(Color::DarkGray),
-                ));
+                lines.push(Line::styled("─".repeat(60), Style::default().fg(theme.dim)));
                 lines.push(Line::default());
             }
 
@@ -158,6 +178,27 @@ pub fn render_markdown(text: &str) -> Vec<Line<'static>> {
     lines
 }
 
+/// The text of the last fenced or indented code block in `text`.
+pub fn last_code_block(text: &str) -> Option<String> {
+    let mut last = None;
+    let mut current: Option<String> = None;
+
+    for event in Parser::new_ext(text, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => current = Some(String::new()),
+            Event::Text(code) => {
+                if let Some(current) = &mut current {
+                    current.push_str(&code);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => last = current.take(),
+            _ => {}
+        }
+    }
+
+    last
+}
+
 /// Push current_spans as a Line and clear the buffer.
 fn flush_line(lines: &mut Vec<Line<'static>>, spans: &mut Vec<Span<'static>>) {
     if !spans.is_empty() {
@@ -177,21 +218,28 @@ mod tests {
     #[test]
     fn test_basic_markdown() {
         let md = "# Hello\n\nThis is **bold** and *italic*.";
-        let lines = render_markdown(md);
+        let lines = render_markdown(md, &Theme::default());
         assert!(!lines.is_empty());
     }
 
     #[test]
     fn test_code_block() {
         let md = "```rust\nfn main() {}\n```";
-        let lines = render_markdown(md);
+        let lines = render_markdown(md, &Theme::default());
         assert!(!lines.is_empty());
     }
 
+    #[test]
+    fn test_last_code_block() {
+        let md = "```sh\ncargo build\n```\n\nThen:\n\n```rust\nfn main() {}\n```\n";
+        assert_eq!(last_code_block(md).as_deref(), Some("fn main() {}\n"));
+        assert_eq!(last_code_block("no code here"), None);
+    }
+
     #[test]
     fn test_list() {
         let md = "- Item 1\n- Item 2\n  - Nested";
-        let lines = render_markdown(md);
+        let lines = render_markdown(md, &Theme::default());
         // Should have items with bullet points
         assert!(lines.len() >= 3);
     }
@@ -199,7 +247,7 @@ mod tests {
     #[test]
     fn test_heading_spacing() {
         let md = "# Title\n\nParagraph text.";
-        let lines = render_markdown(md);
+        let lines = render_markdown(md, &Theme::default());
         // Title, blank, paragraph, blank
         assert!(lines.len() >= 3);
     }
@@ -1,25 +1,62 @@
+mod activity;
+mod diff;
+mod draft;
 mod event;
+mod highlight;
+mod history;
+mod images;
 mod markdown;
+mod pager;
+mod paste;
+mod readline;
 mod render;
-
-use std::path::PathBuf;
+mod status;
+mod templates;
+mod theme;
+mod todos;
+mod tree;
+mod vim;
+#[cfg(feature = "voice")]
+mod voice;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc as std_mpsc;
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use crossterm::event::{Event, KeyCode, KeyModifiers, MouseEventKind};
+use anyhow::{Context, Result};
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind};
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
+use ratatui::text::Line;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
-use claude_code_core::api::Usage;
-use claude_code_core::session::Session;
-
-use crate::commands::{self, CommandResult};
-use crate::permissions::ChannelPermissions;
+use claude_code_core::api::{
+    ApiError, CONTEXT_WINDOW, Content, ContentBlock, Message, TurnUsage, Usage,
+};
+#[cfg(feature = "voice")]
+use claude_code_core::config::VoiceSettings;
+use claude_code_core::config::{self, StatusLineSetting, ThemeSetting};
+use claude_code_core::models::ModelAliases;
+use claude_code_core::permission::PermissionMode;
+use claude_code_core::session::{Budget, BudgetExceeded, Session};
+
+use crate::commands::budget;
+#[cfg(feature = "git")]
+use crate::commands::checkpoints;
+use crate::commands::copy::CopyTarget;
+use crate::commands::doctor;
+use crate::commands::export::{self, ExportMeta};
+use crate::commands::init;
+use crate::commands::login;
+use crate::commands::logout;
+use crate::commands::pin;
+use crate::commands::{self, CommandContext, CommandResult};
+use crate::permissions::{ChannelPermissions, Reply};
 
 pub use event::{ChannelEventHandler, SessionCmd, UiEvent};
+pub use theme::{PRESETS as THEMES, Theme};
 
 // ---------------------------------------------------------------------------
 // Display model
@@ -33,18 +70,29 @@ pub enum AppState {
 
 pub struct PendingPermission {
     pub description: String,
-    pub respond: std_mpsc::SyncSender<bool>,
+    /// Rule saved by "always allow", if the call has one.
+    pub rule: Option<String>,
+    /// Reason being typed after choosing "deny with feedback".
+    pub feedback: Option<String>,
+    pub respond: std_mpsc::SyncSender<Reply>,
 }
 
 pub enum DisplayMessage {
     User(String),
     AssistantText(String),
+    /// Extended thinking, collapsed to one line unless `show_thinking`.
+    Thinking(String),
     ToolUse {
         name: String,
         input: Option<serde_json::Value>,
         output: Option<String>,
         is_error: bool,
+        /// Rendered diff of an Edit or Write.
+        diff: Option<Vec<Line<'static>>>,
     },
+    /// A picture from the conversation, or a local file a response
+    /// mentioned.
+    Image(images::InlineImage),
     Error(String),
     Info(String),
 }
@@ -56,19 +104,70 @@ pub enum DisplayMessage {
 pub struct App {
     pub cwd: PathBuf,
     pub model: String,
+    /// Active credential profile; `None` for the default one.
+    pub profile: Option<String>,
+    pub model_aliases: ModelAliases,
+    pub theme: Theme,
+    images: images::Images,
+    /// Modal editing state when vim mode is on.
+    pub vim: Option<vim::Vim>,
+    pub permission_mode: PermissionMode,
+    pub status_line: status::StatusLine,
+    /// Current git branch, when the status line shows it.
+    pub branch: Option<String>,
     pub usage: Usage,
+    pub turns: Vec<TurnUsage>,
+    /// Context window use after the latest request.
+    pub context_tokens: u64,
+    /// Whether the user was told the context is filling up.
+    context_warned: bool,
     pub messages: Vec<DisplayMessage>,
     pub scroll: u16,
     pub auto_scroll: bool,
+    /// Ctrl+R: expand thinking blocks.
+    pub show_thinking: bool,
     pub max_scroll: u16,
+    /// Height of the conversation pane, for paging.
+    pub page_height: u16,
     pub input: String,
+    history: history::InputHistory,
+    readline: readline::Readline,
+    pastes: paste::Pastes,
     pub cursor: usize,
     pub state: AppState,
     pub pending_perm: Option<PendingPermission>,
+    /// Full-screen view of a tool's output, over the conversation.
+    pub pager: Option<pager::Pager>,
+    /// `/t` without a name: prompt templates to choose from.
+    pub template_picker: Option<templates::TemplatePicker>,
+    /// Message index of the tool call Ctrl+O opens, from the last draw.
+    pub focused_tool: Option<usize>,
+    /// Whether the wheel scrolls the conversation; off, the terminal's own
+    /// text selection works.
+    pub mouse_capture: bool,
+    /// Project files panel, toggled with Alt+T.
+    pub tree: Option<tree::FileTree>,
+    /// Files the agent read or changed, highlighted in the tree.
+    pub touched: HashMap<PathBuf, tree::Touch>,
+    /// Claude's task list, shown above the input.
+    pub todos: todos::Todos,
     pub spinner_frame: usize,
     pub last_spinner_update: Instant,
+    /// Shown next to the spinner while busy.
+    pub activity: activity::Activity,
+    pub pending_login: bool,
+    /// Message to send once `/budget continue` lifts a budget pause.
+    pub paused_input: Option<String>,
+    /// Unsent input saved as it changes, restored on the next start.
+    draft: draft::DraftStore,
+    pub pending_edit: Option<PathBuf>,
+    /// `/init`'s draft, to open in `$EDITOR` before the next draw.
+    pending_claude_md: Option<String>,
+    /// Ctrl+G: edit the input in `$EDITOR` before the next draw.
+    pub pending_prompt_edit: bool,
+    /// Recording and transcription for `Alt+V` and `/rec`.
     #[cfg(feature = "voice")]
-    pub pending_voice_recording: bool,
+    voice: voice::Voice,
     ui_rx: mpsc::UnboundedReceiver<UiEvent>,
     session_tx: mpsc::UnboundedSender<SessionCmd>,
 }
@@ -77,30 +176,60 @@ impl App {
     fn new(
         cwd: PathBuf,
         model: String,
+        profile: Option<String>,
+        theme: Theme,
+        vim_mode: bool,
         ui_rx: mpsc::UnboundedReceiver<UiEvent>,
         session_tx: mpsc::UnboundedSender<SessionCmd>,
     ) -> Self {
         Self {
             cwd,
             model,
-            usage: Usage {
-                input_tokens: 0,
-                output_tokens: 0,
-            },
+            profile,
+            model_aliases: ModelAliases::default(),
+            theme,
+            images: images::Images::default(),
+            vim: vim_mode.then(vim::Vim::default),
+            permission_mode: PermissionMode::Default,
+            status_line: status::StatusLine::default(),
+            branch: None,
+            usage: Usage::default(),
+            turns: Vec::new(),
+            context_tokens: 0,
+            context_warned: false,
             messages: vec![DisplayMessage::Info(
                 "Type your message to start. Ctrl+C to exit.".to_string(),
             )],
             scroll: 0,
             auto_scroll: true,
+            show_thinking: false,
             max_scroll: 0,
+            page_height: 0,
             input: String::new(),
+            history: history::InputHistory::load(),
+            readline: readline::Readline::default(),
+            pastes: paste::Pastes::default(),
             cursor: 0,
             state: AppState::Idle,
             pending_perm: None,
+            pager: None,
+            template_picker: None,
+            focused_tool: None,
+            mouse_capture: true,
+            tree: None,
+            touched: HashMap::new(),
+            todos: todos::Todos::default(),
             spinner_frame: 0,
             last_spinner_update: Instant::now(),
+            activity: activity::Activity::default(),
+            pending_login: false,
+            paused_input: None,
+            draft: draft::DraftStore::default(),
+            pending_edit: None,
+            pending_claude_md: None,
+            pending_prompt_edit: false,
             #[cfg(feature = "voice")]
-            pending_voice_recording: false,
+            voice: voice::Voice::new(VoiceSettings::default()),
             ui_rx,
             session_tx,
         }
@@ -120,22 +249,121 @@ impl App {
             }
         }
 
-        // Esc: stop Claude if busy, do nothing if idle
-        if key.code == KeyCode::Esc && self.state == AppState::Busy {
+        // Permission prompt captures its own keys, Esc included
+        if self.pending_perm.is_some() {
+            return self.handle_perm_key(key);
+        }
+
+        if let Some(pager) = &mut self.pager {
+            if pager.handle_key(key) {
+                self.pager = None;
+            }
+            return false;
+        }
+
+        if let Some(picker) = &mut self.template_picker {
+            match picker.handle_key(key) {
+                templates::Action::None => {}
+                templates::Action::Close => self.template_picker = None,
+                templates::Action::Insert(text, cursor) => {
+                    self.template_picker = None;
+                    self.input = text;
+                    self.cursor = cursor;
+                }
+            }
+            return false;
+        }
+
+        if key.code == KeyCode::Char('t') && key.modifiers.contains(KeyModifiers::ALT) {
+            self.toggle_tree();
+            return false;
+        }
+
+        #[cfg(feature = "voice")]
+        if key.code == KeyCode::Char('v') && key.modifiers.contains(KeyModifiers::ALT) {
+            if let Err(e) = self.voice.press() {
+                self.messages.push(DisplayMessage::Error(format!(
+                    "Voice recording failed: {e:#}"
+                )));
+            }
+            return false;
+        }
+
+        if let Some(tree) = &mut self.tree
+            && tree.focused
+        {
+            match tree.handle_key(key) {
+                tree::Action::None => {}
+                tree::Action::Unfocus => tree.focused = false,
+                tree::Action::Mention(mention) => {
+                    tree.focused = false;
+                    self.insert_mention(&mention);
+                }
+            }
+            return false;
+        }
+
+        // Esc: stop Claude if busy, do nothing if idle. In vim mode it
+        // leaves insert mode first.
+        let vim_insert = self
+            .vim
+            .as_ref()
+            .is_some_and(|v| v.mode == vim::Mode::Insert);
+        if key.code == KeyCode::Esc && self.state == AppState::Busy && !vim_insert {
             let _ = self.session_tx.send(SessionCmd::Stop);
             return false;
         }
 
-        // Permission prompt captures y/n
-        if self.pending_perm.is_some() {
-            return self.handle_perm_key(key.code);
+        if let Some(vim) = &mut self.vim {
+            match vim.handle(key, &mut self.input, &mut self.cursor) {
+                vim::Outcome::Handled => return false,
+                vim::Outcome::ScrollUp => {
+                    self.scroll_up(1);
+                    return false;
+                }
+                vim::Outcome::ScrollDown => {
+                    self.scroll_down(1);
+                    return false;
+                }
+                vim::Outcome::Pass => {}
+            }
+        }
+
+        if self.readline.handle(key, &mut self.input, &mut self.cursor) {
+            return false;
         }
 
         match key.code {
-            KeyCode::Enter => {
-                if !self.input.is_empty() && self.state != AppState::Busy {
-                    return self.submit_input();
-                }
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.pending_prompt_edit |= self.state != AppState::Busy;
+            }
+
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.copy(CopyTarget::Response);
+            }
+
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.copy(CopyTarget::Code);
+            }
+
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.copy(CopyTarget::ToolOutput(1));
+            }
+
+            KeyCode::Char('m') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.set_mouse(!self.mouse_capture);
+            }
+
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_pager();
+            }
+
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.show_thinking = !self.show_thinking;
+            }
+
+            KeyCode::Enter if !self.input.is_empty() && self.state != AppState::Busy => {
+                return self.submit_input();
             }
 
             KeyCode::Char(c) => {
@@ -149,52 +377,70 @@ impl App {
                 self.cursor += 1;
             }
 
-            KeyCode::Backspace => {
-                if self.cursor > 0 {
-                    self.cursor -= 1;
-                    let byte_pos = self
-                        .input
-                        .char_indices()
-                        .nth(self.cursor)
-                        .map(|(i, _)| i)
-                        .unwrap_or(self.input.len());
-                    self.input.remove(byte_pos);
-                }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let byte_pos = self
+                    .input
+                    .char_indices()
+                    .nth(self.cursor)
+                    .map(|(i, _)| i)
+                    .unwrap_or(self.input.len());
+                self.input.remove(byte_pos);
             }
 
-            KeyCode::Delete => {
-                if self.cursor < self.input.chars().count() {
-                    let byte_pos = self
-                        .input
-                        .char_indices()
-                        .nth(self.cursor)
-                        .map(|(i, _)| i)
-                        .unwrap_or(self.input.len());
-                    self.input.remove(byte_pos);
-                }
+            KeyCode::Delete if self.cursor < self.input.chars().count() => {
+                let byte_pos = self
+                    .input
+                    .char_indices()
+                    .nth(self.cursor)
+                    .map(|(i, _)| i)
+                    .unwrap_or(self.input.len());
+                self.input.remove(byte_pos);
             }
 
             KeyCode::Left => {
                 self.cursor = self.cursor.saturating_sub(1);
             }
 
-            KeyCode::Right => {
-                if self.cursor < self.input.chars().count() {
-                    self.cursor += 1;
-                }
+            KeyCode::Right if self.cursor < self.input.chars().count() => {
+                self.cursor += 1;
             }
 
+            // Home/End scroll the conversation with Ctrl, or while the
+            // input is empty
+            KeyCode::Home
+                if key.modifiers.contains(KeyModifiers::CONTROL) || self.input.is_empty() =>
+            {
+                self.scroll = 0;
+                self.auto_scroll = false;
+            }
+            KeyCode::End
+                if key.modifiers.contains(KeyModifiers::CONTROL) || self.input.is_empty() =>
+            {
+                self.auto_scroll = true;
+            }
             KeyCode::Home => self.cursor = 0,
             KeyCode::End => self.cursor = self.input.chars().count(),
 
-            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                self.scroll = self.scroll.saturating_sub(1);
-                self.auto_scroll = false;
+            KeyCode::PageUp => self.scroll_up(self.page_height.saturating_sub(2).max(1)),
+            KeyCode::PageDown => self.scroll_down(self.page_height.saturating_sub(2).max(1)),
+
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => self.scroll_up(1),
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => self.scroll_down(1),
+
+            // Input history
+            KeyCode::Up => {
+                if let Some(entry) = self.history.prev(&self.input) {
+                    self.input = entry.to_string();
+                    self.cursor = self.input.chars().count();
+                }
             }
 
-            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                self.scroll = self.scroll.saturating_add(1);
-                self.auto_scroll = true; // re-enable when scrolling down
+            KeyCode::Down => {
+                if let Some(entry) = self.history.next() {
+                    self.input = entry.to_string();
+                    self.cursor = self.input.chars().count();
+                }
             }
 
             _ => {}
@@ -203,44 +449,434 @@ impl App {
         false
     }
 
-    fn handle_perm_key(&mut self, code: KeyCode) -> bool {
-        let respond = match code {
-            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => Some(true),
-            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => Some(false),
+    // -- Scrolling ----------------------------------------------------------
+
+    fn scroll_up(&mut self, rows: u16) {
+        // While following the output, `scroll` is stale
+        let from = if self.auto_scroll {
+            self.max_scroll
+        } else {
+            self.scroll
+        };
+        self.scroll = from.saturating_sub(rows);
+        self.auto_scroll = false;
+    }
+
+    /// Scrolling back to the bottom follows the output again.
+    fn scroll_down(&mut self, rows: u16) {
+        if self.auto_scroll {
+            return;
+        }
+        self.scroll = self.scroll.saturating_add(rows);
+        if self.scroll >= self.max_scroll {
+            self.auto_scroll = true;
+        }
+    }
+
+    /// Turn mouse capture on or off, saying which.
+    fn set_mouse(&mut self, on: bool) {
+        if let Err(e) = set_mouse_capture(on) {
+            self.messages.push(DisplayMessage::Error(format!(
+                "Failed to change mouse capture: {e}"
+            )));
+            return;
+        }
+
+        self.mouse_capture = on;
+        let message = if on {
+            "Mouse capture on: the wheel scrolls the conversation."
+        } else {
+            "Mouse capture off: select text with the terminal. Alt+M or /mouse turns scrolling back on."
+        };
+        self.messages
+            .push(DisplayMessage::Info(message.to_string()));
+    }
+
+    /// Open the file tree, focus it, or close it once focused.
+    fn toggle_tree(&mut self) {
+        match &mut self.tree {
+            None => self.tree = Some(tree::FileTree::new(&self.cwd)),
+            Some(tree) if !tree.focused => tree.focused = true,
+            Some(_) => self.tree = None,
+        }
+    }
+
+    /// Put a finished transcript into the input box at the cursor.
+    #[cfg(feature = "voice")]
+    fn insert_transcript(&mut self, result: Result<String>) {
+        match result {
+            Ok(text) if text.is_empty() => {
+                self.messages
+                    .push(DisplayMessage::Info("No speech recognized.".to_string()));
+            }
+            Ok(text) => {
+                let byte_pos = self
+                    .input
+                    .char_indices()
+                    .nth(self.cursor)
+                    .map(|(i, _)| i)
+                    .unwrap_or(self.input.len());
+                let space = match self.input[..byte_pos].chars().next_back() {
+                    Some(c) if !c.is_whitespace() => " ",
+                    _ => "",
+                };
+                let text = format!("{space}{text}");
+                self.input.insert_str(byte_pos, &text);
+                self.cursor += text.chars().count();
+            }
+            Err(e) => {
+                self.messages.push(DisplayMessage::Error(format!(
+                    "Transcription failed: {e:#}"
+                )));
+            }
+        }
+    }
+
+    /// Insert `@path ` at the cursor, as its own word.
+    fn insert_mention(&mut self, mention: &str) {
+        let byte_pos = self
+            .input
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.input.len());
+        let space = match self.input[..byte_pos].chars().next_back() {
+            Some(c) if !c.is_whitespace() => " ",
+            _ => "",
+        };
+        let text = format!("{space}{mention} ");
+        self.input.insert_str(byte_pos, &text);
+        self.cursor += text.chars().count();
+    }
+
+    /// Remember what a Read, Write or Edit call does to its file.
+    fn track_touch(&mut self, name: &str, input: &serde_json::Value) {
+        let touch = match name {
+            "Read" => tree::Touch::Read,
+            "Write" | "Edit" => tree::Touch::Modified,
+            _ => return,
+        };
+        let Some(path) = input.get("file_path").and_then(|v| v.as_str()) else {
+            return;
+        };
+
+        let path = Path::new(path);
+        let path = path.strip_prefix(&self.cwd).unwrap_or(path).to_path_buf();
+        let seen = self.touched.entry(path).or_insert(touch);
+        *seen = (*seen).max(touch);
+    }
+
+    /// Show the focused tool call's complete output.
+    fn open_pager(&mut self) {
+        let tool = self.focused_tool.and_then(|i| self.messages.get(i));
+        if let Some(DisplayMessage::ToolUse {
+            name,
+            input,
+            output: Some(output),
+            is_error,
+            ..
+        }) = tool
+        {
+            let title = match input {
+                Some(input) => render::tool_header(name, input, &self.cwd),
+                None => name.clone(),
+            };
+            self.pager = Some(pager::Pager::new(title, output, *is_error));
+        }
+    }
+
+    /// Copy part of the conversation to the clipboard and say what was
+    /// copied.
+    fn copy(&mut self, target: CopyTarget) {
+        let mut assistant_texts = self.messages.iter().rev().filter_map(|m| match m {
+            DisplayMessage::AssistantText(text) => Some(text),
             _ => None,
+        });
+
+        let (what, text) = match target {
+            CopyTarget::Response => ("response", assistant_texts.next().cloned()),
+            CopyTarget::Code => (
+                "code block",
+                assistant_texts
+                    .filter_map(|text| markdown::last_code_block(text))
+                    .next(),
+            ),
+            CopyTarget::ToolOutput(n) => (
+                "tool output",
+                self.messages
+                    .iter()
+                    .rev()
+                    .filter_map(|m| match m {
+                        DisplayMessage::ToolUse {
+                            output: Some(output),
+                            ..
+                        } => Some(output.clone()),
+                        _ => None,
+                    })
+                    .nth(n - 1),
+            ),
+        };
+
+        let message = match text {
+            None => DisplayMessage::Info(format!("No {what} to copy.")),
+            Some(text) => match crate::clipboard::copy(&text) {
+                Ok(()) => {
+                    DisplayMessage::Info(format!("Copied {what} ({} lines).", text.lines().count()))
+                }
+                Err(e) => DisplayMessage::Error(format!("Copy failed: {e:#}")),
+            },
+        };
+        self.messages.push(message);
+    }
+
+    /// Insert pasted text at the cursor as one edit, so its newlines don't
+    /// submit the input.
+    fn handle_paste(&mut self, text: &str) {
+        if let Some(perm) = &mut self.pending_perm {
+            if let Some(feedback) = &mut perm.feedback {
+                feedback.push_str(&text.replace(['\r', '\n'], " "));
+            }
+            return;
+        }
+
+        let text = self.pastes.insert(text);
+        let byte_pos = self
+            .input
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.input.len());
+        self.input.insert_str(byte_pos, &text);
+        self.cursor += text.chars().count();
+    }
+
+    fn handle_perm_key(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(perm) = &mut self.pending_perm else {
+            return false;
+        };
+
+        let reply = match &mut perm.feedback {
+            // Typing the reason for a denial
+            Some(feedback) => match key.code {
+                KeyCode::Enter => {
+                    let feedback = feedback.trim();
+                    Some(if feedback.is_empty() {
+                        Reply::Deny
+                    } else {
+                        Reply::DenyWithFeedback(feedback.to_string())
+                    })
+                }
+                KeyCode::Esc => {
+                    perm.feedback = None;
+                    None
+                }
+                KeyCode::Backspace => {
+                    feedback.pop();
+                    None
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    feedback.push(c);
+                    None
+                }
+                _ => None,
+            },
+            None => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => Some(Reply::AllowOnce),
+                KeyCode::Char('a') | KeyCode::Char('A') if perm.rule.is_some() => {
+                    Some(Reply::AllowAlways)
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => Some(Reply::Deny),
+                KeyCode::Char('f') | KeyCode::Char('F') => {
+                    perm.feedback = Some(String::new());
+                    None
+                }
+                _ => None,
+            },
         };
 
-        if let Some(allowed) = respond
+        if let Some(reply) = reply
             && let Some(perm) = self.pending_perm.take()
         {
-            let _ = perm.respond.send(allowed);
+            let _ = perm.respond.send(reply);
         }
 
         false
     }
 
+    /// Put the draft left by the last run back in the input box.
+    fn restore_draft(&mut self) {
+        let Some(draft) = self.draft.load() else {
+            return;
+        };
+        self.input = draft.into_input();
+        self.cursor = self.input.chars().count();
+        self.messages.push(DisplayMessage::Info(
+            "Restored the unsent draft from the last run.".to_string(),
+        ));
+    }
+
+    /// Save the input box (and a message held by a budget pause) so a quit
+    /// or crash doesn't lose it.
+    fn save_draft(&mut self) {
+        let input = self.pastes.expanded(&self.input);
+        let queued: Vec<String> = self.paused_input.iter().cloned().collect();
+        self.draft.save(&input, &queued);
+    }
+
     /// Process input: slash command or message. Returns `true` to quit.
     fn submit_input(&mut self) -> bool {
-        let text = std::mem::take(&mut self.input);
+        let text = self.pastes.expand(&std::mem::take(&mut self.input));
         self.cursor = 0;
+        if let Some(vim) = &mut self.vim {
+            vim.mode = vim::Mode::Insert;
+        }
+        self.history.push(&text);
 
         // Slash commands
-        if let Some(result) = commands::handle_command(&text, &self.model) {
+        let ctx = CommandContext {
+            model: &self.model,
+            cwd: &self.cwd,
+            turns: &self.turns,
+            profile: self.profile.as_deref(),
+            model_aliases: &self.model_aliases,
+        };
+
+        if let Some(result) = commands::handle_command(&text, &ctx) {
             match result {
                 CommandResult::Exit => return true,
 
                 CommandResult::Clear => {
                     let _ = self.session_tx.send(SessionCmd::Clear);
+                    self.set_turns(Vec::new());
+                    self.context_tokens = 0;
+                    self.context_warned = false;
+                    self.touched.clear();
+                    self.todos.clear();
+                    self.refresh_status();
                     self.messages.clear();
                     self.messages
                         .push(DisplayMessage::Info("Conversation cleared.".to_string()));
                 }
 
+                CommandResult::Compact(focus) => {
+                    self.messages.push(DisplayMessage::Info(
+                        "Compacting the conversation...".to_string(),
+                    ));
+                    self.set_busy();
+                    let _ = self.session_tx.send(SessionCmd::Compact(focus));
+                }
+
                 CommandResult::SetModel { id, label } => {
                     let _ = self.session_tx.send(SessionCmd::SetModel(id.clone()));
                     self.model = id;
                     self.messages
                         .push(DisplayMessage::Info(format!("Switched to {label}.")));
+                    self.refresh_status();
+                }
+
+                CommandResult::Resume(saved) => {
+                    self.messages = replay(&saved.messages, &self.cwd, &self.theme, &self.images);
+                    self.todos = todos::Todos::restore(&self.messages);
+                    self.messages.push(DisplayMessage::Info(format!(
+                        "Resumed session {}.",
+                        saved.id
+                    )));
+                    self.model = saved.model.clone();
+                    self.set_turns(saved.turn_usage());
+                    self.context_tokens = 0;
+                    self.auto_scroll = true;
+                    let _ = self.session_tx.send(SessionCmd::Resume(saved));
+                    self.refresh_status();
+                }
+
+                CommandResult::Export { format, path } => {
+                    let _ = self.session_tx.send(SessionCmd::Export { format, path });
+                }
+
+                CommandResult::Doctor => {
+                    self.messages
+                        .push(DisplayMessage::Info("Running diagnostics...".to_string()));
+                    let _ = self.session_tx.send(SessionCmd::Doctor);
+                }
+
+                CommandResult::Logout => {
+                    let _ = self.session_tx.send(SessionCmd::Logout);
+                }
+
+                CommandResult::Budget { extend } => {
+                    let _ = self.session_tx.send(SessionCmd::Budget { extend });
+                    if extend && let Some(input) = self.paused_input.take() {
+                        self.set_busy();
+                        let _ = self.session_tx.send(SessionCmd::SendMessage(input));
+                    }
+                }
+
+                #[cfg(feature = "git")]
+                CommandResult::Checkpoints(target) => {
+                    let _ = self.session_tx.send(SessionCmd::Checkpoints(target));
+                }
+
+                CommandResult::Permissions { message, change } => {
+                    self.messages.push(DisplayMessage::Info(message));
+                    let _ = self.session_tx.send(SessionCmd::UpdatePermissions(change));
+                }
+
+                CommandResult::Copy(target) => self.copy(target),
+
+                CommandResult::Mouse(on) => self.set_mouse(on.unwrap_or(!self.mouse_capture)),
+
+                CommandResult::AddDir(dir) => {
+                    let _ = self.session_tx.send(SessionCmd::AddDir(dir));
+                }
+
+                CommandResult::Pin(path) => {
+                    let _ = self.session_tx.send(SessionCmd::Pin(path));
+                }
+
+                CommandResult::Unpin(path) => {
+                    let _ = self.session_tx.send(SessionCmd::Unpin(path));
+                }
+
+                CommandResult::EditFile(path) => {
+                    self.pending_edit = Some(path);
+                }
+
+                CommandResult::Init => {
+                    self.messages.push(DisplayMessage::Info(
+                        "Drafting CLAUDE.md from the repository…".to_string(),
+                    ));
+                    let _ = self.session_tx.send(SessionCmd::DraftClaudeMd);
+                }
+
+                CommandResult::MemoryUpdated(message) => {
+                    self.messages.push(DisplayMessage::Info(message));
+                    let _ = self.session_tx.send(SessionCmd::ReloadMemory);
+                }
+
+                CommandResult::Login => {
+                    self.pending_login = true;
+                }
+
+                CommandResult::SwitchProfile {
+                    profile,
+                    creds,
+                    model,
+                } => {
+                    let _ = self.session_tx.send(SessionCmd::SwitchProfile {
+                        profile,
+                        creds,
+                        model,
+                    });
+                }
+
+                CommandResult::PickTemplate(list) => {
+                    self.template_picker = Some(templates::TemplatePicker::new(list));
+                }
+
+                CommandResult::Prompt(prompt) => {
+                    self.messages.push(DisplayMessage::User(prompt.clone()));
+                    self.set_busy();
+                    let _ = self.session_tx.send(SessionCmd::SendMessage(prompt));
                 }
 
                 CommandResult::Info(info) => {
@@ -249,23 +885,13 @@ impl App {
 
                 CommandResult::Continue => {}
 
-                #[cfg(feature = "voice")]
-                CommandResult::SendMessage(msg) => {
-                    // Send the transcribed message as if user typed it
-                    self.messages.push(DisplayMessage::User(msg.clone()));
-                    self.state = AppState::Busy;
-                    self.auto_scroll = true;
-                    let _ = self.session_tx.send(SessionCmd::SendMessage(msg));
-                    return false;
-                }
-
                 #[cfg(feature = "voice")]
                 CommandResult::RecordVoice => {
-                    self.messages.push(DisplayMessage::Info(
-                        "Entering voice recording mode...".to_string(),
-                    ));
-                    self.pending_voice_recording = true;
-                    return false;
+                    if let Err(e) = self.voice.toggle() {
+                        self.messages.push(DisplayMessage::Error(format!(
+                            "Voice recording failed: {e:#}"
+                        )));
+                    }
                 }
             }
 
@@ -274,18 +900,97 @@ impl App {
 
         // Regular message
         self.messages.push(DisplayMessage::User(text.clone()));
-        self.state = AppState::Busy;
-        self.auto_scroll = true;
+        self.set_busy();
         let _ = self.session_tx.send(SessionCmd::SendMessage(text));
 
         false
     }
 
+    fn set_turns(&mut self, turns: Vec<TurnUsage>) {
+        self.usage = Usage::default();
+        for turn in &turns {
+            self.usage += turn.usage;
+        }
+        self.turns = turns;
+    }
+
+    /// Show the local images mentioned in the responses since the last
+    /// prompt.
+    fn show_mentioned_images(&mut self) {
+        let start = self
+            .messages
+            .iter()
+            .rposition(|m| matches!(m, DisplayMessage::User(_)))
+            .map_or(0, |i| i + 1);
+
+        let mut found = Vec::new();
+        for message in &self.messages[start..] {
+            if let DisplayMessage::AssistantText(text) = message {
+                for path in images::referenced_paths(text, &self.cwd) {
+                    if !found.contains(&path) {
+                        found.push(path);
+                    }
+                }
+            }
+        }
+
+        for (shown, path) in found {
+            let image = self.images.file(&path, &shown);
+            self.messages.push(DisplayMessage::Image(image));
+        }
+    }
+
+    /// Suggest `/compact` once the context crosses the warning level.
+    fn warn_context(&mut self) {
+        let percent = self.context_tokens * 100 / CONTEXT_WINDOW;
+        if percent < status::CONTEXT_WARNING {
+            self.context_warned = false;
+        } else if !self.context_warned {
+            self.context_warned = true;
+            self.messages.push(DisplayMessage::Info(format!(
+                "Context is {percent}% full. Run /compact to summarize the conversation and free up space."
+            )));
+        }
+    }
+
+    /// What the status bar shows right now.
+    fn status_info(&self) -> status::StatusInfo<'_> {
+        status::StatusInfo {
+            model: &self.model,
+            profile: self.profile.as_deref(),
+            branch: self.branch.as_deref(),
+            cwd: &self.cwd,
+            permission_mode: self.permission_mode,
+            usage: &self.usage,
+            turns: &self.turns,
+            context_tokens: self.context_tokens,
+            vim: self.vim.as_ref().map(|v| v.mode),
+        }
+    }
+
+    /// Look up the git branch again and re-run the status line command,
+    /// after anything they show may have changed.
+    fn refresh_status(&mut self) {
+        #[cfg(feature = "git")]
+        if self.status_line.wants_branch() {
+            self.branch = ccrs_git::current_branch(&self.cwd).ok().flatten();
+        }
+        self.status_line.refresh(&self.status_info());
+    }
+
+    /// Waiting on the session from now on.
+    fn set_busy(&mut self) {
+        self.state = AppState::Busy;
+        self.auto_scroll = true;
+        self.activity = activity::Activity::default();
+    }
+
     // -- UI event handling --------------------------------------------------
 
     fn handle_ui_event(&mut self, event: UiEvent) {
         match event {
             UiEvent::Text(text) => {
+                self.activity.streamed(&text);
                 if let Some(DisplayMessage::AssistantText(existing)) = self.messages.last_mut() {
                     existing.push_str(&text);
                 } else {
@@ -293,16 +998,41 @@ impl App {
                 }
             }
 
+            UiEvent::Thinking(text) => {
+                self.activity.streamed(&text);
+                if let Some(DisplayMessage::Thinking(existing)) = self.messages.last_mut() {
+                    existing.push_str(&text);
+                } else {
+                    self.messages.push(DisplayMessage::Thinking(text));
+                }
+            }
+
             UiEvent::Error(msg) => {
                 self.messages.push(DisplayMessage::Error(msg));
             }
 
-            UiEvent::ToolStart { name, input } => {
+            UiEvent::Info(msg) => {
+                self.messages.push(DisplayMessage::Info(msg));
+            }
+
+            UiEvent::ClaudeMdDraft(draft) => {
+                self.pending_claude_md = Some(draft);
+            }
+
+            UiEvent::ToolStart {
+                name,
+                input,
+                change,
+            } => {
+                self.activity.tool_started(&name, &input);
+                self.track_touch(&name, &input);
+                self.todos.update(&name, &input);
                 self.messages.push(DisplayMessage::ToolUse {
                     name,
                     input: Some(input),
                     output: None,
                     is_error: false,
+                    diff: change.map(|c| c.render(&self.theme)),
                 });
             }
 
@@ -312,37 +1042,94 @@ impl App {
                 }
             }
 
-            UiEvent::ToolResult { output, is_error } => {
-                if let Some(DisplayMessage::ToolUse {
-                    output: out,
-                    is_error: err,
-                    ..
-                }) = self.messages.last_mut()
-                {
+            UiEvent::ToolResult {
+                name,
+                output,
+                is_error,
+            } => {
+                // Latest pending call of this tool; a Task's own result
+                // arrives after those of the tools its subagent ran
+                let pending = self.messages.iter_mut().rev().find_map(|m| match m {
+                    DisplayMessage::ToolUse {
+                        name: n,
+                        output: out @ None,
+                        is_error: err,
+                        ..
+                    } if *n == name => Some((out, err)),
+                    _ => None,
+                });
+                if let Some((out, err)) = pending {
                     *out = Some(output);
                     *err = is_error;
                 }
             }
 
-            UiEvent::ToolEnd => {}
+            UiEvent::ToolEnd => self.activity.tool_finished(),
+
+            UiEvent::ToolProgress(progress) => self.activity.tool_progress(progress),
+
+            UiEvent::TaskStatus {
+                id,
+                description,
+                status,
+            } => self.activity.task_status(id, description, status),
 
-            UiEvent::Done(usage) => {
-                self.usage.input_tokens += usage.input_tokens;
-                self.usage.output_tokens += usage.output_tokens;
+            UiEvent::Done => {
                 self.state = AppState::Idle;
+                if let Some(tree) = &mut self.tree {
+                    tree.reload();
+                }
+                self.show_mentioned_images();
+                self.warn_context();
+                self.refresh_status();
             }
 
+            UiEvent::Turns(turns) => self.set_turns(turns),
+
+            UiEvent::ContextTokens(tokens) => self.context_tokens = tokens,
+
+            UiEvent::OutputTokens(tokens) => self.activity.output_tokens(tokens),
+
+            UiEvent::StatusLine(line) => self.status_line.set_output(line),
+
             UiEvent::Failed(msg) => {
                 self.messages.push(DisplayMessage::Error(msg));
                 self.state = AppState::Idle;
+                self.refresh_status();
+            }
+
+            UiEvent::BudgetPaused { message, resume } => {
+                self.messages.push(DisplayMessage::Info(format!(
+                    "{message} /budget continue allows as much again and carries on."
+                )));
+                self.paused_input = Some(resume);
+                self.state = AppState::Idle;
+                self.refresh_status();
+            }
+
+            UiEvent::ProfileSwitched { profile, model } => {
+                let name = profile.as_deref().unwrap_or(config::DEFAULT_PROFILE);
+                let message = match &model {
+                    Some(model) => format!("Switched to profile {name} ({model})."),
+                    None => format!("Switched to profile {name}."),
+                };
+                self.messages.push(DisplayMessage::Info(message));
+                self.profile = profile;
+                if let Some(model) = model {
+                    self.model = model;
+                }
+                self.refresh_status();
             }
 
             UiEvent::PermissionRequest {
                 description,
+                rule,
                 respond,
             } => {
                 self.pending_perm = Some(PendingPermission {
                     description,
+                    rule,
+                    feedback: None,
                     respond,
                 });
             }
@@ -356,45 +1143,87 @@ impl App {
 
 async fn session_loop(
     mut session: Session<ChannelPermissions>,
+    mut profile: Option<String>,
     mut cmd_rx: mpsc::UnboundedReceiver<SessionCmd>,
     ui_tx: mpsc::UnboundedSender<UiEvent>,
 ) {
-    let mut handler = ChannelEventHandler { tx: ui_tx.clone() };
+    let mut handler = ChannelEventHandler {
+        tx: ui_tx.clone(),
+        cwd: session.cwd().to_path_buf(),
+    };
 
     while let Some(cmd) = cmd_rx.recv().await {
         match cmd {
             SessionCmd::SendMessage(text) => {
                 let cancel = CancellationToken::new();
-                let token = cancel.clone();
-
-                let message_future = session.send_message(&text, &mut handler, &token);
-                tokio::pin!(message_future);
-
-                // Race message completion against stop commands
-                let result = loop {
-                    tokio::select! {
-                        res = &mut message_future => break res,
-                        Some(cmd) = cmd_rx.recv() => {
-                            if matches!(cmd, SessionCmd::Stop) {
-                                cancel.cancel();
-                            }
-                            // Other commands ignored while busy
-                        }
-                    }
-                };
+                let result = until_stopped(
+                    session.send_message(&text, &mut handler, &cancel),
+                    &cancel,
+                    &mut cmd_rx,
+                )
+                .await;
+
+                let _ = ui_tx.send(UiEvent::Turns(session.turns().to_vec()));
 
                 match result {
-                    Ok(usage) => {
-                        let _ = ui_tx.send(UiEvent::Done(usage));
+                    Ok(_) => {
+                        if let Err(e) = session.save() {
+                            let _ =
+                                ui_tx.send(UiEvent::Error(format!("Failed to save session: {e}")));
+                        }
+                        let _ = ui_tx.send(UiEvent::Done);
+                    }
+                    Err(ref e) if let Some(paused) = e.downcast_ref::<BudgetExceeded>() => {
+                        if let Err(e) = session.save() {
+                            let _ =
+                                ui_tx.send(UiEvent::Error(format!("Failed to save session: {e}")));
+                        }
+                        // A turn stopped partway is picked up where it was
+                        let resume = if paused.mid_turn {
+                            "Continue where you left off.".to_string()
+                        } else {
+                            text
+                        };
+                        let _ = ui_tx.send(UiEvent::BudgetPaused {
+                            message: paused.reason.clone(),
+                            resume,
+                        });
                     }
                     Err(e) => {
-                        let msg = e.to_string();
+                        let _ = ui_tx.send(failure(e));
+                    }
+                }
+            }
 
-                        if msg == "Cancelled" {
-                            let _ = ui_tx.send(UiEvent::Failed("Stopped.".to_string()));
-                        } else {
-                            let _ = ui_tx.send(UiEvent::Failed(msg));
+            SessionCmd::Compact(focus) => {
+                let before = session.context_tokens();
+                let cancel = CancellationToken::new();
+                let result = until_stopped(
+                    session.compact(focus.as_deref(), &cancel),
+                    &cancel,
+                    &mut cmd_rx,
+                )
+                .await;
+
+                let _ = ui_tx.send(UiEvent::Turns(session.turns().to_vec()));
+
+                match result {
+                    Ok(_) => {
+                        let after = session.context_tokens();
+                        let _ = ui_tx.send(UiEvent::ContextTokens(after));
+                        if let Err(e) = session.save() {
+                            let _ =
+                                ui_tx.send(UiEvent::Error(format!("Failed to save session: {e}")));
                         }
+                        let _ = ui_tx.send(UiEvent::Info(format!(
+                            "Compacted the conversation: {} → about {} tokens.",
+                            status::format_tokens(before),
+                            status::format_tokens(after),
+                        )));
+                        let _ = ui_tx.send(UiEvent::Done);
+                    }
+                    Err(e) => {
+                        let _ = ui_tx.send(failure(e));
                     }
                 }
             }
@@ -410,27 +1239,415 @@ async fn session_loop(
             SessionCmd::Clear => {
                 session.clear();
             }
+
+            SessionCmd::Resume(saved) => {
+                session.resume(*saved);
+                let _ = ui_tx.send(UiEvent::ContextTokens(session.context_tokens()));
+            }
+
+            SessionCmd::Export { format, path } => {
+                let meta = ExportMeta {
+                    id: session.id(),
+                    model: session.model(),
+                    cwd: session.cwd(),
+                };
+
+                let event = match export::write(format, path, &meta, session.history()) {
+                    Ok(path) => UiEvent::Info(format!("Exported to {}.", path.display())),
+                    Err(e) => UiEvent::Error(format!("Export failed: {e}")),
+                };
+                let _ = ui_tx.send(event);
+            }
+
+            SessionCmd::ReloadMemory => {
+                session.reload_memory();
+            }
+
+            SessionCmd::DraftClaudeMd => {
+                // Walks the whole repository, so not on this task or the UI's
+                let cwd = session.cwd().to_path_buf();
+                let ui_tx = ui_tx.clone();
+                tokio::spawn(async move {
+                    let event = match tokio::task::spawn_blocking(move || init::draft(&cwd)).await {
+                        Ok(draft) => UiEvent::ClaudeMdDraft(draft),
+                        Err(e) => UiEvent::Error(format!("Failed to draft CLAUDE.md: {e}")),
+                    };
+                    let _ = ui_tx.send(event);
+                });
+            }
+
+            SessionCmd::Budget { extend } => {
+                let message = if extend {
+                    let limits = session.extend_budget();
+                    if limits == Budget::default() {
+                        "No budget is set for this session.".to_string()
+                    } else {
+                        budget::report(session.spent(), limits)
+                    }
+                } else {
+                    budget::report(session.spent(), session.budget_limits())
+                };
+                let _ = ui_tx.send(UiEvent::Info(message));
+            }
+
+            #[cfg(feature = "git")]
+            SessionCmd::Checkpoints(target) => {
+                let event = match (session.checkpoints(), target) {
+                    (Ok(list), None) => UiEvent::Info(checkpoints::format_list(
+                        &list,
+                        claude_code_core::store::now(),
+                    )),
+                    (Ok(list), Some(target)) => {
+                        let id = checkpoints::resolve(&target, &list);
+                        match session.restore_checkpoint(&id) {
+                            Ok(changed) if changed.is_empty() => UiEvent::Info(
+                                "The files already match that checkpoint.".to_string(),
+                            ),
+                            Ok(changed) => UiEvent::Info(format!(
+                                "Restored {} file(s): {}. /checkpoints restore 1 undoes this.",
+                                changed.len(),
+                                changed.join(", ")
+                            )),
+                            Err(e) => UiEvent::Error(format!("Restore failed: {e:#}")),
+                        }
+                    }
+                    (Err(e), _) => UiEvent::Error(format!("Failed to list checkpoints: {e:#}")),
+                };
+                let _ = ui_tx.send(event);
+            }
+
+            SessionCmd::AddDir(dir) => {
+                let dirs = &mut session
+                    .permissions_mut()
+                    .config_mut()
+                    .additional_directories;
+                if !dirs.contains(&dir) {
+                    dirs.push(dir.clone());
+                }
+
+                let message = if session.add_dir(dir.clone()) {
+                    format!(
+                        "Added {}. Claude can read and edit files there, and Search indexes it.",
+                        dir.display()
+                    )
+                } else {
+                    format!("{} was already added.", dir.display())
+                };
+                let _ = ui_tx.send(UiEvent::Info(message));
+            }
+
+            SessionCmd::Pin(path) => {
+                let event = match path {
+                    None => UiEvent::Info(pin::format_list(&session.pinned_files(), session.cwd())),
+                    Some(path) => match session.pin(&path) {
+                        Ok(true) => UiEvent::Info(format!(
+                            "Pinned {}. It's shown at the top of every request and re-read when \
+                             it changes.",
+                            path.display()
+                        )),
+                        Ok(false) => {
+                            UiEvent::Info(format!("{} is pinned already.", path.display()))
+                        }
+                        Err(e) => UiEvent::Error(format!("{e:#}")),
+                    },
+                };
+                let _ = ui_tx.send(event);
+            }
+
+            SessionCmd::Unpin(path) => {
+                let message = if session.unpin(&path) {
+                    format!("Unpinned {}.", path.display())
+                } else {
+                    format!(
+                        "{} isn't pinned. /pin lists the pinned files.",
+                        path.display()
+                    )
+                };
+                let _ = ui_tx.send(UiEvent::Info(message));
+            }
+
+            SessionCmd::UpdatePermissions(change) => {
+                let config = session.permissions_mut().config_mut();
+                if change.added {
+                    config.add_rule(change.kind, &change.rule);
+                } else {
+                    config.remove_rule(change.kind, &change.rule);
+                }
+            }
+
+            SessionCmd::SetCredentials {
+                access_token,
+                is_oauth,
+                refresh,
+            } => {
+                session.set_credentials(access_token, is_oauth, refresh);
+            }
+
+            SessionCmd::SwitchProfile {
+                profile: next,
+                creds,
+                model,
+            } => match login::access_token(&creds).await {
+                Ok(access) => {
+                    if let Some(updated) = &access.updated
+                        && let Err(e) = config::save_credentials(next.as_deref(), updated)
+                    {
+                        let _ = ui_tx.send(UiEvent::Error(format!(
+                            "Failed to save refreshed credentials: {e:#}"
+                        )));
+                    }
+                    let refresh = access.refresh.map(|r| r.save_to(next.clone()));
+                    session.set_credentials(access.token, access.is_oauth, refresh);
+                    if let Some(model) = &model {
+                        session.set_model(model.clone());
+                    }
+                    profile = next.clone();
+                    let _ = ui_tx.send(UiEvent::ProfileSwitched {
+                        profile: next,
+                        model,
+                    });
+                }
+                Err(e) => {
+                    let _ = ui_tx.send(UiEvent::Error(format!("Profile switch failed: {e:#}")));
+                }
+            },
+
+            SessionCmd::Logout => {
+                let message = logout::run(profile.as_deref()).await;
+                let _ = ui_tx.send(UiEvent::Info(message));
+            }
+
+            SessionCmd::Doctor => {
+                let report = doctor::run(&session, profile.as_deref()).await;
+                let _ = ui_tx.send(UiEvent::Info(report));
+            }
+        }
+    }
+}
+
+/// Run `future`, cancelling `cancel` when a stop command arrives. Other
+/// commands are dropped while busy.
+async fn until_stopped<T>(
+    future: impl Future<Output = T>,
+    cancel: &CancellationToken,
+    cmd_rx: &mut mpsc::UnboundedReceiver<SessionCmd>,
+) -> T {
+    tokio::pin!(future);
+
+    loop {
+        tokio::select! {
+            res = &mut future => break res,
+            Some(cmd) = cmd_rx.recv() => {
+                if matches!(cmd, SessionCmd::Stop) {
+                    cancel.cancel();
+                }
+            }
+        }
+    }
+}
+
+/// The event for a message or compaction that did not complete.
+fn failure(e: anyhow::Error) -> UiEvent {
+    if matches!(e.downcast_ref(), Some(ApiError::Cancelled)) {
+        UiEvent::Failed("Stopped.".to_string())
+    } else {
+        UiEvent::Failed(e.to_string())
+    }
+}
+
+/// Rebuild display messages from a resumed conversation.
+fn replay(
+    history: &[Message],
+    cwd: &Path,
+    theme: &Theme,
+    images: &images::Images,
+) -> Vec<DisplayMessage> {
+    let mut out = Vec::new();
+    // Tool use ID → index of its `DisplayMessage::ToolUse`
+    let mut tool_uses = std::collections::HashMap::new();
+
+    for msg in history {
+        let is_user = msg.role == "user";
+
+        let blocks = match &msg.content {
+            Content::Text(text) if is_user => {
+                out.push(DisplayMessage::User(text.clone()));
+                continue;
+            }
+            Content::Text(text) => {
+                out.push(DisplayMessage::AssistantText(text.clone()));
+                push_mentioned_images(&mut out, text, cwd, images);
+                continue;
+            }
+            Content::Blocks(blocks) => blocks,
+        };
+
+        for block in blocks {
+            match block {
+                ContentBlock::Text { text } if is_user => {
+                    out.push(DisplayMessage::User(text.clone()));
+                }
+                ContentBlock::Text { text } => {
+                    out.push(DisplayMessage::AssistantText(text.clone()));
+                    push_mentioned_images(&mut out, text, cwd, images);
+                }
+                ContentBlock::Image { source } => {
+                    out.push(DisplayMessage::Image(images.block(source)));
+                }
+                ContentBlock::Thinking { thinking, .. } => {
+                    out.push(DisplayMessage::Thinking(thinking.clone()));
+                }
+                ContentBlock::RedactedThinking { .. } => {}
+                ContentBlock::ToolUse { id, name, input } => {
+                    tool_uses.insert(id.as_str(), out.len());
+                    out.push(DisplayMessage::ToolUse {
+                        name: name.clone(),
+                        input: Some(input.clone()),
+                        output: None,
+                        is_error: false,
+                        diff: diff::FileChange::after_run(name, input, cwd)
+                            .map(|c| c.render(theme)),
+                    });
+                }
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                    is_error,
+                } => {
+                    if let Some(&i) = tool_uses.get(tool_use_id.as_str())
+                        && let DisplayMessage::ToolUse {
+                            output,
+                            is_error: err,
+                            ..
+                        } = &mut out[i]
+                    {
+                        *output = Some(content.clone());
+                        *err = is_error.unwrap_or(false);
+                    }
+                }
+            }
         }
     }
+
+    out
+}
+
+fn push_mentioned_images(
+    out: &mut Vec<DisplayMessage>,
+    text: &str,
+    cwd: &Path,
+    images: &images::Images,
+) {
+    for (shown, path) in images::referenced_paths(text, cwd) {
+        out.push(DisplayMessage::Image(images.file(&path, &shown)));
+    }
+}
+
+// ---------------------------------------------------------------------------
+// External programs
+// ---------------------------------------------------------------------------
+
+type Term = Terminal<CrosstermBackend<std::io::Stdout>>;
+
+fn set_mouse_capture(on: bool) -> std::io::Result<()> {
+    if on {
+        crossterm::execute!(std::io::stdout(), crossterm::event::EnableMouseCapture)
+    } else {
+        crossterm::execute!(std::io::stdout(), crossterm::event::DisableMouseCapture)
+    }
+}
+
+/// Hand the terminal to `f` (e.g. an editor), then restore the TUI.
+fn suspended<T>(terminal: &mut Term, f: impl FnOnce() -> T) -> Result<T> {
+    #[cfg(feature = "voice")]
+    voice::disable_key_releases();
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste,
+        crossterm::terminal::LeaveAlternateScreen,
+    )?;
+
+    let out = f();
+
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::event::EnableMouseCapture,
+        crossterm::event::EnableBracketedPaste,
+    )?;
+    #[cfg(feature = "voice")]
+    voice::enable_key_releases();
+    terminal.clear()?;
+
+    Ok(out)
+}
+
+/// Open `input` in the user's editor and return the saved text. The outer
+/// error means the terminal could not be restored.
+fn edit_text(terminal: &mut Term, name: &str, input: &str) -> Result<Result<String>> {
+    let path = std::env::temp_dir().join(format!("ccrs-{}-{name}", std::process::id()));
+
+    if let Err(e) = std::fs::write(&path, input) {
+        return Ok(Err(
+            anyhow::Error::new(e).context("Failed to create a temp file")
+        ));
+    }
+
+    let result = suspended(terminal, || crate::editor::open(&path))?.and_then(|()| {
+        std::fs::read_to_string(&path)
+            .context("Failed to read the edited text")
+            // Editors add a final newline
+            .map(|text| text.trim_end().to_string())
+    });
+    let _ = std::fs::remove_file(&path);
+
+    Ok(result)
 }
 
 // ---------------------------------------------------------------------------
 // Entry point
 // ---------------------------------------------------------------------------
 
+/// Appearance and input settings for the TUI.
+pub struct Options<'a> {
+    pub theme: Option<&'a ThemeSetting>,
+    pub vim_mode: bool,
+    pub status_line: Option<&'a StatusLineSetting>,
+    /// Shown by the status line's `mode` segment.
+    pub permission_mode: PermissionMode,
+    pub model_aliases: ModelAliases,
+    #[cfg(feature = "voice")]
+    pub voice: &'a VoiceSettings,
+}
+
 pub fn run(
     cwd: PathBuf,
     session: Session<ChannelPermissions>,
+    profile: Option<String>,
+    options: Options<'_>,
     ui_tx: mpsc::UnboundedSender<UiEvent>,
     ui_rx: mpsc::UnboundedReceiver<UiEvent>,
 ) -> Result<()> {
+    let (theme, theme_error) = match Theme::load(options.theme) {
+        Ok(theme) => (theme, None),
+        Err(e) => (Theme::load(None)?, Some(e)),
+    };
+
     let model = session.model().to_string();
+    let history = session.history().to_vec();
+    let session_id = session.id().to_string();
+    let turns = session.turns().to_vec();
+    let context_tokens = session.context_tokens();
 
     // Channel for UI → session commands
     let (session_tx, session_rx) = mpsc::unbounded_channel();
 
     // Spawn session loop in background
-    tokio::spawn(session_loop(session, session_rx, ui_tx));
+    let status_tx = ui_tx.clone();
+    tokio::spawn(session_loop(session, profile.clone(), session_rx, ui_tx));
 
     // Terminal setup
     crossterm::terminal::enable_raw_mode()?;
@@ -438,70 +1655,170 @@ pub fn run(
         std::io::stdout(),
         crossterm::terminal::EnterAlternateScreen,
         crossterm::event::EnableMouseCapture,
+        crossterm::event::EnableBracketedPaste,
     )?;
 
     let backend = CrosstermBackend::new(std::io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
+    // Queries the terminal, so it needs raw mode
+    let images = images::Images::detect();
+    let history = replay(&history, &cwd, &theme, &images);
+
     // Restore terminal on panic
     let original_hook = std::panic::take_hook();
 
     std::panic::set_hook(Box::new(move |info| {
+        #[cfg(feature = "voice")]
+        voice::disable_key_releases();
         let mut stdout = std::io::stdout();
         let _ = crossterm::execute!(
             stdout,
             crossterm::event::DisableMouseCapture,
+            crossterm::event::DisableBracketedPaste,
             crossterm::terminal::LeaveAlternateScreen,
         );
         let _ = crossterm::terminal::disable_raw_mode();
         original_hook(info);
     }));
 
-    let mut app = App::new(cwd, model, ui_rx, session_tx);
+    let mut app = App::new(
+        cwd,
+        model,
+        profile,
+        theme,
+        options.vim_mode,
+        ui_rx,
+        session_tx,
+    );
+    app.set_turns(turns);
+    app.images = images;
+    app.context_tokens = context_tokens;
+    app.permission_mode = options.permission_mode;
+    app.model_aliases = options.model_aliases;
+    app.status_line = status::StatusLine::new(options.status_line, Some(status_tx));
+    #[cfg(feature = "voice")]
+    {
+        app.voice = voice::Voice::new(options.voice.clone());
+        voice::enable_key_releases();
+    }
+    app.refresh_status();
+
+    if !history.is_empty() {
+        app.todos = todos::Todos::restore(&history);
+        app.messages = history;
+        app.messages.push(DisplayMessage::Info(format!(
+            "Resumed session {session_id}."
+        )));
+    }
+
+    if let Some(e) = theme_error {
+        app.messages.push(DisplayMessage::Error(format!(
+            "Ignoring the theme setting: {e:#}"
+        )));
+    }
+
+    app.draft = draft::DraftStore::for_cwd(&app.cwd);
+    app.restore_draft();
 
     // Start with a clean alternate screen
     terminal.clear()?;
 
     loop {
-        // Handle voice recording if requested
-        #[cfg(feature = "voice")]
-        if app.pending_voice_recording {
-            app.pending_voice_recording = false;
+        if let Some(path) = app.pending_edit.take() {
+            let edit_result = suspended(&mut terminal, || crate::editor::open(&path))?;
+            set_mouse_capture(app.mouse_capture)?;
+
+            match edit_result {
+                Ok(()) => {
+                    let _ = app.session_tx.send(SessionCmd::ReloadMemory);
+                    app.messages
+                        .push(DisplayMessage::Info(format!("Saved {}.", path.display())));
+                }
+                Err(e) => {
+                    app.messages
+                        .push(DisplayMessage::Error(format!("Edit failed: {e:#}")));
+                }
+            }
+        }
+
+        if let Some(draft) = app.pending_claude_md.take() {
+            let edited = edit_text(&mut terminal, init::FILE_NAME, &draft)?;
+            set_mouse_capture(app.mouse_capture)?;
+
+            match edited {
+                Ok(text) => {
+                    app.messages
+                        .push(DisplayMessage::Info(init::save(&app.cwd, &text)));
+                    let _ = app.session_tx.send(SessionCmd::ReloadMemory);
+                }
+                Err(e) => {
+                    app.messages
+                        .push(DisplayMessage::Error(format!("Edit failed: {e:#}")));
+                }
+            }
+        }
 
-            // Exit TUI temporarily - rec::run() handles terminal state
+        if app.pending_prompt_edit {
+            app.pending_prompt_edit = false;
+
+            app.input = app.pastes.expand(&app.input);
+
+            let edited = edit_text(&mut terminal, "prompt.md", &app.input)?;
+            set_mouse_capture(app.mouse_capture)?;
+
+            match edited {
+                Ok(text) => {
+                    app.cursor = text.chars().count();
+                    app.input = text;
+                }
+                Err(e) => {
+                    app.cursor = app.input.chars().count();
+                    app.messages
+                        .push(DisplayMessage::Error(format!("Edit failed: {e:#}")));
+                }
+            }
+        }
+
+        if app.pending_login {
+            app.pending_login = false;
+
+            // Exit TUI temporarily - login::run() handles terminal state
+            #[cfg(feature = "voice")]
+            voice::disable_key_releases();
             drop(terminal);
 
-            // Run voice recording (async, blocks until done)
-            let rec_result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current()
-                    .block_on(async { crate::commands::rec::run().await })
+            let login_result = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(login::run(app.profile.as_deref()))
             });
 
-            // Recreate terminal and re-enable raw mode
-            crossterm::terminal::enable_raw_mode()?;
             crossterm::execute!(
                 std::io::stdout(),
-                crossterm::terminal::EnterAlternateScreen,
                 crossterm::event::EnableMouseCapture,
+                crossterm::event::EnableBracketedPaste,
             )?;
             let backend = CrosstermBackend::new(std::io::stdout());
             terminal = Terminal::new(backend)?;
             terminal.clear()?;
-
-            // Process result
-            match rec_result {
-                Ok(CommandResult::SendMessage(msg)) => {
-                    app.messages.push(DisplayMessage::User(msg.clone()));
-                    app.state = AppState::Busy;
-                    app.auto_scroll = true;
-                    let _ = app.session_tx.send(SessionCmd::SendMessage(msg));
+            set_mouse_capture(app.mouse_capture)?;
+            #[cfg(feature = "voice")]
+            voice::enable_key_releases();
+
+            match login_result {
+                Ok(access) => {
+                    let _ = app.session_tx.send(SessionCmd::SetCredentials {
+                        access_token: access.token,
+                        is_oauth: access.is_oauth,
+                        refresh: access.refresh,
+                    });
+                    app.messages.push(DisplayMessage::Info(
+                        "Logged in. Credentials saved.".to_string(),
+                    ));
                 }
                 Err(e) => {
-                    app.messages.push(DisplayMessage::Error(format!(
-                        "Voice recording failed: {e}"
-                    )));
+                    app.messages
+                        .push(DisplayMessage::Error(format!("Login failed: {e:#}")));
                 }
-                _ => {}
             }
         }
 
@@ -513,30 +1830,37 @@ pub fn run(
             app.last_spinner_update = Instant::now();
         }
 
+        #[cfg(feature = "voice")]
+        if let Some(result) = app.voice.poll() {
+            app.insert_transcript(result);
+        }
+
         terminal.draw(|f| render::render(&mut app, f))?;
 
         // Poll crossterm events (~30 fps)
         if crossterm::event::poll(Duration::from_millis(33))? {
             match crossterm::event::read()? {
-                Event::Key(key) => {
-                    if app.handle_key(key) {
-                        break;
-                    }
+                // Only reported with key releases on, for holding Alt+V
+                #[cfg(feature = "voice")]
+                Event::Key(key)
+                    if key.kind == KeyEventKind::Release && key.code == KeyCode::Char('v') =>
+                {
+                    app.voice.release();
                 }
+                Event::Key(key) if key.kind == KeyEventKind::Release => {}
+                Event::Key(key) if app.handle_key(key) => break,
                 Event::Mouse(mouse) => match mouse.kind {
-                    MouseEventKind::ScrollUp => {
-                        app.scroll = app.scroll.saturating_sub(3);
-                        app.auto_scroll = false;
-                    }
-                    MouseEventKind::ScrollDown => {
-                        app.scroll = app.scroll.saturating_add(3);
-                        // Re-enable auto-scroll if we've reached the bottom
-                        if app.scroll >= app.max_scroll {
-                            app.auto_scroll = true;
-                        }
-                    }
+                    MouseEventKind::ScrollUp => match &mut app.pager {
+                        Some(pager) => pager.scroll_up(3),
+                        None => app.scroll_up(3),
+                    },
+                    MouseEventKind::ScrollDown => match &mut app.pager {
+                        Some(pager) => pager.scroll_down(3),
+                        None => app.scroll_down(3),
+                    },
                     _ => {}
                 },
+                Event::Paste(text) => app.handle_paste(&text),
                 Event::Resize(_, _) => {
                     // Force full redraw after resize
                     terminal.clear()?;
@@ -549,13 +1873,21 @@ pub fn run(
         while let Ok(ev) = app.ui_rx.try_recv() {
             app.handle_ui_event(ev);
         }
+
+        app.save_draft();
     }
 
+    // Whatever is left in the input is kept for next time
+    app.save_draft();
+
     // Cleanup
+    #[cfg(feature = "voice")]
+    voice::disable_key_releases();
     crossterm::terminal::disable_raw_mode()?;
     crossterm::execute!(
         std::io::stdout(),
         crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste,
         crossterm::terminal::LeaveAlternateScreen,
     )?;
 
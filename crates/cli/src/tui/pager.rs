@@ -0,0 +1,268 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+
+use super::theme::Theme;
+
+/// A tool's complete output, scrollable and searchable over the
+/// conversation pane.
+pub struct Pager {
+    title: String,
+    lines: Vec<String>,
+    is_error: bool,
+    /// First line in view.
+    top: usize,
+    /// Rows of output in view, from the last draw.
+    height: usize,
+    /// Search being typed after `/`.
+    input: Option<String>,
+    query: String,
+    /// Lines that match `query`.
+    matches: Vec<usize>,
+    /// Index into `matches` of the match last jumped to.
+    current: Option<usize>,
+}
+
+impl Pager {
+    pub fn new(title: String, output: &str, is_error: bool) -> Self {
+        Self {
+            title,
+            lines: output.lines().map(str::to_string).collect(),
+            is_error,
+            top: 0,
+            height: 1,
+            input: None,
+            query: String::new(),
+            matches: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Returns `true` when the pager should close.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Enter => {
+                    let query = std::mem::take(input);
+                    self.input = None;
+                    self.search(query);
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return false;
+        }
+
+        let page = self.height.saturating_sub(1).max(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter => self.scroll_down(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_up(1),
+            KeyCode::Char(' ') | KeyCode::PageDown => self.scroll_down(page),
+            KeyCode::Char('b') | KeyCode::PageUp => self.scroll_up(page),
+            KeyCode::Char('g') | KeyCode::Home => self.top = 0,
+            KeyCode::Char('G') | KeyCode::End => self.top = self.max_top(),
+            KeyCode::Char('/') => self.input = Some(String::new()),
+            KeyCode::Char('n') => self.next_match(true),
+            KeyCode::Char('N') => self.next_match(false),
+            _ => {}
+        }
+        false
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.top = self.top.saturating_sub(rows);
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.top = (self.top + rows).min(self.max_top());
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    /// Find `query` and jump to its first match at or below the top line.
+    /// Case-insensitive unless the query has capitals.
+    fn search(&mut self, query: String) {
+        let fold = !query.chars().any(char::is_uppercase);
+        let needle = if fold {
+            query.to_lowercase()
+        } else {
+            query.clone()
+        };
+
+        self.matches = if needle.is_empty() {
+            Vec::new()
+        } else {
+            self.lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| match fold {
+                    true => line.to_lowercase().contains(&needle),
+                    false => line.contains(&needle),
+                })
+                .map(|(i, _)| i)
+                .collect()
+        };
+        self.query = query;
+        self.current = None;
+        self.next_match(true);
+    }
+
+    /// Jump to the next match below the top line, or the previous one
+    /// above it, wrapping around.
+    fn next_match(&mut self, forward: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+
+        let len = self.matches.len();
+        let index = match (forward, self.current) {
+            (true, Some(i)) => (i + 1) % len,
+            (false, Some(i)) => (i + len - 1) % len,
+            (true, None) => self
+                .matches
+                .iter()
+                .position(|&line| line >= self.top)
+                .unwrap_or(0),
+            (false, None) => self
+                .matches
+                .iter()
+                .rposition(|&line| line < self.top)
+                .unwrap_or(len - 1),
+        };
+
+        self.current = Some(index);
+        // Keep a little context above the match
+        self.top = self.matches[index].saturating_sub(2).min(self.max_top());
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        self.height = area.height.saturating_sub(2).max(1) as usize;
+        self.top = self.top.min(self.max_top());
+
+        let bar = Style::new().bg(theme.status_bg).fg(theme.status_fg);
+        let last = (self.top + self.height).min(self.lines.len());
+        let position = if self.lines.is_empty() {
+            "empty".to_string()
+        } else {
+            format!("lines {}-{last} of {}", self.top + 1, self.lines.len())
+        };
+        let header = Line::from(vec![
+            Span::styled(format!(" {} ", self.title), bar.bold()),
+            Span::styled(format!("· {position} "), bar),
+        ]);
+
+        let text_style = Style::new().fg(if self.is_error {
+            theme.error
+        } else {
+            theme.tool_output
+        });
+        let current = self.current.map(|i| self.matches[i]);
+        let mut lines = vec![header];
+        for (i, line) in self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(self.top)
+            .take(self.height)
+        {
+            let style = if Some(i) == current {
+                text_style.add_modifier(Modifier::REVERSED)
+            } else if self.matches.binary_search(&i).is_ok() {
+                text_style.add_modifier(Modifier::UNDERLINED)
+            } else {
+                text_style
+            };
+            lines.push(Line::styled(line.replace('\t', "    "), style));
+        }
+        lines.resize(self.height + 1, Line::default());
+
+        let footer = match &self.input {
+            Some(input) => Line::from(format!("/{input}")),
+            None => {
+                let found = match (&self.current, self.matches.len()) {
+                    (Some(i), n) => format!("match {} of {n} · ", i + 1),
+                    (None, _) if !self.query.is_empty() => "no matches · ".to_string(),
+                    _ => String::new(),
+                };
+                Line::styled(
+                    format!("{found}q close · / search · n/N next/previous · j/k, Space/b scroll"),
+                    Style::new().fg(theme.dim),
+                )
+            }
+        };
+        lines.push(footer);
+
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn pager(lines: usize) -> Pager {
+        let output: Vec<String> = (0..lines).map(|i| format!("line {i}")).collect();
+        let mut pager = Pager::new("Bash(seq)".to_string(), &output.join("\n"), false);
+        pager.height = 10;
+        pager
+    }
+
+    fn type_keys(pager: &mut Pager, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            pager.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn scrolling_stops_at_the_last_page() {
+        let mut pager = pager(25);
+        type_keys(&mut pager, "   ");
+        assert_eq!(pager.top, 15);
+        type_keys(&mut pager, "kg");
+        assert_eq!(pager.top, 0);
+        type_keys(&mut pager, "G");
+        assert_eq!(pager.top, 15);
+    }
+
+    #[test]
+    fn search_jumps_between_matches() {
+        let mut pager = pager(100);
+        type_keys(&mut pager, "/LINE 5\n");
+        assert!(pager.matches.is_empty(), "capitals make the search exact");
+
+        type_keys(&mut pager, "/line 5\n");
+        assert_eq!(pager.matches, [5, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59]);
+        assert_eq!(pager.top, 3);
+
+        type_keys(&mut pager, "n");
+        assert_eq!(pager.top, 48);
+        type_keys(&mut pager, "NN");
+        assert_eq!(pager.current, Some(10));
+        assert_eq!(pager.top, 57);
+    }
+
+    #[test]
+    fn q_closes_unless_typing_a_search() {
+        let mut pager = pager(3);
+        let q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+        type_keys(&mut pager, "/");
+        assert!(!pager.handle_key(q));
+        assert!(!pager.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(pager.handle_key(q));
+    }
+}
@@ -0,0 +1,85 @@
+/// Pastes with more lines than this show as a placeholder in the input box.
+const COLLAPSE_LINES: usize = 10;
+
+/// Large pastes waiting in the input box behind `[pasted N lines]`
+/// placeholders. They are expanded back when the input is submitted or
+/// opened in the editor.
+#[derive(Default)]
+pub struct Pastes {
+    /// (placeholder, pasted text) in paste order.
+    entries: Vec<(String, String)>,
+}
+
+impl Pastes {
+    /// Return what to insert at the cursor for a paste: the text itself
+    /// (with line endings normalized), or a placeholder for large pastes.
+    pub fn insert(&mut self, text: &str) -> String {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let lines = text.lines().count();
+
+        if lines <= COLLAPSE_LINES {
+            return text;
+        }
+
+        let placeholder = format!("[pasted {lines} lines]");
+        self.entries.push((placeholder.clone(), text));
+        placeholder
+    }
+
+    /// Replace each placeholder still in `input` with its text, keeping the
+    /// pastes.
+    pub fn expanded(&self, input: &str) -> String {
+        let mut out = input.to_string();
+        for (placeholder, text) in &self.entries {
+            out = out.replacen(placeholder, text, 1);
+        }
+        out
+    }
+
+    /// Replace each placeholder still in `input` with its text, and forget
+    /// all pastes.
+    pub fn expand(&mut self, input: &str) -> String {
+        let mut out = input.to_string();
+
+        // Placeholders may repeat, so match them in paste order
+        for (placeholder, text) in self.entries.drain(..) {
+            out = out.replacen(&placeholder, &text, 1);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_pastes_are_inserted_verbatim() {
+        let mut pastes = Pastes::default();
+
+        assert_eq!(pastes.insert("fn main() {\r\n}\r\n"), "fn main() {\n}\n");
+        assert_eq!(pastes.expand("kept as is"), "kept as is");
+    }
+
+    #[test]
+    fn large_pastes_collapse_and_expand_in_order() {
+        let mut pastes = Pastes::default();
+        let first = "a\n".repeat(12);
+        let second = "b\n".repeat(12);
+
+        let p1 = pastes.insert(&first);
+        let p2 = pastes.insert(&second);
+        assert_eq!(p1, "[pasted 12 lines]");
+        assert_eq!(p1, p2);
+
+        let input = format!("compare {p1} with {p2}");
+        assert_eq!(
+            pastes.expand(&input),
+            format!("compare {first} with {second}")
+        );
+
+        // Expanding forgets the pastes
+        assert_eq!(pastes.expand(&p1), p1);
+    }
+}
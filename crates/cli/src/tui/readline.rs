@@ -0,0 +1,163 @@
+use std::ops::Range;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::vim::{delete, line_bounds};
+
+/// Emacs-style editing keys for the input box. Line motions and kills act
+/// on the cursor's line of a multi-line prompt.
+#[derive(Default)]
+pub struct Readline {
+    /// Text last removed by Ctrl+W, Ctrl+U or Ctrl+K, for Ctrl+Y.
+    killed: String,
+}
+
+impl Readline {
+    /// Apply `key` to the input buffer if it is an editing key. `cursor` is
+    /// a char index. Returns `false` for keys it doesn't handle.
+    pub fn handle(&mut self, key: KeyEvent, input: &mut String, cursor: &mut usize) -> bool {
+        let KeyCode::Char(c) = key.code else {
+            return false;
+        };
+        let chars: Vec<char> = input.chars().collect();
+        let at = (*cursor).min(chars.len());
+        let line = line_bounds(&chars, at);
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            match c {
+                'a' => *cursor = line.start,
+                'e' => *cursor = line.end,
+                'u' => self.kill(input, cursor, line.start..at),
+                // At the end of a line, join the next one
+                'k' if at == line.end && at < chars.len() => self.kill(input, cursor, at..at + 1),
+                'k' => self.kill(input, cursor, at..line.end),
+                'w' => self.kill(input, cursor, word_rubout(&chars, at)..at),
+                'y' => self.yank(input, cursor),
+                _ => return false,
+            }
+        } else if key.modifiers.contains(KeyModifiers::ALT) {
+            match c {
+                'b' => *cursor = word_back(&chars, at),
+                'f' => *cursor = word_forward(&chars, at),
+                _ => return false,
+            }
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    fn kill(&mut self, input: &mut String, cursor: &mut usize, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        self.killed = input.chars().skip(range.start).take(range.len()).collect();
+        *cursor = range.start;
+        delete(input, range);
+    }
+
+    fn yank(&self, input: &mut String, cursor: &mut usize) {
+        let byte = input
+            .char_indices()
+            .nth(*cursor)
+            .map_or(input.len(), |(b, _)| b);
+        input.insert_str(byte, &self.killed);
+        *cursor += self.killed.chars().count();
+    }
+}
+
+/// Ctrl+W: back over whitespace, then to the previous whitespace.
+fn word_rubout(chars: &[char], at: usize) -> usize {
+    let mut i = at;
+    while i > 0 && chars[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    while i > 0 && !chars[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    i
+}
+
+/// Alt+B: the start of this word, or of the previous one.
+fn word_back(chars: &[char], at: usize) -> usize {
+    let mut i = at;
+    while i > 0 && !chars[i - 1].is_alphanumeric() {
+        i -= 1;
+    }
+    while i > 0 && chars[i - 1].is_alphanumeric() {
+        i -= 1;
+    }
+    i
+}
+
+/// Alt+F: the end of this word, or of the next one.
+fn word_forward(chars: &[char], at: usize) -> usize {
+    let mut i = at;
+    while i < chars.len() && !chars[i].is_alphanumeric() {
+        i += 1;
+    }
+    while i < chars.len() && chars[i].is_alphanumeric() {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Press `keys` from `cursor`; return the input and cursor.
+    fn run(input: &str, cursor: usize, keys: &[(KeyModifiers, char)]) -> (String, usize) {
+        let mut readline = Readline::default();
+        let mut input = input.to_string();
+        let mut cursor = cursor;
+        for &(modifiers, c) in keys {
+            let key = KeyEvent::new(KeyCode::Char(c), modifiers);
+            assert!(readline.handle(key, &mut input, &mut cursor));
+        }
+        (input, cursor)
+    }
+
+    const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+    const ALT: KeyModifiers = KeyModifiers::ALT;
+
+    #[test]
+    fn motions() {
+        assert_eq!(run("one\ntwo three", 9, &[(CTRL, 'a')]).1, 4);
+        assert_eq!(run("one\ntwo three", 1, &[(CTRL, 'e')]).1, 3);
+        assert_eq!(run("foo-bar baz", 11, &[(ALT, 'b')]).1, 8);
+        assert_eq!(run("foo-bar baz", 8, &[(ALT, 'b'), (ALT, 'b')]).1, 0);
+        assert_eq!(run("foo-bar baz", 0, &[(ALT, 'f'), (ALT, 'f')]).1, 7);
+    }
+
+    #[test]
+    fn kills_and_yank() {
+        assert_eq!(
+            run("git commit -m", 13, &[(CTRL, 'w')]),
+            ("git commit ".into(), 11)
+        );
+        assert_eq!(
+            run("one\ntwo three", 8, &[(CTRL, 'u')]),
+            ("one\nthree".into(), 4)
+        );
+        assert_eq!(
+            run("one\ntwo", 1, &[(CTRL, 'k'), (CTRL, 'k')]),
+            ("otwo".into(), 1)
+        );
+        assert_eq!(
+            run("hello world", 11, &[(CTRL, 'w'), (CTRL, 'a'), (CTRL, 'y')]),
+            ("worldhello ".into(), 5)
+        );
+    }
+
+    #[test]
+    fn other_keys_pass_through() {
+        let mut input = String::from("abc");
+        let mut cursor = 3;
+        let typed = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE);
+
+        assert!(!Readline::default().handle(typed, &mut input, &mut cursor));
+        assert_eq!(input, "abc");
+    }
+}
@@ -2,11 +2,15 @@ use std::path::Path;
 
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Layout, Rect};
-use ratatui::style::{Color, Style};
+use ratatui::style::Style;
 use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use super::markdown::render_markdown;
+use super::status::Level;
+use super::theme::Theme;
+use super::tree;
 use super::{App, AppState, DisplayMessage};
 
 /// Render the entire UI.
@@ -14,95 +18,150 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     let area = frame.area();
 
     let has_perm = app.pending_perm.is_some();
+    let input_height = input_rows(&app.input) + 1; // + border
+    let todos_height = app.todos.height();
 
     let chunks = if has_perm {
         Layout::vertical([
-            Constraint::Length(1), // status bar
-            Constraint::Min(1),    // messages
-            Constraint::Length(2), // permission prompt
-            Constraint::Length(3), // input area
+            Constraint::Length(1),            // status bar
+            Constraint::Min(1),               // messages
+            Constraint::Length(todos_height), // todo list
+            Constraint::Length(3),            // permission prompt
+            Constraint::Length(input_height), // input area
         ])
         .split(area)
     } else {
         Layout::vertical([
-            Constraint::Length(1), // status bar
-            Constraint::Min(1),    // messages
-            Constraint::Length(0), // no permission prompt
-            Constraint::Length(3), // input area
+            Constraint::Length(1),            // status bar
+            Constraint::Min(1),               // messages
+            Constraint::Length(todos_height), // todo list
+            Constraint::Length(0),            // no permission prompt
+            Constraint::Length(input_height), // input area
         ])
         .split(area)
     };
 
     render_status_bar(app, frame, chunks[0]);
-    render_messages(app, frame, chunks[1]);
+
+    let mut main = chunks[1];
+    if let Some(tree) = &mut app.tree {
+        let [left, right] =
+            Layout::horizontal([Constraint::Length(tree::WIDTH), Constraint::Min(1)]).areas(main);
+        tree.render(frame, left, &app.theme, &app.touched);
+        main = right;
+    }
+
+    match (&mut app.pager, &app.template_picker) {
+        (Some(pager), _) => pager.render(frame, main, &app.theme),
+        (None, Some(picker)) => picker.render(frame, main, &app.theme),
+        (None, None) => render_messages(app, frame, main),
+    }
+
+    if todos_height > 0 {
+        app.todos.render(frame, chunks[2], &app.theme);
+    }
 
     if has_perm {
-        render_permission(app, frame, chunks[2]);
+        render_permission(app, frame, chunks[3]);
     }
 
-    render_input(app, frame, chunks[2 + 1]);
+    render_input(app, frame, chunks[4]);
 }
 
 fn render_status_bar(app: &App, frame: &mut Frame, area: Rect) {
-    let tokens = format!(
-        "{}↑ {}↓",
-        format_tokens(app.usage.input_tokens),
-        format_tokens(app.usage.output_tokens),
-    );
-
-    let bar = Line::from(vec![
-        Span::styled(" claude-code-rs", Style::new().bold()),
-        Span::raw(" │ "),
-        Span::raw(&app.model),
-        Span::raw(" │ "),
-        Span::raw(tokens),
-    ]);
-
-    let widget = Paragraph::new(bar).style(Style::new().bg(Color::DarkGray).fg(Color::White));
+    let theme = &app.theme;
+    let segments = app.status_line.segments(&app.status_info());
+
+    let mut spans = vec![Span::raw(" ")];
+    for (i, segment) in segments.into_iter().enumerate() {
+        let mut style = match segment.level {
+            Level::Normal => Style::new(),
+            Level::Warning => Style::new().fg(theme.warning).bold(),
+            Level::Critical => Style::new().fg(theme.error).bold(),
+        };
+        if i == 0 {
+            style = style.bold();
+        } else {
+            spans.push(Span::raw(app.status_line.separator()));
+        }
+        spans.push(Span::styled(segment.text, style));
+    }
+    let bar = Line::from(spans);
+
+    let widget = Paragraph::new(bar).style(Style::new().bg(theme.status_bg).fg(theme.status_fg));
     frame.render_widget(widget, area);
 }
 
 fn render_messages(app: &mut App, frame: &mut Frame, area: Rect) {
     let mut lines: Vec<Line> = Vec::new();
+    let theme = &app.theme;
+    // Line index of each picture's first row, and the picture
+    let mut pictures = Vec::new();
+    // Line index of each finished tool call's header, and its message
+    let mut tools = Vec::new();
 
-    for msg in &app.messages {
+    for (index, msg) in app.messages.iter().enumerate() {
         match msg {
             DisplayMessage::User(text) => {
                 lines.push(Line::from(vec![
-                    Span::styled("> ", Style::new().fg(Color::Cyan).bold()),
+                    Span::styled("> ", Style::new().fg(theme.user_prompt).bold()),
                     Span::raw(text.as_str()),
                 ]));
                 lines.push(Line::default());
             }
 
             DisplayMessage::AssistantText(text) => {
-                let markdown_lines = render_markdown(text);
+                let markdown_lines = render_markdown(text, theme);
                 lines.extend(markdown_lines);
             }
 
+            DisplayMessage::Thinking(text) => {
+                render_thinking(&mut lines, text, app.show_thinking, theme);
+            }
+
             DisplayMessage::ToolUse {
                 name,
                 input,
                 output,
                 is_error,
+                diff,
             } => {
-                render_tool_block(&mut lines, name, input, output, *is_error, &app.cwd);
+                let tool = ToolCall {
+                    name,
+                    input,
+                    output,
+                    is_error: *is_error,
+                    diff: diff.as_deref(),
+                };
+                if output.is_some() {
+                    tools.push((lines.len(), index));
+                }
+                render_tool_block(&mut lines, &tool, &app.cwd, theme);
+            }
+
+            DisplayMessage::Image(image) => {
+                lines.push(Line::styled(
+                    format!("[image: {}]", image.label),
+                    Style::new().fg(theme.dim),
+                ));
+                if let Some(protocol) = &image.protocol {
+                    pictures.push((lines.len(), protocol));
+                    lines.extend((0..image.rows()).map(|_| Line::default()));
+                }
+                lines.push(Line::default());
             }
 
             DisplayMessage::Error(text) => {
                 lines.push(Line::styled(
                     format!("Error: {text}"),
-                    Style::new().fg(Color::Red),
+                    Style::new().fg(theme.error),
                 ));
                 lines.push(Line::default());
             }
 
             DisplayMessage::Info(text) => {
                 for line in text.lines() {
-                    lines.push(Line::styled(
-                        line.to_string(),
-                        Style::new().fg(Color::DarkGray),
-                    ));
+                    lines.push(Line::styled(line.to_string(), Style::new().fg(theme.dim)));
                 }
 
                 lines.push(Line::default());
@@ -113,9 +172,10 @@ fn render_messages(app: &mut App, frame: &mut Frame, area: Rect) {
     let content_height = wrapped_line_count(&lines, area.width);
 
     // Pad with empty lines so content is bottom-aligned
+    let mut padding = 0;
     if content_height < area.height {
-        let padding = area.height - content_height;
-        let mut padded = vec![Line::default(); padding as usize];
+        padding = (area.height - content_height) as usize;
+        let mut padded = vec![Line::default(); padding];
         padded.append(&mut lines);
         lines = padded;
     }
@@ -125,6 +185,7 @@ fn render_messages(app: &mut App, frame: &mut Frame, area: Rect) {
 
     // Store max_scroll for scroll event handling
     app.max_scroll = max_scroll;
+    app.page_height = area.height;
 
     let scroll = if app.auto_scroll {
         max_scroll
@@ -132,43 +193,164 @@ fn render_messages(app: &mut App, frame: &mut Frame, area: Rect) {
         app.scroll.min(max_scroll)
     };
 
+    // Rows from the top of the content where each picture starts
+    let picture_rows: Vec<_> = pictures
+        .into_iter()
+        .map(|(index, protocol)| {
+            let row = wrapped_line_count(&lines[..index + padding], area.width);
+            (row, protocol)
+        })
+        .collect();
+
+    // Ctrl+O opens the lowest tool result that starts in view, or the
+    // last one above it
+    app.focused_tool = None;
+    let (mut row, mut counted) = (0, 0);
+    for (line, message) in tools {
+        row += wrapped_line_count(&lines[counted..line + padding], area.width);
+        counted = line + padding;
+        if row >= scroll + area.height {
+            break;
+        }
+        app.focused_tool = Some(message);
+    }
+
     let paragraph = Paragraph::new(Text::from(lines))
         .scroll((scroll, 0))
         .wrap(Wrap { trim: false });
 
     frame.render_widget(paragraph, area);
+
+    if scroll < max_scroll {
+        render_more_below(frame, area, max_scroll - scroll, &app.theme);
+    }
+
+    // Pictures are drawn over their blank rows, only when fully in view
+    for (row, protocol) in picture_rows {
+        let size = protocol.area();
+        if row < scroll || row + size.height > scroll + area.height {
+            continue;
+        }
+        let target = Rect {
+            x: area.x,
+            y: area.y + row - scroll,
+            width: size.width.min(area.width),
+            height: size.height,
+        };
+        frame.render_widget(ratatui_image::Image::new(protocol), target);
+    }
+}
+
+/// Bottom-right note that the view is not following the output.
+fn render_more_below(frame: &mut Frame, area: Rect, rows: u16, theme: &Theme) {
+    let plural = if rows == 1 { "" } else { "s" };
+    let note = format!(" ↓ {rows} more line{plural} · End to follow ");
+    let width = (note.width() as u16).min(area.width);
+
+    let target = Rect {
+        x: area.x + area.width - width,
+        y: area.y + area.height.saturating_sub(1),
+        width,
+        height: 1.min(area.height),
+    };
+    let widget = Paragraph::new(note).style(Style::new().bg(theme.status_bg).fg(theme.status_fg));
+    frame.render_widget(widget, target);
 }
 
 fn render_permission(app: &App, frame: &mut Frame, area: Rect) {
+    let theme = &app.theme;
     if let Some(perm) = &app.pending_perm {
         let line = Line::from(vec![
             Span::styled(
                 " ? ",
-                Style::new().fg(Color::Black).bg(Color::Yellow).bold(),
+                Style::new()
+                    .fg(theme.permission_fg)
+                    .bg(theme.permission_bg)
+                    .bold(),
             ),
             Span::raw(" "),
             Span::raw(&perm.description),
-            Span::styled("  [Y/n]", Style::new().fg(Color::DarkGray)),
         ]);
 
+        let key = |k: &'static str| Span::styled(k, Style::new().fg(theme.permission_bg).bold());
+        let dim = Style::new().fg(theme.dim);
+        let options = match &perm.feedback {
+            Some(feedback) => Line::from(vec![
+                Span::raw("   Tell Claude why: "),
+                Span::raw(feedback.as_str()),
+                Span::styled("▌", dim),
+                Span::styled("  Enter to send · Esc to go back", dim),
+            ]),
+            None => {
+                let mut spans = vec![Span::raw("   "), key("y"), Span::raw(" allow once  ")];
+                if let Some(rule) = &perm.rule {
+                    spans.extend([key("a"), Span::raw(format!(" always allow {rule}  "))]);
+                }
+                spans.extend([
+                    key("n"),
+                    Span::raw(" deny  "),
+                    key("f"),
+                    Span::raw(" deny with feedback"),
+                ]);
+                Line::from(spans)
+            }
+        };
+
         let block = Block::default()
             .borders(Borders::TOP)
-            .border_style(Style::new().fg(Color::Yellow));
+            .border_style(Style::new().fg(theme.permission_bg));
 
-        let widget = Paragraph::new(line).block(block);
+        let widget = Paragraph::new(vec![line, options]).block(block);
         frame.render_widget(widget, area);
     }
 }
 
-fn render_tool_block<'a>(
-    lines: &mut Vec<Line<'a>>,
+/// A `DisplayMessage::ToolUse`, borrowed for rendering.
+struct ToolCall<'a> {
     name: &'a str,
-    input: &Option<serde_json::Value>,
-    output: &Option<String>,
+    input: &'a Option<serde_json::Value>,
+    output: &'a Option<String>,
     is_error: bool,
+    diff: Option<&'a [Line<'static>]>,
+}
+
+/// Dimmed reasoning: one summary line, or every line when expanded.
+fn render_thinking<'a>(lines: &mut Vec<Line<'a>>, text: &'a str, expanded: bool, theme: &Theme) {
+    let style = Style::new().fg(theme.dim).italic();
+    let text = text.trim();
+    let count = text.lines().count();
+
+    if expanded {
+        lines.push(Line::styled("✻ Thinking · Ctrl+R to collapse", style));
+        for line in text.lines() {
+            lines.push(Line::styled(format!("  {line}"), style));
+        }
+    } else {
+        let plural = if count == 1 { "" } else { "s" };
+        lines.push(Line::styled(
+            format!("✻ Thinking ({count} line{plural}) · Ctrl+R to expand"),
+            style,
+        ));
+    }
+    lines.push(Line::default());
+}
+
+fn render_tool_block<'a>(
+    lines: &mut Vec<Line<'a>>,
+    tool: &ToolCall<'a>,
     cwd: &Path,
+    theme: &Theme,
 ) {
-    let border = Style::new().fg(Color::DarkGray);
+    const MAX_DIFF_LINES: usize = 40;
+
+    let border = Style::new().fg(theme.border);
+    let ToolCall {
+        name,
+        input,
+        output,
+        is_error,
+        diff,
+    } = *tool;
 
     // Format header + input based on tool type
     let (header, display) = match input {
@@ -179,7 +361,7 @@ fn render_tool_block<'a>(
     // Header
     lines.push(Line::from(vec![
         Span::styled("┌ ", border),
-        Span::styled(header, Style::new().fg(Color::Yellow).bold()),
+        Span::styled(header, Style::new().fg(theme.tool_header).bold()),
         Span::styled(" ─".to_string() + &"─".repeat(20), border),
     ]));
 
@@ -187,11 +369,11 @@ fn render_tool_block<'a>(
     if let Some(display) = &display {
         for line in display.lines() {
             let style = if line.starts_with("- ") {
-                Style::new().fg(Color::Red)
+                Style::new().fg(theme.diff_delete)
             } else if line.starts_with("+ ") {
-                Style::new().fg(Color::Green)
+                Style::new().fg(theme.diff_insert)
             } else {
-                Style::new().fg(Color::White)
+                Style::new().fg(theme.text)
             };
 
             lines.push(Line::from(vec![
@@ -201,12 +383,31 @@ fn render_tool_block<'a>(
         }
     }
 
+    // Edit/Write diff
+    if let Some(diff) = diff {
+        for line in diff.iter().take(MAX_DIFF_LINES) {
+            let mut spans = vec![Span::styled("│ ", border)];
+            spans.extend(line.spans.iter().cloned());
+            lines.push(Line::from(spans));
+        }
+
+        if diff.len() > MAX_DIFF_LINES {
+            lines.push(Line::from(vec![
+                Span::styled("│ ", border),
+                Span::styled(
+                    format!("... ({} more lines)", diff.len() - MAX_DIFF_LINES),
+                    Style::new().fg(theme.dim).italic(),
+                ),
+            ]));
+        }
+    }
+
     // Output
     if let Some(output) = output {
         let style = if is_error {
-            Style::new().fg(Color::Red)
+            Style::new().fg(theme.error)
         } else {
-            Style::new().fg(Color::DarkGray)
+            Style::new().fg(theme.tool_output)
         };
 
         let cwd_prefix = format!("{}/", cwd.display());
@@ -229,7 +430,7 @@ fn render_tool_block<'a>(
                 Span::styled("│ ", border),
                 Span::styled(
                     format!("... ({total} lines total)"),
-                    Style::new().fg(Color::DarkGray).italic(),
+                    Style::new().fg(theme.dim).italic(),
                 ),
             ]));
         }
@@ -243,6 +444,11 @@ fn render_tool_block<'a>(
 // Tool display formatting
 // ---------------------------------------------------------------------------
 
+/// One-line description of a tool call, as in its block's header.
+pub fn tool_header(name: &str, input: &serde_json::Value, cwd: &Path) -> String {
+    format_tool_display(name, input, cwd).0
+}
+
 /// Returns (header, optional body) for the tool block.
 fn format_tool_display(
     name: &str,
@@ -269,10 +475,7 @@ fn format_tool_display(
 
         "Edit" => {
             let path = relative_path(str_field(input, "file_path"), cwd);
-            let old = str_field(input, "old_string");
-            let new = str_field(input, "new_string");
-            let body = format_edit_diff(old, new);
-            (format!("Edit {path}"), Some(body))
+            (format!("Edit {path}"), None)
         }
 
         "Glob" => {
@@ -408,6 +611,28 @@ fn format_tool_display(
             (format!("Search \"{query}\""), None)
         }
 
+        "Test" => {
+            let failed_only = input
+                .get("failed_only")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let header = match input.get("filter").and_then(|v| v.as_str()) {
+                _ if failed_only => "Test (failed only)".to_string(),
+                Some(filter) => format!("Test \"{filter}\""),
+                None => "Test".to_string(),
+            };
+            (header, None)
+        }
+
+        "TodoWrite" => {
+            let count = input
+                .get("todos")
+                .and_then(|v| v.as_array())
+                .map_or(0, |a| a.len());
+            let plural = if count == 1 { "" } else { "s" };
+            (format!("TodoWrite ({count} item{plural})"), None)
+        }
+
         _ => {
             let body = serde_json::to_string_pretty(input).unwrap_or_default();
             (name.to_string(), Some(body))
@@ -415,26 +640,6 @@ fn format_tool_display(
     }
 }
 
-/// Format an Edit diff: lines prefixed with - and +.
-fn format_edit_diff(old: &str, new: &str) -> String {
-    let mut out = String::new();
-
-    for line in old.lines() {
-        out.push_str(&format!("- {line}\n"));
-    }
-
-    for line in new.lines() {
-        out.push_str(&format!("+ {line}\n"));
-    }
-
-    // Remove trailing newline
-    if out.ends_with('\n') {
-        out.pop();
-    }
-
-    out
-}
-
 /// Make a path relative to cwd if it's inside it, otherwise return as-is.
 fn relative_path(path: &str, cwd: &Path) -> String {
     let p = Path::new(path);
@@ -453,44 +658,180 @@ fn str_field<'a>(input: &'a serde_json::Value, key: &str) -> &'a str {
 fn render_input(app: &App, frame: &mut Frame, area: Rect) {
     const SPINNER: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
-    let prompt = if app.state == AppState::Busy {
-        let frame_char = SPINNER[app.spinner_frame % SPINNER.len()];
-        format!("{frame_char} {}", app.input)
+    let marker = if app.state == AppState::Busy {
+        SPINNER[app.spinner_frame % SPINNER.len()]
     } else {
-        format!("> {}", app.input)
+        ">"
     };
 
-    let block = Block::default()
+    // Continuation lines are indented under the first one
+    let lines: Vec<Line> = app
+        .input
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => Line::from(format!("{marker} {line}")),
+            _ => Line::from(format!("  {line}")),
+        })
+        .collect();
+
+    // Keep the cursor's line visible when the input is taller than the box
+    let (row, col) = cursor_position(&app.input, app.cursor);
+    let visible = area.height.saturating_sub(1).max(1);
+    let offset = row.saturating_sub(visible - 1);
+
+    let mut block = Block::default()
         .borders(Borders::TOP)
-        .border_style(Style::new().fg(Color::DarkGray));
+        .border_style(Style::new().fg(app.theme.border));
+    if app.state == AppState::Busy {
+        let summary = format!(" {} ", app.activity.summary());
+        block = block.title(Span::styled(summary, Style::new().fg(app.theme.dim)));
+    }
+    #[cfg(feature = "voice")]
+    if let Some(status) = app.voice.status() {
+        let status = format!(" {status} ");
+        let room = (area.width as usize).saturating_sub(status.width() + 4);
+        block = block.title(Span::styled(status, Style::new().fg(app.theme.warning)));
+        // Live transcription: the latest words, as they come back
+        if let Some(partial) = app.voice.partial()
+            && room > 10
+        {
+            let partial = format!(" {} ", super::voice::tail(&partial, room - 2));
+            block = block.title(Span::styled(partial, Style::new().fg(app.theme.text)));
+        }
+    }
 
-    let input_widget = Paragraph::new(prompt).block(block);
+    let input_widget = Paragraph::new(lines).block(block).scroll((offset, 0));
     frame.render_widget(input_widget, area);
 
-    // Position cursor: area.x + 2 (prompt + space) + cursor offset, area.y + 1 (border)
-    let cursor_x = area.x + 2 + app.cursor as u16;
-    let cursor_y = area.y + 1;
+    // Position cursor: area.x + 2 (prompt + space) + column, area.y + 1 (border) + row
+    let cursor_x = area.x + 2 + col;
+    let cursor_y = area.y + 1 + row - offset;
     frame.set_cursor_position((cursor_x, cursor_y));
 }
 
-fn format_tokens(n: u64) -> String {
-    if n >= 1000 {
-        format!("{:.1}k", n as f64 / 1000.0)
-    } else {
-        n.to_string()
-    }
+/// Rows the input box needs, capped so a long prompt doesn't hide the
+/// conversation.
+fn input_rows(input: &str) -> u16 {
+    const MAX_INPUT_ROWS: usize = 10;
+
+    input.split('\n').count().min(MAX_INPUT_ROWS) as u16
 }
 
-/// Estimate total visual lines after wrapping.
-fn wrapped_line_count(lines: &[Line], width: u16) -> u16 {
-    let w = width.max(1) as usize;
+/// Line and display column of the `cursor`-th character of `input`. Wide
+/// characters (CJK, most emoji) take two columns.
+fn cursor_position(input: &str, cursor: usize) -> (u16, u16) {
+    let before: String = input.chars().take(cursor).collect();
+    let row = before.matches('\n').count();
+    let col = before.rsplit('\n').next().unwrap_or("").width();
 
+    (row as u16, col as u16)
+}
+
+/// Total rows `lines` take when word-wrapped to `width` columns.
+fn wrapped_line_count(lines: &[Line], width: u16) -> u16 {
     lines
         .iter()
-        .map(|line| {
-            let lw = line.width();
-
-            if lw == 0 { 1u16 } else { lw.div_ceil(w) as u16 }
-        })
+        .map(|line| wrapped_rows(line, width.max(1) as usize))
         .sum()
 }
+
+/// Rows one line takes, wrapping like the paragraph widget: words move to
+/// the next row whole unless they are wider than a row, and a wide
+/// character never straddles two rows.
+fn wrapped_rows(line: &Line, width: usize) -> u16 {
+    let (mut rows, mut col) = (1, 0);
+    let mut word = Vec::new();
+
+    for c in line.spans.iter().flat_map(|span| span.content.chars()) {
+        let w = c.width().unwrap_or(0);
+        if c.is_whitespace() {
+            place_word(&mut word, width, &mut rows, &mut col);
+            // Spaces at a row boundary are dropped rather than wrapped
+            if col + w <= width {
+                col += w;
+            }
+        } else {
+            word.push(w);
+        }
+    }
+    place_word(&mut word, width, &mut rows, &mut col);
+
+    rows
+}
+
+/// Put a word's characters, given by width, after column `col`.
+fn place_word(word: &mut Vec<usize>, width: usize, rows: &mut u16, col: &mut usize) {
+    let total: usize = word.iter().sum();
+    if *col > 0 && *col + total > width && total <= width {
+        *rows += 1;
+        *col = 0;
+    }
+    for w in word.drain(..) {
+        if *col + w > width {
+            *rows += 1;
+            *col = 0;
+        }
+        *col += w;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_position_follows_newlines() {
+        let input = "first\nsecond line\n";
+
+        assert_eq!(cursor_position(input, 0), (0, 0));
+        assert_eq!(cursor_position(input, 5), (0, 5));
+        assert_eq!(cursor_position(input, 9), (1, 3));
+        assert_eq!(cursor_position(input, 18), (2, 0));
+        assert_eq!(input_rows(input), 3);
+    }
+
+    #[test]
+    fn cursor_position_counts_wide_characters() {
+        assert_eq!(cursor_position("日本語 ok", 3), (0, 6));
+        assert_eq!(cursor_position("a🙂\nb", 2), (0, 3));
+    }
+
+    #[test]
+    fn wrapping_keeps_words_and_wide_characters_whole() {
+        let rows = |text: &'static str, width| wrapped_line_count(&[Line::from(text)], width);
+
+        assert_eq!(rows("", 10), 1);
+        assert_eq!(rows("hello", 5), 1);
+        assert_eq!(rows("aa bbbb cc", 6), 3);
+        assert_eq!(rows("abcdefghij", 4), 3);
+        // Two columns each: the third row starts at 世
+        assert_eq!(rows("你好世", 3), 3);
+        assert_eq!(rows("你好世界", 4), 2);
+    }
+
+    #[test]
+    fn thinking_is_one_line_until_expanded() {
+        let theme = Theme::default();
+        let text = "First, read the file.\nThen edit it.\n";
+        let shown = |expanded| {
+            let mut lines = Vec::new();
+            render_thinking(&mut lines, text, expanded, &theme);
+            lines.iter().map(|l| l.to_string()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            shown(false),
+            ["✻ Thinking (2 lines) · Ctrl+R to expand", ""]
+        );
+        assert_eq!(
+            shown(true),
+            [
+                "✻ Thinking · Ctrl+R to collapse",
+                "  First, read the file.",
+                "  Then edit it.",
+                "",
+            ]
+        );
+    }
+}
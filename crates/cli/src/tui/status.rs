@@ -0,0 +1,455 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use claude_code_core::api::{AVAILABLE_MODELS, CONTEXT_WINDOW, TurnUsage, Usage};
+use claude_code_core::config::StatusLineSetting;
+use claude_code_core::permission::PermissionMode;
+use claude_code_core::pricing;
+
+use super::UiEvent;
+use super::vim::Mode;
+
+const DEFAULT_SEGMENTS: &[&str] = &[
+    "claude-code-rs",
+    "profile",
+    "model",
+    "tokens",
+    "context",
+    "vim",
+];
+const DEFAULT_SEPARATOR: &str = " │ ";
+
+/// Context use (percent) from which the gauge turns amber, then red.
+pub const CONTEXT_WARNING: u64 = 70;
+pub const CONTEXT_CRITICAL: u64 = 90;
+
+/// Longer-running status commands are killed.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Everything the status bar can show.
+pub struct StatusInfo<'a> {
+    pub model: &'a str,
+    pub profile: Option<&'a str>,
+    pub branch: Option<&'a str>,
+    pub cwd: &'a Path,
+    pub permission_mode: PermissionMode,
+    pub usage: &'a Usage,
+    pub turns: &'a [TurnUsage],
+    pub context_tokens: u64,
+    pub vim: Option<Mode>,
+}
+
+impl StatusInfo<'_> {
+    /// The value of a `{placeholder}`; empty when there is nothing to show.
+    fn value(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "model" => self.model.to_string(),
+            "profile" => self.profile.unwrap_or_default().to_string(),
+            "branch" => self.branch.unwrap_or_default().to_string(),
+            "cwd" => tilde(self.cwd),
+            "mode" => self.permission_mode.to_string(),
+            "tokens" => format!(
+                "{}↑ {}↓",
+                format_tokens(self.usage.input_tokens),
+                format_tokens(self.usage.output_tokens),
+            ),
+            "context" if self.context_tokens > 0 => format!("{}%", self.context_percent()),
+            "context" => String::new(),
+            "cost" => self
+                .cost()
+                .map(|cost| format!("${cost:.2}"))
+                .unwrap_or_default(),
+            "vim" => match self.vim {
+                Some(Mode::Normal) => "NORMAL".to_string(),
+                Some(Mode::Insert) => "INSERT".to_string(),
+                None => String::new(),
+            },
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    pub fn context_percent(&self) -> u64 {
+        self.context_tokens * 100 / CONTEXT_WINDOW
+    }
+
+    /// How close the context is to full.
+    fn context_level(&self) -> Level {
+        match self.context_percent() {
+            p if p >= CONTEXT_CRITICAL => Level::Critical,
+            p if p >= CONTEXT_WARNING => Level::Warning,
+            _ => Level::Normal,
+        }
+    }
+
+    /// Estimated cost so far; `None` before the first turn or with a model
+    /// without known pricing.
+    fn cost(&self) -> Option<f64> {
+        if self.turns.is_empty() {
+            return None;
+        }
+        self.turns
+            .iter()
+            .map(|turn| pricing::cost(&turn.model, &turn.usage))
+            .sum()
+    }
+
+    /// What an external status command receives on stdin. The shape follows
+    /// Claude Code's, so existing scripts keep working.
+    fn to_json(&self) -> serde_json::Value {
+        let display_name = AVAILABLE_MODELS
+            .iter()
+            .find(|(id, _)| *id == self.model)
+            .map_or(self.model, |(_, label)| label);
+
+        serde_json::json!({
+            "model": { "id": self.model, "display_name": display_name },
+            "cwd": self.cwd,
+            "workspace": { "current_dir": self.cwd, "project_dir": self.cwd },
+            "cost": { "total_cost_usd": self.cost().unwrap_or(0.0) },
+            "context": { "used_tokens": self.context_tokens, "window_tokens": CONTEXT_WINDOW },
+            "permission_mode": self.permission_mode.to_string(),
+            "profile": self.profile,
+            "git_branch": self.branch,
+            "version": env!("CARGO_PKG_VERSION"),
+        })
+    }
+}
+
+/// How a segment is colored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Normal,
+    Warning,
+    Critical,
+}
+
+/// One piece of the status bar.
+#[derive(Debug, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    pub level: Level,
+}
+
+/// The status bar layout from the `statusLine` setting, and the latest
+/// output of its command.
+pub struct StatusLine {
+    segments: Vec<String>,
+    separator: String,
+    command: Option<String>,
+    output: Option<String>,
+    /// Where command output is sent; `None` until the TUI is running.
+    tx: Option<mpsc::UnboundedSender<UiEvent>>,
+}
+
+impl Default for StatusLine {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl StatusLine {
+    pub fn new(
+        setting: Option<&StatusLineSetting>,
+        tx: Option<mpsc::UnboundedSender<UiEvent>>,
+    ) -> Self {
+        let setting = setting.cloned().unwrap_or_default();
+        Self {
+            segments: setting
+                .segments
+                .unwrap_or_else(|| DEFAULT_SEGMENTS.iter().map(|s| s.to_string()).collect()),
+            separator: setting
+                .separator
+                .unwrap_or_else(|| DEFAULT_SEPARATOR.to_string()),
+            command: setting.command,
+            output: None,
+            tx,
+        }
+    }
+
+    /// Whether a segment shows the git branch, so it is worth looking up.
+    #[cfg(feature = "git")]
+    pub fn wants_branch(&self) -> bool {
+        self.command.is_some() || self.segments.iter().any(|s| s.contains("branch"))
+    }
+
+    /// The segments to draw, in order. With a command, its latest output is
+    /// the only segment.
+    pub fn segments(&self, info: &StatusInfo) -> Vec<Segment> {
+        if self.command.is_some() {
+            return self
+                .output
+                .iter()
+                .map(|text| Segment {
+                    text: text.clone(),
+                    level: Level::Normal,
+                })
+                .collect();
+        }
+        self.segments
+            .iter()
+            .filter_map(|segment| expand(segment, info))
+            .collect()
+    }
+
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    /// Re-run the status command, if any, in the background. Its output
+    /// arrives as [`UiEvent::StatusLine`].
+    pub fn refresh(&self, info: &StatusInfo) {
+        let (Some(command), Some(tx)) = (self.command.clone(), self.tx.clone()) else {
+            return;
+        };
+        let input = info.to_json().to_string();
+        let cwd = info.cwd.to_path_buf();
+
+        tokio::spawn(async move {
+            match run_command(&command, &input, &cwd).await {
+                Ok(line) => {
+                    let _ = tx.send(UiEvent::StatusLine(line));
+                }
+                Err(e) => tracing::debug!("status line command failed: {e:#}"),
+            }
+        });
+    }
+
+    pub fn set_output(&mut self, line: String) {
+        self.output = Some(line);
+    }
+}
+
+/// A segment is a placeholder name (`model`), or text with `{placeholders}`
+/// (`"⎇ {branch}"`). Segments whose placeholders are all empty are hidden.
+/// Segments showing the context are colored by how full it is.
+fn expand(segment: &str, info: &StatusInfo) -> Option<Segment> {
+    let level = if segment.contains("context") {
+        info.context_level()
+    } else {
+        Level::Normal
+    };
+    expand_text(segment, info).map(|text| Segment { text, level })
+}
+
+fn expand_text(segment: &str, info: &StatusInfo) -> Option<String> {
+    // The bare name gets a gauge: "context: 34% (54k/200k)"
+    if segment == "context" {
+        return (info.context_tokens > 0).then(|| {
+            format!(
+                "context: {}% ({}/{})",
+                info.context_percent(),
+                format_tokens(info.context_tokens),
+                format_tokens(CONTEXT_WINDOW),
+            )
+        });
+    }
+    if let Some(value) = info.value(segment) {
+        return (!value.is_empty()).then_some(value);
+    }
+
+    let mut out = String::new();
+    let mut placeholders = 0;
+    let mut filled = 0;
+    let mut rest = segment;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find('}')
+            .and_then(|close| Some((close, info.value(&after[..close])?)));
+
+        match value {
+            Some((close, value)) => {
+                placeholders += 1;
+                if !value.is_empty() {
+                    filled += 1;
+                }
+                out.push_str(&value);
+                rest = &after[close + 1..];
+            }
+            // Not a placeholder: keep the brace
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+
+    (placeholders == 0 || filled > 0).then_some(out)
+}
+
+/// Run `command` with the session state on stdin and return the first line
+/// it prints, without escape sequences.
+async fn run_command(command: &str, input: &str, cwd: &Path) -> Result<String> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start the status line command")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores stdin may exit before reading it
+        let _ = stdin.write_all(input.as_bytes()).await;
+    }
+
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, child.wait_with_output())
+        .await
+        .context("The status line command timed out")??;
+    if !output.status.success() {
+        bail!("The status line command exited with {}", output.status);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(strip_ansi(stdout.lines().next().unwrap_or_default()))
+}
+
+/// Remove ANSI escape sequences (colors, cursor movement) from `text`.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        // CSI sequences end with a letter; others are a single char
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// `path` with the home directory shortened to `~`.
+fn tilde(path: &Path) -> String {
+    if let Some(home) = std::env::var_os("HOME")
+        && let Ok(rest) = path.strip_prefix(&home)
+    {
+        if rest.as_os_str().is_empty() {
+            return "~".to_string();
+        }
+        return format!("~/{}", rest.display());
+    }
+    path.display().to_string()
+}
+
+pub fn format_tokens(n: u64) -> String {
+    if n >= 100_000 {
+        format!("{}k", n / 1000)
+    } else if n >= 1000 {
+        format!("{:.1}k", n as f64 / 1000.0)
+    } else {
+        n.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info<'a>(usage: &'a Usage, turns: &'a [TurnUsage]) -> StatusInfo<'a> {
+        StatusInfo {
+            model: "claude-sonnet-4-5",
+            profile: None,
+            branch: Some("main"),
+            cwd: Path::new("/work/app"),
+            permission_mode: PermissionMode::Default,
+            usage,
+            turns,
+            context_tokens: 50_000,
+            vim: None,
+        }
+    }
+
+    fn text(segment: &str, info: &StatusInfo) -> Option<String> {
+        expand(segment, info).map(|s| s.text)
+    }
+
+    #[test]
+    fn names_and_templates_expand() {
+        let usage = Usage::default();
+        let info = info(&usage, &[]);
+
+        assert_eq!(text("model", &info).as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(text("⎇ {branch}", &info).as_deref(), Some("⎇ main"));
+        assert_eq!(
+            text("{context} of context", &info).as_deref(),
+            Some("25% of context")
+        );
+        assert_eq!(text("plain text", &info).as_deref(), Some("plain text"));
+        assert_eq!(text("{unknown}", &info).as_deref(), Some("{unknown}"));
+    }
+
+    #[test]
+    fn empty_segments_are_hidden() {
+        let usage = Usage::default();
+        let info = info(&usage, &[]);
+
+        assert_eq!(text("profile", &info), None);
+        assert_eq!(text("vim", &info), None);
+        assert_eq!(text("cost: {cost}", &info), None);
+    }
+
+    #[test]
+    fn context_gauge_warns_near_the_limit() {
+        let usage = Usage::default();
+        let mut info = info(&usage, &[]);
+
+        info.context_tokens = 68_000;
+        assert_eq!(
+            expand("context", &info),
+            Some(Segment {
+                text: "context: 34% (68.0k/200k)".to_string(),
+                level: Level::Normal,
+            })
+        );
+
+        info.context_tokens = 150_000;
+        assert_eq!(expand("context", &info).unwrap().level, Level::Warning);
+
+        info.context_tokens = 185_000;
+        assert_eq!(expand("{context}", &info).unwrap().level, Level::Critical);
+
+        info.context_tokens = 0;
+        assert_eq!(expand("context", &info), None);
+    }
+
+    #[test]
+    fn cost_sums_turns() {
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            ..Usage::default()
+        };
+        let turns = [TurnUsage {
+            model: "claude-sonnet-4-5".to_string(),
+            usage,
+        }];
+        let info = info(&usage, &turns);
+
+        assert_eq!(text("cost", &info).as_deref(), Some("$3.00"));
+    }
+
+    #[test]
+    fn command_output_loses_escape_sequences() {
+        assert_eq!(strip_ansi("\x1b[1;32mmain\x1b[0m ok"), "main ok");
+    }
+}
@@ -0,0 +1,209 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+
+use claude_code_core::templates::Template;
+
+use super::theme::Theme;
+
+/// Lines of the selected template's body previewed under the list.
+const PREVIEW_LINES: usize = 8;
+
+/// What the app should do after a key went to the picker.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    None,
+    Close,
+    /// Replace the input with this `/t` command, cursor at the char index.
+    Insert(String, usize),
+}
+
+/// Prompt templates to choose from, over the conversation pane. Typing
+/// filters them by name.
+pub struct TemplatePicker {
+    templates: Vec<Template>,
+    filter: String,
+    /// Index into the filtered templates.
+    selected: usize,
+}
+
+impl TemplatePicker {
+    pub fn new(templates: Vec<Template>) -> Self {
+        Self {
+            templates,
+            filter: String::new(),
+            selected: 0,
+        }
+    }
+
+    fn visible(&self) -> Vec<&Template> {
+        let filter = self.filter.to_lowercase();
+        self.templates
+            .iter()
+            .filter(|t| t.name.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let count = self.visible().len();
+
+        match key.code {
+            KeyCode::Esc => return Action::Close,
+            KeyCode::Enter => {
+                return match self.visible().get(self.selected) {
+                    Some(template) => command(template),
+                    None => Action::None,
+                };
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Char('n') if ctrl => {
+                self.selected = (self.selected + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.filter.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let bar = Style::new().bg(theme.status_bg).fg(theme.status_fg);
+        let visible = self.visible();
+        let height = area.height as usize;
+        let list_height = height.saturating_sub(PREVIEW_LINES + 4).max(1);
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled(" Templates ", bar.bold()),
+            Span::styled(
+                format!("· {} of {} ", visible.len(), self.templates.len()),
+                bar,
+            ),
+        ])];
+
+        let top = (self.selected + 1).saturating_sub(list_height);
+        for (i, template) in visible.iter().enumerate().skip(top).take(list_height) {
+            let mut style = Style::new().fg(theme.text);
+            if i == self.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            let mut spans = vec![Span::raw("  "), Span::styled(template.name.clone(), style)];
+            if let Some(description) = &template.description {
+                spans.push(Span::styled(
+                    format!(" — {description}"),
+                    Style::new().fg(theme.dim),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+        if visible.is_empty() {
+            lines.push(Line::styled(
+                "  No template matches.",
+                Style::new().fg(theme.dim),
+            ));
+        }
+        lines.resize(list_height + 1, Line::default());
+
+        if let Some(template) = visible.get(self.selected) {
+            lines.push(Line::styled(
+                template.usage(),
+                Style::new().fg(theme.tool_header).bold(),
+            ));
+            for line in template.body.lines().take(PREVIEW_LINES) {
+                lines.push(Line::styled(
+                    format!("  {line}"),
+                    Style::new().fg(theme.tool_output),
+                ));
+            }
+        }
+        lines.resize(height.saturating_sub(1), Line::default());
+
+        let filter = match self.filter.as_str() {
+            "" => String::new(),
+            filter => format!("filter: {filter} · "),
+        };
+        lines.push(Line::styled(
+            format!("{filter}↑/↓ select · Enter use · Esc close · type to filter"),
+            Style::new().fg(theme.dim),
+        ));
+
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+}
+
+/// `/t <name>` with an empty `arg=` for each required placeholder, the
+/// cursor after the first.
+fn command(template: &Template) -> Action {
+    let mut text = format!("/t {}", template.name);
+    let mut cursor = None;
+    for placeholder in template.placeholders() {
+        if placeholder.default.is_none() {
+            text.push_str(&format!(" {}=", placeholder.name));
+            cursor.get_or_insert(text.chars().count());
+        }
+    }
+    text.push(' ');
+    let end = text.chars().count();
+    Action::Insert(text, cursor.unwrap_or(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn picker() -> TemplatePicker {
+        let template = |name: &str, body: &str| Template::parse(body, Path::new(name)).unwrap();
+        TemplatePicker::new(vec![
+            template("release-notes.md", "Notes for v{{version}} on {{date}}"),
+            template("review.md", "Review {{path:file=.}}"),
+        ])
+    }
+
+    fn press(picker: &mut TemplatePicker, code: KeyCode) -> Action {
+        picker.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn enter_inserts_the_command_with_required_args() {
+        let mut picker = picker();
+        assert_eq!(
+            press(&mut picker, KeyCode::Enter),
+            Action::Insert("/t release-notes version= date= ".to_string(), 25)
+        );
+
+        press(&mut picker, KeyCode::Down);
+        assert_eq!(
+            press(&mut picker, KeyCode::Enter),
+            Action::Insert("/t review ".to_string(), 10)
+        );
+    }
+
+    #[test]
+    fn typing_filters_by_name() {
+        let mut picker = picker();
+        press(&mut picker, KeyCode::Char('v'));
+        press(&mut picker, KeyCode::Char('i'));
+        assert_eq!(picker.visible().len(), 1);
+        assert!(matches!(
+            press(&mut picker, KeyCode::Enter),
+            Action::Insert(text, _) if text == "/t review "
+        ));
+
+        press(&mut picker, KeyCode::Char('x'));
+        assert_eq!(press(&mut picker, KeyCode::Enter), Action::None);
+        assert_eq!(press(&mut picker, KeyCode::Esc), Action::Close);
+    }
+}
@@ -0,0 +1,329 @@
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use ratatui::style::Color;
+
+use claude_code_core::config::ThemeSetting;
+
+pub const PRESETS: &[&str] = &["dark", "light", "solarized"];
+
+/// Every color the TUI draws with.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub text: Color,
+    pub dim: Color,
+    pub border: Color,
+    pub error: Color,
+    /// Nearly out of something, e.g. context.
+    pub warning: Color,
+    pub user_prompt: Color,
+    pub status_fg: Color,
+    pub status_bg: Color,
+    pub heading: Color,
+    pub link: Color,
+    pub bullet: Color,
+    /// Inline code, and code blocks in languages without highlighting.
+    pub code: Color,
+    pub tool_header: Color,
+    pub tool_output: Color,
+    pub permission_fg: Color,
+    pub permission_bg: Color,
+    pub diff_delete: Color,
+    pub diff_insert: Color,
+    pub diff_delete_bg: Color,
+    pub diff_insert_bg: Color,
+    pub diff_delete_emphasis: Color,
+    pub diff_insert_emphasis: Color,
+    /// Name of the syntect theme for code.
+    pub syntax: &'static str,
+    /// Whether RGB colors are sent as-is; otherwise they are mapped to the
+    /// 256-color palette.
+    pub truecolor: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    fn dark() -> Self {
+        Self {
+            text: Color::Reset,
+            dim: Color::DarkGray,
+            border: Color::DarkGray,
+            error: Color::Red,
+            warning: Color::Rgb(255, 175, 0),
+            user_prompt: Color::Cyan,
+            status_fg: Color::White,
+            status_bg: Color::DarkGray,
+            heading: Color::Cyan,
+            link: Color::Blue,
+            bullet: Color::Yellow,
+            code: Color::Green,
+            tool_header: Color::Yellow,
+            tool_output: Color::DarkGray,
+            permission_fg: Color::Black,
+            permission_bg: Color::Yellow,
+            diff_delete: Color::Red,
+            diff_insert: Color::Green,
+            diff_delete_bg: Color::Rgb(60, 20, 24),
+            diff_insert_bg: Color::Rgb(20, 50, 28),
+            diff_delete_emphasis: Color::Rgb(120, 30, 40),
+            diff_insert_emphasis: Color::Rgb(30, 105, 50),
+            syntax: "base16-ocean.dark",
+            truecolor: true,
+        }
+    }
+
+    fn light() -> Self {
+        Self {
+            text: Color::Reset,
+            dim: Color::Gray,
+            border: Color::Gray,
+            error: Color::Rgb(175, 0, 0),
+            warning: Color::Rgb(175, 95, 0),
+            user_prompt: Color::Rgb(0, 95, 135),
+            status_fg: Color::Black,
+            status_bg: Color::Rgb(215, 215, 215),
+            heading: Color::Rgb(0, 95, 135),
+            link: Color::Rgb(0, 0, 175),
+            bullet: Color::Rgb(175, 95, 0),
+            code: Color::Rgb(0, 115, 0),
+            tool_header: Color::Rgb(135, 95, 0),
+            tool_output: Color::Rgb(88, 88, 88),
+            permission_fg: Color::Black,
+            permission_bg: Color::Rgb(255, 215, 95),
+            diff_delete: Color::Rgb(175, 0, 0),
+            diff_insert: Color::Rgb(0, 115, 0),
+            diff_delete_bg: Color::Rgb(255, 225, 225),
+            diff_insert_bg: Color::Rgb(220, 255, 220),
+            diff_delete_emphasis: Color::Rgb(255, 175, 175),
+            diff_insert_emphasis: Color::Rgb(170, 240, 170),
+            syntax: "InspiredGitHub",
+            truecolor: true,
+        }
+    }
+
+    fn solarized() -> Self {
+        const BASE01: Color = Color::Rgb(0x58, 0x6e, 0x75);
+        const BASE02: Color = Color::Rgb(0x07, 0x36, 0x42);
+        const BASE1: Color = Color::Rgb(0x93, 0xa1, 0xa1);
+        const YELLOW: Color = Color::Rgb(0xb5, 0x89, 0x00);
+        const RED: Color = Color::Rgb(0xdc, 0x32, 0x2f);
+        const ORANGE: Color = Color::Rgb(0xcb, 0x4b, 0x16);
+        const BLUE: Color = Color::Rgb(0x26, 0x8b, 0xd2);
+        const CYAN: Color = Color::Rgb(0x2a, 0xa1, 0x98);
+        const GREEN: Color = Color::Rgb(0x85, 0x99, 0x00);
+
+        Self {
+            text: Color::Reset,
+            dim: BASE01,
+            border: BASE01,
+            error: RED,
+            warning: ORANGE,
+            user_prompt: CYAN,
+            status_fg: BASE1,
+            status_bg: BASE02,
+            heading: BLUE,
+            link: CYAN,
+            bullet: YELLOW,
+            code: GREEN,
+            tool_header: YELLOW,
+            tool_output: BASE01,
+            permission_fg: BASE02,
+            permission_bg: YELLOW,
+            diff_delete: RED,
+            diff_insert: GREEN,
+            diff_delete_bg: Color::Rgb(0x3d, 0x22, 0x2a),
+            diff_insert_bg: Color::Rgb(0x1e, 0x3a, 0x2a),
+            diff_delete_emphasis: Color::Rgb(0x6e, 0x2a, 0x2c),
+            diff_insert_emphasis: Color::Rgb(0x3d, 0x5a, 0x1e),
+            syntax: "Solarized (dark)",
+            truecolor: true,
+        }
+    }
+
+    fn preset(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            "solarized" => Ok(Self::solarized()),
+            _ => bail!("Unknown theme {name:?} (presets: {})", PRESETS.join(", ")),
+        }
+    }
+
+    /// Build the theme from the `theme` setting (default: dark), fitted to
+    /// the terminal's color support.
+    pub fn load(setting: Option<&ThemeSetting>) -> Result<Self> {
+        let mut theme = match setting {
+            None => Self::dark(),
+            Some(ThemeSetting::Preset(name)) => Self::preset(name)?,
+            Some(ThemeSetting::Custom { preset, colors }) => {
+                let mut theme = Self::preset(preset.as_deref().unwrap_or("dark"))?;
+                for (key, value) in colors {
+                    let color = Color::from_str(value)
+                        .map_err(|_| anyhow::anyhow!("Invalid color for {key}: {value:?}"))?;
+                    *theme.slot(key)? = color;
+                }
+                theme
+            }
+        };
+
+        theme.truecolor = supports_truecolor();
+        theme.fit_all();
+        Ok(theme)
+    }
+
+    /// The field for a settings key (camelCase, as in `settings.json`).
+    fn slot(&mut self, key: &str) -> Result<&mut Color> {
+        Ok(match key {
+            "text" => &mut self.text,
+            "dim" => &mut self.dim,
+            "border" => &mut self.border,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "userPrompt" => &mut self.user_prompt,
+            "statusBar" => &mut self.status_bg,
+            "statusBarText" => &mut self.status_fg,
+            "heading" => &mut self.heading,
+            "link" => &mut self.link,
+            "bullet" => &mut self.bullet,
+            "code" => &mut self.code,
+            "toolHeader" => &mut self.tool_header,
+            "toolOutput" => &mut self.tool_output,
+            "permission" => &mut self.permission_bg,
+            "permissionText" => &mut self.permission_fg,
+            "diffDelete" => &mut self.diff_delete,
+            "diffInsert" => &mut self.diff_insert,
+            "diffDeleteBackground" => &mut self.diff_delete_bg,
+            "diffInsertBackground" => &mut self.diff_insert_bg,
+            "diffDeleteEmphasis" => &mut self.diff_delete_emphasis,
+            "diffInsertEmphasis" => &mut self.diff_insert_emphasis,
+            _ => bail!("Unknown theme color {key:?}"),
+        })
+    }
+
+    fn fit_all(&mut self) {
+        let truecolor = self.truecolor;
+        for color in [
+            &mut self.text,
+            &mut self.dim,
+            &mut self.border,
+            &mut self.error,
+            &mut self.warning,
+            &mut self.user_prompt,
+            &mut self.status_fg,
+            &mut self.status_bg,
+            &mut self.heading,
+            &mut self.link,
+            &mut self.bullet,
+            &mut self.code,
+            &mut self.tool_header,
+            &mut self.tool_output,
+            &mut self.permission_fg,
+            &mut self.permission_bg,
+            &mut self.diff_delete,
+            &mut self.diff_insert,
+            &mut self.diff_delete_bg,
+            &mut self.diff_insert_bg,
+            &mut self.diff_delete_emphasis,
+            &mut self.diff_insert_emphasis,
+        ] {
+            *color = fit(*color, truecolor);
+        }
+    }
+
+    /// `color` as this terminal can show it.
+    pub fn fit(&self, color: Color) -> Color {
+        fit(color, self.truecolor)
+    }
+}
+
+/// Terminals advertise 24-bit color through `COLORTERM`.
+fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit")
+}
+
+fn fit(color: Color, truecolor: bool) -> Color {
+    match color {
+        Color::Rgb(r, g, b) if !truecolor => Color::Indexed(nearest_256(r, g, b)),
+        other => other,
+    }
+}
+
+/// Closest xterm 256-color index: the 6×6×6 cube or the gray ramp.
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    let level = |v: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - v as i32).abs())
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+
+    // Gray ramp 232..=255 covers 8, 18, ..., 238
+    let avg = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = ((avg.saturating_sub(8) + 5) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_index;
+
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+
+    if distance((gray, gray, gray)) < distance(cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn overrides_apply_on_top_of_a_preset() {
+        let setting = ThemeSetting::Custom {
+            preset: Some("light".into()),
+            colors: BTreeMap::from([("heading".into(), "magenta".into())]),
+        };
+
+        let theme = Theme::load(Some(&setting)).unwrap();
+
+        assert_eq!(theme.heading, Color::Magenta);
+        assert_eq!(theme.syntax, "InspiredGitHub");
+    }
+
+    #[test]
+    fn bad_settings_are_errors() {
+        let unknown = ThemeSetting::Preset("neon".into());
+        assert!(Theme::load(Some(&unknown)).is_err());
+
+        let bad_key = ThemeSetting::Custom {
+            preset: None,
+            colors: BTreeMap::from([("sparkles".into(), "red".into())]),
+        };
+        assert!(Theme::load(Some(&bad_key)).is_err());
+
+        let bad_color = ThemeSetting::Custom {
+            preset: None,
+            colors: BTreeMap::from([("heading".into(), "#12345".into())]),
+        };
+        assert!(Theme::load(Some(&bad_color)).is_err());
+    }
+
+    #[test]
+    fn rgb_maps_to_the_256_color_palette_without_truecolor() {
+        assert_eq!(fit(Color::Rgb(255, 0, 0), false), Color::Indexed(196));
+        assert_eq!(fit(Color::Rgb(128, 128, 128), false), Color::Indexed(244));
+        assert_eq!(fit(Color::Rgb(255, 0, 0), true), Color::Rgb(255, 0, 0));
+        assert_eq!(fit(Color::Red, false), Color::Red);
+    }
+}
@@ -0,0 +1,171 @@
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+
+use claude_code_core::tools::todo::{self, Todo, TodoStatus};
+
+use super::DisplayMessage;
+use super::theme::Theme;
+
+/// Items shown at most; longer lists keep the current one in view.
+const MAX_ROWS: usize = 6;
+
+/// Claude's latest TodoWrite list, shown above the input while work
+/// remains.
+#[derive(Default)]
+pub struct Todos {
+    items: Vec<Todo>,
+}
+
+impl Todos {
+    /// The list from the last TodoWrite call in a replayed conversation.
+    pub fn restore(messages: &[DisplayMessage]) -> Self {
+        let mut todos = Self::default();
+        for message in messages {
+            if let DisplayMessage::ToolUse {
+                name,
+                input: Some(input),
+                ..
+            } = message
+            {
+                todos.update(name, input);
+            }
+        }
+        todos
+    }
+
+    /// Take the list from a tool call if it is a valid TodoWrite.
+    pub fn update(&mut self, name: &str, input: &serde_json::Value) {
+        if name == "TodoWrite"
+            && let Ok(items) = todo::parse(input)
+        {
+            self.items = items;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    fn done(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|t| t.status == TodoStatus::Completed)
+            .count()
+    }
+
+    /// Rows the panel needs, border included; 0 once everything is done.
+    pub fn height(&self) -> u16 {
+        if self.done() == self.items.len() {
+            return 0;
+        }
+        self.items.len().min(MAX_ROWS) as u16 + 1
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let title = format!(" Todos {}/{} ", self.done(), self.items.len());
+        let block = Block::default()
+            .borders(Borders::TOP)
+            .border_style(Style::new().fg(theme.border))
+            .title(Span::styled(title, Style::new().fg(theme.dim)));
+
+        frame.render_widget(Paragraph::new(self.lines(theme)).block(block), area);
+    }
+
+    fn lines(&self, theme: &Theme) -> Vec<Line<'_>> {
+        // Start just above the first unfinished item
+        let current = self
+            .items
+            .iter()
+            .position(|t| t.status != TodoStatus::Completed)
+            .unwrap_or(0);
+        let start = current
+            .saturating_sub(1)
+            .min(self.items.len().saturating_sub(MAX_ROWS));
+
+        self.items
+            .iter()
+            .skip(start)
+            .take(MAX_ROWS)
+            .map(|item| {
+                let (mark, style) = match item.status {
+                    TodoStatus::Completed => (
+                        "☒",
+                        Style::new()
+                            .fg(theme.dim)
+                            .add_modifier(Modifier::CROSSED_OUT),
+                    ),
+                    TodoStatus::InProgress => ("◼", Style::new().fg(theme.tool_header).bold()),
+                    TodoStatus::Pending => ("☐", Style::new().fg(theme.text)),
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!(" {mark} "),
+                        style.remove_modifier(Modifier::CROSSED_OUT),
+                    ),
+                    Span::styled(item.label(), style),
+                ])
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todos(statuses: &[&str]) -> Todos {
+        let items: Vec<_> = statuses
+            .iter()
+            .enumerate()
+            .map(|(i, status)| {
+                serde_json::json!({
+                    "content": format!("Step {i}"),
+                    "status": status,
+                    "activeForm": format!("Doing step {i}"),
+                })
+            })
+            .collect();
+        let mut todos = Todos::default();
+        todos.update("TodoWrite", &serde_json::json!({ "todos": items }));
+        todos
+    }
+
+    fn shown(todos: &Todos) -> Vec<String> {
+        let theme = Theme::default();
+        todos.lines(&theme).iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn shows_progress_until_everything_is_done() {
+        let list = todos(&["completed", "in_progress", "pending"]);
+        assert_eq!(list.height(), 4);
+        assert_eq!(shown(&list), [" ☒ Step 0", " ◼ Doing step 1", " ☐ Step 2"]);
+
+        assert_eq!(todos(&["completed", "completed"]).height(), 0);
+        assert_eq!(Todos::default().height(), 0);
+    }
+
+    #[test]
+    fn long_lists_keep_the_current_item_in_view() {
+        let mut statuses = vec!["completed"; 8];
+        statuses.extend(["in_progress", "pending"]);
+        let list = todos(&statuses);
+
+        assert_eq!(list.height(), MAX_ROWS as u16 + 1);
+        let lines = shown(&list);
+        assert_eq!(lines.len(), MAX_ROWS);
+        assert_eq!(lines[0], " ☒ Step 4");
+        assert_eq!(lines[4], " ◼ Doing step 8");
+    }
+
+    #[test]
+    fn other_tools_and_bad_input_are_ignored() {
+        let mut list = todos(&["pending"]);
+        list.update("Read", &serde_json::json!({ "todos": [] }));
+        list.update("TodoWrite", &serde_json::json!({ "todos": "none" }));
+        assert_eq!(shown(&list), [" ☐ Step 0"]);
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+
+use claude_code_core::tools::glob::project_walker;
+
+use super::theme::Theme;
+
+/// Width of the panel, border included.
+pub const WIDTH: u16 = 32;
+
+/// Entries listed at most, so a huge checkout doesn't stall the UI.
+const MAX_ENTRIES: usize = 10_000;
+
+/// What the agent did with a file this session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Touch {
+    Read,
+    Modified,
+}
+
+/// What the app should do after a key went to the tree.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    None,
+    /// Give the input box the keys back.
+    Unfocus,
+    /// Insert `@path` into the input.
+    Mention(String),
+}
+
+struct Child {
+    name: String,
+    is_dir: bool,
+}
+
+/// A visible line of the tree.
+struct Row {
+    /// Relative to the project root.
+    path: PathBuf,
+    depth: usize,
+    is_dir: bool,
+}
+
+/// The project's files, walked with the same filters as Glob and Search.
+pub struct FileTree {
+    root: PathBuf,
+    /// Sorted entries of each directory, by relative path.
+    children: HashMap<PathBuf, Vec<Child>>,
+    expanded: HashSet<PathBuf>,
+    rows: Vec<Row>,
+    selected: usize,
+    /// First row in view.
+    top: usize,
+    /// Whether keys go to the tree rather than the input box.
+    pub focused: bool,
+}
+
+impl FileTree {
+    pub fn new(root: &Path) -> Self {
+        let mut tree = Self {
+            root: root.to_path_buf(),
+            children: HashMap::new(),
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            selected: 0,
+            top: 0,
+            focused: true,
+        };
+        tree.reload();
+        tree
+    }
+
+    /// Walk the project again, keeping expanded directories and the
+    /// selection.
+    pub fn reload(&mut self) {
+        let mut children: HashMap<PathBuf, Vec<Child>> = HashMap::new();
+
+        let entries = project_walker(&self.root)
+            .filter_map(|e| e.ok())
+            .filter(|e| e.depth() > 0)
+            .take(MAX_ENTRIES);
+        for entry in entries {
+            let Ok(path) = entry.path().strip_prefix(&self.root) else {
+                continue;
+            };
+            let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            children.entry(parent).or_default().push(Child {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                is_dir: entry.file_type().is_some_and(|t| t.is_dir()),
+            });
+        }
+
+        // Directories first, then by name
+        for list in children.values_mut() {
+            list.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        }
+
+        let selected = self.rows.get(self.selected).map(|r| r.path.clone());
+        self.children = children;
+        self.layout();
+        if let Some(path) = selected {
+            self.select(&path);
+        }
+    }
+
+    fn layout(&mut self) {
+        let mut rows = Vec::new();
+        self.push_rows(Path::new(""), 0, &mut rows);
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn push_rows(&self, dir: &Path, depth: usize, rows: &mut Vec<Row>) {
+        for child in self.children.get(dir).into_iter().flatten() {
+            let path = dir.join(&child.name);
+            let open = child.is_dir && self.expanded.contains(&path);
+            rows.push(Row {
+                path: path.clone(),
+                depth,
+                is_dir: child.is_dir,
+            });
+            if open {
+                self.push_rows(&path, depth + 1, rows);
+            }
+        }
+    }
+
+    fn select(&mut self, path: &Path) {
+        if let Some(i) = self.rows.iter().position(|r| r.path == path) {
+            self.selected = i;
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let Some(row) = self.rows.get(self.selected) else {
+            return match key.code {
+                KeyCode::Esc => Action::Unfocus,
+                _ => Action::None,
+            };
+        };
+        let (path, is_dir) = (row.path.clone(), row.is_dir);
+
+        match key.code {
+            KeyCode::Esc => return Action::Unfocus,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rows.len() - 1);
+            }
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = self.rows.len() - 1,
+
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') if is_dir => {
+                if !self.expanded.remove(&path) {
+                    self.expanded.insert(path);
+                }
+                self.layout();
+            }
+            KeyCode::Enter => return Action::Mention(mention(&path)),
+
+            // Collapse the directory, or go up to the parent's row
+            KeyCode::Left | KeyCode::Char('h') => {
+                if is_dir && self.expanded.remove(&path) {
+                    self.layout();
+                } else if let Some(parent) = path.parent() {
+                    self.select(parent);
+                }
+            }
+            _ => {}
+        }
+
+        Action::None
+    }
+
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        theme: &Theme,
+        touched: &HashMap<PathBuf, Touch>,
+    ) {
+        let height = area.height.saturating_sub(1) as usize;
+        // Keep the selection in view
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + height {
+            self.top = self.selected + 1 - height;
+        }
+
+        let lines: Vec<Line> = self
+            .rows
+            .iter()
+            .enumerate()
+            .skip(self.top)
+            .take(height)
+            .map(|(i, row)| {
+                let name = row.path.file_name().unwrap_or_default().to_string_lossy();
+                let (marker, name) = match (row.is_dir, self.expanded.contains(&row.path)) {
+                    (true, true) => ("▾ ", format!("{name}/")),
+                    (true, false) => ("▸ ", format!("{name}/")),
+                    (false, _) => ("  ", name.into_owned()),
+                };
+
+                let mut style = match touched.get(&row.path) {
+                    Some(Touch::Modified) => Style::new().fg(theme.diff_insert),
+                    Some(Touch::Read) => Style::new().fg(theme.link),
+                    None if row.is_dir => Style::new().fg(theme.text).bold(),
+                    None => Style::new().fg(theme.text),
+                };
+                if i == self.selected && self.focused {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+
+                Line::from(vec![
+                    Span::raw("  ".repeat(row.depth)),
+                    Span::styled(marker, Style::new().fg(theme.dim)),
+                    Span::styled(name, style),
+                ])
+            })
+            .collect();
+
+        let title_style = if self.focused {
+            Style::new().fg(theme.tool_header).bold()
+        } else {
+            Style::new().fg(theme.dim)
+        };
+        let block = Block::default()
+            .borders(Borders::RIGHT | Borders::TOP)
+            .border_style(Style::new().fg(theme.border))
+            .title(Span::styled(" Files ", title_style));
+
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+/// `@path` with forward slashes, as typed in a prompt.
+fn mention(path: &Path) -> String {
+    let parts: Vec<_> = path.iter().map(|p| p.to_string_lossy()).collect();
+    format!("@{}", parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn press(tree: &mut FileTree, code: KeyCode) -> Action {
+        tree.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn visible(tree: &FileTree) -> Vec<String> {
+        tree.rows
+            .iter()
+            .map(|r| r.path.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lists_directories_first_and_expands_on_enter() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        std::fs::create_dir(dir.path().join("node_modules")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+
+        let mut tree = FileTree::new(dir.path());
+        assert_eq!(visible(&tree), ["src", "Cargo.toml"]);
+
+        press(&mut tree, KeyCode::Enter);
+        assert_eq!(
+            visible(&tree),
+            ["src", "src/bin", "src/main.rs", "Cargo.toml"]
+        );
+
+        press(&mut tree, KeyCode::Down);
+        press(&mut tree, KeyCode::Down);
+        assert_eq!(
+            press(&mut tree, KeyCode::Enter),
+            Action::Mention("@src/main.rs".to_string())
+        );
+
+        // Left goes up to the directory, then collapses it
+        press(&mut tree, KeyCode::Left);
+        press(&mut tree, KeyCode::Left);
+        assert_eq!(visible(&tree), ["src", "Cargo.toml"]);
+        assert_eq!(press(&mut tree, KeyCode::Esc), Action::Unfocus);
+    }
+
+    #[test]
+    fn reload_keeps_the_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.rs"), "").unwrap();
+
+        let mut tree = FileTree::new(dir.path());
+        std::fs::write(dir.path().join("a.rs"), "").unwrap();
+        tree.reload();
+
+        assert_eq!(visible(&tree), ["a.rs", "b.rs"]);
+        assert_eq!(tree.selected, 1);
+    }
+}
@@ -0,0 +1,329 @@
+use std::ops::Range;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Normal,
+    Insert,
+}
+
+/// What the app should do after a key went through vim mode.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// The key was consumed.
+    Handled,
+    /// Let the regular key handling see it (typing in insert mode, Enter,
+    /// history, ...).
+    Pass,
+    ScrollUp,
+    ScrollDown,
+}
+
+/// Modal editing for the input box: normal mode moves and edits with vim
+/// keys, insert mode types as usual.
+pub struct Vim {
+    pub mode: Mode,
+    /// Operator keys typed so far, e.g. `d` or `ci`.
+    pending: String,
+}
+
+impl Default for Vim {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Insert,
+            pending: String::new(),
+        }
+    }
+}
+
+impl Vim {
+    /// Handle `key` against the input buffer. `cursor` is a char index.
+    pub fn handle(&mut self, key: KeyEvent, input: &mut String, cursor: &mut usize) -> Outcome {
+        if self.mode == Mode::Insert {
+            if key.code == KeyCode::Esc {
+                self.mode = Mode::Normal;
+                *cursor = cursor.saturating_sub(1);
+                return Outcome::Handled;
+            }
+            return Outcome::Pass;
+        }
+
+        let c = match key.code {
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                c
+            }
+            KeyCode::Esc => {
+                self.pending.clear();
+                return Outcome::Handled;
+            }
+            _ => return Outcome::Pass,
+        };
+
+        let chars: Vec<char> = input.chars().collect();
+        let len = chars.len();
+
+        if !self.pending.is_empty() {
+            self.pending.push(c);
+            return self.operator(&chars, input, cursor);
+        }
+
+        match c {
+            'h' => *cursor = cursor.saturating_sub(1),
+            'l' => *cursor = (*cursor + 1).min(len.saturating_sub(1)),
+            'w' => *cursor = next_word_start(&chars, *cursor).min(len.saturating_sub(1)),
+            'b' => *cursor = prev_word_start(&chars, *cursor),
+            '0' | '^' => *cursor = line_bounds(&chars, *cursor).start,
+            '$' => {
+                let line = line_bounds(&chars, *cursor);
+                *cursor = line.end.saturating_sub(1).max(line.start);
+            }
+            'j' => return Outcome::ScrollDown,
+            'k' => return Outcome::ScrollUp,
+            'i' => self.mode = Mode::Insert,
+            'a' => {
+                *cursor = (*cursor + 1).min(len);
+                self.mode = Mode::Insert;
+            }
+            'I' => {
+                *cursor = line_bounds(&chars, *cursor).start;
+                self.mode = Mode::Insert;
+            }
+            'A' => {
+                *cursor = line_bounds(&chars, *cursor).end;
+                self.mode = Mode::Insert;
+            }
+            'x' if *cursor < len => {
+                delete(input, *cursor..*cursor + 1);
+                *cursor = (*cursor).min(input.chars().count().saturating_sub(1));
+            }
+            'D' => {
+                delete(input, *cursor..line_bounds(&chars, *cursor).end);
+                *cursor = cursor.saturating_sub(1);
+            }
+            'C' => {
+                delete(input, *cursor..line_bounds(&chars, *cursor).end);
+                self.mode = Mode::Insert;
+            }
+            'd' | 'c' => self.pending.push(c),
+            _ => {}
+        }
+
+        Outcome::Handled
+    }
+
+    /// Apply a pending operator once its motion is complete.
+    fn operator(&mut self, chars: &[char], input: &mut String, cursor: &mut usize) -> Outcome {
+        let at = *cursor;
+        let range = match self.pending.as_str() {
+            "dd" | "cc" => {
+                let line = line_bounds(chars, at);
+                // `dd` takes the line break too; `cc` keeps an empty line
+                if self.pending == "dd" && line.end < chars.len() {
+                    line.start..line.end + 1
+                } else if self.pending == "dd" && line.start > 0 {
+                    line.start - 1..line.end
+                } else {
+                    line
+                }
+            }
+            "dw" => at..next_word_start(chars, at),
+            // Like vim, `cw` stops at the end of the word
+            "cw" => at..word_end(chars, at),
+            "db" | "cb" => prev_word_start(chars, at)..at,
+            "d$" | "c$" => at..line_bounds(chars, at).end,
+            "diw" | "ciw" => inner_word(chars, at),
+            "di" | "ci" => return Outcome::Handled,
+            _ => {
+                self.pending.clear();
+                return Outcome::Handled;
+            }
+        };
+
+        let change = self.pending.starts_with('c');
+        self.pending.clear();
+
+        delete(input, range.clone());
+        let len = input.chars().count();
+        *cursor = range
+            .start
+            .min(if change { len } else { len.saturating_sub(1) });
+        if change {
+            self.mode = Mode::Insert;
+        }
+
+        Outcome::Handled
+    }
+}
+
+/// Remove the chars in `range` from `input`.
+pub(super) fn delete(input: &mut String, range: Range<usize>) {
+    let byte = |i: usize| {
+        input
+            .char_indices()
+            .nth(i)
+            .map(|(b, _)| b)
+            .unwrap_or(input.len())
+    };
+    let (start, end) = (byte(range.start), byte(range.end));
+    input.replace_range(start..end, "");
+}
+
+/// 0 for whitespace, 1 for word characters, 2 for punctuation.
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// Char range of the line containing `at`, without its line break.
+pub(super) fn line_bounds(chars: &[char], at: usize) -> Range<usize> {
+    let at = at.min(chars.len());
+    let start = chars[..at]
+        .iter()
+        .rposition(|&c| c == '\n')
+        .map_or(0, |i| i + 1);
+    let end = chars[at..]
+        .iter()
+        .position(|&c| c == '\n')
+        .map_or(chars.len(), |i| at + i);
+    start..end
+}
+
+/// `w`: the start of the next word.
+fn next_word_start(chars: &[char], at: usize) -> usize {
+    let mut i = at;
+    if let Some(&c) = chars.get(i) {
+        let start_class = class(c);
+        while i < chars.len() && class(chars[i]) == start_class && start_class != 0 {
+            i += 1;
+        }
+    }
+    while i < chars.len() && class(chars[i]) == 0 {
+        i += 1;
+    }
+    i
+}
+
+/// `b`: the start of this word, or of the previous one if already there.
+fn prev_word_start(chars: &[char], at: usize) -> usize {
+    let mut i = at.min(chars.len());
+    while i > 0 && class(chars[i - 1]) == 0 {
+        i -= 1;
+    }
+    if i == 0 {
+        return 0;
+    }
+    let word_class = class(chars[i - 1]);
+    while i > 0 && class(chars[i - 1]) == word_class {
+        i -= 1;
+    }
+    i
+}
+
+/// One past the last char of the word at `at`.
+fn word_end(chars: &[char], at: usize) -> usize {
+    let Some(&c) = chars.get(at) else {
+        return at;
+    };
+    let word_class = class(c);
+    let mut i = at;
+    while i < chars.len() && class(chars[i]) == word_class {
+        i += 1;
+    }
+    i
+}
+
+/// `iw`: the run of same-class chars around `at`.
+fn inner_word(chars: &[char], at: usize) -> Range<usize> {
+    let Some(&c) = chars.get(at) else {
+        return at..at;
+    };
+    let word_class = class(c);
+    let mut start = at;
+    while start > 0 && class(chars[start - 1]) == word_class {
+        start -= 1;
+    }
+    start..word_end(chars, at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `keys` in normal mode from `cursor`; return the input, cursor
+    /// and mode.
+    fn run(input: &str, cursor: usize, keys: &str) -> (String, usize, Mode) {
+        let mut vim = Vim {
+            mode: Mode::Normal,
+            pending: String::new(),
+        };
+        let mut input = input.to_string();
+        let mut cursor = cursor;
+        for c in keys.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            vim.handle(key, &mut input, &mut cursor);
+        }
+        (input, cursor, vim.mode)
+    }
+
+    #[test]
+    fn motions() {
+        assert_eq!(run("foo bar.baz", 0, "w").1, 4);
+        assert_eq!(run("foo bar.baz", 4, "w").1, 7);
+        assert_eq!(run("foo bar.baz", 8, "b").1, 7);
+        assert_eq!(run("foo bar.baz", 8, "bb").1, 4);
+        assert_eq!(run("foo bar", 5, "0").1, 0);
+        assert_eq!(run("foo bar", 0, "$").1, 6);
+        assert_eq!(run("foo bar", 0, "hlll").1, 3);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(run("foo bar baz", 4, "dw").0, "foo baz");
+        assert_eq!(run("foo bar baz", 5, "diw").0, "foo  baz");
+        assert_eq!(run("one\ntwo\nthree", 5, "dd").0, "one\nthree");
+        assert_eq!(run("only line", 3, "dd").0, "");
+        assert_eq!(run("foo bar", 1, "x").0, "fo bar");
+        assert_eq!(run("foo bar", 3, "D").0, "foo");
+    }
+
+    #[test]
+    fn change_enters_insert_mode() {
+        let (input, cursor, mode) = run("let old_name = 1;", 6, "ciw");
+        assert_eq!(input, "let  = 1;");
+        assert_eq!(cursor, 4);
+        assert_eq!(mode, Mode::Insert);
+
+        assert_eq!(run("foo bar baz", 4, "cw").0, "foo  baz");
+        assert_eq!(run("foo", 0, "A").1, 3);
+    }
+
+    #[test]
+    fn insert_mode_passes_keys_through() {
+        let mut vim = Vim::default();
+        let mut input = String::from("ab");
+        let mut cursor = 2;
+
+        let typed = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(vim.handle(typed, &mut input, &mut cursor), Outcome::Pass);
+
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(vim.handle(esc, &mut input, &mut cursor), Outcome::Handled);
+        assert_eq!(vim.mode, Mode::Normal);
+        assert_eq!(cursor, 1);
+
+        assert_eq!(
+            vim.handle(typed, &mut input, &mut cursor),
+            Outcome::ScrollDown
+        );
+    }
+}
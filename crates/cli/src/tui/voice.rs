@@ -0,0 +1,544 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use claude_code_core::config::VoiceSettings;
+use crossterm::event::{
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use tokio::sync::oneshot;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::commands::rec::Capture;
+use crate::commands::transcribe::{self, Recording, Transcriber};
+
+/// A press this soon after the previous one is the terminal's key repeat.
+const REPEAT: Duration = Duration::from_millis(500);
+
+/// Released this soon after pressing, the record key was tapped, not held:
+/// keep recording until silence or the next tap.
+const TAP: Duration = Duration::from_millis(300);
+
+/// Shorter recordings are dropped instead of transcribed.
+const MIN_RECORDING: Duration = Duration::from_millis(300);
+
+/// With live transcription, a pause this long after speech sends what was
+/// said so far.
+const PAUSE: Duration = Duration::from_millis(500);
+
+/// Longest part sent for transcription while still recording, for people
+/// who don't pause.
+const MAX_PART: Duration = Duration::from_secs(20);
+
+static KEY_RELEASES_SUPPORTED: OnceLock<bool> = OnceLock::new();
+static KEY_RELEASES: AtomicBool = AtomicBool::new(false);
+
+/// Ask the terminal to report key releases, for holding `Alt+V`. Needs
+/// raw mode and the alternate screen.
+pub fn enable_key_releases() {
+    let supported = *KEY_RELEASES_SUPPORTED
+        .get_or_init(|| crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false));
+    let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+    if supported
+        && !KEY_RELEASES.load(Ordering::Relaxed)
+        && crossterm::execute!(std::io::stdout(), PushKeyboardEnhancementFlags(flags)).is_ok()
+    {
+        KEY_RELEASES.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Undo [`enable_key_releases`] before handing the terminal to anything
+/// else.
+pub fn disable_key_releases() {
+    if KEY_RELEASES.swap(false, Ordering::Relaxed) {
+        let _ = crossterm::execute!(std::io::stdout(), PopKeyboardEnhancementFlags);
+    }
+}
+
+/// Voice input without leaving the TUI: `Alt+V` held records until it is
+/// released, tapped (or `/rec`) records until trailing silence or another
+/// tap, and the transcript lands in the input box. With live
+/// transcription, each pause is transcribed while recording goes on.
+pub struct Voice {
+    settings: VoiceSettings,
+    phase: Phase,
+    last_press: Option<Instant>,
+}
+
+enum Phase {
+    Idle,
+    Recording(Box<Take>),
+    Transcribing(Parts),
+}
+
+/// A recording in progress.
+struct Take {
+    capture: Capture,
+    started: Instant,
+    /// Stops when the key is released.
+    held: bool,
+    vad: Vad,
+    /// Samples already given to `vad`.
+    read: usize,
+    heard_speech: bool,
+    /// Start of the audio not yet sent for transcription.
+    cut: usize,
+    /// Whether anyone spoke since `cut`.
+    cut_speech: bool,
+    parts: Parts,
+}
+
+impl Voice {
+    pub fn new(settings: VoiceSettings) -> Self {
+        Self {
+            settings,
+            phase: Phase::Idle,
+            last_press: None,
+        }
+    }
+
+    /// `Alt+V` went down.
+    pub fn press(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let repeat = self
+            .last_press
+            .is_some_and(|last| now.duration_since(last) < REPEAT);
+        self.last_press = Some(now);
+
+        match &self.phase {
+            // Without release events, holding the key works like tapping it
+            Phase::Idle => self.start(KEY_RELEASES.load(Ordering::Relaxed)),
+            Phase::Recording(take) if !take.held && !repeat => {
+                self.stop();
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `Alt+V` came back up.
+    pub fn release(&mut self) {
+        let Phase::Recording(take) = &mut self.phase else {
+            return;
+        };
+        if !take.held {
+            return;
+        }
+        if take.started.elapsed() < TAP {
+            take.held = false;
+        } else {
+            self.stop();
+        }
+    }
+
+    /// `/rec`: start recording until silence, or stop early.
+    pub fn toggle(&mut self) -> Result<()> {
+        match self.phase {
+            Phase::Idle => self.start(false),
+            Phase::Recording(_) => {
+                self.stop();
+                Ok(())
+            }
+            Phase::Transcribing(_) => Ok(()),
+        }
+    }
+
+    /// Listen for pauses while recording, and collect the transcript once
+    /// it's ready. Call on every frame.
+    pub fn poll(&mut self) -> Option<Result<String>> {
+        match &mut self.phase {
+            Phase::Idle => None,
+            Phase::Recording(take) => {
+                take.listen(self.settings.live());
+                take.parts.poll();
+                let auto_stop = self.settings.auto_stop().filter(|_| !take.held);
+                if auto_stop.is_some_and(|after| take.heard_speech && take.vad.quiet() >= after) {
+                    self.stop();
+                }
+                None
+            }
+            Phase::Transcribing(parts) => {
+                parts.poll();
+                let result = parts.finished()?;
+                self.phase = Phase::Idle;
+                Some(result)
+            }
+        }
+    }
+
+    /// Shown above the input box while recording or transcribing.
+    pub fn status(&self) -> Option<String> {
+        match &self.phase {
+            Phase::Idle => None,
+            Phase::Recording(take) => {
+                let secs = take.started.elapsed().as_secs();
+                let stop = match (take.held, self.settings.auto_stop().is_some()) {
+                    (true, _) => "release Alt+V to stop",
+                    (false, true) => "pause or press Alt+V to stop",
+                    (false, false) => "press Alt+V to stop",
+                };
+                let mut status = format!("● Recording {}:{:02} · {stop}", secs / 60, secs % 60);
+                if let Some(progress) = take.parts.transcriber.progress() {
+                    status.push_str(&format!(" · {progress}"));
+                }
+                Some(status)
+            }
+            Phase::Transcribing(parts) => Some(
+                parts
+                    .transcriber
+                    .progress()
+                    .unwrap_or_else(|| format!("Transcribing with {}…", parts.transcriber.name())),
+            ),
+        }
+    }
+
+    /// What has been transcribed so far, while recording or transcribing.
+    pub fn partial(&self) -> Option<String> {
+        let text = match &self.phase {
+            Phase::Idle => return None,
+            Phase::Recording(take) => take.parts.text(),
+            Phase::Transcribing(parts) => parts.text(),
+        };
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn start(&mut self, held: bool) -> Result<()> {
+        // Built first, so nobody talks to a recording that can't be sent
+        let transcriber: Arc<dyn Transcriber> = transcribe::from_settings(&self.settings)?.into();
+        let capture = Capture::start()?;
+        let vad = Vad::new(capture.sample_rate());
+        self.phase = Phase::Recording(Box::new(Take {
+            parts: Parts::new(transcriber),
+            capture,
+            started: Instant::now(),
+            held,
+            vad,
+            read: 0,
+            heard_speech: false,
+            cut: 0,
+            cut_speech: false,
+        }));
+        Ok(())
+    }
+
+    /// Send the rest of the recording, if there's anything to hear.
+    fn stop(&mut self) {
+        let Phase::Recording(take) = std::mem::replace(&mut self.phase, Phase::Idle) else {
+            return;
+        };
+        let mut take = *take;
+        take.listen(false);
+
+        let mut recording = take.capture.finish();
+        recording
+            .samples
+            .drain(..take.cut.min(recording.samples.len()));
+        let min_len = MIN_RECORDING.as_millis() as usize * recording.sample_rate as usize / 1000;
+        // The quiet after the last pause isn't worth a request
+        if recording.samples.len() >= min_len && (take.cut_speech || take.parts.is_empty()) {
+            take.parts.send(recording);
+        }
+
+        if !take.parts.is_empty() {
+            self.phase = Phase::Transcribing(take.parts);
+        }
+    }
+}
+
+impl Take {
+    /// Feed new audio to the voice detector and, when `live`, send the
+    /// speech before each pause for transcription.
+    fn listen(&mut self, live: bool) {
+        let samples = self.capture.samples_since(self.read);
+        if self.vad.feed(&samples) {
+            self.heard_speech = true;
+            self.cut_speech = true;
+        }
+        self.read += samples.len();
+
+        let rate = self.capture.sample_rate() as usize;
+        let part_len = self.read - self.cut;
+        let paused = self.cut_speech && self.vad.quiet() >= PAUSE;
+        if !live || !(paused || part_len >= MAX_PART.as_secs() as usize * rate) {
+            return;
+        }
+
+        let mut part = self.capture.samples_since(self.cut);
+        part.truncate(part_len);
+        self.parts.send(Recording {
+            samples: part,
+            sample_rate: rate as u32,
+        });
+        self.cut = self.read;
+        self.cut_speech = false;
+    }
+}
+
+/// The end of `text` that fits in `width` columns.
+pub fn tail(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut used = 1;
+    let mut start = text.len();
+    for (i, c) in text.char_indices().rev() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            break;
+        }
+        start = i;
+    }
+    format!("…{}", &text[start..])
+}
+
+// ---------------------------------------------------------------------------
+// Transcribed parts
+// ---------------------------------------------------------------------------
+
+/// Pieces of one recording, transcribed concurrently and joined in order.
+struct Parts {
+    transcriber: Arc<dyn Transcriber>,
+    parts: Vec<Part>,
+}
+
+enum Part {
+    Pending(oneshot::Receiver<Result<String>>),
+    Done(Result<String>),
+}
+
+impl Parts {
+    fn new(transcriber: Arc<dyn Transcriber>) -> Self {
+        Self {
+            transcriber,
+            parts: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    fn send(&mut self, recording: Recording) {
+        let (tx, rx) = oneshot::channel();
+        let transcriber = Arc::clone(&self.transcriber);
+        tokio::spawn(async move {
+            let _ = tx.send(transcriber.transcribe(&recording).await);
+        });
+        self.parts.push(Part::Pending(rx));
+    }
+
+    fn poll(&mut self) {
+        for part in &mut self.parts {
+            let Part::Pending(rx) = part else {
+                continue;
+            };
+            match rx.try_recv() {
+                Ok(result) => *part = Part::Done(result),
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => {
+                    *part = Part::Done(Err(anyhow!("Transcription stopped unexpectedly")));
+                }
+            }
+        }
+    }
+
+    /// The text of the parts transcribed so far, up to the first one that
+    /// isn't.
+    fn text(&self) -> String {
+        self.parts
+            .iter()
+            .map_while(|part| match part {
+                Part::Done(Ok(text)) => Some(text.as_str()),
+                _ => None,
+            })
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The whole transcript, once every part is back.
+    fn finished(&mut self) -> Option<Result<String>> {
+        if self
+            .parts
+            .iter()
+            .any(|part| matches!(part, Part::Pending(_)))
+        {
+            return None;
+        }
+        let texts: Result<Vec<String>> = self
+            .parts
+            .drain(..)
+            .filter_map(|part| match part {
+                Part::Done(result) => Some(result),
+                Part::Pending(_) => None,
+            })
+            .collect();
+        Some(texts.map(|texts| {
+            texts
+                .into_iter()
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        }))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Silence detection
+// ---------------------------------------------------------------------------
+
+/// Frames quieter than this are never speech, however quiet the room.
+const MIN_SPEECH_RMS: f32 = 0.01;
+
+/// Speech is this many times louder than the quietest frame so far.
+const SPEECH_OVER_FLOOR: f32 = 4.0;
+
+const FRAME_MS: usize = 30;
+
+/// Energy-based voice activity detection over 30 ms frames.
+struct Vad {
+    frame_len: usize,
+    /// Background noise level: the quietest frame heard.
+    floor: f32,
+    /// Frames since the last speech.
+    quiet_frames: usize,
+    pending: Vec<f32>,
+}
+
+impl Vad {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            frame_len: (sample_rate as usize * FRAME_MS / 1000).max(1),
+            floor: f32::MAX,
+            quiet_frames: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns whether any complete frame so far was speech.
+    fn feed(&mut self, samples: &[f32]) -> bool {
+        self.pending.extend_from_slice(samples);
+        let frames = self.pending.len() / self.frame_len;
+        let mut speech = false;
+
+        for frame in self.pending.chunks_exact(self.frame_len).take(frames) {
+            let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+            self.floor = self.floor.min(rms);
+
+            if rms > (self.floor * SPEECH_OVER_FLOOR).max(MIN_SPEECH_RMS) {
+                speech = true;
+                self.quiet_frames = 0;
+            } else {
+                self.quiet_frames += 1;
+            }
+        }
+        self.pending.drain(..frames * self.frame_len);
+
+        speech
+    }
+
+    /// How long it's been quiet.
+    fn quiet(&self) -> Duration {
+        Duration::from_millis((self.quiet_frames * FRAME_MS) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Stub;
+
+    impl Transcriber for Stub {
+        fn name(&self) -> String {
+            "test".to_string()
+        }
+
+        fn transcribe<'a>(
+            &'a self,
+            _recording: &'a Recording,
+        ) -> std::pin::Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>> {
+            Box::pin(async { Ok(String::new()) })
+        }
+    }
+
+    fn tone(ms: usize, amplitude: f32) -> Vec<f32> {
+        (0..16 * ms)
+            .map(|i| if i % 2 == 0 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn measures_the_quiet_after_speech() {
+        let mut vad = Vad::new(16_000);
+
+        assert!(!vad.feed(&tone(1980, 0.002)));
+        assert!(vad.feed(&tone(600, 0.2)));
+        assert!(!vad.feed(&tone(210, 0.002)));
+        assert_eq!(vad.quiet(), Duration::from_millis(210));
+        // The next word resets it
+        assert!(vad.feed(&tone(90, 0.2)));
+        assert_eq!(vad.quiet(), Duration::ZERO);
+    }
+
+    #[test]
+    fn a_noisy_room_is_not_speech() {
+        let mut vad = Vad::new(16_000);
+
+        assert!(!vad.feed(&tone(510, 0.02)));
+        // Louder than the floor, but not by enough
+        assert!(!vad.feed(&tone(510, 0.05)));
+        assert!(vad.feed(&tone(510, 0.3)));
+        assert!(!vad.feed(&tone(300, 0.02)));
+    }
+
+    #[test]
+    fn frames_can_arrive_in_pieces() {
+        let mut vad = Vad::new(16_000);
+        let speech = tone(30, 0.2);
+
+        assert!(!vad.feed(&tone(30, 0.001)));
+        assert!(!vad.feed(&speech[..100]));
+        assert!(vad.feed(&speech[100..]));
+        assert_eq!(vad.pending.len(), 0);
+    }
+
+    #[test]
+    fn parts_join_in_order_once_all_are_back() {
+        let mut parts = Parts::new(Arc::new(Stub));
+        let (first, first_rx) = oneshot::channel();
+        let (second, second_rx) = oneshot::channel();
+        parts.parts.push(Part::Pending(first_rx));
+        parts.parts.push(Part::Pending(second_rx));
+
+        second.send(Ok("world.".to_string())).unwrap();
+        parts.poll();
+        assert_eq!(parts.text(), "");
+        assert!(parts.finished().is_none());
+
+        first.send(Ok("Hello".to_string())).unwrap();
+        parts.poll();
+        assert_eq!(parts.text(), "Hello world.");
+        assert_eq!(parts.finished().unwrap().unwrap(), "Hello world.");
+    }
+
+    #[test]
+    fn long_partial_transcripts_show_their_end() {
+        assert_eq!(tail("Hello world", 20), "Hello world");
+        assert_eq!(tail("Hello world", 6), "…world");
+        assert_eq!(tail("日本語のテキスト", 7), "…キスト");
+    }
+
+    #[test]
+    fn a_failed_part_fails_the_transcript() {
+        let mut parts = Parts::new(Arc::new(Stub));
+        parts.parts.push(Part::Done(Ok("Hello".to_string())));
+        parts.parts.push(Part::Done(Err(anyhow!("quota exceeded"))));
+
+        assert_eq!(parts.text(), "Hello");
+        let error = parts.finished().unwrap().unwrap_err();
+        assert_eq!(error.to_string(), "quota exceeded");
+    }
+}
@@ -1,5 +1,11 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
 use anyhow::Result;
-use dialoguer::{Input, Password, Select};
+use claude_code_core::store::SavedSession;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use dialoguer::{Confirm, Input, Password, Select};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LoginMethod {
@@ -50,3 +56,60 @@ pub fn prompt_oauth_code() -> Result<String> {
         .interact_text()?;
     Ok(code)
 }
+
+/// Offer a session that stopped mid-turn.
+pub fn prompt_restore(session: &SavedSession) -> Result<bool> {
+    let prompt = session.first_prompt().unwrap_or_default();
+    let first_line = prompt.lines().next().unwrap_or("");
+    let preview: String = first_line.chars().take(60).collect();
+    println!("ccrs stopped in the middle of a session here: \"{preview}\"");
+
+    let restore = Confirm::new()
+        .with_prompt("Restore it where it stopped?")
+        .default(true)
+        .interact()?;
+    Ok(restore)
+}
+
+/// Read a line from the terminal. Unlike dialoguer prompts this can be
+/// interrupted: it returns `None` once `cancel` is set, or on Esc or Ctrl+C.
+pub fn read_line_until(prompt: &str, cancel: &AtomicBool) -> Result<Option<String>> {
+    let mut stdout = std::io::stdout();
+    print!("{prompt}: ");
+    stdout.flush()?;
+
+    crossterm::terminal::enable_raw_mode()?;
+    let result = (|| {
+        let mut line = String::new();
+        while !cancel.load(Ordering::Relaxed) {
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Enter => return Ok(Some(line)),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(None);
+                }
+                KeyCode::Char(c) => {
+                    line.push(c);
+                    print!("{c}");
+                }
+                KeyCode::Backspace if line.pop().is_some() => print!("\x08 \x08"),
+                _ => {}
+            }
+            stdout.flush()?;
+        }
+        Ok(None)
+    })();
+    crossterm::terminal::disable_raw_mode()?;
+    println!();
+
+    result
+}
@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+
+use claude_code_core::{config, tls};
+
+const REPO: &str = "max-lt/claude-code-rs";
+const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+/// Startup checks hit GitHub at most this often; in between, the last
+/// answer is reused.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Startup checks give up quickly so a slow network never holds anything up.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Release asset listing each binary's SHA-256, in `sha256sum` format.
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+struct Release {
+    /// Without the leading `v`.
+    version: String,
+    /// `(name, download URL)`
+    assets: Vec<(String, String)>,
+}
+
+// ---------------------------------------------------------------------------
+// Startup check
+// ---------------------------------------------------------------------------
+
+/// Rate-limited startup check. Returns a notice if a newer release exists.
+pub async fn check() -> Option<String> {
+    let path = config::config_dir().ok()?.join("update-check.json");
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    let latest = match load_state(&path) {
+        Some((checked_at, latest)) if now.saturating_sub(checked_at) < CHECK_INTERVAL.as_secs() => {
+            latest
+        }
+        previous => {
+            let fetched = latest_release(CHECK_TIMEOUT).await.ok().map(|r| r.version);
+            // Record failed checks too, so an offline machine doesn't retry on
+            // every launch
+            let latest = fetched.or(previous.and_then(|(_, latest)| latest));
+            save_state(&path, now, latest.as_deref());
+            latest
+        }
+    }?;
+
+    is_newer(&latest, CURRENT).then(|| {
+        format!(
+            "ccrs {latest} is available (you have {CURRENT}). Run `ccrs self-update` to upgrade."
+        )
+    })
+}
+
+/// `(checked_at, latest version)` from the state file.
+fn load_state(path: &Path) -> Option<(u64, Option<String>)> {
+    let text = fs::read_to_string(path).ok()?;
+    let state: serde_json::Value = serde_json::from_str(&text).ok()?;
+    let checked_at = state["checked_at"].as_u64()?;
+    let latest = state["latest"].as_str().map(str::to_string);
+    Some((checked_at, latest))
+}
+
+fn save_state(path: &Path, checked_at: u64, latest: Option<&str>) {
+    let state = serde_json::json!({ "checked_at": checked_at, "latest": latest });
+    let _ = fs::write(path, state.to_string());
+}
+
+// ---------------------------------------------------------------------------
+// Versions
+// ---------------------------------------------------------------------------
+
+/// `major.minor.patch`, with an optional leading `v`. Pre-releases and
+/// anything else unparsable yield `None`, so they're never offered.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let mut parts = version.split('.').map(|p| p.parse::<u64>().ok());
+    let parsed = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(parsed)
+}
+
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// GitHub releases
+// ---------------------------------------------------------------------------
+
+async fn latest_release(timeout: Duration) -> Result<Release> {
+    let client = tls::client_builder()
+        .user_agent(format!("ccrs/{CURRENT}"))
+        .timeout(timeout)
+        .build()?;
+
+    let response = client
+        .get(format!(
+            "https://api.github.com/repos/{REPO}/releases/latest"
+        ))
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .context("Failed to reach GitHub")?
+        .error_for_status()
+        .context("Failed to fetch the latest release")?;
+
+    let body: serde_json::Value =
+        serde_json::from_str(&response.text().await?).context("Invalid release metadata")?;
+
+    let tag = body["tag_name"]
+        .as_str()
+        .context("Release metadata has no tag")?;
+    let assets = body["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|a| {
+            let name = a["name"].as_str()?;
+            let url = a["browser_download_url"].as_str()?;
+            Some((name.to_string(), url.to_string()))
+        })
+        .collect();
+
+    Ok(Release {
+        version: tag.strip_prefix('v').unwrap_or(tag).to_string(),
+        assets,
+    })
+}
+
+/// Release asset holding the binary for this platform, e.g.
+/// `ccrs-x86_64-linux` or `ccrs-aarch64-macos`.
+fn asset_name() -> String {
+    format!(
+        "ccrs-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Self-update
+// ---------------------------------------------------------------------------
+
+/// `ccrs self-update`: download the latest release for this platform and
+/// replace the running binary.
+pub async fn self_update() -> Result<()> {
+    println!("Checking for updates...");
+    let release = latest_release(Duration::from_secs(30)).await?;
+
+    if !is_newer(&release.version, CURRENT) {
+        println!("ccrs {CURRENT} is up to date.");
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let find = |wanted: &str| {
+        release
+            .assets
+            .iter()
+            .find(|(n, _)| n == wanted)
+            .map(|(_, url)| url.as_str())
+    };
+    let Some(url) = find(&name) else {
+        bail!(
+            "Release {} has no build for this platform (expected an asset named {name}). \
+             Install from source with `cargo install --git https://github.com/{REPO}`.",
+            release.version
+        );
+    };
+    let Some(sums_url) = find(CHECKSUMS_ASSET) else {
+        bail!(
+            "Release {} has no {CHECKSUMS_ASSET} asset, so its build can't be verified. \
+             Install from source with `cargo install --git https://github.com/{REPO}`.",
+            release.version
+        );
+    };
+
+    let client = tls::client_builder()
+        .user_agent(format!("ccrs/{CURRENT}"))
+        .build()?;
+
+    println!("Downloading ccrs {}...", release.version);
+    let sums = download(&client, sums_url).await?;
+    let bytes = download(&client, url).await?;
+    verify_checksum(&bytes, &String::from_utf8_lossy(&sums), &name)?;
+
+    let exe = std::env::current_exe().context("Could not locate the running binary")?;
+    replace_binary(&exe, &bytes)?;
+
+    println!("Updated ccrs {CURRENT} → {}.", release.version);
+    Ok(())
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let bytes = client
+        .get(url)
+        .send()
+        .await
+        .context("Failed to download the release")?
+        .error_for_status()
+        .context("Failed to download the release")?
+        .bytes()
+        .await?;
+    Ok(bytes.to_vec())
+}
+
+/// Check `contents` against the line for `name` in a `sha256sum` listing
+/// (`<hex>  <name>`, or `<hex> *<name>` for binary mode).
+fn verify_checksum(contents: &[u8], sums: &str, name: &str) -> Result<()> {
+    let expected = sums
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, file)| file.trim_start().trim_start_matches('*') == name)
+        .map(|(hash, _)| hash.to_ascii_lowercase())
+        .with_context(|| format!("{CHECKSUMS_ASSET} has no entry for {name}"))?;
+
+    let actual = hex::encode(Sha256::digest(contents));
+    if actual != expected {
+        bail!(
+            "The downloaded {name} doesn't match its checksum (expected {expected}, got \
+             {actual}). The binary was left as it is."
+        );
+    }
+    Ok(())
+}
+
+/// Swap `exe` for `contents`. The new binary is written next to the old one
+/// first, so a failed download or write leaves the install untouched.
+fn replace_binary(exe: &Path, contents: &[u8]) -> Result<()> {
+    let staged = sibling(exe, "new");
+    let old = sibling(exe, "old");
+
+    fs::write(&staged, contents)
+        .with_context(|| format!("Failed to write {} (is it writable?)", staged.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))
+            .context("Failed to make the new binary executable")?;
+    }
+
+    // Windows can't overwrite a running executable but can rename it
+    fs::rename(exe, &old).with_context(|| format!("Failed to move {}", exe.display()))?;
+    if let Err(e) = fs::rename(&staged, exe) {
+        let _ = fs::rename(&old, exe);
+        return Err(e).with_context(|| format!("Failed to replace {}", exe.display()));
+    }
+    let _ = fs::remove_file(&old);
+
+    Ok(())
+}
+
+/// `ccrs` → `ccrs.<suffix>`, in the same directory.
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+    exe.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(parse_version("v1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("0.10.0"), Some((0, 10, 0)));
+        assert_eq!(parse_version("1.0.0-beta.1"), None);
+        assert_eq!(parse_version("1.2"), None);
+
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("2.0.0-rc.1", "1.0.0"));
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("update-check.json");
+
+        assert!(load_state(&path).is_none());
+
+        save_state(&path, 42, Some("1.2.3"));
+        assert_eq!(load_state(&path), Some((42, Some("1.2.3".to_string()))));
+
+        save_state(&path, 43, None);
+        assert_eq!(load_state(&path), Some((43, None)));
+    }
+
+    #[test]
+    fn checksums_must_match() {
+        let build = b"new build";
+        let hash = hex::encode(Sha256::digest(build));
+        let sums = format!("{hash}  ccrs-x86_64-linux\n{hash} *ccrs-aarch64-macos\n");
+
+        verify_checksum(build, &sums, "ccrs-x86_64-linux").unwrap();
+        verify_checksum(build, &sums, "ccrs-aarch64-macos").unwrap();
+        assert!(verify_checksum(b"tampered", &sums, "ccrs-x86_64-linux").is_err());
+        let missing = verify_checksum(build, &sums, "ccrs-x86_64-windows.exe").unwrap_err();
+        assert!(missing.to_string().contains("no entry"));
+    }
+
+    #[test]
+    fn replace_binary_swaps_in_place() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exe = tmp.path().join("ccrs");
+        fs::write(&exe, "old build").unwrap();
+
+        replace_binary(&exe, b"new build").unwrap();
+
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new build");
+        assert!(!sibling(&exe, "new").exists());
+        assert!(!sibling(&exe, "old").exists());
+    }
+}
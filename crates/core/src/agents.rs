@@ -0,0 +1,282 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// A subagent defined in `.claude/agents/<name>.md`:
+///
+/// ```markdown
+/// ---
+/// name: reviewer
+/// description: Reviews changes for bugs and style issues
+/// tools: Read, Grep, Glob
+/// model: haiku
+/// ---
+/// You are a careful code reviewer...
+/// ```
+///
+/// `tools` and `model` are optional: without them the agent gets every tool
+/// (except Task) and the session's model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentDef {
+    pub name: String,
+    pub description: String,
+    pub tools: Option<Vec<String>>,
+    pub model: Option<String>,
+    pub prompt: String,
+    pub path: PathBuf,
+}
+
+impl AgentDef {
+    pub fn parse(text: &str, path: &Path) -> Result<Self> {
+        let rest = text
+            .strip_prefix("---")
+            .context("Missing frontmatter (expected a leading `---` line)")?;
+        let (frontmatter, prompt) = rest
+            .split_once("\n---")
+            .context("Unterminated frontmatter (expected a closing `---` line)")?;
+
+        let mut name = None;
+        let mut description = None;
+        let mut tools = None;
+        let mut model = None;
+
+        for line in frontmatter.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .with_context(|| format!("Invalid frontmatter line: {line}"))?;
+            let value = value.trim().to_string();
+
+            match key.trim() {
+                "name" => name = Some(value),
+                "description" => description = Some(value),
+                "tools" => {
+                    tools = Some(
+                        value
+                            .split(',')
+                            .map(|t| t.trim().to_string())
+                            .filter(|t| !t.is_empty())
+                            .collect(),
+                    )
+                }
+                "model" => model = Some(value).filter(|m| !m.is_empty() && m != "inherit"),
+                _ => {} // Unknown keys are ignored for forward compatibility
+            }
+        }
+
+        let name = name.context("Missing `name` in frontmatter")?;
+        if !is_valid_name(&name) {
+            bail!("Invalid agent name `{name}` (use lowercase letters, digits and dashes)");
+        }
+
+        // Drop the rest of the closing `---` line
+        let prompt = prompt.split_once('\n').map_or("", |(_, p)| p).trim();
+
+        Ok(Self {
+            name,
+            description: description.context("Missing `description` in frontmatter")?,
+            tools,
+            model,
+            prompt: prompt.to_string(),
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!(
+            "---\nname: {}\ndescription: {}\n",
+            self.name, self.description
+        );
+        if let Some(tools) = &self.tools {
+            text.push_str(&format!("tools: {}\n", tools.join(", ")));
+        }
+        if let Some(model) = &self.model {
+            text.push_str(&format!("model: {model}\n"));
+        }
+        text.push_str("---\n");
+        text.push_str(&self.prompt);
+        text.push('\n');
+        text
+    }
+
+    /// Whether this agent may use `tool`.
+    pub fn allows(&self, tool: &str) -> bool {
+        self.tools
+            .as_ref()
+            .is_none_or(|tools| tools.iter().any(|t| t == tool))
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// `.claude/agents` in the project.
+pub fn project_agents_dir(project_dir: &Path) -> PathBuf {
+    project_dir.join(".claude").join("agents")
+}
+
+/// Agent directories in load order: user, then project.
+pub fn agent_dirs(project_dir: &Path) -> Vec<PathBuf> {
+    let user = dirs::home_dir().map(|h| h.join(".claude").join("agents"));
+    user.into_iter()
+        .chain([project_agents_dir(project_dir)])
+        .collect()
+}
+
+/// Load agents from the user and project directories. Project agents
+/// replace user agents of the same name; unparsable files are returned as
+/// errors alongside the agents that did load.
+pub fn load(project_dir: &Path) -> (Vec<AgentDef>, Vec<(PathBuf, anyhow::Error)>) {
+    load_from_dirs(&agent_dirs(project_dir))
+}
+
+fn load_from_dirs(dirs: &[PathBuf]) -> (Vec<AgentDef>, Vec<(PathBuf, anyhow::Error)>) {
+    let mut agents: Vec<AgentDef> = Vec::new();
+    let mut errors = Vec::new();
+
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let parsed = fs::read_to_string(&path)
+                .context("Failed to read agent file")
+                .and_then(|text| AgentDef::parse(&text, &path));
+
+            match parsed {
+                Ok(agent) => {
+                    agents.retain(|a| a.name != agent.name);
+                    agents.push(agent);
+                }
+                Err(e) => errors.push((path, e)),
+            }
+        }
+    }
+
+    (agents, errors)
+}
+
+/// Write a new agent definition, refusing to overwrite an existing one.
+pub fn create(dir: &Path, agent: &AgentDef) -> Result<PathBuf> {
+    if !is_valid_name(&agent.name) {
+        bail!(
+            "Invalid agent name `{}` (use lowercase letters, digits and dashes)",
+            agent.name
+        );
+    }
+
+    let path = dir.join(format!("{}.md", agent.name));
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    fs::write(&path, agent.to_markdown())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REVIEWER: &str = "---\n\
+        name: reviewer\n\
+        description: Reviews diffs\n\
+        tools: Read, Grep,Glob\n\
+        model: haiku\n\
+        ---\n\
+        You review code.\n\
+        Be terse.\n";
+
+    #[test]
+    fn parse_reads_frontmatter_and_prompt() {
+        let agent = AgentDef::parse(REVIEWER, Path::new("reviewer.md")).unwrap();
+
+        assert_eq!(agent.name, "reviewer");
+        assert_eq!(agent.description, "Reviews diffs");
+        assert_eq!(
+            agent.tools,
+            Some(vec!["Read".into(), "Grep".into(), "Glob".into()])
+        );
+        assert_eq!(agent.model.as_deref(), Some("haiku"));
+        assert_eq!(agent.prompt, "You review code.\nBe terse.");
+        assert!(agent.allows("Grep"));
+        assert!(!agent.allows("Bash"));
+    }
+
+    #[test]
+    fn parse_defaults_and_errors() {
+        let agent = AgentDef::parse(
+            "---\nname: helper\ndescription: Helps\nmodel: inherit\n---\nHi",
+            Path::new("helper.md"),
+        )
+        .unwrap();
+        assert!(agent.tools.is_none());
+        assert!(agent.model.is_none());
+        assert!(agent.allows("Bash"));
+
+        assert!(AgentDef::parse("no frontmatter", Path::new("x.md")).is_err());
+        assert!(AgentDef::parse("---\nname: x\n---\n", Path::new("x.md")).is_err());
+        assert!(
+            AgentDef::parse(
+                "---\nname: Bad Name\ndescription: d\n---\n",
+                Path::new("x.md")
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn markdown_round_trips() {
+        let agent = AgentDef::parse(REVIEWER, Path::new("reviewer.md")).unwrap();
+        let again = AgentDef::parse(&agent.to_markdown(), Path::new("reviewer.md")).unwrap();
+        assert_eq!(agent, again);
+    }
+
+    #[test]
+    fn project_agents_override_user_agents() {
+        let tmp = tempfile::tempdir().unwrap();
+        let user = tmp.path().join("user");
+        let project = tmp.path().join("project");
+        fs::create_dir_all(&user).unwrap();
+        fs::create_dir_all(&project).unwrap();
+
+        fs::write(user.join("reviewer.md"), REVIEWER).unwrap();
+        fs::write(
+            project.join("reviewer.md"),
+            "---\nname: reviewer\ndescription: Project reviewer\n---\nStrict.",
+        )
+        .unwrap();
+        fs::write(project.join("broken.md"), "oops").unwrap();
+        fs::write(project.join("notes.txt"), "ignored").unwrap();
+
+        let (agents, errors) = load_from_dirs(&[user, project]);
+
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0].description, "Project reviewer");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.ends_with("broken.md"));
+    }
+
+    #[test]
+    fn create_refuses_to_overwrite() {
+        let tmp = tempfile::tempdir().unwrap();
+        let agent = AgentDef::parse(REVIEWER, Path::new("reviewer.md")).unwrap();
+
+        let path = create(tmp.path(), &agent).unwrap();
+        assert_eq!(path, tmp.path().join("reviewer.md"));
+        assert!(create(tmp.path(), &agent).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
@@ -6,11 +7,17 @@ use reqwest_eventsource::{Event, EventSource};
 use serde::{Deserialize, Serialize};
 use tokio_util::sync::CancellationToken;
 
+use crate::bedrock::{Bedrock, EventStream};
 use crate::event::EventHandler;
+use crate::models::ListedModel;
+use crate::openai::{ChatStream, OpenAi};
+use crate::tls;
+use crate::vertex::Vertex;
 
 const API_URL: &str = "https://api.anthropic.com/v1/messages";
+const MODELS_URL: &str = "https://api.anthropic.com/v1/models";
 const API_VERSION: &str = "2023-06-01";
-const MAX_TOKENS: u32 = 16384;
+pub const DEFAULT_MAX_TOKENS: u32 = 16384;
 
 // Conservative limit for request payload size (Anthropic's limit is ~5MB)
 const MAX_REQUEST_SIZE: usize = 4 * 1024 * 1024; // 4 MB
@@ -24,6 +31,21 @@ pub const AVAILABLE_MODELS: &[(&str, &str)] = &[
     ("claude-haiku-4-5", "Haiku 4.5"),
 ];
 
+/// Context window of the available models, in tokens.
+pub const CONTEXT_WINDOW: u64 = 200_000;
+
+/// Find a known model by exact ID, then by substring of its ID or label.
+pub fn resolve_model(requested: &str) -> Option<&'static (&'static str, &'static str)> {
+    AVAILABLE_MODELS
+        .iter()
+        .find(|(id, _)| *id == requested)
+        .or_else(|| {
+            AVAILABLE_MODELS.iter().find(|(id, label)| {
+                id.contains(requested) || label.to_lowercase().contains(&requested.to_lowercase())
+            })
+        })
+}
+
 // ---------------------------------------------------------------------------
 // Content model
 // ---------------------------------------------------------------------------
@@ -80,6 +102,29 @@ pub enum ContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+
+    #[serde(rename = "image")]
+    Image { source: ImageSource },
+
+    /// Extended thinking. Sent back unchanged with the rest of the turn;
+    /// the signature lets the API verify it.
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+
+    /// Thinking the safety systems encrypted; only the API can read it.
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+}
+
+/// Where an image block's data comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ImageSource {
+    #[serde(rename = "base64")]
+    Base64 { media_type: String, data: String },
+
+    #[serde(rename = "url")]
+    Url { url: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,10 +133,109 @@ pub struct Message {
     pub content: Content,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Usage {
     pub input_tokens: u64,
     pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Tokens the conversation occupies after a request with this usage:
+    /// the whole prompt, cached or not, plus the reply.
+    pub fn context_tokens(&self) -> u64 {
+        self.input_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+            + self.output_tokens
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+/// Rough token count of `messages`, at about 4 bytes per token, for when
+/// the API has not reported one.
+pub fn estimate_tokens(messages: &[Message]) -> u64 {
+    let bytes: usize = messages
+        .iter()
+        .map(|m| serde_json::to_string(&m.content).map_or(0, |s| s.len()))
+        .sum();
+    bytes as u64 / 4
+}
+
+/// Token usage of one user turn (a prompt and all tool round-trips it
+/// triggered), with the model that served it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnUsage {
+    pub model: String,
+    pub usage: Usage,
+}
+
+/// Result of [`Session::check_model_access`](crate::session::Session::check_model_access).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelAccess {
+    Granted,
+    /// The credentials were rejected (HTTP 401/403).
+    Unauthorized(String),
+    /// The model does not exist or is not available to this account.
+    NotFound,
+    Other(u16, String),
+}
+
+/// Why a streamed request failed. Returned inside `anyhow::Error`; callers
+/// that care recover it with `downcast_ref`.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("Cancelled")]
+    Cancelled,
+    /// The credentials were rejected (HTTP 401/403).
+    #[error("Authentication failed (HTTP {0}). Run /login to sign in again.")]
+    Unauthorized(u16),
+    /// Any other failed request or broken stream; `status` is `None` for
+    /// network errors.
+    #[error("{message}")]
+    Request {
+        status: Option<u16>,
+        message: String,
+    },
+}
+
+impl ApiError {
+    fn from_stream(e: reqwest_eventsource::Error) -> Self {
+        let status = match &e {
+            reqwest_eventsource::Error::InvalidStatusCode(status, _) => Some(status.as_u16()),
+            _ => None,
+        };
+
+        match status {
+            Some(code @ (401 | 403)) => Self::Unauthorized(code),
+            // Better error message for the common case
+            Some(400) => Self::Request {
+                status,
+                message: "API request rejected (400 Bad Request). The request may be too large. \
+                          Try using /clear to start a new conversation."
+                    .to_string(),
+            },
+            _ => Self::Request {
+                status,
+                message: format!("Stream error: {e}"),
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -105,6 +249,9 @@ pub struct StreamResult {
     pub content: Vec<ContentBlock>,
     pub usage: Usage,
     pub stop_reason: StopReason,
+    /// Tool calls whose arguments didn't parse, by id: they're answered
+    /// with this error instead of being run.
+    pub invalid_tool_calls: HashMap<String, String>,
 }
 
 // ---------------------------------------------------------------------------
@@ -115,6 +262,10 @@ enum BlockKind {
     Text {
         text: String,
     },
+    Thinking {
+        thinking: String,
+        signature: String,
+    },
     ToolUse {
         id: String,
         name: String,
@@ -134,10 +285,7 @@ impl StreamState {
         Self {
             blocks: Vec::new(),
             current: None,
-            usage: Usage {
-                input_tokens: 0,
-                output_tokens: 0,
-            },
+            usage: Usage::default(),
             stop_reason: StopReason::EndTurn,
         }
     }
@@ -153,6 +301,18 @@ impl StreamState {
             "text" => Some(BlockKind::Text {
                 text: String::new(),
             }),
+            "thinking" => Some(BlockKind::Thinking {
+                thinking: String::new(),
+                signature: String::new(),
+            }),
+            // Arrives whole in the start event
+            "redacted_thinking" => {
+                let data = parsed["content_block"]["data"].as_str().unwrap_or("");
+                self.blocks.push(ContentBlock::RedactedThinking {
+                    data: data.to_string(),
+                });
+                None
+            }
             "tool_use" => {
                 let block = &parsed["content_block"];
                 let id = block["id"].as_str().unwrap_or("").to_string();
@@ -183,6 +343,17 @@ impl StreamState {
                     text.push_str(chunk);
                 }
             }
+            (Some(BlockKind::Thinking { thinking, .. }), "thinking_delta") => {
+                if let Some(chunk) = delta.get("thinking").and_then(|t| t.as_str()) {
+                    handler.on_thinking(chunk);
+                    thinking.push_str(chunk);
+                }
+            }
+            (Some(BlockKind::Thinking { signature, .. }), "signature_delta") => {
+                if let Some(chunk) = delta.get("signature").and_then(|t| t.as_str()) {
+                    signature.push_str(chunk);
+                }
+            }
             (Some(BlockKind::ToolUse { json, .. }), "input_json_delta") => {
                 if let Some(chunk) = delta.get("partial_json").and_then(|t| t.as_str()) {
                     json.push_str(chunk);
@@ -202,6 +373,15 @@ impl StreamState {
             BlockKind::Text { text } => {
                 self.blocks.push(ContentBlock::Text { text });
             }
+            BlockKind::Thinking {
+                thinking,
+                signature,
+            } => {
+                self.blocks.push(ContentBlock::Thinking {
+                    thinking,
+                    signature,
+                });
+            }
             BlockKind::ToolUse { id, name, json } => {
                 let input = serde_json::from_str(&json)
                     .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
@@ -215,6 +395,7 @@ impl StreamState {
             content: self.blocks,
             usage: self.usage,
             stop_reason: self.stop_reason,
+            invalid_tool_calls: HashMap::new(),
         }
     }
 }
@@ -223,16 +404,52 @@ impl StreamState {
 // API client
 // ---------------------------------------------------------------------------
 
+#[derive(Clone)]
 pub(crate) struct ApiClient {
     client: reqwest::Client,
     access_token: String,
     is_oauth: bool,
     model: String,
+    /// Output token limit per request, not counting thinking.
+    max_tokens: u32,
+    /// Extended thinking budget per request, in tokens; `None` disables it.
+    thinking: Option<u32>,
+    /// Where requests go; the token is only used by the Anthropic API.
+    backend: Backend,
+}
+
+/// The provider serving the models.
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Backend {
+    #[default]
+    Anthropic,
+    Bedrock(Bedrock),
+    Vertex(Vertex),
+    OpenAi(OpenAi),
+}
+
+impl Backend {
+    /// `None` for the Anthropic API, else the provider and where it runs.
+    pub(crate) fn describe(&self) -> Option<String> {
+        match self {
+            Self::Anthropic => None,
+            Self::Bedrock(bedrock) => Some(format!("Amazon Bedrock ({})", bedrock.region())),
+            Self::Vertex(vertex) => Some(format!(
+                "Google Vertex AI ({}, {})",
+                vertex.project(),
+                vertex.region()
+            )),
+            Self::OpenAi(openai) => Some(format!(
+                "the OpenAI-compatible endpoint at {}",
+                openai.base_url()
+            )),
+        }
+    }
 }
 
 impl ApiClient {
     pub(crate) fn new(access_token: String, is_oauth: bool) -> Self {
-        let client = reqwest::Client::builder()
+        let client = tls::client_builder()
             .timeout(Duration::from_secs(300))
             .build()
             .expect("failed to build HTTP client");
@@ -242,9 +459,20 @@ impl ApiClient {
             access_token,
             is_oauth,
             model: DEFAULT_MODEL.to_string(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+            thinking: None,
+            backend: Backend::Anthropic,
         }
     }
 
+    pub(crate) fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    pub(crate) fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
     pub(crate) fn model(&self) -> &str {
         &self.model
     }
@@ -253,6 +481,23 @@ impl ApiClient {
         self.model = model;
     }
 
+    pub(crate) fn set_max_tokens(&mut self, max_tokens: u32) {
+        self.max_tokens = max_tokens;
+    }
+
+    pub(crate) fn thinking(&self) -> Option<u32> {
+        self.thinking
+    }
+
+    pub(crate) fn set_thinking(&mut self, budget: Option<u32>) {
+        self.thinking = budget;
+    }
+
+    pub(crate) fn set_credentials(&mut self, access_token: String, is_oauth: bool) {
+        self.access_token = access_token;
+        self.is_oauth = is_oauth;
+    }
+
     /// Truncate tool results in messages to prevent oversized requests
     fn truncate_tool_results(messages: &[Message]) -> Vec<Message> {
         messages
@@ -301,33 +546,105 @@ impl ApiClient {
             .collect()
     }
 
-    fn build_request(
-        &self,
-        messages: &[Message],
-        system_prompt: Option<&str>,
-        tools: Option<&[serde_json::Value]>,
-    ) -> reqwest::RequestBuilder {
-        let mut req = self
-            .client
-            .post(API_URL)
-            .header("anthropic-version", API_VERSION)
-            .header("content-type", "application/json");
+    fn authorize(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let req = req.header("anthropic-version", API_VERSION);
 
         if self.is_oauth {
-            req = req
-                .header("authorization", format!("Bearer {}", self.access_token))
-                .header("anthropic-beta", "oauth-2025-04-20");
+            req.header("authorization", format!("Bearer {}", self.access_token))
+                .header("anthropic-beta", "oauth-2025-04-20")
         } else {
-            req = req.header("x-api-key", &self.access_token);
+            req.header("x-api-key", &self.access_token)
+        }
+    }
+
+    /// Probe whether the credentials can use the current model. Errors mean
+    /// the API could not be reached at all.
+    pub(crate) async fn check_model_access(&self) -> Result<ModelAccess> {
+        match &self.backend {
+            Backend::Anthropic => {}
+            Backend::Bedrock(bedrock) => {
+                return bedrock.check_model_access(&self.client, &self.model).await;
+            }
+            Backend::Vertex(vertex) => {
+                return vertex.check_model_access(&self.client, &self.model).await;
+            }
+            Backend::OpenAi(openai) => {
+                return openai.check_model_access(&self.client, &self.model).await;
+            }
+        }
+
+        let resp = self
+            .authorize(self.client.get(format!("{MODELS_URL}/{}", self.model)))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .context("Failed to reach the API")?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(ModelAccess::Granted);
         }
 
+        let body = resp.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"]["message"].as_str().map(String::from))
+            .unwrap_or(body);
+
+        Ok(match status.as_u16() {
+            401 | 403 => ModelAccess::Unauthorized(message),
+            404 => ModelAccess::NotFound,
+            code => ModelAccess::Other(code, message),
+        })
+    }
+
+    /// The models the credentials can use, as listed by the API.
+    pub(crate) async fn list_models(&self) -> Result<Vec<ListedModel>> {
+        #[derive(Deserialize)]
+        struct Page {
+            data: Vec<ListedModel>,
+        }
+
+        anyhow::ensure!(
+            matches!(self.backend, Backend::Anthropic),
+            "Cloud provider models aren't listed by the Anthropic API"
+        );
+
+        let resp = self
+            .authorize(self.client.get(format!("{MODELS_URL}?limit=100")))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .context("Failed to reach the API")?
+            .error_for_status()
+            .context("Failed to list models")?;
+
+        let page: Page = resp.json().await.context("Invalid model list")?;
+        Ok(page.data)
+    }
+
+    fn request_body(
+        &self,
+        messages: &[Message],
+        system_prompt: Option<&str>,
+        tools: Option<&[serde_json::Value]>,
+    ) -> serde_json::Value {
         let mut body = serde_json::json!({
             "model": self.model,
-            "max_tokens": MAX_TOKENS,
+            "max_tokens": self.max_tokens,
             "stream": true,
             "messages": messages,
         });
 
+        if let Some(budget) = self.thinking {
+            // The budget counts against max_tokens, so keep room to answer
+            body["max_tokens"] = serde_json::json!(self.max_tokens + budget);
+            body["thinking"] = serde_json::json!({
+                "type": "enabled",
+                "budget_tokens": budget,
+            });
+        }
+
         if let Some(prompt) = system_prompt {
             body["system"] = serde_json::json!(prompt);
         }
@@ -338,7 +655,7 @@ impl ApiClient {
             body["tools"] = serde_json::json!(tools);
         }
 
-        req.json(&body)
+        body
     }
 
     pub(crate) async fn stream_message(
@@ -353,22 +670,7 @@ impl ApiClient {
         let truncated_messages = Self::truncate_tool_results(messages);
 
         // Build the request body to check its size
-        let mut body = serde_json::json!({
-            "model": self.model,
-            "max_tokens": MAX_TOKENS,
-            "stream": true,
-            "messages": truncated_messages,
-        });
-
-        if let Some(prompt) = system_prompt {
-            body["system"] = serde_json::json!(prompt);
-        }
-
-        if let Some(tools) = tools
-            && !tools.is_empty()
-        {
-            body["tools"] = serde_json::json!(tools);
-        }
+        let body = self.request_body(&truncated_messages, system_prompt, tools);
 
         // Check request size
         let body_json = serde_json::to_string(&body)?;
@@ -382,7 +684,38 @@ impl ApiClient {
             );
         }
 
-        let request = self.build_request(&truncated_messages, system_prompt, tools);
+        tracing::debug!(
+            model = %self.model,
+            messages = truncated_messages.len(),
+            bytes = body_size,
+            "sending message"
+        );
+
+        let request = match &self.backend {
+            Backend::Anthropic => self
+                .authorize(self.client.post(API_URL))
+                .header("content-type", "application/json")
+                .body(body_json),
+            Backend::Bedrock(bedrock) => {
+                return self.stream_bedrock(bedrock, body, handler, cancel).await;
+            }
+            Backend::OpenAi(openai) => {
+                let request = openai.stream_request(
+                    &self.client,
+                    &self.model,
+                    self.max_tokens,
+                    &truncated_messages,
+                    system_prompt,
+                    tools,
+                );
+                return self.stream_openai(request, handler, cancel).await;
+            }
+            // Vertex answers with the same server-sent events
+            Backend::Vertex(vertex) => tokio::select! {
+                req = vertex.stream_request(&self.client, &self.model, body) => req?,
+                () = cancel.cancelled() => return Err(ApiError::Cancelled.into()),
+            },
+        };
         let mut es = EventSource::new(request).context("Failed to create event source")?;
 
         let mut state = StreamState::new();
@@ -405,30 +738,110 @@ impl ApiClient {
                         Err(reqwest_eventsource::Error::StreamEnded) => break,
                         Err(e) => {
                             es.close();
+                            tracing::warn!(model = %self.model, "stream failed: {e}");
+                            return Err(ApiError::from_stream(e).into());
+                        }
+                    }
+                }
 
-                            // Better error messages for common cases
-                            let err_str = e.to_string();
+                () = cancel.cancelled() => {
+                    es.close();
+                    return Err(ApiError::Cancelled.into());
+                }
+            }
+        }
 
-                            if err_str.contains("400") || err_str.contains("Bad Request") {
-                                anyhow::bail!(
-                                    "API request rejected (400 Bad Request). The request may be too large. \
-                                     Try using /clear to start a new conversation."
-                                );
-                            }
+        Ok(state.into_result())
+    }
+
+    /// [`stream_message`](Self::stream_message) through Bedrock, whose
+    /// stream carries the same events in AWS event stream framing.
+    async fn stream_bedrock(
+        &self,
+        bedrock: &Bedrock,
+        body: serde_json::Value,
+        handler: &mut dyn EventHandler,
+        cancel: &CancellationToken,
+    ) -> Result<StreamResult> {
+        let resp = tokio::select! {
+            resp = bedrock.invoke_stream(&self.client, &self.model, body) => resp?,
+            () = cancel.cancelled() => return Err(ApiError::Cancelled.into()),
+        };
+
+        let mut bytes = resp.bytes_stream();
+        let mut events = EventStream::default();
+        let mut state = StreamState::new();
 
-                            anyhow::bail!("Stream error: {e}");
+        loop {
+            tokio::select! {
+                chunk = bytes.next() => {
+                    let Some(chunk) = chunk else { break };
+                    let chunk = chunk.map_err(|e| ApiError::Request {
+                        status: None,
+                        message: format!("Stream error: {e}"),
+                    })?;
+                    events.extend(&chunk);
+
+                    while let Some(event) = events.next_event()? {
+                        if handle_sse_event(&event.kind, &event.data, &mut state, handler)? {
+                            return Ok(state.into_result());
+                        }
+                    }
+                }
+
+                () = cancel.cancelled() => return Err(ApiError::Cancelled.into()),
+            }
+        }
+
+        Ok(state.into_result())
+    }
+
+    /// [`stream_message`](Self::stream_message) through an OpenAI-compatible
+    /// endpoint, whose server-sent events carry Chat Completions chunks.
+    async fn stream_openai(
+        &self,
+        request: reqwest::RequestBuilder,
+        handler: &mut dyn EventHandler,
+        cancel: &CancellationToken,
+    ) -> Result<StreamResult> {
+        let mut es = EventSource::new(request).context("Failed to create event source")?;
+        let mut stream = ChatStream::default();
+
+        loop {
+            tokio::select! {
+                event = es.next() => {
+                    let Some(event) = event else { break };
+
+                    match event {
+                        Ok(Event::Open) => {}
+                        Ok(Event::Message(msg)) if msg.data.trim() == "[DONE]" => {
+                            es.close();
+                            break;
+                        }
+                        Ok(Event::Message(msg)) => {
+                            let chunk: serde_json::Value = serde_json::from_str(&msg.data)?;
+                            if let Err(e) = stream.apply(&chunk, handler) {
+                                es.close();
+                                return Err(e);
+                            }
+                        }
+                        Err(reqwest_eventsource::Error::StreamEnded) => break,
+                        Err(e) => {
+                            es.close();
+                            tracing::warn!(model = %self.model, "stream failed: {e}");
+                            return Err(ApiError::from_stream(e).into());
                         }
                     }
                 }
 
                 () = cancel.cancelled() => {
                     es.close();
-                    anyhow::bail!("Cancelled");
+                    return Err(ApiError::Cancelled.into());
                 }
             }
         }
 
-        Ok(state.into_result())
+        Ok(stream.into_result())
     }
 }
 
@@ -442,10 +855,18 @@ fn handle_sse_event(
         "message_start" => {
             let parsed: serde_json::Value = serde_json::from_str(data)?;
 
-            if let Some(u) = parsed.get("message").and_then(|m| m.get("usage"))
-                && let Some(input) = u.get("input_tokens").and_then(|v| v.as_u64())
-            {
-                state.usage.input_tokens = input;
+            if let Some(u) = parsed.get("message").and_then(|m| m.get("usage")) {
+                let get = |key: &str| u.get(key).and_then(|v| v.as_u64());
+
+                if let Some(input) = get("input_tokens") {
+                    state.usage.input_tokens = input;
+                }
+                if let Some(created) = get("cache_creation_input_tokens") {
+                    state.usage.cache_creation_input_tokens = created;
+                }
+                if let Some(read) = get("cache_read_input_tokens") {
+                    state.usage.cache_read_input_tokens = read;
+                }
             }
         }
         "content_block_start" => {
@@ -530,4 +951,87 @@ mod tests {
             _ => panic!("Expected Blocks"),
         }
     }
+
+    #[derive(Default)]
+    struct Recorder {
+        text: String,
+        thinking: String,
+    }
+
+    impl EventHandler for Recorder {
+        fn on_text(&mut self, text: &str) {
+            self.text.push_str(text);
+        }
+        fn on_error(&mut self, _message: &str) {}
+        fn on_thinking(&mut self, text: &str) {
+            self.thinking.push_str(text);
+        }
+    }
+
+    #[test]
+    fn test_stream_keeps_thinking_blocks() {
+        let events = [
+            (
+                "content_block_start",
+                r#"{"index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"index":0,"delta":{"type":"thinking_delta","thinking":"Check the "}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"index":0,"delta":{"type":"thinking_delta","thinking":"tests."}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"index":0,"delta":{"type":"signature_delta","signature":"sig"}}"#,
+            ),
+            ("content_block_stop", r#"{"index":0}"#),
+            (
+                "content_block_start",
+                r#"{"index":1,"content_block":{"type":"redacted_thinking","data":"opaque"}}"#,
+            ),
+            ("content_block_stop", r#"{"index":1}"#),
+            (
+                "content_block_start",
+                r#"{"index":2,"content_block":{"type":"text","text":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"index":2,"delta":{"type":"text_delta","text":"Done."}}"#,
+            ),
+            ("content_block_stop", r#"{"index":2}"#),
+        ];
+
+        let mut state = StreamState::new();
+        let mut handler = Recorder::default();
+        for (event, data) in events {
+            handle_sse_event(event, data, &mut state, &mut handler).unwrap();
+        }
+
+        assert_eq!(handler.thinking, "Check the tests.");
+        assert_eq!(handler.text, "Done.");
+        let blocks = serde_json::to_value(state.into_result().content).unwrap();
+        assert_eq!(
+            blocks,
+            serde_json::json!([
+                {"type": "thinking", "thinking": "Check the tests.", "signature": "sig"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "text", "text": "Done."},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_thinking_budget_raises_max_tokens() {
+        let mut client = ApiClient::new(String::new(), false);
+        let body = client.request_body(&[], None, None);
+        assert!(body.get("thinking").is_none());
+
+        client.set_thinking(Some(8000));
+        let body = client.request_body(&[], None, None);
+        assert_eq!(body["thinking"]["budget_tokens"], 8000);
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS + 8000);
+    }
 }
@@ -1,16 +1,33 @@
+use std::net::TcpListener;
+use std::time::Duration;
+
 use anyhow::{Context, Result};
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use rand::Rng;
 use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use url::Url;
 
-use crate::config::{Credentials, TokenType};
+use crate::config::{self, Credentials, TokenType};
+use crate::{store, tls};
 
 const CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+/// Shows the code for pasting; used when no local port could be opened.
 const REDIRECT_URI: &str = "https://console.anthropic.com/oauth/code/callback";
+/// Path the local callback server answers on.
+const LOCAL_CALLBACK_PATH: &str = "/callback";
 const AUTH_URL: &str = "https://claude.ai/oauth/authorize";
 const TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
+const REVOKE_URL: &str = "https://console.anthropic.com/v1/oauth/revoke";
 const SCOPES: &str = "org:create_api_key user:profile user:inference";
+/// Refresh this long before the access token expires, so a request that
+/// starts just before the deadline still gets through.
+const REFRESH_MARGIN_SECS: u64 = 5 * 60;
+
+/// How long the browser gets to come back before only a paste will do.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// How long a connection to the local server gets to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
 struct PkceChallenge {
     verifier: String,
@@ -43,13 +60,13 @@ fn generate_pkce() -> PkceChallenge {
     }
 }
 
-fn build_auth_url(pkce: &PkceChallenge) -> Result<String> {
+fn build_auth_url(pkce: &PkceChallenge, redirect_uri: &str) -> Result<String> {
     let mut url = Url::parse(AUTH_URL)?;
 
     url.query_pairs_mut()
         .append_pair("response_type", "code")
         .append_pair("client_id", CLIENT_ID)
-        .append_pair("redirect_uri", REDIRECT_URI)
+        .append_pair("redirect_uri", redirect_uri)
         .append_pair("scope", SCOPES)
         .append_pair("state", &pkce.state)
         .append_pair("code_challenge", &pkce.challenge)
@@ -64,70 +81,217 @@ struct TokenResponse {
     refresh_token: Option<String>,
     #[allow(dead_code)]
     token_type: Option<String>,
-    #[allow(dead_code)]
+    /// Lifetime of the access token, in seconds.
     expires_in: Option<u64>,
 }
 
+impl TokenResponse {
+    /// The access token, with its expiry made absolute.
+    fn access(&self) -> Credentials {
+        Credentials {
+            token: self.access_token.clone(),
+            is_oauth: true,
+            expires_at: self.expires_in.map(|secs| store::now() + secs),
+        }
+    }
+}
+
 /// The result of starting an OAuth flow. The caller is responsible for
 /// presenting `auth_url` to the user (e.g. opening a browser) and collecting
-/// the authorization code.
+/// the authorization code, from [`OAuthSession::wait_for_callback`] when
+/// [`listening`](OAuthSession::listening) or by pasting.
 pub struct OAuthSession {
     pub auth_url: String,
     verifier: String,
     state: String,
+    redirect_uri: String,
+    /// Local server the browser is redirected to; taken by the wait.
+    listener: Option<TcpListener>,
+}
+
+/// An authorization code, with the redirect URI it was issued for (the
+/// token exchange must repeat it).
+pub struct AuthCode {
+    pub code: String,
+    redirect_uri: String,
 }
 
 /// Begin an OAuth flow: generates PKCE parameters and returns an
-/// [`OAuthSession`] containing the URL the user must visit.
+/// [`OAuthSession`] containing the URL the user must visit. The browser is
+/// sent back to a local port when one can be opened, so the code arrives
+/// without pasting.
 pub fn start_oauth() -> Result<OAuthSession> {
     let pkce = generate_pkce();
-    let auth_url = build_auth_url(&pkce)?;
+
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|l| l.set_nonblocking(true).map(|()| l))
+        .inspect_err(|e| tracing::debug!("no local OAuth callback: {e}"))
+        .ok();
+    let redirect_uri = match listener.as_ref().and_then(|l| l.local_addr().ok()) {
+        Some(addr) => format!("http://localhost:{}{LOCAL_CALLBACK_PATH}", addr.port()),
+        None => REDIRECT_URI.to_string(),
+    };
+    let auth_url = build_auth_url(&pkce, &redirect_uri)?;
 
     Ok(OAuthSession {
         auth_url,
         verifier: pkce.verifier,
         state: pkce.state,
+        redirect_uri,
+        listener,
     })
 }
 
+impl OAuthSession {
+    /// Whether the browser will hand the code to [`Self::wait_for_callback`].
+    pub fn listening(&self) -> bool {
+        self.listener.is_some()
+    }
+
+    /// Wait for the browser's redirect to the local server and return its
+    /// code, or `None` if it hasn't come back within a few minutes. Other
+    /// requests (e.g. favicons) get a 404 and the wait goes on.
+    pub async fn wait_for_callback(&mut self) -> Result<Option<AuthCode>> {
+        self.wait_until(CALLBACK_TIMEOUT).await
+    }
+
+    async fn wait_until(&mut self, timeout: Duration) -> Result<Option<AuthCode>> {
+        let listener = self
+            .listener
+            .take()
+            .context("Not listening for the OAuth callback")?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        // Connections are read side by side, so one that never sends a
+        // request (a browser preconnect, another local process) can't hold
+        // up the redirect
+        let mut requests = tokio::task::JoinSet::new();
+        loop {
+            let (mut stream, target) = tokio::select! {
+                () = &mut deadline => return Ok(None),
+                accepted = listener.accept() => {
+                    let (mut stream, _) = accepted?;
+                    requests.spawn(async move {
+                        let target =
+                            tokio::time::timeout(REQUEST_TIMEOUT, read_request_target(&mut stream))
+                                .await
+                                .ok()
+                                .flatten();
+                        (stream, target)
+                    });
+                    continue;
+                }
+                Some(Ok(request)) = requests.join_next() => request,
+            };
+            let Some(target) = target else {
+                continue;
+            };
+            let Ok(url) = Url::parse(&format!("http://localhost{target}")) else {
+                continue;
+            };
+            if url.path() != LOCAL_CALLBACK_PATH {
+                let _ = respond(&mut stream, "404 Not Found", "Not found.").await;
+                continue;
+            }
+
+            let result = self.code_from_url(&url);
+            let (status, page) = match &result {
+                Ok(_) => ("200 OK", "Signed in to ccrs. You can close this tab."),
+                Err(_) => (
+                    "400 Bad Request",
+                    "Sign-in failed. Return to ccrs for details.",
+                ),
+            };
+            let _ = respond(&mut stream, status, page).await;
+            return result.map(Some);
+        }
+    }
+
+    fn code_from_url(&self, url: &Url) -> Result<AuthCode> {
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.to_string())
+        };
+
+        if let Some(error) = param("error") {
+            anyhow::bail!("Authorization failed: {error}");
+        }
+        let code = param("code").context("No 'code' parameter in callback URL")?;
+        if param("state").unwrap_or_default() != self.state {
+            anyhow::bail!("State mismatch — possible CSRF. Please restart.");
+        }
+
+        Ok(AuthCode {
+            code,
+            redirect_uri: self.redirect_uri.clone(),
+        })
+    }
+}
+
+/// The target of an HTTP request line, e.g. `/callback?code=…`.
+async fn read_request_target(stream: &mut tokio::net::TcpStream) -> Option<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    // The request line is all that's needed; stop at the end of the headers
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < 8192 {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    let mut parts = head.lines().next()?.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => Some(target.to_string()),
+        _ => None,
+    }
+}
+
+async fn respond(stream: &mut tokio::net::TcpStream, status: &str, text: &str) -> Result<()> {
+    let body = format!("<!doctype html><title>ccrs</title><p>{text}</p>");
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
 /// Extract the authorization code from a callback URL and verify the state.
 ///
 /// Accepts three formats:
 /// - Full URL: `https://…?code=…&state=…`
 /// - Code#state: `dn0Qsk…#530389…` (as shown on the callback page)
 /// - Bare code: `dn0Qsk…`
-pub fn parse_callback(session: &OAuthSession, input: &str) -> Result<String> {
-    if input.starts_with("http") {
+///
+/// With a local callback server, a browser on another machine can't reach
+/// it; the URL it ends up on can be pasted instead.
+pub fn parse_callback(session: &OAuthSession, input: &str) -> Result<AuthCode> {
+    let code = if input.starts_with("http") {
         let url = Url::parse(input).context("Invalid callback URL")?;
-
-        let code = url
-            .query_pairs()
-            .find(|(k, _)| k == "code")
-            .map(|(_, v)| v.to_string())
-            .context("No 'code' parameter in callback URL")?;
-
-        let returned_state = url
-            .query_pairs()
-            .find(|(k, _)| k == "state")
-            .map(|(_, v)| v.to_string())
-            .unwrap_or_default();
-
-        if returned_state != session.state {
-            anyhow::bail!("State mismatch — possible CSRF. Please restart.");
-        }
-
-        Ok(code)
+        return session.code_from_url(&url);
     } else if let Some((code, returned_state)) = input.split_once('#') {
         // The callback page displays "code#state"
         if returned_state != session.state {
             anyhow::bail!("State mismatch — possible CSRF. Please restart.");
         }
 
-        Ok(code.to_string())
+        code.to_string()
     } else {
         // Bare code only
-        Ok(input.to_string())
-    }
+        input.to_string()
+    };
+
+    Ok(AuthCode {
+        code,
+        redirect_uri: session.redirect_uri.clone(),
+    })
 }
 
 /// Exchange the authorization `code` obtained from the OAuth callback for
@@ -135,10 +299,10 @@ pub fn parse_callback(session: &OAuthSession, input: &str) -> Result<String> {
 /// contain the refresh token (if one was issued).
 pub async fn exchange_oauth_code(
     session: &OAuthSession,
-    code: &str,
+    code: &AuthCode,
     store_refresh: bool,
 ) -> Result<Credentials> {
-    let client = reqwest::Client::new();
+    let client = tls::client();
 
     // Reference script sends JSON, not form-urlencoded
     let resp = client
@@ -147,9 +311,9 @@ pub async fn exchange_oauth_code(
         .json(&serde_json::json!({
             "grant_type": "authorization_code",
             "client_id": CLIENT_ID,
-            "code": code,
+            "code": code.code,
             "state": session.state,
-            "redirect_uri": REDIRECT_URI,
+            "redirect_uri": code.redirect_uri,
             "code_verifier": session.verifier,
         }))
         .send()
@@ -168,26 +332,23 @@ pub async fn exchange_oauth_code(
         .context("Failed to parse token response")?;
 
     if store_refresh && let Some(refresh_token) = token_resp.refresh_token {
-        return Ok(Credentials {
-            token: refresh_token,
-            is_oauth: true,
-        });
+        return Ok(Credentials::new(refresh_token, true));
     }
 
-    Ok(Credentials {
-        token: token_resp.access_token,
-        is_oauth: true,
-    })
+    Ok(token_resp.access())
 }
 
-pub async fn refresh_access_token(creds: &Credentials) -> Result<(String, Credentials)> {
+/// Trade a refresh token for a new access token (with its expiry). Returns
+/// `(access, refresh)`, where `refresh` differs from `creds` when the server
+/// rotated it.
+pub async fn refresh_access_token(creds: &Credentials) -> Result<(Credentials, Credentials)> {
     anyhow::ensure!(
         creds.token_type() == TokenType::OAuthRefresh,
         "Expected OAuth refresh token, got {:?}",
         creds.token_type()
     );
 
-    let client = reqwest::Client::new();
+    let client = tls::client();
 
     let resp = client
         .post(TOKEN_URL)
@@ -212,14 +373,261 @@ pub async fn refresh_access_token(creds: &Credentials) -> Result<(String, Creden
         .await
         .context("Failed to parse token response")?;
 
-    let updated_creds = if let Some(new_refresh) = token_resp.refresh_token {
-        Credentials {
-            token: new_refresh,
-            is_oauth: true,
+    let updated_creds = match &token_resp.refresh_token {
+        Some(new_refresh) => Credentials::new(new_refresh.clone(), true),
+        None => creds.clone(),
+    };
+
+    Ok((token_resp.access(), updated_creds))
+}
+
+/// Outcome of [`revoke`].
+#[derive(Debug)]
+pub enum Revocation {
+    /// No credentials were stored.
+    NothingStored,
+    /// The server revoked the token and the credentials were deleted.
+    Revoked,
+    /// An API key was deleted. Only the Console can revoke it.
+    ApiKeyDeleted,
+    /// The credentials were deleted but the server didn't confirm the
+    /// revocation (e.g. offline), so the token may work until it expires.
+    DeletedOnly(anyhow::Error),
+}
+
+/// Sign `profile` out: revoke its stored OAuth token with the server, then
+/// delete the credentials file. The file goes even when revoking fails, so
+/// nothing usable is left on a shared machine.
+pub async fn revoke(profile: Option<&str>) -> Result<Revocation> {
+    let creds = match config::load_credentials(profile) {
+        Ok(Some(creds)) => creds,
+        Ok(None) => return Ok(Revocation::NothingStored),
+        // A file that can't be read can't be revoked, but it still goes
+        Err(e) => {
+            config::delete_credentials(profile)?;
+            return Ok(Revocation::DeletedOnly(e));
         }
-    } else {
-        creds.clone()
     };
 
-    Ok((token_resp.access_token, updated_creds))
+    let outcome = match creds.token_type() {
+        TokenType::ApiKey => Revocation::ApiKeyDeleted,
+        _ => match revoke_token(&creds).await {
+            Ok(()) => Revocation::Revoked,
+            Err(e) => Revocation::DeletedOnly(e),
+        },
+    };
+    config::delete_credentials(profile)?;
+    Ok(outcome)
+}
+
+async fn revoke_token(creds: &Credentials) -> Result<()> {
+    let resp = tls::client()
+        .post(REVOKE_URL)
+        .header("content-type", "application/json")
+        .json(&revocation_request(creds))
+        .send()
+        .await
+        .context("Failed to reach the revocation endpoint")?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        anyhow::bail!("Token revocation failed ({status}): {body}");
+    }
+    Ok(())
+}
+
+/// RFC 7009 revocation request for an OAuth token.
+fn revocation_request(creds: &Credentials) -> serde_json::Value {
+    let hint = match creds.token_type() {
+        TokenType::OAuthRefresh => "refresh_token",
+        _ => "access_token",
+    };
+    serde_json::json!({
+        "token": creds.token,
+        "token_type_hint": hint,
+        "client_id": CLIENT_ID,
+    })
+}
+
+/// Keeps the access token of a refresh-token login valid through a long
+/// session: once it is within a few minutes of expiring, the next request
+/// trades the refresh token for a new one first.
+#[derive(Debug, Clone)]
+pub struct TokenRefresh {
+    refresh: Credentials,
+    /// Expiry of the access token in use.
+    expires_at: Option<u64>,
+    /// Profile to save rotated refresh tokens to; `None` keeps them in
+    /// memory (credentials from the environment).
+    save_to: Option<Option<String>>,
+}
+
+impl TokenRefresh {
+    /// `access` is the token the session starts with. Returns `None` unless
+    /// `refresh` is a refresh token.
+    pub fn new(refresh: Credentials, access: &Credentials) -> Option<Self> {
+        (refresh.token_type() == TokenType::OAuthRefresh).then_some(Self {
+            refresh,
+            expires_at: access.expires_at,
+            save_to: None,
+        })
+    }
+
+    /// Save rotated refresh tokens to `profile`'s credentials file.
+    #[must_use]
+    pub fn save_to(mut self, profile: Option<String>) -> Self {
+        self.save_to = Some(profile);
+        self
+    }
+
+    /// Whether the access token should be replaced before a request made
+    /// at `now`. Tokens without a known expiry are kept.
+    pub fn is_due(&self, now: u64) -> bool {
+        self.expires_at
+            .is_some_and(|at| now + REFRESH_MARGIN_SECS >= at)
+    }
+
+    /// A new access token if the current one is about to expire.
+    pub async fn refresh_if_due(&mut self) -> Result<Option<String>> {
+        if !self.is_due(store::now()) {
+            return Ok(None);
+        }
+
+        let (access, refresh) = refresh_access_token(&self.refresh).await?;
+        // The old refresh token may be revoked now, so a failed save is
+        // only logged: this session still needs the new one.
+        if refresh.token != self.refresh.token
+            && let Some(profile) = &self.save_to
+            && let Err(e) = config::save_credentials(profile.as_deref(), &refresh)
+        {
+            tracing::warn!("could not save the rotated refresh token: {e:#}");
+        }
+        self.refresh = refresh;
+        self.expires_at = access.expires_at;
+        Ok(Some(access.token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(port: u16, target: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        let request = format!("GET {target} HTTP/1.1\r\nhost: localhost\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    fn port(session: &OAuthSession) -> u16 {
+        Url::parse(&session.redirect_uri).unwrap().port().unwrap()
+    }
+
+    #[tokio::test]
+    async fn local_callback_captures_the_code() {
+        let mut session = start_oauth().unwrap();
+        assert!(session.listening());
+        assert!(
+            session
+                .auth_url
+                .contains("redirect_uri=http%3A%2F%2Flocalhost%3A")
+        );
+        let (port, state) = (port(&session), session.state.clone());
+
+        let wait =
+            tokio::spawn(
+                async move { session.wait_for_callback().await.map(|c| c.map(|c| c.code)) },
+            );
+
+        // A connection that never sends anything doesn't block the others
+        let _idle = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        assert!(get(port, "/favicon.ico").await.starts_with("HTTP/1.1 404"));
+        let page = get(port, &format!("/callback?code=abc123&state={state}")).await;
+        assert!(page.starts_with("HTTP/1.1 200"), "{page}");
+        assert_eq!(wait.await.unwrap().unwrap().as_deref(), Some("abc123"));
+    }
+
+    #[tokio::test]
+    async fn local_callback_gives_up_at_the_deadline() {
+        let mut session = start_oauth().unwrap();
+        let _idle = tokio::net::TcpStream::connect(("127.0.0.1", port(&session)))
+            .await
+            .unwrap();
+
+        let code = session.wait_until(Duration::from_millis(50)).await;
+        assert!(code.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn local_callback_rejects_a_forged_state() {
+        let mut session = start_oauth().unwrap();
+        let port = port(&session);
+
+        let wait = tokio::spawn(async move { session.wait_for_callback().await.map(|_| ()) });
+
+        let page = get(port, "/callback?code=abc123&state=forged").await;
+        assert!(page.starts_with("HTTP/1.1 400"), "{page}");
+        let err = wait.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("State mismatch"), "{err}");
+    }
+
+    #[test]
+    fn pasted_codes_keep_the_session_redirect() {
+        let session = start_oauth().unwrap();
+
+        let pasted = format!("{}?code=xyz&state={}", session.redirect_uri, session.state);
+        let code = parse_callback(&session, &pasted).unwrap();
+        assert_eq!(code.code, "xyz");
+        assert_eq!(code.redirect_uri, session.redirect_uri);
+
+        assert!(parse_callback(&session, "xyz#wrong").is_err());
+        assert_eq!(parse_callback(&session, "xyz").unwrap().code, "xyz");
+    }
+
+    #[test]
+    fn refreshes_shortly_before_the_access_token_expires() {
+        let refresh = Credentials::new("sk-ant-ort-1".to_string(), true);
+        let access = Credentials {
+            expires_at: Some(10_000),
+            ..Credentials::new("sk-ant-oat-1".to_string(), true)
+        };
+
+        let tokens = TokenRefresh::new(refresh.clone(), &access).unwrap();
+        assert!(!tokens.is_due(10_000 - REFRESH_MARGIN_SECS - 1));
+        assert!(tokens.is_due(10_000 - REFRESH_MARGIN_SECS));
+        assert!(tokens.is_due(20_000));
+
+        // Without a known expiry the token is kept
+        let undated = Credentials::new("sk-ant-oat-1".to_string(), true);
+        assert!(
+            !TokenRefresh::new(refresh, &undated)
+                .unwrap()
+                .is_due(u64::MAX / 2)
+        );
+        // Only refresh tokens can renew anything
+        assert!(TokenRefresh::new(access.clone(), &access).is_none());
+    }
+
+    #[test]
+    fn revocation_names_the_token_type() {
+        let refresh = Credentials::new("sk-ant-ort-1".to_string(), true);
+        let body = revocation_request(&refresh);
+        assert_eq!(body["token"], "sk-ant-ort-1");
+        assert_eq!(body["token_type_hint"], "refresh_token");
+        assert_eq!(body["client_id"], CLIENT_ID);
+
+        let access = Credentials::new("sk-ant-oat-1".to_string(), true);
+        assert_eq!(
+            revocation_request(&access)["token_type_hint"],
+            "access_token"
+        );
+    }
 }
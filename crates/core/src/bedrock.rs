@@ -0,0 +1,994 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use crate::api::{ApiError, ModelAccess};
+use crate::config::BedrockSettings;
+use crate::store;
+
+/// Bedrock IDs of the built-in models.
+const MODEL_IDS: &[(&str, &str)] = &[
+    (
+        "claude-sonnet-4-5",
+        "anthropic.claude-sonnet-4-5-20250929-v1:0",
+    ),
+    ("claude-opus-4-6", "anthropic.claude-opus-4-6-v1"),
+    (
+        "claude-haiku-4-5",
+        "anthropic.claude-haiku-4-5-20251001-v1:0",
+    ),
+];
+
+/// Sent in the body, where the Anthropic API takes a version header.
+const BEDROCK_VERSION: &str = "bedrock-2023-05-31";
+
+/// Temporary credentials are replaced this long before they expire.
+const EXPIRY_MARGIN_SECS: u64 = 5 * 60;
+
+/// Amazon Bedrock as the model provider: which region and model IDs
+/// requests go to, and the AWS credentials that sign them.
+#[derive(Clone)]
+pub struct Bedrock {
+    region: String,
+    profile: String,
+    /// The profile was named in settings, so it beats keys in the
+    /// environment.
+    explicit_profile: bool,
+    inference_profile: Option<String>,
+    models: BTreeMap<String, String>,
+    /// Shared by clones, so subagents reuse the credentials.
+    credentials: Arc<Mutex<Option<AwsCredentials>>>,
+}
+
+impl std::fmt::Debug for Bedrock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Bedrock")
+            .field("region", &self.region)
+            .field("profile", &self.profile)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Bedrock {
+    /// Resolve the region and AWS profile from `settings`, the environment
+    /// and the AWS config file. Credentials are loaded on first use.
+    pub fn new(settings: &BedrockSettings) -> Result<Self> {
+        let env_profile = env("AWS_PROFILE");
+        let profile = settings
+            .aws_profile
+            .clone()
+            .or(env_profile)
+            .unwrap_or_else(|| "default".to_string());
+
+        let region = settings
+            .region
+            .clone()
+            .or_else(|| env("AWS_REGION"))
+            .or_else(|| env("AWS_DEFAULT_REGION"))
+            .or_else(|| profile_values(&profile).remove("region"))
+            .context(
+                "No AWS region for Bedrock. Set `bedrock.region` in settings or AWS_REGION.",
+            )?;
+        // It becomes part of the host the signed request is sent to
+        if region.is_empty()
+            || !region
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            bail!("Invalid AWS region {region:?} for Bedrock");
+        }
+
+        Ok(Self {
+            region,
+            explicit_profile: settings.aws_profile.is_some(),
+            profile,
+            inference_profile: settings.inference_profile.clone(),
+            models: settings.models.clone(),
+            credentials: Arc::default(),
+        })
+    }
+
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// The Bedrock model ID (or inference profile) for a ccrs model ID.
+    /// IDs ccrs doesn't know are used as given.
+    pub fn model_id(&self, model: &str) -> String {
+        if let Some(id) = self.models.get(model) {
+            return id.clone();
+        }
+        let id = MODEL_IDS
+            .iter()
+            .find(|(known, _)| *known == model)
+            .map_or(model, |(_, id)| *id);
+
+        match &self.inference_profile {
+            Some(prefix) if id.starts_with("anthropic.") => format!("{prefix}.{id}"),
+            _ => id.to_string(),
+        }
+    }
+
+    /// Start a streamed Messages request. `body` is the Anthropic API
+    /// request; the model moves into the URL.
+    pub(crate) async fn invoke_stream(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+        mut body: serde_json::Value,
+    ) -> Result<reqwest::Response> {
+        if let Some(fields) = body.as_object_mut() {
+            fields.remove("model");
+            fields.remove("stream");
+            fields.insert("anthropic_version".into(), BEDROCK_VERSION.into());
+        }
+        let path = format!(
+            "/model/{}/invoke-with-response-stream",
+            uri_encode(&self.model_id(model))
+        );
+
+        let resp = self
+            .send(
+                client,
+                "bedrock-runtime",
+                "POST",
+                &path,
+                serde_json::to_vec(&body)?,
+            )
+            .await
+            .map_err(|e| ApiError::Request {
+                status: None,
+                message: format!("{e:#}"),
+            })?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let message = error_message(&resp.text().await.unwrap_or_default());
+        Err(ApiError::Request {
+            status: Some(status.as_u16()),
+            message: format!("Bedrock request failed ({status}): {message}"),
+        }
+        .into())
+    }
+
+    /// Probe the model (or inference profile) through the Bedrock control
+    /// plane, for `/doctor`.
+    pub(crate) async fn check_model_access(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+    ) -> Result<ModelAccess> {
+        let id = self.model_id(model);
+        let path = if id.starts_with("anthropic.") {
+            format!("/foundation-models/{}", uri_encode(&id))
+        } else {
+            format!("/inference-profiles/{}", uri_encode(&id))
+        };
+
+        let resp = self
+            .send(client, "bedrock", "GET", &path, Vec::new())
+            .await
+            .context("Failed to reach Bedrock")?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(ModelAccess::Granted);
+        }
+
+        let message = error_message(&resp.text().await.unwrap_or_default());
+        Ok(match status.as_u16() {
+            401 | 403 => ModelAccess::Unauthorized(message),
+            404 => ModelAccess::NotFound,
+            code => ModelAccess::Other(code, message),
+        })
+    }
+
+    /// Send a SigV4-signed request to a Bedrock endpoint in our region.
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        service: &str,
+        method: &str,
+        path: &str,
+        payload: Vec<u8>,
+    ) -> Result<reqwest::Response> {
+        let creds = self.credentials().await?;
+        let host = format!("{service}.{}.amazonaws.com", self.region);
+        let headers = sign(
+            &creds,
+            &self.region,
+            "bedrock",
+            store::now(),
+            &Canonical {
+                method,
+                host: &host,
+                path,
+                payload: &payload,
+            },
+        );
+
+        let method = reqwest::Method::from_bytes(method.as_bytes())?;
+        let mut req = client
+            .request(method, format!("https://{host}{path}"))
+            .header("content-type", "application/json")
+            .body(payload);
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
+        Ok(req.send().await?)
+    }
+
+    async fn credentials(&self) -> Result<AwsCredentials> {
+        let mut cached = self.credentials.lock().await;
+        if let Some(creds) = &*cached
+            && creds
+                .expires_at
+                .is_none_or(|at| store::now() + EXPIRY_MARGIN_SECS < at)
+        {
+            return Ok(creds.clone());
+        }
+
+        let creds = self.load_credentials().await?;
+        *cached = Some(creds.clone());
+        Ok(creds)
+    }
+
+    /// The standard AWS chain: keys in the environment, the shared profile
+    /// (keys or `credential_process`), then the container and EC2 instance
+    /// endpoints.
+    async fn load_credentials(&self) -> Result<AwsCredentials> {
+        if !self.explicit_profile
+            && let Some(creds) = credentials_from_env()
+        {
+            return Ok(creds);
+        }
+        if let Some(creds) = credentials_from_profile(&self.profile).await? {
+            return Ok(creds);
+        }
+        if let Some(creds) = credentials_from_container().await? {
+            return Ok(creds);
+        }
+        if let Some(creds) = credentials_from_instance().await {
+            return Ok(creds);
+        }
+        bail!(
+            "No AWS credentials for Bedrock. Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, \
+             or configure the `{}` profile in ~/.aws/credentials.",
+            self.profile
+        )
+    }
+}
+
+/// The `message` of an AWS error body, or the body itself.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| {
+            v.get("message")
+                .or_else(|| v.get("Message"))
+                .and_then(|m| m.as_str())
+                .map(String::from)
+        })
+        .unwrap_or_else(|| body.to_string())
+}
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+// ---------------------------------------------------------------------------
+// AWS credentials
+// ---------------------------------------------------------------------------
+
+#[derive(Clone)]
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    /// Unix seconds; `None` for long-term keys.
+    expires_at: Option<u64>,
+}
+
+/// Temporary credentials as the container and instance endpoints and
+/// `credential_process` return them.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Temporary {
+    access_key_id: String,
+    secret_access_key: String,
+    #[serde(alias = "SessionToken")]
+    token: Option<String>,
+    expiration: Option<String>,
+}
+
+impl From<Temporary> for AwsCredentials {
+    fn from(t: Temporary) -> Self {
+        Self {
+            access_key_id: t.access_key_id,
+            secret_access_key: t.secret_access_key,
+            session_token: t.token,
+            expires_at: t.expiration.as_deref().and_then(parse_utc),
+        }
+    }
+}
+
+fn credentials_from_env() -> Option<AwsCredentials> {
+    Some(AwsCredentials {
+        access_key_id: env("AWS_ACCESS_KEY_ID")?,
+        secret_access_key: env("AWS_SECRET_ACCESS_KEY")?,
+        session_token: env("AWS_SESSION_TOKEN"),
+        expires_at: None,
+    })
+}
+
+async fn credentials_from_profile(profile: &str) -> Result<Option<AwsCredentials>> {
+    let mut values = profile_values(profile);
+
+    if let (Some(access_key_id), Some(secret_access_key)) = (
+        values.remove("aws_access_key_id"),
+        values.remove("aws_secret_access_key"),
+    ) {
+        return Ok(Some(AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token: values.remove("aws_session_token"),
+            expires_at: None,
+        }));
+    }
+
+    if let Some(command) = values.get("credential_process") {
+        return run_credential_process(command).await.map(Some);
+    }
+
+    // SSO and role assumption need the AWS SDK; the CLI can do it for us
+    if ["sso_session", "sso_start_url", "role_arn"]
+        .iter()
+        .any(|key| values.contains_key(*key))
+    {
+        bail!(
+            "The AWS profile `{profile}` needs SSO or role assumption, which ccrs can't do \
+             itself. Add `credential_process = aws configure export-credentials --profile \
+             {profile} --format process` to a profile and use that one."
+        );
+    }
+    Ok(None)
+}
+
+async fn run_credential_process(command: &str) -> Result<AwsCredentials> {
+    #[cfg(windows)]
+    let mut cmd = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    cmd.arg("/C");
+    #[cfg(not(windows))]
+    let mut cmd = tokio::process::Command::new("sh");
+    #[cfg(not(windows))]
+    cmd.arg("-c");
+
+    let output = cmd
+        .arg(command)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run credential_process `{command}`"))?;
+    if !output.status.success() {
+        bail!(
+            "credential_process `{command}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let temporary: Temporary = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("credential_process `{command}` printed invalid JSON"))?;
+    Ok(temporary.into())
+}
+
+/// ECS and EKS Pod Identity hand out credentials on a local endpoint.
+async fn credentials_from_container() -> Result<Option<AwsCredentials>> {
+    let url = match (
+        env("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI"),
+        env("AWS_CONTAINER_CREDENTIALS_FULL_URI"),
+    ) {
+        (Some(relative), _) => format!("http://169.254.170.2{relative}"),
+        (None, Some(full)) => full,
+        (None, None) => return Ok(None),
+    };
+    let token = match env("AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE") {
+        Some(path) => Some(std::fs::read_to_string(path)?.trim().to_string()),
+        None => env("AWS_CONTAINER_AUTHORIZATION_TOKEN"),
+    };
+
+    let mut req = reqwest::Client::new()
+        .get(&url)
+        .timeout(Duration::from_secs(5));
+    if let Some(token) = token {
+        req = req.header("authorization", token);
+    }
+    let temporary: Temporary = req
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .context("Failed to get container credentials")?
+        .json()
+        .await
+        .context("Invalid container credentials")?;
+    Ok(Some(temporary.into()))
+}
+
+/// The role attached to an EC2 instance, from IMDSv2. Off EC2 the
+/// endpoint doesn't answer, so every failure just means "none".
+async fn credentials_from_instance() -> Option<AwsCredentials> {
+    const IMDS: &str = "http://169.254.169.254/latest";
+
+    if env("AWS_EC2_METADATA_DISABLED").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+        return None;
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(1))
+        .build()
+        .ok()?;
+
+    let token = client
+        .put(format!("{IMDS}/api/token"))
+        .header("x-aws-ec2-metadata-token-ttl-seconds", "21600")
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    let get = |path: String| {
+        client
+            .get(format!("{IMDS}/meta-data/iam/security-credentials/{path}"))
+            .header("x-aws-ec2-metadata-token", &token)
+            .send()
+    };
+
+    let roles = get(String::new()).await.ok()?.text().await.ok()?;
+    let role = roles.lines().next()?.trim().to_string();
+    let temporary: Temporary = get(role).await.ok()?.json().await.ok()?;
+    Some(temporary.into())
+}
+
+/// A profile's settings from `~/.aws/config`, overridden by the ones in
+/// `~/.aws/credentials`.
+fn profile_values(profile: &str) -> BTreeMap<String, String> {
+    let read = |var: &str, name: &str| {
+        env(var)
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".aws").join(name)))
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .unwrap_or_default()
+    };
+
+    // The config file prefixes every section but the default one
+    let config_section = if profile == "default" {
+        profile.to_string()
+    } else {
+        format!("profile {profile}")
+    };
+    let mut values = ini_section(&read("AWS_CONFIG_FILE", "config"), &config_section);
+    values.extend(ini_section(
+        &read("AWS_SHARED_CREDENTIALS_FILE", "credentials"),
+        profile,
+    ));
+    values
+}
+
+/// `key = value` pairs in the `[section]` of an AWS INI file.
+fn ini_section(text: &str, section: &str) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    let mut inside = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            inside = name.trim() == section;
+            continue;
+        }
+        if inside && let Some((key, value)) = line.split_once('=') {
+            values.insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    values
+}
+
+// ---------------------------------------------------------------------------
+// Signature Version 4
+// ---------------------------------------------------------------------------
+
+/// The parts of a request SigV4 covers.
+struct Canonical<'a> {
+    method: &'a str,
+    host: &'a str,
+    /// As sent, already percent-encoded.
+    path: &'a str,
+    payload: &'a [u8],
+}
+
+/// Headers that sign `req` for `service` at `now` (Unix seconds). The
+/// `host` header is signed but left for the HTTP client to send.
+fn sign(
+    creds: &AwsCredentials,
+    region: &str,
+    service: &str,
+    now: u64,
+    req: &Canonical,
+) -> Vec<(&'static str, String)> {
+    let amz_date = amz_date(now);
+    let date = &amz_date[..8];
+
+    // Sorted by name, as the canonical form requires
+    let mut headers = vec![
+        ("host", req.host.to_string()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &creds.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect();
+
+    // Paths are encoded a second time for every service but S3
+    let canonical_path = req
+        .path
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let canonical_request = format!(
+        "{}\n{canonical_path}\n\n{canonical_headers}\n{signed_headers}\n{}",
+        req.method,
+        hex::encode(Sha256::digest(req.payload))
+    );
+
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let mut key = hmac(
+        format!("AWS4{}", creds.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [region, service, "aws4_request"] {
+        key = hmac(&key, part.as_bytes());
+    }
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+    headers.remove(0);
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, \
+             Signature={signature}",
+            creds.access_key_id
+        ),
+    ));
+    headers
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters.
+fn uri_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// `20150830T123600Z` for a Unix time.
+fn amz_date(now: u64) -> String {
+    let (days, secs) = ((now / 86_400) as i64, now % 86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Unix time of an RFC 3339 UTC timestamp like `2026-10-17T12:00:00Z`.
+fn parse_utc(s: &str) -> Option<u64> {
+    let field = |range: std::ops::Range<usize>| s.get(range)?.parse::<i64>().ok();
+    let days = days_from_civil(field(0..4)?, field(5..7)?, field(8..10)?);
+    let secs = days * 86_400 + field(11..13)? * 3600 + field(14..16)? * 60 + field(17..19)?;
+    u64::try_from(secs).ok()
+}
+
+// Calendar conversions from http://howardhinnant.github.io/date_algorithms.html
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// ---------------------------------------------------------------------------
+// Event stream
+// ---------------------------------------------------------------------------
+
+/// A Messages API event from the stream: its `type` and JSON, as the
+/// Anthropic API would have sent them over SSE.
+pub(crate) struct StreamEvent {
+    pub kind: String,
+    pub data: String,
+}
+
+/// Decodes the binary `application/vnd.amazon.eventstream` framing
+/// Bedrock streams responses in. Each `chunk` frame wraps one Messages API
+/// event, base64-encoded.
+#[derive(Default)]
+pub(crate) struct EventStream {
+    buf: Vec<u8>,
+}
+
+impl EventStream {
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The next event, or `None` until more bytes arrive. Exceptions in the
+    /// stream (throttling, validation) become errors.
+    pub fn next_event(&mut self) -> Result<Option<StreamEvent>> {
+        while let Some(frame) = self.next_frame()? {
+            let header = |name: &str| frame.headers.get(name).map(String::as_str);
+
+            match header(":message-type") {
+                Some("event") if header(":event-type") == Some("chunk") => {
+                    #[derive(Deserialize)]
+                    struct Chunk {
+                        bytes: String,
+                    }
+                    let chunk: Chunk = serde_json::from_slice(&frame.payload)
+                        .context("Invalid Bedrock stream chunk")?;
+                    let data = String::from_utf8(STANDARD.decode(chunk.bytes)?)?;
+                    let event: serde_json::Value = serde_json::from_str(&data)?;
+                    let kind = event["type"].as_str().unwrap_or_default().to_string();
+                    return Ok(Some(StreamEvent { kind, data }));
+                }
+                Some("exception" | "error") => {
+                    let kind = header(":exception-type")
+                        .or(header(":error-code"))
+                        .unwrap_or("error");
+                    let message = error_message(&String::from_utf8_lossy(&frame.payload));
+                    return Err(ApiError::Request {
+                        status: None,
+                        message: format!("Bedrock {kind}: {message}"),
+                    }
+                    .into());
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>> {
+        // Prelude: total length, headers length, prelude checksum
+        if self.buf.len() < 12 {
+            return Ok(None);
+        }
+        let word = |buf: &[u8], at: usize| u32::from_be_bytes(buf[at..at + 4].try_into().unwrap());
+        if crc32fast::hash(&self.buf[..8]) != word(&self.buf, 8) {
+            bail!("Corrupt Bedrock event stream (prelude checksum)");
+        }
+        let total = word(&self.buf, 0) as usize;
+        let headers_len = word(&self.buf, 4) as usize;
+        if total < 16 + headers_len {
+            bail!("Corrupt Bedrock event stream (frame length)");
+        }
+        if self.buf.len() < total {
+            return Ok(None);
+        }
+
+        let frame: Vec<u8> = self.buf.drain(..total).collect();
+        if crc32fast::hash(&frame[..total - 4]) != word(&frame, total - 4) {
+            bail!("Corrupt Bedrock event stream (message checksum)");
+        }
+        Ok(Some(Frame {
+            headers: parse_headers(&frame[12..12 + headers_len])?,
+            payload: frame[12 + headers_len..total - 4].to_vec(),
+        }))
+    }
+}
+
+struct Frame {
+    /// String-valued headers; others are skipped.
+    headers: BTreeMap<String, String>,
+    payload: Vec<u8>,
+}
+
+fn parse_headers(mut bytes: &[u8]) -> Result<BTreeMap<String, String>> {
+    let mut take = |n: usize| -> Result<&[u8]> {
+        anyhow::ensure!(bytes.len() >= n, "Corrupt Bedrock event stream (headers)");
+        let (head, rest) = bytes.split_at(n);
+        bytes = rest;
+        Ok(head)
+    };
+
+    let mut headers = BTreeMap::new();
+    loop {
+        let Ok(len) = take(1) else { break };
+        let name = String::from_utf8_lossy(take(len[0] as usize)?).into_owned();
+        let value = match take(1)?[0] {
+            // true, false
+            0 | 1 => None,
+            2 => take(1).map(|_| None)?,
+            3 => take(2).map(|_| None)?,
+            4 => take(4).map(|_| None)?,
+            5 | 8 => take(8).map(|_| None)?,
+            9 => take(16).map(|_| None)?,
+            // Byte array or string, with a 16-bit length
+            kind @ (6 | 7) => {
+                let len = take(2)?;
+                let value = take(u16::from_be_bytes([len[0], len[1]]) as usize)?;
+                (kind == 7).then(|| String::from_utf8_lossy(value).into_owned())
+            }
+            kind => bail!("Corrupt Bedrock event stream (header type {kind})"),
+        };
+        if let Some(value) = value {
+            headers.insert(name, value);
+        }
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bedrock(inference_profile: Option<&str>, models: &[(&str, &str)]) -> Bedrock {
+        Bedrock {
+            region: "us-east-1".to_string(),
+            profile: "default".to_string(),
+            explicit_profile: false,
+            inference_profile: inference_profile.map(String::from),
+            models: models
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            credentials: Arc::default(),
+        }
+    }
+
+    /// An event stream frame with string headers.
+    fn frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for (name, value) in headers {
+            encoded.push(name.len() as u8);
+            encoded.extend_from_slice(name.as_bytes());
+            encoded.push(7);
+            encoded.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            encoded.extend_from_slice(value.as_bytes());
+        }
+
+        let total = 16 + encoded.len() + payload.len();
+        let mut out = Vec::new();
+        out.extend_from_slice(&(total as u32).to_be_bytes());
+        out.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        out.extend_from_slice(&crc32fast::hash(&out).to_be_bytes());
+        out.extend_from_slice(&encoded);
+        out.extend_from_slice(payload);
+        out.extend_from_slice(&crc32fast::hash(&out).to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn signs_like_the_aws_test_suite() {
+        // "get-vanilla" from the AWS SigV4 test suite
+        let creds = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+            expires_at: None,
+        };
+        let headers = sign(
+            &creds,
+            "us-east-1",
+            "service",
+            1_440_938_160,
+            &Canonical {
+                method: "GET",
+                host: "example.amazonaws.com",
+                path: "/",
+                payload: b"",
+            },
+        );
+
+        assert_eq!(
+            headers,
+            [
+                ("x-amz-date", "20150830T123600Z".to_string()),
+                (
+                    "authorization",
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/\
+                     aws4_request, SignedHeaders=host;x-amz-date, \
+                     Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+                        .to_string()
+                ),
+            ]
+        );
+
+        let temporary = AwsCredentials {
+            session_token: Some("token".to_string()),
+            ..creds
+        };
+        let headers = sign(
+            &temporary,
+            "us-east-1",
+            "bedrock",
+            0,
+            &Canonical {
+                method: "POST",
+                host: "bedrock-runtime.us-east-1.amazonaws.com",
+                path: "/model/anthropic.claude-v2%3A1/invoke",
+                payload: b"{}",
+            },
+        );
+        assert_eq!(headers[1], ("x-amz-security-token", "token".to_string()));
+        assert!(
+            headers[2]
+                .1
+                .contains("SignedHeaders=host;x-amz-date;x-amz-security-token")
+        );
+    }
+
+    #[test]
+    fn maps_models_to_bedrock_ids() {
+        let plain = bedrock(None, &[]);
+        assert_eq!(
+            plain.model_id("claude-sonnet-4-5"),
+            "anthropic.claude-sonnet-4-5-20250929-v1:0"
+        );
+        assert_eq!(plain.model_id("my-custom-model"), "my-custom-model");
+
+        let routed = bedrock(
+            Some("eu"),
+            &[(
+                "claude-opus-4-6",
+                "arn:aws:bedrock:eu-west-1:1:application-inference-profile/x",
+            )],
+        );
+        assert_eq!(
+            routed.model_id("claude-haiku-4-5"),
+            "eu.anthropic.claude-haiku-4-5-20251001-v1:0"
+        );
+        assert_eq!(
+            routed.model_id("claude-opus-4-6"),
+            "arn:aws:bedrock:eu-west-1:1:application-inference-profile/x"
+        );
+        assert_eq!(uri_encode("a.b:0/c"), "a.b%3A0%2Fc");
+    }
+
+    #[test]
+    fn rejects_regions_that_would_change_the_host() {
+        let settings = BedrockSettings {
+            region: Some("evil.example/x#".to_string()),
+            ..BedrockSettings::default()
+        };
+        let error = Bedrock::new(&settings).unwrap_err();
+        assert!(error.to_string().contains("Invalid AWS region"), "{error}");
+
+        let settings = BedrockSettings {
+            region: Some("eu-west-1".to_string()),
+            ..BedrockSettings::default()
+        };
+        assert_eq!(Bedrock::new(&settings).unwrap().region(), "eu-west-1");
+    }
+
+    #[test]
+    fn reads_profiles_from_ini_files() {
+        let text = "\
+[default]
+region = us-east-1
+
+[profile work]
+# comment
+region=eu-west-1
+credential_process = aws configure export-credentials --format process
+";
+        let work = ini_section(text, "profile work");
+        assert_eq!(work["region"], "eu-west-1");
+        assert!(work["credential_process"].starts_with("aws configure"));
+        assert_eq!(ini_section(text, "default").len(), 1);
+        assert!(ini_section(text, "missing").is_empty());
+    }
+
+    #[test]
+    fn converts_timestamps() {
+        assert_eq!(amz_date(1_440_938_160), "20150830T123600Z");
+        assert_eq!(parse_utc("2015-08-30T12:36:00Z"), Some(1_440_938_160));
+        assert_eq!(parse_utc("2024-02-29T00:00:00.000Z"), Some(1_709_164_800));
+        assert_eq!(parse_utc("soon"), None);
+    }
+
+    #[test]
+    fn decodes_chunks_split_across_reads() {
+        let event = r#"{"type":"content_block_delta","delta":{"type":"text_delta","text":"Hi"}}"#;
+        let payload = serde_json::json!({ "bytes": STANDARD.encode(event) }).to_string();
+        let bytes = frame(
+            &[(":message-type", "event"), (":event-type", "chunk")],
+            payload.as_bytes(),
+        );
+
+        let mut stream = EventStream::default();
+        let (head, tail) = bytes.split_at(20);
+        stream.extend(head);
+        assert!(stream.next_event().unwrap().is_none());
+        stream.extend(tail);
+
+        let decoded = stream.next_event().unwrap().unwrap();
+        assert_eq!(decoded.kind, "content_block_delta");
+        assert_eq!(decoded.data, event);
+        assert!(stream.next_event().unwrap().is_none());
+    }
+
+    #[test]
+    fn stream_exceptions_become_errors() {
+        let mut stream = EventStream::default();
+        stream.extend(&frame(
+            &[
+                (":message-type", "exception"),
+                (":exception-type", "throttlingException"),
+            ],
+            br#"{"message":"Too many requests"}"#,
+        ));
+        let error = stream.next_event().err().unwrap().to_string();
+        assert_eq!(error, "Bedrock throttlingException: Too many requests");
+
+        let mut corrupt = frame(&[(":message-type", "event")], b"{}");
+        corrupt[13] ^= 1;
+        let mut stream = EventStream::default();
+        stream.extend(&corrupt);
+        assert!(stream.next_event().is_err());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::api::Usage;
+use crate::tools::ToolProgress;
+
 /// Receives streaming events from an API interaction.
 ///
 /// New methods can be added with default impls without breaking existing code.
@@ -5,8 +8,33 @@ pub trait EventHandler: Send {
     fn on_text(&mut self, text: &str);
     fn on_error(&mut self, message: &str);
 
+    /// Extended thinking, streamed before the text it leads to.
+    fn on_thinking(&mut self, _text: &str) {}
+
     fn on_tool_use_start(&mut self, _name: &str, _id: &str, _input: &serde_json::Value) {}
     fn on_tool_use_end(&mut self, _name: &str) {}
     fn on_tool_executing(&mut self, _name: &str, _input: &serde_json::Value) {}
     fn on_tool_result(&mut self, _name: &str, _output: &str, _is_error: bool) {}
+    fn on_tool_progress(&mut self, _name: &str, _progress: &ToolProgress) {}
+
+    /// Called after each model request with its token usage.
+    fn on_usage(&mut self, _usage: &Usage) {}
+
+    /// A subagent's state changed. Task calls that run side by side report
+    /// their progress this way instead of through the tool events above;
+    /// `id` is the Task call's tool use ID.
+    fn on_task_status(&mut self, _id: &str, _description: &str, _status: &TaskStatus) {}
+}
+
+/// What a subagent is doing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// Waiting for another parallel task to finish.
+    Queued,
+    /// Working, with the name of the tool it's running, if any.
+    Running(Option<String>),
+    /// Rate limited or overloaded; retrying in this many seconds.
+    Retrying(u64),
+    Done,
+    Failed,
 }
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use serde::Deserialize;
+
+/// Points in a session where Claude Code runs hooks. Names match Claude
+/// Code's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PreToolUse,
+    PostToolUse,
+    UserPromptSubmit,
+    Notification,
+    Stop,
+    SubagentStop,
+    PreCompact,
+    SessionStart,
+    SessionEnd,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PreToolUse => "PreToolUse",
+            Self::PostToolUse => "PostToolUse",
+            Self::UserPromptSubmit => "UserPromptSubmit",
+            Self::Notification => "Notification",
+            Self::Stop => "Stop",
+            Self::SubagentStop => "SubagentStop",
+            Self::PreCompact => "PreCompact",
+            Self::SessionStart => "SessionStart",
+            Self::SessionEnd => "SessionEnd",
+        }
+    }
+}
+
+/// `"hooks"` in settings, in Claude Code's format: event name → matchers,
+/// each with the commands to run.
+///
+/// ```json
+/// {"hooks": {"PostToolUse": [
+///   {"matcher": "Edit|Write", "hooks": [{"type": "command", "command": "cargo fmt"}]}
+/// ]}}
+/// ```
+///
+/// ccrs reads and checks hooks, so settings shared with Claude Code load
+/// cleanly, but doesn't run them yet.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct HooksConfig {
+    events: BTreeMap<String, Vec<HookMatcher>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HookMatcher {
+    /// Tool names the hooks apply to, as a regex such as `Edit|Write`.
+    /// Empty or `*` matches every tool; events without a tool ignore it.
+    #[serde(default)]
+    pub matcher: String,
+    pub hooks: Vec<Hook>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookKind {
+    /// A shell command.
+    Command,
+}
+
+/// One command to run.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Hook {
+    #[serde(rename = "type")]
+    pub kind: HookKind,
+    pub command: String,
+    /// Seconds before the command is killed (default 60).
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        self.events.values().all(Vec::is_empty)
+    }
+
+    /// Hooks from both layers are kept: matchers are appended per event.
+    pub fn merge(mut self, other: Self) -> Self {
+        for (event, matchers) in other.events {
+            self.events.entry(event).or_default().extend(matchers);
+        }
+        self
+    }
+
+    /// Number of commands configured for each event, for display.
+    pub fn counts(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.events
+            .iter()
+            .map(|(event, matchers)| (event.as_str(), matchers.iter().map(|m| m.hooks.len()).sum()))
+            .filter(|(_, n)| *n > 0)
+    }
+
+    /// The hooks for an event, in settings order. `tool` is the
+    /// tool name for `PreToolUse` and `PostToolUse`.
+    pub fn matching(&self, event: HookEvent, tool: Option<&str>) -> Vec<&Hook> {
+        self.events
+            .get(event.name())
+            .into_iter()
+            .flatten()
+            .filter(|m| tool.is_none_or(|tool| m.matches(tool)))
+            .flat_map(|m| &m.hooks)
+            .collect()
+    }
+}
+
+impl HookMatcher {
+    fn matches(&self, tool: &str) -> bool {
+        let pattern = self.matcher.trim();
+        if pattern.is_empty() || pattern == "*" {
+            return true;
+        }
+        // Whole-name match; a pattern that isn't a valid regex is a plain name
+        match Regex::new(&format!("^(?:{pattern})$")) {
+            Ok(re) => re.is_match(tool),
+            Err(_) => pattern == tool,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> HooksConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn commands(hooks: Vec<&Hook>) -> Vec<&str> {
+        hooks.iter().map(|h| h.command.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_claude_code_hooks() {
+        let hooks = parse(
+            r#"{
+                "PreToolUse": [
+                    {"matcher": "Bash", "hooks": [{"type": "command", "command": "./check.sh", "timeout": 5}]},
+                    {"matcher": "Edit|Write", "hooks": [{"type": "command", "command": "./guard.sh"}]},
+                    {"hooks": [{"type": "command", "command": "./log.sh"}]}
+                ],
+                "Stop": [{"matcher": "", "hooks": [{"type": "command", "command": "notify-send done"}]}]
+            }"#,
+        );
+
+        assert_eq!(
+            commands(hooks.matching(HookEvent::PreToolUse, Some("Bash"))),
+            ["./check.sh", "./log.sh"]
+        );
+        assert_eq!(
+            commands(hooks.matching(HookEvent::PreToolUse, Some("Write"))),
+            ["./guard.sh", "./log.sh"]
+        );
+        // Anchored: `Edit|Write` doesn't match MultiEdit
+        assert_eq!(
+            commands(hooks.matching(HookEvent::PreToolUse, Some("MultiEdit"))),
+            ["./log.sh"]
+        );
+        assert_eq!(
+            commands(hooks.matching(HookEvent::Stop, None)),
+            ["notify-send done"]
+        );
+        assert!(
+            hooks
+                .matching(HookEvent::PostToolUse, Some("Bash"))
+                .is_empty()
+        );
+        assert_eq!(
+            hooks.matching(HookEvent::PreToolUse, Some("Bash"))[0].timeout,
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn merge_keeps_hooks_from_every_layer() {
+        let user = parse(r#"{"Stop": [{"hooks": [{"type": "command", "command": "a"}]}]}"#);
+        let project = parse(
+            r#"{"Stop": [{"hooks": [{"type": "command", "command": "b"}]}],
+                "SessionStart": [{"hooks": [{"type": "command", "command": "c"}]}]}"#,
+        );
+
+        let merged = user.merge(project);
+
+        assert_eq!(commands(merged.matching(HookEvent::Stop, None)), ["a", "b"]);
+        assert_eq!(
+            merged.counts().collect::<Vec<_>>(),
+            [("SessionStart", 1), ("Stop", 2)]
+        );
+        assert!(HooksConfig::default().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_hook_types() {
+        let bad = r#"{"Stop": [{"hooks": [{"type": "webhook", "command": "x"}]}]}"#;
+        assert!(serde_json::from_str::<HooksConfig>(bad).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Prompts for the requests that keep a session tidy rather than answer
+//! the user: compaction, condensing long tool output and drafting commit
+//! messages. They go to the small model, not the session's.
+
+use crate::api::{Content, ContentBlock, Message};
+
+/// The small model unless settings name another.
+pub(crate) const DEFAULT_SMALL_MODEL: &str = "haiku";
+
+/// Tool output longer than this is condensed before the model sees it.
+pub(crate) const SUMMARIZE_OVER_CHARS: usize = 30_000;
+
+/// Most of a tool output or diff sent along to be condensed; the middle of
+/// anything longer is dropped.
+const MAX_INPUT_CHARS: usize = 400_000;
+
+/// Tools whose output is logs or pages, safe to condense. Output that
+/// other tools act on verbatim, like Read's, is never touched.
+const SUMMARIZED_TOOLS: &[&str] = &["Bash", "Fetch"];
+
+pub(crate) const COMPACT_PROMPT: &str = "Summarize the conversation so far so that it can \
+    continue from the summary alone. Cover the user's requests and goals, decisions made, \
+    files read or changed (with paths), commands run and their results, errors and how they \
+    were resolved, and what remains to be done. Reply with the summary only.";
+
+pub(crate) const COMPACT_PREAMBLE: &str =
+    "This session continues an earlier conversation, summarized below.";
+
+pub(crate) const SYSTEM_PROMPT: &str = "You do housekeeping for a coding agent: you condense \
+    and describe material for it. Reply with the requested text only, without preamble.";
+
+/// Whether `tool`'s output should be condensed before the model sees it.
+pub(crate) fn should_summarize(tool: &str, output: &str) -> bool {
+    SUMMARIZED_TOOLS.contains(&tool) && output.len() > SUMMARIZE_OVER_CHARS
+}
+
+/// Ask for a condensed version of a tool call's output.
+pub(crate) fn summarize_prompt(tool: &str, input: &serde_json::Value, output: &str) -> String {
+    format!(
+        "The agent called the {tool} tool with this input:\n{input}\n\n\
+         It returned {} characters:\n<output>\n{}\n</output>\n\n\
+         Condense the output to what the agent needs to carry on, in at most a few hundred \
+         words. Quote errors, warnings, failing test names, file paths with line numbers, \
+         versions and the final status verbatim; drop progress lines, repetition and \
+         boilerplate.",
+        output.len(),
+        clip(output)
+    )
+}
+
+/// The tool result that replaces a condensed output.
+pub(crate) fn summarized_output(summary: &str, tool: &str, chars: usize, model: &str) -> String {
+    format!(
+        "{}\n\n[The {tool} output was {chars} characters; {model} condensed it. Run a narrower \
+         command (grep, head, tail, a smaller page) if you need the exact text.]",
+        summary.trim()
+    )
+}
+
+/// Ask for a commit message describing `diff`.
+#[cfg(feature = "git")]
+pub(crate) fn commit_prompt(diff: &str) -> String {
+    format!(
+        "Write a git commit message for these staged changes:\n<diff>\n{}\n</diff>\n\n\
+         Use a summary line of at most 72 characters in the imperative mood (\"Fix ...\", \
+         \"Add ...\"). If the change needs explaining, add a blank line and a short body \
+         saying what changed and why. Reply with the message only.",
+        clip(diff)
+    )
+}
+
+/// The commit message in a reply, without the code fence or quotes a model
+/// may wrap it in. `None` if nothing is left.
+#[cfg(feature = "git")]
+pub(crate) fn commit_message(reply: &str) -> Option<String> {
+    let mut text = reply.trim();
+    if let Some(inner) = text.strip_prefix("```") {
+        // Drop the fence's language tag along with it
+        let inner = inner.split_once('\n').map_or("", |(_, rest)| rest);
+        text = inner.trim_end().strip_suffix("```").unwrap_or(inner).trim();
+    }
+    let text = text.trim_matches('"').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The conversation without thinking blocks, whose signatures belong to
+/// the model that wrote them.
+pub(crate) fn without_thinking(messages: &[Message]) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| match &message.content {
+            Content::Blocks(blocks) => Message {
+                role: message.role.clone(),
+                content: Content::blocks(
+                    blocks
+                        .iter()
+                        .filter(|block| {
+                            !matches!(
+                                block,
+                                ContentBlock::Thinking { .. }
+                                    | ContentBlock::RedactedThinking { .. }
+                            )
+                        })
+                        .cloned()
+                        .collect(),
+                ),
+            },
+            Content::Text(_) => message.clone(),
+        })
+        .collect()
+}
+
+/// `text`, or its start and end if it's longer than [`MAX_INPUT_CHARS`].
+fn clip(text: &str) -> String {
+    if text.len() <= MAX_INPUT_CHARS {
+        return text.to_string();
+    }
+    let half = MAX_INPUT_CHARS / 2;
+    let head = floor_boundary(text, half);
+    let tail = ceil_boundary(text, text.len() - half);
+    format!(
+        "{}\n[... {} characters omitted ...]\n{}",
+        &text[..head],
+        tail - head,
+        &text[tail..]
+    )
+}
+
+fn floor_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_long_logs_and_pages_are_summarized() {
+        let long = "x".repeat(SUMMARIZE_OVER_CHARS + 1);
+        assert!(should_summarize("Bash", &long));
+        assert!(should_summarize("Fetch", &long));
+        assert!(!should_summarize("Read", &long));
+        assert!(!should_summarize("Bash", "short"));
+    }
+
+    #[test]
+    fn long_input_keeps_its_start_and_end() {
+        let text = format!(
+            "{}é{}",
+            "a".repeat(MAX_INPUT_CHARS),
+            "z".repeat(MAX_INPUT_CHARS)
+        );
+        let clipped = clip(&text);
+        assert!(clipped.starts_with('a'));
+        assert!(clipped.ends_with("zzzzzzzzzz"));
+        assert!(clipped.contains("characters omitted"));
+        assert!(clipped.len() < text.len());
+        assert_eq!(clip("short"), "short");
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn commit_messages_lose_fences_and_quotes() {
+        assert_eq!(
+            commit_message("```text\nFix the parser\n\nIt dropped commas.\n```").as_deref(),
+            Some("Fix the parser\n\nIt dropped commas.")
+        );
+        assert_eq!(
+            commit_message("\"Add a flag\"\n").as_deref(),
+            Some("Add a flag")
+        );
+        assert_eq!(commit_message("```\n```"), None);
+        assert_eq!(commit_message("  "), None);
+    }
+
+    #[test]
+    fn thinking_is_dropped_from_the_history() {
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: Content::text("hi"),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: Content::blocks(vec![
+                    ContentBlock::Thinking {
+                        thinking: "hmm".to_string(),
+                        signature: "sig".to_string(),
+                    },
+                    ContentBlock::Text {
+                        text: "hello".to_string(),
+                    },
+                ]),
+            },
+        ];
+        let stripped = without_thinking(&messages);
+        assert_eq!(stripped[0].content.to_text(), "hi");
+        let Content::Blocks(blocks) = &stripped[1].content else {
+            panic!("expected blocks");
+        };
+        assert!(matches!(blocks.as_slice(), [ContentBlock::Text { .. }]));
+    }
+}
@@ -1,7 +1,25 @@
+pub mod agents;
 pub mod api;
 pub mod auth;
+pub mod bedrock;
 pub mod config;
 pub mod event;
+pub mod hooks;
+mod housekeeping;
+pub mod memory;
+pub mod models;
+pub mod openai;
 pub mod permission;
+pub mod pinned;
+pub mod pricing;
+pub mod project;
 pub mod session;
+pub mod store;
+mod subagent;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod templates;
+pub mod tls;
 pub mod tools;
+pub mod vault;
+pub mod vertex;
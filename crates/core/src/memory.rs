@@ -0,0 +1,146 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Where a memory file lives, in load order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryFile {
+    /// `~/.claude/CLAUDE.md` — personal, applies to every project
+    User,
+    /// `CLAUDE.md` in the project root
+    Project,
+    /// `.claude/instructions.md` in the project root
+    Instructions,
+}
+
+impl MemoryFile {
+    pub const ALL: [Self; 3] = [Self::User, Self::Project, Self::Instructions];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Project => "project",
+            Self::Instructions => "instructions",
+        }
+    }
+
+    /// `None` if the home directory can't be determined.
+    pub fn path(self, project_dir: &Path) -> Option<PathBuf> {
+        match self {
+            Self::User => dirs::home_dir().map(|h| h.join(".claude").join("CLAUDE.md")),
+            Self::Project => Some(project_dir.join("CLAUDE.md")),
+            Self::Instructions => Some(project_dir.join(".claude").join("instructions.md")),
+        }
+    }
+}
+
+impl std::str::FromStr for MemoryFile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|file| file.name() == s)
+            .with_context(|| {
+                format!("Unknown memory file: {s} (expected user, project or instructions)")
+            })
+    }
+}
+
+/// Concatenate the memory files that exist, each under a heading naming its
+/// path. `None` if there are none.
+pub fn load(project_dir: &Path) -> Option<String> {
+    let paths: Vec<PathBuf> = MemoryFile::ALL
+        .into_iter()
+        .filter_map(|file| file.path(project_dir))
+        .collect();
+
+    load_from_paths(&paths)
+}
+
+fn load_from_paths(paths: &[PathBuf]) -> Option<String> {
+    let sections: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            let contents = fs::read_to_string(path).ok()?;
+            let contents = contents.trim();
+            (!contents.is_empty()).then(|| format!("Contents of {}:\n\n{contents}", path.display()))
+        })
+        .collect();
+
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+/// Append `entry` to a memory file as a bullet, creating the file if needed.
+pub fn append(path: &Path, entry: &str) -> Result<()> {
+    let existing = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let separator = if existing.is_empty() || existing.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{separator}- {}", entry.trim())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_skips_missing_and_empty_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let a = tmp.path().join("a.md");
+        let b = tmp.path().join("b.md");
+        let c = tmp.path().join("c.md");
+        fs::write(&a, "Use tabs.\n").unwrap();
+        fs::write(&b, "  \n").unwrap();
+
+        let text = load_from_paths(&[a.clone(), b, c]).unwrap();
+
+        assert_eq!(text, format!("Contents of {}:\n\nUse tabs.", a.display()));
+    }
+
+    #[test]
+    fn load_returns_none_without_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(load_from_paths(&[tmp.path().join("CLAUDE.md")]).is_none());
+    }
+
+    #[test]
+    fn append_adds_bullets_on_their_own_line() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(".claude").join("instructions.md");
+
+        append(&path, " Run cargo fmt before committing ").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "- Run cargo fmt before committing\n"
+        );
+
+        fs::write(&path, "# Notes").unwrap();
+        append(&path, "Prefer anyhow").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Notes\n- Prefer anyhow\n"
+        );
+    }
+}
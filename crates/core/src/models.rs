@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{self, AVAILABLE_MODELS, ApiClient, Backend};
+use crate::config;
+
+/// Built-in aliases; each names the newest model of its family.
+pub const FAMILY_ALIASES: &[&str] = &["opus", "sonnet", "haiku"];
+
+/// The model list is fetched at most this often; in between, the cached
+/// one is reused.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A model from the API's model-listing endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListedModel {
+    pub id: String,
+    pub display_name: String,
+    /// RFC 3339 release time.
+    #[serde(default)]
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    fetched_at: u64,
+    models: Vec<ListedModel>,
+}
+
+/// Resolves short model names to full model IDs: user-defined aliases from
+/// the `modelAliases` setting, then the built-in family aliases, then the
+/// known model IDs and labels.
+#[derive(Debug, Clone, Default)]
+pub struct ModelAliases {
+    user: BTreeMap<String, String>,
+    /// From the last refresh; empty until one succeeds.
+    listed: Vec<ListedModel>,
+}
+
+impl ModelAliases {
+    /// The given user aliases, with the family aliases kept up to date by
+    /// the cached model list.
+    pub fn new(user: BTreeMap<String, String>) -> Self {
+        let listed = cache_path()
+            .and_then(|path| load_cache(&path))
+            .map(|cache| cache.models)
+            .unwrap_or_default();
+        Self { user, listed }
+    }
+
+    /// `(id, label)` for a model name, or `None` if nothing matches.
+    /// User aliases may point at a built-in alias or at any raw model ID.
+    pub fn resolve(&self, requested: &str) -> Option<(String, String)> {
+        match self.user.get(requested) {
+            Some(target) => Some(
+                self.resolve_builtin(target)
+                    .unwrap_or_else(|| (target.clone(), target.clone())),
+            ),
+            None => self.resolve_builtin(requested),
+        }
+    }
+
+    fn resolve_builtin(&self, requested: &str) -> Option<(String, String)> {
+        if let Some(id) = self.latest(requested) {
+            return Some(self.describe(&id));
+        }
+        if let Some((id, _)) = api::resolve_model(requested) {
+            return Some(self.describe(id));
+        }
+        self.listed
+            .iter()
+            .find(|m| m.id == requested)
+            .map(|m| self.describe(&m.id))
+    }
+
+    /// The newest model of a family alias, preferring the listed models
+    /// over the ones built in.
+    fn latest(&self, family: &str) -> Option<String> {
+        if !FAMILY_ALIASES.contains(&family) {
+            return None;
+        }
+        let listed = self
+            .listed
+            .iter()
+            .filter(|m| in_family(&m.id, family))
+            .max_by(|a, b| a.created_at.cmp(&b.created_at))
+            .map(|m| m.id.clone());
+        listed.or_else(|| {
+            AVAILABLE_MODELS
+                .iter()
+                .find(|(id, _)| in_family(id, family))
+                .map(|(id, _)| id.to_string())
+        })
+    }
+
+    fn describe(&self, id: &str) -> (String, String) {
+        let label = AVAILABLE_MODELS
+            .iter()
+            .find(|(known, _)| *known == id)
+            .map(|(_, label)| label.to_string())
+            .or_else(|| {
+                self.listed
+                    .iter()
+                    .find(|m| m.id == id)
+                    .map(|m| m.display_name.clone())
+            })
+            .unwrap_or_else(|| id.to_string());
+        (id.to_string(), label)
+    }
+
+    /// Every alias with the model ID it currently resolves to, built-in
+    /// ones first.
+    pub fn list(&self) -> Vec<(String, String)> {
+        let builtin = FAMILY_ALIASES
+            .iter()
+            .filter(|alias| !self.user.contains_key(**alias))
+            .map(|alias| alias.to_string());
+        builtin
+            .chain(self.user.keys().cloned())
+            .filter_map(|alias| {
+                let (id, _) = self.resolve(&alias)?;
+                Some((alias, id))
+            })
+            .collect()
+    }
+}
+
+/// `claude-opus-4-6` and `claude-3-5-haiku-20241022` name their family as
+/// one of the dash-separated parts.
+fn in_family(id: &str, family: &str) -> bool {
+    id.split('-').any(|part| part == family)
+}
+
+// ---------------------------------------------------------------------------
+// Model list cache
+// ---------------------------------------------------------------------------
+
+fn cache_path() -> Option<PathBuf> {
+    config::config_dir().ok().map(|dir| dir.join("models.json"))
+}
+
+fn load_cache(path: &Path) -> Option<Cache> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn save_cache(path: &Path, cache: &Cache) {
+    if let Ok(json) = serde_json::to_string(cache) {
+        let _ = fs::write(path, json);
+    }
+}
+
+/// Fetch the model list if the cached one is older than a day, so the
+/// family aliases pick up new releases on the next launch. Failures are
+/// only logged: the aliases fall back to the built-in models.
+pub(crate) async fn refresh(client: &ApiClient) {
+    // Cloud provider model IDs differ; the aliases keep the built-in mapping
+    if !matches!(client.backend(), Backend::Anthropic) {
+        return;
+    }
+    let Some(path) = cache_path() else {
+        return;
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    if let Some(cache) = load_cache(&path)
+        && now.saturating_sub(cache.fetched_at) < REFRESH_INTERVAL.as_secs()
+    {
+        return;
+    }
+
+    match client.list_models().await {
+        Ok(models) => save_cache(
+            &path,
+            &Cache {
+                fetched_at: now,
+                models,
+            },
+        ),
+        Err(e) => tracing::debug!("could not refresh the model list: {e:#}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(id: &str, name: &str, created_at: &str) -> ListedModel {
+        ListedModel {
+            id: id.to_string(),
+            display_name: name.to_string(),
+            created_at: created_at.to_string(),
+        }
+    }
+
+    fn aliases(user: &[(&str, &str)], listed: Vec<ListedModel>) -> ModelAliases {
+        let user = user
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ModelAliases { user, listed }
+    }
+
+    #[test]
+    fn family_aliases_fall_back_to_the_built_in_models() {
+        let aliases = ModelAliases::default();
+
+        assert_eq!(
+            aliases.resolve("opus"),
+            Some(("claude-opus-4-6".to_string(), "Opus 4.6".to_string()))
+        );
+        assert_eq!(aliases.resolve("haiku").unwrap().0, "claude-haiku-4-5");
+        // Full IDs and label fragments still work
+        assert_eq!(aliases.resolve("Sonnet").unwrap().0, "claude-sonnet-4-5");
+        assert_eq!(aliases.resolve("gpt-5"), None);
+    }
+
+    #[test]
+    fn family_aliases_follow_the_newest_listed_model() {
+        let aliases = aliases(
+            &[],
+            vec![
+                listed("claude-opus-4-6", "Claude Opus 4.6", "2026-02-05T00:00:00Z"),
+                listed("claude-opus-4-7", "Claude Opus 4.7", "2026-09-01T00:00:00Z"),
+                listed(
+                    "claude-3-5-haiku-20241022",
+                    "Claude Haiku 3.5",
+                    "2024-10-22T00:00:00Z",
+                ),
+            ],
+        );
+
+        assert_eq!(
+            aliases.resolve("opus"),
+            Some(("claude-opus-4-7".to_string(), "Claude Opus 4.7".to_string()))
+        );
+        assert_eq!(
+            aliases.resolve("haiku").unwrap().0,
+            "claude-3-5-haiku-20241022"
+        );
+        // Listed models can be picked by ID
+        assert_eq!(
+            aliases.resolve("claude-3-5-haiku-20241022").unwrap().1,
+            "Claude Haiku 3.5"
+        );
+    }
+
+    #[test]
+    fn user_aliases_resolve_through_built_in_ones_or_to_raw_ids() {
+        let aliases = aliases(
+            &[
+                ("fast", "haiku"),
+                ("legacy", "claude-opus-4-1-20250805"),
+                ("opus", "claude-opus-4-1-20250805"),
+            ],
+            Vec::new(),
+        );
+
+        assert_eq!(aliases.resolve("fast").unwrap().0, "claude-haiku-4-5");
+        assert_eq!(
+            aliases.resolve("legacy"),
+            Some((
+                "claude-opus-4-1-20250805".to_string(),
+                "claude-opus-4-1-20250805".to_string()
+            ))
+        );
+        // User aliases override the built-in ones
+        assert_eq!(
+            aliases.resolve("opus").unwrap().0,
+            "claude-opus-4-1-20250805"
+        );
+
+        let list = aliases.list();
+        assert_eq!(list.len(), 5);
+        assert_eq!(
+            list[0],
+            ("sonnet".to_string(), "claude-sonnet-4-5".to_string())
+        );
+        assert!(list.contains(&("fast".to_string(), "claude-haiku-4-5".to_string())));
+    }
+
+    #[test]
+    fn cache_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("models.json");
+        assert!(load_cache(&path).is_none());
+
+        let models = vec![listed(
+            "claude-opus-4-7",
+            "Claude Opus 4.7",
+            "2026-09-01T00:00:00Z",
+        )];
+        save_cache(
+            &path,
+            &Cache {
+                fetched_at: 42,
+                models: models.clone(),
+            },
+        );
+
+        let cache = load_cache(&path).unwrap();
+        assert_eq!(cache.fetched_at, 42);
+        assert_eq!(cache.models, models);
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+
+use crate::api::{
+    AVAILABLE_MODELS, ApiError, Content, ContentBlock, ImageSource, Message, ModelAccess,
+    StopReason, StreamResult, Usage,
+};
+use crate::config::OpenAiSettings;
+use crate::event::EventHandler;
+
+/// Ollama's endpoint, the most common local server.
+const DEFAULT_BASE_URL: &str = "http://localhost:11434/v1";
+
+/// An OpenAI-compatible Chat Completions endpoint as the model provider,
+/// such as Ollama, vLLM or LM Studio. Requests and streamed replies are
+/// translated to and from the Messages API, so the agent loop and tools
+/// don't know the difference.
+#[derive(Clone)]
+pub struct OpenAi {
+    base_url: String,
+    api_key: Option<String>,
+    /// Stands in for the built-in Claude models.
+    model: Option<String>,
+    models: BTreeMap<String, String>,
+}
+
+impl std::fmt::Debug for OpenAi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenAi")
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OpenAi {
+    /// Resolve the endpoint and key from `settings` and the environment.
+    pub fn new(settings: &OpenAiSettings) -> Result<Self> {
+        let base_url = settings
+            .base_url
+            .clone()
+            .or_else(|| env("OPENAI_BASE_URL"))
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            bail!("`openai.baseUrl` must be an http:// or https:// URL, not {base_url:?}");
+        }
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: settings.api_key.clone().or_else(|| env("OPENAI_API_KEY")),
+            model: settings.model.clone(),
+            models: settings.models.clone(),
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The endpoint's model name for a ccrs model ID: a mapped one, the
+    /// configured model for the built-in Claude models, else as given.
+    pub fn model_id(&self, model: &str) -> String {
+        if let Some(id) = self.models.get(model) {
+            return id.clone();
+        }
+        match &self.model {
+            Some(id) if AVAILABLE_MODELS.iter().any(|(known, _)| *known == model) => id.clone(),
+            _ => model.to_string(),
+        }
+    }
+
+    fn authorize(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => req.bearer_auth(key),
+            None => req,
+        }
+    }
+
+    /// A streamed Chat Completions request for a Messages API conversation.
+    pub(crate) fn stream_request(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+        max_tokens: u32,
+        messages: &[Message],
+        system_prompt: Option<&str>,
+        tools: Option<&[Value]>,
+    ) -> reqwest::RequestBuilder {
+        let mut body = json!({
+            "model": self.model_id(model),
+            "max_tokens": max_tokens,
+            "stream": true,
+            "stream_options": {"include_usage": true},
+            "messages": chat_messages(system_prompt, messages),
+        });
+        let tools = tools.map(chat_tools).unwrap_or_default();
+        if !tools.is_empty() {
+            body["tools"] = json!(tools);
+        }
+
+        self.authorize(client.post(format!("{}/chat/completions", self.base_url)))
+            .json(&body)
+    }
+
+    /// Look for the model in the endpoint's model list, for `/doctor`.
+    pub(crate) async fn check_model_access(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+    ) -> Result<ModelAccess> {
+        let resp = self
+            .authorize(client.get(format!("{}/models", self.base_url)))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.base_url))?;
+
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            let message = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|v| v["error"]["message"].as_str().map(String::from))
+                .unwrap_or(body);
+            return Ok(match status.as_u16() {
+                401 | 403 => ModelAccess::Unauthorized(message),
+                code => ModelAccess::Other(code, message),
+            });
+        }
+
+        // Servers without a usable list get the benefit of the doubt
+        let id = self.model_id(model);
+        let listed = serde_json::from_str::<Value>(&body).ok().and_then(|v| {
+            v["data"]
+                .as_array()
+                .map(|models| models.iter().any(|m| m["id"] == id.as_str()))
+        });
+        Ok(match listed {
+            Some(false) => ModelAccess::NotFound,
+            _ => ModelAccess::Granted,
+        })
+    }
+}
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+// ---------------------------------------------------------------------------
+// Request translation
+// ---------------------------------------------------------------------------
+
+/// The conversation as Chat Completions messages: tool results become
+/// `tool` messages, tool calls move into `tool_calls`, and thinking, which
+/// only Claude can read back, is dropped.
+fn chat_messages(system_prompt: Option<&str>, messages: &[Message]) -> Vec<Value> {
+    let mut out = Vec::new();
+    if let Some(prompt) = system_prompt {
+        out.push(json!({"role": "system", "content": prompt}));
+    }
+
+    for message in messages {
+        match &message.content {
+            Content::Text(text) => out.push(json!({"role": message.role, "content": text})),
+            Content::Blocks(blocks) if message.role == "assistant" => {
+                out.push(assistant_message(blocks));
+            }
+            Content::Blocks(blocks) => user_messages(blocks, &mut out),
+        }
+    }
+    out
+}
+
+fn assistant_message(blocks: &[ContentBlock]) -> Value {
+    let mut text = String::new();
+    let mut calls = Vec::new();
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text: chunk } => text.push_str(chunk),
+            ContentBlock::ToolUse { id, name, input } => calls.push(json!({
+                "id": id,
+                "type": "function",
+                "function": {"name": name, "arguments": input.to_string()},
+            })),
+            _ => {}
+        }
+    }
+
+    let mut message = json!({"role": "assistant", "content": text});
+    if !calls.is_empty() {
+        if text.is_empty() {
+            message["content"] = Value::Null;
+        }
+        message["tool_calls"] = json!(calls);
+    }
+    message
+}
+
+/// Tool results first, as they must follow the assistant's calls, then
+/// whatever the user added.
+fn user_messages(blocks: &[ContentBlock], out: &mut Vec<Value>) {
+    let mut texts = Vec::new();
+    let mut images = Vec::new();
+    for block in blocks {
+        match block {
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => {
+                let content = if *is_error == Some(true) {
+                    format!("Error: {content}")
+                } else {
+                    content.clone()
+                };
+                out.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tool_use_id,
+                    "content": content,
+                }));
+            }
+            ContentBlock::Text { text } => texts.push(text.as_str()),
+            ContentBlock::Image { source } => {
+                let url = match source {
+                    ImageSource::Base64 { media_type, data } => {
+                        format!("data:{media_type};base64,{data}")
+                    }
+                    ImageSource::Url { url } => url.clone(),
+                };
+                images.push(json!({"type": "image_url", "image_url": {"url": url}}));
+            }
+            _ => {}
+        }
+    }
+
+    // Plain strings unless there are images: not every server takes parts
+    if images.is_empty() {
+        if !texts.is_empty() {
+            out.push(json!({"role": "user", "content": texts.join("\n\n")}));
+        }
+    } else {
+        let mut parts: Vec<Value> = texts
+            .iter()
+            .map(|text| json!({"type": "text", "text": text}))
+            .collect();
+        parts.extend(images);
+        out.push(json!({"role": "user", "content": parts}));
+    }
+}
+
+/// Messages API tool definitions as functions.
+fn chat_tools(defs: &[Value]) -> Vec<Value> {
+    defs.iter()
+        .filter(|def| def.get("input_schema").is_some())
+        .map(|def| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": def["name"],
+                    "description": def["description"],
+                    "parameters": def["input_schema"],
+                },
+            })
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Stream translation
+// ---------------------------------------------------------------------------
+
+struct ToolCall {
+    index: u64,
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Builds a [`StreamResult`] from streamed Chat Completions chunks.
+#[derive(Default)]
+pub(crate) struct ChatStream {
+    text: String,
+    calls: Vec<ToolCall>,
+    usage: Usage,
+    finish_reason: Option<String>,
+}
+
+impl ChatStream {
+    /// Apply one `data:` chunk, streaming text and reasoning to `handler`.
+    pub(crate) fn apply(&mut self, chunk: &Value, handler: &mut dyn EventHandler) -> Result<()> {
+        if let Some(error) = chunk.get("error") {
+            let message = error["message"].as_str().unwrap_or("Unknown error");
+            return Err(ApiError::Request {
+                status: None,
+                message: message.to_string(),
+            }
+            .into());
+        }
+
+        // Sent last, with no choices, when usage was asked for
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            let get = |value: &Value| value.as_u64().unwrap_or(0);
+            let cached = get(&usage["prompt_tokens_details"]["cached_tokens"]);
+            self.usage = Usage {
+                input_tokens: get(&usage["prompt_tokens"]).saturating_sub(cached),
+                output_tokens: get(&usage["completion_tokens"]),
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: cached,
+            };
+        }
+
+        let Some(choice) = chunk["choices"].get(0) else {
+            return Ok(());
+        };
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+
+        let delta = &choice["delta"];
+        // vLLM and DeepSeek call it reasoning_content, Ollama reasoning
+        let reasoning = delta["reasoning_content"]
+            .as_str()
+            .or_else(|| delta["reasoning"].as_str());
+        if let Some(chunk) = reasoning.filter(|chunk| !chunk.is_empty()) {
+            handler.on_thinking(chunk);
+        }
+        if let Some(chunk) = delta["content"].as_str().filter(|chunk| !chunk.is_empty()) {
+            handler.on_text(chunk);
+            self.text.push_str(chunk);
+        }
+
+        for call in delta["tool_calls"].as_array().into_iter().flatten() {
+            // Servers that send whole calls may leave out the index
+            let index = call["index"].as_u64().unwrap_or(self.calls.len() as u64);
+            let position = match self.calls.iter().position(|c| c.index == index) {
+                Some(position) => position,
+                None => {
+                    self.calls.push(ToolCall {
+                        index,
+                        id: String::new(),
+                        name: String::new(),
+                        arguments: String::new(),
+                    });
+                    self.calls.len() - 1
+                }
+            };
+            let entry = &mut self.calls[position];
+            if let Some(id) = call["id"].as_str() {
+                entry.id = id.to_string();
+            }
+            if let Some(name) = call["function"]["name"].as_str() {
+                entry.name.push_str(name);
+            }
+            if let Some(arguments) = call["function"]["arguments"].as_str() {
+                entry.arguments.push_str(arguments);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_result(self) -> StreamResult {
+        let mut content = Vec::new();
+        if !self.text.is_empty() {
+            content.push(ContentBlock::Text { text: self.text });
+        }
+
+        // Some servers finish with "stop" even after calling tools
+        let stop_reason = if !self.calls.is_empty() {
+            StopReason::ToolUse
+        } else if self.finish_reason.as_deref() == Some("length") {
+            StopReason::MaxTokens
+        } else {
+            StopReason::EndTurn
+        };
+
+        let mut invalid_tool_calls = HashMap::new();
+        for call in self.calls {
+            let id = if call.id.is_empty() {
+                format!("call_{}", call.index)
+            } else {
+                call.id
+            };
+            // Calls without parameters may come with no arguments at all
+            let arguments = match call.arguments.trim() {
+                "" => "{}",
+                arguments => arguments,
+            };
+            let input = match serde_json::from_str::<Value>(arguments) {
+                Ok(input) if input.is_object() => input,
+                parsed => {
+                    let reason = match parsed {
+                        Err(e) => e.to_string(),
+                        Ok(_) => "not an object".to_string(),
+                    };
+                    invalid_tool_calls.insert(
+                        id.clone(),
+                        format!(
+                            "The arguments for {} are not a JSON object ({reason}), so it was not run.",
+                            call.name
+                        ),
+                    );
+                    json!({})
+                }
+            };
+            content.push(ContentBlock::ToolUse {
+                id,
+                name: call.name,
+                input,
+            });
+        }
+
+        StreamResult {
+            content,
+            usage: self.usage,
+            stop_reason,
+            invalid_tool_calls,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(String);
+
+    impl EventHandler for Recorder {
+        fn on_text(&mut self, text: &str) {
+            self.0.push_str(text);
+        }
+        fn on_error(&mut self, _message: &str) {}
+    }
+
+    fn endpoint(model: Option<&str>) -> OpenAi {
+        OpenAi {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key: None,
+            model: model.map(String::from),
+            models: [("claude-opus-4-6".to_string(), "llama3.3:70b".to_string())].into(),
+        }
+    }
+
+    #[test]
+    fn builtin_models_map_to_the_configured_one() {
+        let openai = endpoint(Some("qwen2.5-coder:32b"));
+        assert_eq!(openai.model_id("claude-sonnet-4-5"), "qwen2.5-coder:32b");
+        assert_eq!(openai.model_id("claude-opus-4-6"), "llama3.3:70b");
+        assert_eq!(openai.model_id("mistral-small"), "mistral-small");
+        assert_eq!(
+            endpoint(None).model_id("claude-haiku-4-5"),
+            "claude-haiku-4-5"
+        );
+    }
+
+    #[test]
+    fn tool_round_trips_become_tool_calls_and_tool_messages() {
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: Content::text("List the files"),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: Content::blocks(vec![
+                    ContentBlock::Thinking {
+                        thinking: "hmm".to_string(),
+                        signature: "sig".to_string(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "t1".to_string(),
+                        name: "Glob".to_string(),
+                        input: json!({"pattern": "*"}),
+                    },
+                ]),
+            },
+            Message {
+                role: "user".to_string(),
+                content: Content::blocks(vec![
+                    ContentBlock::ToolResult {
+                        tool_use_id: "t1".to_string(),
+                        content: "No such directory".to_string(),
+                        is_error: Some(true),
+                    },
+                    ContentBlock::Text {
+                        text: "Try src".to_string(),
+                    },
+                ]),
+            },
+        ];
+
+        let chat = chat_messages(Some("Be brief."), &messages);
+        assert_eq!(chat.len(), 5);
+        assert_eq!(chat[0], json!({"role": "system", "content": "Be brief."}));
+        assert_eq!(chat[2]["content"], Value::Null);
+        assert_eq!(chat[2]["tool_calls"][0]["id"], "t1");
+        assert_eq!(
+            chat[2]["tool_calls"][0]["function"]["arguments"],
+            r#"{"pattern":"*"}"#
+        );
+        assert_eq!(
+            chat[3],
+            json!({"role": "tool", "tool_call_id": "t1", "content": "Error: No such directory"})
+        );
+        assert_eq!(chat[4], json!({"role": "user", "content": "Try src"}));
+
+        let tools = chat_tools(&[json!({
+            "name": "Glob",
+            "description": "Find files",
+            "input_schema": {"type": "object"},
+        })]);
+        assert_eq!(
+            tools[0]["function"]["parameters"],
+            json!({"type": "object"})
+        );
+    }
+
+    #[test]
+    fn streamed_chunks_build_text_and_tool_calls() {
+        let chunks = [
+            json!({"choices": [{"delta": {"content": "Let me look."}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "id": "call_a",
+                "function": {"name": "Read", "arguments": "{\"file_"}}]}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0,
+                "function": {"arguments": "path\": \"a.rs\"}"}}]}}]}),
+            json!({"choices": [{"delta": {}, "finish_reason": "stop"}]}),
+            json!({"choices": [], "usage": {"prompt_tokens": 120, "completion_tokens": 30,
+                "prompt_tokens_details": {"cached_tokens": 100}}}),
+        ];
+
+        let mut recorder = Recorder(String::new());
+        let mut stream = ChatStream::default();
+        for chunk in &chunks {
+            stream.apply(chunk, &mut recorder).unwrap();
+        }
+        let result = stream.into_result();
+
+        assert_eq!(recorder.0, "Let me look.");
+        assert_eq!(result.stop_reason, StopReason::ToolUse);
+        assert_eq!(result.usage.input_tokens, 20);
+        assert_eq!(result.usage.cache_read_input_tokens, 100);
+        assert_eq!(result.usage.output_tokens, 30);
+        let [
+            ContentBlock::Text { text },
+            ContentBlock::ToolUse { id, name, input },
+        ] = result.content.as_slice()
+        else {
+            panic!("expected text and a tool call");
+        };
+        assert_eq!(text, "Let me look.");
+        assert_eq!((id.as_str(), name.as_str()), ("call_a", "Read"));
+        assert_eq!(input, &json!({"file_path": "a.rs"}));
+
+        assert!(result.invalid_tool_calls.is_empty());
+
+        let error = json!({"error": {"message": "model not found"}});
+        assert!(ChatStream::default().apply(&error, &mut recorder).is_err());
+    }
+
+    #[test]
+    fn unparsable_tool_arguments_are_reported() {
+        let chunks = [
+            json!({"choices": [{"delta": {"tool_calls": [
+                {"index": 0, "id": "call_a", "function": {"name": "Read", "arguments": "{\"file_path\": "}},
+                {"index": 1, "id": "call_b", "function": {"name": "Glob", "arguments": "[]"}},
+                {"index": 2, "id": "call_c", "function": {"name": "TodoRead", "arguments": ""}},
+            ]}}]}),
+            json!({"choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+        ];
+
+        let mut stream = ChatStream::default();
+        for chunk in &chunks {
+            stream.apply(chunk, &mut Recorder(String::new())).unwrap();
+        }
+        let result = stream.into_result();
+
+        assert_eq!(result.content.len(), 3);
+        let read = &result.invalid_tool_calls["call_a"];
+        assert!(
+            read.starts_with("The arguments for Read are not a JSON object (EOF"),
+            "{read}"
+        );
+        assert!(result.invalid_tool_calls["call_b"].contains("(not an object)"));
+        assert!(!result.invalid_tool_calls.contains_key("call_c"));
+    }
+}
@@ -5,16 +5,47 @@ use serde::Deserialize;
 /// Describes a tool invocation that requires permission.
 #[non_exhaustive]
 pub enum Tool<'a> {
-    Bash { command: &'a str },
-    Read { path: &'a Path },
-    Write { path: &'a Path },
-    Edit { path: &'a Path },
-    Fetch { url: &'a str, method: &'a str },
-    Git { subcommand: &'a str },
+    Bash {
+        command: &'a str,
+    },
+    Read {
+        path: &'a Path,
+    },
+    Write {
+        path: &'a Path,
+    },
+    Edit {
+        path: &'a Path,
+    },
+    Fetch {
+        url: &'a str,
+        method: &'a str,
+    },
+    Git {
+        subcommand: &'a str,
+    },
+    /// A GitHub or GitLab request, such as `create_pr`.
+    Repo {
+        action: &'a str,
+    },
     Glob,
     Grep,
     List,
     Search,
+    /// Only updates the task list shown to the user.
+    TodoWrite,
+    /// Runs the project's test suite, which executes its code.
+    Test,
+}
+
+/// The answer to a permission request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Allow,
+    Deny,
+    /// Denied, with the user's reason or instructions, passed on to the
+    /// model.
+    DenyWithFeedback(String),
 }
 
 /// Determines whether a given tool invocation is allowed.
@@ -22,6 +53,16 @@ pub enum Tool<'a> {
 /// `&mut self` allows stateful handlers (caching decisions, counters, etc.).
 pub trait PermissionHandler: Send {
     fn allow(&mut self, tool: &Tool<'_>) -> bool;
+
+    /// Like [`allow`](Self::allow), but a denial may carry feedback for the
+    /// model. Defaults to `allow`.
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        if self.allow(tool) {
+            Decision::Allow
+        } else {
+            Decision::Deny
+        }
+    }
 }
 
 /// Permits every tool invocation.
@@ -46,6 +87,93 @@ impl PermissionHandler for Box<dyn PermissionHandler> {
     fn allow(&mut self, tool: &Tool<'_>) -> bool {
         (**self).allow(tool)
     }
+
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        (**self).decide(tool)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Permission modes
+// ---------------------------------------------------------------------------
+
+/// How much the agent may do without asking, layered on top of the rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    /// Rules decide; anything unmatched is prompted.
+    #[default]
+    Default,
+    /// File writes and edits are allowed anywhere unless a rule denies them.
+    AcceptEdits,
+    /// Read-only: anything that can modify the system is denied.
+    Plan,
+    /// Everything is allowed, including what deny rules would block.
+    BypassPermissions,
+}
+
+impl PermissionMode {
+    /// Combine the mode with the rule decision from [`PermissionConfig::check`].
+    pub fn resolve(self, tool: &Tool<'_>, rule: Option<bool>) -> Option<bool> {
+        match self {
+            Self::Default => rule,
+            Self::AcceptEdits => match tool {
+                Tool::Write { .. } | Tool::Edit { .. } => rule.or(Some(true)),
+                _ => rule,
+            },
+            Self::Plan => {
+                if is_mutating(tool) {
+                    Some(false)
+                } else {
+                    rule
+                }
+            }
+            Self::BypassPermissions => Some(true),
+        }
+    }
+}
+
+impl std::str::FromStr for PermissionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "acceptEdits" => Ok(Self::AcceptEdits),
+            "plan" => Ok(Self::Plan),
+            "bypassPermissions" => Ok(Self::BypassPermissions),
+            _ => Err(format!(
+                "unknown permission mode '{s}' (expected default, acceptEdits, plan or bypassPermissions)"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for PermissionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Default => "default",
+            Self::AcceptEdits => "acceptEdits",
+            Self::Plan => "plan",
+            Self::BypassPermissions => "bypassPermissions",
+        })
+    }
+}
+
+/// Whether a tool invocation can change files, run programs or send data.
+fn is_mutating(tool: &Tool<'_>) -> bool {
+    match tool {
+        Tool::Bash { .. } | Tool::Write { .. } | Tool::Edit { .. } | Tool::Test => true,
+        Tool::Fetch { method, .. } => !method.eq_ignore_ascii_case("GET"),
+        Tool::Git { subcommand } => !is_readonly_git_command(subcommand),
+        Tool::Repo { action } => !is_readonly_repo_action(action),
+        Tool::Read { .. }
+        | Tool::Glob
+        | Tool::Grep
+        | Tool::List
+        | Tool::Search
+        | Tool::TodoWrite => false,
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -59,7 +187,8 @@ impl PermissionHandler for Box<dyn PermissionHandler> {
 ///   "permissions": {
 ///     "allow": ["Bash(psql:*)", "Bash(find:*)"],
 ///     "deny": [],
-///     "additionalDirectories": ["/extra/path"]
+///     "additionalDirectories": ["/extra/path"],
+///     "defaultMode": "acceptEdits"
 ///   }
 /// }
 /// ```
@@ -73,9 +202,64 @@ pub struct PermissionConfig {
 
     #[serde(default, rename = "additionalDirectories")]
     pub additional_directories: Vec<PathBuf>,
+
+    /// Mode to start in when `--permission-mode` isn't given.
+    #[serde(default, rename = "defaultMode")]
+    pub default_mode: Option<PermissionMode>,
+
+    /// `"disable"` refuses `bypassPermissions` mode, so deny rules always
+    /// apply. Usually set in managed settings.
+    #[serde(default, rename = "disableBypassPermissionsMode")]
+    pub disable_bypass_permissions_mode: Option<String>,
+}
+
+impl PermissionConfig {
+    pub fn bypass_disabled(&self) -> bool {
+        self.disable_bypass_permissions_mode.as_deref() == Some("disable")
+    }
+}
+
+/// Which rule list a permission rule belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleKind {
+    Allow,
+    Deny,
+}
+
+impl RuleKind {
+    /// Key of the list under `permissions` in settings files.
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        }
+    }
 }
 
 impl PermissionConfig {
+    /// Add a rule unless it is already present.
+    pub fn add_rule(&mut self, kind: RuleKind, rule: &str) {
+        let rules = self.rules_mut(kind);
+        if !rules.iter().any(|r| r == rule) {
+            rules.push(rule.to_string());
+        }
+    }
+
+    /// Remove every copy of a rule. Returns `false` if it wasn't present.
+    pub fn remove_rule(&mut self, kind: RuleKind, rule: &str) -> bool {
+        let rules = self.rules_mut(kind);
+        let before = rules.len();
+        rules.retain(|r| r != rule);
+        rules.len() != before
+    }
+
+    fn rules_mut(&mut self, kind: RuleKind) -> &mut Vec<String> {
+        match kind {
+            RuleKind::Allow => &mut self.allow,
+            RuleKind::Deny => &mut self.deny,
+        }
+    }
+
     /// Check if a tool invocation is auto-allowed by the configured rules.
     ///
     /// Returns `Some(true)` if explicitly allowed, `Some(false)` if explicitly
@@ -93,12 +277,15 @@ impl PermissionConfig {
 
         // Read-only tools are always allowed
         match tool {
-            Tool::Glob | Tool::Grep | Tool::List | Tool::Search => return Some(true),
-            Tool::Git { subcommand } => {
-                // Read-only git commands are auto-allowed
-                if is_readonly_git_command(subcommand) {
-                    return Some(true);
-                }
+            Tool::Glob | Tool::Grep | Tool::List | Tool::Search | Tool::TodoWrite => {
+                return Some(true);
+            }
+            // Read-only git commands are auto-allowed
+            Tool::Git { subcommand } if is_readonly_git_command(subcommand) => {
+                return Some(true);
+            }
+            Tool::Repo { action } if is_readonly_repo_action(action) => {
+                return Some(true);
             }
             _ => {}
         }
@@ -143,10 +330,58 @@ fn rule_matches(rule: &str, tool: &Tool<'_>) -> bool {
         ("Write", Tool::Write { path }) => pattern_matches(&path.display().to_string(), pattern),
         ("Edit", Tool::Edit { path }) => pattern_matches(&path.display().to_string(), pattern),
         ("Git", Tool::Git { subcommand }) => pattern_matches(subcommand, pattern),
+        ("Repo", Tool::Repo { action }) => pattern_matches(action, pattern),
+        ("Test", Tool::Test) => pattern == "*",
         _ => false,
     }
 }
 
+/// An allow rule covering `tool` and similar calls, for "always allow":
+/// the command and its subcommand for Bash (`Bash(cargo test:*)`), the
+/// subcommand for Git, the action for Repo, every run for Test, and the
+/// exact path for file tools. `None` for tools
+/// rules cannot match.
+pub fn suggested_rule(tool: &Tool<'_>) -> Option<String> {
+    match tool {
+        Tool::Bash { command } => {
+            let mut words = command.split_whitespace();
+            let program = words.next()?;
+            // Subcommands like `test` in `cargo test`, but not flags or paths
+            let prefix = match words.next() {
+                Some(sub)
+                    if sub.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                        && !sub.starts_with('-') =>
+                {
+                    format!("{program} {sub}")
+                }
+                _ => program.to_string(),
+            };
+            Some(format!("Bash({prefix}:*)"))
+        }
+        Tool::Git { subcommand } => {
+            let sub = subcommand.split_whitespace().next()?;
+            Some(format!("Git({sub}:*)"))
+        }
+        Tool::Repo { action } => Some(format!("Repo({action})")),
+        Tool::Test => Some("Test(*)".to_string()),
+        Tool::Read { path } => Some(format!("Read({})", path.display())),
+        Tool::Write { path } => Some(format!("Write({})", path.display())),
+        Tool::Edit { path } => Some(format!("Edit({})", path.display())),
+        _ => None,
+    }
+}
+
+/// Check that a rule is `ToolName(pattern)` for a tool rules can match.
+pub fn is_valid_rule(rule: &str) -> bool {
+    parse_rule(rule).is_some_and(|(tool, pattern)| {
+        !pattern.is_empty()
+            && matches!(
+                tool,
+                "Bash" | "Read" | "Write" | "Edit" | "Git" | "Repo" | "Test"
+            )
+    })
+}
+
 /// Extract tool name and pattern from `ToolName(pattern)`.
 fn parse_rule(rule: &str) -> Option<(&str, &str)> {
     let open = rule.find('(')?;
@@ -190,6 +425,11 @@ fn is_readonly_git_command(subcommand: &str) -> bool {
     )
 }
 
+/// Check if a Repo action only reads from the forge.
+fn is_readonly_repo_action(action: &str) -> bool {
+    matches!(action, "list_issues" | "view_issue" | "ci_status")
+}
+
 /// Resolve a potentially relative path against the project directory.
 fn resolve_path(path: &Path, project_dir: &Path) -> PathBuf {
     if path.is_absolute() {
@@ -360,6 +600,24 @@ mod tests {
         assert_eq!(config.check(&Tool::Grep, project), Some(true));
     }
 
+    #[test]
+    fn test_test_runs_need_a_rule() {
+        let project = Path::new("/project");
+        assert_eq!(
+            PermissionConfig::default().check(&Tool::Test, project),
+            None
+        );
+        assert_eq!(PermissionMode::Plan.resolve(&Tool::Test, None), Some(false));
+
+        let rule = suggested_rule(&Tool::Test).unwrap();
+        assert!(is_valid_rule(&rule));
+        let config = PermissionConfig {
+            allow: vec![rule],
+            ..Default::default()
+        };
+        assert_eq!(config.check(&Tool::Test, project), Some(true));
+    }
+
     #[test]
     fn test_git_readonly_allowed() {
         let config = PermissionConfig::default();
@@ -425,6 +683,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_repo_reads_allowed_and_writes_need_a_rule() {
+        let project = Path::new("/project");
+        let ci = Tool::Repo {
+            action: "ci_status",
+        };
+        let pr = Tool::Repo {
+            action: "create_pr",
+        };
+
+        let config = PermissionConfig::default();
+        assert_eq!(config.check(&ci, project), Some(true));
+        assert_eq!(config.check(&pr, project), None);
+        assert_eq!(PermissionMode::Plan.resolve(&pr, None), Some(false));
+
+        let rule = suggested_rule(&pr).unwrap();
+        assert_eq!(rule, "Repo(create_pr)");
+        assert!(is_valid_rule(&rule));
+        let config = PermissionConfig {
+            allow: vec![rule],
+            ..Default::default()
+        };
+        assert_eq!(config.check(&pr, project), Some(true));
+        assert_eq!(
+            config.check(
+                &Tool::Repo {
+                    action: "comment_issue"
+                },
+                project
+            ),
+            None
+        );
+    }
+
     #[test]
     fn test_edit_in_project_dir() {
         let config = PermissionConfig::default();
@@ -449,4 +741,120 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    fn test_permission_mode_parse_roundtrip() {
+        for mode in [
+            PermissionMode::Default,
+            PermissionMode::AcceptEdits,
+            PermissionMode::Plan,
+            PermissionMode::BypassPermissions,
+        ] {
+            assert_eq!(mode.to_string().parse::<PermissionMode>(), Ok(mode));
+        }
+        assert!("yolo".parse::<PermissionMode>().is_err());
+    }
+
+    #[test]
+    fn test_permission_mode_resolve() {
+        let edit = Tool::Edit {
+            path: Path::new("/elsewhere/file.rs"),
+        };
+        let bash = Tool::Bash { command: "ls" };
+        let status = Tool::Git {
+            subcommand: "status",
+        };
+
+        assert_eq!(PermissionMode::Default.resolve(&edit, None), None);
+
+        assert_eq!(PermissionMode::AcceptEdits.resolve(&edit, None), Some(true));
+        assert_eq!(
+            PermissionMode::AcceptEdits.resolve(&edit, Some(false)),
+            Some(false)
+        );
+        assert_eq!(PermissionMode::AcceptEdits.resolve(&bash, None), None);
+
+        assert_eq!(PermissionMode::Plan.resolve(&bash, Some(true)), Some(false));
+        assert_eq!(
+            PermissionMode::Plan.resolve(&status, Some(true)),
+            Some(true)
+        );
+
+        assert_eq!(
+            PermissionMode::BypassPermissions.resolve(&bash, Some(false)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_is_valid_rule() {
+        assert!(is_valid_rule("Bash(cargo:*)"));
+        assert!(is_valid_rule("Git(push:*)"));
+        assert!(!is_valid_rule("Bash()"));
+        assert!(!is_valid_rule("Fetch(*)"));
+        assert!(!is_valid_rule("cargo test"));
+    }
+
+    #[test]
+    fn test_add_and_remove_rule() {
+        let mut config = PermissionConfig::default();
+
+        config.add_rule(RuleKind::Allow, "Bash(ls:*)");
+        config.add_rule(RuleKind::Allow, "Bash(ls:*)");
+        assert_eq!(config.allow, vec!["Bash(ls:*)"]);
+        assert_eq!(
+            config.check(&Tool::Bash { command: "ls -la" }, Path::new("/p")),
+            Some(true)
+        );
+
+        assert!(config.remove_rule(RuleKind::Allow, "Bash(ls:*)"));
+        assert!(!config.remove_rule(RuleKind::Allow, "Bash(ls:*)"));
+        assert_eq!(
+            config.check(&Tool::Bash { command: "ls -la" }, Path::new("/p")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_suggested_rule_matches_the_call() {
+        let cases = [
+            (
+                Tool::Bash {
+                    command: "cargo test -p core",
+                },
+                "Bash(cargo test:*)",
+            ),
+            (
+                Tool::Bash {
+                    command: "ls -la src",
+                },
+                "Bash(ls:*)",
+            ),
+            (
+                Tool::Git {
+                    subcommand: "push origin main",
+                },
+                "Git(push:*)",
+            ),
+            (
+                Tool::Edit {
+                    path: Path::new("/etc/hosts"),
+                },
+                "Edit(/etc/hosts)",
+            ),
+        ];
+
+        for (tool, expected) in cases {
+            let rule = suggested_rule(&tool).unwrap();
+            assert_eq!(rule, expected);
+            assert!(is_valid_rule(&rule));
+            assert!(rule_matches(&rule, &tool));
+        }
+
+        let fetch = Tool::Fetch {
+            url: "https://example.com",
+            method: "GET",
+        };
+        assert_eq!(suggested_rule(&fetch), None);
+    }
 }
@@ -0,0 +1,188 @@
+//! Pinned files: kept at the top of the context, re-read whenever they
+//! change, so the model always has their current contents.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Result, bail};
+
+/// Most of a pinned file that goes into the context; the rest is cut off
+/// with a note.
+const MAX_PINNED_BYTES: usize = 50_000;
+
+#[derive(Debug, Default)]
+pub struct PinnedFiles {
+    files: Vec<Pinned>,
+}
+
+#[derive(Debug)]
+struct Pinned {
+    path: PathBuf,
+    /// Modification time at the last read, `None` if the file was missing.
+    mtime: Option<(u64, u32)>,
+    content: Option<String>,
+}
+
+impl PinnedFiles {
+    /// Pin `paths`, which need not exist yet: missing files are left out
+    /// of the context until they appear.
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut pinned = Self::default();
+        for path in paths {
+            if !pinned.contains(&path) {
+                pinned.files.push(Pinned::read(path));
+            }
+        }
+        pinned
+    }
+
+    /// Pin an existing file. Returns `false` if it was pinned already.
+    pub fn add(&mut self, path: PathBuf) -> Result<bool> {
+        if self.contains(&path) {
+            return Ok(false);
+        }
+        if !path.is_file() {
+            bail!("{} is not a file", path.display());
+        }
+        self.files.push(Pinned::read(path));
+        Ok(true)
+    }
+
+    /// Returns `false` if `path` wasn't pinned.
+    pub fn remove(&mut self, path: &Path) -> bool {
+        let before = self.files.len();
+        self.files.retain(|file| file.path != path);
+        self.files.len() != before
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|file| file.path.as_path())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.files.iter().any(|file| file.path == path)
+    }
+
+    /// Re-read the files whose modification time changed. Returns `true`
+    /// if any content did.
+    pub fn refresh(&mut self) -> bool {
+        let mut changed = false;
+        for file in &mut self.files {
+            if get_mtime(&file.path) != file.mtime {
+                let fresh = Pinned::read(file.path.clone());
+                changed |= fresh.content != file.content;
+                *file = fresh;
+            }
+        }
+        changed
+    }
+
+    /// The files' contents for the context, paths shown relative to `cwd`.
+    /// `None` if nothing pinned is readable.
+    pub fn render(&self, cwd: &Path) -> Option<String> {
+        let mut text = String::new();
+        for file in &self.files {
+            let Some(content) = &file.content else {
+                continue;
+            };
+            let path = file.path.strip_prefix(cwd).unwrap_or(&file.path);
+            text.push_str(&format!(
+                "\n<pinned-file path=\"{}\">\n{}\n</pinned-file>\n",
+                path.display(),
+                content.trim_end()
+            ));
+        }
+        if text.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "The user pinned these files. Their current contents are always shown here, so \
+             there's no need to Read them again.\n{text}"
+        ))
+    }
+}
+
+impl Pinned {
+    fn read(path: PathBuf) -> Self {
+        // Stat first: a write between the two reads is caught next time
+        let mtime = get_mtime(&path);
+        let content = std::fs::read(&path).ok().map(|bytes| {
+            let mut text = String::from_utf8_lossy(&bytes).into_owned();
+            if text.len() > MAX_PINNED_BYTES {
+                let mut end = MAX_PINNED_BYTES;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                text.truncate(end);
+                text.push_str(&format!(
+                    "\n[... truncated: the file has {} bytes; Read the rest if needed]",
+                    bytes.len()
+                ));
+            }
+            text
+        });
+        Self {
+            path,
+            mtime,
+            content,
+        }
+    }
+}
+
+fn get_mtime(path: &Path) -> Option<(u64, u32)> {
+    let modified = path.metadata().ok()?.modified().ok()?;
+    let duration = modified.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+    Some((duration.as_secs(), duration.subsec_nanos()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_files_are_re_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        let schema = tmp.path().join("schema.json");
+        std::fs::write(&schema, "{\"v\": 1}").unwrap();
+
+        let mut pinned = PinnedFiles::new([schema.clone(), tmp.path().join("later.md")]);
+        let text = pinned.render(tmp.path()).unwrap();
+        assert!(text.contains("<pinned-file path=\"schema.json\">\n{\"v\": 1}\n</pinned-file>"));
+        assert!(!text.contains("later.md"));
+        assert!(!pinned.refresh());
+
+        // Force a different mtime whatever the filesystem's resolution
+        std::fs::write(&schema, "{\"v\": 2}").unwrap();
+        let file = std::fs::File::options().write(true).open(&schema).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1))
+            .unwrap();
+        std::fs::write(tmp.path().join("later.md"), "notes").unwrap();
+
+        assert!(pinned.refresh());
+        let text = pinned.render(tmp.path()).unwrap();
+        assert!(text.contains("{\"v\": 2}"));
+        assert!(text.contains("<pinned-file path=\"later.md\">\nnotes\n"));
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("a.txt");
+        std::fs::write(&file, "x".repeat(MAX_PINNED_BYTES + 10)).unwrap();
+
+        let mut pinned = PinnedFiles::default();
+        assert!(pinned.render(tmp.path()).is_none());
+        assert!(pinned.add(file.clone()).unwrap());
+        assert!(!pinned.add(file.clone()).unwrap());
+        assert!(pinned.add(tmp.path().join("missing")).is_err());
+        assert!(pinned.render(tmp.path()).unwrap().contains("truncated"));
+
+        assert!(pinned.remove(&file));
+        assert!(!pinned.remove(&file));
+        assert!(pinned.is_empty());
+    }
+}
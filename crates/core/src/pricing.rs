@@ -0,0 +1,155 @@
+use crate::api::Usage;
+
+/// Per-million-token prices in USD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+/// Opus 4.5 and later.
+const OPUS: Pricing = Pricing {
+    input: 5.0,
+    output: 25.0,
+    cache_write: 6.25,
+    cache_read: 0.50,
+};
+
+/// Opus 4.1 and earlier, back to Claude 3 Opus.
+const OPUS_4: Pricing = Pricing {
+    input: 15.0,
+    output: 75.0,
+    cache_write: 18.75,
+    cache_read: 1.50,
+};
+
+const SONNET: Pricing = Pricing {
+    input: 3.0,
+    output: 15.0,
+    cache_write: 3.75,
+    cache_read: 0.30,
+};
+
+const HAIKU: Pricing = Pricing {
+    input: 1.0,
+    output: 5.0,
+    cache_write: 1.25,
+    cache_read: 0.10,
+};
+
+const HAIKU_3_5: Pricing = Pricing {
+    input: 0.80,
+    output: 4.0,
+    cache_write: 1.0,
+    cache_read: 0.08,
+};
+
+const HAIKU_3: Pricing = Pricing {
+    input: 0.25,
+    output: 1.25,
+    cache_write: 0.30,
+    cache_read: 0.03,
+};
+
+/// Model versions and their prices; a date or other suffix may follow.
+const PRICES: &[(&str, Pricing)] = &[
+    ("claude-opus-4-6", OPUS),
+    ("claude-opus-4-5", OPUS),
+    ("claude-opus-4-1", OPUS_4),
+    ("claude-opus-4", OPUS_4),
+    ("claude-3-opus", OPUS_4),
+    ("claude-sonnet-4-5", SONNET),
+    ("claude-sonnet-4", SONNET),
+    ("claude-3-7-sonnet", SONNET),
+    ("claude-3-5-sonnet", SONNET),
+    ("claude-haiku-4-5", HAIKU),
+    ("claude-3-5-haiku", HAIKU_3_5),
+    ("claude-3-haiku", HAIKU_3),
+];
+
+/// Published list prices by model version, for Anthropic IDs and the
+/// Bedrock and Vertex forms of them (`us.anthropic.claude-…-v1:0`,
+/// `claude-…@date`). `None` for versions not listed here, rather than a
+/// guess from the family.
+pub fn pricing(model: &str) -> Option<Pricing> {
+    let id = &model[model.find("claude-")?..];
+    PRICES
+        .iter()
+        .find(|(version, _)| {
+            id.strip_prefix(version)
+                .is_some_and(|rest| !is_minor_version(rest))
+        })
+        .map(|(_, pricing)| *pricing)
+}
+
+/// Whether what follows a version continues it, as `-1` does after
+/// `claude-opus-4`, rather than being a date (`-20250514`) or other suffix.
+fn is_minor_version(rest: &str) -> bool {
+    let Some(rest) = rest.strip_prefix('-') else {
+        return rest.starts_with(|c: char| c.is_ascii_digit());
+    };
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    (1..8).contains(&digits)
+}
+
+/// Estimated cost in USD of `usage` on `model`.
+pub fn cost(model: &str, usage: &Usage) -> Option<f64> {
+    let p = pricing(model)?;
+
+    let tokens_times_price = usage.input_tokens as f64 * p.input
+        + usage.output_tokens as f64 * p.output
+        + usage.cache_creation_input_tokens as f64 * p.cache_write
+        + usage.cache_read_input_tokens as f64 * p.cache_read;
+
+    Some(tokens_times_price / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cost_sums_all_token_kinds() {
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_creation_input_tokens: 200_000,
+            cache_read_input_tokens: 1_000_000,
+        };
+
+        // 3.00 + 1.50 + 0.75 + 0.30
+        let cost = cost("claude-sonnet-4-5", &usage).unwrap();
+        assert!((cost - 5.55).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unknown_model_has_no_cost() {
+        assert_eq!(cost("gpt-4", &Usage::default()), None);
+        assert_eq!(pricing("claude-haiku-4-5"), Some(HAIKU));
+        // Newer versions aren't priced like the ones before them
+        assert_eq!(pricing("claude-opus-4-7"), None);
+        assert_eq!(pricing("claude-opus-5"), None);
+        assert_eq!(pricing("claude-3-5-opus"), None);
+    }
+
+    #[test]
+    fn older_versions_have_their_own_prices() {
+        assert_eq!(pricing("claude-opus-4-6"), Some(OPUS));
+        assert_eq!(pricing("claude-opus-4-5-20251101"), Some(OPUS));
+        assert_eq!(pricing("claude-opus-4-1"), Some(OPUS_4));
+        assert_eq!(pricing("claude-opus-4-20250514"), Some(OPUS_4));
+        assert_eq!(pricing("claude-3-opus-20240229"), Some(OPUS_4));
+        assert_eq!(pricing("claude-3-5-haiku-latest"), Some(HAIKU_3_5));
+        assert_eq!(pricing("claude-3-haiku-20240307"), Some(HAIKU_3));
+        assert_eq!(pricing("claude-sonnet-4-20250514"), Some(SONNET));
+
+        // Bedrock and Vertex IDs
+        assert_eq!(
+            pricing("us.anthropic.claude-opus-4-1-20250805-v1:0"),
+            Some(OPUS_4)
+        );
+        assert_eq!(pricing("claude-3-5-haiku@20241022"), Some(HAIKU_3_5));
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::tools::glob::project_walker;
+
+/// Files looked at before the language census stops, to bound `/init` on
+/// huge repositories.
+const MAX_FILES: usize = 50_000;
+
+/// Languages listed in the generated summary.
+const MAX_LANGUAGES: usize = 5;
+
+/// What `/init` learned about a repository.
+#[derive(Debug, Default)]
+pub struct ProjectInfo {
+    /// Languages by file count, most common first.
+    pub languages: Vec<(&'static str, usize)>,
+    pub build_systems: Vec<BuildSystem>,
+    /// Top-level directories, sorted.
+    pub top_dirs: Vec<String>,
+    pub branch: Option<String>,
+    /// Subjects of the latest commits, newest first.
+    pub recent_commits: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct BuildSystem {
+    pub name: &'static str,
+    /// `(label, command)`, e.g. `("Test", "cargo test")`.
+    pub commands: Vec<(&'static str, String)>,
+}
+
+/// Inspect the build files, file types and git history under `root`.
+pub fn analyze(root: &Path) -> ProjectInfo {
+    let (branch, recent_commits) = git_summary(root);
+
+    ProjectInfo {
+        languages: count_languages(root),
+        build_systems: detect_build_systems(root),
+        top_dirs: top_dirs(root),
+        branch,
+        recent_commits,
+    }
+}
+
+#[cfg(feature = "git")]
+fn git_summary(root: &Path) -> (Option<String>, Vec<String>) {
+    let branch = ccrs_git::current_branch(root).ok().flatten();
+    let subjects = ccrs_git::git_log(root, 5)
+        .map(|entries| {
+            entries
+                .into_iter()
+                .map(|e| e.message.lines().next().unwrap_or("").to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    (branch, subjects)
+}
+
+#[cfg(not(feature = "git"))]
+fn git_summary(_root: &Path) -> (Option<String>, Vec<String>) {
+    (None, Vec::new())
+}
+
+impl ProjectInfo {
+    /// Render a starter `CLAUDE.md` for the project called `name`.
+    pub fn to_claude_md(&self, name: &str) -> String {
+        let mut out = format!(
+            "# CLAUDE.md\n\nGuidance for working in the {name} repository.\n\n## Overview\n\n"
+        );
+
+        if !self.languages.is_empty() {
+            let langs: Vec<String> = self
+                .languages
+                .iter()
+                .map(|(lang, n)| format!("{lang} ({n} files)"))
+                .collect();
+            out.push_str(&format!("- Languages: {}\n", langs.join(", ")));
+        }
+
+        if !self.build_systems.is_empty() {
+            let names: Vec<&str> = self.build_systems.iter().map(|b| b.name).collect();
+            out.push_str(&format!("- Build system: {}\n", names.join(", ")));
+        }
+
+        if let Some(branch) = &self.branch {
+            out.push_str(&format!("- Git branch: `{branch}`\n"));
+        }
+
+        out.push_str("- TODO: describe what the project does.\n");
+
+        let commands: Vec<&(&str, String)> = self
+            .build_systems
+            .iter()
+            .flat_map(|b| &b.commands)
+            .collect();
+
+        if !commands.is_empty() {
+            out.push_str("\n## Commands\n\n");
+            for (label, command) in commands {
+                out.push_str(&format!("- {label}: `{command}`\n"));
+            }
+        }
+
+        if !self.top_dirs.is_empty() {
+            out.push_str("\n## Layout\n\n");
+            for dir in &self.top_dirs {
+                out.push_str(&format!("- `{dir}/` — TODO\n"));
+            }
+        }
+
+        if !self.recent_commits.is_empty() {
+            out.push_str("\n## Conventions\n\nRecent commit subjects, for message style:\n\n");
+            for subject in &self.recent_commits {
+                out.push_str(&format!("- {subject}\n"));
+            }
+        }
+
+        out
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Languages
+// ---------------------------------------------------------------------------
+
+fn language(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        "rs" => "Rust",
+        "ts" | "tsx" => "TypeScript",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "py" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "cxx" | "hpp" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "swift" => "Swift",
+        "sh" | "bash" => "Shell",
+        "zig" => "Zig",
+        "ex" | "exs" => "Elixir",
+        "scala" => "Scala",
+        _ => return None,
+    })
+}
+
+fn count_languages(root: &Path) -> Vec<(&'static str, usize)> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+
+    for entry in project_walker(root)
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .take(MAX_FILES)
+    {
+        if let Some(lang) = entry
+            .path()
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(language)
+        {
+            *counts.entry(lang).or_default() += 1;
+        }
+    }
+
+    let mut langs: Vec<_> = counts.into_iter().collect();
+    langs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    langs.truncate(MAX_LANGUAGES);
+    langs
+}
+
+// ---------------------------------------------------------------------------
+// Build systems
+// ---------------------------------------------------------------------------
+
+fn detect_build_systems(root: &Path) -> Vec<BuildSystem> {
+    let exists = |name: &str| root.join(name).is_file();
+    let read = |name: &str| fs::read_to_string(root.join(name)).unwrap_or_default();
+    let cmds = |list: &[(&'static str, &str)]| {
+        list.iter()
+            .map(|(label, cmd)| (*label, cmd.to_string()))
+            .collect::<Vec<_>>()
+    };
+
+    let mut systems = Vec::new();
+
+    if exists("Cargo.toml") {
+        let workspace = read("Cargo.toml").contains("[workspace]");
+        let all = if workspace { " --workspace" } else { "" };
+        systems.push(BuildSystem {
+            name: "Cargo",
+            commands: vec![
+                ("Build", format!("cargo build{all}")),
+                ("Test", format!("cargo test{all}")),
+                (
+                    "Lint",
+                    format!("cargo clippy{all} --all-targets -- -D warnings"),
+                ),
+                ("Format", "cargo fmt --all".to_string()),
+            ],
+        });
+    }
+
+    if exists("package.json") {
+        systems.push(node_build_system(root, &read("package.json")));
+    }
+
+    if exists("pyproject.toml") || exists("setup.py") {
+        let pyproject = read("pyproject.toml");
+        let run = if exists("uv.lock") {
+            "uv run "
+        } else if exists("poetry.lock") {
+            "poetry run "
+        } else {
+            ""
+        };
+
+        let mut commands = vec![("Test", format!("{run}pytest"))];
+        if pyproject.contains("ruff") {
+            commands.push(("Lint", format!("{run}ruff check .")));
+            commands.push(("Format", format!("{run}ruff format .")));
+        }
+
+        systems.push(BuildSystem {
+            name: "Python",
+            commands,
+        });
+    }
+
+    if exists("go.mod") {
+        systems.push(BuildSystem {
+            name: "Go",
+            commands: cmds(&[
+                ("Build", "go build ./..."),
+                ("Test", "go test ./..."),
+                ("Lint", "go vet ./..."),
+                ("Format", "gofmt -w ."),
+            ]),
+        });
+    }
+
+    if exists("CMakeLists.txt") {
+        systems.push(BuildSystem {
+            name: "CMake",
+            commands: cmds(&[
+                ("Build", "cmake -B build && cmake --build build"),
+                ("Test", "ctest --test-dir build"),
+            ]),
+        });
+    }
+
+    if exists("pom.xml") {
+        systems.push(BuildSystem {
+            name: "Maven",
+            commands: cmds(&[("Build", "mvn package"), ("Test", "mvn test")]),
+        });
+    }
+
+    if exists("build.gradle") || exists("build.gradle.kts") {
+        let gradle = if exists("gradlew") {
+            "./gradlew"
+        } else {
+            "gradle"
+        };
+        systems.push(BuildSystem {
+            name: "Gradle",
+            commands: vec![
+                ("Build", format!("{gradle} build")),
+                ("Test", format!("{gradle} test")),
+            ],
+        });
+    }
+
+    if exists("Makefile") {
+        let makefile = read("Makefile");
+        let commands = ["build", "test", "lint", "fmt", "format"]
+            .iter()
+            .filter(|target| {
+                makefile
+                    .lines()
+                    .any(|l| l.starts_with(&format!("{target}:")))
+            })
+            .map(|target| (make_label(target), format!("make {target}")))
+            .collect();
+
+        systems.push(BuildSystem {
+            name: "Make",
+            commands,
+        });
+    }
+
+    systems
+}
+
+fn make_label(target: &str) -> &'static str {
+    match target {
+        "build" => "Build",
+        "test" => "Test",
+        "lint" => "Lint",
+        _ => "Format",
+    }
+}
+
+/// Node projects: package manager from the lockfile, commands from the
+/// `scripts` that exist.
+fn node_build_system(root: &Path, package_json: &str) -> BuildSystem {
+    let (name, runner) = if root.join("pnpm-lock.yaml").is_file() {
+        ("pnpm", "pnpm")
+    } else if root.join("yarn.lock").is_file() {
+        ("Yarn", "yarn")
+    } else if root.join("bun.lockb").is_file() || root.join("bun.lock").is_file() {
+        ("Bun", "bun run")
+    } else {
+        ("npm", "npm run")
+    };
+
+    let scripts: Vec<String> = serde_json::from_str::<serde_json::Value>(package_json)
+        .ok()
+        .and_then(|v| {
+            v.get("scripts")?
+                .as_object()
+                .map(|s| s.keys().cloned().collect())
+        })
+        .unwrap_or_default();
+
+    let commands = [
+        ("Build", "build"),
+        ("Test", "test"),
+        ("Lint", "lint"),
+        ("Format", "format"),
+    ]
+    .into_iter()
+    .filter(|(_, script)| scripts.iter().any(|s| s == script))
+    .map(|(label, script)| (label, format!("{runner} {script}")))
+    .collect();
+
+    BuildSystem { name, commands }
+}
+
+// ---------------------------------------------------------------------------
+// Layout
+// ---------------------------------------------------------------------------
+
+fn top_dirs(root: &Path) -> Vec<String> {
+    let mut dirs: Vec<String> = fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.') && !ccrs_utils::is_ignored_dir(name))
+        .collect();
+
+    dirs.sort();
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_cargo_workspace_and_languages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        fs::create_dir_all(root.join("crates/a/src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("crates/a/src/lib.rs"), "").unwrap();
+        fs::write(root.join("crates/a/src/main.rs"), "").unwrap();
+        fs::write(root.join("build.sh"), "").unwrap();
+        fs::write(root.join("target/out.rs"), "").unwrap();
+
+        let info = analyze(root);
+
+        assert_eq!(info.languages, vec![("Rust", 2), ("Shell", 1)]);
+        assert_eq!(info.top_dirs, vec!["crates"]);
+        assert_eq!(info.build_systems.len(), 1);
+        assert!(
+            info.build_systems[0]
+                .commands
+                .contains(&("Test", "cargo test --workspace".to_string()))
+        );
+    }
+
+    #[test]
+    fn node_commands_follow_scripts_and_lockfile() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::write(
+            root.join("package.json"),
+            r#"{"scripts":{"test":"vitest","lint":"eslint ."}}"#,
+        )
+        .unwrap();
+        fs::write(root.join("pnpm-lock.yaml"), "").unwrap();
+
+        let systems = detect_build_systems(root);
+
+        assert_eq!(systems[0].name, "pnpm");
+        assert_eq!(
+            systems[0].commands,
+            vec![
+                ("Test", "pnpm test".to_string()),
+                ("Lint", "pnpm lint".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn makefile_targets() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("Makefile"),
+            "build:\n\tcc x.c\ntest: build\n\t./t\n",
+        )
+        .unwrap();
+
+        let systems = detect_build_systems(tmp.path());
+
+        assert_eq!(
+            systems[0].commands,
+            vec![
+                ("Build", "make build".to_string()),
+                ("Test", "make test".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn claude_md_lists_commands_and_layout() {
+        let info = ProjectInfo {
+            languages: vec![("Go", 12)],
+            build_systems: vec![BuildSystem {
+                name: "Go",
+                commands: vec![("Test", "go test ./...".to_string())],
+            }],
+            top_dirs: vec!["cmd".to_string()],
+            branch: Some("main".to_string()),
+            recent_commits: vec!["Fix parser".to_string()],
+        };
+
+        let md = info.to_claude_md("demo");
+
+        assert!(md.starts_with("# CLAUDE.md\n\nGuidance for working in the demo repository."));
+        assert!(md.contains("- Languages: Go (12 files)\n"));
+        assert!(md.contains("- Git branch: `main`\n"));
+        assert!(md.contains("## Commands\n\n- Test: `go test ./...`\n"));
+        assert!(md.contains("- `cmd/` — TODO\n"));
+        assert!(md.contains("- Fix parser\n"));
+    }
+}
@@ -1,27 +1,125 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
-use crate::api::{ApiClient, Content, ContentBlock, Message, StopReason, Usage};
+use crate::agents::{self, AgentDef};
+use crate::api::{
+    ApiClient, Backend, Content, ContentBlock, Message, ModelAccess, StopReason, TurnUsage, Usage,
+    estimate_tokens,
+};
+use crate::auth::TokenRefresh;
+use crate::bedrock::Bedrock;
+use crate::config::RepoSettings;
 use crate::event::EventHandler;
-use crate::permission::{AllowAll, PermissionHandler};
-use crate::tools::{self, ToolRegistry};
+use crate::housekeeping;
+use crate::memory;
+use crate::models::{self, ModelAliases};
+use crate::openai::OpenAi;
+use crate::permission::{AllowAll, Decision, PermissionHandler};
+use crate::pinned::PinnedFiles;
+use crate::pricing;
+use crate::store::{self, SavedSession};
+use crate::subagent;
+use crate::tools::{self, AdditionalDirs, ToolEnv, ToolRegistry};
+use crate::vertex::Vertex;
 
 pub struct Session<P: PermissionHandler> {
     client: ApiClient,
+    id: String,
+    created_at: u64,
+    turns: Vec<TurnUsage>,
+    /// Context size after the latest request; 0 until one completes.
+    context_tokens: u64,
     cwd: PathBuf,
     permissions: P,
     messages: Vec<Message>,
     bootstrap_len: usize,
     system_prompt: String,
     tools: ToolRegistry,
+    additional_dirs: AdditionalDirs,
+    /// Files shown at the top of the context, re-read when they change.
+    pinned: PinnedFiles,
+    max_turns: Option<usize>,
+    instructions: Instructions,
+    model_aliases: ModelAliases,
+    /// Model name or alias for compaction, condensed tool output and
+    /// commit messages.
+    small_model: String,
+    summarize_tool_output: bool,
+    /// The configured budget, and the limits in force after any
+    /// extensions.
+    budget: Budget,
+    budget_limits: Budget,
+    token_refresh: Option<TokenRefresh>,
+    /// Recovery file of the turn in progress.
+    autosave: Option<store::Autosave>,
+    /// Snapshot the files to the session's checkpoint ref around each turn.
+    #[cfg(feature = "git")]
+    checkpoints: bool,
+}
+
+/// Caller overrides for the generated instructions in the bootstrap
+/// context. (The API system prompt itself stays fixed: OAuth tokens require
+/// it.)
+#[derive(Default)]
+struct Instructions {
+    replace: Option<String>,
+    append: Option<String>,
+}
+
+/// `send_message` stopped after `max_turns` model round-trips with tool
+/// calls still pending.
+#[derive(Debug, thiserror::Error)]
+#[error("Stopped after reaching the limit of {0} turns")]
+pub struct MaxTurnsReached(pub usize);
+
+/// Spending limits for a session, counted over all its turns, resumed ones
+/// included. Reaching one pauses the session with [`BudgetExceeded`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    /// Estimated cost in US dollars. Models without known prices don't
+    /// count towards it.
+    pub max_usd: Option<f64>,
+    /// Input, cache and output tokens.
+    pub max_tokens: Option<u64>,
+}
+
+/// `send_message` paused because the session reached its budget. Nothing
+/// more is sent until [`Session::extend_budget`] raises it.
+#[derive(Debug, thiserror::Error)]
+#[error("{reason}")]
+pub struct BudgetExceeded {
+    pub reason: String,
+    /// The turn was stopped after some of its requests, rather than before
+    /// it started.
+    pub mid_turn: bool,
 }
 
 pub struct SessionBuilder {
     access_token: String,
     is_oauth: bool,
     cwd: Option<PathBuf>,
+    model: Option<String>,
+    resume: Option<SavedSession>,
+    additional_dirs: Vec<PathBuf>,
+    pinned_files: Vec<PathBuf>,
+    max_turns: Option<usize>,
+    max_tokens: Option<u32>,
+    thinking: Option<u32>,
+    instructions: Instructions,
+    model_aliases: ModelAliases,
+    small_model: Option<String>,
+    summarize_tool_output: bool,
+    budget: Budget,
+    tool_env: ToolEnv,
+    repo: RepoSettings,
+    #[cfg(feature = "git")]
+    checkpoints: bool,
+    token_refresh: Option<TokenRefresh>,
+    backend: Backend,
 }
 
 impl SessionBuilder {
@@ -30,6 +128,24 @@ impl SessionBuilder {
             access_token,
             is_oauth,
             cwd: None,
+            model: None,
+            resume: None,
+            additional_dirs: Vec::new(),
+            pinned_files: Vec::new(),
+            max_turns: None,
+            max_tokens: None,
+            thinking: None,
+            instructions: Instructions::default(),
+            model_aliases: ModelAliases::default(),
+            small_model: None,
+            summarize_tool_output: false,
+            budget: Budget::default(),
+            tool_env: ToolEnv::default(),
+            repo: RepoSettings::default(),
+            #[cfg(feature = "git")]
+            checkpoints: false,
+            token_refresh: None,
+            backend: Backend::Anthropic,
         }
     }
 
@@ -39,6 +155,163 @@ impl SessionBuilder {
         self
     }
 
+    #[must_use]
+    pub fn model(mut self, model: String) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Directories outside `cwd` the session may use. Search indexes them
+    /// too; file access is still up to the permission handler.
+    #[must_use]
+    pub fn additional_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.additional_dirs = dirs;
+        self
+    }
+
+    /// Files to keep at the top of the context, relative to `cwd` or
+    /// absolute. Missing ones are shown once they exist.
+    #[must_use]
+    pub fn pinned_files(mut self, paths: Vec<PathBuf>) -> Self {
+        self.pinned_files = paths;
+        self
+    }
+
+    /// Cap the model round-trips one `send_message` may take; reaching it
+    /// fails with [`MaxTurnsReached`].
+    #[must_use]
+    pub fn max_turns(mut self, max_turns: usize) -> Self {
+        self.max_turns = Some(max_turns);
+        self
+    }
+
+    /// Limit each response to `max_tokens` output tokens (default
+    /// [`DEFAULT_MAX_TOKENS`](crate::api::DEFAULT_MAX_TOKENS)).
+    #[must_use]
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Let the model think before answering, with up to `budget` tokens
+    /// (at least 1024) of extended thinking per request.
+    #[must_use]
+    pub fn thinking(mut self, budget: u32) -> Self {
+        self.thinking = Some(budget);
+        self
+    }
+
+    /// How agent definitions' `model` names resolve (default: the built-in
+    /// models only).
+    #[must_use]
+    pub fn model_aliases(mut self, aliases: ModelAliases) -> Self {
+        self.model_aliases = aliases;
+        self
+    }
+
+    /// Model, by ID or alias, for housekeeping requests: compaction,
+    /// condensed tool output and drafted commit messages (default: haiku).
+    #[must_use]
+    pub fn small_model(mut self, model: String) -> Self {
+        self.small_model = Some(model);
+        self
+    }
+
+    /// Pause the session once it has spent this much (default: no limit).
+    #[must_use]
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Have the small model condense long Bash and Fetch output before the
+    /// session's model reads it (default: off).
+    #[must_use]
+    pub fn summarize_tool_output(mut self, enabled: bool) -> Self {
+        self.summarize_tool_output = enabled;
+        self
+    }
+
+    /// Environment for the commands tools run, from the `env` setting.
+    #[must_use]
+    pub fn tool_env(mut self, env: ToolEnv) -> Self {
+        self.tool_env = env;
+        self
+    }
+
+    /// Tokens and hosts for the Repo tool (default: tokens from the
+    /// environment).
+    #[must_use]
+    pub fn repo(mut self, settings: RepoSettings) -> Self {
+        self.repo = settings;
+        self
+    }
+
+    /// Snapshot the working tree to `refs/ccrs/checkpoints/<session id>`
+    /// before and after each turn, when the working directory is in a git
+    /// repository (default: off).
+    #[cfg(feature = "git")]
+    #[must_use]
+    pub fn checkpoints(mut self, enabled: bool) -> Self {
+        self.checkpoints = enabled;
+        self
+    }
+
+    /// Renew the OAuth access token before it expires, for sessions that
+    /// outlive it.
+    #[must_use]
+    pub fn token_refresh(mut self, refresh: TokenRefresh) -> Self {
+        self.token_refresh = Some(refresh);
+        self
+    }
+
+    /// Send requests to Amazon Bedrock, signed with AWS credentials; the
+    /// access token is then unused.
+    #[must_use]
+    pub fn bedrock(mut self, bedrock: Bedrock) -> Self {
+        self.backend = Backend::Bedrock(bedrock);
+        self
+    }
+
+    /// Send requests to Google Vertex AI, authorized with Google
+    /// credentials; the access token is then unused.
+    #[must_use]
+    pub fn vertex(mut self, vertex: Vertex) -> Self {
+        self.backend = Backend::Vertex(vertex);
+        self
+    }
+
+    /// Send requests to an OpenAI-compatible endpoint, such as a local
+    /// Ollama or vLLM server; the access token is then unused.
+    #[must_use]
+    pub fn openai(mut self, openai: OpenAi) -> Self {
+        self.backend = Backend::OpenAi(openai);
+        self
+    }
+
+    /// Use `prompt` instead of the generated instructions (tool guide,
+    /// directories and memory files).
+    #[must_use]
+    pub fn system_prompt(mut self, prompt: String) -> Self {
+        self.instructions.replace = Some(prompt);
+        self
+    }
+
+    /// Add `prompt` after the generated (or replaced) instructions.
+    #[must_use]
+    pub fn append_system_prompt(mut self, prompt: String) -> Self {
+        self.instructions.append = Some(prompt);
+        self
+    }
+
+    /// Continue a saved conversation. Its model is used unless `model()` is
+    /// also set.
+    #[must_use]
+    pub fn resume(mut self, saved: SavedSession) -> Self {
+        self.resume = Some(saved);
+        self
+    }
+
     pub fn permissions<P: PermissionHandler>(self, permissions: P) -> Result<Session<P>> {
         let cwd = match self.cwd {
             Some(cwd) => cwd,
@@ -47,49 +320,17 @@ impl SessionBuilder {
 
         let system_prompt = "You are Claude Code, Anthropic's official CLI for Claude.".to_string();
 
-        let git_tool_line = if cfg!(feature = "git") {
-            "\n             - **Git**: Git operations (status, diff, log, branch, add, commit, push, reset, checkout) via libgit2. Prefer this over `git` CLI."
-        } else {
-            ""
-        };
-
-        let search_tool_line = if cfg!(feature = "search") {
-            "\n             - **Search**: Full-text search across the codebase with BM25 ranking."
-        } else {
-            ""
-        };
+        let pinned = PinnedFiles::new(self.pinned_files.iter().map(|path| cwd.join(path)));
 
-        let context_prompt = format!(
-            "Working directory: {cwd}\n\
-             \n\
-             You have access to these tools:\n\
-             - **Bash**: Execute shell commands. Use for running programs, builds, etc.\n\
-             - **Read**: Read a file's contents. Always prefer this over `cat` or `head`.\n\
-             - **Write**: Write content to a file. Always prefer this over shell redirects.\n\
-             - **Edit**: Perform exact string replacements in files.\n\
-             - **Glob**: Find files by glob pattern (e.g. \"**/*.rs\"). Use this instead of `find`.\n\
-             - **List**: List directory contents. Use this instead of `ls`.\n\
-             - **Fetch**: Make HTTP requests (GET, POST, etc.). Use this instead of curl/wget.\n\
-             - **Grep**: Search file contents with regex. Use this instead of `grep`.{git_tool_line}{search_tool_line}\n\
-             \n\
-             Important:\n\
-             - Use Read/Write/Edit instead of Bash for file operations.\n\
-             - Use List instead of `ls`, Glob instead of `find`, Grep instead of `grep`.\n\
-             - Use Fetch instead of curl/wget for HTTP requests.{git_use_hint}\n\
-             - Keep responses concise.\n\
-             - When executing commands, use the working directory as the base for relative paths.",
-            cwd = cwd.display(),
-            git_use_hint = if cfg!(feature = "git") {
-                "\n             - Use the Git tool instead of `git` CLI for status, diff, log, and branch operations."
-            } else {
-                ""
-            },
-        );
+        let additional_dirs = AdditionalDirs::default();
+        for dir in self.additional_dirs {
+            additional_dirs.add(dir);
+        }
 
         let bootstrap_messages = vec![
             Message {
                 role: "user".to_string(),
-                content: Content::text(context_prompt),
+                content: Content::text(String::new()),
             },
             Message {
                 role: "assistant".to_string(),
@@ -101,15 +342,50 @@ impl SessionBuilder {
 
         let bootstrap_len = bootstrap_messages.len();
 
-        Ok(Session {
+        let mut session = Session {
             client: ApiClient::new(self.access_token, self.is_oauth),
+            id: store::new_id(),
+            created_at: store::now(),
+            turns: Vec::new(),
+            context_tokens: 0,
             cwd,
             permissions,
             messages: bootstrap_messages,
             bootstrap_len,
             system_prompt,
-            tools: tools::default_registry(),
-        })
+            tools: tools::default_registry(&additional_dirs, self.tool_env, self.repo),
+            additional_dirs,
+            pinned,
+            max_turns: self.max_turns,
+            instructions: self.instructions,
+            model_aliases: self.model_aliases,
+            small_model: self
+                .small_model
+                .unwrap_or_else(|| housekeeping::DEFAULT_SMALL_MODEL.to_string()),
+            summarize_tool_output: self.summarize_tool_output,
+            budget: self.budget,
+            budget_limits: self.budget,
+            token_refresh: self.token_refresh,
+            autosave: None,
+            #[cfg(feature = "git")]
+            checkpoints: self.checkpoints,
+        };
+        session.reload_memory();
+
+        if let Some(saved) = self.resume {
+            session.resume(saved);
+        }
+
+        if let Some(model) = self.model {
+            session.set_model(model);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            session.client.set_max_tokens(max_tokens);
+        }
+        session.client.set_thinking(self.thinking);
+        session.client.set_backend(self.backend);
+
+        Ok(session)
     }
 
     pub fn build(self) -> Result<Session<AllowAll>> {
@@ -118,6 +394,10 @@ impl SessionBuilder {
 }
 
 impl<P: PermissionHandler> Session<P> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
     pub fn cwd(&self) -> &Path {
         &self.cwd
     }
@@ -130,8 +410,245 @@ impl<P: PermissionHandler> Session<P> {
         &self.messages
     }
 
+    /// Re-read memory files into the bootstrap context, e.g. after editing
+    /// them mid-session.
+    pub fn reload_memory(&mut self) {
+        self.messages[0].content = Content::text(self.context_prompt());
+    }
+
+    pub fn additional_dirs(&self) -> Vec<PathBuf> {
+        self.additional_dirs.get()
+    }
+
+    /// Give the session another directory to work in: Search indexes it and
+    /// the context lists it. Returns `false` if it was already added. Callers
+    /// grant file access separately through the permission handler.
+    pub fn add_dir(&mut self, dir: PathBuf) -> bool {
+        let added = self.additional_dirs.add(dir);
+        if added {
+            self.reload_memory();
+        }
+        added
+    }
+
+    /// Pin a file, relative to `cwd` or absolute. Returns `false` if it
+    /// was pinned already.
+    pub fn pin(&mut self, path: &Path) -> Result<bool> {
+        let added = self.pinned.add(self.cwd.join(path))?;
+        if added {
+            self.reload_memory();
+        }
+        Ok(added)
+    }
+
+    /// Returns `false` if `path` wasn't pinned.
+    pub fn unpin(&mut self, path: &Path) -> bool {
+        let removed = self.pinned.remove(&self.cwd.join(path));
+        if removed {
+            self.reload_memory();
+        }
+        removed
+    }
+
+    pub fn pinned_files(&self) -> Vec<PathBuf> {
+        self.pinned.paths().map(Path::to_path_buf).collect()
+    }
+
+    fn context_prompt(&self) -> String {
+        let mut prompt = match &self.instructions.replace {
+            Some(prompt) => prompt.clone(),
+            None => context_prompt(&self.cwd, &self.additional_dirs.get()),
+        };
+        if let Some(extra) = &self.instructions.append {
+            prompt.push_str("\n\n");
+            prompt.push_str(extra);
+        }
+        if let Some(pinned) = self.pinned.render(&self.cwd) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&pinned);
+        }
+        prompt
+    }
+
+    /// Messages exchanged after the bootstrap context.
+    pub fn history(&self) -> &[Message] {
+        &self.messages[self.bootstrap_len..]
+    }
+
+    /// Drop the conversation. The next save starts a new session file so the
+    /// cleared transcript stays resumable.
     pub fn clear(&mut self) {
         self.messages.truncate(self.bootstrap_len);
+        self.id = store::new_id();
+        self.created_at = store::now();
+        self.turns.clear();
+        self.context_tokens = 0;
+    }
+
+    /// Replace the conversation with a saved one, taking over its ID, model
+    /// and per-turn token usage.
+    pub fn resume(&mut self, saved: SavedSession) {
+        self.turns = saved.turn_usage();
+        self.messages.truncate(self.bootstrap_len);
+        self.messages.extend(saved.messages);
+        self.id = saved.id;
+        self.created_at = saved.created_at;
+        self.context_tokens = estimate_tokens(&self.messages);
+        self.client.set_model(saved.model);
+    }
+
+    /// Cumulative token usage for this conversation.
+    pub fn usage(&self) -> Usage {
+        let mut total = Usage::default();
+        for turn in &self.turns {
+            total += turn.usage;
+        }
+        total
+    }
+
+    /// Token usage of each completed turn, oldest first.
+    pub fn turns(&self) -> &[TurnUsage] {
+        &self.turns
+    }
+
+    /// Estimated cost in US dollars and tokens used so far, over every turn
+    /// including resumed ones.
+    pub fn spent(&self) -> (f64, u64) {
+        self.spent_with(Usage::default())
+    }
+
+    /// [`spent`](Self::spent), counting `pending` usage of the current
+    /// model not recorded yet.
+    fn spent_with(&self, pending: Usage) -> (f64, u64) {
+        let pending = TurnUsage {
+            model: self.model().to_string(),
+            usage: pending,
+        };
+        self.turns
+            .iter()
+            .chain([&pending])
+            .fold((0.0, 0), |(usd, tokens), turn| {
+                let usage = &turn.usage;
+                (
+                    usd + pricing::cost(&turn.model, usage).unwrap_or(0.0),
+                    tokens
+                        + usage.input_tokens
+                        + usage.output_tokens
+                        + usage.cache_creation_input_tokens
+                        + usage.cache_read_input_tokens,
+                )
+            })
+    }
+
+    /// The limits in force, which [`extend_budget`](Self::extend_budget)
+    /// raises.
+    pub fn budget_limits(&self) -> Budget {
+        self.budget_limits
+    }
+
+    /// Allow another configured budget's worth of spending on top of what
+    /// has been spent so far. Returns the new limits.
+    pub fn extend_budget(&mut self) -> Budget {
+        let (usd, tokens) = self.spent();
+        self.budget_limits = Budget {
+            max_usd: self.budget.max_usd.map(|max| usd + max),
+            max_tokens: self.budget.max_tokens.map(|max| tokens + max),
+        };
+        self.budget_limits
+    }
+
+    fn check_budget(&self, pending: Usage, mid_turn: bool) -> Result<()> {
+        let (usd, tokens) = self.spent_with(pending);
+        let limits = self.budget_limits;
+        let reason = if let Some(max) = limits.max_usd.filter(|&max| usd >= max) {
+            format!("Paused: this session has spent ${usd:.2} of its ${max:.2} budget.")
+        } else if let Some(max) = limits.max_tokens.filter(|&max| tokens >= max) {
+            format!("Paused: this session has used {tokens} of its {max} token budget.")
+        } else {
+            return Ok(());
+        };
+        Err(BudgetExceeded { reason, mid_turn }.into())
+    }
+
+    /// Tokens the conversation occupied after the latest request, out of
+    /// [`CONTEXT_WINDOW`](crate::api::CONTEXT_WINDOW). Estimated after a
+    /// resume or compaction; 0 before the first request and after a clear.
+    pub fn context_tokens(&self) -> u64 {
+        self.context_tokens
+    }
+
+    fn record_turn(&mut self, usage: Usage) {
+        if !usage.is_empty() {
+            self.turns.push(TurnUsage {
+                model: self.model().to_string(),
+                usage,
+            });
+        }
+    }
+
+    /// Checkpoint the files, unless nothing changed since the last
+    /// checkpoint. Failures are logged: they shouldn't stop the turn.
+    #[cfg(feature = "git")]
+    async fn snapshot(&self, message: String) {
+        if !self.checkpoints {
+            return;
+        }
+        let cwd = self.cwd.clone();
+        let id = self.id.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            // Not a repository: nothing to do
+            if ccrs_git::repo_root(&cwd).is_err() {
+                return Ok(None);
+            }
+            ccrs_git::snapshot(&cwd, &id, &message)
+        })
+        .await;
+        match result {
+            Ok(Ok(Some(checkpoint))) => tracing::debug!(id = %checkpoint.id, "checkpoint"),
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => tracing::warn!("could not checkpoint the files: {e:#}"),
+            Err(e) => tracing::warn!("checkpoint task failed: {e}"),
+        }
+    }
+
+    /// This session's checkpoints, newest first.
+    #[cfg(feature = "git")]
+    pub fn checkpoints(&self) -> Result<Vec<ccrs_git::Checkpoint>> {
+        ccrs_git::checkpoints(&self.cwd, &self.id)
+    }
+
+    /// Put the files back as they were at checkpoint `id`, which may come
+    /// from any session. The current files are checkpointed first, so the
+    /// restore can itself be undone. Returns the paths that changed.
+    #[cfg(feature = "git")]
+    pub fn restore_checkpoint(&self, id: &str) -> Result<Vec<String>> {
+        let checkpoint = ccrs_git::find_checkpoint(&self.cwd, id)?;
+        let message = format!("Before restoring {}", &checkpoint.id[..7]);
+        ccrs_git::snapshot(&self.cwd, &self.id, &message)?;
+        ccrs_git::restore(&self.cwd, &checkpoint.id)
+    }
+
+    /// Persist the conversation to the session store. Does nothing until the
+    /// first message has been sent.
+    pub fn save(&self) -> Result<()> {
+        if self.history().is_empty() {
+            return Ok(());
+        }
+
+        store::save(&self.saved())
+    }
+
+    fn saved(&self) -> SavedSession {
+        SavedSession {
+            id: self.id.clone(),
+            cwd: self.cwd.clone(),
+            model: self.model().to_string(),
+            created_at: self.created_at,
+            updated_at: store::now(),
+            usage: self.usage(),
+            turns: self.turns.clone(),
+            messages: self.history().to_vec(),
+        }
     }
 
     pub fn model(&self) -> &str {
@@ -142,34 +659,146 @@ impl<P: PermissionHandler> Session<P> {
         self.client.set_model(model);
     }
 
+    /// Extended thinking budget per request, if enabled.
+    pub fn thinking(&self) -> Option<u32> {
+        self.client.thinking()
+    }
+
+    pub fn set_thinking(&mut self, budget: Option<u32>) {
+        self.client.set_thinking(budget);
+    }
+
+    /// Swap the token used for API requests, e.g. after re-authenticating.
+    /// `refresh` renews it before it expires, as with
+    /// [`SessionBuilder::token_refresh`].
+    pub fn set_credentials(
+        &mut self,
+        access_token: String,
+        is_oauth: bool,
+        refresh: Option<TokenRefresh>,
+    ) {
+        self.client.set_credentials(access_token, is_oauth);
+        self.token_refresh = refresh;
+    }
+
+    /// Swap in a new access token if the current one is about to expire.
+    /// A failed refresh is logged and the old token kept: the request may
+    /// still succeed, and the next one tries again.
+    async fn refresh_token_if_due(&mut self) {
+        let Some(refresh) = &mut self.token_refresh else {
+            return;
+        };
+        match refresh.refresh_if_due().await {
+            Ok(Some(access_token)) => self.client.set_credentials(access_token, true),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("could not refresh the access token: {e:#}"),
+        }
+    }
+
+    /// The cloud provider serving the models, such as "Amazon Bedrock
+    /// (us-east-1)"; `None` for the Anthropic API.
+    pub fn cloud_provider(&self) -> Option<String> {
+        self.client.backend().describe()
+    }
+
+    /// Update the cached model list behind the family aliases, at most once
+    /// a day. The future owns its client, so it can be spawned.
+    pub fn refresh_model_list(&self) -> impl Future<Output = ()> + Send + 'static {
+        let client = self.client.clone();
+        async move { models::refresh(&client).await }
+    }
+
+    /// Check that the API is reachable and the credentials can use the
+    /// current model.
+    pub async fn check_model_access(&self) -> Result<ModelAccess> {
+        self.client.check_model_access().await
+    }
+
     pub async fn send_message(
         &mut self,
         input: &str,
         handler: &mut dyn EventHandler,
         cancel: &CancellationToken,
+    ) -> Result<Usage> {
+        self.check_budget(Usage::default(), false)?;
+
+        #[cfg(feature = "otel")]
+        let mut turn = crate::telemetry::Turn::start(self.model(), handler);
+        #[cfg(feature = "otel")]
+        let handler = &mut turn;
+
+        self.autosave = match store::Autosave::create(&self.id) {
+            Ok(autosave) => Some(autosave),
+            Err(e) => {
+                tracing::warn!("could not autosave the turn: {e:#}");
+                None
+            }
+        };
+        #[cfg(feature = "git")]
+        self.snapshot(format!("Before: {}", checkpoint_label(input)))
+            .await;
+        let result = self.run_turn(input, handler, cancel).await;
+        #[cfg(feature = "git")]
+        self.snapshot(format!("After: {}", checkpoint_label(input)))
+            .await;
+        if let Some(autosave) = self.autosave.take()
+            && let Err(e) = autosave.finish()
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        #[cfg(feature = "otel")]
+        turn.finish(&result, cancel.is_cancelled());
+        result
+    }
+
+    async fn run_turn(
+        &mut self,
+        input: &str,
+        handler: &mut dyn EventHandler,
+        cancel: &CancellationToken,
     ) -> Result<Usage> {
         self.messages.push(Message {
             role: "user".to_string(),
             content: Content::text(input),
         });
+        self.checkpoint(Usage::default(), &[]);
 
-        let tool_defs = self.tools.api_definitions();
+        // Re-read agent files each turn so new definitions apply immediately
+        let (agents, _) = agents::load(&self.cwd);
+        let tool_defs = self.tool_definitions(&agents);
         let tools_param = if tool_defs.is_empty() {
             None
         } else {
             Some(tool_defs.as_slice())
         };
 
-        let mut total_usage = Usage {
-            input_tokens: 0,
-            output_tokens: 0,
-        };
+        let mut total_usage = Usage::default();
+        let mut rounds = 0;
 
         loop {
             if cancel.is_cancelled() {
                 break;
             }
 
+            if self.max_turns.is_some_and(|max| rounds >= max) {
+                self.record_turn(total_usage);
+                return Err(MaxTurnsReached(rounds).into());
+            }
+            if rounds > 0
+                && let Err(e) = self.check_budget(total_usage, true)
+            {
+                self.record_turn(total_usage);
+                return Err(e);
+            }
+            rounds += 1;
+
+            // Edits to pinned files, by tools or anyone else, show up in
+            // the next request
+            if self.pinned.refresh() {
+                self.reload_memory();
+            }
+            self.refresh_token_if_due().await;
             let result = self
                 .client
                 .stream_message(
@@ -185,18 +814,22 @@ impl<P: PermissionHandler> Session<P> {
                 Ok(r) => r,
                 Err(e) => {
                     self.messages.pop(); // rollback
+                    // Earlier round-trips of this turn were still billed
+                    self.record_turn(total_usage);
                     return Err(e);
                 }
             };
 
-            total_usage.input_tokens += stream_result.usage.input_tokens;
-            total_usage.output_tokens += stream_result.usage.output_tokens;
+            total_usage += stream_result.usage;
+            self.context_tokens = stream_result.usage.context_tokens();
+            handler.on_usage(&stream_result.usage);
 
             // Push assistant message with all content blocks
             self.messages.push(Message {
                 role: "assistant".to_string(),
                 content: Content::blocks(stream_result.content.clone()),
             });
+            self.checkpoint(total_usage, &[]);
 
             if stream_result.stop_reason != StopReason::ToolUse {
                 break;
@@ -204,7 +837,14 @@ impl<P: PermissionHandler> Session<P> {
 
             // Execute tool calls and collect results
             let tool_results = self
-                .execute_tool_calls(&stream_result.content, handler)
+                .execute_tool_calls(
+                    &stream_result.content,
+                    &stream_result.invalid_tool_calls,
+                    &agents,
+                    total_usage,
+                    handler,
+                    cancel,
+                )
                 .await;
 
             if tool_results.is_empty() {
@@ -216,17 +856,225 @@ impl<P: PermissionHandler> Session<P> {
                 role: "user".to_string(),
                 content: Content::blocks(tool_results),
             });
+            self.checkpoint(total_usage, &[]);
         }
 
+        self.record_turn(total_usage);
         Ok(total_usage)
     }
 
+    /// Replace the conversation with a summary written by the small model, to
+    /// free up context. `focus` says what the summary should keep, e.g.
+    /// "the API design". Like [`clear`](Self::clear), this starts a new
+    /// session file, so the full transcript stays resumable.
+    pub async fn compact(
+        &mut self,
+        focus: Option<&str>,
+        cancel: &CancellationToken,
+    ) -> Result<Usage> {
+        if self.history().is_empty() {
+            bail!("Nothing to compact yet.");
+        }
+
+        let mut prompt = housekeeping::COMPACT_PROMPT.to_string();
+        if let Some(focus) = focus {
+            prompt.push_str(&format!("\n\nFocus the summary on: {focus}"));
+        }
+        let mut messages = housekeeping::without_thinking(&self.messages);
+        messages.push(Message {
+            role: "user".to_string(),
+            content: Content::text(prompt),
+        });
+
+        // Tool definitions are needed for the tool calls in the history
+        let (agents, _) = agents::load(&self.cwd);
+        let tool_defs = self.tool_definitions(&agents);
+        let system_prompt = self.system_prompt.clone();
+
+        let (summary, turn) = self
+            .housekeeping(&messages, &system_prompt, Some(&tool_defs), cancel)
+            .await?;
+        if summary.trim().is_empty() {
+            bail!("The model returned an empty summary.");
+        }
+
+        self.messages.truncate(self.bootstrap_len);
+        self.messages.push(Message {
+            role: "user".to_string(),
+            content: Content::text(format!("{}\n\n{summary}", housekeeping::COMPACT_PREAMBLE)),
+        });
+        self.id = store::new_id();
+        self.created_at = store::now();
+        self.context_tokens = estimate_tokens(&self.messages);
+
+        Ok(turn.usage)
+    }
+
+    /// Send a housekeeping request to the small model, falling back to the
+    /// session's model if that fails. Returns the reply's text and the
+    /// model and usage behind it, which are also recorded.
+    async fn housekeeping(
+        &mut self,
+        messages: &[Message],
+        system_prompt: &str,
+        tool_defs: Option<&[serde_json::Value]>,
+        cancel: &CancellationToken,
+    ) -> Result<(String, TurnUsage)> {
+        self.refresh_token_if_due().await;
+
+        let mut small = self.client.clone();
+        small.set_model(
+            self.model_aliases
+                .resolve(&self.small_model)
+                .map_or_else(|| self.small_model.clone(), |(id, _)| id),
+        );
+        small.set_thinking(None);
+
+        let result = match small
+            .stream_message(
+                messages,
+                Some(system_prompt),
+                tool_defs,
+                &mut Silent,
+                cancel,
+            )
+            .await
+        {
+            Ok(result) => (result, small.model().to_string()),
+            Err(e) if cancel.is_cancelled() || small.model() == self.model() => return Err(e),
+            Err(e) => {
+                tracing::warn!(
+                    model = small.model(),
+                    "housekeeping request failed, using the session's model: {e:#}"
+                );
+                let result = self
+                    .client
+                    .stream_message(
+                        messages,
+                        Some(system_prompt),
+                        tool_defs,
+                        &mut Silent,
+                        cancel,
+                    )
+                    .await?;
+                (result, self.model().to_string())
+            }
+        };
+        let (result, model) = result;
+        let turn = TurnUsage {
+            model,
+            usage: result.usage,
+        };
+        if !turn.usage.is_empty() {
+            self.turns.push(turn.clone());
+        }
+
+        let text = result
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        Ok((text, turn))
+    }
+
+    /// The small model's digest of a long tool output, or the output itself
+    /// if it's short, not worth condensing or the request fails.
+    async fn summarize_output(
+        &mut self,
+        name: &str,
+        input: &serde_json::Value,
+        output: tools::ToolOutput,
+        cancel: &CancellationToken,
+    ) -> tools::ToolOutput {
+        if !self.summarize_tool_output || !housekeeping::should_summarize(name, &output.content) {
+            return output;
+        }
+        let messages = [Message {
+            role: "user".to_string(),
+            content: Content::text(housekeeping::summarize_prompt(name, input, &output.content)),
+        }];
+        match self
+            .housekeeping(&messages, housekeeping::SYSTEM_PROMPT, None, cancel)
+            .await
+        {
+            Ok((summary, turn)) if !summary.trim().is_empty() => tools::ToolOutput {
+                content: housekeeping::summarized_output(
+                    &summary,
+                    name,
+                    output.content.len(),
+                    &turn.model,
+                ),
+                is_error: output.is_error,
+            },
+            Ok(_) => output,
+            Err(e) => {
+                tracing::warn!(tool = name, "could not condense the tool output: {e:#}");
+                output
+            }
+        }
+    }
+
+    /// A Git commit call without a message gets one drafted from the staged
+    /// diff. Other calls, and calls whose draft fails, are returned as they
+    /// are.
+    #[cfg(feature = "git")]
+    async fn draft_commit_message(
+        &mut self,
+        name: &str,
+        input: &serde_json::Value,
+        cancel: &CancellationToken,
+    ) -> serde_json::Value {
+        let needs_message = name == "Git"
+            && input.get("subcommand").and_then(|s| s.as_str()) == Some("commit")
+            && input
+                .get("message")
+                .and_then(|m| m.as_str())
+                .is_none_or(|m| m.trim().is_empty());
+        let Some(diff) = needs_message
+            .then(|| tools::git::staged_diff(&self.cwd))
+            .flatten()
+        else {
+            return input.clone();
+        };
+
+        let messages = [Message {
+            role: "user".to_string(),
+            content: Content::text(housekeeping::commit_prompt(&diff)),
+        }];
+        let reply = self
+            .housekeeping(&messages, housekeeping::SYSTEM_PROMPT, None, cancel)
+            .await;
+        let mut input = input.clone();
+        match reply.map(|(text, _)| housekeeping::commit_message(&text)) {
+            Ok(Some(message)) => input["message"] = serde_json::Value::String(message),
+            Ok(None) => tracing::warn!("the drafted commit message was empty"),
+            Err(e) => tracing::warn!("could not draft a commit message: {e:#}"),
+        }
+        input
+    }
+
+    fn tool_definitions(&self, agents: &[AgentDef]) -> Vec<serde_json::Value> {
+        let mut tool_defs = self.tools.api_definitions();
+        tool_defs.push(subagent::task_definition(agents));
+        tool_defs
+    }
+
     async fn execute_tool_calls(
         &mut self,
         content: &[ContentBlock],
+        invalid: &HashMap<String, String>,
+        agents: &[AgentDef],
+        usage: Usage,
         handler: &mut dyn EventHandler,
+        cancel: &CancellationToken,
     ) -> Vec<ContentBlock> {
         let mut results = Vec::new();
+        let mut parallel = self
+            .run_parallel_tasks(content, invalid, agents, handler, cancel)
+            .await;
 
         for block in content {
             let (id, name, input) = match block {
@@ -234,42 +1082,370 @@ impl<P: PermissionHandler> Session<P> {
                 _ => continue,
             };
 
-            handler.on_tool_use_start(name, id, input);
+            #[cfg(feature = "git")]
+            let drafted = self.draft_commit_message(name, input, cancel).await;
+            #[cfg(feature = "git")]
+            let input = &drafted;
 
-            // Permission check
-            let perm_tool = tools::to_permission_tool(name, input);
-            let allowed = match &perm_tool {
-                Some(tool) => self.permissions.allow(tool),
-                None => false,
+            let output = if let Some(error) = invalid.get(id) {
+                handler.on_tool_use_start(name, id, input);
+                handler.on_tool_result(name, error, true);
+                handler.on_tool_use_end(name);
+                tools::ToolOutput::error(error.clone())
+            } else if let Some(output) = parallel.remove(id) {
+                // Reported now, in call order, rather than as they finished
+                handler.on_tool_use_start(name, id, input);
+                handler.on_tool_executing(name, input);
+                handler.on_tool_result(name, &output.content, output.is_error);
+                handler.on_tool_use_end(name);
+                output
+            } else if name == subagent::TASK_TOOL {
+                self.run_task(id, input, agents, handler, cancel).await
+            } else {
+                let output = execute_tool(
+                    &self.tools,
+                    &mut self.permissions,
+                    &self.cwd,
+                    id,
+                    name,
+                    input,
+                    handler,
+                )
+                .await;
+                self.summarize_output(name, input, output, cancel).await
             };
 
-            let result = if !allowed {
-                ContentBlock::ToolResult {
-                    tool_use_id: id.clone(),
-                    content: "Permission denied by user.".to_string(),
-                    is_error: Some(true),
+            results.push(ContentBlock::ToolResult {
+                tool_use_id: id.clone(),
+                content: output.content,
+                is_error: if output.is_error { Some(true) } else { None },
+            });
+            self.checkpoint(usage, &results);
+        }
+
+        results
+    }
+
+    /// Write the turn so far to its recovery file: `usage` is the turn's
+    /// usage until now, `tool_results` the answers to the latest tool calls
+    /// that already ran.
+    fn checkpoint(&mut self, usage: Usage, tool_results: &[ContentBlock]) {
+        if self.autosave.is_none() {
+            return;
+        }
+        let mut saved = self.saved();
+        if !usage.is_empty() {
+            saved.usage += usage;
+            saved.turns.push(TurnUsage {
+                model: self.model().to_string(),
+                usage,
+            });
+        }
+        if !tool_results.is_empty() {
+            saved.messages.push(Message {
+                role: "user".to_string(),
+                content: Content::blocks(tool_results.to_vec()),
+            });
+        }
+
+        if let Some(autosave) = &mut self.autosave
+            && let Err(e) = autosave.write(&saved)
+        {
+            tracing::warn!("could not autosave the turn: {e:#}");
+            self.autosave = None;
+        }
+    }
+
+    /// Run a Task call in a subagent. Its sub-tool calls go through the same
+    /// permission handler; its token usage is recorded as a separate turn.
+    async fn run_task(
+        &mut self,
+        id: &str,
+        input: &serde_json::Value,
+        agents: &[AgentDef],
+        handler: &mut dyn EventHandler,
+        cancel: &CancellationToken,
+    ) -> tools::ToolOutput {
+        let name = subagent::TASK_TOOL;
+        handler.on_tool_use_start(name, id, input);
+        handler.on_tool_executing(name, input);
+
+        let context_prompt = self.context_prompt();
+        let parent = subagent::Parent {
+            client: &self.client,
+            tools: &self.tools,
+            permissions: &mut self.permissions,
+            cwd: &self.cwd,
+            system_prompt: &self.system_prompt,
+            context_prompt,
+            model_aliases: &self.model_aliases,
+        };
+        let (output, usage) = subagent::run(parent, agents, id, input, handler, cancel).await;
+        self.turns.extend(usage);
+
+        handler.on_tool_result(name, &output.content, output.is_error);
+        handler.on_tool_use_end(name);
+        output
+    }
+
+    /// Run a response's Task calls side by side when it has more than one.
+    /// Returns their outputs by tool use ID; empty otherwise.
+    async fn run_parallel_tasks(
+        &mut self,
+        content: &[ContentBlock],
+        invalid: &HashMap<String, String>,
+        agents: &[AgentDef],
+        handler: &mut dyn EventHandler,
+        cancel: &CancellationToken,
+    ) -> HashMap<String, tools::ToolOutput> {
+        let calls: Vec<(&str, &serde_json::Value)> = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { id, name, input }
+                    if name == subagent::TASK_TOOL && !invalid.contains_key(id) =>
+                {
+                    Some((id.as_str(), input))
                 }
-            } else {
-                handler.on_tool_executing(name, input);
+                _ => None,
+            })
+            .collect();
+        if calls.len() < 2 {
+            return HashMap::new();
+        }
 
-                let output = match self.tools.get(name) {
-                    Some(tool) => tool.execute_dyn(input, &self.cwd).await,
-                    None => tools::ToolOutput::error(format!("Unknown tool: {name}")),
-                };
+        let context_prompt = self.context_prompt();
+        let parent = subagent::Parent {
+            client: &self.client,
+            tools: &self.tools,
+            permissions: &mut self.permissions,
+            cwd: &self.cwd,
+            system_prompt: &self.system_prompt,
+            context_prompt,
+            model_aliases: &self.model_aliases,
+        };
+        let outcomes = subagent::run_parallel(parent, agents, &calls, handler, cancel).await;
 
-                handler.on_tool_result(name, &output.content, output.is_error);
+        let mut outputs = HashMap::new();
+        for ((id, _), (output, usage)) in calls.into_iter().zip(outcomes) {
+            self.turns.extend(usage);
+            outputs.insert(id.to_string(), output);
+        }
+        outputs
+    }
+}
 
-                ContentBlock::ToolResult {
-                    tool_use_id: id.clone(),
-                    content: output.content,
-                    is_error: if output.is_error { Some(true) } else { None },
+/// Check permission for and run one tool call, reporting progress to
+/// `handler`.
+pub(crate) async fn execute_tool<P: PermissionHandler>(
+    tools: &ToolRegistry,
+    permissions: &mut P,
+    cwd: &Path,
+    id: &str,
+    name: &str,
+    input: &serde_json::Value,
+    handler: &mut dyn EventHandler,
+) -> tools::ToolOutput {
+    handler.on_tool_use_start(name, id, input);
+
+    // Permission check
+    let perm_tool = tools::to_permission_tool(name, input);
+    let decision = match &perm_tool {
+        Some(tool) => permissions.decide(tool),
+        None => Decision::Deny,
+    };
+
+    let output = if decision != Decision::Allow {
+        tracing::debug!(tool = name, "tool call denied");
+        match decision {
+            Decision::DenyWithFeedback(feedback) => {
+                tools::ToolOutput::error(format!("Permission denied by user, who said: {feedback}"))
+            }
+            _ => tools::ToolOutput::error("Permission denied by user."),
+        }
+    } else {
+        tracing::debug!(tool = name, "executing tool");
+        handler.on_tool_executing(name, input);
+
+        let output = match tools.get(name) {
+            Some(tool) => {
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                let run = tool.execute_dyn(input, cwd, tools::ProgressSink::new(tx));
+                tokio::pin!(run);
+                loop {
+                    tokio::select! {
+                        output = &mut run => break output,
+                        Some(progress) = rx.recv() => handler.on_tool_progress(name, &progress),
+                    }
                 }
-            };
+            }
+            None => tools::ToolOutput::error(format!("Unknown tool: {name}")),
+        };
+
+        handler.on_tool_result(name, &output.content, output.is_error);
+        output
+    };
+
+    handler.on_tool_use_end(name);
+    output
+}
+
+/// Swallows the events of a request whose output is not shown.
+struct Silent;
+
+impl EventHandler for Silent {
+    fn on_text(&mut self, _text: &str) {}
+    fn on_error(&mut self, _message: &str) {}
+}
+
+/// A checkpoint message for the turn started by `prompt`: its first line,
+/// shortened.
+#[cfg(feature = "git")]
+fn checkpoint_label(prompt: &str) -> String {
+    const MAX_CHARS: usize = 60;
+    let line = prompt.lines().next().unwrap_or("").trim();
+    if line.chars().count() > MAX_CHARS {
+        let short: String = line.chars().take(MAX_CHARS).collect();
+        format!("{}…", short.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+/// The bootstrap context: working directory, tool guidance and memory files.
+fn context_prompt(cwd: &Path, additional_dirs: &[PathBuf]) -> String {
+    let git_tool_line = if cfg!(feature = "git") {
+        "\n             - **Git**: Git operations (status, diff, log, branch, add, commit, push, reset, checkout) via libgit2. Prefer this over `git` CLI.\n             - **Repo**: GitHub/GitLab issues, pull requests and CI status for the `origin` remote. Prefer this over `gh`/`glab`."
+    } else {
+        ""
+    };
+
+    let search_tool_line = if cfg!(feature = "search") {
+        "\n             - **Search**: Full-text search across the codebase with BM25 ranking."
+    } else {
+        ""
+    };
+
+    let mut prompt = format!(
+        "Working directory: {cwd}\n\
+         \n\
+         You have access to these tools:\n\
+         - **Bash**: Execute shell commands. Use for running programs, builds, etc.\n\
+         - **Test**: Run the project's tests (cargo, pytest or jest) and get each failure's name and message.\n\
+         - **Read**: Read a file's contents. Always prefer this over `cat` or `head`.\n\
+         - **Write**: Write content to a file. Always prefer this over shell redirects.\n\
+         - **Edit**: Perform exact string replacements in files.\n\
+         - **Glob**: Find files by glob pattern (e.g. \"**/*.rs\"). Use this instead of `find`.\n\
+         - **List**: List directory contents. Use this instead of `ls`.\n\
+         - **Fetch**: Make HTTP requests (GET, POST, etc.). Use this instead of curl/wget.\n\
+         - **Grep**: Search file contents with regex. Use this instead of `grep`.{git_tool_line}{search_tool_line}\n\
+         - **TodoWrite**: Keep a task list for multi-step work, updated as each step starts and finishes.\n\
+         \n\
+         Important:\n\
+         - Use Read/Write/Edit instead of Bash for file operations.\n\
+         - Use List instead of `ls`, Glob instead of `find`, Grep instead of `grep`.\n\
+         - Use Fetch instead of curl/wget for HTTP requests, and Test to run the test suite.{git_use_hint}\n\
+         - Keep responses concise.\n\
+         - When executing commands, use the working directory as the base for relative paths.",
+        cwd = cwd.display(),
+        git_use_hint = if cfg!(feature = "git") {
+            "\n             - Use the Git tool instead of `git` CLI for status, diff, log, and branch operations."
+        } else {
+            ""
+        },
+    );
 
-            handler.on_tool_use_end(name);
-            results.push(result);
+    if !additional_dirs.is_empty() {
+        prompt.push_str("\n\nAdditional directories you may also work in (use absolute paths):");
+        for dir in additional_dirs {
+            prompt.push_str(&format!("\n- {}", dir.display()));
         }
+    }
 
-        results
+    if let Some(memory) = memory::load(cwd) {
+        prompt.push_str("\n\nProject and user instructions (follow them):\n\n");
+        prompt.push_str(&memory);
+    }
+
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(builder: SessionBuilder) -> String {
+        let tmp = tempfile::tempdir().unwrap();
+        let session = builder.cwd(tmp.path().to_path_buf()).build().unwrap();
+        match &session.messages()[0].content {
+            Content::Text(text) => text.clone(),
+            other => panic!("unexpected bootstrap content: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn instructions_replace_and_extend_the_generated_prompt() {
+        let builder = || SessionBuilder::new(String::new(), false);
+
+        let generated = context(builder().append_system_prompt("Answer in French.".into()));
+        assert!(generated.starts_with("Working directory:"));
+        assert!(generated.ends_with("\n\nAnswer in French."));
+
+        let replaced = context(
+            builder()
+                .system_prompt("Only review diffs.".into())
+                .append_system_prompt("Be brief.".into()),
+        );
+        assert_eq!(replaced, "Only review diffs.\n\nBe brief.");
+    }
+
+    #[test]
+    fn pinned_files_follow_the_instructions() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("api.yaml"), "openapi: 3.1").unwrap();
+        let mut session = SessionBuilder::new(String::new(), false)
+            .cwd(tmp.path().to_path_buf())
+            .system_prompt("Be brief.".into())
+            .pinned_files(vec!["api.yaml".into()])
+            .build()
+            .unwrap();
+
+        let text = session.messages()[0].content.to_text();
+        assert!(text.starts_with("Be brief.\n\nThe user pinned these files."));
+        assert!(text.contains("<pinned-file path=\"api.yaml\">\nopenapi: 3.1\n"));
+        assert_eq!(session.pinned_files(), [tmp.path().join("api.yaml")]);
+
+        assert!(session.unpin(Path::new("api.yaml")));
+        assert_eq!(session.messages()[0].content.to_text(), "Be brief.");
+        assert!(session.pin(Path::new("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn budget_pauses_until_extended() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut session = SessionBuilder::new(String::new(), false)
+            .cwd(tmp.path().to_path_buf())
+            .budget(Budget {
+                max_usd: None,
+                max_tokens: Some(1_000),
+            })
+            .build()
+            .unwrap();
+        let usage = |input_tokens| Usage {
+            input_tokens,
+            output_tokens: 100,
+            ..Usage::default()
+        };
+
+        assert!(session.check_budget(usage(500), true).is_ok());
+        session.record_turn(usage(900));
+        let paused = session.check_budget(Usage::default(), false).unwrap_err();
+        let paused = paused.downcast_ref::<BudgetExceeded>().unwrap();
+        assert!(!paused.mid_turn);
+        assert!(paused.reason.contains("1000 of its 1000 token budget"));
+
+        let limits = session.extend_budget();
+        assert_eq!(limits.max_tokens, Some(2_000));
+        assert!(session.check_budget(Usage::default(), false).is_ok());
+        assert!(session.check_budget(usage(900), true).is_err());
     }
 }
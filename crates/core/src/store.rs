@@ -0,0 +1,628 @@
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{Content, ContentBlock, Message, TurnUsage, Usage};
+use crate::config;
+
+/// A conversation persisted to `<config_dir>/sessions/<id>.json`.
+///
+/// `messages` holds the conversation only — the bootstrap context is
+/// rebuilt when the session is resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub id: String,
+    pub cwd: PathBuf,
+    pub model: String,
+    /// Unix timestamp (seconds).
+    pub created_at: u64,
+    /// Unix timestamp (seconds).
+    pub updated_at: u64,
+    /// Cumulative token usage.
+    #[serde(default)]
+    pub usage: Usage,
+    #[serde(default)]
+    pub turns: Vec<TurnUsage>,
+    pub messages: Vec<Message>,
+}
+
+impl SavedSession {
+    /// Usage per turn. Sessions saved before turns were recorded only have
+    /// totals, which count as a single turn on the session's model.
+    pub fn turn_usage(&self) -> Vec<TurnUsage> {
+        if !self.turns.is_empty() || self.usage.is_empty() {
+            return self.turns.clone();
+        }
+        vec![TurnUsage {
+            model: self.model.clone(),
+            usage: self.usage,
+        }]
+    }
+
+    /// Text of the first user message, for listings.
+    pub fn first_prompt(&self) -> Option<String> {
+        self.messages
+            .iter()
+            .filter(|m| m.role == "user")
+            .find_map(|m| match &m.content {
+                Content::Text(text) => Some(text.clone()),
+                Content::Blocks(blocks) => blocks.iter().find_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.clone()),
+                    _ => None,
+                }),
+            })
+    }
+}
+
+/// Generate a new session ID: hex milliseconds, so IDs sort by creation
+/// time, then a counter that keeps this process's IDs apart and random bits
+/// for other processes'.
+pub fn new_id() -> String {
+    static NEXT: AtomicU16 = AtomicU16::new(0);
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let count = NEXT.fetch_add(1, Ordering::Relaxed);
+    format!("{millis:x}{count:04x}{:08x}", rand::random::<u32>())
+}
+
+/// Current Unix timestamp in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn sessions_dir() -> Result<PathBuf> {
+    let dir = config::config_dir()?.join("sessions");
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir).context("Failed to create sessions directory")?;
+    }
+
+    Ok(dir)
+}
+
+pub fn save(session: &SavedSession) -> Result<()> {
+    save_in(&sessions_dir()?, session)
+}
+
+pub fn load(id: &str) -> Result<SavedSession> {
+    load_in(&sessions_dir()?, id)
+}
+
+/// All saved sessions, most recently updated first.
+pub fn list() -> Result<Vec<SavedSession>> {
+    list_in(&sessions_dir()?)
+}
+
+/// The most recently updated session started in `cwd`.
+pub fn latest_for(cwd: &Path) -> Result<Option<SavedSession>> {
+    Ok(list()?.into_iter().find(|s| s.cwd == cwd))
+}
+
+fn save_in(dir: &Path, session: &SavedSession) -> Result<()> {
+    let path = dir.join(format!("{}.json", session.id));
+    let contents = serde_json::to_string(session)?;
+    open_private(&path, true)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .context("Failed to write session file")
+}
+
+/// Open `path` for writing, readable by the owner alone like the
+/// credentials file: sessions hold whole conversations.
+fn open_private(path: &Path, truncate: bool) -> std::io::Result<fs::File> {
+    let mut options = fs::File::options();
+    options.create(true).truncate(truncate).write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // Files from before sessions were private
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+fn load_in(dir: &Path, id: &str) -> Result<SavedSession> {
+    // IDs are plain hex; reject anything that could escape the directory
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        anyhow::bail!("Invalid session ID: {id}");
+    }
+
+    let path = dir.join(format!("{id}.json"));
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("No saved session with ID {id}"))?;
+    serde_json::from_str(&contents).context("Failed to parse session file")
+}
+
+// ---------------------------------------------------------------------------
+// Retention
+// ---------------------------------------------------------------------------
+
+/// How long saved sessions are kept, from the `cleanupPeriodDays` and
+/// `sessionsMaxSizeMb` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// Sessions idle for longer are deleted; `None` keeps them.
+    pub max_age_days: Option<u32>,
+    /// The oldest sessions are deleted until the rest fit; `None` for no
+    /// limit.
+    pub max_bytes: Option<u64>,
+}
+
+/// What [`prune`] deleted, or would delete on a dry run.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Pruned {
+    pub removed: usize,
+    pub bytes: u64,
+    pub kept: usize,
+}
+
+/// Delete sessions beyond the retention limits, judged by when each file
+/// was last written. `keep` is never deleted: the session being resumed.
+pub fn prune(retention: Retention, keep: Option<&str>, dry_run: bool) -> Result<Pruned> {
+    prune_in(&sessions_dir()?, retention, keep, now(), dry_run)
+}
+
+fn prune_in(
+    dir: &Path,
+    retention: Retention,
+    keep: Option<&str>,
+    now: u64,
+    dry_run: bool,
+) -> Result<Pruned> {
+    struct File {
+        path: PathBuf,
+        modified: u64,
+        bytes: u64,
+        pinned: bool,
+    }
+
+    let mut files: Vec<File> = fs::read_dir(dir)
+        .context("Failed to read sessions directory")?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            let path = entry.path();
+            let pinned = keep.is_some_and(|id| path.file_stem().is_some_and(|stem| stem == id));
+            Some(File {
+                path,
+                modified: modified.as_secs(),
+                bytes: meta.len(),
+                pinned,
+            })
+        })
+        .collect();
+    files.sort_by_key(|f| std::cmp::Reverse(f.modified));
+
+    let cutoff = retention
+        .max_age_days
+        .map(|days| now.saturating_sub(u64::from(days) * 24 * 60 * 60));
+    let mut pruned = Pruned::default();
+    let mut total = 0;
+
+    for file in files {
+        let expired = cutoff.is_some_and(|cutoff| file.modified < cutoff);
+        let over_size = retention
+            .max_bytes
+            .is_some_and(|max| total + file.bytes > max);
+
+        if file.pinned || !(expired || over_size) {
+            total += file.bytes;
+            pruned.kept += 1;
+            continue;
+        }
+        if !dry_run {
+            fs::remove_file(&file.path)
+                .with_context(|| format!("Failed to delete {}", file.path.display()))?;
+        }
+        pruned.removed += 1;
+        pruned.bytes += file.bytes;
+    }
+
+    Ok(pruned)
+}
+
+/// Unreadable or malformed files are skipped.
+fn list_in(dir: &Path) -> Result<Vec<SavedSession>> {
+    let mut sessions: Vec<SavedSession> = fs::read_dir(dir)
+        .context("Failed to read sessions directory")?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let contents = fs::read_to_string(entry.path()).ok()?;
+            serde_json::from_str(&contents).ok()
+        })
+        .collect();
+
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+    Ok(sessions)
+}
+
+// ---------------------------------------------------------------------------
+// Crash recovery
+// ---------------------------------------------------------------------------
+
+/// Result given to tool calls that were cut off by a crash.
+const INTERRUPTED_TOOL: &str = "Not run: ccrs stopped before this tool call finished.";
+
+/// The state of a turn in progress, in `<sessions>/recovery/<id>.json`.
+/// The file stays locked while its process lives, so one nobody holds was
+/// left behind by a crash.
+pub struct Autosave {
+    file: fs::File,
+    path: PathBuf,
+}
+
+impl Autosave {
+    pub fn create(id: &str) -> Result<Self> {
+        Self::create_in(&recovery_dir()?, id)
+    }
+
+    fn create_in(dir: &Path, id: &str) -> Result<Self> {
+        let path = dir.join(format!("{id}.json"));
+        let file = open_private(&path, false).context("Failed to create the recovery file")?;
+        file.try_lock()
+            .context("The session is already running elsewhere")?;
+        Ok(Self { file, path })
+    }
+
+    pub fn write(&mut self, session: &SavedSession) -> Result<()> {
+        let contents = serde_json::to_vec(session)?;
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file
+            .write_all(&contents)
+            .context("Failed to write the recovery file")
+    }
+
+    /// The turn ended without a crash: nothing to recover.
+    pub fn finish(self) -> Result<()> {
+        let Self { file, path } = self;
+        drop(file);
+        fs::remove_file(&path).context("Failed to remove the recovery file")
+    }
+}
+
+fn recovery_dir() -> Result<PathBuf> {
+    let dir = sessions_dir()?.join("recovery");
+    fs::create_dir_all(&dir).context("Failed to create the recovery directory")?;
+    Ok(dir)
+}
+
+/// The latest session in `cwd` that stopped mid-turn, with the tool calls
+/// it never answered marked as not run, so it can be resumed as is.
+pub fn interrupted_for(cwd: &Path) -> Result<Option<SavedSession>> {
+    interrupted_in(&recovery_dir()?, cwd)
+}
+
+/// Forget an interrupted session, whether or not it was restored.
+pub fn discard_interrupted(id: &str) -> Result<()> {
+    fs::remove_file(recovery_dir()?.join(format!("{id}.json")))
+        .context("Failed to remove the recovery file")
+}
+
+fn interrupted_in(dir: &Path, cwd: &Path) -> Result<Option<SavedSession>> {
+    let latest = fs::read_dir(dir)
+        .context("Failed to read the recovery directory")?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            // Held by a running session
+            let file = fs::File::open(entry.path()).ok()?;
+            file.try_lock_shared().ok()?;
+            let session: SavedSession = serde_json::from_reader(&file).ok()?;
+            (session.cwd == cwd).then_some(session)
+        })
+        .max_by_key(|session| session.updated_at);
+
+    Ok(latest.map(|mut session| {
+        answer_tool_calls(&mut session.messages);
+        session
+    }))
+}
+
+/// Give every tool call in the last assistant message a result, as the API
+/// requires before the conversation can go on.
+fn answer_tool_calls(messages: &mut Vec<Message>) {
+    let Some(asked) = messages.iter().rposition(|m| m.role == "assistant") else {
+        return;
+    };
+    let Content::Blocks(blocks) = &messages[asked].content else {
+        return;
+    };
+    let calls: Vec<String> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { id, .. } => Some(id.clone()),
+            _ => None,
+        })
+        .collect();
+    if calls.is_empty() {
+        return;
+    }
+
+    if asked + 1 == messages.len() {
+        messages.push(Message {
+            role: "user".to_string(),
+            content: Content::blocks(Vec::new()),
+        });
+    }
+    let Content::Blocks(results) = &mut messages[asked + 1].content else {
+        return;
+    };
+    for id in calls {
+        let answered = results.iter().any(
+            |block| matches!(block, ContentBlock::ToolResult { tool_use_id, .. } if *tool_use_id == id),
+        );
+        if !answered {
+            results.push(ContentBlock::ToolResult {
+                tool_use_id: id,
+                content: INTERRUPTED_TOOL.to_string(),
+                is_error: Some(true),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(id: &str, cwd: &str, updated_at: u64) -> SavedSession {
+        SavedSession {
+            id: id.to_string(),
+            cwd: PathBuf::from(cwd),
+            model: "claude-sonnet-4-5".to_string(),
+            created_at: 1,
+            updated_at,
+            usage: Usage::default(),
+            turns: Vec::new(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: Content::text("hello"),
+            }],
+        }
+    }
+
+    #[test]
+    fn save_and_load_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        save_in(tmp.path(), &saved("abc123", "/project", 10)).unwrap();
+
+        let loaded = load_in(tmp.path(), "abc123").unwrap();
+        assert_eq!(loaded.cwd, PathBuf::from("/project"));
+        assert_eq!(loaded.messages.len(), 1);
+        assert_eq!(loaded.messages[0].content.to_text(), "hello");
+    }
+
+    #[test]
+    fn load_rejects_path_traversal() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(load_in(tmp.path(), "../credentials").is_err());
+        assert!(load_in(tmp.path(), "").is_err());
+    }
+
+    #[test]
+    fn list_sorts_newest_first_and_skips_garbage() {
+        let tmp = tempfile::tempdir().unwrap();
+        save_in(tmp.path(), &saved("a", "/one", 10)).unwrap();
+        save_in(tmp.path(), &saved("b", "/two", 30)).unwrap();
+        save_in(tmp.path(), &saved("c", "/one", 20)).unwrap();
+        fs::write(tmp.path().join("broken.json"), "not json").unwrap();
+
+        let ids: Vec<String> = list_in(tmp.path())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn prune_deletes_expired_sessions_then_the_oldest_over_the_size_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let day = 24 * 60 * 60;
+        let now = now();
+        // Same-length IDs, so every file has the same size
+        for (id, age_days) in [("day00", 0), ("day07", 7), ("day40", 40), ("day90", 90)] {
+            save_in(tmp.path(), &saved(id, "/p", 1)).unwrap();
+            let file = fs::File::options()
+                .write(true)
+                .open(tmp.path().join(format!("{id}.json")))
+                .unwrap();
+            let modified = UNIX_EPOCH + std::time::Duration::from_secs(now - age_days * day);
+            file.set_modified(modified).unwrap();
+        }
+        let size = fs::metadata(tmp.path().join("day00.json")).unwrap().len();
+        let exists = |id: &str| tmp.path().join(format!("{id}.json")).exists();
+
+        // day90 is being resumed
+        let by_age = Retention {
+            max_age_days: Some(30),
+            max_bytes: None,
+        };
+        let dry = prune_in(tmp.path(), by_age, Some("day90"), now, true).unwrap();
+        assert_eq!((dry.removed, dry.kept), (1, 3));
+        assert!(exists("day40"));
+
+        prune_in(tmp.path(), by_age, Some("day90"), now, false).unwrap();
+        assert!(!exists("day40"));
+        assert!(exists("day90"));
+
+        let by_size = Retention {
+            max_age_days: None,
+            max_bytes: Some(size * 2),
+        };
+        let pruned = prune_in(tmp.path(), by_size, None, now, false).unwrap();
+        assert_eq!((pruned.removed, pruned.bytes), (1, size));
+        assert!(!exists("day90"));
+        assert!(exists("day07"));
+    }
+
+    #[test]
+    fn new_ids_are_unique_and_safe() {
+        let ids: std::collections::HashSet<String> = (0..1000).map(|_| new_id()).collect();
+        assert_eq!(ids.len(), 1000);
+        assert!(
+            ids.iter()
+                .all(|id| id.chars().all(|c| c.is_ascii_hexdigit()))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sessions_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("a.json");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        save_in(tmp.path(), &saved("a", "/one", 1)).unwrap();
+        save_in(tmp.path(), &saved("b", "/one", 1)).unwrap();
+        for id in ["a", "b"] {
+            let meta = fs::metadata(tmp.path().join(format!("{id}.json"))).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        }
+        assert_eq!(load_in(tmp.path(), "a").unwrap().id, "a");
+    }
+
+    #[test]
+    fn old_sessions_count_their_totals_as_one_turn() {
+        let mut session = saved("a", "/one", 1);
+        assert!(session.turn_usage().is_empty());
+
+        session.usage.input_tokens = 1200;
+        session.usage.output_tokens = 300;
+        let turns = session.turn_usage();
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0].model, "claude-sonnet-4-5");
+        assert_eq!(turns[0].usage, session.usage);
+
+        session.turns = vec![turns[0].clone(), turns[0].clone()];
+        assert_eq!(session.turn_usage().len(), 2);
+    }
+
+    #[test]
+    fn first_prompt_skips_tool_results() {
+        let mut session = saved("a", "/one", 1);
+        session.messages.insert(
+            0,
+            Message {
+                role: "user".to_string(),
+                content: Content::blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "t".to_string(),
+                    content: "output".to_string(),
+                    is_error: None,
+                }]),
+            },
+        );
+
+        assert_eq!(session.first_prompt().as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn sessions_without_usage_still_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("old.json"),
+            r#"{"id":"old","cwd":"/p","model":"m","created_at":1,"updated_at":2,"messages":[]}"#,
+        )
+        .unwrap();
+
+        let loaded = load_in(tmp.path(), "old").unwrap();
+        assert_eq!(loaded.usage.input_tokens, 0);
+    }
+
+    #[test]
+    fn only_unlocked_recovery_files_are_interrupted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cwd = Path::new("/project");
+
+        let mut running = Autosave::create_in(tmp.path(), "live").unwrap();
+        running.write(&saved("live", "/project", 5)).unwrap();
+        assert!(interrupted_in(tmp.path(), cwd).unwrap().is_none());
+
+        // A crash drops the file without finishing it
+        let mut crashed = Autosave::create_in(tmp.path(), "gone").unwrap();
+        crashed.write(&saved("gone", "/project", 3)).unwrap();
+        drop(crashed);
+        let found = interrupted_in(tmp.path(), cwd).unwrap().unwrap();
+        assert_eq!(found.id, "gone");
+        assert!(
+            interrupted_in(tmp.path(), Path::new("/other"))
+                .unwrap()
+                .is_none()
+        );
+
+        running.finish().unwrap();
+        assert!(!tmp.path().join("live.json").exists());
+    }
+
+    #[test]
+    fn interrupted_tool_calls_get_results() {
+        let call = |id: &str| ContentBlock::ToolUse {
+            id: id.to_string(),
+            name: "Bash".to_string(),
+            input: serde_json::json!({}),
+        };
+        let result = |id: &str, content: &str| ContentBlock::ToolResult {
+            tool_use_id: id.to_string(),
+            content: content.to_string(),
+            is_error: None,
+        };
+        let mut messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: Content::text("build it"),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: Content::blocks(vec![call("a"), call("b")]),
+            },
+            Message {
+                role: "user".to_string(),
+                content: Content::blocks(vec![result("a", "ok")]),
+            },
+        ];
+
+        answer_tool_calls(&mut messages);
+        let Content::Blocks(results) = &messages[2].content else {
+            panic!("expected tool results");
+        };
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            &results[1],
+            ContentBlock::ToolResult { tool_use_id, is_error: Some(true), .. } if tool_use_id == "b"
+        ));
+
+        // Nothing ran yet
+        messages.pop();
+        answer_tool_calls(&mut messages);
+        assert_eq!(messages.len(), 3);
+
+        // A finished turn is left alone
+        let mut done = vec![Message {
+            role: "assistant".to_string(),
+            content: Content::text("Done."),
+        }];
+        answer_tool_calls(&mut done);
+        assert_eq!(done.len(), 1);
+    }
+}
@@ -0,0 +1,537 @@
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use futures::{FutureExt, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::AgentDef;
+use crate::api::{
+    ApiClient, ApiError, Content, ContentBlock, Message, StopReason, TurnUsage, Usage,
+};
+use crate::event::{EventHandler, TaskStatus};
+use crate::models::ModelAliases;
+use crate::permission::{Decision, PermissionHandler, Tool};
+use crate::session;
+use crate::tools::{ToolOutput, ToolProgress, ToolRegistry};
+
+pub(crate) const TASK_TOOL: &str = "Task";
+
+/// Upper bound on model round-trips for one task.
+const MAX_ROUNDS: usize = 50;
+
+/// Task calls from one response that run at once; the others queue.
+pub(crate) const MAX_PARALLEL: usize = 4;
+
+/// Rate-limited or overloaded requests each subagent retries before it
+/// gives up, waiting twice as long each time.
+const RATE_LIMIT_RETRIES: u32 = 4;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// API definition of the Task tool, listing the available agents.
+pub(crate) fn task_definition(agents: &[AgentDef]) -> serde_json::Value {
+    let mut description = String::from(
+        "Launch a subagent to handle a self-contained task (research, review, a focused \
+         change) in a fresh context. The subagent returns a single final report; give it \
+         everything it needs in `prompt`, since it cannot see this conversation. For \
+         independent tasks, call Task several times in one response: they run in parallel.",
+    );
+
+    if !agents.is_empty() {
+        description.push_str("\n\nAvailable agents (pass the name as `subagent_type`):");
+        for agent in agents {
+            description.push_str(&format!("\n- {}: {}", agent.name, agent.description));
+        }
+        description.push_str("\nOmit `subagent_type` for a general-purpose agent.");
+    }
+
+    serde_json::json!({
+        "name": TASK_TOOL,
+        "description": description,
+        "input_schema": {
+            "type": "object",
+            "properties": {
+                "description": {
+                    "type": "string",
+                    "description": "Short (3-5 word) summary of the task"
+                },
+                "prompt": {
+                    "type": "string",
+                    "description": "Full instructions for the subagent"
+                },
+                "subagent_type": {
+                    "type": "string",
+                    "description": "Name of the agent to use"
+                }
+            },
+            "required": ["description", "prompt"]
+        }
+    })
+}
+
+/// Everything a subagent borrows from the parent session.
+pub(crate) struct Parent<'a, P: PermissionHandler> {
+    pub client: &'a ApiClient,
+    pub tools: &'a ToolRegistry,
+    pub permissions: &'a mut P,
+    pub cwd: &'a Path,
+    pub system_prompt: &'a str,
+    pub context_prompt: String,
+    pub model_aliases: &'a ModelAliases,
+}
+
+/// Run a Task tool call to completion. Returns the subagent's final report
+/// and the tokens it used, attributed to the model it ran on.
+pub(crate) async fn run<P: PermissionHandler>(
+    parent: Parent<'_, P>,
+    agents: &[AgentDef],
+    id: &str,
+    input: &serde_json::Value,
+    handler: &mut dyn EventHandler,
+    cancel: &CancellationToken,
+) -> (ToolOutput, Option<TurnUsage>) {
+    let Some(prompt) = input.get("prompt").and_then(|p| p.as_str()) else {
+        return (
+            ToolOutput::error("Missing required parameter: prompt"),
+            None,
+        );
+    };
+
+    let agent = match input.get("subagent_type").and_then(|s| s.as_str()) {
+        None | Some("") | Some("general-purpose") => None,
+        Some(name) => match agents.iter().find(|a| a.name == name) {
+            Some(agent) => Some(agent),
+            None => {
+                let known: Vec<&str> = agents.iter().map(|a| a.name.as_str()).collect();
+                let known = if known.is_empty() {
+                    "none defined".to_string()
+                } else {
+                    known.join(", ")
+                };
+                return (
+                    ToolOutput::error(format!("Unknown agent: {name} (available: {known})")),
+                    None,
+                );
+            }
+        },
+    };
+
+    let mut client = parent.client.clone();
+    if let Some(model) = agent.and_then(|a| a.model.as_deref()) {
+        let id = parent
+            .model_aliases
+            .resolve(model)
+            .map_or_else(|| model.to_string(), |(id, _)| id);
+        client.set_model(id);
+    }
+
+    let allowed = |name: &str| name != TASK_TOOL && agent.is_none_or(|a| a.allows(name));
+    let tool_defs: Vec<serde_json::Value> = parent
+        .tools
+        .api_definitions()
+        .into_iter()
+        .filter(|def| def["name"].as_str().is_some_and(allowed))
+        .collect();
+
+    let mut brief = parent.context_prompt;
+    if let Some(agent) = agent {
+        brief.push_str("\n\n");
+        brief.push_str(&agent.prompt);
+    }
+    brief.push_str("\n\nYour task:\n\n");
+    brief.push_str(prompt);
+    brief.push_str(
+        "\n\nWork autonomously. When done, reply with a concise final report; \
+         it is the only thing returned to the caller.",
+    );
+
+    let mut messages = vec![Message {
+        role: "user".to_string(),
+        content: Content::text(brief),
+    }];
+
+    let description = input
+        .get("description")
+        .and_then(|d| d.as_str())
+        .unwrap_or("");
+    let mut quiet = Quiet { inner: handler };
+    let mut usage = Usage::default();
+    let mut report = String::new();
+    let mut retries = 0;
+
+    for _ in 0..MAX_ROUNDS {
+        if cancel.is_cancelled() {
+            break;
+        }
+
+        let result = loop {
+            let result = client
+                .stream_message(
+                    &messages,
+                    Some(parent.system_prompt),
+                    Some(&tool_defs),
+                    &mut quiet,
+                    cancel,
+                )
+                .await;
+            match &result {
+                Err(e) if retries < RATE_LIMIT_RETRIES && is_rate_limited(e) => {
+                    let delay = FIRST_RETRY_DELAY * 2u32.pow(retries);
+                    retries += 1;
+                    let status = TaskStatus::Retrying(delay.as_secs());
+                    quiet.on_task_status(id, description, &status);
+                    tokio::select! {
+                        _ = cancel.cancelled() => break result,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                    quiet.on_task_status(id, description, &TaskStatus::Running(None));
+                }
+                _ => break result,
+            }
+        };
+
+        let stream_result = match result {
+            Ok(r) => r,
+            Err(e) => {
+                return (
+                    ToolOutput::error(format!("Subagent failed: {e:#}")),
+                    turn_usage(&client, usage),
+                );
+            }
+        };
+
+        usage += stream_result.usage;
+        report = stream_result
+            .content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        messages.push(Message {
+            role: "assistant".to_string(),
+            content: Content::blocks(stream_result.content.clone()),
+        });
+
+        if stream_result.stop_reason != StopReason::ToolUse {
+            break;
+        }
+
+        let mut results = Vec::new();
+        for block in &stream_result.content {
+            let ContentBlock::ToolUse { id, name, input } = block else {
+                continue;
+            };
+
+            let output = if let Some(error) = stream_result.invalid_tool_calls.get(id) {
+                ToolOutput::error(error.clone())
+            } else if allowed(name) {
+                session::execute_tool(
+                    parent.tools,
+                    parent.permissions,
+                    parent.cwd,
+                    id,
+                    name,
+                    input,
+                    &mut quiet,
+                )
+                .await
+            } else {
+                ToolOutput::error(format!("Tool {name} is not available to this agent."))
+            };
+
+            results.push(ContentBlock::ToolResult {
+                tool_use_id: id.clone(),
+                content: output.content,
+                is_error: output.is_error.then_some(true),
+            });
+        }
+
+        messages.push(Message {
+            role: "user".to_string(),
+            content: Content::blocks(results),
+        });
+    }
+
+    let output = if report.trim().is_empty() {
+        ToolOutput::error("Subagent finished without a report.")
+    } else {
+        ToolOutput::success(report)
+    };
+
+    (output, turn_usage(&client, usage))
+}
+
+/// Run several Task calls at once, at most [`MAX_PARALLEL`] at a time, each
+/// with its own cancellation token and retry budget. Their progress is
+/// reported as [`TaskStatus`] updates, since their tool events would
+/// interleave; the results come back in call order.
+pub(crate) async fn run_parallel<P: PermissionHandler>(
+    parent: Parent<'_, P>,
+    agents: &[AgentDef],
+    calls: &[(&str, &serde_json::Value)],
+    handler: &mut dyn EventHandler,
+    cancel: &CancellationToken,
+) -> Vec<(ToolOutput, Option<TurnUsage>)> {
+    let Parent {
+        client,
+        tools,
+        permissions,
+        cwd,
+        system_prompt,
+        context_prompt,
+        model_aliases,
+    } = parent;
+    let permissions = Mutex::new(permissions);
+    let handler = Mutex::new(handler);
+    let (permissions, handler, context_prompt) = (&permissions, &handler, &context_prompt);
+
+    for &(id, input) in calls {
+        let description = input["description"].as_str().unwrap_or("");
+        lock(handler).on_task_status(id, description, &TaskStatus::Queued);
+    }
+
+    let runs: Vec<_> = calls
+        .iter()
+        .map(|&(id, input)| {
+            async move {
+                let mut events = TaskEvents {
+                    handler,
+                    id,
+                    description: input["description"].as_str().unwrap_or(""),
+                };
+                events.status(TaskStatus::Running(None));
+
+                let parent = Parent {
+                    client,
+                    tools,
+                    permissions: &mut SharedPermissions(permissions),
+                    cwd,
+                    system_prompt,
+                    context_prompt: context_prompt.clone(),
+                    model_aliases,
+                };
+                let token = cancel.child_token();
+                let (output, usage) = run(parent, agents, id, input, &mut events, &token).await;
+
+                events.status(if output.is_error {
+                    TaskStatus::Failed
+                } else {
+                    TaskStatus::Done
+                });
+                (output, usage)
+            }
+            // Boxed and collected so the futures are known to be Send despite
+            // the borrows
+            .boxed()
+        })
+        .collect();
+
+    futures::stream::iter(runs)
+        .buffered(MAX_PARALLEL)
+        .collect()
+        .await
+}
+
+/// Whether a request failed for being rate limited (HTTP 429) or hitting
+/// an overloaded API (529), which waiting may fix.
+fn is_rate_limited(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<ApiError>(),
+        Some(ApiError::Request {
+            status: Some(429 | 529),
+            ..
+        })
+    )
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The parent's permission handler, shared by the parallel subagents.
+struct SharedPermissions<'a, 'p, P>(&'a Mutex<&'p mut P>);
+
+impl<P: PermissionHandler> PermissionHandler for SharedPermissions<'_, '_, P> {
+    fn allow(&mut self, tool: &Tool<'_>) -> bool {
+        lock(self.0).allow(tool)
+    }
+
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        lock(self.0).decide(tool)
+    }
+}
+
+/// One parallel subagent's events: its tool calls become status updates
+/// on the shared handler; errors pass through.
+struct TaskEvents<'a, 'h> {
+    handler: &'a Mutex<&'h mut dyn EventHandler>,
+    id: &'a str,
+    description: &'a str,
+}
+
+impl TaskEvents<'_, '_> {
+    fn status(&mut self, status: TaskStatus) {
+        lock(self.handler).on_task_status(self.id, self.description, &status);
+    }
+}
+
+impl EventHandler for TaskEvents<'_, '_> {
+    fn on_text(&mut self, _text: &str) {}
+
+    fn on_error(&mut self, message: &str) {
+        lock(self.handler).on_error(message);
+    }
+
+    fn on_tool_executing(&mut self, name: &str, _input: &serde_json::Value) {
+        self.status(TaskStatus::Running(Some(name.to_string())));
+    }
+
+    fn on_tool_use_end(&mut self, _name: &str) {
+        self.status(TaskStatus::Running(None));
+    }
+
+    fn on_task_status(&mut self, id: &str, description: &str, status: &TaskStatus) {
+        lock(self.handler).on_task_status(id, description, status);
+    }
+}
+
+fn turn_usage(client: &ApiClient, usage: Usage) -> Option<TurnUsage> {
+    (!usage.is_empty()).then(|| TurnUsage {
+        model: client.model().to_string(),
+        usage,
+    })
+}
+
+/// Forwards a subagent's tool activity to the parent handler but not its
+/// intermediate text; only the final report is returned.
+struct Quiet<'a> {
+    inner: &'a mut dyn EventHandler,
+}
+
+impl EventHandler for Quiet<'_> {
+    fn on_text(&mut self, _text: &str) {}
+
+    fn on_error(&mut self, message: &str) {
+        self.inner.on_error(message);
+    }
+
+    fn on_tool_use_start(&mut self, name: &str, id: &str, input: &serde_json::Value) {
+        self.inner.on_tool_use_start(name, id, input);
+    }
+
+    fn on_tool_use_end(&mut self, name: &str) {
+        self.inner.on_tool_use_end(name);
+    }
+
+    fn on_tool_executing(&mut self, name: &str, input: &serde_json::Value) {
+        self.inner.on_tool_executing(name, input);
+    }
+
+    fn on_tool_result(&mut self, name: &str, output: &str, is_error: bool) {
+        self.inner.on_tool_result(name, output, is_error);
+    }
+
+    fn on_tool_progress(&mut self, name: &str, progress: &ToolProgress) {
+        self.inner.on_tool_progress(name, progress);
+    }
+
+    fn on_task_status(&mut self, id: &str, description: &str, status: &TaskStatus) {
+        self.inner.on_task_status(id, description, status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_definition_lists_agents() {
+        let agent = AgentDef::parse(
+            "---\nname: reviewer\ndescription: Reviews diffs\n---\nReview.",
+            Path::new("reviewer.md"),
+        )
+        .unwrap();
+
+        let def = task_definition(&[agent]);
+
+        assert_eq!(def["name"], "Task");
+        let description = def["description"].as_str().unwrap();
+        assert!(description.contains("- reviewer: Reviews diffs"));
+        assert_eq!(
+            def["input_schema"]["required"],
+            serde_json::json!(["description", "prompt"])
+        );
+    }
+
+    #[derive(Default)]
+    struct Statuses(Vec<(String, TaskStatus)>);
+
+    impl EventHandler for Statuses {
+        fn on_text(&mut self, _text: &str) {}
+        fn on_error(&mut self, _message: &str) {}
+
+        fn on_task_status(&mut self, id: &str, _description: &str, status: &TaskStatus) {
+            self.0.push((id.to_string(), status.clone()));
+        }
+    }
+
+    #[test]
+    fn parallel_task_tool_calls_become_statuses() {
+        let mut statuses = Statuses::default();
+        {
+            let shared: Mutex<&mut dyn EventHandler> = Mutex::new(&mut statuses);
+            let mut events = TaskEvents {
+                handler: &shared,
+                id: "t1",
+                description: "Find callers",
+            };
+
+            let input = serde_json::json!({"pattern": "login"});
+            events.on_tool_use_start("Grep", "g1", &input);
+            events.on_tool_executing("Grep", &input);
+            events.on_tool_result("Grep", "src/a.rs", false);
+            events.on_tool_use_end("Grep");
+        }
+
+        assert_eq!(
+            statuses.0,
+            [
+                (
+                    "t1".to_string(),
+                    TaskStatus::Running(Some("Grep".to_string()))
+                ),
+                ("t1".to_string(), TaskStatus::Running(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_rate_limits_and_overload_are_retried() {
+        let error = |status| {
+            anyhow::Error::new(ApiError::Request {
+                status,
+                message: String::new(),
+            })
+        };
+        assert!(is_rate_limited(&error(Some(429))));
+        assert!(is_rate_limited(&error(Some(529))));
+        assert!(!is_rate_limited(&error(Some(500))));
+        assert!(!is_rate_limited(&error(None)));
+        assert!(!is_rate_limited(&anyhow::Error::new(ApiError::Cancelled)));
+    }
+
+    #[test]
+    fn task_definition_without_agents_is_general_purpose() {
+        let def = task_definition(&[]);
+        assert!(
+            !def["description"]
+                .as_str()
+                .unwrap()
+                .contains("subagent_type")
+        );
+    }
+}
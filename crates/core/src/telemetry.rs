@@ -0,0 +1,375 @@
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use anyhow::{Context as _, Result, bail};
+use opentelemetry::metrics::{Counter, Histogram};
+use opentelemetry::trace::{Status, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue, global};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithHttpConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::metrics::periodic_reader_with_async_runtime::PeriodicReader;
+use opentelemetry_sdk::runtime::Tokio;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::trace::span_processor_with_async_runtime::BatchSpanProcessor;
+
+use crate::api::Usage;
+use crate::event::{EventHandler, TaskStatus};
+use crate::session::{BudgetExceeded, MaxTurnsReached};
+use crate::tls;
+use crate::tools::ToolProgress;
+
+/// Set to `1` to export metrics and traces, as in Claude Code.
+pub const ENABLE_ENV: &str = "CLAUDE_CODE_ENABLE_TELEMETRY";
+
+const NAME: &str = "claude-code-rs";
+
+/// Flushes and stops the exporters when dropped.
+pub struct Telemetry {
+    tracer: Option<SdkTracerProvider>,
+    meter: Option<SdkMeterProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(tracer) = &self.tracer {
+            let _ = tracer.shutdown();
+        }
+        if let Some(meter) = &self.meter {
+            let _ = meter.shutdown();
+        }
+    }
+}
+
+/// Start the OTLP exporters if [`ENABLE_ENV`] is set. The endpoint,
+/// headers, timeouts, intervals and resource come from the standard
+/// `OTEL_*` variables; `OTEL_METRICS_EXPORTER` and `OTEL_TRACES_EXPORTER`
+/// can be `none`. Call after [`tls::configure`], inside the Tokio runtime.
+pub fn init() -> Result<Option<Telemetry>> {
+    if !std::env::var(ENABLE_ENV).is_ok_and(|v| v == "1" || v == "true") {
+        return Ok(None);
+    }
+    if let Ok(protocol) = std::env::var("OTEL_EXPORTER_OTLP_PROTOCOL")
+        && protocol != "http/protobuf"
+    {
+        bail!("OTEL_EXPORTER_OTLP_PROTOCOL={protocol} isn't supported; use http/protobuf");
+    }
+
+    let mut resource = Resource::builder()
+        .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")));
+    if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+        resource = resource.with_service_name(NAME);
+    }
+    let resource = resource.build();
+
+    let tracer = match exporter("OTEL_TRACES_EXPORTER")? {
+        false => None,
+        true => {
+            let exporter = SpanExporter::builder()
+                .with_http()
+                .with_http_client(tls::client())
+                .build()
+                .context("Failed to create the OTLP trace exporter")?;
+            let provider = SdkTracerProvider::builder()
+                .with_span_processor(BatchSpanProcessor::builder(exporter, Tokio).build())
+                .with_resource(resource.clone())
+                .build();
+            global::set_tracer_provider(provider.clone());
+            Some(provider)
+        }
+    };
+
+    let meter = match exporter("OTEL_METRICS_EXPORTER")? {
+        false => None,
+        true => {
+            let exporter = MetricExporter::builder()
+                .with_http()
+                .with_http_client(tls::client())
+                .build()
+                .context("Failed to create the OTLP metric exporter")?;
+            let provider = SdkMeterProvider::builder()
+                .with_reader(PeriodicReader::builder(exporter, Tokio).build())
+                .with_resource(resource)
+                .build();
+            global::set_meter_provider(provider.clone());
+            Some(provider)
+        }
+    };
+
+    Ok(Some(Telemetry { tracer, meter }))
+}
+
+/// Whether a signal's exporter is on: `otlp` (the default) or `none`.
+fn exporter(var: &str) -> Result<bool> {
+    exporter_enabled(var, std::env::var(var).ok().as_deref())
+}
+
+fn exporter_enabled(var: &str, value: Option<&str>) -> Result<bool> {
+    match value {
+        None | Some("otlp") => Ok(true),
+        Some("none") => Ok(false),
+        Some(other) => bail!("{var}={other} isn't supported; use otlp or none"),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Instruments
+// ---------------------------------------------------------------------------
+
+struct Instruments {
+    turn_duration: Histogram<f64>,
+    tokens: Counter<u64>,
+    tool_calls: Counter<u64>,
+    tool_duration: Histogram<f64>,
+}
+
+/// Created on first use, after [`init`] installed the meter provider.
+fn instruments() -> &'static Instruments {
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+    INSTRUMENTS.get_or_init(|| {
+        let meter = global::meter(NAME);
+        Instruments {
+            turn_duration: meter
+                .f64_histogram("claude_code.turn.duration")
+                .with_unit("s")
+                .with_description("Time from a prompt to the final response")
+                .build(),
+            tokens: meter
+                .u64_counter("claude_code.token.usage")
+                .with_unit("tokens")
+                .with_description("Tokens used, by type")
+                .build(),
+            tool_calls: meter
+                .u64_counter("claude_code.tool.calls")
+                .with_description("Tool calls, by tool and outcome")
+                .build(),
+            tool_duration: meter
+                .f64_histogram("claude_code.tool.duration")
+                .with_unit("s")
+                .with_description("Time tools take to run")
+                .build(),
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Turns
+// ---------------------------------------------------------------------------
+
+/// Records one `send_message` call: a span with a child per tool call,
+/// and the turn, token and tool metrics. Passes every event on.
+pub(crate) struct Turn<'a> {
+    inner: &'a mut dyn EventHandler,
+    model: String,
+    started: Instant,
+    cx: Context,
+    /// The running tool's name, start time and span.
+    tool: Option<(String, Instant, Context)>,
+    usage: Usage,
+}
+
+impl<'a> Turn<'a> {
+    pub(crate) fn start(model: &str, inner: &'a mut dyn EventHandler) -> Self {
+        let span = global::tracer(NAME).start("turn");
+        let cx = Context::current_with_span(span);
+        cx.span()
+            .set_attribute(KeyValue::new("model", model.to_string()));
+        Self {
+            inner,
+            model: model.to_string(),
+            started: Instant::now(),
+            cx,
+            tool: None,
+            usage: Usage::default(),
+        }
+    }
+
+    pub(crate) fn finish(mut self, result: &Result<Usage>, cancelled: bool) {
+        self.end_tool(None);
+
+        let outcome = match result {
+            Ok(_) if cancelled => "cancelled",
+            Ok(_) => "success",
+            Err(e) if e.is::<MaxTurnsReached>() => "max_turns",
+            Err(e) if e.is::<BudgetExceeded>() => "budget",
+            Err(_) => "error",
+        };
+        instruments().turn_duration.record(
+            self.started.elapsed().as_secs_f64(),
+            &[
+                KeyValue::new("model", self.model.clone()),
+                KeyValue::new("outcome", outcome),
+            ],
+        );
+
+        let span = self.cx.span();
+        span.set_attribute(KeyValue::new("outcome", outcome));
+        for (kind, tokens) in token_types(&self.usage) {
+            span.set_attribute(KeyValue::new(format!("tokens.{kind}"), tokens as i64));
+        }
+        if let Err(e) = result {
+            span.set_status(Status::error(format!("{e:#}")));
+        }
+        span.end();
+    }
+
+    /// End the running tool's span; `None` if it never reported back.
+    fn end_tool(&mut self, is_error: Option<bool>) {
+        let Some((name, started, cx)) = self.tool.take() else {
+            return;
+        };
+        let outcome = match is_error {
+            Some(false) => "success",
+            Some(true) => "error",
+            None => "interrupted",
+        };
+        let tool = KeyValue::new("tool_name", name);
+        instruments()
+            .tool_calls
+            .add(1, &[tool.clone(), KeyValue::new("outcome", outcome)]);
+        instruments()
+            .tool_duration
+            .record(started.elapsed().as_secs_f64(), &[tool]);
+
+        let span = cx.span();
+        span.set_attribute(KeyValue::new("outcome", outcome));
+        if is_error == Some(true) {
+            span.set_status(Status::error("tool returned an error"));
+        }
+        span.end();
+    }
+}
+
+fn token_types(usage: &Usage) -> [(&'static str, u64); 4] {
+    [
+        ("input", usage.input_tokens),
+        ("output", usage.output_tokens),
+        ("cacheRead", usage.cache_read_input_tokens),
+        ("cacheCreation", usage.cache_creation_input_tokens),
+    ]
+}
+
+impl EventHandler for Turn<'_> {
+    fn on_text(&mut self, text: &str) {
+        self.inner.on_text(text);
+    }
+
+    fn on_error(&mut self, message: &str) {
+        self.cx
+            .span()
+            .add_event("error", vec![KeyValue::new("message", message.to_string())]);
+        self.inner.on_error(message);
+    }
+
+    fn on_thinking(&mut self, text: &str) {
+        self.inner.on_thinking(text);
+    }
+
+    fn on_tool_use_start(&mut self, name: &str, id: &str, input: &serde_json::Value) {
+        self.inner.on_tool_use_start(name, id, input);
+    }
+
+    fn on_tool_use_end(&mut self, name: &str) {
+        self.inner.on_tool_use_end(name);
+    }
+
+    fn on_tool_executing(&mut self, name: &str, input: &serde_json::Value) {
+        self.end_tool(None);
+        let span = global::tracer(NAME).start_with_context(format!("tool {name}"), &self.cx);
+        self.tool = Some((
+            name.to_string(),
+            Instant::now(),
+            Context::current_with_span(span),
+        ));
+        self.inner.on_tool_executing(name, input);
+    }
+
+    fn on_tool_result(&mut self, name: &str, output: &str, is_error: bool) {
+        self.end_tool(Some(is_error));
+        self.inner.on_tool_result(name, output, is_error);
+    }
+
+    fn on_tool_progress(&mut self, name: &str, progress: &ToolProgress) {
+        self.inner.on_tool_progress(name, progress);
+    }
+
+    fn on_task_status(&mut self, id: &str, description: &str, status: &TaskStatus) {
+        self.inner.on_task_status(id, description, status);
+    }
+
+    fn on_usage(&mut self, usage: &Usage) {
+        let model = KeyValue::new("model", self.model.clone());
+        for (kind, tokens) in token_types(usage) {
+            instruments()
+                .tokens
+                .add(tokens, &[model.clone(), KeyValue::new("type", kind)]);
+        }
+        self.usage.input_tokens += usage.input_tokens;
+        self.usage.output_tokens += usage.output_tokens;
+        self.usage.cache_read_input_tokens += usage.cache_read_input_tokens;
+        self.usage.cache_creation_input_tokens += usage.cache_creation_input_tokens;
+        self.inner.on_usage(usage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl EventHandler for Recorder {
+        fn on_text(&mut self, text: &str) {
+            self.0.push(format!("text {text}"));
+        }
+
+        fn on_error(&mut self, message: &str) {
+            self.0.push(format!("error {message}"));
+        }
+
+        fn on_tool_result(&mut self, name: &str, _output: &str, is_error: bool) {
+            self.0.push(format!("result {name} {is_error}"));
+        }
+
+        fn on_usage(&mut self, usage: &Usage) {
+            self.0.push(format!("usage {}", usage.output_tokens));
+        }
+    }
+
+    #[test]
+    fn turns_pass_events_through_and_total_the_usage() {
+        let mut recorder = Recorder::default();
+        let mut turn = Turn::start("claude-sonnet-4-5", &mut recorder);
+
+        turn.on_text("Hi");
+        turn.on_tool_executing("Read", &serde_json::json!({}));
+        assert!(turn.tool.is_some());
+        turn.on_tool_result("Read", "contents", false);
+        assert!(turn.tool.is_none());
+        let usage = Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            ..Usage::default()
+        };
+        turn.on_usage(&usage);
+        turn.on_usage(&usage);
+        assert_eq!(turn.usage.output_tokens, 10);
+        turn.finish(&Ok(usage), false);
+
+        assert_eq!(
+            recorder.0,
+            ["text Hi", "result Read false", "usage 5", "usage 5"]
+        );
+    }
+
+    #[test]
+    fn only_known_exporters_are_accepted() {
+        let var = "OTEL_TRACES_EXPORTER";
+        assert!(exporter_enabled(var, None).unwrap());
+        assert!(exporter_enabled(var, Some("otlp")).unwrap());
+        assert!(!exporter_enabled(var, Some("none")).unwrap());
+        let error = exporter_enabled(var, Some("zipkin")).unwrap_err();
+        assert!(error.to_string().starts_with("OTEL_TRACES_EXPORTER=zipkin"));
+    }
+}
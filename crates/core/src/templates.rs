@@ -0,0 +1,472 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// A reusable prompt in `.claude/templates/<name>.md`, with placeholders
+/// filled in from `/t <name> arg=value`:
+///
+/// ```markdown
+/// ---
+/// description: Write release notes for a version
+/// ---
+/// Write release notes for v{{version}} covering the commits since
+/// {{since=the previous tag}}. {{breaking:bool=false}} means list breaking
+/// changes first.
+/// ```
+///
+/// A placeholder is `{{name}}`, optionally with a type (`string`, `number`,
+/// `bool` or `file`, an existing path) and a default after `=`. The
+/// frontmatter is optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// The file name without `.md`.
+    pub name: String,
+    pub description: Option<String>,
+    pub body: String,
+    pub path: PathBuf,
+}
+
+/// The type a placeholder's value must have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    String,
+    Number,
+    Bool,
+    /// A path to an existing file or directory, relative to the working
+    /// directory.
+    File,
+}
+
+impl ArgKind {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "string" => Self::String,
+            "number" => Self::Number,
+            "bool" => Self::Bool,
+            "file" => Self::File,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::File => "file",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    pub kind: ArgKind,
+    /// Makes the argument optional.
+    pub default: Option<String>,
+}
+
+impl Placeholder {
+    /// `{{…}}` contents: `name`, `name:type`, `name=default` or
+    /// `name:type=default`. `None` if it isn't a placeholder.
+    fn parse(inner: &str) -> Option<Self> {
+        let (spec, default) = match inner.split_once('=') {
+            Some((spec, default)) => (spec.trim(), Some(default.trim().to_string())),
+            None => (inner.trim(), None),
+        };
+        let (name, kind) = match spec.split_once(':') {
+            Some((name, kind)) => (name.trim(), ArgKind::parse(kind.trim())?),
+            None => (spec, ArgKind::String),
+        };
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        valid.then(|| Self {
+            name: name.to_string(),
+            kind,
+            default,
+        })
+    }
+
+    /// `version`, `breaking:bool=false`, as shown in usage lines.
+    pub fn usage(&self) -> String {
+        let mut text = self.name.clone();
+        if self.kind != ArgKind::String {
+            text.push(':');
+            text.push_str(self.kind.name());
+        }
+        if let Some(default) = &self.default {
+            text.push('=');
+            text.push_str(default);
+        }
+        text
+    }
+
+    /// Check `value` against the type; booleans are normalized.
+    fn check(&self, value: &str, cwd: &Path) -> Result<String> {
+        let name = &self.name;
+        match self.kind {
+            ArgKind::String => Ok(value.to_string()),
+            ArgKind::Number => match value.parse::<f64>() {
+                Ok(_) => Ok(value.to_string()),
+                Err(_) => bail!("`{name}` must be a number, not {value:?}"),
+            },
+            ArgKind::Bool => match value.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok("true".to_string()),
+                "false" | "no" | "off" | "0" => Ok("false".to_string()),
+                _ => bail!("`{name}` must be true or false, not {value:?}"),
+            },
+            ArgKind::File if cwd.join(value).exists() => Ok(value.to_string()),
+            ArgKind::File => bail!("`{name}`: {value} does not exist"),
+        }
+    }
+}
+
+/// One piece of a template body.
+enum Piece<'a> {
+    Text(&'a str),
+    Placeholder(Placeholder),
+}
+
+/// Split `body` into text and placeholders. Braces that don't hold a
+/// valid placeholder stay text.
+fn pieces(body: &str) -> Vec<Piece<'_>> {
+    let mut out = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        match Placeholder::parse(&rest[start + 2..end - 2]) {
+            Some(placeholder) => {
+                out.push(Piece::Text(&rest[..start]));
+                out.push(Piece::Placeholder(placeholder));
+            }
+            None => out.push(Piece::Text(&rest[..end])),
+        }
+        rest = &rest[end..];
+    }
+    out.push(Piece::Text(rest));
+    out
+}
+
+impl Template {
+    pub fn parse(text: &str, path: &Path) -> Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .context("Template file has no name")?;
+
+        let Some(rest) = text.strip_prefix("---") else {
+            return Ok(Self {
+                name,
+                description: None,
+                body: text.trim().to_string(),
+                path: path.to_path_buf(),
+            });
+        };
+        let (frontmatter, body) = rest
+            .split_once("\n---")
+            .context("Unterminated frontmatter (expected a closing `---` line)")?;
+
+        let mut description = None;
+        for line in frontmatter.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .with_context(|| format!("Invalid frontmatter line: {line}"))?;
+            if key.trim() == "description" {
+                description = Some(value.trim().to_string());
+            }
+        }
+
+        // Drop the rest of the closing `---` line
+        let body = body.split_once('\n').map_or("", |(_, b)| b).trim();
+
+        Ok(Self {
+            name,
+            description,
+            body: body.to_string(),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// The placeholders in order of first use, each once.
+    pub fn placeholders(&self) -> Vec<Placeholder> {
+        let mut out: Vec<Placeholder> = Vec::new();
+        for piece in pieces(&self.body) {
+            if let Piece::Placeholder(placeholder) = piece
+                && !out.iter().any(|p| p.name == placeholder.name)
+            {
+                out.push(placeholder);
+            }
+        }
+        out
+    }
+
+    /// `/t release-notes version=<string> [since=...]`
+    pub fn usage(&self) -> String {
+        let mut text = format!("/t {}", self.name);
+        for placeholder in self.placeholders() {
+            match &placeholder.default {
+                Some(_) => text.push_str(&format!(" [{}]", placeholder.usage())),
+                None => text.push_str(&format!(
+                    " {}=<{}>",
+                    placeholder.name,
+                    placeholder.kind.name()
+                )),
+            }
+        }
+        text
+    }
+
+    /// The prompt with every placeholder filled in. Fails on unknown,
+    /// missing or mistyped arguments, naming them all.
+    pub fn render(&self, args: &BTreeMap<String, String>, cwd: &Path) -> Result<String> {
+        let placeholders = self.placeholders();
+        let mut problems = Vec::new();
+
+        for name in args.keys() {
+            if !placeholders.iter().any(|p| &p.name == name) {
+                problems.push(format!("unknown argument `{name}`"));
+            }
+        }
+
+        let mut values = BTreeMap::new();
+        for placeholder in &placeholders {
+            let value = match (args.get(&placeholder.name), &placeholder.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => default,
+                (None, None) => {
+                    problems.push(format!(
+                        "missing `{}` ({})",
+                        placeholder.name,
+                        placeholder.kind.name()
+                    ));
+                    continue;
+                }
+            };
+            match placeholder.check(value, cwd) {
+                Ok(value) => {
+                    values.insert(placeholder.name.as_str(), value);
+                }
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+
+        if !problems.is_empty() {
+            bail!("{}. Usage: {}", problems.join("; "), self.usage());
+        }
+
+        Ok(pieces(&self.body)
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text,
+                Piece::Placeholder(placeholder) => values[placeholder.name.as_str()].as_str(),
+            })
+            .collect())
+    }
+}
+
+/// `arg=value` pairs, with single or double quotes around values that
+/// contain spaces: `version=2.1 title="Spring release"`.
+pub fn parse_args(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut args = BTreeMap::new();
+    let mut chars = text.trim().chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(args);
+        }
+
+        let name: String =
+            std::iter::from_fn(|| chars.next_if(|&c| c != '=' && !c.is_whitespace())).collect();
+        if chars.next() != Some('=') {
+            bail!("Expected arg=value, got `{name}`");
+        }
+
+        let value = match chars.peek() {
+            Some(&quote @ ('"' | '\'')) => {
+                chars.next();
+                let value: String = std::iter::from_fn(|| chars.next_if(|&c| c != quote)).collect();
+                if chars.next() != Some(quote) {
+                    bail!("Unterminated quote in `{name}`");
+                }
+                value
+            }
+            _ => std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect(),
+        };
+        args.insert(name, value);
+    }
+}
+
+/// `.claude/templates` in the project.
+pub fn project_templates_dir(project_dir: &Path) -> PathBuf {
+    project_dir.join(".claude").join("templates")
+}
+
+/// Template directories in load order: user, then project.
+pub fn template_dirs(project_dir: &Path) -> Vec<PathBuf> {
+    let user = dirs::home_dir().map(|h| h.join(".claude").join("templates"));
+    user.into_iter()
+        .chain([project_templates_dir(project_dir)])
+        .collect()
+}
+
+/// Load templates from the user and project directories, sorted by name.
+/// Project templates replace user templates of the same name; unparsable
+/// files are returned as errors alongside the templates that did load.
+pub fn load(project_dir: &Path) -> (Vec<Template>, Vec<(PathBuf, anyhow::Error)>) {
+    load_from_dirs(&template_dirs(project_dir))
+}
+
+fn load_from_dirs(dirs: &[PathBuf]) -> (Vec<Template>, Vec<(PathBuf, anyhow::Error)>) {
+    let mut templates: Vec<Template> = Vec::new();
+    let mut errors = Vec::new();
+
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+
+        for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+            if path.extension().is_none_or(|ext| ext != "md") {
+                continue;
+            }
+            let parsed = fs::read_to_string(&path)
+                .context("Failed to read template file")
+                .and_then(|text| Template::parse(&text, &path));
+
+            match parsed {
+                Ok(template) => {
+                    templates.retain(|t| t.name != template.name);
+                    templates.push(template);
+                }
+                Err(e) => errors.push((path, e)),
+            }
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    (templates, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASE_NOTES: &str = "---\n\
+        description: Release notes\n\
+        ---\n\
+        Notes for v{{version}} since {{since=the last tag}}.\n\
+        Breaking first: {{breaking:bool=no}}. Again: v{{ version }}. Literal {{not a placeholder}}.";
+
+    fn release_notes() -> Template {
+        Template::parse(RELEASE_NOTES, Path::new("/t/release-notes.md")).unwrap()
+    }
+
+    fn args(text: &str) -> BTreeMap<String, String> {
+        parse_args(text).unwrap()
+    }
+
+    #[test]
+    fn placeholders_are_listed_once_with_types() {
+        let template = release_notes();
+        assert_eq!(template.name, "release-notes");
+        assert_eq!(template.description.as_deref(), Some("Release notes"));
+
+        let names: Vec<String> = template.placeholders().iter().map(|p| p.usage()).collect();
+        assert_eq!(names, ["version", "since=the last tag", "breaking:bool=no"]);
+        assert_eq!(
+            template.usage(),
+            "/t release-notes version=<string> [since=the last tag] [breaking:bool=no]"
+        );
+    }
+
+    #[test]
+    fn render_fills_values_and_defaults() {
+        let tmp = tempfile::tempdir().unwrap();
+        let text = release_notes()
+            .render(&args("version=2.1 breaking=yes"), tmp.path())
+            .unwrap();
+        assert_eq!(
+            text,
+            "Notes for v2.1 since the last tag.\n\
+             Breaking first: true. Again: v2.1. Literal {{not a placeholder}}."
+        );
+    }
+
+    #[test]
+    fn render_reports_every_problem() {
+        let tmp = tempfile::tempdir().unwrap();
+        let template = Template::parse(
+            "Review {{path:file}} at {{depth:number}}",
+            Path::new("review.md"),
+        )
+        .unwrap();
+        assert!(template.description.is_none());
+
+        let error = template
+            .render(&args("path=missing.rs depth=deep extra=1"), tmp.path())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown argument `extra`"), "{error}");
+        assert!(error.contains("missing.rs does not exist"), "{error}");
+        assert!(error.contains("`depth` must be a number"), "{error}");
+
+        fs::write(tmp.path().join("lib.rs"), "").unwrap();
+        let error = template
+            .render(&args("path=lib.rs"), tmp.path())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("missing `depth` (number)"), "{error}");
+        assert!(
+            template
+                .render(&args("path=lib.rs depth=3"), tmp.path())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn args_take_quoted_values() {
+        assert_eq!(
+            args(r#"version=2.1 title="Spring release" note='say "hi"' empty="""#),
+            BTreeMap::from([
+                ("version".to_string(), "2.1".to_string()),
+                ("title".to_string(), "Spring release".to_string()),
+                ("note".to_string(), "say \"hi\"".to_string()),
+                ("empty".to_string(), String::new()),
+            ])
+        );
+        assert!(parse_args("version").is_err());
+        assert!(parse_args("title=\"open").is_err());
+        assert!(args("  ").is_empty());
+    }
+
+    #[test]
+    fn project_templates_override_user_templates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let user = tmp.path().join("user");
+        let project = tmp.path().join("project");
+        fs::create_dir_all(&user).unwrap();
+        fs::create_dir_all(&project).unwrap();
+
+        fs::write(user.join("release-notes.md"), RELEASE_NOTES).unwrap();
+        fs::write(user.join("triage.md"), "Triage {{issue:number}}").unwrap();
+        fs::write(project.join("release-notes.md"), "Project notes").unwrap();
+        fs::write(project.join("broken.md"), "---\nunterminated").unwrap();
+        fs::write(project.join("notes.txt"), "ignored").unwrap();
+
+        let (templates, errors) = load_from_dirs(&[user, project]);
+
+        let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["release-notes", "triage"]);
+        assert_eq!(templates[0].body, "Project notes");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.ends_with("broken.md"));
+    }
+}
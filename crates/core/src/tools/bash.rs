@@ -3,9 +3,17 @@ use std::time::Duration;
 
 use tokio::process::Command;
 
-use super::{ToolDef, ToolOutput};
+use super::{ToolDef, ToolEnv, ToolOutput};
 
-pub struct BashTool;
+pub struct BashTool {
+    env: ToolEnv,
+}
+
+impl BashTool {
+    pub fn new(env: ToolEnv) -> Self {
+        Self { env }
+    }
+}
 
 impl ToolDef for BashTool {
     fn name(&self) -> &'static str {
@@ -51,15 +59,11 @@ impl ToolDef for BashTool {
             .unwrap_or(120_000)
             .min(600_000);
 
-        let result = tokio::time::timeout(
-            Duration::from_millis(timeout_ms),
-            Command::new("bash")
-                .arg("-c")
-                .arg(command)
-                .current_dir(cwd)
-                .output(),
-        )
-        .await;
+        let mut child = Command::new("bash");
+        child.arg("-c").arg(command).current_dir(cwd);
+        self.env.apply(&mut child);
+
+        let result = tokio::time::timeout(Duration::from_millis(timeout_ms), child.output()).await;
 
         match result {
             Ok(Ok(output)) => {
@@ -85,6 +89,7 @@ impl ToolDef for BashTool {
                     content.push_str("(no output)");
                 }
 
+                let content = self.env.redact(&content);
                 if output.status.success() {
                     ToolOutput::success(content)
                 } else {
@@ -97,3 +102,23 @@ impl ToolDef for BashTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn commands_see_the_env_setting_with_secrets_masked() {
+        let vars = [("GREETING", "hello"), ("API_TOKEN", "s3cr3t-value")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let bash = BashTool::new(ToolEnv::new(vars, &["API_TOKEN".to_string()]));
+
+        let input = serde_json::json!({"command": "echo \"$GREETING $API_TOKEN\""});
+        let output = bash.execute(&input, Path::new(".")).await;
+
+        assert!(!output.is_error);
+        assert_eq!(output.content.trim(), "hello [$API_TOKEN]");
+    }
+}
@@ -4,6 +4,7 @@ use std::time::Duration;
 use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, USER_AGENT};
 
 use super::{ToolDef, ToolOutput};
+use crate::tls;
 
 pub struct FetchTool {
     client: reqwest::Client,
@@ -17,7 +18,7 @@ impl Default for FetchTool {
 
 impl FetchTool {
     pub fn new() -> Self {
-        let client = reqwest::Client::builder()
+        let client = tls::client_builder()
             .timeout(Duration::from_secs(30))
             .redirect(reqwest::redirect::Policy::limited(10))
             .build()
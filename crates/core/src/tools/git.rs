@@ -65,7 +65,7 @@ impl ToolDef for GitTool {
                 },
                 "message": {
                     "type": "string",
-                    "description": "Commit message"
+                    "description": "Commit message; omit it to have one drafted from the staged changes"
                 },
                 "remote": {
                     "type": "string",
@@ -265,6 +265,13 @@ fn exec_diff_staged(cwd: &Path) -> ToolOutput {
     }
 }
 
+/// The staged changes as `diff_staged` shows them; `None` if nothing is
+/// staged or the diff fails.
+pub(crate) fn staged_diff(cwd: &Path) -> Option<String> {
+    let (entries, stat) = ccrs_git::diff_staged(cwd).ok()?;
+    (!entries.is_empty()).then(|| format_diff(entries, stat).content)
+}
+
 fn exec_diff_unstaged(cwd: &Path) -> ToolOutput {
     match ccrs_git::diff_unstaged(cwd) {
         Ok((entries, stat)) => format_diff(entries, stat),
@@ -427,7 +434,11 @@ fn exec_unstage(cwd: &Path, pathspec: &[&str]) -> ToolOutput {
 
 fn exec_commit(cwd: &Path, message: &str) -> ToolOutput {
     match ccrs_git::commit(cwd, message) {
-        Ok(oid) => ToolOutput::success(format!("Created commit {}", &oid[..8])),
+        Ok(oid) => ToolOutput::success(format!(
+            "Created commit {}: {}",
+            &oid[..8],
+            message.lines().next().unwrap_or("")
+        )),
         Err(e) => ToolOutput::error(format!("git commit failed: {e}")),
     }
 }
@@ -51,24 +51,7 @@ impl ToolDef for GlobTool {
 
         let mut files: Vec<(std::path::PathBuf, std::time::SystemTime)> = Vec::new();
 
-        // Use ignore::WalkBuilder with the same filters as search
-        let walker = ignore::WalkBuilder::new(&base_dir)
-            .hidden(false)
-            .git_ignore(true)
-            .git_global(false)
-            .git_exclude(false)
-            .add_custom_ignore_filename(".claudeignore")
-            .filter_entry(|entry| {
-                let name = entry
-                    .path()
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("");
-                !ccrs_utils::is_ignored_dir(name)
-            })
-            .build();
-
-        for result in walker {
+        for result in project_walker(&base_dir) {
             let entry = match result {
                 Ok(e) => e,
                 Err(_) => continue,
@@ -98,7 +81,7 @@ impl ToolDef for GlobTool {
         }
 
         // Sort by modification time, most recent first
-        files.sort_by(|a, b| b.1.cmp(&a.1));
+        files.sort_by_key(|f| std::cmp::Reverse(f.1));
 
         if files.is_empty() {
             return ToolOutput::success("No files matched the pattern.");
@@ -108,3 +91,23 @@ impl ToolDef for GlobTool {
         ToolOutput::success(result.join("\n"))
     }
 }
+
+/// Walk `base_dir` with the same filters as search: `.gitignore`,
+/// `.claudeignore` and the shared ignored directories.
+pub fn project_walker(base_dir: &Path) -> ignore::Walk {
+    ignore::WalkBuilder::new(base_dir)
+        .hidden(false)
+        .git_ignore(true)
+        .git_global(false)
+        .git_exclude(false)
+        .add_custom_ignore_filename(".claudeignore")
+        .filter_entry(|entry| {
+            let name = entry
+                .path()
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("");
+            !ccrs_utils::is_ignored_dir(name)
+        })
+        .build()
+}
@@ -7,14 +7,23 @@ pub mod glob;
 pub mod grep;
 pub mod list;
 pub mod read;
+#[cfg(feature = "git")]
+pub mod repo;
 #[cfg(feature = "search")]
 pub mod search;
+pub mod test;
+pub mod todo;
 pub mod write;
 
+use std::collections::BTreeMap;
 use std::future::Future;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::mpsc;
 
+use crate::config::RepoSettings;
 use crate::permission;
 
 // ---------------------------------------------------------------------------
@@ -42,6 +51,39 @@ impl ToolOutput {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Tool progress
+// ---------------------------------------------------------------------------
+
+/// How far a slow step of a tool call has got, e.g. a model download.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolProgress {
+    /// What is being done, e.g. "Embedding files".
+    pub label: String,
+    pub done: u64,
+    /// 0 when unknown.
+    pub total: u64,
+    /// Counts bytes rather than items.
+    pub bytes: bool,
+}
+
+/// Where a running tool reports progress. Cheap to clone and usable from
+/// blocking threads.
+#[derive(Clone, Default)]
+pub struct ProgressSink(Option<mpsc::UnboundedSender<ToolProgress>>);
+
+impl ProgressSink {
+    pub fn new(tx: mpsc::UnboundedSender<ToolProgress>) -> Self {
+        Self(Some(tx))
+    }
+
+    pub fn report(&self, progress: ToolProgress) {
+        if let Some(tx) = &self.0 {
+            let _ = tx.send(progress);
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // ToolDef — the user-facing trait (uses async fn directly)
 // ---------------------------------------------------------------------------
@@ -55,6 +97,16 @@ pub trait ToolDef: Send + Sync {
         input: &serde_json::Value,
         cwd: &Path,
     ) -> impl Future<Output = ToolOutput> + Send;
+
+    /// `execute` for tools with slow steps worth showing.
+    fn execute_with_progress(
+        &self,
+        input: &serde_json::Value,
+        cwd: &Path,
+        _progress: ProgressSink,
+    ) -> impl Future<Output = ToolOutput> + Send {
+        self.execute(input, cwd)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -69,6 +121,7 @@ pub trait ToolDefDyn: Send + Sync {
         &'a self,
         input: &'a serde_json::Value,
         cwd: &'a Path,
+        progress: ProgressSink,
     ) -> Pin<Box<dyn Future<Output = ToolOutput> + Send + 'a>>;
 }
 
@@ -89,8 +142,84 @@ impl<T: ToolDef> ToolDefDyn for T {
         &'a self,
         input: &'a serde_json::Value,
         cwd: &'a Path,
+        progress: ProgressSink,
     ) -> Pin<Box<dyn Future<Output = ToolOutput> + Send + 'a>> {
-        Box::pin(ToolDef::execute(self, input, cwd))
+        Box::pin(ToolDef::execute_with_progress(self, input, cwd, progress))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Additional directories
+// ---------------------------------------------------------------------------
+
+/// Directories outside the working directory that the session may use,
+/// shared between the session and the tools that index them. Cloning
+/// shares the list.
+#[derive(Clone, Default)]
+pub struct AdditionalDirs(Arc<RwLock<Vec<PathBuf>>>);
+
+impl AdditionalDirs {
+    pub fn get(&self) -> Vec<PathBuf> {
+        self.0.read().map(|dirs| dirs.clone()).unwrap_or_default()
+    }
+
+    /// Add a directory unless it is already listed. Returns `false` if it
+    /// was.
+    pub fn add(&self, dir: PathBuf) -> bool {
+        let Ok(mut dirs) = self.0.write() else {
+            return false;
+        };
+        if dirs.contains(&dir) {
+            return false;
+        }
+        dirs.push(dir);
+        true
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Command environment
+// ---------------------------------------------------------------------------
+
+/// Shortest value that gets redacted; shorter ones would garble output.
+const MIN_REDACTED_LEN: usize = 4;
+
+/// Variables from the `env` setting, set for every command a tool runs.
+/// Values of the variables named in `redactEnv` are masked in the output
+/// the tools return.
+#[derive(Clone, Default)]
+pub struct ToolEnv {
+    vars: BTreeMap<String, String>,
+    /// `(name, value)`, longest value first so overlapping values mask fully.
+    secrets: Vec<(String, String)>,
+}
+
+impl ToolEnv {
+    pub fn new(vars: BTreeMap<String, String>, redact: &[String]) -> Self {
+        let mut secrets: Vec<_> = redact
+            .iter()
+            .filter_map(|name| {
+                let value = vars.get(name)?;
+                (value.len() >= MIN_REDACTED_LEN).then(|| (name.clone(), value.clone()))
+            })
+            .collect();
+        secrets.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+        Self { vars, secrets }
+    }
+
+    pub fn apply(&self, command: &mut tokio::process::Command) {
+        command.envs(&self.vars);
+    }
+
+    /// `text` with each redacted value replaced by `[$NAME]`.
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (name, value) in &self.secrets {
+            if text.contains(value.as_str()) {
+                text = text.replace(value.as_str(), &format!("[${name}]"));
+            }
+        }
+        text
     }
 }
 
@@ -140,9 +269,17 @@ impl ToolRegistry {
 }
 
 /// Create a registry with the default set of tools.
-pub fn default_registry() -> ToolRegistry {
+/// Every built-in tool. `dirs` lets Search index the additional directories
+/// alongside the working directory; `env` is passed to the commands Bash
+/// and Test run, and `repo` holds the Repo tool's tokens.
+#[cfg_attr(
+    any(not(feature = "search"), not(feature = "git")),
+    allow(unused_variables)
+)]
+pub fn default_registry(dirs: &AdditionalDirs, env: ToolEnv, repo: RepoSettings) -> ToolRegistry {
     let mut r = ToolRegistry::new();
-    r.register(bash::BashTool);
+    r.register(bash::BashTool::new(env.clone()));
+    r.register(test::TestTool::new(env));
     r.register(read::ReadTool);
     r.register(write::WriteTool);
     r.register(edit::EditTool);
@@ -150,12 +287,16 @@ pub fn default_registry() -> ToolRegistry {
     r.register(grep::GrepTool);
     r.register(list::ListTool);
     r.register(fetch::FetchTool::new());
+    r.register(todo::TodoWriteTool);
 
     #[cfg(feature = "git")]
-    r.register(git::GitTool);
+    {
+        r.register(git::GitTool);
+        r.register(repo::RepoTool::new(repo));
+    }
 
     #[cfg(feature = "search")]
-    r.register(search::SearchTool::new());
+    r.register(search::SearchTool::new(dirs.clone()));
 
     r
 }
@@ -222,7 +363,13 @@ pub fn to_permission_tool<'a>(
                 .unwrap_or("");
             Some(permission::Tool::Git { subcommand })
         }
+        "Repo" => {
+            let action = input.get("action").and_then(|a| a.as_str()).unwrap_or("");
+            Some(permission::Tool::Repo { action })
+        }
         "Search" => Some(permission::Tool::Search),
+        "TodoWrite" => Some(permission::Tool::TodoWrite),
+        "Test" => Some(permission::Tool::Test),
         _ => None,
     }
 }
@@ -0,0 +1,724 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::{RequestBuilder, Url};
+use serde_json::{Value, json};
+
+use super::{ToolDef, ToolOutput};
+use crate::config::RepoSettings;
+use crate::tls;
+
+/// Most items a listing returns.
+const MAX_LIMIT: u64 = 100;
+
+pub struct RepoTool {
+    client: reqwest::Client,
+    settings: RepoSettings,
+}
+
+impl RepoTool {
+    pub fn new(settings: RepoSettings) -> Self {
+        let client = tls::client_builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("failed to build HTTP client");
+        Self { client, settings }
+    }
+}
+
+impl ToolDef for RepoTool {
+    fn name(&self) -> &'static str {
+        "Repo"
+    }
+
+    fn description(&self) -> &'static str {
+        "GitHub or GitLab requests for the repository's `origin` remote: list, view and comment on \
+         issues, open a pull request (merge request on GitLab) and read CI status. \
+         Push the branch with the Git tool before opening a pull request. \
+         Use this instead of the gh or glab CLIs."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list_issues", "view_issue", "comment_issue", "create_pr", "ci_status"],
+                    "description": "The request to make"
+                },
+                "number": {
+                    "type": "integer",
+                    "description": "Issue number for view_issue and comment_issue; on GitHub a pull request number works too"
+                },
+                "state": {
+                    "type": "string",
+                    "enum": ["open", "closed", "all"],
+                    "description": "Which issues list_issues returns (default: open)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Max issues for list_issues (default: 20, at most 100)"
+                },
+                "title": {
+                    "type": "string",
+                    "description": "Pull request title for create_pr"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Pull request description for create_pr, or the comment for comment_issue (Markdown)"
+                },
+                "head": {
+                    "type": "string",
+                    "description": "Branch with the changes for create_pr (default: the current branch)"
+                },
+                "base": {
+                    "type": "string",
+                    "description": "Branch to merge into for create_pr (default: the repository's default branch)"
+                },
+                "draft": {
+                    "type": "boolean",
+                    "description": "Open the pull request as a draft (default: false)"
+                },
+                "ref": {
+                    "type": "string",
+                    "description": "Branch or commit for ci_status (default: the current branch)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, input: &serde_json::Value, cwd: &Path) -> ToolOutput {
+        let action = match input.get("action").and_then(|v| v.as_str()) {
+            Some(a) => a,
+            None => return ToolOutput::error("Missing required parameter: action"),
+        };
+
+        let api = match Api::for_repo(&self.client, &self.settings, cwd) {
+            Ok(api) => api,
+            Err(e) => return ToolOutput::error(e),
+        };
+
+        let result = match action {
+            "list_issues" => {
+                let state = input
+                    .get("state")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("open");
+                let limit = input
+                    .get("limit")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(20)
+                    .clamp(1, MAX_LIMIT);
+                api.list_issues(state, limit).await
+            }
+            "view_issue" => match input.get("number").and_then(|v| v.as_u64()) {
+                Some(number) => api.view_issue(number).await,
+                None => return ToolOutput::error("view_issue requires 'number' parameter"),
+            },
+            "comment_issue" => {
+                let Some(number) = input.get("number").and_then(|v| v.as_u64()) else {
+                    return ToolOutput::error("comment_issue requires 'number' parameter");
+                };
+                let Some(body) = input.get("body").and_then(|v| v.as_str()) else {
+                    return ToolOutput::error("comment_issue requires 'body' parameter");
+                };
+                api.comment_issue(number, body).await
+            }
+            "create_pr" => {
+                let Some(title) = input.get("title").and_then(|v| v.as_str()) else {
+                    return ToolOutput::error("create_pr requires 'title' parameter");
+                };
+                let head = match input.get("head").and_then(|v| v.as_str()) {
+                    Some(head) => head.to_string(),
+                    None => match current_branch(cwd) {
+                        Ok(branch) => branch,
+                        Err(e) => return ToolOutput::error(e),
+                    },
+                };
+                let pr = NewPr {
+                    title,
+                    body: input.get("body").and_then(|v| v.as_str()).unwrap_or(""),
+                    head: &head,
+                    base: input.get("base").and_then(|v| v.as_str()),
+                    draft: input
+                        .get("draft")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                };
+                api.create_pr(&pr).await
+            }
+            "ci_status" => {
+                let reference = match input.get("ref").and_then(|v| v.as_str()) {
+                    Some(r) => r.to_string(),
+                    None => match current_branch(cwd) {
+                        Ok(branch) => branch,
+                        Err(e) => return ToolOutput::error(e),
+                    },
+                };
+                api.ci_status(&reference).await
+            }
+            other => return ToolOutput::error(format!("Unknown action: {other}")),
+        };
+
+        match result {
+            Ok(text) => ToolOutput::success(text),
+            Err(e) => ToolOutput::error(e),
+        }
+    }
+}
+
+fn current_branch(cwd: &Path) -> Result<String, String> {
+    match ccrs_git::current_branch(cwd) {
+        Ok(Some(branch)) if branch != "HEAD" => Ok(branch),
+        Ok(_) => Err("HEAD is detached; name the branch explicitly".to_string()),
+        Err(e) => Err(format!("Failed to read the current branch: {e}")),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Finding the forge
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Forge {
+    GitHub,
+    GitLab,
+}
+
+impl Forge {
+    fn name(self) -> &'static str {
+        match self {
+            Self::GitHub => "GitHub",
+            Self::GitLab => "GitLab",
+        }
+    }
+}
+
+/// Host and project path (`owner/repo`, or `group/subgroup/repo` on
+/// GitLab) of a remote URL, in the https, ssh or `git@host:path` forms.
+fn parse_remote(url: &str) -> Option<(String, String)> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let (authority, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        None => url.split_once(':')?,
+    };
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.split(':').next()?;
+    let path = path.trim_start_matches('/');
+
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+    Some((host.to_ascii_lowercase(), path.to_string()))
+}
+
+/// The forge behind `remote` and its API URL for the project.
+fn locate(remote: &str, settings: &RepoSettings) -> Result<(Forge, Url), String> {
+    let (host, path) =
+        parse_remote(remote).ok_or_else(|| format!("Can't read the remote URL {remote}"))?;
+
+    let self_hosted = settings
+        .gitlab_url
+        .as_deref()
+        .and_then(|url| Url::parse(url).ok())
+        .filter(|url| url.host_str() == Some(host.as_str()));
+
+    let (forge, mut url) = if host == "github.com" {
+        (
+            Forge::GitHub,
+            Url::parse("https://api.github.com/repos").unwrap(),
+        )
+    } else if let Some(base) = self_hosted {
+        (Forge::GitLab, base)
+    } else if host == "gitlab.com" {
+        (Forge::GitLab, Url::parse("https://gitlab.com").unwrap())
+    } else {
+        return Err(format!(
+            "{host} is neither GitHub nor GitLab; for a self-hosted GitLab, set \"repo\": {{\"gitlabUrl\": ...}} in user or local settings"
+        ));
+    };
+
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|()| format!("Invalid API URL for {host}"))?;
+        segments.pop_if_empty();
+        match forge {
+            Forge::GitHub => {
+                let Some((owner, repo)) = path.split_once('/') else {
+                    unreachable!("checked by parse_remote")
+                };
+                segments.extend([owner, repo]);
+            }
+            // The whole path is the ID, slashes encoded
+            Forge::GitLab => {
+                segments.extend(["api", "v4", "projects", path.as_str()]);
+            }
+        }
+    }
+    Ok((forge, url))
+}
+
+// ---------------------------------------------------------------------------
+// API requests
+// ---------------------------------------------------------------------------
+
+struct Api<'a> {
+    client: &'a reqwest::Client,
+    forge: Forge,
+    /// `https://api.github.com/repos/{owner}/{repo}` or
+    /// `{gitlab}/api/v4/projects/{path}`.
+    project: Url,
+    token: String,
+}
+
+struct NewPr<'a> {
+    title: &'a str,
+    body: &'a str,
+    head: &'a str,
+    base: Option<&'a str>,
+    draft: bool,
+}
+
+impl<'a> Api<'a> {
+    fn for_repo(
+        client: &'a reqwest::Client,
+        settings: &RepoSettings,
+        cwd: &Path,
+    ) -> Result<Self, String> {
+        let remote = match ccrs_git::remote_url(cwd, "origin") {
+            Ok(Some(url)) => url,
+            Ok(None) => return Err("The repository has no `origin` remote".to_string()),
+            Err(e) => return Err(format!("Failed to open the repository: {e}")),
+        };
+        let (forge, project) = locate(&remote, settings)?;
+
+        let token = match forge {
+            Forge::GitHub => settings.github_token().ok_or(
+                "No GitHub token: set GITHUB_TOKEN or \"repo\": {\"githubToken\": ...} in settings",
+            ),
+            Forge::GitLab => settings.gitlab_token().ok_or(
+                "No GitLab token: set GITLAB_TOKEN or \"repo\": {\"gitlabToken\": ...} in settings",
+            ),
+        }?;
+
+        Ok(Self {
+            client,
+            forge,
+            project,
+            token,
+        })
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.project.clone();
+        url.path_segments_mut()
+            .expect("API URLs have paths")
+            .extend(segments);
+        url
+    }
+
+    async fn get(&self, segments: &[&str], query: &[(&str, String)]) -> Result<Value, String> {
+        self.send(self.client.get(self.url(segments)).query(query))
+            .await
+    }
+
+    async fn post(&self, segments: &[&str], body: Value) -> Result<Value, String> {
+        self.send(self.client.post(self.url(segments)).json(&body))
+            .await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Value, String> {
+        let request = match self.forge {
+            Forge::GitHub => request
+                .header(AUTHORIZATION, format!("Bearer {}", self.token))
+                .header(ACCEPT, "application/vnd.github+json")
+                .header("X-GitHub-Api-Version", "2022-11-28"),
+            Forge::GitLab => request.header("PRIVATE-TOKEN", &self.token),
+        };
+        let response = request
+            .header(USER_AGENT, "ccrs")
+            .send()
+            .await
+            .map_err(|e| format!("{} request failed: {e}", self.forge.name()))?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read the {} response: {e}", self.forge.name()))?;
+        let body: Value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+
+        if status.is_success() {
+            return Ok(body);
+        }
+        let message = api_message(&body);
+        let hint = match status.as_u16() {
+            401 => " (check the token)",
+            403 | 404 => " (the token may lack access to this repository)",
+            _ => "",
+        };
+        Err(format!(
+            "{} returned {status}: {message}{hint}",
+            self.forge.name()
+        ))
+    }
+
+    async fn list_issues(&self, state: &str, limit: u64) -> Result<String, String> {
+        let limit = limit.to_string();
+        let issues = match self.forge {
+            Forge::GitHub => {
+                let query = [("state", state.to_string()), ("per_page", limit)];
+                let issues = self.get(&["issues"], &query).await?;
+                // The issues API lists pull requests too
+                array(&issues)
+                    .iter()
+                    .filter(|issue| issue.get("pull_request").is_none())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            }
+            Forge::GitLab => {
+                let state = if state == "open" { "opened" } else { state };
+                let query = [("state", state.to_string()), ("per_page", limit)];
+                array(&self.get(&["issues"], &query).await?).to_vec()
+            }
+        };
+
+        if issues.is_empty() {
+            return Ok(format!("No {state} issues."));
+        }
+        let mut out = String::new();
+        for issue in &issues {
+            let _ = writeln!(out, "{}", self.issue_line(issue));
+        }
+        Ok(out)
+    }
+
+    async fn view_issue(&self, number: u64) -> Result<String, String> {
+        let number = number.to_string();
+        let (issue, comments) = match self.forge {
+            Forge::GitHub => (
+                self.get(&["issues", &number], &[]).await?,
+                self.get(&["issues", &number, "comments"], &[]).await?,
+            ),
+            Forge::GitLab => (
+                self.get(&["issues", &number], &[]).await?,
+                self.get(
+                    &["issues", &number, "notes"],
+                    &[("sort", "asc".to_string())],
+                )
+                .await?,
+            ),
+        };
+
+        let mut out = self.issue_line(&issue);
+        if let Some(url) = web_url(&issue) {
+            let _ = write!(out, "\n{url}");
+        }
+        let body = match self.forge {
+            Forge::GitHub => str_field(&issue, "body"),
+            Forge::GitLab => str_field(&issue, "description"),
+        };
+        if !body.is_empty() {
+            let _ = write!(out, "\n\n{body}");
+        }
+
+        for comment in array(&comments) {
+            // GitLab notes include events such as label changes
+            if comment.get("system").and_then(Value::as_bool) == Some(true) {
+                continue;
+            }
+            let _ = write!(
+                out,
+                "\n\n--- {} ({}):\n{}",
+                author(comment),
+                str_field(comment, "created_at"),
+                str_field(comment, "body")
+            );
+        }
+        Ok(out)
+    }
+
+    async fn comment_issue(&self, number: u64, body: &str) -> Result<String, String> {
+        let number = number.to_string();
+        let path = match self.forge {
+            Forge::GitHub => ["issues", number.as_str(), "comments"],
+            Forge::GitLab => ["issues", number.as_str(), "notes"],
+        };
+        let comment = self.post(&path, json!({ "body": body })).await?;
+
+        Ok(match web_url(&comment) {
+            Some(url) => format!("Commented on #{number}: {url}"),
+            None => format!("Commented on #{number}"),
+        })
+    }
+
+    async fn create_pr(&self, pr: &NewPr<'_>) -> Result<String, String> {
+        let base = match pr.base {
+            Some(base) => base.to_string(),
+            None => {
+                let project = self.get(&[], &[]).await?;
+                let branch = str_field(&project, "default_branch");
+                if branch.is_empty() {
+                    return Err("The repository has no default branch; pass 'base'".to_string());
+                }
+                branch.to_string()
+            }
+        };
+
+        let created = match self.forge {
+            Forge::GitHub => {
+                let body = json!({
+                    "title": pr.title,
+                    "body": pr.body,
+                    "head": pr.head,
+                    "base": base,
+                    "draft": pr.draft,
+                });
+                self.post(&["pulls"], body).await?
+            }
+            Forge::GitLab => {
+                let title = if pr.draft {
+                    format!("Draft: {}", pr.title)
+                } else {
+                    pr.title.to_string()
+                };
+                let body = json!({
+                    "title": title,
+                    "description": pr.body,
+                    "source_branch": pr.head,
+                    "target_branch": base,
+                });
+                self.post(&["merge_requests"], body).await?
+            }
+        };
+
+        let (kind, number) = match self.forge {
+            Forge::GitHub => ("pull request", format!("#{}", created["number"])),
+            Forge::GitLab => ("merge request", format!("!{}", created["iid"])),
+        };
+        let url = web_url(&created).unwrap_or_default();
+        Ok(format!(
+            "Opened {kind} {number} ({} into {base}): {url}",
+            pr.head
+        ))
+    }
+
+    async fn ci_status(&self, reference: &str) -> Result<String, String> {
+        match self.forge {
+            Forge::GitHub => {
+                let checks = self.get(&["commits", reference, "check-runs"], &[]).await?;
+                let statuses = self.get(&["commits", reference, "status"], &[]).await?;
+                Ok(github_ci(reference, &checks, &statuses))
+            }
+            Forge::GitLab => {
+                let query = [
+                    ("ref", reference.to_string()),
+                    ("per_page", "1".to_string()),
+                ];
+                let pipelines = self.get(&["pipelines"], &query).await?;
+                let Some(pipeline) = array(&pipelines).first() else {
+                    return Ok(format!("No pipelines for {reference}."));
+                };
+                let id = pipeline["id"].to_string();
+                let jobs = self
+                    .get(
+                        &["pipelines", &id, "jobs"],
+                        &[("per_page", MAX_LIMIT.to_string())],
+                    )
+                    .await?;
+                Ok(gitlab_ci(reference, pipeline, &jobs))
+            }
+        }
+    }
+
+    /// `#12 [open] Title (alice; bug, ui)`
+    fn issue_line(&self, issue: &Value) -> String {
+        let number = match self.forge {
+            Forge::GitHub => &issue["number"],
+            Forge::GitLab => &issue["iid"],
+        };
+        let labels: Vec<&str> = array(&issue["labels"])
+            .iter()
+            .filter_map(|label| label.as_str().or_else(|| label["name"].as_str()))
+            .collect();
+        let mut line = format!(
+            "#{number} [{}] {} ({}",
+            str_field(issue, "state"),
+            str_field(issue, "title"),
+            author(issue)
+        );
+        if !labels.is_empty() {
+            let _ = write!(line, "; {}", labels.join(", "));
+        }
+        line.push(')');
+        line
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Formatting
+// ---------------------------------------------------------------------------
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn str_field<'v>(value: &'v Value, key: &str) -> &'v str {
+    value.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+fn author(value: &Value) -> &str {
+    // `user` on GitHub, `author` on GitLab
+    let user = value.get("user").or_else(|| value.get("author"));
+    user.and_then(|user| user.get("login").or_else(|| user.get("username")))
+        .and_then(Value::as_str)
+        .unwrap_or("unknown")
+}
+
+fn web_url(value: &Value) -> Option<&str> {
+    value
+        .get("html_url")
+        .or_else(|| value.get("web_url"))
+        .and_then(Value::as_str)
+}
+
+/// The error message from an API error response.
+fn api_message(body: &Value) -> String {
+    let message = match body {
+        Value::String(text) => text.trim().to_string(),
+        _ => body
+            .get("message")
+            .or_else(|| body.get("error"))
+            .map(|m| match m {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .unwrap_or_else(|| body.to_string()),
+    };
+    // GitHub explains validation failures in `errors`
+    let details: Vec<String> = array(&body["errors"])
+        .iter()
+        .filter_map(|e| e["message"].as_str().map(String::from))
+        .collect();
+    if details.is_empty() {
+        message
+    } else {
+        format!("{message} ({})", details.join("; "))
+    }
+}
+
+fn github_ci(reference: &str, checks: &Value, statuses: &Value) -> String {
+    let mut lines = Vec::new();
+    for run in array(&checks["check_runs"]) {
+        // `conclusion` is null until the run completes
+        let state = run["conclusion"]
+            .as_str()
+            .unwrap_or_else(|| str_field(run, "status"));
+        lines.push(format!(
+            "{state:<10} {}  {}",
+            str_field(run, "name"),
+            str_field(run, "html_url")
+        ));
+    }
+    for status in array(&statuses["statuses"]) {
+        lines.push(format!(
+            "{:<10} {}  {}",
+            str_field(status, "state"),
+            str_field(status, "context"),
+            str_field(status, "target_url")
+        ));
+    }
+
+    if lines.is_empty() {
+        return format!("No checks for {reference}.");
+    }
+    format!("CI for {reference}:\n{}", lines.join("\n"))
+}
+
+fn gitlab_ci(reference: &str, pipeline: &Value, jobs: &Value) -> String {
+    let mut out = format!(
+        "Pipeline {} for {reference}: {}  {}",
+        pipeline["id"],
+        str_field(pipeline, "status"),
+        str_field(pipeline, "web_url")
+    );
+    for job in array(jobs) {
+        let _ = write!(
+            out,
+            "\n{:<10} {} / {}",
+            str_field(job, "status"),
+            str_field(job, "stage"),
+            str_field(job, "name")
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remotes_point_at_their_forge_api() {
+        let settings = RepoSettings {
+            gitlab_url: Some("https://git.example.com/".to_string()),
+            ..Default::default()
+        };
+        let api = |remote: &str| locate(remote, &settings).map(|(f, url)| (f, url.to_string()));
+
+        let github = (
+            Forge::GitHub,
+            "https://api.github.com/repos/max-lt/claude-code-rs".to_string(),
+        );
+        assert_eq!(
+            api("git@github.com:max-lt/claude-code-rs.git"),
+            Ok(github.clone())
+        );
+        assert_eq!(api("https://github.com/max-lt/claude-code-rs"), Ok(github));
+        assert_eq!(
+            api("ssh://git@gitlab.com:22/group/sub/project.git"),
+            Ok((
+                Forge::GitLab,
+                "https://gitlab.com/api/v4/projects/group%2Fsub%2Fproject".to_string()
+            ))
+        );
+        assert_eq!(
+            api("https://me@git.example.com/team/app.git"),
+            Ok((
+                Forge::GitLab,
+                "https://git.example.com/api/v4/projects/team%2Fapp".to_string()
+            ))
+        );
+        assert!(api("git@bitbucket.org:team/app.git").is_err());
+        assert!(api("/srv/git/app.git").is_err());
+    }
+
+    #[test]
+    fn ci_lists_checks_and_statuses() {
+        let checks = json!({"check_runs": [
+            {"name": "test", "status": "completed", "conclusion": "failure", "html_url": "https://ci/1"},
+            {"name": "lint", "status": "in_progress", "conclusion": null, "html_url": "https://ci/2"},
+        ]});
+        let statuses = json!({"statuses": [
+            {"context": "deploy", "state": "pending", "target_url": "https://ci/3"},
+        ]});
+
+        let report = github_ci("main", &checks, &statuses);
+        assert!(report.contains("failure    test"));
+        assert!(report.contains("in_progress lint"));
+        assert!(report.contains("pending    deploy"));
+        assert_eq!(
+            github_ci("main", &json!({}), &json!({})),
+            "No checks for main."
+        );
+    }
+}
@@ -1,53 +1,102 @@
-use std::path::Path;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
-use super::{ToolDef, ToolOutput};
+use ccrs_search::{Progress, ScoreExplanation, SearchHit, SearchIndex, SourceRank, Stage};
 
+use super::{AdditionalDirs, ProgressSink, ToolDef, ToolOutput, ToolProgress};
+
+type Indexes = Arc<RwLock<Vec<(PathBuf, Arc<SearchIndex>)>>>;
+
+/// Searches the working directory and any additional directories, with one
+/// index per root.
 pub struct SearchTool {
-    index: Mutex<Option<ccrs_search::SearchIndex>>,
+    indexes: Indexes,
+    dirs: AdditionalDirs,
 }
 
 impl Default for SearchTool {
     fn default() -> Self {
-        Self::new()
+        Self::new(AdditionalDirs::default())
     }
 }
 
 impl SearchTool {
-    pub fn new() -> Self {
+    pub fn new(dirs: AdditionalDirs) -> Self {
         Self {
-            index: Mutex::new(None),
+            indexes: Arc::default(),
+            dirs,
         }
     }
+}
 
-    fn ensure_index(&self, cwd: &Path) -> Result<(), String> {
-        let mut guard = self.index.lock().map_err(|e| e.to_string())?;
+/// Return a shared handle to the index for `root`, building it on first
+/// use and updating it incrementally afterwards.
+fn ensure_index(
+    indexes: &Indexes,
+    root: &Path,
+    progress: &ProgressSink,
+) -> Result<Arc<SearchIndex>, String> {
+    let existing = indexes
+        .read()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .find(|(r, _)| r == root)
+        .map(|(_, index)| Arc::clone(index));
 
-        if let Some(index) = guard.as_mut() {
-            // Incremental update
-            let stats = index.update().map_err(|e| e.to_string())?;
+    if let Some(index) = existing {
+        // Incremental update
+        let stats = index.update().map_err(|e| e.to_string())?;
 
-            if stats.has_changes() {
-                eprintln!(
-                    "Index updated: +{} ~{} -{}",
-                    stats.added, stats.modified, stats.removed
-                );
-            }
-        } else {
-            // First build
-            let (index, stats) = ccrs_search::SearchIndex::open(cwd).map_err(|e| e.to_string())?;
-
-            eprintln!(
-                "Index built: {} files, {:.1} KB",
-                stats.files,
-                stats.bytes as f64 / 1024.0
+        if stats.has_changes() {
+            tracing::info!(
+                added = stats.added,
+                modified = stats.modified,
+                removed = stats.removed,
+                "search index updated"
             );
-
-            *guard = Some(index);
         }
 
-        Ok(())
+        return Ok(index);
     }
+
+    let mut guard = indexes.write().map_err(|e| e.to_string())?;
+
+    // Another call may have built it while we waited for the write lock
+    if let Some((_, index)) = guard.iter().find(|(r, _)| r == root) {
+        return Ok(Arc::clone(index));
+    }
+
+    // First build
+    let (index, stats) = SearchIndex::open_with_progress(root, &|p| report(progress, p))
+        .map_err(|e| e.to_string())?;
+
+    tracing::info!(
+        root = %root.display(),
+        files = stats.files,
+        bytes = stats.bytes,
+        pruned_terms = stats.pruned_terms,
+        "search index built"
+    );
+
+    let index = Arc::new(index);
+    guard.push((root.to_path_buf(), Arc::clone(&index)));
+
+    Ok(index)
+}
+
+fn report(sink: &ProgressSink, progress: Progress) {
+    let label = match progress.stage {
+        Stage::Download => "Downloading the embedding model",
+        Stage::Index => "Indexing files",
+        Stage::Embed => "Embedding files",
+    };
+
+    sink.report(ToolProgress {
+        label: label.to_string(),
+        done: progress.done,
+        total: progress.total,
+        bytes: progress.stage == Stage::Download,
+    });
 }
 
 impl ToolDef for SearchTool {
@@ -76,6 +125,10 @@ impl ToolDef for SearchTool {
                 "context_lines": {
                     "type": "integer",
                     "description": "Number of context lines around matches in snippets (default: 2)"
+                },
+                "explain": {
+                    "type": "boolean",
+                    "description": "Report per-hit BM25 rank, semantic rank, RRF contributions and path boost (default: false)"
                 }
             },
             "required": ["query"]
@@ -83,8 +136,18 @@ impl ToolDef for SearchTool {
     }
 
     async fn execute(&self, input: &serde_json::Value, cwd: &Path) -> ToolOutput {
+        self.execute_with_progress(input, cwd, ProgressSink::default())
+            .await
+    }
+
+    async fn execute_with_progress(
+        &self,
+        input: &serde_json::Value,
+        cwd: &Path,
+        progress: ProgressSink,
+    ) -> ToolOutput {
         let query = match input.get("query").and_then(|q| q.as_str()) {
-            Some(q) => q,
+            Some(q) => q.to_string(),
             None => return ToolOutput::error("Missing required parameter: query"),
         };
 
@@ -95,49 +158,155 @@ impl ToolDef for SearchTool {
             .and_then(|v| v.as_u64())
             .unwrap_or(2) as usize;
 
-        if let Err(e) = self.ensure_index(cwd) {
-            return ToolOutput::error(format!("Failed to build search index: {e}"));
-        }
+        let explain = input
+            .get("explain")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
 
-        let mut guard = match self.index.lock() {
-            Ok(g) => g,
-            Err(e) => return ToolOutput::error(format!("Index lock error: {e}")),
-        };
+        // `None` marks the working directory, whose hits keep relative paths
+        let mut roots = vec![(None, cwd.to_path_buf())];
+        roots.extend(self.dirs.get().into_iter().map(|d| (Some(d.clone()), d)));
 
-        let index = match guard.as_mut() {
-            Some(i) => i,
-            None => return ToolOutput::error("Search index not available"),
-        };
+        // Indexing, the lazy embedding build and searching are CPU-bound
+        let indexes = Arc::clone(&self.indexes);
+        let result = tokio::task::spawn_blocking(move || {
+            let mut hits = Vec::new();
+            let mut notes = Vec::new();
+            for (prefix, root) in roots {
+                let index = match ensure_index(&indexes, &root, &progress) {
+                    Ok(index) => index,
+                    Err(e) if prefix.is_none() => {
+                        anyhow::bail!("Failed to build search index: {e}");
+                    }
+                    Err(e) => {
+                        notes.push(format!("Skipped {}: {e}", root.display()));
+                        continue;
+                    }
+                };
+
+                index
+                    .build_embeddings_with_progress(&|p| report(&progress, p))
+                    .map_err(|e| anyhow::anyhow!("Search failed: {e}"))?;
+                let mut root_hits = index
+                    .search(&query, limit, context_lines)
+                    .map_err(|e| anyhow::anyhow!("Search failed: {e}"))?;
+                if let Some(prefix) = prefix {
+                    for hit in &mut root_hits {
+                        hit.path = prefix.join(&hit.path).display().to_string();
+                    }
+                }
+                hits.extend(root_hits);
+            }
+            anyhow::Ok((merge_hits(hits, limit), notes))
+        })
+        .await;
 
-        let hits = match index.search(query, limit, context_lines) {
-            Ok(h) => h,
-            Err(e) => return ToolOutput::error(format!("Search failed: {e}")),
+        let (hits, notes) = match result {
+            Ok(Ok(found)) => found,
+            Ok(Err(e)) => return ToolOutput::error(e.to_string()),
+            Err(e) => return ToolOutput::error(format!("Search task failed: {e}")),
         };
 
-        if hits.is_empty() {
-            return ToolOutput::success("No results found.");
+        let mut output = if hits.is_empty() {
+            "No results found.".to_string()
+        } else {
+            format_hits(&hits, explain)
+        };
+        for note in notes {
+            output.push_str(&format!("\n\n{note}"));
         }
 
-        let mut output = String::new();
+        ToolOutput::success(output)
+    }
+}
 
-        for (i, hit) in hits.iter().enumerate() {
-            output.push_str(&format!(
-                "{}. {} (score: {:.4})\n",
-                i + 1,
-                hit.path,
-                hit.score
-            ));
+/// Combine per-root results into one ranking. Scores are rank-based (RRF),
+/// so they compare across indexes.
+fn merge_hits(mut hits: Vec<SearchHit>, limit: usize) -> Vec<SearchHit> {
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    hits
+}
 
-            for snippet in &hit.snippets {
-                for (j, line) in snippet.lines.iter().enumerate() {
-                    let line_num = snippet.line_number + j;
-                    output.push_str(&format!("  {line_num:>4} | {line}\n"));
-                }
+/// Render hits as a numbered list with line-numbered snippets, optionally
+/// followed by each hit's score breakdown.
+pub fn format_hits(hits: &[SearchHit], explain: bool) -> String {
+    let mut output = String::new();
+
+    for (i, hit) in hits.iter().enumerate() {
+        output.push_str(&format!(
+            "{}. {} (score: {:.4})\n",
+            i + 1,
+            hit.path,
+            hit.score
+        ));
+
+        if explain {
+            output.push_str(&format!("     {}\n", format_explanation(&hit.explanation)));
+        }
 
-                output.push('\n');
+        for snippet in &hit.snippets {
+            for (j, line) in snippet.lines.iter().enumerate() {
+                let line_num = snippet.line_number + j;
+                output.push_str(&format!("  {line_num:>4} | {line}\n"));
             }
+
+            output.push('\n');
         }
+    }
+
+    output.trim_end().to_string()
+}
+
+/// One-line breakdown, e.g.
+/// `bm25 #1 (12.34) → 0.0164 · semantic #3 (0.712) → 0.0159 · fused 0.0323 × boost 1.1`.
+fn format_explanation(explanation: &ScoreExplanation) -> String {
+    let source = |label: &str, rank: Option<SourceRank>| match rank {
+        Some(r) => format!(
+            "{label} #{} ({:.3}) → {:.4}",
+            r.rank, r.score, r.contribution
+        ),
+        None => format!("{label} —"),
+    };
+
+    format!(
+        "{} · {} · fused {:.4} × boost {}",
+        source("bm25", explanation.bm25),
+        source("semantic", explanation.semantic),
+        explanation.fused,
+        explanation.boost
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(path: &str, score: f32) -> SearchHit {
+        SearchHit {
+            path: path.to_string(),
+            score,
+            snippets: Vec::new(),
+            explanation: ScoreExplanation {
+                bm25: None,
+                semantic: None,
+                fused: score,
+                boost: 1.0,
+            },
+        }
+    }
+
+    #[test]
+    fn merge_hits_ranks_across_roots() {
+        let hits = vec![
+            hit("src/a.rs", 0.02),
+            hit("src/b.rs", 0.01),
+            hit("/shared/lib/c.rs", 0.03),
+        ];
+
+        let merged = merge_hits(hits, 2);
 
-        ToolOutput::success(output.trim_end())
+        let paths: Vec<&str> = merged.iter().map(|h| h.path.as_str()).collect();
+        assert_eq!(paths, ["/shared/lib/c.rs", "src/a.rs"]);
     }
 }
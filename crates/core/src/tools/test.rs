@@ -0,0 +1,663 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::process::Command;
+
+use super::{ToolDef, ToolEnv, ToolOutput};
+
+/// Failures shown with their details; the rest are listed by name.
+const MAX_DETAILED: usize = 10;
+/// Lines of each failure's details.
+const MAX_DETAIL_LINES: usize = 30;
+/// Lines of raw output shown when no results could be read from it.
+const MAX_TAIL_LINES: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runner {
+    Cargo,
+    Pytest,
+    Jest,
+}
+
+impl Runner {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Pytest => "pytest",
+            Self::Jest => "jest",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "cargo" => Some(Self::Cargo),
+            "pytest" => Some(Self::Pytest),
+            "jest" => Some(Self::Jest),
+            _ => None,
+        }
+    }
+
+    /// The runner for the project in `dir`, from its build files.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        if dir.join("Cargo.toml").is_file() {
+            return Some(Self::Cargo);
+        }
+        let jest_config = ["js", "ts", "mjs", "cjs", "json"]
+            .iter()
+            .any(|ext| dir.join(format!("jest.config.{ext}")).is_file());
+        let jest_dependency = std::fs::read_to_string(dir.join("package.json"))
+            .is_ok_and(|package| package.contains("\"jest\""));
+        if jest_config || jest_dependency {
+            return Some(Self::Jest);
+        }
+        let python = [
+            "pytest.ini",
+            "pyproject.toml",
+            "setup.cfg",
+            "tox.ini",
+            "conftest.py",
+            "setup.py",
+        ];
+        if python.iter().any(|file| dir.join(file).is_file()) {
+            return Some(Self::Pytest);
+        }
+        None
+    }
+
+    /// Program and arguments running the tests matching `filter`, or
+    /// exactly the `failed` ones when there are any. Jest writes its JSON
+    /// results to `report`.
+    fn command(self, filter: Option<&str>, failed: &[String], report: &Path) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        match self {
+            Self::Cargo => {
+                args.extend(
+                    ["cargo", "test", "--no-fail-fast", "--", "--color", "never"].map(String::from),
+                );
+                if failed.is_empty() {
+                    args.extend(filter.map(String::from));
+                } else {
+                    args.push("--exact".to_string());
+                    args.extend(failed.iter().cloned());
+                }
+            }
+            Self::Pytest => {
+                args.extend(["pytest", "-q", "-rfE", "--tb=short", "--color=no"].map(String::from));
+                if failed.is_empty() {
+                    if let Some(filter) = filter {
+                        args.extend(["-k".to_string(), filter.to_string()]);
+                    }
+                } else {
+                    args.extend(failed.iter().cloned());
+                }
+            }
+            Self::Jest => {
+                args.extend(["npx", "jest", "--ci", "--json"].map(String::from));
+                args.push(format!("--outputFile={}", report.display()));
+                let pattern = if failed.is_empty() {
+                    filter.map(String::from)
+                } else {
+                    let names: Vec<String> = failed.iter().map(|name| regex_escape(name)).collect();
+                    Some(format!("^(?:{})$", names.join("|")))
+                };
+                if let Some(pattern) = pattern {
+                    args.extend(["-t".to_string(), pattern]);
+                }
+            }
+        }
+        args
+    }
+}
+
+/// One failed test.
+#[derive(Debug, PartialEq)]
+struct Failure {
+    /// What the runner takes to run just this test again.
+    name: String,
+    /// The assertion or panic message and where it happened.
+    details: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Results {
+    passed: usize,
+    skipped: usize,
+    failed: Vec<Failure>,
+}
+
+impl Results {
+    fn is_empty(&self) -> bool {
+        self.passed == 0 && self.skipped == 0 && self.failed.is_empty()
+    }
+}
+
+pub struct TestTool {
+    env: ToolEnv,
+    /// The failures of the last run, for `failed_only`.
+    last_failed: Mutex<Option<(Runner, Vec<String>)>>,
+}
+
+impl TestTool {
+    pub fn new(env: ToolEnv) -> Self {
+        Self {
+            env,
+            last_failed: Mutex::new(None),
+        }
+    }
+}
+
+impl ToolDef for TestTool {
+    fn name(&self) -> &'static str {
+        "Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Runs the project's tests with cargo test, pytest or jest (detected from the project \
+         files unless given) and reports the counts and each failed test's name and assertion \
+         message. Prefer this over running test commands with Bash. After a run with failures, \
+         set failed_only to run just those tests again."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "runner": {
+                    "type": "string",
+                    "enum": ["cargo", "pytest", "jest"],
+                    "description": "The test runner; detected from Cargo.toml, package.json or Python project files if omitted"
+                },
+                "filter": {
+                    "type": "string",
+                    "description": "Only run matching tests: a name substring for cargo, a -k expression for pytest, a name pattern for jest"
+                },
+                "failed_only": {
+                    "type": "boolean",
+                    "description": "Run only the tests that failed in the previous Test run"
+                },
+                "timeout": {
+                    "type": "integer",
+                    "description": "Optional timeout in milliseconds (max 1800000, default 600000)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, input: &serde_json::Value, cwd: &Path) -> ToolOutput {
+        let failed_only = input
+            .get("failed_only")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let filter = input.get("filter").and_then(|v| v.as_str());
+
+        let previous = self.last_failed.lock().map_or(None, |last| last.clone());
+        let (runner, failed) = match (input.get("runner").and_then(|v| v.as_str()), failed_only) {
+            (_, true) => match previous {
+                Some((runner, failed)) if !failed.is_empty() => (runner, failed),
+                _ => {
+                    return ToolOutput::error(
+                        "No failed tests recorded: failed_only reruns the failures of an \
+                         earlier Test run in this session.",
+                    );
+                }
+            },
+            (Some(name), false) => match Runner::parse(name) {
+                Some(runner) => (runner, Vec::new()),
+                None => {
+                    return ToolOutput::error(format!(
+                        "Unknown runner \"{name}\": use cargo, pytest or jest"
+                    ));
+                }
+            },
+            (None, false) => match Runner::detect(cwd) {
+                Some(runner) => (runner, Vec::new()),
+                None => {
+                    return ToolOutput::error(
+                        "No Cargo.toml, package.json with jest, or Python project files here; \
+                         pass a runner or run the tests with Bash.",
+                    );
+                }
+            },
+        };
+
+        let timeout_ms = input
+            .get("timeout")
+            .and_then(|t| t.as_u64())
+            .unwrap_or(600_000)
+            .min(1_800_000);
+
+        let report = std::env::temp_dir().join(format!(
+            "ccrs-jest-{}-{}.json",
+            std::process::id(),
+            crate::store::new_id()
+        ));
+        let args = runner.command(filter, &failed, &report);
+        let mut command = Command::new(&args[0]);
+        command
+            .args(&args[1..])
+            .current_dir(cwd)
+            .env("NO_COLOR", "1")
+            .env("CARGO_TERM_COLOR", "never")
+            // Panic messages say enough; the env setting can turn it back on
+            .env("RUST_BACKTRACE", "0")
+            .env("FORCE_COLOR", "0")
+            .kill_on_drop(true);
+        self.env.apply(&mut command);
+
+        let started = Instant::now();
+        let result =
+            tokio::time::timeout(Duration::from_millis(timeout_ms), command.output()).await;
+        let output = match result {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => return ToolOutput::error(format!("Failed to run {}: {e}", args[0])),
+            Err(_) => {
+                let _ = std::fs::remove_file(&report);
+                return ToolOutput::error(format!("Tests timed out after {timeout_ms}ms"));
+            }
+        };
+        let elapsed = started.elapsed();
+
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        let results = match runner {
+            Runner::Cargo => parse_cargo(&text),
+            Runner::Pytest => parse_pytest(&text),
+            Runner::Jest => std::fs::read_to_string(&report)
+                .ok()
+                .and_then(|json| parse_jest(&json))
+                .unwrap_or_default(),
+        };
+        let _ = std::fs::remove_file(&report);
+
+        if !results.is_empty()
+            && let Ok(mut last) = self.last_failed.lock()
+        {
+            let names = results.failed.iter().map(|f| f.name.clone()).collect();
+            *last = Some((runner, names));
+        }
+
+        let content = self.env.redact(&format_results(
+            runner,
+            &results,
+            output.status.success(),
+            &text,
+            elapsed,
+        ));
+        if output.status.success() && results.failed.is_empty() {
+            ToolOutput::success(content)
+        } else {
+            ToolOutput::error(content)
+        }
+    }
+}
+
+/// The summary the model reads: counts, then each failure.
+fn format_results(
+    runner: Runner,
+    results: &Results,
+    success: bool,
+    output: &str,
+    elapsed: Duration,
+) -> String {
+    let mut text = format!(
+        "{}: {} failed, {} passed, {} skipped ({:.1}s)",
+        runner.name(),
+        results.failed.len(),
+        results.passed,
+        results.skipped,
+        elapsed.as_secs_f64()
+    );
+
+    if results.is_empty() {
+        if success {
+            text.push_str("\nNo tests ran.");
+            return text;
+        }
+        // Most likely the build or test collection failed
+        text.push_str("\nNo test results in the output; it ends with:\n");
+        text.push_str(&tail(output, MAX_TAIL_LINES));
+        return text;
+    }
+
+    if !results.failed.is_empty() {
+        text.push_str("\n\nFailures:");
+        for (i, failure) in results.failed.iter().enumerate() {
+            text.push_str(&format!("\n{}. {}", i + 1, failure.name));
+            if i >= MAX_DETAILED {
+                continue;
+            }
+            let lines: Vec<&str> = failure.details.lines().collect();
+            for line in lines.iter().take(MAX_DETAIL_LINES) {
+                text.push_str("\n   ");
+                text.push_str(line);
+            }
+            if lines.len() > MAX_DETAIL_LINES {
+                text.push_str(&format!(
+                    "\n   ... ({} more lines)",
+                    lines.len() - MAX_DETAIL_LINES
+                ));
+            }
+        }
+        text.push_str("\n\nSet failed_only to run just these tests again.");
+    } else if !success {
+        text.push_str("\nThe tests passed but the run failed; the output ends with:\n");
+        text.push_str(&tail(output, MAX_TAIL_LINES));
+    }
+    text
+}
+
+// ---------------------------------------------------------------------------
+// Output parsing
+// ---------------------------------------------------------------------------
+
+/// libtest's output: `test name ... ok` lines, then a `---- name stdout ----`
+/// section per failure.
+fn parse_cargo(output: &str) -> Results {
+    let mut results = Results::default();
+    let mut failed = Vec::new();
+    for line in output.lines() {
+        let Some((name, status)) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.rsplit_once(" ... "))
+        else {
+            continue;
+        };
+        match status.trim() {
+            "ok" => results.passed += 1,
+            "FAILED" => failed.push(name.to_string()),
+            status if status.starts_with("ignored") => results.skipped += 1,
+            _ => {}
+        }
+    }
+
+    results.failed = failed
+        .into_iter()
+        .map(|name| {
+            let header = format!("---- {name} stdout ----");
+            let details = output
+                .split_once(&header)
+                .map(|(_, rest)| {
+                    rest.lines()
+                        .take_while(|line| !line.starts_with("---- ") && *line != "failures:")
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                        .trim()
+                        .to_string()
+                })
+                .unwrap_or_default();
+            Failure { name, details }
+        })
+        .collect();
+    results
+}
+
+/// pytest's `-rfE` summary lines (`FAILED tests/test_a.py::test_b - msg`),
+/// the `--tb=short` traceback of each and the final counts.
+fn parse_pytest(output: &str) -> Results {
+    let mut results = Results::default();
+
+    let counts = output.lines().rev().find(|line| {
+        line.contains(" passed") || line.contains(" failed") || line.contains(" error")
+    });
+    if let Some(line) = counts {
+        let words: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '=')
+            .filter(|w| !w.is_empty())
+            .collect();
+        for pair in words.windows(2) {
+            let Ok(n) = pair[0].parse::<usize>() else {
+                continue;
+            };
+            match pair[1] {
+                "passed" => results.passed += n,
+                "skipped" | "xfailed" | "deselected" => results.skipped += n,
+                _ => {}
+            }
+        }
+    }
+
+    for line in output.lines() {
+        let Some(rest) = line
+            .strip_prefix("FAILED ")
+            .or_else(|| line.strip_prefix("ERROR "))
+        else {
+            continue;
+        };
+        let (name, message) = rest.split_once(" - ").unwrap_or((rest, ""));
+        // The traceback section is titled with the test's name inside the
+        // file, classes joined with dots
+        let title = name
+            .split_once("::")
+            .map_or(name, |(_, test)| test)
+            .replace("::", ".");
+        let details = traceback(output, &title).unwrap_or_else(|| message.to_string());
+        results.failed.push(Failure {
+            name: name.to_string(),
+            details,
+        });
+    }
+    results
+}
+
+/// The `___ title ___` section of pytest's failure report.
+fn traceback(output: &str, title: &str) -> Option<String> {
+    let mut lines = output.lines().skip_while(|line| {
+        !(line.starts_with('_') && line.trim_matches(|c| c == '_' || c == ' ') == title)
+    });
+    lines.next()?;
+    let body: Vec<&str> = lines
+        .take_while(|line| !line.starts_with("____") && !line.starts_with("===="))
+        .collect();
+    Some(body.join("\n").trim().to_string())
+}
+
+/// Jest's `--json` report.
+fn parse_jest(json: &str) -> Option<Results> {
+    let report: serde_json::Value = serde_json::from_str(json).ok()?;
+    let count = |key: &str| report.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+    let mut results = Results {
+        passed: count("numPassedTests"),
+        skipped: count("numPendingTests") + count("numTodoTests"),
+        failed: Vec::new(),
+    };
+
+    for suite in report.get("testResults")?.as_array()? {
+        let assertions = suite
+            .get("assertionResults")
+            .and_then(|v| v.as_array())
+            .map_or(&[][..], Vec::as_slice);
+        for assertion in assertions {
+            if assertion.get("status").and_then(|v| v.as_str()) != Some("failed") {
+                continue;
+            }
+            let messages = assertion
+                .get("failureMessages")
+                .and_then(|v| v.as_array())
+                .map(|messages| {
+                    messages
+                        .iter()
+                        .filter_map(|m| m.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default();
+            results.failed.push(Failure {
+                name: assertion
+                    .get("fullName")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                details: messages.trim().to_string(),
+            });
+        }
+
+        // A suite that failed to run, e.g. on a syntax error, has no tests
+        if assertions.is_empty() && suite.get("status").and_then(|v| v.as_str()) == Some("failed") {
+            results.failed.push(Failure {
+                name: suite
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                details: suite
+                    .get("message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .trim()
+                    .to_string(),
+            });
+        }
+    }
+    Some(results)
+}
+
+fn regex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The last `n` lines of `text`.
+fn tail(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cargo_failures_come_with_their_panics() {
+        let output = "\
+running 3 tests
+test parser::tests::commas ... FAILED
+test parser::tests::spaces ... ok
+test slow ... ignored, needs network
+test src/lib.rs - parse (line 10) ... ok
+
+failures:
+
+---- parser::tests::commas stdout ----
+
+thread 'parser::tests::commas' panicked at src/parser.rs:42:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+
+failures:
+    parser::tests::commas
+
+test result: FAILED. 2 passed; 1 failed; 1 ignored
+";
+        let results = parse_cargo(output);
+        assert_eq!(results.passed, 2);
+        assert_eq!(results.skipped, 1);
+        assert_eq!(
+            results.failed,
+            [Failure {
+                name: "parser::tests::commas".to_string(),
+                details: "thread 'parser::tests::commas' panicked at src/parser.rs:42:9:\n\
+                          assertion `left == right` failed\n  left: 1\n right: 2"
+                    .to_string(),
+            }]
+        );
+
+        let args = Runner::Cargo.command(None, &["a::b".to_string()], Path::new("r"));
+        assert_eq!(args[args.len() - 2..], ["--exact", "a::b"]);
+    }
+
+    #[test]
+    fn pytest_failures_come_with_their_tracebacks() {
+        let output = "\
+..F.s
+=================================== FAILURES ===================================
+_____________________________ TestParse.test_comma _____________________________
+tests/test_parse.py:12: in test_comma
+    assert parse(\"a,b\") == [\"a\"]
+E   AssertionError: assert ['a', 'b'] == ['a']
+=========================== short test summary info ============================
+FAILED tests/test_parse.py::TestParse::test_comma - AssertionError: assert ['a', 'b'] == ['a']
+ERROR tests/test_io.py - ModuleNotFoundError: No module named 'yaml'
+1 failed, 3 passed, 1 skipped, 1 error in 0.12s
+";
+        let results = parse_pytest(output);
+        assert_eq!(results.passed, 3);
+        assert_eq!(results.skipped, 1);
+        assert_eq!(results.failed.len(), 2);
+        assert_eq!(
+            results.failed[0].name,
+            "tests/test_parse.py::TestParse::test_comma"
+        );
+        assert!(
+            results.failed[0]
+                .details
+                .starts_with("tests/test_parse.py:12: in test_comma")
+        );
+        assert!(results.failed[0].details.ends_with("== ['a']"));
+        assert_eq!(
+            results.failed[1].details,
+            "ModuleNotFoundError: No module named 'yaml'"
+        );
+    }
+
+    #[test]
+    fn jest_reruns_failures_by_full_name() {
+        let json = r#"{
+            "numPassedTests": 4, "numPendingTests": 1, "numTodoTests": 0,
+            "testResults": [
+                {"name": "/p/sum.test.js", "status": "failed", "assertionResults": [
+                    {"fullName": "sum adds (two) numbers", "status": "failed",
+                     "failureMessages": ["Error: expect(received).toBe(expected)\n\nExpected: 3"]},
+                    {"fullName": "sum handles zero", "status": "passed", "failureMessages": []}
+                ]},
+                {"name": "/p/broken.test.js", "status": "failed", "assertionResults": [],
+                 "message": "SyntaxError: Unexpected token"}
+            ]
+        }"#;
+        let results = parse_jest(json).unwrap();
+        assert_eq!(results.passed, 4);
+        assert_eq!(results.skipped, 1);
+        assert_eq!(results.failed[0].name, "sum adds (two) numbers");
+        assert!(results.failed[0].details.contains("Expected: 3"));
+        assert_eq!(results.failed[1].details, "SyntaxError: Unexpected token");
+
+        let names = ["sum adds (two) numbers".to_string()];
+        let args = Runner::Jest.command(None, &names, Path::new("/tmp/r.json"));
+        assert_eq!(
+            args[args.len() - 2..],
+            ["-t", r"^(?:sum adds \(two\) numbers)$"]
+        );
+    }
+
+    #[test]
+    fn the_runner_is_detected_from_project_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(Runner::detect(tmp.path()), None);
+        std::fs::write(tmp.path().join("pyproject.toml"), "").unwrap();
+        assert_eq!(Runner::detect(tmp.path()), Some(Runner::Pytest));
+        std::fs::write(
+            tmp.path().join("package.json"),
+            r#"{"devDependencies": {"jest": "^29"}}"#,
+        )
+        .unwrap();
+        assert_eq!(Runner::detect(tmp.path()), Some(Runner::Jest));
+        std::fs::write(tmp.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(Runner::detect(tmp.path()), Some(Runner::Cargo));
+    }
+
+    #[tokio::test]
+    async fn failed_only_needs_an_earlier_failure() {
+        let tool = TestTool::new(ToolEnv::default());
+        let input = serde_json::json!({ "failed_only": true });
+        let output = tool.execute(&input, Path::new(".")).await;
+        assert!(output.is_error);
+        assert!(output.content.contains("No failed tests recorded"));
+    }
+}
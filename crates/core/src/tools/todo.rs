@@ -0,0 +1,162 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{ToolDef, ToolOutput};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+/// One step of the model's plan.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Todo {
+    /// Imperative form, e.g. "Run the tests".
+    pub content: String,
+    pub status: TodoStatus,
+    /// Shown while in progress, e.g. "Running the tests".
+    #[serde(default, rename = "activeForm")]
+    pub active_form: String,
+}
+
+impl Todo {
+    /// What to show for this step in its current status.
+    pub fn label(&self) -> &str {
+        if self.status == TodoStatus::InProgress && !self.active_form.is_empty() {
+            &self.active_form
+        } else {
+            &self.content
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Input {
+    todos: Vec<Todo>,
+}
+
+/// The list in a TodoWrite call's input.
+pub fn parse(input: &serde_json::Value) -> Result<Vec<Todo>, serde_json::Error> {
+    Input::deserialize(input).map(|i| i.todos)
+}
+
+/// Replaces the session's task list. Stateless: each call carries the whole
+/// list, and front ends show the latest one.
+pub struct TodoWriteTool;
+
+impl ToolDef for TodoWriteTool {
+    fn name(&self) -> &'static str {
+        "TodoWrite"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create or update the task list for the current work. Use it for tasks with three or \
+         more steps: write the plan up front, mark one item in_progress before starting it and \
+         completed as soon as it is done. Each call replaces the whole list."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "todos": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "content": {
+                                "type": "string",
+                                "description": "The step, in imperative form (e.g. \"Run the tests\")"
+                            },
+                            "status": {
+                                "type": "string",
+                                "enum": ["pending", "in_progress", "completed"]
+                            },
+                            "activeForm": {
+                                "type": "string",
+                                "description": "The step while it runs (e.g. \"Running the tests\")"
+                            }
+                        },
+                        "required": ["content", "status", "activeForm"]
+                    }
+                }
+            },
+            "required": ["todos"]
+        })
+    }
+
+    async fn execute(&self, input: &serde_json::Value, _cwd: &Path) -> ToolOutput {
+        let todos = match parse(input) {
+            Ok(todos) => todos,
+            Err(e) => return ToolOutput::error(format!("Invalid todo list: {e}")),
+        };
+
+        let active = todos
+            .iter()
+            .filter(|t| t.status == TodoStatus::InProgress)
+            .count();
+        if active > 1 {
+            return ToolOutput::error(format!(
+                "{active} items are in_progress; keep exactly one in progress at a time."
+            ));
+        }
+
+        if todos.is_empty() {
+            return ToolOutput::success("Todo list cleared.");
+        }
+
+        let mut out = String::new();
+        for todo in &todos {
+            let mark = match todo.status {
+                TodoStatus::Pending => "[ ]",
+                TodoStatus::InProgress => "[~]",
+                TodoStatus::Completed => "[x]",
+            };
+            writeln!(out, "{mark} {}", todo.content).unwrap();
+        }
+        out.pop();
+
+        ToolOutput::success(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lists_the_todos_as_a_checklist() {
+        let input = serde_json::json!({"todos": [
+            {"content": "Write the parser", "status": "completed", "activeForm": "Writing the parser"},
+            {"content": "Run the tests", "status": "in_progress", "activeForm": "Running the tests"},
+            {"content": "Update the docs", "status": "pending", "activeForm": "Updating the docs"},
+        ]});
+
+        let output = TodoWriteTool.execute(&input, Path::new(".")).await;
+        assert!(!output.is_error);
+        assert_eq!(
+            output.content,
+            "[x] Write the parser\n[~] Run the tests\n[ ] Update the docs"
+        );
+        assert_eq!(parse(&input).unwrap()[1].label(), "Running the tests");
+    }
+
+    #[tokio::test]
+    async fn rejects_two_items_in_progress() {
+        let input = serde_json::json!({"todos": [
+            {"content": "A", "status": "in_progress", "activeForm": "Doing A"},
+            {"content": "B", "status": "in_progress", "activeForm": "Doing B"},
+        ]});
+
+        let output = TodoWriteTool.execute(&input, Path::new(".")).await;
+        assert!(output.is_error);
+
+        let bad = serde_json::json!({"todos": [{"content": "A", "status": "done"}]});
+        assert!(TodoWriteTool.execute(&bad, Path::new(".")).await.is_error);
+    }
+}
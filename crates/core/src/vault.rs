@@ -0,0 +1,293 @@
+use std::num::NonZeroU32;
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rand::Rng;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, Nonce, UnboundKey};
+use ring::{hkdf, pbkdf2};
+use serde::{Deserialize, Serialize};
+
+/// Encrypts stored credentials with this passphrase instead of the machine
+/// key, so the file is useless without it.
+pub const PASSPHRASE_ENV: &str = "CLAUDE_CODE_CREDENTIALS_PASSPHRASE";
+
+/// PBKDF2-HMAC-SHA256 rounds for passphrases (OWASP's 2023 figure).
+const PASSPHRASE_ITERATIONS: u32 = 600_000;
+
+/// Binds the ciphertext to its purpose.
+const AAD: &[u8] = b"ccrs credentials v1";
+
+/// What the key of an encrypted file is derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    /// The OS's machine ID: the file can't be decrypted on another machine,
+    /// say from a backup or a synced home directory.
+    Machine,
+    /// [`PASSPHRASE_ENV`].
+    Passphrase,
+}
+
+/// An encrypted file: `{"encrypted": {...}}`, told apart from plain
+/// credentials by its only key.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    encrypted: Sealed,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    key: KeySource,
+    /// PBKDF2 rounds; unused for the machine key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    iterations: Option<u32>,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+enum Secret {
+    Machine(Vec<u8>),
+    Passphrase(String, u32),
+}
+
+impl Secret {
+    /// The passphrase if one is set, else the machine ID. `None` when
+    /// neither is available.
+    fn current() -> Option<Self> {
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => {
+                Some(Self::Passphrase(passphrase, PASSPHRASE_ITERATIONS))
+            }
+            _ => machine_id().map(Self::Machine),
+        }
+    }
+
+    fn for_file(sealed: &Sealed) -> Result<Self> {
+        match sealed.key {
+            KeySource::Machine => machine_id()
+                .map(Self::Machine)
+                .context("The credentials file is encrypted with a machine ID this system lacks"),
+            KeySource::Passphrase => {
+                let passphrase = std::env::var(PASSPHRASE_ENV)
+                    .ok()
+                    .filter(|p| !p.is_empty())
+                    .with_context(|| {
+                        format!(
+                            "The credentials file is encrypted with a passphrase; set {PASSPHRASE_ENV}"
+                        )
+                    })?;
+                let iterations = sealed.iterations.unwrap_or(PASSPHRASE_ITERATIONS);
+                Ok(Self::Passphrase(passphrase, iterations))
+            }
+        }
+    }
+
+    fn source(&self) -> KeySource {
+        match self {
+            Self::Machine(_) => KeySource::Machine,
+            Self::Passphrase(..) => KeySource::Passphrase,
+        }
+    }
+
+    fn key(&self, salt: &[u8]) -> Result<LessSafeKey> {
+        let unbound = match self {
+            // High-entropy already; HKDF only binds it to the salt
+            Self::Machine(id) => hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+                .extract(id)
+                .expand(&[AAD], &CHACHA20_POLY1305)
+                .map_err(|_| anyhow::anyhow!("Failed to derive the credentials key"))?
+                .into(),
+            Self::Passphrase(passphrase, iterations) => {
+                let iterations =
+                    NonZeroU32::new(*iterations).context("Invalid PBKDF2 iteration count")?;
+                let mut key = [0; 32];
+                pbkdf2::derive(
+                    pbkdf2::PBKDF2_HMAC_SHA256,
+                    iterations,
+                    salt,
+                    passphrase.as_bytes(),
+                    &mut key,
+                );
+                UnboundKey::new(&CHACHA20_POLY1305, &key)
+                    .map_err(|_| anyhow::anyhow!("Failed to derive the credentials key"))?
+            }
+        };
+        Ok(LessSafeKey::new(unbound))
+    }
+}
+
+/// Encrypt the contents of a credentials file with the passphrase, else the
+/// machine key. `None` when neither is available: the caller stores the
+/// contents as they are.
+pub(crate) fn seal(plaintext: &str) -> Result<Option<String>> {
+    match Secret::current() {
+        Some(secret) => seal_with(&secret, plaintext).map(Some),
+        None => {
+            tracing::debug!("no machine ID or passphrase; credentials are stored unencrypted");
+            Ok(None)
+        }
+    }
+}
+
+/// The decrypted contents of a credentials file, or `None` if it isn't
+/// encrypted.
+pub(crate) fn open(contents: &str) -> Result<Option<String>> {
+    let Ok(envelope) = serde_json::from_str::<Envelope>(contents) else {
+        return Ok(None);
+    };
+    let secret = Secret::for_file(&envelope.encrypted)?;
+    open_with(&secret, &envelope.encrypted).map(Some)
+}
+
+/// How a credentials file is encrypted, or `None` if it isn't.
+pub(crate) fn key_source(contents: &str) -> Option<KeySource> {
+    serde_json::from_str::<Envelope>(contents)
+        .ok()
+        .map(|envelope| envelope.encrypted.key)
+}
+
+fn seal_with(secret: &Secret, plaintext: &str) -> Result<String> {
+    let mut rng = rand::rng();
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt);
+    let mut nonce = [0u8; 12];
+    rng.fill(&mut nonce);
+
+    let mut buffer = plaintext.as_bytes().to_vec();
+    secret
+        .key(&salt)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(AAD),
+            &mut buffer,
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt the credentials"))?;
+
+    let envelope = Envelope {
+        encrypted: Sealed {
+            key: secret.source(),
+            iterations: match secret {
+                Secret::Machine(_) => None,
+                Secret::Passphrase(_, iterations) => Some(*iterations),
+            },
+            salt: STANDARD.encode(salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(buffer),
+        },
+    };
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
+
+fn open_with(secret: &Secret, sealed: &Sealed) -> Result<String> {
+    let decode = |field: &str| {
+        STANDARD
+            .decode(field)
+            .context("The encrypted credentials file is corrupt")
+    };
+    let salt = decode(&sealed.salt)?;
+    let nonce = Nonce::try_assume_unique_for_key(&decode(&sealed.nonce)?)
+        .map_err(|_| anyhow::anyhow!("The encrypted credentials file is corrupt"))?;
+    let mut buffer = decode(&sealed.ciphertext)?;
+
+    let Ok(plaintext) = secret
+        .key(&salt)?
+        .open_in_place(nonce, Aad::from(AAD), &mut buffer)
+    else {
+        match sealed.key {
+            KeySource::Machine => {
+                bail!("Failed to decrypt the credentials file; it was encrypted on another machine")
+            }
+            KeySource::Passphrase => {
+                bail!("Failed to decrypt the credentials file; check {PASSPHRASE_ENV}")
+            }
+        }
+    };
+    String::from_utf8(plaintext.to_vec()).context("The encrypted credentials file is corrupt")
+}
+
+/// A stable per-installation ID from the OS: systemd's machine ID, the Mac's
+/// hardware UUID, or Windows' `MachineGuid`.
+fn machine_id() -> Option<Vec<u8>> {
+    let id = if cfg!(target_os = "macos") {
+        let out = std::process::Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .find(|line| line.contains("\"IOPlatformUUID\""))?
+            .rsplit('"')
+            .nth(1)?
+            .to_string()
+    } else if cfg!(windows) {
+        let out = std::process::Command::new("reg")
+            .args([
+                "query",
+                r"HKLM\SOFTWARE\Microsoft\Cryptography",
+                "/v",
+                "MachineGuid",
+            ])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .find(|line| line.contains("MachineGuid"))?
+            .split_whitespace()
+            .last()?
+            .to_string()
+    } else {
+        ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+            .iter()
+            .find_map(|path| std::fs::read_to_string(path).ok())?
+    };
+
+    let id = id.trim();
+    (!id.is_empty()).then(|| id.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(sealed: &str) -> Sealed {
+        serde_json::from_str::<Envelope>(sealed).unwrap().encrypted
+    }
+
+    #[test]
+    fn machine_key_round_trips_and_fails_elsewhere() {
+        let secret = Secret::Machine(b"4c4c4544-0042".to_vec());
+        let sealed = seal_with(&secret, r#"{"token": "sk-ant-oat01-x"}"#).unwrap();
+
+        assert!(!sealed.contains("sk-ant"));
+        assert_eq!(key_source(&sealed), Some(KeySource::Machine));
+        assert_eq!(
+            open_with(&secret, &parse(&sealed)).unwrap(),
+            r#"{"token": "sk-ant-oat01-x"}"#
+        );
+
+        let elsewhere = Secret::Machine(b"another-machine".to_vec());
+        let error = open_with(&elsewhere, &parse(&sealed)).unwrap_err();
+        assert!(error.to_string().contains("another machine"));
+    }
+
+    #[test]
+    fn passphrase_key_records_its_rounds() {
+        let secret = Secret::Passphrase("correct horse".to_string(), 10);
+        let sealed = parse(&seal_with(&secret, "{}").unwrap());
+
+        assert_eq!(sealed.key, KeySource::Passphrase);
+        assert_eq!(sealed.iterations, Some(10));
+        assert_eq!(open_with(&secret, &sealed).unwrap(), "{}");
+
+        let wrong = Secret::Passphrase("battery staple".to_string(), 10);
+        assert!(open_with(&wrong, &sealed).is_err());
+    }
+
+    #[test]
+    fn plain_credentials_are_not_encrypted_files() {
+        let plain = r#"{"token": "sk-ant-api03-x", "is_oauth": false}"#;
+        assert_eq!(key_source(plain), None);
+        assert!(open(plain).unwrap().is_none());
+    }
+}
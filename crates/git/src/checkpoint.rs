@@ -0,0 +1,315 @@
+//! Snapshots of the working tree, kept under `refs/ccrs/checkpoints/`
+//! instead of a branch: HEAD, the index and the files are never touched
+//! by taking one.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use git2::{Commit, Delta, FileMode, IndexAddOption, Repository, Signature, Tree};
+
+use crate::repo::open_repo;
+
+/// One snapshot in a session's chain.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub id: String,
+    pub message: String,
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+}
+
+impl Checkpoint {
+    fn from_commit(commit: &Commit<'_>) -> Self {
+        Self {
+            id: commit.id().to_string(),
+            message: commit.message().unwrap_or("").trim_end().to_string(),
+            time: commit.time().seconds(),
+        }
+    }
+}
+
+/// The ref holding the checkpoints of `session`.
+pub fn checkpoint_ref(session: &str) -> String {
+    format!("refs/ccrs/checkpoints/{session}")
+}
+
+/// Commit the working tree, tracked files as they are now and untracked
+/// ones that aren't ignored, on top of the session's previous checkpoint.
+/// `None` when nothing changed since that one.
+pub fn snapshot(cwd: &Path, session: &str, message: &str) -> Result<Option<Checkpoint>> {
+    let repo = open_repo(cwd)?;
+    let tree = worktree_tree(&repo)?;
+
+    let name = checkpoint_ref(session);
+    let parent = match repo.find_reference(&name) {
+        Ok(reference) => Some(reference.peel_to_commit()?),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree.id()) {
+        return Ok(None);
+    }
+
+    let sig = repo
+        .signature()
+        .or_else(|_| Signature::now("ccrs", "ccrs@localhost"))?;
+    let parents: Vec<&Commit<'_>> = parent.iter().collect();
+    let oid = repo
+        .commit(None, &sig, &sig, message, &tree, &parents)
+        .context("Failed to commit the checkpoint")?;
+    repo.reference(&name, oid, true, message)
+        .with_context(|| format!("Failed to update {name}"))?;
+
+    Ok(Some(Checkpoint::from_commit(&repo.find_commit(oid)?)))
+}
+
+/// The session's checkpoints, newest first.
+pub fn checkpoints(cwd: &Path, session: &str) -> Result<Vec<Checkpoint>> {
+    let repo = open_repo(cwd)?;
+    let mut commit = match repo.find_reference(&checkpoint_ref(session)) {
+        Ok(reference) => reference.peel_to_commit()?,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut list = vec![Checkpoint::from_commit(&commit)];
+    while let Ok(parent) = commit.parent(0) {
+        list.push(Checkpoint::from_commit(&parent));
+        commit = parent;
+    }
+    Ok(list)
+}
+
+/// The checkpoint `id` names, a full or abbreviated commit ID from any
+/// session's chain. Branches, tags and expressions such as `HEAD~3` are not
+/// checkpoints.
+pub fn find_checkpoint(cwd: &Path, id: &str) -> Result<Checkpoint> {
+    let repo = open_repo(cwd)?;
+    Ok(Checkpoint::from_commit(&checkpoint_commit(&repo, id)?))
+}
+
+fn checkpoint_commit<'r>(repo: &'r Repository, id: &str) -> Result<Commit<'r>> {
+    if id.len() < 4 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("No checkpoint {id}");
+    }
+    let commit = repo
+        .find_commit_by_prefix(id)
+        .with_context(|| format!("No checkpoint {id}"))?;
+    for reference in repo.references_glob(&checkpoint_ref("*"))? {
+        let Some(tip) = reference?.target() else {
+            continue;
+        };
+        if tip == commit.id() || repo.graph_descendant_of(tip, commit.id())? {
+            return Ok(commit);
+        }
+    }
+    anyhow::bail!("No checkpoint {id}")
+}
+
+/// Put the files back as they were at checkpoint `id` (see
+/// [`find_checkpoint`]): files it has are rewritten and files created
+/// since are deleted. HEAD and the index stay as they are. Returns the
+/// paths that changed.
+pub fn restore(cwd: &Path, id: &str) -> Result<Vec<String>> {
+    let repo = open_repo(cwd)?;
+    let workdir = repo
+        .workdir()
+        .context("bare repository has no working directory")?
+        .to_path_buf();
+    let target = checkpoint_commit(&repo, id)?.tree()?;
+    let current = worktree_tree(&repo)?;
+
+    let diff = repo.diff_tree_to_tree(Some(&current), Some(&target), None)?;
+    let mut changed = Vec::new();
+    for delta in diff.deltas() {
+        match delta.status() {
+            Delta::Deleted => {
+                let Some(path) = delta.old_file().path() else {
+                    continue;
+                };
+                let full = workdir.join(path);
+                fs::remove_file(&full)
+                    .with_context(|| format!("Failed to delete {}", full.display()))?;
+                remove_empty_parents(&full, &workdir);
+                changed.push(path.display().to_string());
+            }
+            Delta::Added | Delta::Modified | Delta::Typechange => {
+                let file = delta.new_file();
+                let Some(path) = file.path() else {
+                    continue;
+                };
+                let blob = repo.find_blob(file.id())?;
+                write_file(&workdir.join(path), blob.content(), file.mode())?;
+                changed.push(path.display().to_string());
+            }
+            _ => {}
+        }
+    }
+    Ok(changed)
+}
+
+/// The tree the index would have if every change were added. The
+/// repository's index is read into memory and never written back.
+fn worktree_tree(repo: &Repository) -> Result<Tree<'_>> {
+    let mut index = repo.index()?;
+    index
+        .add_all(["*"], IndexAddOption::DEFAULT, None)
+        .context("Failed to read the working tree")?;
+    index.update_all(["*"], None)?;
+    let id = index.write_tree().context("Failed to write the tree")?;
+    // Drop the in-memory changes in case the index is used again
+    index.read(true)?;
+    Ok(repo.find_tree(id)?)
+}
+
+fn write_file(path: &Path, content: &[u8], mode: FileMode) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // A symlink or a directory may be in the way
+    if path.symlink_metadata().is_ok_and(|m| !m.is_file()) {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+
+    #[cfg(unix)]
+    if mode == FileMode::Link {
+        let target = String::from_utf8_lossy(content).into_owned();
+        return std::os::unix::fs::symlink(target, path)
+            .with_context(|| format!("Failed to create {}", path.display()));
+    }
+
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // Executable by whoever may read it, as git does
+        let mut permissions = fs::metadata(path)?.permissions();
+        let bits = permissions.mode();
+        permissions.set_mode(if mode == FileMode::BlobExecutable {
+            bits | (bits & 0o444) >> 2
+        } else {
+            bits & !0o111
+        });
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// Delete the directories `file` leaves empty, up to `root`.
+fn remove_empty_parents(file: &Path, root: &Path) {
+    let mut dir = file.parent();
+    while let Some(d) = dir {
+        if d == root || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn init_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.add_path(Path::new(".gitignore")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test", "test@test.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+        dir
+    }
+
+    #[test]
+    fn snapshots_chain_and_skip_unchanged_trees() {
+        let dir = init_repo();
+        let cwd = dir.path();
+
+        let first = snapshot(cwd, "s1", "Before: fix it").unwrap().unwrap();
+        assert!(snapshot(cwd, "s1", "After: fix it").unwrap().is_none());
+
+        fs::write(cwd.join("a.txt"), "two\n").unwrap();
+        let second = snapshot(cwd, "s1", "After: fix it").unwrap().unwrap();
+
+        let list = checkpoints(cwd, "s1").unwrap();
+        let ids: Vec<_> = list.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, [second.id.as_str(), first.id.as_str()]);
+        assert_eq!(list[0].message, "After: fix it");
+        assert!(checkpoints(cwd, "other").unwrap().is_empty());
+
+        // Only the hidden ref moved
+        let repo = Repository::open(cwd).unwrap();
+        assert_eq!(
+            repo.head().unwrap().peel_to_commit().unwrap().message(),
+            Some("initial")
+        );
+        assert!(
+            repo.statuses(None)
+                .unwrap()
+                .iter()
+                .any(|s| s.path() == Some("a.txt"))
+        );
+    }
+
+    #[test]
+    fn restore_rewrites_changed_files_and_removes_new_ones() {
+        let dir = init_repo();
+        let cwd = dir.path();
+        let before = snapshot(cwd, "s1", "Before").unwrap().unwrap();
+
+        fs::write(cwd.join("a.txt"), "changed\n").unwrap();
+        fs::create_dir_all(cwd.join("src/new")).unwrap();
+        fs::write(cwd.join("src/new/b.txt"), "new\n").unwrap();
+        fs::create_dir_all(cwd.join("target")).unwrap();
+        fs::write(cwd.join("target/out"), "ignored\n").unwrap();
+
+        let mut changed = restore(cwd, &before.id[..10]).unwrap();
+        changed.sort();
+        assert_eq!(changed, ["a.txt", "src/new/b.txt"]);
+        assert_eq!(fs::read_to_string(cwd.join("a.txt")).unwrap(), "one\n");
+        assert!(!cwd.join("src").exists());
+        // Ignored files are neither saved nor removed
+        assert!(cwd.join("target/out").exists());
+
+        assert!(restore(cwd, "0000000").is_err());
+    }
+
+    #[test]
+    fn only_checkpoints_can_be_restored() {
+        let dir = init_repo();
+        let cwd = dir.path();
+        let checkpoint = snapshot(cwd, "s1", "Before").unwrap().unwrap();
+        fs::write(cwd.join("new.txt"), "keep me\n").unwrap();
+
+        let head = Repository::open(cwd)
+            .unwrap()
+            .head()
+            .unwrap()
+            .target()
+            .unwrap()
+            .to_string();
+        for id in ["HEAD", "HEAD~0", "master", head.as_str(), "abcdéf0"] {
+            assert!(restore(cwd, id).is_err(), "{id}");
+        }
+        assert!(cwd.join("new.txt").exists());
+
+        assert_eq!(
+            find_checkpoint(cwd, &checkpoint.id[..7]).unwrap().id,
+            checkpoint.id
+        );
+    }
+}
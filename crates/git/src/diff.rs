@@ -69,6 +69,42 @@ pub fn diff_range(path: &Path, from: &str, to: &str) -> Result<(Vec<DiffEntry>,
     collect_diff(&diff)
 }
 
+/// List the paths touched between two revisions (old and new names of renames
+/// included), like `git diff --name-only rev1..rev2`. Paths are relative to
+/// the repository root.
+pub fn changed_paths(path: &Path, from: &str, to: &str) -> Result<Vec<String>> {
+    let repo = open_repo(path)?;
+
+    let from_tree = repo
+        .revparse_single(from)
+        .with_context(|| format!("cannot resolve revision: {from}"))?
+        .peel_to_tree()
+        .with_context(|| format!("{from} does not point to a tree"))?;
+    let to_tree = repo
+        .revparse_single(to)
+        .with_context(|| format!("cannot resolve revision: {to}"))?
+        .peel_to_tree()
+        .with_context(|| format!("{to} does not point to a tree"))?;
+
+    let diff = repo
+        .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)
+        .context("failed to compute diff")?;
+
+    let mut paths = Vec::new();
+
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(p) = file.path().and_then(|p| p.to_str())
+                && !paths.iter().any(|existing| existing == p)
+            {
+                paths.push(p.to_string());
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
 // ── helpers ──────────────────────────────────────────────────────────────
 
 fn diff_opts() -> DiffOptions {
@@ -176,6 +212,33 @@ mod tests {
         assert!(entries[0].patch.contains("+line 2"));
     }
 
+    #[test]
+    fn test_changed_paths() {
+        let (dir, repo) = init_repo_with_file();
+        let first = repo.head().unwrap().target().unwrap().to_string();
+
+        fs::write(dir.path().join("hello.txt"), "changed\n").unwrap();
+        fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+
+        {
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("hello.txt")).unwrap();
+            index.add_path(Path::new("new.txt")).unwrap();
+            index.write().unwrap();
+
+            let tree_id = index.write_tree().unwrap();
+            let tree = repo.find_tree(tree_id).unwrap();
+            let parent = repo.head().unwrap().peel_to_commit().unwrap();
+            let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "second", &tree, &[&parent])
+                .unwrap();
+        }
+
+        let mut paths = changed_paths(dir.path(), &first, "HEAD").unwrap();
+        paths.sort();
+        assert_eq!(paths, vec!["hello.txt", "new.txt"]);
+    }
+
     #[test]
     fn test_diff_unstaged() {
         let (dir, _) = init_repo_with_file();
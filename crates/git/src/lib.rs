@@ -1,6 +1,7 @@
 //! Git operations via libgit2 — no CLI dependency.
 
 mod blame;
+mod checkpoint;
 mod diff;
 pub(crate) mod log;
 mod repo;
@@ -9,9 +10,12 @@ mod status;
 mod write;
 
 pub use blame::{BlameLine, blame, blame_range};
-pub use diff::{DiffEntry, DiffStat, diff_range, diff_staged, diff_unstaged};
+pub use checkpoint::{Checkpoint, checkpoint_ref, checkpoints, find_checkpoint, restore, snapshot};
+pub use diff::{DiffEntry, DiffStat, changed_paths, diff_range, diff_staged, diff_unstaged};
 pub use log::{LogEntry, log as git_log};
-pub use repo::{BranchInfo, current_branch, list_branches, open_repo, repo_root};
+pub use repo::{
+    BranchInfo, current_branch, head_commit, list_branches, open_repo, remote_url, repo_root,
+};
 pub use show::{CommitDetail, show};
 pub use status::{FileStatus, StatusEntry, status};
 pub use write::{
@@ -23,6 +23,34 @@ pub fn current_branch(path: &Path) -> Result<Option<String>> {
     Ok(head.shorthand().map(|s| s.to_string()))
 }
 
+/// Return the commit id HEAD points to, or `None` on an unborn branch.
+pub fn head_commit(path: &Path) -> Result<Option<String>> {
+    let repo = open_repo(path)?;
+
+    match repo.head() {
+        Ok(head) => {
+            let commit = head
+                .peel_to_commit()
+                .context("HEAD does not point to a commit")?;
+            Ok(Some(commit.id().to_string()))
+        }
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Return the URL of the remote called `name`, or `None` if there is no
+/// such remote.
+pub fn remote_url(path: &Path, name: &str) -> Result<Option<String>> {
+    let repo = open_repo(path)?;
+
+    match repo.find_remote(name) {
+        Ok(remote) => Ok(remote.url().map(|url| url.to_string())),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 /// Information about a branch.
 pub struct BranchInfo {
     pub name: String,
@@ -115,6 +143,21 @@ mod tests {
         assert!(branch.is_some());
     }
 
+    #[test]
+    fn test_head_commit() {
+        let (dir, repo) = init_repo();
+        let head = head_commit(dir.path()).unwrap();
+        let expected = repo.head().unwrap().target().unwrap().to_string();
+        assert_eq!(head, Some(expected));
+    }
+
+    #[test]
+    fn test_head_commit_unborn() {
+        let dir = TempDir::new().unwrap();
+        Repository::init(dir.path()).unwrap();
+        assert_eq!(head_commit(dir.path()).unwrap(), None);
+    }
+
     #[test]
     fn test_list_branches() {
         let (dir, _) = init_repo();
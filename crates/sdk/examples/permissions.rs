@@ -0,0 +1,50 @@
+//! Let Claude change files, asking on the terminal before each command or
+//! edit.
+//!
+//! ```sh
+//! ANTHROPIC_API_KEY=... cargo run -p claude-code-sdk --example permissions -- "Add a test for parse()"
+//! ```
+
+use claude_code_sdk::permissions::ReadOnly;
+use claude_code_sdk::{Credentials, Decision, PermissionHandler, Result, Session, Tool};
+
+/// Reads and searches go through; everything else is asked.
+struct Ask;
+
+impl PermissionHandler for Ask {
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        if ReadOnly.decide(tool) == Decision::Allow {
+            return Decision::Allow;
+        }
+
+        let what = match tool {
+            Tool::Bash { command } => format!("Run `{command}`"),
+            Tool::Write { path } | Tool::Edit { path } => format!("Change {}", path.display()),
+            Tool::Fetch { url, .. } => format!("Fetch {url}"),
+            _ => "Use a tool".to_string(),
+        };
+        eprint!("{what}? [y/N/reason] ");
+
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).is_err() {
+            return Decision::Deny;
+        }
+        match answer.trim() {
+            "y" | "yes" => Decision::Allow,
+            "" | "n" | "no" => Decision::Deny,
+            reason => Decision::DenyWithFeedback(reason.to_string()),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let prompt = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    let mut session = Session::builder(Credentials::from_env()?)
+        .permissions(Ask)
+        .build()?;
+
+    let reply = session.prompt(&prompt).await?;
+    println!("{reply}");
+    Ok(())
+}
@@ -0,0 +1,39 @@
+//! Stream a reply to stdout, with tool calls on stderr.
+//!
+//! ```sh
+//! ANTHROPIC_API_KEY=... cargo run -p claude-code-sdk --example prompt -- "What does this crate do?"
+//! ```
+
+use std::io::Write;
+
+use claude_code_sdk::{Credentials, EventHandler, Result, Session};
+
+struct Print;
+
+impl EventHandler for Print {
+    fn on_text(&mut self, text: &str) {
+        print!("{text}");
+        let _ = std::io::stdout().flush();
+    }
+
+    fn on_tool_call(&mut self, name: &str, input: &serde_json::Value) {
+        eprintln!("\n[{name}] {input}");
+    }
+
+    fn on_error(&mut self, message: &str) {
+        eprintln!("\nerror: {message}");
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let prompt = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    let mut session = Session::builder(Credentials::from_env()?).build()?;
+
+    let usage = session.send(&prompt, &mut Print).await?;
+    eprintln!(
+        "\n{} tokens in, {} out",
+        usage.input_tokens, usage.output_tokens
+    );
+    Ok(())
+}
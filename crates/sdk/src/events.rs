@@ -0,0 +1,173 @@
+//! What a session reports while it works.
+
+use claude_code_core::{api, event, tools};
+
+/// Receives a turn's progress as it streams. Every method ignores its
+/// event by default, so implement only the ones you need; new events are
+/// added the same way.
+pub trait EventHandler: Send {
+    /// The next piece of Claude's reply.
+    fn on_text(&mut self, _text: &str) {}
+
+    /// Extended thinking, streamed before the text it leads to.
+    fn on_thinking(&mut self, _text: &str) {}
+
+    /// An error the API reported in the middle of a reply.
+    fn on_error(&mut self, _message: &str) {}
+
+    /// Claude called a tool. Permission hasn't been checked yet.
+    fn on_tool_call(&mut self, _name: &str, _input: &serde_json::Value) {}
+
+    /// A slow step of a running tool moved on, e.g. a model download.
+    fn on_tool_progress(&mut self, _name: &str, _progress: &ToolProgress) {}
+
+    /// What a tool returned to Claude, including denials.
+    fn on_tool_result(&mut self, _name: &str, _output: &str, _is_error: bool) {}
+
+    /// Token usage of each request to the model.
+    fn on_usage(&mut self, _usage: &Usage) {}
+}
+
+/// Ignores every event.
+pub struct Ignore;
+
+impl EventHandler for Ignore {}
+
+/// Tokens used by one request, or a whole turn or session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Input tokens read from the prompt cache.
+    pub cache_read_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    pub cache_write_tokens: u64,
+}
+
+impl From<api::Usage> for Usage {
+    fn from(usage: api::Usage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_read_tokens: usage.cache_read_input_tokens,
+            cache_write_tokens: usage.cache_creation_input_tokens,
+        }
+    }
+}
+
+/// How far a slow step of a tool call has got.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ToolProgress {
+    /// What is being done, e.g. "Embedding files".
+    pub label: String,
+    pub done: u64,
+    pub total: Option<u64>,
+    /// Counts bytes rather than items.
+    pub bytes: bool,
+}
+
+impl From<&tools::ToolProgress> for ToolProgress {
+    fn from(progress: &tools::ToolProgress) -> Self {
+        Self {
+            label: progress.label.clone(),
+            done: progress.done,
+            total: (progress.total > 0).then_some(progress.total),
+            bytes: progress.bytes,
+        }
+    }
+}
+
+/// Passes the core session's events on to an SDK handler.
+pub(crate) struct Forward<'a>(pub(crate) &'a mut dyn EventHandler);
+
+impl event::EventHandler for Forward<'_> {
+    fn on_text(&mut self, text: &str) {
+        self.0.on_text(text);
+    }
+
+    fn on_error(&mut self, message: &str) {
+        self.0.on_error(message);
+    }
+
+    fn on_thinking(&mut self, text: &str) {
+        self.0.on_thinking(text);
+    }
+
+    fn on_tool_use_start(&mut self, name: &str, _id: &str, input: &serde_json::Value) {
+        self.0.on_tool_call(name, input);
+    }
+
+    fn on_tool_result(&mut self, name: &str, output: &str, is_error: bool) {
+        self.0.on_tool_result(name, output, is_error);
+    }
+
+    fn on_tool_progress(&mut self, name: &str, progress: &tools::ToolProgress) {
+        self.0.on_tool_progress(name, &progress.into());
+    }
+
+    fn on_usage(&mut self, usage: &api::Usage) {
+        self.0.on_usage(&(*usage).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_code_core::event::EventHandler as _;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl EventHandler for Log {
+        fn on_text(&mut self, text: &str) {
+            self.0.push(format!("text {text}"));
+        }
+
+        fn on_tool_call(&mut self, name: &str, input: &serde_json::Value) {
+            self.0.push(format!("call {name} {input}"));
+        }
+
+        fn on_tool_progress(&mut self, name: &str, progress: &ToolProgress) {
+            self.0.push(format!("progress {name} {:?}", progress.total));
+        }
+
+        fn on_usage(&mut self, usage: &Usage) {
+            self.0.push(format!("usage {}", usage.cache_read_tokens));
+        }
+    }
+
+    #[test]
+    fn core_events_reach_the_handler() {
+        let mut log = Log::default();
+        let mut forward = Forward(&mut log);
+        forward.on_text("Hi");
+        forward.on_tool_use_start("Read", "toolu_1", &serde_json::json!({"file_path": "a"}));
+        forward.on_tool_progress(
+            "Search",
+            &tools::ToolProgress {
+                label: "Embedding files".to_string(),
+                done: 1,
+                total: 0,
+                bytes: false,
+            },
+        );
+        forward.on_usage(&api::Usage {
+            cache_read_input_tokens: 7,
+            ..Default::default()
+        });
+        // Not handled by Log, so ignored
+        forward.on_thinking("hmm");
+
+        assert_eq!(
+            log.0,
+            vec![
+                "text Hi",
+                r#"call Read {"file_path":"a"}"#,
+                "progress Search None",
+                "usage 7",
+            ]
+        );
+    }
+}
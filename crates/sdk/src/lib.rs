@@ -0,0 +1,33 @@
+//! Embed the ccrs agent loop in a Rust program.
+//!
+//! A [`Session`] is a conversation with Claude that can use the same tools
+//! as `ccrs`: Bash, Read, Write, Edit, Glob, Grep, Fetch and the rest. Tool
+//! calls are approved by a [`PermissionHandler`] and everything the session
+//! does is reported to an [`EventHandler`].
+//!
+//! ```no_run
+//! use claude_code_sdk::{Credentials, Session};
+//!
+//! # async fn run() -> claude_code_sdk::Result<()> {
+//! let mut session = Session::builder(Credentials::from_env()?)
+//!     .cwd("/path/to/project")
+//!     .build()?;
+//! let reply = session.prompt("What does this project do?").await?;
+//! println!("{reply}");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! This crate follows semver. It wraps `claude-code-core`, which doesn't:
+//! depend on this one instead.
+
+pub mod events;
+pub mod permissions;
+mod session;
+
+pub use anyhow::{Error, Result};
+pub use tokio_util::sync::CancellationToken;
+
+pub use events::{EventHandler, Usage};
+pub use permissions::{Decision, PermissionHandler, Tool};
+pub use session::{Credentials, Session, SessionBuilder};
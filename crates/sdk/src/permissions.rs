@@ -0,0 +1,142 @@
+//! Which tool calls a session may make.
+
+use claude_code_core::permission;
+
+/// A tool call waiting for permission. New tools will be added as
+/// variants, so match with a fallback arm.
+pub use claude_code_core::permission::Tool;
+
+/// The answer to a permission request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Decision {
+    Allow,
+    Deny,
+    /// Denied, with a reason or instructions passed on to Claude.
+    DenyWithFeedback(String),
+}
+
+/// Approves or denies each tool call. Closures work too:
+///
+/// ```
+/// use claude_code_sdk::{Decision, Tool};
+///
+/// let no_network = |tool: &Tool<'_>| match tool {
+///     Tool::Fetch { .. } => Decision::DenyWithFeedback("No network access.".into()),
+///     _ => Decision::Allow,
+/// };
+/// # let _: &dyn claude_code_sdk::PermissionHandler = &no_network;
+/// ```
+pub trait PermissionHandler: Send {
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision;
+}
+
+impl<F> PermissionHandler for F
+where
+    F: FnMut(&Tool<'_>) -> Decision + Send,
+{
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        self(tool)
+    }
+}
+
+/// Permits every tool call, including commands and file changes.
+pub struct AllowAll;
+
+impl PermissionHandler for AllowAll {
+    fn decide(&mut self, _tool: &Tool<'_>) -> Decision {
+        Decision::Allow
+    }
+}
+
+/// Denies every tool call.
+pub struct DenyAll;
+
+impl PermissionHandler for DenyAll {
+    fn decide(&mut self, _tool: &Tool<'_>) -> Decision {
+        Decision::Deny
+    }
+}
+
+/// Permits reading and searching files, and nothing else: no commands,
+/// file changes, git or network. The default.
+pub struct ReadOnly;
+
+impl PermissionHandler for ReadOnly {
+    fn decide(&mut self, tool: &Tool<'_>) -> Decision {
+        match tool {
+            Tool::Read { .. }
+            | Tool::Glob
+            | Tool::Grep
+            | Tool::List
+            | Tool::Search
+            | Tool::TodoWrite => Decision::Allow,
+            _ => Decision::Deny,
+        }
+    }
+}
+
+/// Lets the core session ask an SDK handler.
+pub(crate) struct Adapter(pub(crate) Box<dyn PermissionHandler>);
+
+impl permission::PermissionHandler for Adapter {
+    fn allow(&mut self, tool: &Tool<'_>) -> bool {
+        self.0.decide(tool) == Decision::Allow
+    }
+
+    fn decide(&mut self, tool: &Tool<'_>) -> permission::Decision {
+        match self.0.decide(tool) {
+            Decision::Allow => permission::Decision::Allow,
+            Decision::Deny => permission::Decision::Deny,
+            Decision::DenyWithFeedback(feedback) => {
+                permission::Decision::DenyWithFeedback(feedback)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use claude_code_core::permission::PermissionHandler as _;
+
+    #[test]
+    fn read_only_allows_reads_and_searches() {
+        let read = Tool::Read {
+            path: Path::new("src/main.rs"),
+        };
+        let write = Tool::Write {
+            path: Path::new("src/main.rs"),
+        };
+        let bash = Tool::Bash { command: "ls" };
+
+        assert_eq!(ReadOnly.decide(&read), Decision::Allow);
+        assert_eq!(ReadOnly.decide(&Tool::Grep), Decision::Allow);
+        assert_eq!(ReadOnly.decide(&write), Decision::Deny);
+        assert_eq!(ReadOnly.decide(&bash), Decision::Deny);
+    }
+
+    #[test]
+    fn closures_decide_and_feedback_reaches_the_session() {
+        let mut asked = 0;
+        let mut adapter = Adapter(Box::new(move |tool: &Tool<'_>| {
+            asked += 1;
+            match tool {
+                Tool::Bash { command } if command.starts_with("rm ") => {
+                    Decision::DenyWithFeedback(format!("Don't delete anything ({asked})."))
+                }
+                _ => Decision::Allow,
+            }
+        }));
+
+        assert!(adapter.allow(&Tool::Bash { command: "ls" }));
+        assert_eq!(
+            adapter.decide(&Tool::Bash {
+                command: "rm -rf /"
+            }),
+            permission::Decision::DenyWithFeedback("Don't delete anything (2).".to_string())
+        );
+    }
+}
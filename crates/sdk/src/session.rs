@@ -0,0 +1,254 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use claude_code_core::api::{Content, ContentBlock};
+use claude_code_core::{config, session};
+use tokio_util::sync::CancellationToken;
+
+use crate::events::{EventHandler, Forward, Ignore, Usage};
+use crate::permissions::{Adapter, PermissionHandler, ReadOnly};
+
+/// How a session signs in to the Anthropic API.
+#[derive(Clone)]
+pub struct Credentials {
+    token: String,
+    is_oauth: bool,
+}
+
+impl Credentials {
+    pub fn api_key(key: impl Into<String>) -> Self {
+        Self {
+            token: key.into(),
+            is_oauth: false,
+        }
+    }
+
+    /// A Claude subscription token, as `claude setup-token` prints.
+    pub fn oauth_token(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            is_oauth: true,
+        }
+    }
+
+    /// `ANTHROPIC_API_KEY`, else `CLAUDE_CODE_OAUTH_TOKEN`.
+    pub fn from_env() -> Result<Self> {
+        let (_, credentials) = config::credentials_from_env()
+            .context("Set ANTHROPIC_API_KEY or CLAUDE_CODE_OAUTH_TOKEN")?;
+        Ok(Self {
+            token: credentials.token,
+            is_oauth: credentials.is_oauth,
+        })
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.is_oauth {
+            "OAuth token"
+        } else {
+            "API key"
+        };
+        write!(f, "Credentials({kind})")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Builder
+// ---------------------------------------------------------------------------
+
+/// Configures a [`Session`]. Tool calls are limited to reading and
+/// searching unless [`permissions`](Self::permissions) says otherwise.
+pub struct SessionBuilder {
+    inner: session::SessionBuilder,
+    permissions: Box<dyn PermissionHandler>,
+}
+
+impl SessionBuilder {
+    /// The directory tools work in. Defaults to the current directory.
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.inner = self.inner.cwd(cwd.into());
+        self
+    }
+
+    /// A model ID such as `claude-sonnet-4-5`.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.inner = self.inner.model(model.into());
+        self
+    }
+
+    /// More directories the tools may use besides the working directory.
+    pub fn additional_dirs(mut self, dirs: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.inner = self.inner.additional_dirs(dirs.into_iter().collect());
+        self
+    }
+
+    /// Replace the generated instructions (working directory, git status,
+    /// CLAUDE.md).
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.inner = self.inner.system_prompt(prompt.into());
+        self
+    }
+
+    /// Add to the instructions.
+    pub fn append_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.inner = self.inner.append_system_prompt(prompt.into());
+        self
+    }
+
+    /// Stop a turn after this many requests to the model.
+    pub fn max_turns(mut self, max_turns: usize) -> Self {
+        self.inner = self.inner.max_turns(max_turns);
+        self
+    }
+
+    /// Longest reply per request, in tokens.
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner = self.inner.max_tokens(max_tokens);
+        self
+    }
+
+    /// Let the model think first, with this many tokens.
+    pub fn thinking(mut self, budget: u32) -> Self {
+        self.inner = self.inner.thinking(budget);
+        self
+    }
+
+    pub fn permissions(mut self, permissions: impl PermissionHandler + 'static) -> Self {
+        self.permissions = Box::new(permissions);
+        self
+    }
+
+    pub fn build(self) -> Result<Session> {
+        let inner = self.inner.permissions(Adapter(self.permissions))?;
+        Ok(Session { inner })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Session
+// ---------------------------------------------------------------------------
+
+/// A conversation with Claude, which keeps its history between prompts.
+pub struct Session {
+    inner: session::Session<Adapter>,
+}
+
+impl Session {
+    pub fn builder(credentials: Credentials) -> SessionBuilder {
+        SessionBuilder {
+            inner: session::SessionBuilder::new(credentials.token, credentials.is_oauth),
+            permissions: Box::new(ReadOnly),
+        }
+    }
+
+    /// Send a prompt and return the text of Claude's final reply, after
+    /// any tool calls.
+    pub async fn prompt(&mut self, prompt: &str) -> Result<String> {
+        self.send(prompt, &mut Ignore).await?;
+        Ok(self.reply())
+    }
+
+    /// Send a prompt, reporting the turn to `events` as it goes. Returns
+    /// the turn's token usage.
+    pub async fn send(&mut self, prompt: &str, events: &mut dyn EventHandler) -> Result<Usage> {
+        self.send_until(prompt, events, &CancellationToken::new())
+            .await
+    }
+
+    /// [`send`](Self::send), stopping early once `cancel` is cancelled.
+    pub async fn send_until(
+        &mut self,
+        prompt: &str,
+        events: &mut dyn EventHandler,
+        cancel: &CancellationToken,
+    ) -> Result<Usage> {
+        let usage = self
+            .inner
+            .send_message(prompt, &mut Forward(events), cancel)
+            .await?;
+        Ok(usage.into())
+    }
+
+    pub fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    pub fn cwd(&self) -> &Path {
+        self.inner.cwd()
+    }
+
+    pub fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    pub fn set_model(&mut self, model: impl Into<String>) {
+        self.inner.set_model(model.into());
+    }
+
+    /// Tokens used by the whole conversation.
+    pub fn usage(&self) -> Usage {
+        self.inner.usage().into()
+    }
+
+    /// Start a new conversation with the same settings.
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Save the conversation where `ccrs --resume <id>` finds it.
+    pub fn save(&self) -> Result<()> {
+        self.inner.save()
+    }
+
+    /// The text of the latest reply.
+    fn reply(&self) -> String {
+        let Some(last) = self.inner.history().last() else {
+            return String::new();
+        };
+        if last.role != "assistant" {
+            return String::new();
+        }
+        match &last.content {
+            Content::Text(text) => text.clone(),
+            Content::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credentials_stay_out_of_debug_output() {
+        let key = Credentials::api_key("sk-ant-api03-secret");
+        assert_eq!(format!("{key:?}"), "Credentials(API key)");
+    }
+
+    #[test]
+    fn builds_without_network() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut session = Session::builder(Credentials::api_key("key"))
+            .cwd(tmp.path())
+            .model("claude-haiku-4-5")
+            .build()
+            .unwrap();
+
+        assert_eq!(session.cwd(), tmp.path());
+        assert_eq!(session.model(), "claude-haiku-4-5");
+        assert_eq!(session.usage(), Usage::default());
+        assert_eq!(session.reply(), "");
+
+        let id = session.id().to_string();
+        session.clear();
+        assert_ne!(session.id(), id);
+    }
+}
@@ -1,11 +1,33 @@
 //! BM25 full-text search powered by Tantivy (in-memory).
+//!
+//! Terms present in most files of a large corpus (`use`, `return`, license
+//! headers…) carry no ranking signal; they are pruned as stop words.
+
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{Context, Result};
 use tantivy::collector::TopDocs;
 use tantivy::query::QueryParser;
-use tantivy::schema::{Field, STORED, STRING, Schema, TEXT, Value as _};
+use tantivy::schema::{
+    Field, IndexRecordOption, STORED, STRING, Schema, TextFieldIndexing, TextOptions, Value as _,
+};
+use tantivy::tokenizer::{
+    LowerCaser, RemoveLongFilter, SimpleTokenizer, StopWordFilter, TextAnalyzer, TokenStream,
+};
 use tantivy::{Index, IndexWriter, TantivyDocument, Term};
 
+// ---------------------------------------------------------------------------
+// Constants
+// ---------------------------------------------------------------------------
+
+const TOKENIZER: &str = "code";
+
+/// Terms appearing in more than this fraction of files are pruned.
+const MAX_DOC_FREQ_RATIO: f64 = 0.6;
+
+/// Below this many files document frequencies are too noisy to prune on.
+const MIN_DOCS_FOR_PRUNING: usize = 20;
+
 // ---------------------------------------------------------------------------
 // Bm25Index
 // ---------------------------------------------------------------------------
@@ -17,14 +39,28 @@ pub(crate) struct Bm25Index {
 }
 
 impl Bm25Index {
-    pub fn new() -> Result<Self> {
+    /// Create an empty index whose tokenizer drops `stop_words`.
+    pub fn new(stop_words: Vec<String>) -> Result<Self> {
+        let content_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+
         let mut schema_builder = Schema::builder();
         let path_field = schema_builder.add_text_field("path", STRING | STORED);
-        let content_field = schema_builder.add_text_field("content", TEXT);
+        let content_field = schema_builder.add_text_field("content", content_options);
         let schema = schema_builder.build();
 
         let index = Index::create_in_ram(schema);
 
+        let analyzer = TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+            .filter(StopWordFilter::remove(stop_words))
+            .build();
+        index.tokenizers().register(TOKENIZER, analyzer);
+
         Ok(Self {
             index,
             path_field,
@@ -82,3 +118,105 @@ impl Bm25Index {
         Ok(results)
     }
 }
+
+// ---------------------------------------------------------------------------
+// Boilerplate pruning
+// ---------------------------------------------------------------------------
+
+/// Return the terms that appear in more than 60% of `documents`, sorted.
+///
+/// Returns nothing for corpora smaller than [`MIN_DOCS_FOR_PRUNING`].
+pub(crate) fn boilerplate_terms<'a>(
+    documents: impl ExactSizeIterator<Item = &'a str>,
+) -> Vec<String> {
+    let total = documents.len();
+
+    if total < MIN_DOCS_FOR_PRUNING {
+        return vec![];
+    }
+
+    // Same pipeline as the index, minus the stop word filter
+    let mut analyzer = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .build();
+
+    let mut doc_freq: HashMap<String, usize> = HashMap::new();
+
+    for content in documents {
+        let mut seen = HashSet::new();
+        let mut stream = analyzer.token_stream(content);
+
+        while stream.advance() {
+            let text = &stream.token().text;
+
+            if !seen.contains(text) {
+                seen.insert(text.clone());
+            }
+        }
+
+        for term in seen {
+            *doc_freq.entry(term).or_default() += 1;
+        }
+    }
+
+    let threshold = (total as f64 * MAX_DOC_FREQ_RATIO) as usize;
+
+    let mut terms: Vec<String> = doc_freq
+        .into_iter()
+        .filter(|(_, count)| *count > threshold)
+        .map(|(term, _)| term)
+        .collect();
+
+    terms.sort();
+    terms
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus(n: usize) -> Vec<String> {
+        (0..n)
+            .map(|i| format!("// Licensed under MIT\nuse std;\nfn handler{i}() {{ return; }}\n"))
+            .collect()
+    }
+
+    #[test]
+    fn test_boilerplate_terms_found() {
+        let docs = corpus(25);
+        let terms = boilerplate_terms(docs.iter().map(String::as_str));
+
+        assert!(terms.contains(&"licensed".to_string()));
+        assert!(terms.contains(&"return".to_string()));
+        assert!(!terms.iter().any(|t| t.starts_with("handler")));
+    }
+
+    #[test]
+    fn test_boilerplate_terms_small_corpus() {
+        let docs = corpus(MIN_DOCS_FOR_PRUNING - 1);
+        assert!(boilerplate_terms(docs.iter().map(String::as_str)).is_empty());
+    }
+
+    #[test]
+    fn test_pruned_terms_not_searchable() {
+        let docs = corpus(25);
+        let stop_words = boilerplate_terms(docs.iter().map(String::as_str));
+        let index = Bm25Index::new(stop_words).unwrap();
+
+        let mut writer = index.writer().unwrap();
+        for (i, doc) in docs.iter().enumerate() {
+            index.add(&mut writer, &format!("f{i}.rs"), doc);
+        }
+        writer.commit().unwrap();
+
+        assert!(index.search("licensed", 10).unwrap().is_empty());
+
+        let hits = index.search("handler7", 10).unwrap();
+        assert_eq!(hits[0].0, "f7.rs");
+    }
+}
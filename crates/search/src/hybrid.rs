@@ -16,11 +16,11 @@ pub(crate) fn rrf_merge(
     let mut scores: HashMap<&str, f32> = HashMap::new();
 
     for (rank, (path, _)) in bm25.iter().enumerate() {
-        *scores.entry(path.as_str()).or_default() += 1.0 / (K + rank as f32 + 1.0);
+        *scores.entry(path.as_str()).or_default() += rrf_contribution(rank);
     }
 
     for (rank, (path, _)) in semantic.iter().enumerate() {
-        *scores.entry(path.as_str()).or_default() += 1.0 / (K + rank as f32 + 1.0);
+        *scores.entry(path.as_str()).or_default() += rrf_contribution(rank);
     }
 
     let mut results: Vec<(String, f32)> = scores
@@ -34,6 +34,11 @@ pub(crate) fn rrf_merge(
     results
 }
 
+/// RRF score contributed by an entry at 0-based `rank` in one result set.
+pub(crate) fn rrf_contribution(rank: usize) -> f32 {
+    1.0 / (K + rank as f32 + 1.0)
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -75,6 +80,14 @@ mod tests {
         assert_eq!(merged.len(), 2);
     }
 
+    #[test]
+    fn test_rrf_contribution_matches_merge() {
+        let bm25 = vec![("a.rs".to_string(), 10.0), ("b.rs".to_string(), 5.0)];
+
+        let merged = rrf_merge(&bm25, &[], 10);
+        assert_eq!(merged[1].1, rrf_contribution(1));
+    }
+
     #[test]
     fn test_rrf_merge_empty() {
         let merged = rrf_merge(&[], &[], 10);
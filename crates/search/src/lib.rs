@@ -1,7 +1,11 @@
 //! Hybrid search: BM25 + semantic (fastembed) with Reciprocal Rank Fusion.
 //!
-//! Session-scoped, in-memory index with incremental mtime-based updates.
+//! Session-scoped, in-memory index with incremental mtime-based updates
+//! (narrowed to `git status` + HEAD changes inside a git repository).
 //! Embeddings are computed lazily on the first `search()` call.
+//!
+//! [`SearchIndex`] is `Send + Sync` and all its methods take `&self`: share it
+//! behind an `Arc` and searches run concurrently with updates.
 
 mod bm25;
 mod hybrid;
@@ -9,13 +13,16 @@ mod semantic;
 mod snippet;
 pub(crate) mod walk;
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 
-use bm25::Bm25Index;
+use bm25::{Bm25Index, boilerplate_terms};
 use semantic::SemanticIndex;
-use snippet::{apply_boost, extract_query_terms, extract_snippets};
+pub use semantic::{is_model_cached, model_cache_dir};
+use snippet::{apply_boost, boost_factor, extract_query_terms, extract_snippets};
 use walk::FileWalker;
 
 // ---------------------------------------------------------------------------
@@ -25,12 +32,16 @@ use walk::FileWalker;
 pub struct SearchIndex {
     bm25: Bm25Index,
     semantic: SemanticIndex,
-    walker: FileWalker,
+    /// Writer state: held for the duration of an update or embedding build.
+    walker: Mutex<FileWalker>,
+    root_dir: PathBuf,
 }
 
 pub struct OpenStats {
     pub files: usize,
     pub bytes: u64,
+    /// Terms dropped from the BM25 index for appearing in most files.
+    pub pruned_terms: usize,
 }
 
 pub struct UpdateStats {
@@ -49,6 +60,29 @@ pub struct SearchHit {
     pub path: String,
     pub score: f32,
     pub snippets: Vec<Snippet>,
+    pub explanation: ScoreExplanation,
+}
+
+/// How a hit's final score was computed: `fused * boost`.
+#[derive(Debug, Clone)]
+pub struct ScoreExplanation {
+    pub bm25: Option<SourceRank>,
+    pub semantic: Option<SourceRank>,
+    /// Sum of the RRF contributions of both sources.
+    pub fused: f32,
+    /// Path-based multiplier (tests, docs, mocks, sources).
+    pub boost: f32,
+}
+
+/// A hit's position in one of the ranked result sets fed into RRF.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceRank {
+    /// 1-based rank.
+    pub rank: usize,
+    /// Raw score from that source (BM25 score or cosine similarity).
+    pub score: f32,
+    /// Share of the fused RRF score.
+    pub contribution: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -57,6 +91,27 @@ pub struct Snippet {
     pub lines: Vec<String>,
 }
 
+/// A slow setup step, for progress reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Downloading the embedding model; counts bytes.
+    Download,
+    /// Reading files into the keyword index; counts files.
+    Index,
+    /// Computing embeddings; counts files.
+    Embed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub stage: Stage,
+    pub done: u64,
+    pub total: u64,
+}
+
+/// Receives progress reports, possibly from another thread.
+pub type OnProgress<'a> = &'a (dyn Fn(Progress) + Sync);
+
 // ---------------------------------------------------------------------------
 // SearchIndex
 // ---------------------------------------------------------------------------
@@ -66,16 +121,27 @@ impl SearchIndex {
     ///
     /// BM25 index is built immediately. Embeddings are deferred until the
     /// first `search()` call.
+    ///
+    /// Boilerplate terms are computed from this initial walk only; later
+    /// updates reuse the same list.
     pub fn open(dir: &Path) -> Result<(Self, OpenStats)> {
+        Self::open_with_progress(dir, &|_| {})
+    }
+
+    /// [`open`](Self::open), reporting how many files have been read.
+    pub fn open_with_progress(dir: &Path, on_progress: OnProgress) -> Result<(Self, OpenStats)> {
         let root_dir = dir
             .canonicalize()
             .with_context(|| format!("cannot resolve path: {}", dir.display()))?;
 
-        let bm25 = Bm25Index::new()?;
         let semantic = SemanticIndex::new();
-        let mut walker = FileWalker::new(root_dir);
+        let mut walker = FileWalker::new(root_dir.clone());
+
+        let (entries, walk_stats) = walker.walk_all(on_progress)?;
 
-        let (entries, walk_stats) = walker.walk_all()?;
+        let stop_words = boilerplate_terms(entries.iter().map(|e| e.content.as_str()));
+        let pruned_terms = stop_words.len();
+        let bm25 = Bm25Index::new(stop_words)?;
 
         // Populate BM25 index
         let mut writer = bm25.writer()?;
@@ -89,20 +155,29 @@ impl SearchIndex {
         let stats = OpenStats {
             files: walk_stats.files,
             bytes: walk_stats.bytes,
+            pruned_terms,
         };
 
         let index = Self {
             bm25,
             semantic,
-            walker,
+            walker: Mutex::new(walker),
+            root_dir,
         };
 
         Ok((index, stats))
     }
 
     /// Incrementally update: diff mtimes, re-index changed files.
-    pub fn update(&mut self) -> Result<UpdateStats> {
-        let result = self.walker.walk_incremental()?;
+    ///
+    /// In a git repository only files reported by git are stat-ed, which
+    /// keeps this near-instant on large trees.
+    ///
+    /// Updates are serialized; concurrent searches keep seeing the previous
+    /// state until the new one is committed.
+    pub fn update(&self) -> Result<UpdateStats> {
+        let mut walker = self.lock_walker()?;
+        let result = walker.walk_incremental()?;
 
         let stats = UpdateStats {
             added: result
@@ -153,7 +228,7 @@ impl SearchIndex {
     ///
     /// The first call triggers lazy embedding model load + batch embed of all files.
     pub fn search(
-        &mut self,
+        &self,
         query: &str,
         limit: usize,
         context_lines: usize,
@@ -174,15 +249,27 @@ impl SearchIndex {
         // RRF merge
         let merged = hybrid::rrf_merge(&bm25_results, &semantic_results, limit);
 
+        let bm25_ranks = source_ranks(&bm25_results);
+        let semantic_ranks = source_ranks(&semantic_results);
+
         // Build hits with boosting
         let mut hits: Vec<SearchHit> = merged
             .into_iter()
-            .map(|(path, score)| {
-                let boosted = apply_boost(&path, score);
+            .map(|(path, fused)| {
+                let boost = boost_factor(&path);
+                let score = apply_boost(&path, fused);
+                let explanation = ScoreExplanation {
+                    bm25: bm25_ranks.get(path.as_str()).copied(),
+                    semantic: semantic_ranks.get(path.as_str()).copied(),
+                    fused,
+                    boost,
+                };
+
                 SearchHit {
                     path,
-                    score: boosted,
+                    score,
                     snippets: vec![],
+                    explanation,
                 }
             })
             .collect();
@@ -197,10 +284,9 @@ impl SearchIndex {
         // Extract snippets
         if context_lines > 0 {
             let query_terms = extract_query_terms(query);
-            let root = self.walker.root();
 
             for hit in &mut hits {
-                let full_path = root.join(&hit.path);
+                let full_path = self.root_dir.join(&hit.path);
                 hit.snippets = extract_snippets(&full_path, &query_terms, context_lines, 3);
             }
         }
@@ -208,19 +294,62 @@ impl SearchIndex {
         Ok(hits)
     }
 
-    /// Walk all indexed files and batch-embed them.
-    fn build_embeddings(&mut self) -> Result<()> {
-        let (entries, _) = self.walker.walk_all()?;
+    /// Walk all indexed files and batch-embed them (downloading the model on
+    /// first use). Called lazily by `search()`; a no-op once embeddings exist.
+    ///
+    /// Holds the walker lock so concurrent first searches embed only once.
+    pub fn build_embeddings(&self) -> Result<()> {
+        self.build_embeddings_with_progress(&|_| {})
+    }
+
+    /// [`build_embeddings`](Self::build_embeddings), reporting the model
+    /// download and the embedded files.
+    pub fn build_embeddings_with_progress(&self, on_progress: OnProgress) -> Result<()> {
+        let mut walker = self.lock_walker()?;
+
+        if self.semantic.is_ready() {
+            return Ok(());
+        }
+
+        let (entries, _) = walker.walk_all(&|_| {})?;
 
         let files: Vec<(String, String)> = entries
             .into_iter()
             .map(|e| (e.relative, e.content))
             .collect();
 
-        self.semantic.embed_all(&files)?;
+        let start = std::time::Instant::now();
+        self.semantic.embed_all(&files, on_progress)?;
+        tracing::info!(
+            files = files.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "embeddings built"
+        );
 
         Ok(())
     }
+
+    fn lock_walker(&self) -> Result<MutexGuard<'_, FileWalker>> {
+        self.walker
+            .lock()
+            .map_err(|_| anyhow!("search index walker lock poisoned"))
+    }
+}
+
+/// Index a ranked result set by path.
+fn source_ranks(results: &[(String, f32)]) -> HashMap<&str, SourceRank> {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, (path, score))| {
+            let rank = SourceRank {
+                rank: i + 1,
+                score: *score,
+                contribution: hybrid::rrf_contribution(i),
+            };
+            (path.as_str(), rank)
+        })
+        .collect()
 }
 
 // ---------------------------------------------------------------------------
@@ -279,10 +408,25 @@ mod tests {
         assert!(stats.bytes > 0);
     }
 
+    #[test]
+    fn test_open_reports_files_read() {
+        let dir = setup_test_dir();
+        let reports = Mutex::new(Vec::new());
+        let (_, stats) =
+            SearchIndex::open_with_progress(dir.path(), &|p| reports.lock().unwrap().push(p))
+                .unwrap();
+
+        let reports = reports.into_inner().unwrap();
+        let last = reports.last().unwrap();
+        assert!(reports.iter().all(|p| p.stage == Stage::Index));
+        assert_eq!(last.done, last.total);
+        assert!(last.total >= stats.files as u64);
+    }
+
     #[test]
     fn test_update_no_changes() {
         let dir = setup_test_dir();
-        let (mut index, _) = SearchIndex::open(dir.path()).unwrap();
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
 
         let stats = index.update().unwrap();
         assert!(!stats.has_changes());
@@ -291,7 +435,7 @@ mod tests {
     #[test]
     fn test_update_detects_added_file() {
         let dir = setup_test_dir();
-        let (mut index, _) = SearchIndex::open(dir.path()).unwrap();
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
 
         // Add a new file
         fs::write(dir.path().join("src/new.rs"), "fn new_func() {}\n").unwrap();
@@ -305,7 +449,7 @@ mod tests {
     #[test]
     fn test_update_detects_removed_file() {
         let dir = setup_test_dir();
-        let (mut index, _) = SearchIndex::open(dir.path()).unwrap();
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
 
         // Remove a file
         fs::remove_file(dir.path().join("README.md")).unwrap();
@@ -317,7 +461,7 @@ mod tests {
     #[test]
     fn test_update_detects_modified_file() {
         let dir = setup_test_dir();
-        let (mut index, _) = SearchIndex::open(dir.path()).unwrap();
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
 
         // Wait a tiny bit to ensure mtime changes
         std::thread::sleep(std::time::Duration::from_millis(50));
@@ -333,6 +477,117 @@ mod tests {
         assert_eq!(stats.modified, 1);
     }
 
+    #[cfg(feature = "git")]
+    fn commit_all(dir: &Path, message: &str) {
+        let repo = git2::Repository::open(dir)
+            .or_else(|_| git2::Repository::init(dir))
+            .unwrap();
+
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.update_all(["*"], None).unwrap();
+        index.write().unwrap();
+
+        let tree_id = index.write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap();
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn test_git_update_detects_worktree_changes() {
+        let dir = setup_test_dir();
+        commit_all(dir.path(), "initial");
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
+
+        assert!(!index.update().unwrap().has_changes());
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("src/new.rs"), "fn new_func() {}\n").unwrap();
+        fs::remove_file(dir.path().join("README.md")).unwrap();
+
+        let stats = index.update().unwrap();
+        assert_eq!(stats.added, 1);
+        assert_eq!(stats.modified, 1);
+        assert_eq!(stats.removed, 1);
+
+        // Still dirty but unchanged since the last update
+        assert!(!index.update().unwrap().has_changes());
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn test_git_update_detects_committed_changes() {
+        let dir = setup_test_dir();
+        commit_all(dir.path(), "initial");
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
+
+        fs::write(dir.path().join("src/committed.rs"), "fn committed() {}\n").unwrap();
+        commit_all(dir.path(), "second");
+
+        let stats = index.update().unwrap();
+        assert_eq!(stats.added, 1);
+        assert_eq!(stats.modified, 0);
+        assert_eq!(stats.removed, 0);
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn test_git_update_skips_ignored_dirs() {
+        let dir = setup_test_dir();
+        commit_all(dir.path(), "initial");
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
+
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::write(dir.path().join("node_modules/pkg/index.js"), "x\n").unwrap();
+
+        assert!(!index.update().unwrap().has_changes());
+    }
+
+    #[test]
+    fn test_index_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SearchIndex>();
+    }
+
+    #[test]
+    fn test_concurrent_update_and_bm25_search() {
+        let dir = setup_test_dir();
+        let (index, _) = SearchIndex::open(dir.path()).unwrap();
+        let index = std::sync::Arc::new(index);
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let index = std::sync::Arc::clone(&index);
+                let path = dir.path().join(format!("src/extra_{i}.rs"));
+
+                std::thread::spawn(move || {
+                    fs::write(&path, format!("fn extra_{i}() {{}}\n")).unwrap();
+                    index.update().unwrap();
+                    index.bm25.search("hello world", 10).unwrap()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let hits = handle.join().unwrap();
+            assert!(hits[0].0.contains("main.rs"));
+        }
+
+        // All four files were picked up by one of the updates
+        assert!(!index.update().unwrap().has_changes());
+        assert!(!index.bm25.search("extra_3", 10).unwrap().is_empty());
+    }
+
     #[test]
     fn test_bm25_search() {
         let dir = setup_test_dir();
@@ -353,6 +608,21 @@ mod tests {
         assert!(hits.is_empty());
     }
 
+    #[test]
+    fn test_source_ranks() {
+        let results = vec![("a.rs".to_string(), 3.0), ("b.rs".to_string(), 1.0)];
+        let ranks = source_ranks(&results);
+
+        assert_eq!(ranks["b.rs"].rank, 2);
+        assert_eq!(ranks["b.rs"].score, 1.0);
+        assert_eq!(ranks["b.rs"].contribution, hybrid::rrf_contribution(1));
+    }
+
+    #[test]
+    fn test_boost_factor_default() {
+        assert_eq!(snippet::boost_factor("Cargo.toml"), 1.0);
+    }
+
     #[test]
     fn test_boost_source_files() {
         let score = snippet::apply_boost("src/lib.rs", 1.0);
@@ -2,11 +2,26 @@
 //!
 //! The ONNX model is downloaded to the system cache on first use.
 //! Embeddings are computed lazily on the first `search()` call.
+//!
+//! All methods take `&self`: the model sits behind a `Mutex` (inference needs
+//! `&mut`), the vectors behind an `RwLock` so queries can run concurrently.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
 
 use crate::walk::FileChange;
+use crate::{OnProgress, Progress, Stage};
+
+/// Files embedded per model call, and per progress report.
+const BATCH_SIZE: usize = 64;
+
+/// Approximate size of the model files, for download progress.
+const MODEL_BYTES: u64 = 91_000_000;
 
 // ---------------------------------------------------------------------------
 // Types
@@ -22,58 +37,95 @@ struct EmbeddingEntry {
 // ---------------------------------------------------------------------------
 
 pub(crate) struct SemanticIndex {
-    model: Option<TextEmbedding>,
-    entries: Vec<EmbeddingEntry>,
+    model: Mutex<Option<TextEmbedding>>,
+    entries: RwLock<Vec<EmbeddingEntry>>,
+    ready: AtomicBool,
+}
+
+/// Where the embedding model is downloaded.
+pub fn model_cache_dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("ccrs").join("models"))
+}
+
+/// Whether the embedding model has already been downloaded, so the first
+/// semantic search won't stall on a download.
+pub fn is_model_cached() -> bool {
+    let Some(dir) = model_cache_dir() else {
+        return false;
+    };
+
+    std::fs::read_dir(dir).into_iter().flatten().any(|entry| {
+        entry.is_ok_and(|e| e.file_name().to_string_lossy().contains("all-MiniLM-L6-v2"))
+    })
 }
 
 impl SemanticIndex {
     pub fn new() -> Self {
         Self {
-            model: None,
-            entries: Vec::new(),
+            model: Mutex::new(None),
+            entries: RwLock::new(Vec::new()),
+            ready: AtomicBool::new(false),
         }
     }
 
     pub fn is_ready(&self) -> bool {
-        !self.entries.is_empty()
+        self.ready.load(Ordering::Acquire)
     }
 
-    /// Embed all files from scratch.
-    pub fn embed_all(&mut self, files: &[(String, String)]) -> Result<()> {
-        if files.is_empty() {
-            self.entries.clear();
-            return Ok(());
-        }
-
-        let model = self.ensure_model()?;
-
-        let texts: Vec<String> = files
-            .iter()
-            .map(|(_, content)| truncate(content, 8192))
-            .collect();
-
-        let vectors = model
-            .embed(texts, None)
-            .context("failed to compute embeddings")?;
-
-        self.entries.clear();
+    /// Embed all files from scratch, reporting each batch.
+    pub fn embed_all(&self, files: &[(String, String)], on_progress: OnProgress) -> Result<()> {
+        let mut new_entries = Vec::with_capacity(files.len());
+
+        if !files.is_empty() {
+            let texts: Vec<String> = files
+                .iter()
+                .map(|(_, content)| truncate(content, 8192))
+                .collect();
+
+            let total = texts.len() as u64;
+            let vectors = self
+                .with_model(on_progress, |model| {
+                    let mut vectors = Vec::with_capacity(texts.len());
+                    for batch in texts.chunks(BATCH_SIZE) {
+                        vectors.extend(model.embed(batch, None)?);
+                        on_progress(Progress {
+                            stage: Stage::Embed,
+                            done: vectors.len() as u64,
+                            total,
+                        });
+                    }
+                    Ok(vectors)
+                })
+                .context("failed to compute embeddings")?;
 
-        for ((path, _), vector) in files.iter().zip(vectors) {
-            self.entries.push(EmbeddingEntry {
-                path: path.clone(),
-                vector,
-            });
+            for ((path, _), vector) in files.iter().zip(vectors) {
+                new_entries.push(EmbeddingEntry {
+                    path: path.clone(),
+                    vector,
+                });
+            }
         }
 
+        *self.write_entries()? = new_entries;
+        self.ready.store(true, Ordering::Release);
+
         Ok(())
     }
 
     /// Incrementally update embeddings for changed/removed files.
-    pub fn embed_incremental(&mut self, changes: &[FileChange], removed: &[String]) -> Result<()> {
+    pub fn embed_incremental(&self, changes: &[FileChange], removed: &[String]) -> Result<()> {
         if changes.is_empty() && removed.is_empty() {
             return Ok(());
         }
 
+        // Embed new/modified files before taking the write lock
+        let vectors = if changes.is_empty() {
+            Vec::new()
+        } else {
+            let texts: Vec<String> = changes.iter().map(|c| truncate(&c.content, 8192)).collect();
+            self.embed(texts).context("failed to compute embeddings")?
+        };
+
         // Remove entries for changed + removed files
         let to_remove: std::collections::HashSet<&str> = changes
             .iter()
@@ -81,45 +133,32 @@ impl SemanticIndex {
             .chain(removed.iter().map(|s| s.as_str()))
             .collect();
 
-        self.entries
-            .retain(|e| !to_remove.contains(e.path.as_str()));
-
-        // Embed new/modified files
-        if !changes.is_empty() {
-            let model = self.ensure_model()?;
-
-            let texts: Vec<String> = changes.iter().map(|c| truncate(&c.content, 8192)).collect();
-
-            let vectors = model
-                .embed(texts, None)
-                .context("failed to compute embeddings")?;
+        let mut entries = self.write_entries()?;
+        entries.retain(|e| !to_remove.contains(e.path.as_str()));
 
-            for (change, vector) in changes.iter().zip(vectors) {
-                self.entries.push(EmbeddingEntry {
-                    path: change.relative.clone(),
-                    vector,
-                });
-            }
+        for (change, vector) in changes.iter().zip(vectors) {
+            entries.push(EmbeddingEntry {
+                path: change.relative.clone(),
+                vector,
+            });
         }
 
         Ok(())
     }
 
     /// Search by cosine similarity. Returns (path, score) pairs.
-    pub fn search(&mut self, query: &str, limit: usize) -> Result<Vec<(String, f32)>> {
-        if self.entries.is_empty() {
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<(String, f32)>> {
+        if self.read_entries()?.is_empty() {
             return Ok(vec![]);
         }
 
-        let model = self.ensure_model()?;
-
-        let query_vectors = model
-            .embed(vec![query.to_string()], None)
+        let query_vectors = self
+            .embed(vec![query.to_string()])
             .context("failed to embed query")?;
         let query_vec = &query_vectors[0];
 
         let mut scored: Vec<(String, f32)> = self
-            .entries
+            .read_entries()?
             .iter()
             .map(|e| (e.path.clone(), cosine_similarity(query_vec, &e.vector)))
             .collect();
@@ -130,27 +169,72 @@ impl SemanticIndex {
         Ok(scored)
     }
 
-    fn ensure_model(&mut self) -> Result<&mut TextEmbedding> {
-        if self.model.is_none() {
-            let cache_dir = dirs::cache_dir()
-                .context("could not find system cache directory")?
-                .join("ccrs")
-                .join("models");
+    fn read_entries(&self) -> Result<std::sync::RwLockReadGuard<'_, Vec<EmbeddingEntry>>> {
+        self.entries
+            .read()
+            .map_err(|_| anyhow!("embedding store lock poisoned"))
+    }
+
+    fn write_entries(&self) -> Result<std::sync::RwLockWriteGuard<'_, Vec<EmbeddingEntry>>> {
+        self.entries
+            .write()
+            .map_err(|_| anyhow!("embedding store lock poisoned"))
+    }
+
+    /// Run the model on `texts`, loading it on first use.
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        self.with_model(&|_| {}, |model| model.embed(texts, None))
+    }
+
+    /// Run `f` with the model, loading it first if needed. A download is
+    /// reported by polling the cache directory's size, since fastembed only
+    /// offers a terminal progress bar.
+    fn with_model<T>(
+        &self,
+        on_progress: OnProgress,
+        f: impl FnOnce(&mut TextEmbedding) -> Result<T>,
+    ) -> Result<T> {
+        let mut model = self
+            .model
+            .lock()
+            .map_err(|_| anyhow!("embedding model lock poisoned"))?;
+
+        if model.is_none() {
+            let cache_dir = model_cache_dir().context("could not find system cache directory")?;
 
             std::fs::create_dir_all(&cache_dir)
                 .context("failed to create model cache directory")?;
 
+            let downloading = AtomicBool::new(!is_model_cached());
+
             let mut options = InitOptions::default();
             options.model_name = EmbeddingModel::AllMiniLML6V2;
-            options.cache_dir = cache_dir;
-            options.show_download_progress = true;
-
-            let model =
-                TextEmbedding::try_new(options).context("failed to load embedding model")?;
-            self.model = Some(model);
+            options.cache_dir = cache_dir.clone();
+            options.show_download_progress = false;
+
+            let loaded = std::thread::scope(|scope| {
+                if downloading.load(Ordering::Acquire) {
+                    scope.spawn(|| {
+                        while downloading.load(Ordering::Acquire) {
+                            on_progress(Progress {
+                                stage: Stage::Download,
+                                done: dir_size(&cache_dir).min(MODEL_BYTES),
+                                total: MODEL_BYTES,
+                            });
+                            std::thread::sleep(Duration::from_millis(200));
+                        }
+                    });
+                }
+
+                let loaded = TextEmbedding::try_new(options);
+                downloading.store(false, Ordering::Release);
+                loaded
+            })
+            .context("failed to load embedding model")?;
+            *model = Some(loaded);
         }
 
-        Ok(self.model.as_mut().unwrap())
+        f(model.as_mut().unwrap())
     }
 }
 
@@ -158,6 +242,20 @@ impl SemanticIndex {
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// Total size of the files under `dir`.
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map_or(0, |m| m.len()),
+            _ => 0,
+        })
+        .sum()
+}
+
 fn truncate(s: &str, max_chars: usize) -> String {
     s.chars().take(max_chars).collect()
 }
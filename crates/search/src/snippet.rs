@@ -9,29 +9,34 @@ use crate::Snippet;
 // ---------------------------------------------------------------------------
 
 pub(crate) fn apply_boost(path: &str, score: f32) -> f32 {
+    score * boost_factor(path)
+}
+
+/// Multiplier applied to a hit's fused score based on its path.
+pub(crate) fn boost_factor(path: &str) -> f32 {
     let p = path.to_lowercase();
 
     // Tests: 0.5x
     if p.contains("/test") || p.contains("_test.") || p.contains(".test.") || p.contains(".spec.") {
-        return score * 0.5;
+        return 0.5;
     }
 
     // Mocks: 0.4x
     if p.contains("/mock") || p.contains(".mock.") {
-        return score * 0.4;
+        return 0.4;
     }
 
     // Docs: 0.6x
     if p.ends_with(".md") || p.contains("/docs/") {
-        return score * 0.6;
+        return 0.6;
     }
 
     // Source: 1.1x
     if p.contains("/src") || p.contains("/lib") {
-        return score * 1.1;
+        return 1.1;
     }
 
-    score
+    1.0
 }
 
 // ---------------------------------------------------------------------------
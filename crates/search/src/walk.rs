@@ -1,16 +1,26 @@
 //! File walking with mtime-based change tracking.
+//!
+//! Inside a git repository, incremental walks only stat the paths reported by
+//! `git status` and by a HEAD-to-HEAD diff instead of the whole tree.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use anyhow::Result;
 use ignore::WalkBuilder;
 
+use crate::{OnProgress, Progress, Stage};
+#[cfg(feature = "git")]
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
 // ---------------------------------------------------------------------------
 // Constants
 // ---------------------------------------------------------------------------
 
+/// Files read between progress reports.
+const PROGRESS_EVERY: usize = 100;
+
 const TEXT_EXTENSIONS: &[&str] = &[
     // Programming
     "rs",
@@ -130,6 +140,8 @@ pub struct WalkStats {
 pub(crate) struct FileWalker {
     root_dir: PathBuf,
     mtimes: HashMap<String, (u64, u32)>,
+    #[cfg(feature = "git")]
+    git: Option<GitSnapshot>,
 }
 
 impl FileWalker {
@@ -137,35 +149,42 @@ impl FileWalker {
         Self {
             root_dir,
             mtimes: HashMap::new(),
+            #[cfg(feature = "git")]
+            git: None,
         }
     }
 
-    pub fn root(&self) -> &Path {
-        &self.root_dir
-    }
-
     /// Walk all files, record mtimes, return entries.
-    pub fn walk_all(&mut self) -> Result<(Vec<FileEntry>, WalkStats)> {
+    pub fn walk_all(&mut self, on_progress: OnProgress) -> Result<(Vec<FileEntry>, WalkStats)> {
         let mut entries = Vec::new();
         let mut stats = WalkStats { files: 0, bytes: 0 };
 
         self.mtimes.clear();
 
-        for entry in self.walker() {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
-
-            if !entry.file_type().is_some_and(|ft| ft.is_file()) {
-                continue;
-            }
-
-            let path = entry.path();
+        #[cfg(feature = "git")]
+        {
+            self.git = self.git_snapshot();
+        }
 
-            if !is_text_file(path) {
-                continue;
+        // List first so reading the files can report progress
+        let paths: Vec<PathBuf> = self
+            .walker()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+            .map(|entry| entry.into_path())
+            .filter(|path| is_text_file(path))
+            .collect();
+        let total = paths.len() as u64;
+
+        for (i, path) in paths.iter().enumerate() {
+            if i % PROGRESS_EVERY == 0 {
+                on_progress(Progress {
+                    stage: Stage::Index,
+                    done: i as u64,
+                    total,
+                });
             }
+            let path = path.as_path();
 
             let metadata = match path.metadata() {
                 Ok(m) => m,
@@ -209,13 +228,40 @@ impl FileWalker {
             });
         }
 
+        on_progress(Progress {
+            stage: Stage::Index,
+            done: total,
+            total,
+        });
+
         Ok((entries, stats))
     }
 
-    /// Walk incrementally: compare mtimes, return only changes.
+    /// Walk incrementally, return only changes.
+    ///
+    /// In a git repository only the files dirty now or at the previous walk,
+    /// plus those touched by commits since then, are checked. Otherwise (or
+    /// if git fails) every file's mtime is compared.
     pub fn walk_incremental(&mut self) -> Result<IncrementalResult> {
+        #[cfg(feature = "git")]
+        {
+            let previous = self.git.take();
+            self.git = self.git_snapshot();
+
+            if let (Some(previous), Some(current)) = (&previous, &self.git)
+                && let Some(candidates) = self.git_candidates(previous, current)
+            {
+                return Ok(self.check_candidates(candidates));
+            }
+        }
+
+        self.walk_mtimes()
+    }
+
+    /// Full walk comparing the mtime of every file against the last walk.
+    fn walk_mtimes(&mut self) -> Result<IncrementalResult> {
         let mut changes = Vec::new();
-        let mut seen = std::collections::HashSet::new();
+        let mut seen = HashSet::new();
         let mut new_mtimes = HashMap::new();
 
         for entry in self.walker() {
@@ -332,10 +378,176 @@ impl FileWalker {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Git-aware change detection
+// ---------------------------------------------------------------------------
+
+/// Repository state recorded at each walk.
+#[cfg(feature = "git")]
+struct GitSnapshot {
+    repo_root: PathBuf,
+    head: Option<String>,
+    /// Paths (relative to the walk root) reported by `git status`.
+    dirty: HashSet<String>,
+}
+
+#[cfg(feature = "git")]
+impl FileWalker {
+    fn git_snapshot(&self) -> Option<GitSnapshot> {
+        let repo_root = ccrs_git::repo_root(&self.root_dir)
+            .ok()?
+            .canonicalize()
+            .ok()?;
+        let head = ccrs_git::head_commit(&self.root_dir).ok()?;
+
+        let dirty = ccrs_git::status(&self.root_dir)
+            .ok()?
+            .iter()
+            .filter_map(|entry| self.relative_to_root(&repo_root, &entry.path))
+            .collect();
+
+        Some(GitSnapshot {
+            repo_root,
+            head,
+            dirty,
+        })
+    }
+
+    /// Paths that may have changed between two snapshots, or `None` when the
+    /// history cannot be compared (e.g. first commit created in between).
+    fn git_candidates(
+        &self,
+        previous: &GitSnapshot,
+        current: &GitSnapshot,
+    ) -> Option<HashSet<String>> {
+        let mut candidates: HashSet<String> =
+            previous.dirty.union(&current.dirty).cloned().collect();
+
+        if previous.head != current.head {
+            let (Some(from), Some(to)) = (&previous.head, &current.head) else {
+                return None;
+            };
+
+            for path in ccrs_git::changed_paths(&self.root_dir, from, to).ok()? {
+                if let Some(relative) = self.relative_to_root(&current.repo_root, &path) {
+                    candidates.insert(relative);
+                }
+            }
+        }
+
+        Some(candidates)
+    }
+
+    /// Re-check only the candidate paths against the recorded mtimes.
+    fn check_candidates(&mut self, candidates: HashSet<String>) -> IncrementalResult {
+        let ignore = self.ignore_matcher();
+
+        let mut candidates: Vec<String> = candidates.into_iter().collect();
+        candidates.sort();
+
+        let mut changes = Vec::new();
+        let mut removed = Vec::new();
+
+        for relative in candidates {
+            let path = self.root_dir.join(&relative);
+            let current_mtime = get_mtime(&path);
+
+            if current_mtime.is_some() && self.mtimes.get(&relative) == current_mtime.as_ref() {
+                continue;
+            }
+
+            let content = if self.is_walkable(&relative, &ignore) {
+                read_text(&path)
+            } else {
+                None
+            };
+
+            let Some(content) = content else {
+                if self.mtimes.remove(&relative).is_some() {
+                    removed.push(relative);
+                }
+
+                continue;
+            };
+
+            let kind = if self.mtimes.contains_key(&relative) {
+                ChangeKind::Modified
+            } else {
+                ChangeKind::Added
+            };
+
+            if let Some(mtime) = current_mtime {
+                self.mtimes.insert(relative.clone(), mtime);
+            }
+
+            changes.push(FileChange {
+                relative,
+                content,
+                kind,
+            });
+        }
+
+        IncrementalResult { changes, removed }
+    }
+
+    /// Apply the walker's filters to a single path.
+    ///
+    /// Only the root `.gitignore` / `.claudeignore` are consulted; git status
+    /// already honours nested `.gitignore` files.
+    fn is_walkable(&self, relative: &str, ignore: &Gitignore) -> bool {
+        let rel = Path::new(relative);
+
+        if rel
+            .components()
+            .any(|c| ccrs_utils::is_ignored_dir(&c.as_os_str().to_string_lossy()))
+        {
+            return false;
+        }
+
+        if ignore.matched_path_or_any_parents(rel, false).is_ignore() {
+            return false;
+        }
+
+        let path = self.root_dir.join(rel);
+        path.is_file() && is_text_file(&path)
+    }
+
+    fn ignore_matcher(&self) -> Gitignore {
+        let mut builder = GitignoreBuilder::new(&self.root_dir);
+        builder.add(self.root_dir.join(".gitignore"));
+        builder.add(self.root_dir.join(".claudeignore"));
+        builder.build().unwrap_or_else(|_| Gitignore::empty())
+    }
+
+    /// Convert a repo-relative path to a walk-root-relative one, or `None`
+    /// if it lies outside the walk root.
+    fn relative_to_root(&self, repo_root: &Path, repo_path: &str) -> Option<String> {
+        let full = repo_root.join(repo_path);
+        let relative = full.strip_prefix(&self.root_dir).ok()?;
+        Some(relative.to_string_lossy().to_string())
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// Read a file as UTF-8 text, skipping oversized and binary files.
+#[cfg(feature = "git")]
+fn read_text(path: &Path) -> Option<String> {
+    if path.metadata().ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+
+    let content = std::fs::read(path).ok()?;
+
+    if is_binary(&content) {
+        return None;
+    }
+
+    String::from_utf8(content).ok()
+}
+
 fn get_mtime(path: &Path) -> Option<(u64, u32)> {
     let meta = path.metadata().ok()?;
     let modified = meta.modified().ok()?;